@@ -0,0 +1,193 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use tracing::info;
+
+use crate::{
+    error::BrokerError,
+    registry::{Frame, StreamEntry},
+    spill,
+    stats::unix_now,
+    AppState,
+};
+
+/// Posisi satu ack subscriber di stream
+#[derive(Debug, Clone, Serialize)]
+pub struct AckCursor {
+    /// Every frame up to and including this seq was acknowledged
+    pub acked_seq: u64,
+    /// Unix seconds
+    pub registered_at: u64,
+}
+
+/// Ack subscriber terdaftar per stream; frame yang belum di-ack semuanya ditahan di DVR
+#[derive(Debug, Default)]
+pub struct AckSubscribers {
+    cursors: BTreeMap<String, AckCursor>,
+}
+
+impl AckSubscribers {
+    pub fn get(&self, name: &str) -> Option<&AckCursor> {
+        self.cursors.get(name)
+    }
+
+    /// Seq terakhir yang sudah di-ack oleh semua subscriber; `None` tanpa subscriber
+    pub fn floor(&self) -> Option<u64> {
+        self.cursors.values().map(|cursor| cursor.acked_seq).min()
+    }
+}
+
+/// Pesan teks dari ack subscriber: `{"type":"ack","seq":N}` (kumulatif)
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Ack { seq: u64 },
+}
+
+/// Sesuaikan retensi DVR dengan ack subscriber stream ini
+fn update_retention(state: &AppState, entry: &mut StreamEntry) {
+    let floor = entry.acks.floor();
+    entry.dvr.set_retention(floor, state.config.ack_max_pending_frames);
+}
+
+/// Catat ack kumulatif; ack mundur diabaikan. Mengembalikan seq yang sudah di-ack
+pub fn record_ack(state: &AppState, stream_id: &str, name: &str, seq: u64) -> Option<u64> {
+    state.with_stream(stream_id, |entry| {
+        let seq = seq.min(entry.last_seq());
+        let cursor = entry.acks.cursors.get_mut(name)?;
+        cursor.acked_seq = cursor.acked_seq.max(seq);
+        let acked = cursor.acked_seq;
+        update_retention(state, entry);
+        Some(acked)
+    })
+}
+
+/// Frame setelah `after_seq` dan sebelum `before_seq` dari DVR (memori lalu spill)
+///
+/// Used to fill gaps for ack subscribers: frames they skipped while lagging, or
+/// while the breaker or their bandwidth cap held live frames back.
+pub fn redeliver(state: &AppState, stream_id: &str, after_seq: u64, before_seq: u64) -> Vec<Frame> {
+    let (spilled, retained) =
+        state.with_stream(stream_id, |entry| (entry.dvr.spilled_since(after_seq), entry.dvr.since(after_seq)));
+    spill::read(&spilled)
+        .into_iter()
+        .chain(retained)
+        .filter(|frame| frame.seq < before_seq)
+        .collect()
+}
+
+fn cursor_json(stream_id: &str, name: &str, cursor: &AckCursor, last_seq: u64) -> serde_json::Value {
+    json!({
+        "stream": stream_id,
+        "name": name,
+        "acked_seq": cursor.acked_seq,
+        "pending": last_seq.saturating_sub(cursor.acked_seq),
+        "registered_at": cursor.registered_at,
+    })
+}
+
+/// Handler untuk PUT /api/streams/:stream_id/acks/:name
+/// Daftarkan ack subscriber; frame setelah seq saat ini ditahan sampai ia meng-ack-nya
+pub async fn put_ack_handler(
+    AxumPath((stream_id, name)): AxumPath<(String, String)>,
+    State(state): State<AppState>,
+) -> Response {
+    let (created, body) = state.with_stream(&stream_id, |entry| {
+        let last_seq = entry.last_seq();
+        let mut created = false;
+        let cursor = entry.acks.cursors.entry(name.clone()).or_insert_with(|| {
+            created = true;
+            AckCursor {
+                acked_seq: last_seq,
+                registered_at: unix_now(),
+            }
+        });
+        let body = cursor_json(&stream_id, &name, cursor, last_seq);
+        update_retention(&state, entry);
+        (created, body)
+    });
+    if created {
+        info!("Registered ack subscriber {} on stream {}", name, stream_id);
+    }
+    let status = if created { StatusCode::CREATED } else { StatusCode::OK };
+    (status, Json(body)).into_response()
+}
+
+/// Handler untuk GET /api/streams/:stream_id/acks
+pub async fn list_acks_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, BrokerError> {
+    let streams = state.streams.lock();
+    let entry = streams
+        .get(&stream_id)
+        .ok_or_else(|| BrokerError::StreamNotFound(stream_id.clone()))?;
+    let subscribers: Vec<_> = entry
+        .acks
+        .cursors
+        .iter()
+        .map(|(name, cursor)| cursor_json(&stream_id, name, cursor, entry.last_seq()))
+        .collect();
+    Ok(Json(json!({
+        "stream": stream_id,
+        "last_seq": entry.last_seq(),
+        "retained_after": entry.acks.floor(),
+        "subscribers": subscribers,
+    })))
+}
+
+/// Handler untuk DELETE /api/streams/:stream_id/acks/:name
+/// Hapus ack subscriber dan lepaskan frame yang hanya ditahan untuknya
+pub async fn delete_ack_handler(
+    AxumPath((stream_id, name)): AxumPath<(String, String)>,
+    State(state): State<AppState>,
+) -> Result<StatusCode, BrokerError> {
+    let mut streams = state.streams.lock();
+    let entry = streams
+        .get_mut(&stream_id)
+        .ok_or_else(|| BrokerError::StreamNotFound(stream_id.clone()))?;
+    if entry.acks.cursors.remove(&name).is_none() {
+        return Err(BrokerError::not_found("ack subscriber", name));
+    }
+    update_retention(&state, entry);
+    info!("Removed ack subscriber {} from stream {}", name, stream_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use bytes::Bytes;
+
+    #[test]
+    fn test_unacked_frames_outlive_dvr_capacity() {
+        let state = AppState::new(Config {
+            dvr_frames: 2,
+            ack_max_pending_frames: 100,
+            ..Config::default()
+        });
+        state.with_stream("jobs", |entry| {
+            entry.acks.cursors.insert("worker".into(), AckCursor { acked_seq: 0, registered_at: 0 });
+            update_retention(&state, entry);
+            for i in 1..=10 {
+                let _ = entry.publish(Bytes::from(format!("job-{}", i)));
+            }
+        });
+        // Semua 10 frame masih bisa dikirim ulang walau DVR hanya 2 frame
+        assert_eq!(redeliver(&state, "jobs", 0, u64::MAX).len(), 10);
+        assert_eq!(redeliver(&state, "jobs", 3, 6).iter().map(|f| f.seq).collect::<Vec<_>>(), [4, 5]);
+
+        // Ack kumulatif melepas frame; ack mundur atau melewati seq terakhir dibatasi
+        assert_eq!(record_ack(&state, "jobs", "worker", 8), Some(8));
+        assert_eq!(record_ack(&state, "jobs", "worker", 3), Some(8));
+        assert_eq!(redeliver(&state, "jobs", 0, u64::MAX).first().map(|f| f.seq), Some(9));
+        assert_eq!(record_ack(&state, "jobs", "worker", 99), Some(10));
+        assert_eq!(record_ack(&state, "jobs", "nobody", 1), None);
+    }
+}
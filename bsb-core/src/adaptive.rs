@@ -0,0 +1,113 @@
+use serde_json::json;
+
+/// Level degradasi maksimum: kirim 1 dari 2^MAX_LEVEL frame
+const MAX_LEVEL: u32 = 3;
+/// Observasi berturut-turut dengan antrian penuh sebelum turun satu level
+const PRESSURE_STREAK: u32 = 8;
+/// Frame berturut-turut dengan antrian kosong sebelum naik satu level
+const RECOVERY_STREAK: u32 = 64;
+
+/// Adaptive frame-rate control for one subscriber (`?adaptive=true`)
+///
+/// A subscriber whose broadcast queue stays more than half full is stepped
+/// down to every 2nd, 4th, then 8th frame instead of lagging; once its queue
+/// stays empty it is stepped back up one level at a time.
+#[derive(Debug)]
+pub struct QualityController {
+    capacity: usize,
+    level: u32,
+    pressure: u32,
+    healthy: u32,
+    counter: u64,
+}
+
+impl QualityController {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            level: 0,
+            pressure: 0,
+            healthy: 0,
+            counter: 0,
+        }
+    }
+
+    /// Observe the queue backlog when a frame is received
+    ///
+    /// Returns whether to send this frame and, if the level changed, the
+    /// status message for the client.
+    pub fn on_frame(&mut self, backlog: usize) -> (bool, Option<String>) {
+        let mut status = None;
+        if backlog * 2 > self.capacity {
+            self.healthy = 0;
+            self.pressure += 1;
+            if self.pressure >= PRESSURE_STREAK {
+                status = self.step_down();
+            }
+        } else if backlog == 0 {
+            self.pressure = 0;
+            self.healthy += 1;
+            if self.healthy >= RECOVERY_STREAK && self.level > 0 {
+                self.level -= 1;
+                self.healthy = 0;
+                status = Some(self.status("recovering"));
+            }
+        }
+
+        let send = self.counter.is_multiple_of(1 << self.level);
+        self.counter += 1;
+        (send, status)
+    }
+
+    /// The subscriber overflowed its queue: degrade immediately
+    pub fn on_lagged(&mut self) -> Option<String> {
+        self.step_down()
+    }
+
+    fn step_down(&mut self) -> Option<String> {
+        self.pressure = 0;
+        self.healthy = 0;
+        if self.level == MAX_LEVEL {
+            return None;
+        }
+        self.level += 1;
+        Some(self.status("congested"))
+    }
+
+    fn status(&self, reason: &str) -> String {
+        json!({
+            "type": "quality",
+            "level": self.level,
+            "frame_divisor": 1u32 << self.level,
+            "reason": reason,
+        })
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_down_and_recovers() {
+        let mut quality = QualityController::new(16);
+        for _ in 0..PRESSURE_STREAK - 1 {
+            assert_eq!(quality.on_frame(12), (true, None));
+        }
+        let (_, status) = quality.on_frame(12);
+        assert!(status.unwrap().contains("\"frame_divisor\":2"));
+
+        // Level 1: every other frame
+        let sent = (0..10).filter(|_| quality.on_frame(4).0).count();
+        assert_eq!(sent, 5);
+
+        let mut recovered = None;
+        for _ in 0..RECOVERY_STREAK {
+            if let (_, Some(status)) = quality.on_frame(0) {
+                recovered = Some(status);
+            }
+        }
+        assert!(recovered.unwrap().contains("\"level\":0"));
+    }
+}
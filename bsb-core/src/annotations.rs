@@ -0,0 +1,277 @@
+use axum::{
+    extract::{Path as AxumPath, Query, State},
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    fs,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncWriteExt, BufWriter},
+};
+use tracing::warn;
+
+use crate::{error::BrokerError, runtime, segment, AppState};
+
+/// Akhiran file sidecar anotasi: `<start_ms segmen>.annotations.jsonl`
+pub const ANNOTATIONS_SUFFIX: &str = ".annotations.jsonl";
+/// Anotasi yang dikembalikan per query bila `?limit=` tidak diberikan
+pub const DEFAULT_LIMIT: usize = 1000;
+
+/// Metadata dari producer (deteksi, nilai sensor) yang diarsipkan bersama rekaman
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// When the broker received the message (unix ms)
+    pub at_ms: u64,
+    /// Newest recorded frame when the message arrived
+    pub seq: u64,
+    pub data: Value,
+}
+
+/// Pesan data channel yang diarsipkan: hanya objek JSON, teks bebas (chat) dilewati
+pub fn parse(text: &str) -> Option<Value> {
+    serde_json::from_str::<Value>(text).ok().filter(Value::is_object)
+}
+
+/// Penulis sidecar anotasi di direktori rekaman stream
+///
+/// Annotations go to the file named after the segment being recorded, so
+/// each sidecar covers the same stretch of time as its segment. While no
+/// segment is open (before the first frame, after a write error) they stay
+/// in the previous file rather than starting one per message.
+pub struct AnnotationWriter {
+    dir: PathBuf,
+    current: Option<(u64, BufWriter<File>)>,
+}
+
+impl AnnotationWriter {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            current: None,
+        }
+    }
+
+    /// Tambahkan satu baris JSON ke sidecar segmen `segment_ms` (masih di buffer)
+    pub async fn append(&mut self, segment_ms: Option<u64>, annotation: &Annotation) -> io::Result<()> {
+        let key = segment_ms
+            .or(self.current.as_ref().map(|(key, _)| *key))
+            .unwrap_or(annotation.at_ms);
+        if self.current.as_ref().is_none_or(|(current, _)| *current != key) {
+            if let Some((_, mut previous)) = self.current.take() {
+                previous.flush().await?;
+            }
+            tokio::fs::create_dir_all(&self.dir).await?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dir.join(format!("{}{}", key, ANNOTATIONS_SUFFIX)))
+                .await?;
+            self.current = Some((key, BufWriter::new(file)));
+        }
+        let (_, file) = self.current.as_mut().expect("opened above");
+        let mut line = serde_json::to_vec(annotation).expect("annotation serializes");
+        line.push(b'\n');
+        file.write_all(&line).await
+    }
+
+    /// Flush dan fsync sidecar yang terbuka, bersama sync segmen
+    pub async fn sync(&mut self) -> io::Result<()> {
+        if let Some((_, file)) = self.current.as_mut() {
+            file.flush().await?;
+            file.get_ref().sync_data().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Syarat `?where=`: `path=value` atau `path` (field ada), dipisah koma dan semuanya harus cocok
+///
+/// Paths are dotted (`zone.id`). Strings compare as-is, other values are
+/// parsed as JSON first, so `zone=3` matches both `"zone":3` and `"zone":"3"`.
+#[derive(Debug, Default, Clone)]
+pub struct AnnotationFilter {
+    terms: Vec<(Vec<String>, Option<String>)>,
+}
+
+impl AnnotationFilter {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut terms = Vec::new();
+        for term in spec.split(',').map(str::trim).filter(|term| !term.is_empty()) {
+            let (path, expected) = match term.split_once('=') {
+                Some((path, value)) => (path.trim(), Some(value.trim().to_string())),
+                None => (term, None),
+            };
+            if path.is_empty() || path.split('.').any(str::is_empty) {
+                return Err(format!("invalid where term: {}", term));
+            }
+            terms.push((path.split('.').map(str::to_string).collect(), expected));
+        }
+        Ok(Self { terms })
+    }
+
+    pub fn matches(&self, data: &Value) -> bool {
+        self.terms.iter().all(|(path, expected)| {
+            let found = path.iter().try_fold(data, |value, key| value.get(key.as_str()));
+            match (found, expected) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(Value::String(text)), Some(expected)) => text == expected,
+                (Some(value), Some(expected)) => {
+                    serde_json::from_str::<Value>(expected).is_ok_and(|parsed| parsed == *value)
+                }
+            }
+        })
+    }
+}
+
+/// Anotasi dalam `[from_ms, to_ms]` yang cocok dengan `filter`, terlama lebih dulu
+///
+/// A sidecar only holds messages from its own start up to the next
+/// sidecar's start, so files outside the window are not read. A line cut
+/// short by a crash is skipped.
+pub fn search(
+    dir: &Path,
+    from_ms: u64,
+    to_ms: u64,
+    filter: &AnnotationFilter,
+    limit: usize,
+) -> io::Result<Vec<Annotation>> {
+    let mut sidecars: Vec<(u64, PathBuf)> = fs::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?;
+            let start_ms = name.strip_suffix(ANNOTATIONS_SUFFIX)?.parse().ok()?;
+            Some((start_ms, path))
+        })
+        .collect();
+    sidecars.sort_by_key(|(start_ms, _)| *start_ms);
+
+    let mut found = Vec::new();
+    for (i, (start_ms, path)) in sidecars.iter().enumerate() {
+        if *start_ms > to_ms {
+            break;
+        }
+        if sidecars.get(i + 1).is_some_and(|(next_ms, _)| *next_ms <= from_ms) {
+            continue;
+        }
+        for line in BufReader::new(fs::File::open(path)?).lines() {
+            let Ok(annotation) = serde_json::from_str::<Annotation>(&line?) else {
+                warn!("Skipping unreadable annotation in {}", path.display());
+                continue;
+            };
+            if (from_ms..=to_ms).contains(&annotation.at_ms) && filter.matches(&annotation.data) {
+                found.push(annotation);
+                if found.len() == limit {
+                    return Ok(found);
+                }
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Query untuk GET /api/recordings/:stream_id/annotations
+#[derive(Debug, Default, Deserialize)]
+pub struct AnnotationParams {
+    /// Unix ms, inklusif
+    from: Option<u64>,
+    /// Unix ms, inklusif
+    to: Option<u64>,
+    #[serde(rename = "where")]
+    filter: Option<String>,
+    /// Oldest N matching annotations (default 1000)
+    limit: Option<usize>,
+}
+
+/// Handler untuk GET /api/recordings/:stream_id/annotations?from=&to=&where=&limit=
+pub async fn list_annotations_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    Query(params): Query<AnnotationParams>,
+    State(state): State<AppState>,
+) -> Response {
+    let from_ms = params.from.unwrap_or(0);
+    let to_ms = params.to.unwrap_or(u64::MAX);
+    if from_ms > to_ms {
+        return BrokerError::InvalidRequest("from must not be after to".into()).into_response();
+    }
+    let filter = match AnnotationFilter::parse(params.filter.as_deref().unwrap_or("")) {
+        Ok(filter) => filter,
+        Err(e) => return BrokerError::InvalidRequest(e).into_response(),
+    };
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).max(1);
+
+    let dir = segment::stream_dir(&state.config.recordings_dir, &stream_id);
+    // Satu anotasi lebih untuk mengetahui apakah hasilnya terpotong
+    let result = runtime::spawn_disk_blocking(move || search(&dir, from_ms, to_ms, &filter, limit + 1)).await;
+    match result {
+        Ok(Ok(mut annotations)) => {
+            let truncated = annotations.len() > limit;
+            annotations.truncate(limit);
+            Json(json!({
+                "stream": stream_id,
+                "annotations": annotations,
+                "truncated": truncated,
+            }))
+            .into_response()
+        }
+        Ok(Err(e)) if e.kind() == io::ErrorKind::NotFound => {
+            BrokerError::not_found("recordings", stream_id).into_response()
+        }
+        Ok(Err(e)) => BrokerError::from(e).into_response(),
+        Err(e) => BrokerError::from(e).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_annotations_follow_segments_and_are_searchable() {
+        let dir = std::env::temp_dir().join(format!("bsb-annotations-test-{}", std::process::id()));
+        let mut writer = AnnotationWriter::new(&dir);
+        let annotation = |at_ms, seq, data: &str| Annotation {
+            at_ms,
+            seq,
+            data: parse(data).unwrap(),
+        };
+        // Sebelum segmen pertama terbuka, lalu dua segmen berurutan
+        writer.append(None, &annotation(900, 0, r#"{"event":"boot"}"#)).await.unwrap();
+        writer.append(Some(1000), &annotation(1010, 1, r#"{"event":"motion","zone":3}"#)).await.unwrap();
+        writer.append(Some(1000), &annotation(1500, 9, r#"{"event":"motion","zone":"1"}"#)).await.unwrap();
+        writer.append(Some(2000), &annotation(2100, 20, r#"{"event":"motion","zone":3,"box":{"w":40}}"#)).await.unwrap();
+        writer.append(None, &annotation(2200, 21, r#"{"temp":41.5}"#)).await.unwrap();
+        writer.sync().await.unwrap();
+        let mut files: Vec<_> = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        files.sort();
+        assert_eq!(files, ["1000.annotations.jsonl", "2000.annotations.jsonl", "900.annotations.jsonl"]);
+        // Baris yang terpotong crash dilewati
+        fs::OpenOptions::new()
+            .append(true)
+            .open(dir.join("2000.annotations.jsonl"))
+            .and_then(|mut file| io::Write::write_all(&mut file, b"{\"at_ms\":23"))
+            .unwrap();
+
+        let find = |from, to, filter: &str, limit| -> Vec<u64> {
+            let filter = AnnotationFilter::parse(filter).unwrap();
+            search(&dir, from, to, &filter, limit).unwrap().iter().map(|a| a.seq).collect()
+        };
+        assert_eq!(find(0, u64::MAX, "", 100), [0, 1, 9, 20, 21]);
+        assert_eq!(find(0, u64::MAX, "event=motion,zone=3", 100), [1, 20]);
+        assert_eq!(find(1200, u64::MAX, "zone=3", 100), [20]);
+        assert_eq!(find(0, u64::MAX, "box.w=40", 100), [20]);
+        assert_eq!(find(0, u64::MAX, "temp", 100), [21]);
+        assert_eq!(find(0, u64::MAX, "event", 2), [0, 1]);
+        assert!(find(3000, 4000, "", 100).is_empty());
+
+        assert!(parse("just chatting").is_none() && parse("[1,2]").is_none());
+        assert!(AnnotationFilter::parse("zone..id=3").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::{mux::glob_match, registry::Frame};
+
+/// Jenis payload stream; menentukan ukuran buffer, validasi dan level log
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamType {
+    #[default]
+    Video,
+    /// One Opus packet per frame (RFC 6716), typically 20 ms
+    Opus,
+    /// Raw PCM chunks
+    Pcm,
+}
+
+impl StreamType {
+    pub fn is_audio(self) -> bool {
+        self != StreamType::Video
+    }
+}
+
+impl std::str::FromStr for StreamType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "video" => Ok(StreamType::Video),
+            "opus" => Ok(StreamType::Opus),
+            "pcm" => Ok(StreamType::Pcm),
+            other => Err(format!("unknown stream type {:?} (use video, opus or pcm)", other)),
+        }
+    }
+}
+
+/// `AUDIO_STREAMS`: pattern glob ke jenis audio, mis. `voice/*=opus,intercom=pcm`
+#[derive(Debug, Clone, Default)]
+pub struct AudioStreams(Vec<(String, StreamType)>);
+
+impl AudioStreams {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut patterns = Vec::new();
+        for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (pattern, kind) = match entry.split_once('=') {
+                Some((pattern, kind)) => (pattern.trim(), kind.parse::<StreamType>()?),
+                None => (entry, StreamType::Opus),
+            };
+            if !kind.is_audio() {
+                return Err(format!("AUDIO_STREAMS entry {:?} is not an audio type", entry));
+            }
+            patterns.push((pattern.to_string(), kind));
+        }
+        Ok(Self(patterns))
+    }
+
+    pub fn for_stream(&self, stream_id: &str) -> Option<StreamType> {
+        self.0
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, stream_id))
+            .map(|(_, kind)| *kind)
+    }
+}
+
+pub use bsb_proto::opus::packet_duration_us as opus_packet_duration_us;
+
+/// Kumpulan frame yang dikirim ke subscriber sebagai satu binary message (`?batch_ms=`)
+#[derive(Debug)]
+pub struct FrameBatch {
+    window: Duration,
+    frames: Vec<Frame>,
+    /// Kapan batch dikirim; `None` selama batch kosong
+    pub deadline: Option<Instant>,
+}
+
+impl FrameBatch {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            frames: Vec::new(),
+            deadline: None,
+        }
+    }
+
+    pub fn push(&mut self, frame: Frame) {
+        self.deadline.get_or_insert_with(|| Instant::now() + self.window);
+        self.frames.push(frame);
+    }
+
+    /// Ambil isi batch; batch kosong kembali menunggu frame berikutnya
+    pub fn take(&mut self) -> Vec<Frame> {
+        self.deadline = None;
+        std::mem::take(&mut self.frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opus_toc_and_audio_patterns() {
+        // config 1 (SILK NB 20 ms), code 0: satu frame
+        assert_eq!(opus_packet_duration_us(&[1 << 3, 0xAA]), Ok(20_000));
+        // config 31 (CELT FB 20 ms), code 1: dua frame berukuran sama
+        assert_eq!(opus_packet_duration_us(&[31 << 3 | 1, 1, 2]), Ok(40_000));
+        // config 16 (CELT 2.5 ms), code 3 dengan 4 frame
+        assert_eq!(opus_packet_duration_us(&[16 << 3 | 3, 4]), Ok(10_000));
+        assert!(opus_packet_duration_us(&[]).is_err());
+        assert!(opus_packet_duration_us(&[31 << 3 | 1, 1]).is_err());
+        // 3 x 60 ms melebihi batas 120 ms
+        assert!(opus_packet_duration_us(&[3 << 3 | 3, 3]).is_err());
+
+        let audio = AudioStreams::parse("voice/*=opus, intercom=pcm, radio*").unwrap();
+        assert_eq!(audio.for_stream("voice/room1"), Some(StreamType::Opus));
+        assert_eq!(audio.for_stream("intercom"), Some(StreamType::Pcm));
+        assert_eq!(audio.for_stream("radio2"), Some(StreamType::Opus));
+        assert_eq!(audio.for_stream("cam1"), None);
+        assert!(AudioStreams::parse("cams/*=video").is_err());
+    }
+}
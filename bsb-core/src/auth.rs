@@ -0,0 +1,478 @@
+use axum::{
+    extract::{Path as AxumPath, Request, State},
+    http::{header, HeaderMap, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tracing::warn;
+
+use crate::{
+    error::BrokerError,
+    hooks::{Access, AuthDecision, AuthRequest},
+    mux::glob_match,
+    tls::ClientIdentity,
+    AppState,
+};
+
+/// Peran credential admin; peran yang lebih tinggi mencakup izin peran di bawahnya
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read-only: stream lists, stats, health, recordings listings
+    Viewer,
+    /// Changes stream settings, taps, groups, exports and clips
+    Operator,
+    /// Everything, including deleting recordings and the profiling/debug endpoints
+    Admin,
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "viewer" => Ok(Self::Viewer),
+            "operator" => Ok(Self::Operator),
+            "admin" => Ok(Self::Admin),
+            other => Err(format!("Unknown role '{}' (expected viewer, operator or admin)", other)),
+        }
+    }
+}
+
+/// Peran minimum untuk sebuah request ke endpoint admin
+///
+/// The backup archive holds password hashes and source credentials, so reading
+/// it needs the same role as restoring it.
+pub fn required_role(method: &Method, path: &str) -> Role {
+    if path.starts_with("/debug/pprof/") || path.starts_with("/api/debug/") || path.starts_with("/api/users") {
+        return Role::Admin;
+    }
+    if path == "/api/backup" || path == "/api/restore" {
+        return Role::Admin;
+    }
+    match *method {
+        Method::GET | Method::HEAD => Role::Viewer,
+        Method::DELETE if path.starts_with("/api/recordings/") || path.contains("/clips/") => Role::Admin,
+        _ => Role::Operator,
+    }
+}
+
+/// Satu credential producer: token (dan opsional username untuk Basic auth)
+#[derive(Clone)]
+pub struct Credential {
+    pub username: Option<String>,
+    secret: String,
+    patterns: Vec<String>,
+    /// Admin role from an `@role` entry; `None` = only the path globs apply
+    pub role: Option<Role>,
+    /// Extra scopes from `+scope` entries, e.g. `+metadata:full` for `METADATA_REDACTION`
+    pub scopes: Vec<String>,
+}
+
+impl Credential {
+    /// Apakah credential ini boleh mengirim frame ke `stream_id`
+    pub fn allows(&self, stream_id: &str) -> bool {
+        self.patterns.iter().any(|p| glob_match(p, stream_id))
+    }
+
+    /// Apakah credential admin ini boleh menjalankan `method` pada `path`
+    pub fn permits(&self, method: &Method, path: &str) -> bool {
+        self.allows(path) || self.role.is_some_and(|role| role >= required_role(method, path))
+    }
+
+    /// In-memory key for per-credential bookkeeping (session quotas); never logged
+    pub fn session_key(&self) -> &str {
+        &self.secret
+    }
+
+    /// Name used in logs, never the secret itself
+    pub fn label(&self) -> &str {
+        self.username.as_deref().unwrap_or("<token>")
+    }
+}
+
+/// Store of ingest credentials shared by Bearer tokens and HTTP Basic auth
+///
+/// Format of `INGEST_CREDENTIALS`: `[user:]secret=pattern,pattern;...`, e.g.
+/// `tok-123=cam1,cam2;legacy-dvr:pa55=site-a/*`. A Bearer token matches any
+/// entry's secret. With Basic auth enabled, `user:password` matches an entry
+/// with that username and secret, or a username-less entry whose secret is
+/// the password (for devices that insist on sending some username).
+#[derive(Clone, Default)]
+pub struct CredentialStore {
+    credentials: Vec<Credential>,
+    pub allow_basic: bool,
+}
+
+impl std::fmt::Debug for CredentialStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CredentialStore")
+            .field("credentials", &self.credentials.len())
+            .field("allow_basic", &self.allow_basic)
+            .finish()
+    }
+}
+
+impl CredentialStore {
+    pub fn parse(spec: &str, allow_basic: bool) -> Result<Self, String> {
+        let mut credentials = Vec::new();
+        for rule in spec.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            let (ident, patterns) = rule
+                .rsplit_once('=')
+                .ok_or_else(|| "Invalid INGEST_CREDENTIALS rule (missing '=')".to_string())?;
+            let (username, secret) = match ident.split_once(':') {
+                Some((user, secret)) => (Some(user.to_string()), secret.to_string()),
+                None => (None, ident.to_string()),
+            };
+            if secret.is_empty() {
+                return Err("Invalid INGEST_CREDENTIALS rule (empty secret)".to_string());
+            }
+            let mut role = None;
+            let mut scopes = Vec::new();
+            let mut globs = Vec::new();
+            for pattern in patterns.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                if let Some(name) = pattern.strip_prefix('@') {
+                    role = Some(name.parse()?);
+                } else if let Some(scope) = pattern.strip_prefix('+') {
+                    scopes.push(scope.to_string());
+                } else {
+                    globs.push(pattern.to_string());
+                }
+            }
+            credentials.push(Credential {
+                username,
+                secret,
+                patterns: globs,
+                role,
+                scopes,
+            });
+        }
+        Ok(Self {
+            credentials,
+            allow_basic,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.credentials.is_empty()
+    }
+
+    /// Resolve an `Authorization` header value into the credential it names
+    pub fn authenticate(&self, authorization: &str) -> Option<&Credential> {
+        if let Some(token) = authorization.strip_prefix("Bearer ") {
+            let token = token.trim();
+            return self
+                .credentials
+                .iter()
+                .find(|c| constant_time_eq(&c.secret, token));
+        }
+        if !self.allow_basic {
+            return None;
+        }
+        let encoded = authorization.strip_prefix("Basic ")?;
+        let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+        let (user, password) = decoded.split_once(':')?;
+        self.credentials.iter().find(|c| {
+            constant_time_eq(&c.secret, password)
+                && c.username.as_deref().is_none_or(|u| u == user)
+        })
+    }
+}
+
+/// Compare secrets without short-circuiting on the first differing byte
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a
+            .bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+fn unauthorized(state: &AppState) -> Response {
+    let error = BrokerError::Unauthorized("missing or invalid ingest credential".to_string());
+    if state.config.ingest_credentials.allow_basic {
+        (
+            [(header::WWW_AUTHENTICATE, "Basic realm=\"ingest\"")],
+            error,
+        )
+            .into_response()
+    } else {
+        error.into_response()
+    }
+}
+
+/// Middleware untuk endpoint ingest
+///
+/// Auth is only enforced when credentials, mTLS or OIDC are configured. A
+/// request passes with a permitted token/Basic credential, an OIDC token with
+/// a matching publish scope, or a permitted client certificate; an
+/// authenticated identity without access to the stream gets 403. Embedder
+/// [`Authenticator`](crate::hooks::Authenticator)s are asked first.
+pub async fn ingest_auth_middleware(
+    State(state): State<AppState>,
+    AxumPath(stream_id): AxumPath<String>,
+    identity: Option<Extension<ClientIdentity>>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Response {
+    let hook = AuthRequest {
+        access: Access::Publish,
+        stream_id: &stream_id,
+        headers: &headers,
+        token: None,
+    };
+    match state.hooks.authenticate(hook).await {
+        AuthDecision::Allow(_) => return next.run(request).await,
+        AuthDecision::Deny(e) => {
+            warn!("Rejected ingest for stream {}: {}", stream_id, e);
+            return e.into_response();
+        }
+        AuthDecision::Pass => {}
+    }
+
+    let credentials = &state.config.ingest_credentials;
+    let mtls = state
+        .config
+        .tls
+        .as_ref()
+        .filter(|tls| tls.client_ca_path.is_some());
+    let oidc = state.oidc.as_ref().filter(|oidc| oidc.config().ingest);
+    if credentials.is_empty() && mtls.is_none() && oidc.is_none() {
+        return next.run(request).await;
+    }
+
+    let mut authenticated = false;
+
+    if let Some(authorization) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+    {
+        match (credentials.authenticate(authorization), oidc, authorization.strip_prefix("Bearer ")) {
+            (Some(credential), _, _) if credential.allows(&stream_id) => return next.run(request).await,
+            (Some(credential), _, _) => {
+                warn!(
+                    "Rejected ingest for stream {}: credential {} not permitted",
+                    stream_id,
+                    credential.label()
+                );
+                authenticated = true;
+            }
+            // Token yang tidak dikenal secara lokal divalidasi identity provider
+            (None, Some(oidc), Some(token)) => match oidc.validate(token.trim()).await {
+                Ok(grants) if grants.can_publish(&stream_id) => return next.run(request).await,
+                Ok(grants) => {
+                    warn!("Rejected ingest for stream {}: {} has no publish scope", stream_id, grants.subject);
+                    authenticated = true;
+                }
+                // Provider tidak terjangkau: 503 supaya producer mencoba lagi
+                Err(e @ BrokerError::Unavailable(_)) => return e.into_response(),
+                Err(e) => warn!("Rejected ingest for stream {}: {}", stream_id, e),
+            },
+            (None, _, _) => warn!("Rejected ingest for stream {}: invalid credentials", stream_id),
+        }
+    }
+
+    if let (Some(tls), Some(Extension(identity))) = (mtls, identity) {
+        if tls.client_permissions.allows(&identity, &stream_id) {
+            return next.run(request).await;
+        }
+        warn!(
+            "Rejected ingest for stream {}: certificate {:?} not permitted",
+            stream_id, identity.names
+        );
+        authenticated = true;
+    }
+
+    if authenticated {
+        BrokerError::Forbidden(format!("credential is not permitted for stream {}", stream_id)).into_response()
+    } else {
+        unauthorized(&state)
+    }
+}
+
+/// Middleware untuk endpoint admin (`/debug/pprof/*`, `/api/debug/state`, `/api/users`)
+///
+/// Unlike ingest, admin endpoints are closed unless `ADMIN_CREDENTIALS` is
+/// set or `USERS_FILE` has a user with a role. An admin credential lists
+/// globs over request paths and/or a role, e.g. `ops:s3cret=/debug/pprof/*`
+/// or `grafana:pw=@viewer`; see [`required_role`] for what each role may call.
+pub async fn admin_auth_middleware(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Response {
+    let credentials = &state.config.admin_credentials;
+    if credentials.is_empty() && !state.users.has_admins() {
+        let error = "admin endpoints are disabled (no ADMIN_CREDENTIALS or admin users)";
+        return BrokerError::Forbidden(error.to_string()).into_response();
+    }
+    let path = request.uri().path().to_string();
+    let method = request.method().clone();
+    let authorization = headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok());
+    // Credential dari ADMIN_CREDENTIALS dulu, lalu user lokal (USERS_FILE) dengan perannya
+    let identity = match authorization.and_then(|a| credentials.authenticate(a)) {
+        Some(credential) => Some((credential.label().to_string(), credential.permits(&method, &path))),
+        None => match authorization {
+            Some(authorization) => state.users.authenticate(authorization).await.map(|(name, user)| {
+                let permitted = user.role.is_some_and(|role| role >= required_role(&method, &path));
+                (name, permitted)
+            }),
+            None => None,
+        },
+    };
+    match identity {
+        Some((_, true)) => next.run(request).await,
+        Some((label, false)) => {
+            warn!("Rejected admin request {} {}: credential {} not permitted", method, path, label);
+            BrokerError::Forbidden(format!("credential is not permitted for {} {}", method, path)).into_response()
+        }
+        None => {
+            warn!("Rejected admin request {}: invalid credentials", path);
+            let error = BrokerError::Unauthorized("missing or invalid admin credential".to_string());
+            ([(header::WWW_AUTHENTICATE, "Basic realm=\"admin\"")], error).into_response()
+        }
+    }
+}
+
+/// Apakah `path` termasuk admin API yang dijaga `ADMIN_API_AUTH`
+///
+/// Frame pulls/bootstraps are subscriber traffic and keep their own auth, as
+/// do cluster-internal calls when `CLUSTER_TOKEN` guards them; without a
+/// token those need an operator like any other change. Paths in
+/// [`route_guarded`] are skipped so a request is verified only once.
+fn is_admin_api(path: &str, cluster_token: bool) -> bool {
+    if route_guarded(path) {
+        return false;
+    }
+    if path == "/debug" || path.starts_with("/debug/") {
+        return true;
+    }
+    let Some(rest) = path.strip_prefix("/api/") else {
+        return false;
+    };
+    let internal = cluster_token && ["cluster/gossip", "cluster/handoff", "cluster/drain"].contains(&rest);
+    !internal && !rest.ends_with("/frames") && !rest.ends_with("/bootstrap")
+}
+
+/// Apakah route `path` sudah memasang `admin_auth_middleware` sendiri
+///
+/// These endpoints are guarded regardless of `ADMIN_API_AUTH`; keep the list in
+/// step with the `route_layer`s in `build_router`.
+fn route_guarded(path: &str) -> bool {
+    if path.starts_with("/debug/pprof/") || path.starts_with("/api/debug/") || path.starts_with("/api/users") {
+        return true;
+    }
+    if ["/api/backup", "/api/restore", "/api/config/validate"].contains(&path) {
+        return true;
+    }
+    let stream_route = path.strip_prefix("/api/streams/").and_then(|rest| rest.split_once('/'));
+    stream_route.is_some_and(|(id, route)| !id.is_empty() && (route == "chaos" || route == "replay"))
+}
+
+/// Middleware seluruh router: peran credential admin untuk `/api/*` dan `/debug`
+///
+/// Off unless `ADMIN_API_AUTH=true`, so existing deployments keep an open API.
+pub async fn admin_api_middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if !state.config.admin_api_auth || !is_admin_api(request.uri().path(), state.config.cluster.has_token()) {
+        return next.run(request).await;
+    }
+    let headers = request.headers().clone();
+    admin_auth_middleware(State(state), headers, request, next).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn basic(user: &str, password: &str) -> String {
+        format!("Basic {}", STANDARD.encode(format!("{}:{}", user, password)))
+    }
+
+    #[test]
+    fn test_credential_store_bearer_and_basic() {
+        let store =
+            CredentialStore::parse("tok-123=cam1,cam2; legacy-dvr:pa55=site-a/*", true).unwrap();
+
+        let token = store.authenticate("Bearer tok-123").unwrap();
+        assert!(token.allows("cam2"));
+        assert!(!token.allows("cam3"));
+
+        let dvr = store.authenticate(&basic("legacy-dvr", "pa55")).unwrap();
+        assert!(dvr.allows("site-a/cam1"));
+        assert!(store.authenticate(&basic("someone-else", "pa55")).is_none());
+        // Username-less entries accept any username with the token as password
+        assert!(store.authenticate(&basic("admin", "tok-123")).is_some());
+        assert!(store.authenticate("Bearer wrong").is_none());
+
+        let no_basic = CredentialStore::parse("tok-123=cam1", false).unwrap();
+        assert!(no_basic.authenticate(&basic("admin", "tok-123")).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_admin_roles_enforced_per_endpoint() {
+        use crate::config::Config;
+        use axum::{body::Body, http::StatusCode, middleware, routing::get, Router};
+        use tower::util::ServiceExt;
+
+        let store =
+            CredentialStore::parse("grafana:view=@viewer; oncall:op=@operator; root:adm=@admin; ops:s3cret=/debug/pprof/*", true)
+                .unwrap();
+        assert!(CredentialStore::parse("x:y=@superuser", true).is_err());
+        let state = crate::AppState::new(Config {
+            admin_credentials: store,
+            admin_api_auth: true,
+            ..Config::default()
+        });
+        let ok = || async { "ok" };
+        let app = Router::new()
+            .route("/api/streams", get(ok))
+            .route("/api/streams/:id/taps/:name", get(ok).put(ok).delete(ok))
+            .route("/api/streams/:id/frames", get(ok))
+            .route("/api/recordings/:id/exports/:export", get(ok).delete(ok))
+            .route("/api/cluster/drain", axum::routing::post(ok))
+            .layer(middleware::from_fn_with_state(state.clone(), admin_api_middleware))
+            .with_state(state);
+        let send = |method: Method, path: &str, user: Option<(&str, &str)>| {
+            let mut request = Request::builder().method(method).uri(path);
+            if let Some((user, password)) = user {
+                request = request.header(header::AUTHORIZATION, basic(user, password));
+            }
+            let response = app.clone().oneshot(request.body(Body::empty()).unwrap());
+            async move { response.await.unwrap().status() }
+        };
+        let viewer = Some(("grafana", "view"));
+        let operator = Some(("oncall", "op"));
+        let admin = Some(("root", "adm"));
+
+        assert_eq!(send(Method::GET, "/api/streams", None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(send(Method::GET, "/api/streams", viewer).await, StatusCode::OK);
+        // Viewer hanya membaca; operator mengubah setting stream
+        assert_eq!(send(Method::PUT, "/api/streams/cam1/taps/ai", viewer).await, StatusCode::FORBIDDEN);
+        assert_eq!(send(Method::DELETE, "/api/streams/cam1/taps/ai", operator).await, StatusCode::OK);
+        // Hapus rekaman hanya untuk admin
+        let export = "/api/recordings/cam1/exports/1";
+        assert_eq!(send(Method::GET, export, viewer).await, StatusCode::OK);
+        assert_eq!(send(Method::DELETE, export, operator).await, StatusCode::FORBIDDEN);
+        assert_eq!(send(Method::DELETE, export, admin).await, StatusCode::OK);
+        // Credential lama dengan glob path tidak mendapat akses API
+        assert_eq!(send(Method::GET, "/api/streams", Some(("ops", "s3cret"))).await, StatusCode::FORBIDDEN);
+        // Pull frame punya auth sendiri; drain tanpa CLUSTER_TOKEN butuh operator
+        assert_eq!(send(Method::GET, "/api/streams/cam1/frames", None).await, StatusCode::OK);
+        assert_eq!(send(Method::POST, "/api/cluster/drain", None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(send(Method::POST, "/api/cluster/drain", viewer).await, StatusCode::FORBIDDEN);
+        assert_eq!(send(Method::POST, "/api/cluster/drain", operator).await, StatusCode::OK);
+        assert!(is_admin_api("/api/cluster/drain", false));
+        assert!(!is_admin_api("/api/cluster/drain", true));
+        // Route dengan guard sendiri tidak diverifikasi dua kali
+        assert!(!is_admin_api("/api/backup", false));
+        assert!(!is_admin_api("/api/streams/cam1/chaos", false));
+        assert!(is_admin_api("/api/streams/cam1/labels", false));
+    }
+}
@@ -0,0 +1,246 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use tracing::info;
+
+use crate::{error::BrokerError, runtime, sources, stats::unix_now_ms, users, AppState};
+
+/// Penanda arsip backup broker
+pub const FORMAT: &str = "bsb-backup";
+/// Versi arsip terbaru yang bisa di-restore broker ini
+pub const VERSION: u32 = 1;
+
+/// Arsip konfigurasi broker dari GET /api/backup, dipulihkan dengan POST /api/restore
+///
+/// Holds what a replacement unit needs to take over from a failed one: the
+/// static stream definitions (with their source credentials and labels such
+/// as `tenant`), the local users with their password hashes, and the stream
+/// groups. Sections missing from an archive are left alone on restore.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Backup {
+    pub format: String,
+    pub version: u32,
+    /// Broker version that wrote the archive
+    #[serde(default)]
+    pub broker_version: String,
+    #[serde(default)]
+    pub created_ms: u64,
+    /// Contents of `STATIC_STREAMS_FILE`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub static_streams: Option<Value>,
+    /// Contents of `USERS_FILE`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub users: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub groups: Option<BTreeMap<String, Vec<String>>>,
+}
+
+/// Handler untuk GET /api/backup
+/// Download the broker configuration as one JSON archive
+pub async fn backup_handler(State(state): State<AppState>) -> Response {
+    let static_streams = match state.config.static_streams_file.clone() {
+        Some(path) => {
+            let store = state.hooks.store.clone();
+            let loaded = runtime::spawn_disk_blocking(move || store.load(&path)).await;
+            match loaded {
+                Ok(Ok(Some(contents))) => match serde_json::from_slice(&contents) {
+                    Ok(streams) => Some(streams),
+                    Err(e) => {
+                        let error = format!("STATIC_STREAMS_FILE is not valid JSON: {}", e);
+                        return BrokerError::Internal(error).into_response();
+                    }
+                },
+                Ok(Ok(None)) => None,
+                Ok(Err(e)) => return BrokerError::from(e).into_response(),
+                Err(e) => return BrokerError::from(e).into_response(),
+            }
+        }
+        None => None,
+    };
+    let backup = Backup {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        broker_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_ms: unix_now_ms(),
+        static_streams,
+        users: state.users.enabled().then(|| state.users.export()),
+        groups: Some(state.groups.list().into_iter().collect()),
+    };
+    let filename = format!("bsb-backup-{}.json", backup.created_ms);
+    (
+        [(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename))],
+        Json(backup),
+    )
+        .into_response()
+}
+
+/// Handler untuk POST /api/restore
+/// Replace the configuration with an archive from GET /api/backup
+///
+/// Every section is checked before any is applied, so a bad archive changes
+/// nothing. Users and groups take effect right away; static streams are
+/// written to `STATIC_STREAMS_FILE` and created on the next start.
+pub async fn restore_handler(State(state): State<AppState>, Json(backup): Json<Backup>) -> Response {
+    if backup.format != FORMAT {
+        return BrokerError::InvalidRequest(format!("not a {} archive", FORMAT)).into_response();
+    }
+    if backup.version > VERSION {
+        let error = format!("archive version {} is newer than this broker supports ({})", backup.version, VERSION);
+        return BrokerError::InvalidRequest(error).into_response();
+    }
+
+    let static_streams = match (backup.static_streams, state.config.static_streams_file.clone()) {
+        (Some(streams), Some(path)) => {
+            if let Err(e) = sources::parse(&streams.to_string()) {
+                return BrokerError::InvalidRequest(format!("invalid static streams: {}", e)).into_response();
+            }
+            Some((path, serde_json::to_vec_pretty(&streams).expect("JSON value serializes")))
+        }
+        (Some(_), None) => {
+            let error = "archive has static streams but STATIC_STREAMS_FILE is not set".to_string();
+            return BrokerError::Unavailable(error).into_response();
+        }
+        (None, _) => None,
+    };
+    let users = match backup.users {
+        Some(_) if !state.users.enabled() => {
+            let error = "archive has users but USERS_FILE is not set".to_string();
+            return BrokerError::Unavailable(error).into_response();
+        }
+        Some(users) => match users::parse_backup(users) {
+            Ok(users) => Some(users),
+            Err(e) => return BrokerError::InvalidRequest(e).into_response(),
+        },
+        None => None,
+    };
+
+    let mut restored = Vec::new();
+    let restart_required = static_streams.is_some();
+    if let Some((path, contents)) = static_streams {
+        let store = state.hooks.store.clone();
+        match runtime::spawn_disk_blocking(move || store.save(&path, &contents)).await {
+            Ok(Ok(())) => restored.push("static_streams"),
+            Ok(Err(e)) => return BrokerError::from(e).into_response(),
+            Err(e) => return BrokerError::from(e).into_response(),
+        }
+    }
+    if let Some(users) = users {
+        if let Err(e) = state.users.replace(users).await {
+            return e.into_response();
+        }
+        restored.push("users");
+    }
+    if let Some(groups) = backup.groups {
+        state.groups.replace_all(groups);
+        restored.push("groups");
+    }
+    info!(
+        "Restored {:?} from a backup of broker {} taken at {}",
+        restored, backup.broker_version, backup.created_ms
+    );
+    Json(json!({
+        "restored": restored,
+        "restart_required": restart_required,
+    }))
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, testing::TestBroker};
+    use axum::body::to_bytes;
+
+    #[tokio::test]
+    async fn test_backup_restores_onto_a_replacement_broker() {
+        let dir = std::env::temp_dir().join(format!("bsb-backup-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        std::fs::write(
+            path("old-streams.json"),
+            r#"[{"id":"gate","source":"http://10.0.0.5/mjpeg","record":true,"labels":{"tenant":"acme"}}]"#,
+        )
+        .unwrap();
+        std::fs::write(
+            path("old-users.json"),
+            r#"{"users":{"ops":{"password_hash":"$argon2id$v=19$m=19456,t=2,p=1$c2FsdHNhbHRzYWx0$ZmFrZWhhc2hmYWtlaGFzaGZha2VoYXNo","streams":["*"]}}}"#,
+        )
+        .unwrap();
+
+        let failed = TestBroker::start(Config {
+            static_streams_file: Some(path("old-streams.json")),
+            users_file: Some(path("old-users.json")),
+            ..Config::default()
+        })
+        .await;
+        failed.state.users.load().unwrap();
+        failed.state.groups.set("site-a", vec!["gate".to_string()]);
+        let response = backup_handler(State(failed.state.clone())).await;
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let backup: Backup = serde_json::from_slice(&body).unwrap();
+        assert_eq!((backup.format.as_str(), backup.version), (FORMAT, VERSION));
+
+        let replacement = TestBroker::start(Config {
+            static_streams_file: Some(path("new-streams.json")),
+            users_file: Some(path("new-users.json")),
+            ..Config::default()
+        })
+        .await;
+        let response = restore_handler(State(replacement.state.clone()), Json(backup)).await;
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let result: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(result["restored"], json!(["static_streams", "users", "groups"]));
+        assert_eq!(result["restart_required"], true);
+
+        let streams = sources::load(&path("new-streams.json")).unwrap();
+        assert_eq!((streams[0].id.as_str(), streams[0].labels["tenant"].as_str()), ("gate", "acme"));
+        assert!(replacement.state.users.has_viewers());
+        assert!(std::fs::read_to_string(path("new-users.json")).unwrap().contains("\"ops\""));
+        assert_eq!(replacement.state.groups.members("site-a"), Some(vec!["gate".to_string()]));
+
+        // Arsip yang rusak ditolak tanpa mengubah apa pun
+        let broken: Backup = serde_json::from_value(json!({
+            "format": FORMAT,
+            "version": VERSION,
+            "users": {"users": {"eve": {"password_hash": "plain"}}},
+            "groups": {},
+        }))
+        .unwrap();
+        let response = restore_handler(State(replacement.state.clone()), Json(broken)).await;
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+        assert!(replacement.state.groups.members("site-a").is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_backup_needs_admin_role() {
+        use crate::auth::CredentialStore;
+        use axum::http::{header, Request, StatusCode};
+        use base64::{engine::general_purpose::STANDARD, Engine};
+        use tower::util::ServiceExt;
+
+        // Hasilnya sama dengan atau tanpa ADMIN_API_AUTH (hanya guard route yang berlaku)
+        let credentials = "grafana:view=@viewer;oncall:op=@operator;root:adm=@admin";
+        for admin_api_auth in [false, true] {
+            let state = crate::AppState::new(Config {
+                admin_credentials: CredentialStore::parse(credentials, true).unwrap(),
+                admin_api_auth,
+                ..Config::default()
+            });
+            let get = |user: &str, password: &str| {
+                let authorization = format!("Basic {}", STANDARD.encode(format!("{}:{}", user, password)));
+                let request = Request::get("/api/backup").header(header::AUTHORIZATION, authorization);
+                crate::build_router(state.clone()).oneshot(request.body(axum::body::Body::empty()).unwrap())
+            };
+            // Arsip berisi hash password; viewer dan operator tidak boleh membacanya
+            assert_eq!(get("grafana", "view").await.unwrap().status(), StatusCode::FORBIDDEN);
+            assert_eq!(get("oncall", "op").await.unwrap().status(), StatusCode::FORBIDDEN);
+            assert_eq!(get("root", "adm").await.unwrap().status(), StatusCode::OK);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use axum::http::HeaderMap;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{playback, AppState};
+
+/// Burst token bucket: kuota satu detik
+const BURST: Duration = Duration::from_secs(1);
+
+/// Batas egress subscriber dari `SUBSCRIBER_MAX_KBPS` dan `PLAYBACK_BANDWIDTH_KBPS`
+#[derive(Debug, Clone, Default)]
+pub struct BandwidthLimits {
+    /// Cap for every subscriber without a named limit (0 = unlimited)
+    pub default_kbps: u64,
+    /// Per playback credential username (`viewer:secret=...` in `PLAYBACK_CREDENTIALS`)
+    per_user: HashMap<String, u64>,
+}
+
+impl BandwidthLimits {
+    /// `PLAYBACK_BANDWIDTH_KBPS`: `user=kbps,...`, mis. `site-a=4000,guest=500`
+    pub fn parse(default_kbps: u64, spec: &str) -> Result<Self, String> {
+        let mut per_user = HashMap::new();
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (user, kbps) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid PLAYBACK_BANDWIDTH_KBPS entry {:?} (expected user=kbps)", entry))?;
+            let kbps = kbps
+                .trim()
+                .parse()
+                .map_err(|e| format!("Invalid PLAYBACK_BANDWIDTH_KBPS entry {:?}: {}", entry, e))?;
+            per_user.insert(user.trim().to_string(), kbps);
+        }
+        Ok(Self {
+            default_kbps,
+            per_user,
+        })
+    }
+
+    /// Kbps untuk credential ini; `None` = tanpa batas
+    pub fn for_user(&self, user: Option<&str>) -> Option<u64> {
+        let kbps = user
+            .and_then(|user| self.per_user.get(user))
+            .copied()
+            .unwrap_or(self.default_kbps);
+        (kbps > 0).then_some(kbps)
+    }
+}
+
+/// Batas egress subscriber `/ws/:stream_id`: konfigurasi token, diperketat oleh `?max_kbps=`
+pub fn subscriber_limit(
+    state: &AppState,
+    headers: &HeaderMap,
+    token: Option<&str>,
+    requested_kbps: Option<u64>,
+) -> Option<u64> {
+    let credential = playback::credential(state, headers, token);
+    let configured = state
+        .config
+        .subscriber_bandwidth
+        .for_user(credential.and_then(|credential| credential.username.as_deref()));
+    match (configured, requested_kbps.filter(|&kbps| kbps > 0)) {
+        (Some(configured), Some(requested)) => Some(configured.min(requested)),
+        (configured, requested) => configured.or(requested),
+    }
+}
+
+/// Token bucket atas byte frame yang dikirim ke satu subscriber
+///
+/// Live frames that do not fit are skipped (`admit`) so the client stays
+/// real-time; replayed and timeshifted frames are paced instead (`delay`).
+/// A frame larger than the whole burst is still admitted once the bucket is
+/// full, so big keyframes are never starved; the deficit is paid back first.
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// Bytes per second
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(kbps: u64, now: Instant) -> Self {
+        let rate = kbps as f64 * 1000.0 / 8.0;
+        Self {
+            rate,
+            tokens: rate * BURST.as_secs_f64(),
+            last: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let burst = self.rate * BURST.as_secs_f64();
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(burst);
+        self.last = now;
+    }
+
+    /// Ambil kuota untuk frame live; `false` jika frame harus dilewati
+    pub fn admit(&mut self, bytes: usize, now: Instant) -> bool {
+        self.refill(now);
+        let full = self.tokens >= self.rate * BURST.as_secs_f64();
+        if self.tokens < bytes as f64 && !full {
+            return false;
+        }
+        self.tokens -= bytes as f64;
+        true
+    }
+
+    /// Ambil kuota untuk frame replay/timeshift; jeda sebelum frame boleh dikirim
+    pub fn delay(&mut self, bytes: usize, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_caps_egress() {
+        // 80 kbps = 10_000 byte/detik, burst 10_000 byte
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(80, start);
+        assert!(bucket.admit(6_000, start));
+        assert!(!bucket.admit(6_000, start));
+        // 0,2 detik kemudian: 4_000 + 2_000 byte tersedia
+        assert!(bucket.admit(6_000, start + Duration::from_millis(200)));
+
+        // Frame lebih besar dari burst tetap lolos saat bucket penuh, lalu defisit dibayar
+        let later = start + Duration::from_secs(5);
+        assert!(bucket.admit(25_000, later));
+        assert!(!bucket.admit(1, later + Duration::from_secs(1)));
+        assert_eq!(bucket.delay(10_000, later + Duration::from_secs(2)), Duration::from_millis(500));
+
+        let limits = BandwidthLimits::parse(2000, "site-a=4000, guest=500, vip=0").unwrap();
+        assert_eq!(limits.for_user(Some("guest")), Some(500));
+        assert_eq!(limits.for_user(Some("other")), Some(2000));
+        assert_eq!(limits.for_user(None), Some(2000));
+        assert_eq!(limits.for_user(Some("vip")), None);
+        assert!(BandwidthLimits::parse(0, "guest").is_err());
+    }
+}
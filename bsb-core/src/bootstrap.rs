@@ -0,0 +1,150 @@
+use axum::{
+    extract::{Path as AxumPath, Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Json, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{error::BrokerError, playback, registry::Frame, AppState};
+
+/// Query untuk GET /api/streams/:stream_id/bootstrap
+#[derive(Debug, Default, Deserialize)]
+pub struct BootstrapParams {
+    /// Playback token for clients that cannot set `Authorization`
+    token: Option<String>,
+}
+
+/// Frame yang diawali box `ftyp` adalah init segment fragmented MP4
+///
+/// The init segment carries the codec configuration; every later fragment is
+/// undecodable without it, so it is cached separately from the latest frame.
+pub fn is_init_segment(data: &[u8]) -> bool {
+    data.get(4..8) == Some(&b"ftyp"[..])
+}
+
+/// Frame sebagai JSON, payload di-encode base64
+fn frame_json(frame: &Frame) -> serde_json::Value {
+    json!({
+        "seq": frame.seq,
+        "producer_ms": frame.producer_ms,
+        "age_ms": frame.received_at.elapsed().as_millis() as u64,
+        "bytes": frame.data.len(),
+        "data": STANDARD.encode(&frame.data),
+    })
+}
+
+/// Handler untuk GET /api/streams/:stream_id/bootstrap
+/// Init segment, frame terakhir, sequence dan metadata dalam satu respons, supaya relay dan
+/// subscriber yang terlambat bisa langsung lanjut dengan `/ws/:stream_id?resume_from=<seq>`
+///
+/// Access is checked like `/ws/:stream_id` and holds a playback session while the
+/// response is built.
+pub async fn bootstrap_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    Query(params): Query<BootstrapParams>,
+    State(state): State<AppState>,
+    request_headers: HeaderMap,
+) -> Response {
+    let _viewer = match playback::authorize(&state, &stream_id, &request_headers, params.token.as_deref()).await {
+        Ok(viewer) => viewer,
+        Err(e) => return e.into_response(),
+    };
+    let map = state.streams.lock();
+    let Some(entry) = map.get(&stream_id) else {
+        return BrokerError::StreamNotFound(stream_id).into_response();
+    };
+    Json(json!({
+        "stream": stream_id,
+        "seq": entry.last_seq(),
+        "stream_type": entry.stream_type,
+        "format": entry.format.format(),
+        "codec": entry.codec,
+        "labels": entry.labels,
+        "subscribers": entry.tx.receiver_count(),
+        "ended": entry.lifetime.ended,
+        "init_segment": entry.init_segment.as_ref().map(frame_json),
+        "keyframe": entry.keyframe.as_ref().map(frame_json),
+    }))
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use axum::{body::Body, http::Request, http::StatusCode};
+    use bytes::Bytes;
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn test_bootstrap_returns_init_segment_and_latest_frame() {
+        let state = AppState::new(Config::default());
+        state.with_stream("cam1", |entry| {
+            let _ = entry.publish(Bytes::from_static(b"\0\0\0\x18ftypiso5"));
+            let _ = entry.publish(Bytes::from_static(b"moof-1"));
+            let _ = entry.publish(Bytes::from_static(b"moof-2"));
+        });
+        let app = crate::build_router(state.clone());
+
+        let response = app
+            .clone()
+            .oneshot(Request::get("/api/streams/cam1/bootstrap").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["seq"], 3);
+        assert_eq!(body["init_segment"]["seq"], 1);
+        assert_eq!(body["keyframe"]["seq"], 3);
+        assert_eq!(body["keyframe"]["data"], STANDARD.encode("moof-2"));
+
+        let response = app
+            .clone()
+            .oneshot(Request::get("/api/streams/cam2/bootstrap").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // H.264 terdeteksi: keyframe adalah IDR terakhir, bukan P-frame terakhir
+        let idr = Bytes::from_static(&[0, 0, 0, 1, 0x67, 0x42, 0, 0, 1, 0x65, 0x88]);
+        let p_frame = Bytes::from_static(&[0, 0, 0, 1, 0x41, 0x9A]);
+        state.with_stream("h264", |entry| {
+            for data in [idr, p_frame.clone(), p_frame.clone(), p_frame] {
+                let _ = entry.publish(data);
+            }
+        });
+        let response = app
+            .oneshot(Request::get("/api/streams/h264/bootstrap").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["format"], "h264");
+        assert_eq!(body["keyframe"]["seq"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_requires_playback_token() {
+        use crate::auth::CredentialStore;
+
+        let state = AppState::new(Config {
+            playback_credentials: CredentialStore::parse("guest=cam*", false).unwrap(),
+            ..Config::default()
+        });
+        state.with_stream("cam1", |entry| {
+            let _ = entry.publish(Bytes::from_static(b"\0\0\0\x18ftypiso5"));
+        });
+        state.with_stream("lobby", |_| ());
+        let get = |uri: &str| {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            crate::build_router(state.clone()).oneshot(request)
+        };
+        assert_eq!(get("/api/streams/cam1/bootstrap").await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let other = get("/api/streams/lobby/bootstrap?token=guest").await.unwrap();
+        assert_eq!(other.status(), StatusCode::FORBIDDEN);
+        assert_eq!(get("/api/streams/cam1/bootstrap?token=guest").await.unwrap().status(), StatusCode::OK);
+    }
+}
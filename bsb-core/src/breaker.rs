@@ -0,0 +1,244 @@
+use parking_lot::Mutex;
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+use crate::registry::{StreamEntry, StreamEvent};
+
+/// Jendela waktu penghitungan kegagalan kirim
+const WINDOW: Duration = Duration::from_secs(5);
+
+/// Ambang trip breaker dari `BREAKER_*`
+#[derive(Debug, Clone, Copy)]
+pub struct BreakerConfig {
+    /// Share of the window's subscribers whose sends failed that trips the breaker (0 = disabled)
+    pub failure_percent: u32,
+    /// Failures needed within a window before the share is considered
+    pub min_failures: u64,
+    /// First open period; doubled on every consecutive trip up to `max_backoff`
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_percent: 50,
+            min_failures: 10,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Perubahan state breaker, dilaporkan sekali oleh sampler
+#[derive(Debug, Clone, PartialEq)]
+pub enum BreakerChange {
+    Opened { failures: u64, subscribers: u64, retry: Duration },
+    /// `suppressed` send errors were not logged while the breaker was open
+    Closed { suppressed: u64 },
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    window_start: Instant,
+    /// Peak subscriber count seen by the sampler within the window
+    subscribers: u64,
+    failures: u64,
+    open_until: Option<Instant>,
+    /// Open period of the next trip
+    backoff: Duration,
+    /// A trip that the sampler has not reported yet
+    pending: Option<BreakerChange>,
+    suppressed: u64,
+}
+
+/// Circuit breaker fan-out per stream atas error kirim ke subscriber
+///
+/// Subscriber tasks record failed sends; the sampler reports the subscriber
+/// count. When a large share of the subscribers fail within a window (a
+/// broken network path rather than one bad client), the breaker opens:
+/// subscribers skip live frames until it closes and further errors are only
+/// counted, so the logs get one line per transition instead of one per frame.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: BreakerConfig,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: BreakerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(BreakerState {
+                window_start: Instant::now(),
+                subscribers: 0,
+                failures: 0,
+                open_until: None,
+                backoff: config.backoff,
+                pending: None,
+                suppressed: 0,
+            }),
+        }
+    }
+
+    /// Fan-out sedang ditahan
+    pub fn is_open(&self, now: Instant) -> bool {
+        self.state
+            .lock()
+            .open_until
+            .is_some_and(|until| now < until)
+    }
+
+    /// Jumlah subscriber saat ini (dari sampler)
+    pub fn observe_subscribers(&self, subscribers: usize, now: Instant) {
+        let mut state = self.state.lock();
+        roll_window(&mut state, &self.config, now);
+        state.subscribers = state.subscribers.max(subscribers as u64);
+    }
+
+    /// Catat kirim yang gagal; `true` jika error ini perlu di-log (breaker tertutup)
+    pub fn record_failure(&self, now: Instant) -> bool {
+        let mut state = self.state.lock();
+        if state.open_until.is_some() {
+            state.suppressed += 1;
+            return false;
+        }
+        roll_window(&mut state, &self.config, now);
+        state.failures += 1;
+
+        // Subscriber yang gagal sudah keluar dari hitungan sampler berikutnya
+        let subscribers = state.subscribers.max(state.failures);
+        let config = &self.config;
+        let tripped = config.failure_percent > 0
+            && state.failures >= config.min_failures
+            && state.failures * 100 >= subscribers * config.failure_percent as u64;
+        if !tripped {
+            return true;
+        }
+        let retry = state.backoff;
+        state.open_until = Some(now + retry);
+        state.backoff = (retry * 2).min(config.max_backoff);
+        state.pending = Some(BreakerChange::Opened {
+            failures: state.failures,
+            subscribers,
+            retry,
+        });
+        state.suppressed = 0;
+        false
+    }
+
+    /// Dipanggil sampler tiap detik: trip yang baru terjadi, atau breaker yang menutup lagi
+    pub fn poll(&self, now: Instant) -> Option<BreakerChange> {
+        let mut state = self.state.lock();
+        if let Some(change) = state.pending.take() {
+            return Some(change);
+        }
+        let until = state.open_until?;
+        if now < until {
+            return None;
+        }
+        // Half-open: jendela baru; gagal lagi berarti trip dengan backoff lebih panjang
+        state.open_until = None;
+        state.window_start = now;
+        state.subscribers = 0;
+        state.failures = 0;
+        Some(BreakerChange::Closed {
+            suppressed: std::mem::take(&mut state.suppressed),
+        })
+    }
+}
+
+/// Dipanggil sampler: catat jumlah subscriber, lalu log dan siarkan perubahan state breaker
+pub fn report(stream_id: &str, entry: &mut StreamEntry, subscribers: usize) {
+    let now = Instant::now();
+    entry.breaker.observe_subscribers(subscribers, now);
+    let seq = entry.last_seq();
+    let event = match entry.breaker.poll(now) {
+        None => return,
+        Some(BreakerChange::Opened { failures, subscribers, retry }) => {
+            warn!(
+                "Circuit breaker opened for stream {}: {} of {} subscribers failed, holding fan-out for {:?}",
+                stream_id, failures, subscribers, retry
+            );
+            entry.counters.breaker_trips.fetch_add(1, Ordering::Relaxed);
+            StreamEvent::BreakerOpened {
+                failures,
+                subscribers,
+                retry_secs: retry.as_secs_f64(),
+                seq,
+            }
+        }
+        Some(BreakerChange::Closed { suppressed }) => {
+            info!(
+                "Circuit breaker closed for stream {} ({} send errors suppressed)",
+                stream_id, suppressed
+            );
+            StreamEvent::BreakerClosed {
+                suppressed_errors: suppressed,
+                seq,
+            }
+        }
+    };
+    entry.emit(event);
+}
+
+/// Mulai jendela baru; jendela tanpa trip mengembalikan backoff ke nilai awal
+fn roll_window(state: &mut BreakerState, config: &BreakerConfig, now: Instant) {
+    if now.saturating_duration_since(state.window_start) < WINDOW {
+        return;
+    }
+    if state.open_until.is_none() {
+        state.backoff = config.backoff;
+    }
+    state.window_start = now;
+    state.subscribers = 0;
+    state.failures = 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_trips_backs_off_and_recovers() {
+        let breaker = CircuitBreaker::new(BreakerConfig {
+            failure_percent: 50,
+            min_failures: 3,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(3),
+        });
+        let start = Instant::now();
+        breaker.observe_subscribers(10, start);
+        // 3 dari 10 subscriber gagal: di bawah 50%, semua error di-log
+        assert!((0..3).all(|_| breaker.record_failure(start)));
+        assert!(!breaker.is_open(start));
+        assert_eq!(breaker.poll(start), None);
+
+        // Jendela baru yang didominasi kegagalan membuka breaker
+        let later = start + WINDOW;
+        assert!(breaker.record_failure(later));
+        assert!(breaker.record_failure(later));
+        assert!(!breaker.record_failure(later));
+        assert!(breaker.is_open(later));
+        assert!(!breaker.record_failure(later));
+        assert_eq!(
+            breaker.poll(later),
+            Some(BreakerChange::Opened { failures: 3, subscribers: 3, retry: Duration::from_secs(1) })
+        );
+        assert_eq!(breaker.poll(later), None);
+
+        let reopened = later + Duration::from_secs(1);
+        assert!(!breaker.is_open(reopened));
+        assert_eq!(breaker.poll(reopened), Some(BreakerChange::Closed { suppressed: 1 }));
+
+        // Trip berikutnya menunggu dua kali lebih lama, dibatasi max_backoff
+        for _ in 0..3 {
+            breaker.record_failure(reopened);
+        }
+        let change = breaker.poll(reopened);
+        assert!(matches!(change, Some(BreakerChange::Opened { retry, .. }) if retry == Duration::from_secs(2)));
+    }
+}
@@ -0,0 +1,178 @@
+use axum::Router;
+use bytes::Bytes;
+use std::sync::{atomic::AtomicBool, Arc};
+use tokio::sync::broadcast;
+
+use crate::{
+    config::Config, durable, error::BrokerError,
+    hooks::{Authenticator, FrameInterceptor, Hooks, StateStore},
+    leaks, merge, outbound, registry::Frame, stats, storage, supervisor, systemd, AppState, IngestParams,
+};
+
+/// Menyiapkan [`Broker`] untuk dipakai in-process
+///
+/// Starts from [`Config::default`]; use [`BrokerBuilder::config`] with
+/// [`Config::from_env`] or a hand-built config to change it.
+pub struct BrokerBuilder {
+    config: Config,
+    paused: Arc<AtomicBool>,
+    hooks: Hooks,
+}
+
+impl BrokerBuilder {
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Flag shared with a service manager: while set, ingest answers `503`
+    pub fn paused(mut self, paused: Arc<AtomicBool>) -> Self {
+        self.paused = paused;
+        self
+    }
+
+    /// Tambah authenticator; dijalankan sesuai urutan pendaftaran, sebelum credential bawaan
+    pub fn authenticator(mut self, authenticator: impl Authenticator + 'static) -> Self {
+        self.hooks.authenticators.push(Arc::new(authenticator));
+        self
+    }
+
+    /// Tambah interceptor untuk setiap frame yang masuk
+    pub fn frame_interceptor(mut self, interceptor: impl FrameInterceptor + 'static) -> Self {
+        self.hooks.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Simpan offset durable dan user lokal di tempat lain selain file
+    pub fn state_store(mut self, store: impl StateStore + 'static) -> Self {
+        self.hooks.store = Arc::new(store);
+        self
+    }
+
+    /// Pulihkan state tersimpan dan jalankan task latar belakang broker
+    ///
+    /// Durable offsets and `USERS_FILE` users are loaded before the broker is
+    /// returned, so no subscriber can connect ahead of them. Must be called
+    /// inside a Tokio runtime; the background tasks live on it.
+    pub async fn build(self) -> Result<Broker, Box<dyn std::error::Error>> {
+        outbound::configure(self.config.outbound.clone());
+        let state = AppState {
+            paused: self.paused,
+            ..AppState::with_hooks(self.config, self.hooks)
+        };
+
+        durable::restore(&state)?;
+        state.users.load()?;
+        let flusher_state = state.clone();
+        supervisor::supervise("durable offsets".to_string(), move || durable::run_flusher(flusher_state.clone()));
+        let sampler_state = state.clone();
+        supervisor::supervise("stats sampler".to_string(), move || stats::run_sampler(sampler_state.clone()));
+        let storage_state = state.clone();
+        supervisor::supervise("storage monitor".to_string(), move || storage::run_monitor(storage_state.clone()));
+        let leaks_state = state.clone();
+        supervisor::supervise("leak detector".to_string(), move || leaks::run_sampler(leaks_state.clone()));
+        merge::spawn_all(&state);
+        // Segmen yang terpotong oleh crash dipulihkan sebelum recorder membuka segmen baru
+        #[cfg(feature = "recording")]
+        {
+            let recordings_dir = state.config.recordings_dir.clone();
+            crate::runtime::spawn_disk_blocking(move || crate::recorder::recover_all(&recordings_dir)).await?;
+        }
+        // Broker passive baru menjalankan pull source setelah takeover
+        #[cfg(feature = "cluster")]
+        {
+            crate::ha::replication::spawn(&state);
+            crate::cluster::gossip::spawn_gossip(&state);
+        }
+        #[cfg(not(feature = "cluster"))]
+        crate::sources::spawn_all(&state);
+        crate::pipe::spawn_all(&state);
+        crate::shm::spawn(&state);
+        systemd::spawn_watchdog(&state);
+
+        Ok(Broker { state })
+    }
+}
+
+/// Broker yang berjalan di dalam proses aplikasi lain
+///
+/// Cloning is cheap and every clone shares the same streams.
+#[derive(Clone)]
+pub struct Broker {
+    pub(crate) state: AppState,
+}
+
+impl Broker {
+    pub fn builder() -> BrokerBuilder {
+        BrokerBuilder {
+            config: Config::default(),
+            paused: Arc::new(AtomicBool::new(false)),
+            hooks: Hooks::default(),
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.state.config
+    }
+
+    /// Semua endpoint broker, siap di-`merge`/`nest` ke router aplikasi host
+    pub fn router(&self) -> Router {
+        crate::build_router(self.state.clone())
+    }
+
+    /// Kirim frame ke `stream_id` seperti `POST /ingest/:stream_id` tanpa query parameter
+    pub fn publish(&self, stream_id: &str, data: impl Into<Bytes>) -> Result<(), BrokerError> {
+        crate::ingest_frame(&self.state, stream_id, &IngestParams::default(), data.into(), None).map(|_| ())
+    }
+
+    /// Terima frame live `stream_id`; stream dibuat bila belum ada
+    ///
+    /// A receiver that falls more than the stream's channel capacity behind
+    /// gets `RecvError::Lagged`, like a slow WebSocket subscriber.
+    pub fn subscribe(&self, stream_id: &str) -> broadcast::Receiver<Frame> {
+        self.state.with_stream(stream_id, |entry| entry.tx.subscribe())
+    }
+
+    /// Simpan offset durable terakhir; dipanggil sebelum aplikasi host berhenti
+    pub fn shutdown(&self) {
+        durable::flush(&self.state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Request, http::StatusCode, routing::get};
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn test_embedded_broker_mounts_into_host_router() {
+        let broker = Broker::builder()
+            .config(Config {
+                channel_capacity: 8,
+                ..Config::default()
+            })
+            .build()
+            .await
+            .unwrap();
+        let app = Router::new()
+            .route("/status", get(|| async { "host app" }))
+            .nest("/broker", broker.router());
+
+        // Producer dan consumer in-process tanpa network hop
+        let mut frames = broker.subscribe("arm/camera");
+        broker.publish("arm/camera", Bytes::from_static(b"frame-1")).unwrap();
+        let frame = frames.recv().await.unwrap();
+        assert_eq!((frame.seq, &frame.data[..]), (1, &b"frame-1"[..]));
+
+        // Frame HTTP lewat router host juga sampai ke consumer in-process
+        let request = Request::post("/broker/ingest/arm-camera").body(Body::from("frame-2")).unwrap();
+        let mut http_frames = broker.subscribe("arm-camera");
+        assert!(app.clone().oneshot(request).await.unwrap().status().is_success());
+        assert_eq!(&http_frames.recv().await.unwrap().data[..], b"frame-2");
+
+        let status = app.oneshot(Request::get("/status").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(status.status(), StatusCode::OK);
+        assert!(broker.state.streams.lock().contains_key("arm/camera"));
+    }
+}
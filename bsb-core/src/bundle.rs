@@ -0,0 +1,373 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+};
+use futures_util::{future::select_all, SinkExt, StreamExt};
+use serde::Deserialize;
+use std::{sync::Arc, time::Instant};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{error, info, warn};
+
+use crate::{
+    error::BrokerError,
+    playback::{self, Session},
+    registry::Frame,
+    stats::StreamCounters,
+    ws::{self, Subprotocol},
+    AppState,
+};
+
+/// Stream dalam satu bundle; index stream ditulis sebagai satu byte
+pub const MAX_BUNDLE_STREAMS: usize = 16;
+/// Jendela sinkronisasi terlebar (`?window_ms=`)
+pub const MAX_WINDOW_MS: u64 = 1000;
+
+/// Jam yang dipakai untuk menyelaraskan frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlignClock {
+    /// When the broker received the frame
+    #[default]
+    Broker,
+    /// Capture time sent by the producer; frames without one fall back to `broker`
+    Producer,
+}
+
+/// Query parameter untuk GET /bundle
+#[derive(Debug, Deserialize)]
+pub struct BundleParams {
+    /// Comma-separated stream IDs; their order gives each stream's index in a bundle
+    streams: String,
+    /// How far apart frames of one bundle may be, and how long a bundle waits for its members
+    #[serde(default = "default_window_ms")]
+    window_ms: u64,
+    #[serde(default)]
+    align: AlignClock,
+    /// Playback token for browsers that cannot set an `Authorization` header
+    token: Option<String>,
+}
+
+fn default_window_ms() -> u64 {
+    40
+}
+
+/// Satu kelompok frame yang waktunya berdekatan, paling banyak satu per stream
+#[derive(Debug)]
+pub struct Bundle {
+    /// Time of the frame that opened the bundle (unix ms, on the aligned clock)
+    pub anchor_ms: u64,
+    /// `(stream index, frame, time)` in stream order; streams that sent nothing in time are absent
+    pub members: Vec<(usize, Frame, u64)>,
+}
+
+impl Bundle {
+    /// `[u64 anchor_ms][u8 count]` lalu per frame `[u8 index][u64 seq][u64 at_ms][u32 len][payload]`
+    pub fn encode(&self) -> Vec<u8> {
+        let payload: usize = self.members.iter().map(|(_, frame, _)| 21 + frame.data.len()).sum();
+        let mut buf = Vec::with_capacity(9 + payload);
+        buf.extend_from_slice(&self.anchor_ms.to_be_bytes());
+        buf.push(self.members.len() as u8);
+        for (index, frame, at_ms) in &self.members {
+            buf.push(*index as u8);
+            buf.extend_from_slice(&frame.seq.to_be_bytes());
+            buf.extend_from_slice(&at_ms.to_be_bytes());
+            buf.extend_from_slice(&(frame.data.len() as u32).to_be_bytes());
+            buf.extend_from_slice(&frame.data);
+        }
+        buf
+    }
+}
+
+/// Kelompokkan frame dari N stream menjadi bundle selaras waktu
+///
+/// The first frame after a bundle is sent opens the next one. Frames of the
+/// other streams join it while they are within `window_ms` of that frame.
+/// The bundle is sent as soon as every stream is in it, when a stream sends
+/// its next frame before the others caught up, when a frame falls outside
+/// the window, or once `window_ms` has passed: a stalled camera delays its
+/// peers by at most one window.
+#[derive(Debug)]
+pub struct Aligner {
+    streams: usize,
+    window_ms: u64,
+    open: Option<Bundle>,
+    deadline: Option<tokio::time::Instant>,
+}
+
+impl Aligner {
+    pub fn new(streams: usize, window_ms: u64) -> Self {
+        Self {
+            streams,
+            window_ms,
+            open: None,
+            deadline: None,
+        }
+    }
+
+    /// Masukkan frame stream `index`; bundle yang selesai karenanya dikembalikan
+    pub fn push(&mut self, index: usize, frame: Frame, at_ms: u64, now: tokio::time::Instant) -> Vec<Bundle> {
+        let mut ready = Vec::new();
+        if let Some(open) = &self.open {
+            let taken = open.members.iter().any(|(member, _, _)| *member == index);
+            if taken || at_ms.abs_diff(open.anchor_ms) > self.window_ms {
+                ready.extend(self.take());
+            }
+        }
+        let open = self.open.get_or_insert_with(|| Bundle {
+            anchor_ms: at_ms,
+            members: Vec::new(),
+        });
+        let position = open.members.partition_point(|(member, _, _)| *member < index);
+        open.members.insert(position, (index, frame, at_ms));
+        if open.members.len() == self.streams {
+            ready.extend(self.take());
+        } else {
+            self.deadline.get_or_insert(now + std::time::Duration::from_millis(self.window_ms));
+        }
+        ready
+    }
+
+    /// Batas waktu bundle yang sedang terbuka
+    pub fn deadline(&self) -> Option<tokio::time::Instant> {
+        self.deadline
+    }
+
+    /// Ambil bundle yang terbuka (dipanggil saat deadline lewat)
+    pub fn take(&mut self) -> Option<Bundle> {
+        self.deadline = None;
+        self.open.take()
+    }
+}
+
+/// Waktu frame pada jam yang dipilih
+fn frame_time(frame: &Frame, align: AlignClock, now: Instant, now_ms: u64) -> u64 {
+    let received_ms = now_ms.saturating_sub(now.saturating_duration_since(frame.received_at).as_millis() as u64);
+    match align {
+        AlignClock::Producer => frame.producer_ms.unwrap_or(received_ms),
+        AlignClock::Broker => received_ms,
+    }
+}
+
+/// Handler untuk GET /bundle?streams=left,right
+/// Frame dari beberapa stream dikirim berkelompok sesuai waktunya (stereo, multi-angle)
+pub async fn bundle_handler(
+    ws: WebSocketUpgrade,
+    Query(params): Query<BundleParams>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    let protocol = match Subprotocol::negotiate(&headers, &[Subprotocol::Bundle], Subprotocol::Bundle) {
+        Ok(protocol) => protocol,
+        Err(e) => return e.into_response(),
+    };
+    let streams: Vec<String> = params
+        .streams
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect();
+    let distinct = streams.iter().enumerate().all(|(i, id)| !streams[..i].contains(id));
+    if !(2..=MAX_BUNDLE_STREAMS).contains(&streams.len()) || !distinct {
+        let error = format!("?streams= needs 2 to {} distinct stream IDs", MAX_BUNDLE_STREAMS);
+        return BrokerError::InvalidRequest(error).into_response();
+    }
+    if !(1..=MAX_WINDOW_MS).contains(&params.window_ms) {
+        let error = format!("window_ms must be between 1 and {}", MAX_WINDOW_MS);
+        return BrokerError::InvalidRequest(error).into_response();
+    }
+    let mut sessions = Vec::new();
+    for stream_id in &streams {
+        if state.config.strict_streams && !crate::stream_exists(&state, stream_id) {
+            return BrokerError::StreamNotFound(stream_id.clone()).into_response();
+        }
+        match playback::authorize(&state, stream_id, &headers, params.token.as_deref()).await {
+            Ok(viewer) => sessions.extend(viewer.session),
+            Err(e) => return e.into_response(),
+        }
+    }
+    info!("Bundle subscription for {:?} (window {} ms)", streams, params.window_ms);
+    let (window_ms, align) = (params.window_ms, params.align);
+    ws::accept_protocol(ws, protocol)
+        .on_upgrade(move |socket| bundle_connection(socket, streams, window_ms, align, sessions, state))
+}
+
+async fn bundle_connection(
+    socket: WebSocket,
+    streams: Vec<String>,
+    window_ms: u64,
+    align: AlignClock,
+    mut sessions: Vec<Session>,
+    state: AppState,
+) {
+    let (mut sender, mut receiver) = socket.split();
+
+    // Setiap stream diteruskan oleh task-nya sendiri ke satu antrian, seperti `/mux`
+    let (tx, mut rx) = mpsc::channel::<(usize, Frame)>(state.config.channel_capacity);
+    let forwarders: Vec<_> = streams
+        .iter()
+        .enumerate()
+        .map(|(index, stream_id)| {
+            let (frames, counters) =
+                state.with_stream(stream_id, |entry| (entry.tx.subscribe(), entry.counters.clone()));
+            tokio::spawn(forward_stream(index, stream_id.clone(), frames, counters, tx.clone()))
+        })
+        .collect();
+    drop(tx);
+
+    let mut aligner = Aligner::new(streams.len(), window_ms);
+    loop {
+        let ready = tokio::select! {
+            Some((index, frame)) = rx.recv() => {
+                let at_ms = frame_time(&frame, align, Instant::now(), state.clock.now_ms());
+                aligner.push(index, frame, at_ms, tokio::time::Instant::now())
+            }
+            _ = tokio::time::sleep_until(aligner.deadline().unwrap_or_else(tokio::time::Instant::now)),
+                if aligner.deadline().is_some() => {
+                aligner.take().into_iter().collect()
+            }
+            _ = async { select_all(sessions.iter_mut().map(|session| Box::pin(session.kicked()))).await }, if !sessions.is_empty() => {
+                warn!("Closing bundle subscriber for {:?}: playback session limit exceeded", streams);
+                let close = BrokerError::SessionLimit("session limit exceeded".to_string()).close_frame();
+                let _ = sender.send(Message::Close(Some(close))).await;
+                break;
+            }
+            msg = receiver.next() => match msg {
+                Some(Ok(Message::Ping(data))) => {
+                    if let Err(e) = sender.send(Message::Pong(data)).await {
+                        error!("Failed to send pong: {}", e);
+                        break;
+                    }
+                    continue;
+                }
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => {
+                    error!("WebSocket error: {}", e);
+                    break;
+                }
+            },
+        };
+        let mut failed = false;
+        for bundle in ready {
+            if let Err(e) = sender.send(Message::Binary(bundle.encode())).await {
+                error!("Failed to send bundle to client: {}", e);
+                failed = true;
+                break;
+            }
+        }
+        if failed {
+            break;
+        }
+    }
+
+    for task in forwarders {
+        task.abort();
+    }
+    info!("Bundle subscriber for {:?} disconnected", streams);
+}
+
+/// Teruskan frame dari satu stream ke antrian bundle
+async fn forward_stream(
+    index: usize,
+    stream_id: String,
+    mut frames: broadcast::Receiver<Frame>,
+    counters: Arc<StreamCounters>,
+    tx: mpsc::Sender<(usize, Frame)>,
+) {
+    loop {
+        match frames.recv().await {
+            Ok(frame) => {
+                if tx.send((index, frame)).await.is_err() {
+                    break;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Bundle subscriber lagged, skipped {} frames for stream: {}", skipped, stream_id);
+                counters.record_drops(skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    fn frame(seq: u64) -> Frame {
+        Frame {
+            seq,
+            data: Bytes::from(format!("f{}", seq)),
+            received_at: Instant::now(),
+            producer_ms: None,
+        }
+    }
+
+    fn seqs(bundle: &Bundle) -> Vec<(usize, u64)> {
+        bundle.members.iter().map(|(index, frame, _)| (*index, frame.seq)).collect()
+    }
+
+    #[test]
+    fn test_aligner_groups_frames_within_window() {
+        let now = tokio::time::Instant::now();
+        let mut aligner = Aligner::new(3, 40);
+        // Urutan kedatangan bebas; bundle berurutan per index stream
+        assert!(aligner.push(2, frame(10), 1_000, now).is_empty());
+        assert!(aligner.push(0, frame(20), 1_015, now).is_empty());
+        assert_eq!(aligner.deadline(), Some(now + std::time::Duration::from_millis(40)));
+        let full = aligner.push(1, frame(30), 990, now);
+        assert_eq!(full.len(), 1);
+        assert_eq!((full[0].anchor_ms, seqs(&full[0])), (1_000, vec![(0, 20), (1, 30), (2, 10)]));
+        assert_eq!(aligner.deadline(), None);
+
+        // Stream 0 mengirim frame berikutnya sebelum yang lain: bundle parsial dikirim
+        aligner.push(0, frame(21), 1_100, now);
+        let partial = aligner.push(0, frame(22), 1_133, now);
+        assert_eq!(seqs(&partial[0]), vec![(0, 21)]);
+        // Di luar jendela: bundle lama ditutup, frame ini membuka yang baru
+        let late = aligner.push(1, frame(31), 1_200, now);
+        assert_eq!(seqs(&late[0]), vec![(0, 22)]);
+        assert_eq!(seqs(&aligner.take().unwrap()), vec![(1, 31)]);
+
+        let encoded = full[0].encode();
+        assert_eq!(&encoded[0..8], &1_000u64.to_be_bytes());
+        assert_eq!((encoded[8], encoded[9]), (3, 0));
+        assert_eq!(&encoded[10..18], &20u64.to_be_bytes());
+        assert_eq!(&encoded[26..30], &3u32.to_be_bytes());
+        assert_eq!(&encoded[30..33], b"f20");
+    }
+
+    #[tokio::test]
+    async fn test_bundle_endpoint_delivers_stereo_pairs() {
+        use crate::{config::Config, testing::TestBroker};
+
+        let broker = TestBroker::start(Config::default()).await;
+        let (mut bundles, protocol) = broker
+            .connect_with_protocols("/bundle?streams=left,right&window_ms=200", "bsb.bundle.v1")
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(protocol.as_deref(), Some("bsb.bundle.v1"));
+        assert_eq!(broker.post_frame("left", "L1").await, axum::http::StatusCode::OK);
+        assert_eq!(broker.post_frame("right", "R1").await, axum::http::StatusCode::OK);
+
+        let pair = bundles.expect_binary().await;
+        assert_eq!(pair[8], 2);
+        assert_eq!((pair[9], &pair[30..32]), (0, &b"L1"[..]));
+        assert_eq!((pair[32], &pair[53..55]), (1, &b"R1"[..]));
+
+        // Kamera kanan diam: kiri tetap dikirim sendiri setelah jendela lewat
+        broker.post_frame("left", "L2").await;
+        let single = bundles.expect_binary().await;
+        assert_eq!((single[8], single[9], &single[30..]), (1, 0, &b"L2"[..]));
+
+        let rejected = broker.connect_with_headers("/bundle?streams=left", &[]).await;
+        assert_eq!(rejected.err(), Some(axum::http::StatusCode::BAD_REQUEST));
+    }
+}
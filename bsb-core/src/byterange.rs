@@ -0,0 +1,203 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::{io::SeekFrom, time::UNIX_EPOCH};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+use tokio_util::io::ReaderStream;
+
+use crate::error::BrokerError;
+
+/// Rentang byte inklusif `[start, end]` dari header `Range`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Hasil membaca header `Range` terhadap file sepanjang `length`
+#[derive(Debug, PartialEq)]
+pub enum RangeRequest {
+    /// Tanpa `Range` (atau bentuk yang tidak dilayani): kirim seluruh file
+    Full,
+    Partial(ByteRange),
+    /// Tidak ada byte yang bisa dikirim: `416`
+    Unsatisfiable,
+}
+
+/// Parse `Range: bytes=...` untuk file sepanjang `length`
+///
+/// Only a single range is served; multi-range requests and other units get
+/// the whole file, which RFC 9110 allows and every player handles.
+pub fn parse(value: &str, length: u64) -> RangeRequest {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return RangeRequest::Full;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Full;
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return RangeRequest::Full;
+    };
+    let range = match (first.trim(), last.trim()) {
+        // `bytes=-N`: N byte terakhir
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return RangeRequest::Unsatisfiable,
+            Ok(suffix) if length > 0 => ByteRange {
+                start: length.saturating_sub(suffix),
+                end: length - 1,
+            },
+            Ok(_) => return RangeRequest::Unsatisfiable,
+            Err(_) => return RangeRequest::Full,
+        },
+        (first, last) => {
+            let Ok(start) = first.parse::<u64>() else {
+                return RangeRequest::Full;
+            };
+            let end = match last {
+                "" => u64::MAX,
+                last => match last.parse::<u64>() {
+                    Ok(end) if end >= start => end,
+                    _ => return RangeRequest::Full,
+                },
+            };
+            if start >= length {
+                return RangeRequest::Unsatisfiable;
+            }
+            ByteRange {
+                start,
+                end: end.min(length - 1),
+            }
+        }
+    };
+    RangeRequest::Partial(range)
+}
+
+/// Kirim file hasil rekaman dengan dukungan `Range`, `If-Range` dan ETag
+///
+/// The ETag is derived from the file's length and modification time, so a
+/// resumed download whose file changed in between (`If-Range` mismatch)
+/// starts over instead of splicing two versions together.
+pub async fn serve_file(
+    mut file: File,
+    content_type: &str,
+    filename: &str,
+    request_headers: &HeaderMap,
+) -> Result<Response, BrokerError> {
+    let metadata = file.metadata().await?;
+    let length = metadata.len();
+    let modified_ms = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_millis())
+        .unwrap_or(0);
+    let etag = format!("\"{:x}-{:x}\"", length, modified_ms);
+
+    // `If-Range` tanggal tidak dibandingkan: anggap berubah dan kirim ulang seluruhnya
+    let if_range_matches = request_headers
+        .get(header::IF_RANGE)
+        .is_none_or(|value| value.to_str().is_ok_and(|value| value.trim() == etag));
+    let range = match request_headers.get(header::RANGE).and_then(|value| value.to_str().ok()) {
+        Some(value) if if_range_matches => parse(value, length),
+        _ => RangeRequest::Full,
+    };
+
+    let mut headers = vec![
+        (header::CONTENT_TYPE, content_type.to_string()),
+        (header::ACCEPT_RANGES, "bytes".to_string()),
+        (header::ETAG, etag),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        ),
+    ];
+    let response = match range {
+        RangeRequest::Full => {
+            headers.push((header::CONTENT_LENGTH, length.to_string()));
+            (StatusCode::OK, headers_map(headers), Body::from_stream(ReaderStream::new(file))).into_response()
+        }
+        RangeRequest::Partial(ByteRange { start, end }) => {
+            file.seek(SeekFrom::Start(start)).await?;
+            let count = end - start + 1;
+            headers.push((header::CONTENT_LENGTH, count.to_string()));
+            headers.push((header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, length)));
+            let body = Body::from_stream(ReaderStream::new(file.take(count)));
+            (StatusCode::PARTIAL_CONTENT, headers_map(headers), body).into_response()
+        }
+        RangeRequest::Unsatisfiable => (
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [(header::CONTENT_RANGE, format!("bytes */{}", length))],
+        )
+            .into_response(),
+    };
+    Ok(response)
+}
+
+fn headers_map(headers: Vec<(header::HeaderName, String)>) -> HeaderMap {
+    headers
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value.parse().ok()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+
+    #[tokio::test]
+    async fn test_range_requests_resume_and_seek() {
+        assert_eq!(parse("bytes=2-5", 10), RangeRequest::Partial(ByteRange { start: 2, end: 5 }));
+        assert_eq!(parse("bytes=7-", 10), RangeRequest::Partial(ByteRange { start: 7, end: 9 }));
+        assert_eq!(parse("bytes=-3", 10), RangeRequest::Partial(ByteRange { start: 7, end: 9 }));
+        assert_eq!(parse("bytes=-30", 10), RangeRequest::Partial(ByteRange { start: 0, end: 9 }));
+        assert_eq!(parse("bytes=4-100", 10), RangeRequest::Partial(ByteRange { start: 4, end: 9 }));
+        assert_eq!(parse("bytes=10-", 10), RangeRequest::Unsatisfiable);
+        assert_eq!(parse("bytes=0-1,4-5", 10), RangeRequest::Full);
+        assert_eq!(parse("bytes=5-2", 10), RangeRequest::Full);
+        assert_eq!(parse("items=0-1", 10), RangeRequest::Full);
+
+        let path = std::env::temp_dir().join(format!("bsb-range-test-{}", std::process::id()));
+        std::fs::write(&path, b"0123456789").unwrap();
+        let serve = |range: Option<&str>, if_range: Option<&str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(range) = range {
+                headers.insert(header::RANGE, range.parse().unwrap());
+            }
+            if let Some(if_range) = if_range {
+                headers.insert(header::IF_RANGE, if_range.parse().unwrap());
+            }
+            let path = path.clone();
+            async move {
+                let file = File::open(&path).await.unwrap();
+                serve_file(file, "application/octet-stream", "seg.bsbrec", &headers).await.unwrap()
+            }
+        };
+
+        let response = serve(None, None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ACCEPT_RANGES], "bytes");
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+
+        let response = serve(Some("bytes=3-5"), Some(&etag)).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 3-5/10");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "3");
+        assert_eq!(&to_bytes(response.into_body(), usize::MAX).await.unwrap()[..], b"345");
+
+        // File berubah sejak download terputus: kirim ulang seluruhnya
+        let response = serve(Some("bytes=3-"), Some("\"stale\"")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap().len(), 10);
+
+        let response = serve(Some("bytes=20-"), None).await;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */10");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
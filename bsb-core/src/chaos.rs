@@ -0,0 +1,274 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+use crate::{error::BrokerError, stats::unix_now_ms, AppState};
+
+/// Fault injection paling lama aktif sebelum dihapus otomatis
+pub const MAX_DURATION_SECS: u64 = 24 * 3600;
+
+/// Body untuk PUT /api/streams/:id/chaos
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChaosBody {
+    /// Extra delay of every live frame, keeping the frame rate
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Random extra delay on top of `latency_ms`, 0 to this many ms per frame
+    #[serde(default)]
+    pub jitter_ms: u64,
+    /// Share of live frames that are not delivered (a gap in sequence mode)
+    #[serde(default)]
+    pub drop_percent: f64,
+    /// Close every subscriber this long after it connected (code 1012)
+    pub disconnect_after_secs: Option<u64>,
+    /// Send no faster than this, as a subscriber on a slow link would read
+    pub slow_kbps: Option<u64>,
+    /// The faults are removed on their own after this long
+    #[serde(default = "default_duration_secs")]
+    pub duration_secs: u64,
+}
+
+fn default_duration_secs() -> u64 {
+    600
+}
+
+impl ChaosBody {
+    fn validate(&self) -> Result<(), String> {
+        if !(0.0..=100.0).contains(&self.drop_percent) {
+            return Err("drop_percent must be between 0 and 100".to_string());
+        }
+        if self.slow_kbps == Some(0) {
+            return Err("slow_kbps must be greater than 0".to_string());
+        }
+        if self.duration_secs == 0 || self.duration_secs > MAX_DURATION_SECS {
+            return Err(format!("duration_secs must be between 1 and {}", MAX_DURATION_SECS));
+        }
+        Ok(())
+    }
+}
+
+/// Fault yang sedang disuntikkan ke subscriber satu stream
+#[derive(Debug)]
+pub struct ChaosFaults {
+    pub body: ChaosBody,
+    expires_at: Instant,
+    expires_ms: u64,
+    dropped: AtomicU64,
+    disconnected: AtomicU64,
+}
+
+/// Yang harus dilakukan subscriber dengan frame live berikutnya
+#[derive(Debug, PartialEq)]
+pub enum ChaosAction {
+    Deliver,
+    /// Send after sleeping this long
+    Delay(Duration),
+    Drop,
+    Disconnect,
+}
+
+/// Fault injection per stream (`/api/streams/:id/chaos`), hanya dengan `CHAOS_ENABLED=true`
+#[derive(Debug, Default)]
+pub struct ChaosRegistry {
+    active: Mutex<HashMap<String, Arc<ChaosFaults>>>,
+}
+
+impl ChaosRegistry {
+    fn get(&self, stream_id: &str) -> Option<Arc<ChaosFaults>> {
+        let mut active = self.active.lock();
+        match active.get(stream_id) {
+            Some(faults) if faults.expires_at <= Instant::now() => {
+                info!("Chaos faults on stream {} expired", stream_id);
+                active.remove(stream_id);
+                None
+            }
+            faults => faults.cloned(),
+        }
+    }
+}
+
+/// Keadaan fault injection satu subscriber
+#[derive(Debug)]
+pub struct ChaosSubscriber {
+    connected_at: Instant,
+    /// xorshift64*; bukan untuk kriptografi, cukup untuk drop dan jitter
+    rng: u64,
+}
+
+impl ChaosSubscriber {
+    pub fn new(connection_id: u64) -> Self {
+        Self {
+            connected_at: Instant::now(),
+            rng: (unix_now_ms() ^ connection_id.rotate_left(32)) | 1,
+        }
+    }
+
+    /// Angka acak di `[0, 1)`
+    fn next(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        (self.rng.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Fault untuk satu frame live yang diterima broker pada `received_at`
+    pub fn on_frame(&mut self, state: &AppState, stream_id: &str, received_at: Instant, bytes: usize) -> ChaosAction {
+        let Some(faults) = state.chaos.get(stream_id) else {
+            return ChaosAction::Deliver;
+        };
+        let now = Instant::now();
+        let body = &faults.body;
+        if body
+            .disconnect_after_secs
+            .is_some_and(|secs| now.duration_since(self.connected_at) >= Duration::from_secs(secs))
+        {
+            faults.disconnected.fetch_add(1, Ordering::Relaxed);
+            return ChaosAction::Disconnect;
+        }
+        if body.drop_percent > 0.0 && self.next() * 100.0 < body.drop_percent {
+            faults.dropped.fetch_add(1, Ordering::Relaxed);
+            return ChaosAction::Drop;
+        }
+        // Latensi dihitung dari waktu terima, jadi frame rate tetap; link lambat menambah waktu kirim
+        let jitter = (self.next() * body.jitter_ms as f64) as u64;
+        let due = received_at + Duration::from_millis(body.latency_ms + jitter);
+        let transfer = body
+            .slow_kbps
+            .map_or(Duration::ZERO, |kbps| Duration::from_micros(bytes as u64 * 8 * 1000 / kbps));
+        let delay = due.saturating_duration_since(now) + transfer;
+        if delay.is_zero() {
+            ChaosAction::Deliver
+        } else {
+            ChaosAction::Delay(delay)
+        }
+    }
+}
+
+fn to_json(stream_id: &str, faults: &ChaosFaults) -> serde_json::Value {
+    json!({
+        "stream": stream_id,
+        "faults": faults.body,
+        "expires_ms": faults.expires_ms,
+        "dropped_frames": faults.dropped.load(Ordering::Relaxed),
+        "disconnects": faults.disconnected.load(Ordering::Relaxed),
+    })
+}
+
+fn require_enabled(state: &AppState) -> Result<(), BrokerError> {
+    if state.config.chaos_enabled {
+        Ok(())
+    } else {
+        Err(BrokerError::Forbidden("fault injection is disabled (CHAOS_ENABLED)".to_string()))
+    }
+}
+
+/// Handler untuk GET /api/streams/:id/chaos
+pub async fn get_chaos_handler(AxumPath(stream_id): AxumPath<String>, State(state): State<AppState>) -> Response {
+    if let Err(e) = require_enabled(&state) {
+        return e.into_response();
+    }
+    match state.chaos.get(&stream_id) {
+        Some(faults) => Json(to_json(&stream_id, &faults)).into_response(),
+        None => BrokerError::not_found("chaos faults", stream_id).into_response(),
+    }
+}
+
+/// Handler untuk PUT /api/streams/:id/chaos
+/// Inject latency, drops, disconnects or a slow link into the stream's live subscribers
+pub async fn put_chaos_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+    Json(body): Json<ChaosBody>,
+) -> Response {
+    if let Err(e) = require_enabled(&state) {
+        return e.into_response();
+    }
+    if let Err(message) = body.validate() {
+        return BrokerError::InvalidRequest(message).into_response();
+    }
+    warn!("Injecting faults into stream {} for {}s: {:?}", stream_id, body.duration_secs, body);
+    let duration = Duration::from_secs(body.duration_secs);
+    let faults = Arc::new(ChaosFaults {
+        expires_at: Instant::now() + duration,
+        expires_ms: unix_now_ms() + duration.as_millis() as u64,
+        body,
+        dropped: AtomicU64::new(0),
+        disconnected: AtomicU64::new(0),
+    });
+    let response = to_json(&stream_id, &faults);
+    state.chaos.active.lock().insert(stream_id, faults);
+    Json(response).into_response()
+}
+
+/// Handler untuk DELETE /api/streams/:id/chaos
+pub async fn delete_chaos_handler(AxumPath(stream_id): AxumPath<String>, State(state): State<AppState>) -> Response {
+    if let Err(e) = require_enabled(&state) {
+        return e.into_response();
+    }
+    if state.chaos.active.lock().remove(&stream_id).is_some() {
+        info!("Chaos faults removed from stream {}", stream_id);
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        BrokerError::not_found("chaos faults", stream_id).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, testing::TestBroker};
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+
+    async fn inject(broker: &TestBroker, faults: serde_json::Value) -> Response {
+        let body = serde_json::from_value(faults).unwrap();
+        put_chaos_handler(AxumPath("cam1".to_string()), State(broker.state.clone()), Json(body)).await
+    }
+
+    #[tokio::test]
+    async fn test_injected_faults_reach_live_subscribers() {
+        let disabled = TestBroker::start(Config::default()).await;
+        assert_eq!(inject(&disabled, json!({ "drop_percent": 100.0 })).await.status(), StatusCode::FORBIDDEN);
+
+        let broker = TestBroker::start(Config {
+            chaos_enabled: true,
+            ..Config::default()
+        })
+        .await;
+        broker.state.with_stream("cam1", |_| ());
+        let mut viewer = broker.subscriber("cam1?seq=true").await;
+        assert_eq!(viewer.expect_json().await["type"], "sync");
+
+        assert_eq!(inject(&broker, json!({ "drop_percent": 100.0 })).await.status(), StatusCode::OK);
+        broker.post_frame("cam1", "frame-1").await;
+        // Frame yang dibuang muncul sebagai gap sebelum frame berikutnya yang tertunda
+        inject(&broker, json!({ "latency_ms": 150 })).await;
+        broker.post_frame("cam1", "frame-2").await;
+        let sent = Instant::now();
+        let gap = viewer.expect_json().await;
+        assert_eq!((gap["type"].as_str(), gap["from"].as_u64()), (Some("gap"), Some(1)));
+        assert_eq!(viewer.expect_binary().await, b"frame-2");
+        assert!(sent.elapsed() >= Duration::from_millis(100));
+
+        inject(&broker, json!({ "disconnect_after_secs": 0 })).await;
+        broker.post_frame("cam1", "frame-3").await;
+        let close = viewer.expect_close(Duration::from_secs(2)).await.unwrap();
+        assert_eq!(close.code, CloseCode::Restart);
+        let faults = broker.state.chaos.get("cam1").unwrap();
+        assert_eq!(faults.disconnected.load(Ordering::Relaxed), 1);
+    }
+}
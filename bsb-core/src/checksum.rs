@@ -0,0 +1,41 @@
+use serde::Deserialize;
+
+/// Checksum yang bisa ditambahkan di akhir frame (`?checksum=crc32c`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumKind {
+    /// CRC-32C (Castagnoli), 4 bytes big-endian after the payload
+    Crc32c,
+}
+
+pub use bsb_proto::checksum::crc32c;
+
+impl ChecksumKind {
+    /// Payload diikuti trailer checksum
+    pub fn append(self, payload: &[u8]) -> Vec<u8> {
+        match self {
+            ChecksumKind::Crc32c => bsb_proto::checksum::append_crc32c(payload),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bsb_proto::checksum::strip_crc32c;
+
+    #[test]
+    fn test_crc32c_roundtrip() {
+        // Standard check value for CRC-32C
+        assert_eq!(crc32c(b"123456789"), 0xE306_9283);
+
+        let framed = ChecksumKind::Crc32c.append(b"frame");
+        let payload = strip_crc32c(&framed).unwrap();
+        assert_eq!(payload, b"frame");
+
+        let mut corrupted = framed;
+        corrupted[1] ^= 0x01;
+        assert!(strip_crc32c(&corrupted).is_none());
+        assert!(strip_crc32c(b"ab").is_none());
+    }
+}
@@ -0,0 +1,471 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use bytes::Bytes;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+use tracing::{info, warn};
+
+use crate::{
+    byterange,
+    error::BrokerError,
+    export::JobState,
+    federation::encode_path_segment,
+    outbound::HttpTarget,
+    segment::{self, RECORD_HEADER_LEN, SEGMENT_EXTENSION},
+    recordings::SearchClock,
+    registry::Frame,
+    runtime,
+    spill::{self, SpillRef},
+    stats::unix_now_ms,
+    AppState,
+};
+
+/// Body untuk POST /api/streams/:stream_id/clip
+#[derive(Debug, Default, Deserialize)]
+pub struct ClipRequest {
+    /// Unix ms, inklusif
+    pub from_ms: Option<u64>,
+    /// Unix ms, inklusif; defaults to now
+    pub to_ms: Option<u64>,
+    /// "Save the last N seconds" instead of `from_ms`/`to_ms`
+    pub last_secs: Option<u64>,
+    /// `http://` URL that receives the finished (or failed) job as JSON
+    pub webhook: Option<String>,
+}
+
+impl ClipRequest {
+    /// Rentang `[from_ms, to_ms]` klip dalam unix ms
+    fn range(&self, now_ms: u64) -> Result<(u64, u64), &'static str> {
+        match (self.last_secs, self.from_ms, self.to_ms) {
+            (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+                Err("use either last_secs or from_ms/to_ms")
+            }
+            (Some(0), _, _) => Err("last_secs must be greater than 0"),
+            (Some(secs), None, None) => Ok((now_ms.saturating_sub(secs.saturating_mul(1000)), now_ms)),
+            (None, Some(from_ms), to_ms) => {
+                let to_ms = to_ms.unwrap_or(now_ms);
+                if from_ms > to_ms {
+                    return Err("from_ms must not be after to_ms");
+                }
+                Ok((from_ms, to_ms))
+            }
+            (None, None, _) => Err("from_ms or last_secs is required"),
+        }
+    }
+}
+
+/// Satu klip yang ditulis di background
+#[derive(Debug, Clone, Serialize)]
+pub struct ClipJob {
+    pub id: u64,
+    pub stream: String,
+    pub from_ms: u64,
+    pub to_ms: u64,
+    #[serde(flatten)]
+    pub state: JobState,
+    pub frames: u64,
+    /// Frames older than the DVR buffer, read from recordings
+    pub recorded_frames: u64,
+    pub bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_seq: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seq: Option<u64>,
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(skip)]
+    webhook: Option<String>,
+}
+
+impl ClipJob {
+    fn download_path(&self) -> String {
+        format!("/api/streams/{}/clips/{}/download", encode_path_segment(&self.stream), self.id)
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).expect("job serializes");
+        if self.state == JobState::Done {
+            value["download"] = self.download_path().into();
+        }
+        value
+    }
+}
+
+/// Klip yang diketahui sejak broker start (file tetap di `CLIPS_DIR` sampai dihapus)
+#[derive(Debug, Default)]
+pub struct ClipJobs {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<u64, ClipJob>>,
+}
+
+impl ClipJobs {
+    fn update(&self, id: u64, f: impl FnOnce(&mut ClipJob)) {
+        if let Some(job) = self.jobs.lock().get_mut(&id) {
+            f(job);
+        }
+    }
+
+    fn get(&self, stream_id: &str, id: u64) -> Option<ClipJob> {
+        self.jobs
+            .lock()
+            .get(&id)
+            .filter(|job| job.stream == stream_id)
+            .cloned()
+    }
+}
+
+/// Isi DVR buffer (memori + spill) saat klip diminta
+///
+/// Taken under the registry lock; spilled payloads are read later, off the lock.
+#[derive(Debug, Default)]
+pub struct DvrSnapshot {
+    spilled: Vec<SpillRef>,
+    frames: Vec<Frame>,
+    taken_at: Option<(Instant, u64)>,
+}
+
+impl DvrSnapshot {
+    fn take(state: &AppState, stream_id: &str) -> Self {
+        let mut streams = state.streams.lock();
+        let Some(entry) = streams.get_mut(stream_id) else {
+            return Self::default();
+        };
+        Self {
+            spilled: entry.dvr.spilled_since(0),
+            frames: entry.dvr.since(0),
+            taken_at: Some((Instant::now(), unix_now_ms())),
+        }
+    }
+}
+
+/// Hasil penulisan klip
+#[derive(Debug, Default, PartialEq)]
+struct ClipSummary {
+    frames: u64,
+    recorded_frames: u64,
+    bytes: u64,
+    first_seq: Option<u64>,
+    last_seq: Option<u64>,
+}
+
+impl ClipSummary {
+    fn write(&mut self, out: &mut impl Write, seq: u64, unix_ms: u64, payload: &[u8]) -> io::Result<()> {
+        out.write_all(&segment::encode_record(seq, unix_ms, payload))?;
+        self.frames += 1;
+        self.bytes += payload.len() as u64;
+        self.first_seq.get_or_insert(seq);
+        self.last_seq = Some(seq);
+        Ok(())
+    }
+}
+
+/// Tulis frame dalam rentang klip ke file berformat segmen rekaman (blocking)
+///
+/// Frames still in the DVR buffer come from memory/spill; the part of the range
+/// that is older than the buffer is read from the stream's recordings.
+fn capture(state: &AppState, job: &ClipJob, snapshot: DvrSnapshot) -> Result<ClipSummary, String> {
+    let dvr: Vec<Frame> = spill::read(&snapshot.spilled)
+        .into_iter()
+        .chain(snapshot.frames)
+        .collect();
+    // Waktu terima (Instant) dikonversi ke unix ms relatif terhadap saat snapshot
+    let unix_ms = |frame: &Frame| match snapshot.taken_at {
+        Some((now, now_ms)) => {
+            now_ms.saturating_sub(now.saturating_duration_since(frame.received_at).as_millis() as u64)
+        }
+        None => 0,
+    };
+    let dvr_start_ms = dvr.first().map(unix_ms);
+
+    if let Some(parent) = job.path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut out = BufWriter::new(File::create(&job.path).map_err(|e| e.to_string())?);
+    let mut summary = ClipSummary::default();
+
+    if dvr_start_ms.is_none_or(|start_ms| job.from_ms < start_ms) {
+        let until_ms = dvr_start_ms.map_or(job.to_ms, |start_ms| job.to_ms.min(start_ms - 1));
+        copy_recorded(state, job, until_ms, &mut out, &mut summary).map_err(|e| e.to_string())?;
+        summary.recorded_frames = summary.frames;
+    }
+    for frame in &dvr {
+        let ms = unix_ms(frame);
+        if (job.from_ms..=job.to_ms).contains(&ms) {
+            summary
+                .write(&mut out, frame.seq, ms, &frame.data)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let file = out.into_inner().map_err(|e| e.to_string())?;
+    file.sync_all().map_err(|e| e.to_string())?;
+    if summary.frames == 0 {
+        let _ = fs::remove_file(&job.path);
+        return Err("no frames in the requested range".to_string());
+    }
+    Ok(summary)
+}
+
+/// Salin record rekaman dengan waktu dalam `[job.from_ms, until_ms]`
+fn copy_recorded(
+    state: &AppState,
+    job: &ClipJob,
+    until_ms: u64,
+    out: &mut impl Write,
+    summary: &mut ClipSummary,
+) -> io::Result<()> {
+    let root = &state.config.recordings_dir;
+    let segments = match state.recordings.search(root, &job.stream, job.from_ms, until_ms, SearchClock::Wall) {
+        Ok(segments) => segments,
+        // Stream yang tidak direkam: klip hanya dari DVR
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let dir = segment::stream_dir(root, &job.stream);
+    for segment in segments {
+        let mut reader = BufReader::new(File::open(dir.join(&segment.meta.segment))?);
+        let mut header = [0u8; RECORD_HEADER_LEN];
+        // Record terakhir segmen yang masih ditulis bisa belum lengkap
+        while reader.read_exact(&mut header).is_ok() {
+            let (seq, unix_ms, len) = segment::decode_record_header(&header);
+            let mut payload = vec![0; len];
+            if reader.read_exact(&mut payload).is_err() {
+                break;
+            }
+            if (job.from_ms..=until_ms).contains(&unix_ms) {
+                summary.write(out, seq, unix_ms, &payload)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Kirim job yang selesai/gagal ke webhook klip
+async fn notify(job: ClipJob) {
+    let Some(url) = job.webhook.clone() else {
+        return;
+    };
+    let body = Bytes::from(job.to_json().to_string());
+    let result = match HttpTarget::parse(&url) {
+        Ok(mut target) => target.post("application/json", &[], body).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(status) if status.is_success() => {}
+        Ok(status) => warn!("Clip webhook {} answered {}", url, status),
+        Err(e) => warn!("Clip webhook {} failed: {}", url, e),
+    }
+}
+
+/// Handler untuk POST /api/streams/:stream_id/clip
+pub async fn create_clip_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+    Json(request): Json<ClipRequest>,
+) -> Response {
+    let (from_ms, to_ms) = match request.range(unix_now_ms()) {
+        Ok(range) => range,
+        Err(e) => return BrokerError::InvalidRequest(e.into()).into_response(),
+    };
+    if let Some(Err(e)) = request.webhook.as_deref().map(HttpTarget::parse) {
+        return BrokerError::InvalidRequest(e).into_response();
+    }
+    // Snapshot diambil sekarang, supaya frame yang diminta tidak keburu dibuang dari DVR
+    let snapshot = DvrSnapshot::take(&state, &stream_id);
+
+    let id = state.clips.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+    let path = Path::new(&state.config.clips_dir).join(format!(
+        "{}-{}.{}",
+        encode_path_segment(&stream_id),
+        id,
+        SEGMENT_EXTENSION
+    ));
+    let job = ClipJob {
+        id,
+        stream: stream_id.clone(),
+        from_ms,
+        to_ms,
+        state: JobState::Running,
+        frames: 0,
+        recorded_frames: 0,
+        bytes: 0,
+        first_seq: None,
+        last_seq: None,
+        path,
+        webhook: request.webhook,
+    };
+    state.clips.jobs.lock().insert(id, job.clone());
+    info!("Clip {} of {} requested ({}..={})", id, stream_id, from_ms, to_ms);
+
+    let response = job.to_json();
+    let location = format!("/api/streams/{}/clips/{}", encode_path_segment(&stream_id), id);
+    let task_state = state.clone();
+    runtime::spawn_disk(async move {
+        let capture_state = task_state.clone();
+        let result = tokio::task::spawn_blocking(move || capture(&capture_state, &job, snapshot))
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()));
+        task_state.clips.update(id, |job| match result {
+            Ok(summary) => {
+                info!("Clip {} finished: {} frames", id, summary.frames);
+                job.frames = summary.frames;
+                job.recorded_frames = summary.recorded_frames;
+                job.bytes = summary.bytes;
+                job.first_seq = summary.first_seq;
+                job.last_seq = summary.last_seq;
+                job.state = JobState::Done;
+            }
+            Err(error) => {
+                warn!("Clip {} failed: {}", id, error);
+                job.state = JobState::Failed { error };
+            }
+        });
+        if let Some(job) = task_state.clips.get(&stream_id, id) {
+            notify(job).await;
+        }
+    });
+    (StatusCode::ACCEPTED, [(header::LOCATION, location)], Json(response)).into_response()
+}
+
+fn clip_not_found(id: u64) -> Response {
+    BrokerError::not_found("clip", id).into_response()
+}
+
+/// Handler untuk GET /api/streams/:stream_id/clips/:id
+pub async fn get_clip_handler(
+    AxumPath((stream_id, id)): AxumPath<(String, u64)>,
+    State(state): State<AppState>,
+) -> Response {
+    match state.clips.get(&stream_id, id) {
+        Some(job) => Json(job.to_json()).into_response(),
+        None => clip_not_found(id),
+    }
+}
+
+/// Handler untuk GET /api/streams/:stream_id/clips/:id/download
+pub async fn download_clip_handler(
+    AxumPath((stream_id, id)): AxumPath<(String, u64)>,
+    State(state): State<AppState>,
+    request_headers: HeaderMap,
+) -> Response {
+    let Some(job) = state.clips.get(&stream_id, id) else {
+        return clip_not_found(id);
+    };
+    if job.state != JobState::Done {
+        let error = BrokerError::Conflict {
+            message: "clip is not finished".to_string(),
+            details: Some(json!({ "job": job.to_json() })),
+        };
+        return error.into_response();
+    }
+    let file = match tokio::fs::File::open(&job.path).await {
+        Ok(file) => file,
+        Err(e) => {
+            return BrokerError::Gone(format!("clip file is gone: {}", e)).into_response()
+        }
+    };
+    let filename = job.path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    byterange::serve_file(file, "application/octet-stream", &filename, &request_headers)
+        .await
+        .unwrap_or_else(IntoResponse::into_response)
+}
+
+/// Handler untuk DELETE /api/streams/:stream_id/clips/:id (hapus klip dan file-nya)
+pub async fn delete_clip_handler(
+    AxumPath((stream_id, id)): AxumPath<(String, u64)>,
+    State(state): State<AppState>,
+) -> Response {
+    let removed = {
+        let mut jobs = state.clips.jobs.lock();
+        match jobs.get(&id) {
+            Some(job) if job.stream == stream_id && job.state != JobState::Running => jobs.remove(&id),
+            Some(job) if job.stream == stream_id => {
+                return BrokerError::conflict("clip is still being written").into_response()
+            }
+            _ => None,
+        }
+    };
+    let Some(job) = removed else {
+        return clip_not_found(id);
+    };
+    let _ = tokio::fs::remove_file(&job.path).await;
+    StatusCode::NO_CONTENT.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_clip_captures_dvr_range() {
+        let request = |last_secs, from_ms, to_ms| ClipRequest {
+            last_secs,
+            from_ms,
+            to_ms,
+            ..ClipRequest::default()
+        };
+        assert_eq!(request(Some(30), None, None).range(100_000), Ok((70_000, 100_000)));
+        assert_eq!(request(None, Some(5), None).range(100_000), Ok((5, 100_000)));
+        assert!(request(Some(30), Some(5), None).range(100_000).is_err());
+        assert!(request(None, Some(9), Some(5)).range(100_000).is_err());
+        assert!(request(None, None, None).range(100_000).is_err());
+
+        let root = std::env::temp_dir().join(format!("bsb-clip-test-{}", std::process::id()));
+        let config = Config {
+            recordings_dir: root.join("recordings").display().to_string(),
+            ..Config::default()
+        };
+        let state = AppState::new(config);
+        state.with_stream("cam1", |entry| {
+            for payload in ["a", "bb", "ccc"] {
+                let _ = entry.publish(Bytes::from(payload));
+            }
+        });
+        let now_ms = unix_now_ms();
+        let job = ClipJob {
+            id: 1,
+            stream: "cam1".to_string(),
+            from_ms: now_ms - 60_000,
+            to_ms: now_ms + 1000,
+            state: JobState::Running,
+            frames: 0,
+            recorded_frames: 0,
+            bytes: 0,
+            first_seq: None,
+            last_seq: None,
+            path: root.join("clips").join("cam1-1.bsbrec"),
+            webhook: None,
+        };
+        let summary = capture(&state, &job, DvrSnapshot::take(&state, "cam1")).unwrap();
+        assert_eq!((summary.frames, summary.recorded_frames, summary.bytes), (3, 0, 6));
+        assert_eq!((summary.first_seq, summary.last_seq), (Some(1), Some(3)));
+
+        let clip = fs::read(&job.path).unwrap();
+        assert_eq!(clip.len(), 3 * RECORD_HEADER_LEN + 6);
+        let header: [u8; RECORD_HEADER_LEN] = clip[..RECORD_HEADER_LEN].try_into().unwrap();
+        assert_eq!(segment::decode_record_header(&header).0, 1);
+        assert_eq!(&clip[RECORD_HEADER_LEN..RECORD_HEADER_LEN + 1], b"a");
+
+        // Rentang sebelum semua frame, tanpa rekaman: gagal dan tidak meninggalkan file
+        let empty = ClipJob {
+            to_ms: now_ms - 30_000,
+            ..job.clone()
+        };
+        assert!(capture(&state, &empty, DvrSnapshot::take(&state, "cam1")).is_err());
+        assert!(!job.path.exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,308 @@
+use axum::{extract::State, response::Json};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use tokio::time::{Interval, MissedTickBehavior};
+use tracing::{info, warn};
+
+use crate::{registry::StreamEntry, stats::unix_now_ms, AppState};
+
+/// Batas `?clock_ms=`: lebih sering dari ini hanya menambah trafik kontrol
+pub const MIN_CLOCK_MS: u64 = 100;
+pub const MAX_CLOCK_MS: u64 = 60_000;
+
+/// Sumber timestamp broker dari `TIMESTAMP_SOURCE`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ClockSource {
+    /// `CLOCK_REALTIME`: follows NTP, including the steps it makes
+    #[default]
+    System,
+    /// Wall clock at startup advanced by `CLOCK_MONOTONIC`: never steps, drifts with the oscillator
+    Monotonic,
+    /// PTP hardware clock (`/dev/ptpN`), e.g. a NIC disciplined by ptp4l
+    Ptp(String),
+}
+
+impl ClockSource {
+    /// `system`, `monotonic`, `ptp` (`/dev/ptp0`) atau `ptp:/dev/ptpN`
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim() {
+            "" | "system" => Ok(ClockSource::System),
+            "monotonic" => Ok(ClockSource::Monotonic),
+            "ptp" => Ok(ClockSource::Ptp("/dev/ptp0".to_string())),
+            other => match other.strip_prefix("ptp:") {
+                Some(device) if !device.is_empty() => Ok(ClockSource::Ptp(device.to_string())),
+                _ => Err(format!(
+                    "Invalid TIMESTAMP_SOURCE {:?}, expected system, monotonic, ptp or ptp:<device>",
+                    other
+                )),
+            },
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClockSource::System => "system",
+            ClockSource::Monotonic => "monotonic",
+            ClockSource::Ptp(_) => "ptp",
+        }
+    }
+}
+
+/// PTP hardware clock yang dibaca lewat `clock_gettime` pada dynamic clock id
+#[cfg(target_os = "linux")]
+struct PtpClock {
+    /// Clock id only stays valid while the device is open
+    _device: std::fs::File,
+    id: libc::clockid_t,
+}
+
+#[cfg(target_os = "linux")]
+impl PtpClock {
+    fn open(device: &str) -> std::io::Result<Self> {
+        use std::os::fd::AsRawFd;
+
+        let file = std::fs::File::open(device)?;
+        // FD_TO_CLOCKID dari linux/posix-timers.h
+        let id = ((!file.as_raw_fd()) << 3) | 3;
+        let clock = PtpClock { _device: file, id };
+        clock.now().map(|_| clock)
+    }
+
+    fn now(&self) -> std::io::Result<Duration> {
+        // SAFETY: timespec is plain data; all-zero is a valid value
+        let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+        // SAFETY: `ts` is a valid timespec to write into
+        if unsafe { libc::clock_gettime(self.id, &mut ts) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+    }
+}
+
+/// Di luar Linux tidak ada PHC; broker jatuh ke jam sistem
+#[cfg(not(target_os = "linux"))]
+enum PtpClock {}
+
+#[cfg(not(target_os = "linux"))]
+impl PtpClock {
+    fn open(_device: &str) -> std::io::Result<Self> {
+        Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "PTP clocks need Linux"))
+    }
+
+    fn now(&self) -> std::io::Result<Duration> {
+        match *self {}
+    }
+}
+
+/// Jam untuk timestamp yang diambil broker: penanda `clock` dan waktu terima di `/bundle`
+///
+/// A PTP device that cannot be opened falls back to the system clock with a
+/// warning, so a missing NIC does not keep the broker from starting; GET
+/// /api/clock shows the source actually in use and its offset from the system
+/// clock.
+pub struct TimestampClock {
+    source: ClockSource,
+    /// Wall clock and monotonic instant at startup, the base of `monotonic`
+    started: (Instant, u64),
+    ptp: Option<PtpClock>,
+    /// PHCs run on TAI; subtracted to get unix (UTC) ms
+    ptp_utc_offset_ms: u64,
+}
+
+impl TimestampClock {
+    pub fn new(source: &ClockSource, ptp_utc_offset_secs: u64) -> Self {
+        let mut clock = Self {
+            source: source.clone(),
+            started: (Instant::now(), unix_now_ms()),
+            ptp: None,
+            ptp_utc_offset_ms: ptp_utc_offset_secs * 1000,
+        };
+        if let ClockSource::Ptp(device) = source {
+            match PtpClock::open(device) {
+                Ok(ptp) => {
+                    info!("Timestamps from PTP clock {}", device);
+                    clock.ptp = Some(ptp);
+                }
+                Err(e) => {
+                    warn!("PTP clock {} unavailable ({}), timestamps use the system clock", device, e);
+                    clock.source = ClockSource::System;
+                }
+            }
+        }
+        clock
+    }
+
+    /// Sumber yang benar-benar dipakai (setelah fallback)
+    pub fn source(&self) -> &ClockSource {
+        &self.source
+    }
+
+    /// Sekarang dalam unix ms menurut sumber yang dipilih
+    pub fn now_ms(&self) -> u64 {
+        match (&self.source, &self.ptp) {
+            (ClockSource::Monotonic, _) => self.started.1 + self.started.0.elapsed().as_millis() as u64,
+            (ClockSource::Ptp(_), Some(ptp)) => match ptp.now() {
+                Ok(tai) => (tai.as_millis() as u64).saturating_sub(self.ptp_utc_offset_ms),
+                Err(_) => unix_now_ms(),
+            },
+            _ => unix_now_ms(),
+        }
+    }
+}
+
+impl std::fmt::Debug for TimestampClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimestampClock").field("source", &self.source).finish()
+    }
+}
+
+/// Handler untuk GET /api/clock
+/// The timestamp source in use and how far it is from this host's system clock
+pub async fn clock_handler(State(state): State<AppState>) -> Json<Value> {
+    let (now_ms, system_ms) = (state.clock.now_ms(), unix_now_ms());
+    let source = state.clock.source();
+    Json(json!({
+        "source": source.name(),
+        "device": match source {
+            ClockSource::Ptp(device) => Some(device),
+            _ => None,
+        },
+        "configured": state.config.timestamp_source.name(),
+        "now_ms": now_ms,
+        "system_ms": system_ms,
+        "offset_ms": now_ms as i64 - system_ms as i64,
+    }))
+}
+
+/// Penanda waktu untuk menyelaraskan beberapa stream ke jam broker (`?clock_ms=`)
+///
+/// Pairs the broker clock (`TIMESTAMP_SOURCE`) with the stream's newest frame, so a client
+/// watching several cameras can map each stream's sequence numbers onto one
+/// timeline. `received_ms` is always on the broker clock; `producer_ms` is the
+/// camera's own clock and only present when the producer sent it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "type", rename = "clock")]
+pub struct ClockMarker {
+    /// Broker wall clock when the marker was taken (unix ms)
+    pub broker_ms: u64,
+    /// Newest frame of the stream (0 before the first one)
+    pub seq: u64,
+    /// When the broker received frame `seq` (unix ms)
+    pub received_ms: Option<u64>,
+    /// Capture time the producer reported for frame `seq` (unix ms)
+    pub producer_ms: Option<u64>,
+}
+
+impl ClockMarker {
+    /// `broker_ms` dari `TimestampClock::now_ms`
+    pub fn of(entry: &StreamEntry, broker_ms: u64) -> Self {
+        let now = Instant::now();
+        let timing = entry.last_timing();
+        Self {
+            broker_ms,
+            seq: entry.last_seq(),
+            received_ms: timing.map(|(received_at, _)| {
+                broker_ms.saturating_sub(now.saturating_duration_since(received_at).as_millis() as u64)
+            }),
+            producer_ms: timing.and_then(|(_, producer_ms)| producer_ms),
+        }
+    }
+}
+
+/// Validasi `?clock_ms=`
+pub fn validate_interval(clock_ms: u64) -> Result<(), String> {
+    if (MIN_CLOCK_MS..=MAX_CLOCK_MS).contains(&clock_ms) {
+        Ok(())
+    } else {
+        Err(format!("clock_ms must be between {} and {}", MIN_CLOCK_MS, MAX_CLOCK_MS))
+    }
+}
+
+/// Ticker penanda yang jatuh pada kelipatan `clock_ms` jam broker
+///
+/// Every subscriber with the same interval gets its markers at the same
+/// broker instants, whichever stream it watches and whenever it connected,
+/// so markers of different streams line up without interpolation.
+pub fn ticker(clock_ms: u64, now_ms: u64) -> Interval {
+    let into_period = now_ms % clock_ms;
+    let first = tokio::time::Instant::now() + Duration::from_millis(clock_ms - into_period);
+    let mut ticker = tokio::time::interval_at(first, Duration::from_millis(clock_ms));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    ticker
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn test_clock_marker_maps_newest_frame_to_broker_clock() {
+        let mut entry = StreamEntry::new(8, 8);
+        let empty = ClockMarker::of(&entry, unix_now_ms());
+        assert_eq!((empty.seq, empty.received_ms, empty.producer_ms), (0, None, None));
+
+        let _rx = entry.tx.subscribe();
+        entry.publish(Bytes::from_static(b"frame-1")).unwrap();
+        entry.publish_at(Bytes::from_static(b"frame-2"), Some(1_700_000_000_000)).unwrap();
+        let marker = ClockMarker::of(&entry, unix_now_ms());
+        assert_eq!((marker.seq, marker.producer_ms), (2, Some(1_700_000_000_000)));
+        let received_ms = marker.received_ms.unwrap();
+        assert!(received_ms <= marker.broker_ms && marker.broker_ms - received_ms < 1000);
+
+        let json = serde_json::to_value(marker).unwrap();
+        assert_eq!((json["type"].as_str(), json["seq"].as_u64()), (Some("clock"), Some(2)));
+        assert!(validate_interval(50).is_err() && validate_interval(1000).is_ok());
+    }
+
+    #[test]
+    fn test_clock_sources_and_ptp_fallback() {
+        assert_eq!(ClockSource::parse("ptp").unwrap(), ClockSource::Ptp("/dev/ptp0".to_string()));
+        assert_eq!(ClockSource::parse("ptp:/dev/ptp1").unwrap(), ClockSource::Ptp("/dev/ptp1".to_string()));
+        assert!(ClockSource::parse("gps").is_err());
+
+        // Monotonic mulai dari jam dinding saat start dan tidak ikut langkah NTP
+        let monotonic = TimestampClock::new(&ClockSource::Monotonic, 37);
+        assert!(monotonic.now_ms().abs_diff(unix_now_ms()) < 1000);
+        let missing = TimestampClock::new(&ClockSource::Ptp("/nonexistent/ptp9".to_string()), 37);
+        assert_eq!(missing.source(), &ClockSource::System);
+        assert!(missing.now_ms().abs_diff(unix_now_ms()) < 1000);
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_get_aligned_clock_markers() {
+        use crate::{config::Config, testing::TestBroker};
+        use tokio_tungstenite::tungstenite::Message;
+
+        let broker = TestBroker::start(Config::default()).await;
+        let mut cam1 = broker.subscriber("cam1?clock_ms=100").await;
+        let mut cam2 = broker.subscriber("cam2?clock_ms=100").await;
+        let mut producer = broker.producer("cam1").await;
+        producer.send_frame(b"frame-1").await;
+        assert_eq!(cam1.expect_json().await["type"], "sync");
+        assert_eq!(cam2.expect_json().await["type"], "sync");
+
+        // Frame dan penanda bisa datang dalam urutan mana pun; cari penanda setelah frame 1
+        let marker = loop {
+            match cam1.next_within(Duration::from_secs(2)).await.expect("clock marker") {
+                Message::Text(text) => {
+                    let marker: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    if marker["seq"] == 1 {
+                        break marker;
+                    }
+                }
+                _ => continue,
+            }
+        };
+        assert_eq!(marker["type"], "clock");
+        assert!(marker["received_ms"].as_u64().unwrap() <= marker["broker_ms"].as_u64().unwrap());
+        // Jatuh pada kelipatan 100 ms jam broker, sama untuk stream lain
+        assert!(marker["broker_ms"].as_u64().unwrap() % 100 < 80);
+        let other = cam2.expect_json().await;
+        assert_eq!((other["type"].as_str(), other["seq"].as_u64()), (Some("clock"), Some(0)));
+
+        let rejected = broker.connect_with_headers("/ws/cam1?clock_ms=10", &[]).await;
+        assert_eq!(rejected.err(), Some(axum::http::StatusCode::BAD_REQUEST));
+    }
+}
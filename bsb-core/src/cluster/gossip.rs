@@ -0,0 +1,514 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::{header, HeaderMap, StatusCode},
+    response::Json,
+};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+use tracing::{debug, info, warn};
+
+use super::{Cluster, ClusterNode, MemberState, MemberStatus};
+use crate::{auth::constant_time_eq, error::BrokerError, outbound::HttpTarget, supervisor, AppState};
+
+/// Interval gossip: setiap node mengirim daftar anggotanya ke satu node lain
+const GOSSIP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Satu anggota dalam pesan gossip; heartbeat lebih tinggi selalu menang
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GossipMember {
+    pub id: String,
+    pub url: String,
+    pub heartbeat: u64,
+    #[serde(default)]
+    pub draining: bool,
+}
+
+/// Body POST /api/cluster/gossip: pandangan pengirim atas anggota yang masih hidup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Gossip {
+    pub from: String,
+    pub members: Vec<GossipMember>,
+}
+
+impl Cluster {
+    /// Gabungkan pandangan anggota dari node lain
+    pub fn merge(&self, gossip: Gossip, now: Instant) {
+        {
+            let mut members = self.members.lock();
+            for incoming in gossip.members {
+                if Some(&incoming.id) == self.node_id.as_ref() {
+                    continue;
+                }
+                match members.get_mut(&incoming.id) {
+                    Some(member) if incoming.heartbeat <= member.heartbeat => {}
+                    Some(member) => {
+                        if member.status != MemberStatus::Alive {
+                            info!("Cluster member {} is alive again", incoming.id);
+                        }
+                        if member.draining != incoming.draining {
+                            info!("Cluster member {} draining: {}", incoming.id, incoming.draining);
+                        }
+                        member.url = incoming.url;
+                        member.heartbeat = incoming.heartbeat;
+                        member.draining = incoming.draining;
+                        member.status = MemberStatus::Alive;
+                        member.seen_at = now;
+                    }
+                    None => {
+                        info!("Cluster member {} joined ({})", incoming.id, incoming.url);
+                        let member = MemberState {
+                            url: incoming.url,
+                            heartbeat: incoming.heartbeat,
+                            status: MemberStatus::Alive,
+                            draining: incoming.draining,
+                            seen_at: now,
+                        };
+                        members.insert(incoming.id, member);
+                    }
+                }
+            }
+        }
+        self.rebuild_ring();
+    }
+
+    /// Failure detector: tandai anggota yang heartbeat-nya macet sebagai suspect/dead
+    pub fn refresh(&self, now: Instant) {
+        {
+            let mut members = self.members.lock();
+            for (id, member) in members.iter_mut() {
+                let silent = now.saturating_duration_since(member.seen_at);
+                let status = if silent >= self.config.dead_after {
+                    MemberStatus::Dead
+                } else if silent >= self.config.suspect_after {
+                    MemberStatus::Suspect
+                } else {
+                    MemberStatus::Alive
+                };
+                if status != member.status {
+                    warn!("Cluster member {} is {:?} (silent for {:?})", id, status, silent);
+                    member.status = status;
+                }
+            }
+        }
+        self.rebuild_ring();
+    }
+
+    /// Pesan gossip dari node ini, dengan heartbeat yang dinaikkan
+    fn next_gossip(&self) -> Gossip {
+        let node_id = self.node_id.clone().unwrap_or_default();
+        let mut members: Vec<GossipMember> = self
+            .members
+            .lock()
+            .iter()
+            .filter(|(_, member)| member.status != MemberStatus::Dead)
+            .map(|(id, member)| GossipMember {
+                id: id.clone(),
+                url: member.url.clone(),
+                heartbeat: member.heartbeat,
+                draining: member.draining,
+            })
+            .collect();
+        members.push(GossipMember {
+            id: node_id.clone(),
+            url: self.url.clone(),
+            heartbeat: self.heartbeat.fetch_add(1, Ordering::Relaxed) + 1,
+            draining: self.is_draining(),
+        });
+        Gossip { from: node_id, members }
+    }
+
+    /// Tandai anggota yang sedang di-drain tanpa menunggu gossip berikutnya (handoff)
+    fn mark_draining(&self, id: &str) {
+        if let Some(member) = self.members.lock().get_mut(id) {
+            member.draining = true;
+        }
+        self.rebuild_ring();
+    }
+
+    /// Token cluster (`CLUSTER_TOKEN`) untuk endpoint antar node dan drain
+    fn authorize(&self, headers: &HeaderMap) -> Result<(), BrokerError> {
+        let Some(token) = self.config.token.as_deref() else {
+            return Ok(());
+        };
+        let authorized = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|authorization| authorization.strip_prefix("Bearer "))
+            .is_some_and(|presented| constant_time_eq(token, presented.trim()));
+        if authorized {
+            Ok(())
+        } else {
+            Err(BrokerError::Unauthorized("invalid cluster token".to_string()))
+        }
+    }
+
+    fn token_headers(&self) -> Vec<(&'static str, String)> {
+        self.config
+            .token
+            .iter()
+            .map(|token| ("authorization", format!("Bearer {}", token)))
+            .collect()
+    }
+
+    /// Target gossip putaran ini: anggota berikutnya (bergiliran) plus seed yang belum dikenal
+    fn gossip_targets(&self, round: usize) -> Vec<String> {
+        let members = self.members.lock();
+        let mut urls: Vec<&String> = members.values().map(|member| &member.url).collect();
+        urls.sort();
+        let mut targets: Vec<String> = Vec::new();
+        if !urls.is_empty() {
+            targets.push(urls[round % urls.len()].clone());
+        }
+        for seed in &self.config.seeds {
+            if *seed != self.url && !urls.contains(&seed) && !targets.contains(seed) {
+                targets.push(seed.clone());
+            }
+        }
+        targets
+    }
+}
+
+/// Kirim gossip ke satu anggota per detik dan jalankan failure detector
+pub fn spawn_gossip(state: &AppState) {
+    if state.cluster.node_id.is_none() {
+        return;
+    }
+    let state = state.clone();
+    supervisor::supervise("cluster gossip".to_string(), move || run_gossip(state.clone()));
+}
+
+async fn run_gossip(state: AppState) {
+    let cluster = &state.cluster;
+    let mut targets: HashMap<String, HttpTarget> = HashMap::new();
+    let mut ticker = tokio::time::interval(GOSSIP_INTERVAL);
+    let mut round = 0;
+    loop {
+        ticker.tick().await;
+        cluster.refresh(Instant::now());
+        let body = Bytes::from(serde_json::to_vec(&cluster.next_gossip()).unwrap_or_default());
+        let headers = cluster.token_headers();
+        for url in cluster.gossip_targets(round) {
+            let target = match targets.entry(url.clone()) {
+                std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    match HttpTarget::parse(&format!("{}/api/cluster/gossip", url)) {
+                        Ok(target) => entry.insert(target),
+                        Err(e) => {
+                            warn!("Cannot gossip to {}: {}", url, e);
+                            continue;
+                        }
+                    }
+                }
+            };
+            let post = target.post("application/json", &headers, body.clone());
+            match tokio::time::timeout(GOSSIP_INTERVAL, post).await {
+                Ok(Ok(status)) if status.is_success() => {}
+                Ok(Ok(status)) => warn!("Gossip to {} rejected with {}", url, status),
+                Ok(Err(e)) => debug!("Gossip to {} failed: {}", url, e),
+                Err(_) => debug!("Gossip to {} timed out", url),
+            }
+        }
+        round = round.wrapping_add(1);
+    }
+}
+
+/// Handler untuk POST /api/cluster/gossip
+pub async fn gossip_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(gossip): Json<Gossip>,
+) -> Result<StatusCode, BrokerError> {
+    if state.cluster.node_id.is_none() {
+        return Err(BrokerError::not_found("cluster", "gossip"));
+    }
+    if let Err(e) = state.cluster.authorize(&headers) {
+        warn!("Rejected cluster gossip from {}: invalid token", gossip.from);
+        return Err(e);
+    }
+    state.cluster.merge(gossip, Instant::now());
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler untuk GET /api/cluster/members
+pub async fn list_members_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let cluster = &state.cluster;
+    let now = Instant::now();
+    let mut members: Vec<_> = cluster
+        .members
+        .lock()
+        .iter()
+        .map(|(id, member)| {
+            json!({
+                "id": id,
+                "url": member.url,
+                "status": member.status,
+                "heartbeat": member.heartbeat,
+                "draining": member.draining,
+                "silent_ms": now.saturating_duration_since(member.seen_at).as_millis() as u64,
+            })
+        })
+        .collect();
+    members.sort_by(|a, b| a["id"].as_str().cmp(&b["id"].as_str()));
+    let ring: Vec<String> = cluster.ring().nodes().iter().map(|node| node.id.clone()).collect();
+    Json(json!({
+        "node": cluster.node_id,
+        "url": (cluster.node_id.is_some()).then_some(&cluster.url),
+        "heartbeat": cluster.heartbeat.load(Ordering::Relaxed),
+        "draining": cluster.is_draining(),
+        "members": members,
+        "ring": ring,
+    }))
+}
+
+/// Posisi seq stream yang diserahkan ke node pemilik baru
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandoffStream {
+    pub id: String,
+    pub seq: u64,
+}
+
+/// Body POST /api/cluster/handoff dari node yang di-drain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Handoff {
+    pub from: String,
+    pub streams: Vec<HandoffStream>,
+}
+
+/// Handler untuk POST /api/cluster/drain
+///
+/// Takes this node out of the ring (requests for its streams are redirected
+/// from now on), hands each stream's last seq to its new owner so numbering
+/// continues there, and then tells subscribers and producers to reconnect to
+/// the new owner.
+pub async fn drain_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, BrokerError> {
+    let cluster = &state.cluster;
+    let Some(node_id) = cluster.node_id.clone() else {
+        return Err(BrokerError::not_found("cluster", "drain"));
+    };
+    cluster.authorize(&headers)?;
+    if !cluster.draining.swap(true, Ordering::Relaxed) {
+        warn!("Draining cluster node {}", node_id);
+        cluster.rebuild_ring();
+    }
+
+    let positions: Vec<(String, u64)> = state
+        .streams
+        .lock()
+        .iter()
+        .map(|(id, entry)| (id.clone(), entry.last_seq()))
+        .collect();
+    let mut handoffs: HashMap<String, (ClusterNode, Vec<HandoffStream>)> = HashMap::new();
+    for (id, seq) in positions {
+        if let Some(owner) = cluster.remote_owner(&id) {
+            let (_, streams) = handoffs.entry(owner.id.clone()).or_insert_with(|| (owner, Vec::new()));
+            streams.push(HandoffStream { id, seq });
+        }
+    }
+
+    let headers = cluster.token_headers();
+    let mut moved = serde_json::Map::new();
+    let mut failed = Vec::new();
+    for (owner_id, (owner, streams)) in handoffs {
+        let count = streams.len();
+        let handoff = Handoff {
+            from: node_id.clone(),
+            streams,
+        };
+        let body = Bytes::from(serde_json::to_vec(&handoff).unwrap_or_default());
+        let result = match HttpTarget::parse(&format!("{}/api/cluster/handoff", owner.url)) {
+            Ok(mut target) => {
+                let post = target.post("application/json", &headers, body);
+                match tokio::time::timeout(GOSSIP_INTERVAL * 5, post).await {
+                    Ok(Ok(status)) if status.is_success() => Ok(()),
+                    Ok(Ok(status)) => Err(status.to_string()),
+                    Ok(Err(e)) => Err(e.to_string()),
+                    Err(_) => Err("timed out".to_string()),
+                }
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(()) => {
+                info!("Handed off {} streams to cluster node {}", count, owner_id);
+                moved.insert(owner_id, count.into());
+            }
+            Err(e) => {
+                // Subscriber tetap dipindah; penomoran di node baru saja yang tidak bersambung
+                warn!("Handoff of {} streams to {} failed: {}", count, owner_id, e);
+                failed.push(owner_id);
+            }
+        }
+    }
+    cluster.handed_off.send_replace(true);
+    Ok(Json(json!({
+        "node": node_id,
+        "draining": true,
+        "streams": moved,
+        "failed": failed,
+    })))
+}
+
+/// Handler untuk DELETE /api/cluster/drain: node kembali masuk ring
+pub async fn undrain_handler(State(state): State<AppState>, headers: HeaderMap) -> Result<StatusCode, BrokerError> {
+    let cluster = &state.cluster;
+    if cluster.node_id.is_none() {
+        return Err(BrokerError::not_found("cluster", "drain"));
+    }
+    cluster.authorize(&headers)?;
+    if cluster.draining.swap(false, Ordering::Relaxed) {
+        info!("Cluster node {} rejoins the ring", cluster.node_id.as_deref().unwrap_or_default());
+        cluster.handed_off.send_replace(false);
+        cluster.rebuild_ring();
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler untuk POST /api/cluster/handoff
+pub async fn handoff_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(handoff): Json<Handoff>,
+) -> Result<StatusCode, BrokerError> {
+    if state.cluster.node_id.is_none() {
+        return Err(BrokerError::not_found("cluster", "handoff"));
+    }
+    state.cluster.authorize(&headers)?;
+    // Node pengirim keluar dari ring di sini juga, supaya klien yang pindah tidak di-redirect balik
+    state.cluster.mark_draining(&handoff.from);
+    for stream in &handoff.streams {
+        state.with_stream(&stream.id, |entry| entry.continue_from(stream.seq));
+    }
+    info!("Took over {} streams from draining node {}", handoff.streams.len(), handoff.from);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler untuk GET /api/cluster/owner/:stream_id
+pub async fn stream_owner_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Json<serde_json::Value> {
+    let ring = state.cluster.ring();
+    let owner = ring.owner(&stream_id);
+    Json(json!({
+        "stream": stream_id,
+        "node": state.cluster.node_id,
+        "owner": owner.map(|node| &node.id),
+        "url": owner.map(|node| &node.url),
+        "local": owner.is_none_or(|node| Some(&node.id) == state.cluster.node_id.as_ref()),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cluster::{tests::nodes, ClusterConfig, HashRing, ResumeToken},
+        config::Config,
+    };
+    use axum::extract::ws::close_code;
+
+    #[test]
+    fn test_failure_detector_rebalances_ring() {
+        let config = ClusterConfig::new(Some("a".to_string()), nodes(&["a", "b"]), None, Vec::new(), None).unwrap();
+        let cluster = Cluster::new(&config);
+        let start = Instant::now();
+        let ids = |cluster: &Cluster| -> Vec<String> { cluster.ring().nodes().iter().map(|n| n.id.clone()).collect() };
+        assert_eq!(ids(&cluster), vec!["a", "b"]);
+
+        // Node c bergabung lewat gossip dari b
+        let gossip = Gossip {
+            from: "b".to_string(),
+            members: vec![
+                GossipMember { id: "b".to_string(), url: "http://b:3091".to_string(), heartbeat: 1, draining: false },
+                GossipMember { id: "c".to_string(), url: "http://c:3091".to_string(), heartbeat: 4, draining: false },
+            ],
+        };
+        cluster.merge(gossip.clone(), start);
+        assert_eq!(ids(&cluster), vec!["a", "b", "c"]);
+
+        // b terus mengirim heartbeat, c diam: suspect masih di ring, dead keluar
+        let beat = |heartbeat: u64| Gossip {
+            from: "b".to_string(),
+            members: vec![GossipMember { id: "b".to_string(), url: "http://b:3091".to_string(), heartbeat, draining: false }],
+        };
+        cluster.merge(beat(2), start + Duration::from_secs(6));
+        cluster.refresh(start + Duration::from_secs(6));
+        assert_eq!(cluster.members.lock()["c"].status, MemberStatus::Suspect);
+        assert_eq!(ids(&cluster), vec!["a", "b", "c"]);
+        cluster.merge(beat(3), start + Duration::from_secs(16));
+        cluster.refresh(start + Duration::from_secs(16));
+        assert_eq!(cluster.members.lock()["c"].status, MemberStatus::Dead);
+        assert_eq!(ids(&cluster), vec!["a", "b"]);
+
+        // Heartbeat lama tidak menghidupkan c; heartbeat baru iya
+        cluster.merge(gossip, start + Duration::from_secs(17));
+        assert_eq!(ids(&cluster), vec!["a", "b"]);
+        let revived = Gossip {
+            from: "c".to_string(),
+            members: vec![GossipMember { id: "c".to_string(), url: "http://c:3091".to_string(), heartbeat: 5, draining: false }],
+        };
+        cluster.merge(revived, start + Duration::from_secs(18));
+        assert_eq!(ids(&cluster), vec!["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_drain_migrates_subscribers_with_resume_token() {
+        use crate::testing::TestBroker;
+
+        // Node b belum mengenal a: semua stream miliknya sampai handoff tiba
+        let b = TestBroker::start(Config {
+            cluster: ClusterConfig::new(Some("b".to_string()), nodes(&["b"]), None, Vec::new(), None).unwrap(),
+            ..Config::default()
+        })
+        .await;
+        let b_url = format!("http://{}", b.addr);
+        let peers = vec![
+            ClusterNode { id: "a".to_string(), url: "http://a.invalid".to_string() },
+            ClusterNode { id: "b".to_string(), url: b_url.clone() },
+        ];
+        let ring = HashRing::new(peers.clone());
+        let stream = (0..).map(|i| format!("cam{}", i)).find(|s| ring.owner(s).unwrap().id == "a").unwrap();
+        let a = TestBroker::start(Config {
+            cluster: ClusterConfig::new(Some("a".to_string()), peers, None, Vec::new(), None).unwrap(),
+            ..Config::default()
+        })
+        .await;
+
+        let mut viewer = a.subscriber(&format!("{}?seq=true", stream)).await;
+        assert_eq!(viewer.expect_json().await["type"], "sync");
+        for frame in ["f1", "f2", "f3"] {
+            assert_eq!(a.post_frame(&stream, frame).await, StatusCode::OK);
+            assert_eq!(viewer.expect_binary().await, frame.as_bytes());
+        }
+
+        let mut drain = HttpTarget::parse(&a.url("/api/cluster/drain")).unwrap();
+        assert_eq!(drain.post("application/json", &[], Bytes::new()).await.unwrap(), StatusCode::OK);
+        let migrate = viewer.expect_json().await;
+        assert_eq!(migrate["type"], "migrate");
+        assert_eq!(migrate["node"], "b");
+        assert_eq!(migrate["seq"], 3);
+        let close = viewer.expect_close(Duration::from_secs(2)).await.unwrap();
+        assert_eq!(u16::from(close.code), close_code::RESTART);
+        // Ingest ke node yang di-drain di-redirect
+        assert_eq!(a.post_frame(&stream, "late").await, StatusCode::TEMPORARY_REDIRECT);
+
+        // Node baru melanjutkan penomoran: tidak ada gap setelah frame 3
+        let url = migrate["url"].as_str().unwrap();
+        let path = url.strip_prefix(&b_url.replacen("http", "ws", 1)).unwrap();
+        let token = ResumeToken::decode(migrate["resume_token"].as_str().unwrap()).unwrap();
+        assert_eq!((token.stream.as_str(), token.seq, token.node.as_str()), (stream.as_str(), 3, "a"));
+        let mut moved = b.subscriber(path.strip_prefix("/ws/").unwrap()).await;
+        assert_eq!(moved.expect_json().await, json!({ "type": "sync", "seq": 3 }));
+        assert_eq!(b.post_frame(&stream, "f4").await, StatusCode::OK);
+        assert_eq!(moved.expect_binary().await, b"f4");
+    }
+}
@@ -0,0 +1,528 @@
+// Tanpa fitur `cluster` tidak ada gossip: ring hanya berisi node ini dan state membership tidak terpakai
+#![cfg_attr(not(feature = "cluster"), allow(dead_code))]
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket},
+        Path as AxumPath, Request, State,
+    },
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use futures_util::{stream::SplitSink, SinkExt};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::watch;
+use tracing::{debug, info};
+
+use crate::{error::BrokerError, AppState};
+
+// Gossip, drain/handoff dan HTTP API cluster; tanpa fitur `cluster` ring hanya berisi node ini
+#[cfg(feature = "cluster")]
+pub mod gossip;
+
+/// Titik virtual per node di ring; lebih banyak = pembagian stream lebih rata
+const VIRTUAL_NODES: u32 = 128;
+
+/// Satu broker di pool cluster
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterNode {
+    pub id: String,
+    /// Base URL clients are redirected to, e.g. `http://10.0.0.2:3091`
+    pub url: String,
+}
+
+/// Node dari `CLUSTER_NODES`
+///
+/// Format: `id=url,id=url`, e.g. `node-a=http://10.0.0.1:3091,node-b=http://10.0.0.2:3091`.
+/// Nodes gossip over these URLs, so like other outbound targets they are plain HTTP.
+pub fn parse_nodes(spec: &str) -> Result<Vec<ClusterNode>, String> {
+    let mut nodes: Vec<ClusterNode> = Vec::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let invalid = || format!("Invalid CLUSTER_NODES entry: {}", entry);
+        let (id, url) = entry.split_once('=').ok_or_else(invalid)?;
+        let (id, url) = (id.trim(), url.trim().trim_end_matches('/'));
+        if id.is_empty() || !url.starts_with("http://") {
+            return Err(invalid());
+        }
+        if nodes.iter().any(|node| node.id == id) {
+            return Err(format!("Duplicate CLUSTER_NODES id: {}", id));
+        }
+        nodes.push(ClusterNode {
+            id: id.to_string(),
+            url: url.to_string(),
+        });
+    }
+    Ok(nodes)
+}
+
+/// FNV-1a dengan finalizer splitmix64; stabil antar versi Rust dan antar node
+fn hash(key: &str) -> u64 {
+    let mut h = 0xcbf2_9ce4_8422_2325u64;
+    for b in key.bytes() {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+    h ^= h >> 30;
+    h = h.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h ^= h >> 27;
+    h = h.wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+/// Consistent-hashing ring: setiap stream_id dimiliki tepat satu node
+///
+/// Adding or removing a node only moves the streams that hash next to its
+/// points, so the rest of the pool keeps its streams (and their DVR state).
+#[derive(Debug, Default)]
+pub struct HashRing {
+    nodes: Vec<ClusterNode>,
+    /// (hash, index into `nodes`), sorted by hash
+    points: Vec<(u64, usize)>,
+}
+
+impl HashRing {
+    pub fn new(nodes: Vec<ClusterNode>) -> Self {
+        let mut points: Vec<(u64, usize)> = nodes
+            .iter()
+            .enumerate()
+            .flat_map(|(index, node)| {
+                (0..VIRTUAL_NODES).map(move |i| (hash(&format!("{}#{}", node.id, i)), index))
+            })
+            .collect();
+        points.sort_unstable();
+        Self { nodes, points }
+    }
+
+    pub fn nodes(&self) -> &[ClusterNode] {
+        &self.nodes
+    }
+
+    /// Node pemilik `stream_id`: titik pertama searah jarum jam dari hash-nya
+    pub fn owner(&self, stream_id: &str) -> Option<&ClusterNode> {
+        if self.points.is_empty() {
+            return None;
+        }
+        let h = hash(stream_id);
+        let idx = self.points.partition_point(|(point, _)| *point < h) % self.points.len();
+        Some(&self.nodes[self.points[idx].1])
+    }
+}
+
+/// Pengaturan cluster dari `CLUSTER_NODE_ID`, `CLUSTER_NODES`, `CLUSTER_SEEDS`, ...
+#[derive(Clone)]
+pub struct ClusterConfig {
+    /// `None` outside cluster mode
+    pub node_id: Option<String>,
+    /// Nodes known at boot; gossip adds the rest
+    pub nodes: Vec<ClusterNode>,
+    /// URL of this node as other nodes and redirected clients reach it
+    pub advertise_url: Option<String>,
+    /// `http://` URLs of nodes to contact until they show up in the member list
+    pub seeds: Vec<String>,
+    token: Option<String>,
+    /// A member without a newer heartbeat for this long is suspect (still owns its streams)
+    pub suspect_after: Duration,
+    /// ... and for this long is dead and leaves the ring
+    pub dead_after: Duration,
+}
+
+impl std::fmt::Debug for ClusterConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClusterConfig")
+            .field("node_id", &self.node_id)
+            .field("nodes", &self.nodes)
+            .field("advertise_url", &self.advertise_url)
+            .field("seeds", &self.seeds)
+            .finish()
+    }
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            node_id: None,
+            nodes: Vec::new(),
+            advertise_url: None,
+            seeds: Vec::new(),
+            token: None,
+            suspect_after: Duration::from_secs(5),
+            dead_after: Duration::from_secs(15),
+        }
+    }
+}
+
+impl ClusterConfig {
+    /// Apakah endpoint antar node dijaga `CLUSTER_TOKEN`
+    pub fn has_token(&self) -> bool {
+        self.token.is_some()
+    }
+
+    pub fn new(
+        node_id: Option<String>,
+        nodes: Vec<ClusterNode>,
+        advertise_url: Option<String>,
+        seeds: Vec<String>,
+        token: Option<String>,
+    ) -> Result<Self, String> {
+        let Some(id) = node_id.as_deref() else {
+            if !nodes.is_empty() || !seeds.is_empty() {
+                return Err("CLUSTER_NODES and CLUSTER_SEEDS require CLUSTER_NODE_ID".to_string());
+            }
+            return Ok(Self::default());
+        };
+        if nodes.is_empty() && seeds.is_empty() {
+            return Err("CLUSTER_NODE_ID requires CLUSTER_NODES or CLUSTER_SEEDS".to_string());
+        }
+        let listed = nodes.iter().any(|node| node.id == id);
+        if advertise_url.is_none() && !listed {
+            return Err(format!(
+                "CLUSTER_NODE_ID {} is not listed in CLUSTER_NODES and CLUSTER_ADVERTISE_URL is not set",
+                id
+            ));
+        }
+        for url in advertise_url.iter().chain(&seeds) {
+            if !url.starts_with("http://") {
+                return Err(format!("Invalid cluster URL {} (use http://)", url));
+            }
+        }
+        Ok(Self {
+            node_id,
+            nodes,
+            advertise_url: advertise_url.map(|url| url.trim_end_matches('/').to_string()),
+            seeds: seeds.iter().map(|url| url.trim_end_matches('/').to_string()).collect(),
+            token,
+            ..Self::default()
+        })
+    }
+
+    /// URL node ini: `CLUSTER_ADVERTISE_URL`, atau entrinya di `CLUSTER_NODES`
+    fn self_url(&self) -> String {
+        let listed = self
+            .nodes
+            .iter()
+            .find(|node| Some(&node.id) == self.node_id.as_ref())
+            .map(|node| node.url.clone());
+        self.advertise_url.clone().or(listed).unwrap_or_default()
+    }
+}
+
+/// Status anggota menurut failure detector
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemberStatus {
+    Alive,
+    /// Heartbeat stalled for `CLUSTER_SUSPECT_SECS`; still in the ring
+    Suspect,
+    /// Heartbeat stalled for `CLUSTER_DEAD_SECS`; its streams moved to other nodes
+    Dead,
+}
+
+#[derive(Debug, Clone)]
+struct MemberState {
+    url: String,
+    heartbeat: u64,
+    status: MemberStatus,
+    /// Leaving the ring for maintenance (`POST /api/cluster/drain` on that node)
+    draining: bool,
+    /// When `heartbeat` last increased
+    seen_at: Instant,
+}
+
+
+/// Mode cluster: node ini, daftar anggota dari gossip, dan ring kepemilikan stream
+///
+/// Every node pushes its member list to one other node per second. A member
+/// whose heartbeat stops increasing becomes suspect and then dead; the ring
+/// is rebuilt from the nodes that are not dead, so ownership rebalances
+/// without a central coordinator.
+#[derive(Debug, Default)]
+pub struct Cluster {
+    /// `CLUSTER_NODE_ID`; `None` outside cluster mode
+    pub node_id: Option<String>,
+    config: ClusterConfig,
+    url: String,
+    heartbeat: AtomicU64,
+    members: Mutex<HashMap<String, MemberState>>,
+    ring: Mutex<Arc<HashRing>>,
+    /// This node left the ring; its streams are redirected to their new owners
+    draining: AtomicBool,
+    /// Set once the stream positions were handed off; subscribers and producers then migrate
+    handed_off: watch::Sender<bool>,
+}
+
+impl Cluster {
+    pub fn new(config: &ClusterConfig) -> Self {
+        let now = Instant::now();
+        let members = config
+            .nodes
+            .iter()
+            .filter(|node| Some(&node.id) != config.node_id.as_ref())
+            .map(|node| {
+                let member = MemberState {
+                    url: node.url.clone(),
+                    heartbeat: 0,
+                    status: MemberStatus::Alive,
+                    draining: false,
+                    seen_at: now,
+                };
+                (node.id.clone(), member)
+            })
+            .collect();
+        let cluster = Self {
+            node_id: config.node_id.clone(),
+            config: config.clone(),
+            url: config.self_url(),
+            heartbeat: AtomicU64::new(0),
+            members: Mutex::new(members),
+            ring: Mutex::new(Arc::new(HashRing::default())),
+            draining: AtomicBool::new(false),
+            handed_off: watch::Sender::new(false),
+        };
+        cluster.rebuild_ring();
+        cluster
+    }
+
+    pub fn ring(&self) -> Arc<HashRing> {
+        self.ring.lock().clone()
+    }
+
+    /// Node lain yang memiliki `stream_id`; `None` bila milik node ini (atau tanpa cluster)
+    pub fn remote_owner(&self, stream_id: &str) -> Option<ClusterNode> {
+        let node_id = self.node_id.as_deref()?;
+        let ring = self.ring();
+        ring.owner(stream_id).filter(|owner| owner.id != node_id).cloned()
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Berubah menjadi `true` saat node ini selesai menyerahkan stream-nya (drain)
+    pub fn handoffs(&self) -> watch::Receiver<bool> {
+        self.handed_off.subscribe()
+    }
+
+    /// Bangun ulang ring dari node ini plus anggota yang belum dead atau di-drain; `true` bila berubah
+    fn rebuild_ring(&self) -> bool {
+        let Some(node_id) = self.node_id.clone() else {
+            return false;
+        };
+        let mut nodes: Vec<ClusterNode> = self
+            .members
+            .lock()
+            .iter()
+            .filter(|(_, member)| member.status != MemberStatus::Dead && !member.draining)
+            .map(|(id, member)| ClusterNode {
+                id: id.clone(),
+                url: member.url.clone(),
+            })
+            .collect();
+        if !self.is_draining() {
+            nodes.push(ClusterNode {
+                id: node_id,
+                url: self.url.clone(),
+            });
+        }
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut ring = self.ring.lock();
+        if ring.nodes() == nodes.as_slice() {
+            return false;
+        }
+        info!(
+            "Cluster ring changed: {:?}",
+            nodes.iter().map(|node| node.id.as_str()).collect::<Vec<_>>()
+        );
+        *ring = Arc::new(HashRing::new(nodes));
+        true
+    }
+}
+
+/// Posisi subscriber yang dipindah ke node lain (`?resume_token=`)
+///
+/// Handed to subscribers of a draining node in the `migrate` message. The new
+/// owner continues the stream's numbering from the drained node (see
+/// `POST /api/cluster/drain`), so the token's seq means the same frame there.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResumeToken {
+    pub stream: String,
+    /// Last frame the subscriber received
+    pub seq: u64,
+    /// Node the subscriber migrated from
+    pub node: String,
+}
+
+impl ResumeToken {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(token: &str) -> Result<Self, BrokerError> {
+        URL_SAFE_NO_PAD
+            .decode(token.trim())
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| BrokerError::InvalidRequest("malformed resume_token".to_string()))
+    }
+}
+
+/// Kirim `migrate` (mode seq) lalu tutup subscriber dengan `1012`
+///
+/// The message carries the new owner's `/ws` URL with a resume token for the
+/// last frame this subscriber received.
+pub async fn migrate_subscriber(
+    sender: &mut SplitSink<WebSocket, Message>,
+    state: &AppState,
+    stream_id: &str,
+    owner: &ClusterNode,
+    last_seq: u64,
+    seq_mode: bool,
+) {
+    info!("Migrating subscriber of stream {} to node {}", stream_id, owner.id);
+    if seq_mode {
+        let token = ResumeToken {
+            stream: stream_id.to_string(),
+            seq: last_seq,
+            node: state.cluster.node_id.clone().unwrap_or_default(),
+        }
+        .encode();
+        // http(s):// -> ws(s)://
+        let url = format!("{}/ws/{}?resume_token={}", owner.url.replacen("http", "ws", 1), stream_id, token);
+        let message = json!({
+            "type": "migrate",
+            "node": owner.id,
+            "url": url,
+            "resume_token": token,
+            "seq": last_seq,
+        });
+        let _ = sender.send(Message::Text(message.to_string())).await;
+    }
+    let _ = sender.send(Message::Close(Some(migrate_close_frame(owner)))).await;
+}
+
+/// Close frame untuk klien yang harus pindah ke node pemilik baru
+pub fn migrate_close_frame(owner: &ClusterNode) -> CloseFrame<'static> {
+    let mut reason = format!("stream moved to node {} ({})", owner.id, owner.url);
+    while reason.len() > 123 {
+        reason.pop();
+    }
+    CloseFrame {
+        code: close_code::RESTART,
+        reason: reason.into(),
+    }
+}
+
+/// Middleware untuk endpoint per stream (ingest, /ws, pull)
+///
+/// Requests for streams owned by another node are bounced with a `307` to
+/// the same path on the owner, so each stream lives on exactly one node.
+pub async fn owner_redirect_middleware(
+    State(state): State<AppState>,
+    AxumPath(stream_id): AxumPath<String>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(owner) = state.cluster.remote_owner(&stream_id) else {
+        return next.run(request).await;
+    };
+    let path = request
+        .uri()
+        .path_and_query()
+        .map_or("/", |path| path.as_str());
+    let location = format!("{}{}", owner.url, path);
+    debug!("Redirecting request for stream {} to owner {}", stream_id, owner.id);
+    let mut response = StatusCode::TEMPORARY_REDIRECT.into_response();
+    if let Ok(location) = HeaderValue::from_str(&location) {
+        response.headers_mut().insert(header::LOCATION, location);
+    }
+    if let Ok(owner) = HeaderValue::from_str(&owner.id) {
+        response.headers_mut().insert("x-stream-owner", owner);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use axum::{body::Body, routing::post, Router};
+    use tower::util::ServiceExt;
+
+    pub(super) fn nodes(ids: &[&str]) -> Vec<ClusterNode> {
+        ids.iter()
+            .map(|id| ClusterNode {
+                id: id.to_string(),
+                url: format!("http://{}:3091", id),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ring_balances_and_moves_few_streams() {
+        let ring = HashRing::new(nodes(&["a", "b", "c"]));
+        let streams: Vec<String> = (0..3000).map(|i| format!("cam{}", i)).collect();
+        for id in ["a", "b", "c"] {
+            let owned = streams.iter().filter(|s| ring.owner(s).unwrap().id == id).count();
+            assert!((700..1300).contains(&owned), "node {} owns {}", id, owned);
+        }
+
+        // Node c keluar: hanya stream milik c yang pindah
+        let smaller = HashRing::new(nodes(&["a", "b"]));
+        for stream in &streams {
+            let before = &ring.owner(stream).unwrap().id;
+            if before != "c" {
+                assert_eq!(&smaller.owner(stream).unwrap().id, before);
+            }
+        }
+        assert!(HashRing::default().owner("cam1").is_none());
+        assert!(parse_nodes("a=http://x:1,a=http://y:1").is_err());
+        assert!(parse_nodes("a=ws://x:1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_non_owner_redirects_ingest() {
+        let ring = HashRing::new(nodes(&["a", "b"]));
+        let remote = (0..)
+            .map(|i| format!("cam{}", i))
+            .find(|s| ring.owner(s).unwrap().id == "b")
+            .unwrap();
+        let state = AppState::new(Config {
+            cluster: ClusterConfig::new(Some("a".to_string()), nodes(&["a", "b"]), None, Vec::new(), None).unwrap(),
+            ..Config::default()
+        });
+        let app = Router::new()
+            .route(
+                "/ingest/:stream_id",
+                post(|| async { StatusCode::OK }).route_layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    owner_redirect_middleware,
+                )),
+            )
+            .with_state(state);
+
+        let response = app
+            .oneshot(
+                Request::post(format!("/ingest/{}?fps=5", remote))
+                    .body(Body::from("frame"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            format!("http://b:3091/ingest/{}?fps=5", remote).as_str()
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use serde::Serialize;
+
+use crate::{bootstrap, checksum::crc32c, sniff::PayloadFormat};
+
+/// Parameter codec yang dibawa sebuah frame (SPS/PPS H.264, header SOF JPEG, init segment fMP4)
+///
+/// A decoder has to be reinitialized when these change; the broker compares
+/// `fingerprint` between frames that carry parameters and tells subscribers.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CodecParams {
+    pub format: PayloadFormat,
+    /// Picture size in pixels, when the parameters could be parsed
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// CRC32C of the raw parameter bytes
+    #[serde(skip)]
+    pub fingerprint: u32,
+}
+
+impl CodecParams {
+    /// Parameter codec dari satu frame; `None` jika frame tidak membawanya
+    pub fn extract(data: &[u8]) -> Option<Self> {
+        if bootstrap::is_init_segment(data) {
+            let (width, height) = tkhd_size(data).unzip();
+            return Some(Self {
+                format: PayloadFormat::Fmp4,
+                width,
+                height,
+                fingerprint: crc32c(data),
+            });
+        }
+        match PayloadFormat::sniff(data) {
+            PayloadFormat::H264 => h264_params(data),
+            PayloadFormat::Mjpeg => jpeg_params(data),
+            _ => None,
+        }
+    }
+
+    /// Decoder harus diinisialisasi ulang dari `self` ke `next`
+    pub fn changed(&self, next: &CodecParams) -> bool {
+        self.format != next.format || self.fingerprint != next.fingerprint
+    }
+}
+
+/// NAL unit Annex-B lengkap (header byte dan payload, tanpa start code)
+fn nal_units(data: &[u8]) -> Vec<&[u8]> {
+    let mut starts = Vec::new();
+    let mut i = 0;
+    while i + 3 <= data.len() {
+        if data[i..i + 3] == [0, 0, 1] {
+            starts.push(i + 3);
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| {
+            let end = starts.get(n + 1).map_or(data.len(), |next| next - 3);
+            // Nol sisa start code 4 byte milik NAL berikutnya
+            let mut unit = &data[start..end.max(start)];
+            while unit.len() > 1 && unit.ends_with(&[0]) {
+                unit = &unit[..unit.len() - 1];
+            }
+            unit
+        })
+        .collect()
+}
+
+/// SPS (NAL 7) dan PPS (NAL 8) dari access unit H.264
+fn h264_params(data: &[u8]) -> Option<CodecParams> {
+    let units = nal_units(data);
+    let sps = units.iter().find(|unit| unit.first().is_some_and(|nal| nal & 0x1F == 7))?;
+    let mut config = sps.to_vec();
+    for pps in units.iter().filter(|unit| unit.first().is_some_and(|nal| nal & 0x1F == 8)) {
+        config.extend_from_slice(pps);
+    }
+    let (width, height) = sps_size(sps).unzip();
+    Some(CodecParams {
+        format: PayloadFormat::H264,
+        width,
+        height,
+        fingerprint: crc32c(&config),
+    })
+}
+
+/// Pembaca bit Exp-Golomb di atas RBSP (emulation prevention byte sudah dibuang)
+struct BitReader {
+    bytes: Vec<u8>,
+    pos: usize,
+}
+
+impl BitReader {
+    fn new(nal: &[u8]) -> Self {
+        let mut bytes = Vec::with_capacity(nal.len());
+        for &byte in nal {
+            if byte == 3 && bytes.ends_with(&[0, 0]) {
+                continue;
+            }
+            bytes.push(byte);
+        }
+        Self { bytes, pos: 0 }
+    }
+
+    fn bit(&mut self) -> Option<u32> {
+        let byte = self.bytes.get(self.pos / 8)?;
+        let bit = (byte >> (7 - self.pos % 8)) & 1;
+        self.pos += 1;
+        Some(bit as u32)
+    }
+
+    fn bits(&mut self, count: u32) -> Option<u32> {
+        (0..count).try_fold(0, |value, _| Some((value << 1) | self.bit()?))
+    }
+
+    fn ue(&mut self) -> Option<u32> {
+        let mut zeros = 0;
+        while self.bit()? == 0 {
+            zeros += 1;
+            if zeros > 31 {
+                return None;
+            }
+        }
+        Some((1u32 << zeros) - 1 + self.bits(zeros)?)
+    }
+
+    fn se(&mut self) -> Option<i32> {
+        let value = self.ue()?;
+        let magnitude = value.div_ceil(2) as i32;
+        Some(if value % 2 == 1 { magnitude } else { -magnitude })
+    }
+}
+
+/// Lebar dan tinggi gambar dari SPS (ITU-T H.264 7.3.2.1.1), setelah cropping
+fn sps_size(sps: &[u8]) -> Option<(u32, u32)> {
+    let mut reader = BitReader::new(sps.get(1..)?);
+    let profile_idc = reader.bits(8)?;
+    reader.bits(16)?; // constraint flags, level_idc
+    reader.ue()?; // seq_parameter_set_id
+    let mut chroma_format_idc = 1;
+    if matches!(profile_idc, 100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135) {
+        chroma_format_idc = reader.ue()?;
+        if chroma_format_idc == 3 && reader.bit()? == 1 {
+            // separate_colour_plane_flag: diperlakukan seperti monokrom untuk cropping
+            chroma_format_idc = 0;
+        }
+        reader.ue()?; // bit_depth_luma_minus8
+        reader.ue()?; // bit_depth_chroma_minus8
+        reader.bit()?; // qpprime_y_zero_transform_bypass_flag
+        if reader.bit()? == 1 {
+            let lists = if chroma_format_idc == 3 { 12 } else { 8 };
+            for list in 0..lists {
+                if reader.bit()? == 1 {
+                    skip_scaling_list(&mut reader, if list < 6 { 16 } else { 64 })?;
+                }
+            }
+        }
+    }
+    reader.ue()?; // log2_max_frame_num_minus4
+    match reader.ue()? {
+        0 => {
+            reader.ue()?; // log2_max_pic_order_cnt_lsb_minus4
+        }
+        1 => {
+            reader.bit()?;
+            reader.se()?;
+            reader.se()?;
+            for _ in 0..reader.ue()? {
+                reader.se()?;
+            }
+        }
+        _ => {}
+    }
+    reader.ue()?; // max_num_ref_frames
+    reader.bit()?; // gaps_in_frame_num_value_allowed_flag
+    let width_mbs = reader.ue()? + 1;
+    let height_map_units = reader.ue()? + 1;
+    let frame_mbs_only = reader.bit()?;
+    if frame_mbs_only == 0 {
+        reader.bit()?; // mb_adaptive_frame_field_flag
+    }
+    reader.bit()?; // direct_8x8_inference_flag
+    let (mut left, mut right, mut top, mut bottom) = (0, 0, 0, 0);
+    if reader.bit()? == 1 {
+        (left, right, top, bottom) = (reader.ue()?, reader.ue()?, reader.ue()?, reader.ue()?);
+    }
+
+    let (sub_width, sub_height) = match chroma_format_idc {
+        0 => (1, 1),
+        1 => (2, 2),
+        2 => (2, 1),
+        _ => (1, 1),
+    };
+    let crop_x = sub_width;
+    let crop_y = sub_height * (2 - frame_mbs_only);
+    let width = (width_mbs * 16).checked_sub((left + right) * crop_x)?;
+    let height = ((2 - frame_mbs_only) * height_map_units * 16).checked_sub((top + bottom) * crop_y)?;
+    Some((width, height))
+}
+
+fn skip_scaling_list(reader: &mut BitReader, size: usize) -> Option<()> {
+    let (mut last, mut next) = (8i32, 8i32);
+    for _ in 0..size {
+        if next != 0 {
+            next = (last + reader.se()? + 256) % 256;
+        }
+        if next != 0 {
+            last = next;
+        }
+    }
+    Some(())
+}
+
+/// Header SOF JPEG: ukuran gambar, presisi dan sampling komponen
+fn jpeg_params(data: &[u8]) -> Option<CodecParams> {
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        // Marker tanpa panjang segmen (RSTn, TEM, padding 0xFF)
+        if matches!(marker, 0x01 | 0xD0..=0xD7 | 0xFF) {
+            pos += if marker == 0xFF { 1 } else { 2 };
+            continue;
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let segment = data.get(pos + 4..pos + 2 + length)?;
+        // SOF0..SOF15 kecuali DHT (C4), JPG (C8) dan DAC (CC)
+        if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let height = u16::from_be_bytes([*segment.get(1)?, *segment.get(2)?]) as u32;
+            let width = u16::from_be_bytes([*segment.get(3)?, *segment.get(4)?]) as u32;
+            return Some(CodecParams {
+                format: PayloadFormat::Mjpeg,
+                width: Some(width),
+                height: Some(height),
+                fingerprint: crc32c(segment),
+            });
+        }
+        if marker == 0xDA {
+            return None;
+        }
+        pos += 2 + length;
+    }
+    None
+}
+
+/// Ukuran track video pertama dari box `tkhd` init segment (fixed-point 16.16)
+fn tkhd_size(data: &[u8]) -> Option<(u32, u32)> {
+    let be_u32 = |at: usize| data.get(at..at + 4).map(|bytes| u32::from_be_bytes(bytes.try_into().expect("4 bytes")));
+    data.windows(4)
+        .enumerate()
+        .filter(|(at, kind)| *kind == b"tkhd" && *at >= 4)
+        .filter_map(|(at, _)| {
+            let start = at - 4;
+            let end = start + be_u32(start)? as usize;
+            let (width, height) = (be_u32(end.checked_sub(8)?)? >> 16, be_u32(end.checked_sub(4)?)? >> 16);
+            (width > 0 && height > 0).then_some((width, height))
+        })
+        .next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{StreamEntry, StreamEvent};
+    use bytes::Bytes;
+
+    /// Access unit IDR dengan SPS baseline 1280x720 (SPS/PPS dari x264)
+    fn h264_idr(pps_qp: u8) -> Bytes {
+        let mut data = vec![0, 0, 0, 1, 0x67, 0x42, 0xC0, 0x1F, 0xDA, 0x01, 0x40, 0x16, 0xE4];
+        data.extend_from_slice(&[0, 0, 0, 1, 0x68, 0xCE, 0x3C, pps_qp]);
+        data.extend_from_slice(&[0, 0, 0, 1, 0x65, 0x88, 0x84]);
+        Bytes::from(data)
+    }
+
+    fn jpeg(width: u16, height: u16) -> Bytes {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x0B, 0x08];
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&[0x01, 0x01, 0x11, 0x00, 0xFF, 0xDA, 0x00, 0x02, 0xFF, 0xD9]);
+        Bytes::from(data)
+    }
+
+    #[test]
+    fn test_codec_changes_are_announced_and_reprime_caches() {
+        let params = CodecParams::extract(&h264_idr(0x80)).unwrap();
+        assert_eq!((params.width, params.height), (Some(1280), Some(720)));
+        assert_eq!(CodecParams::extract(&jpeg(640, 480)).unwrap().width, Some(640));
+        assert!(CodecParams::extract(&[0, 0, 0, 1, 0x41, 0x9A]).is_none());
+
+        let mut entry = StreamEntry::new(16, 0);
+        let mut events = entry.events.subscribe();
+        for _ in 0..3 {
+            let _ = entry.publish(jpeg(640, 480));
+        }
+        assert!(events.try_recv().is_err());
+
+        // Resolusi berubah: subscriber diberi tahu, keyframe lama diganti
+        let _ = entry.publish(jpeg(1280, 720));
+        match events.try_recv().unwrap() {
+            StreamEvent::CodecChanged { codec, seq } => {
+                assert_eq!((codec.width, codec.height, seq), (Some(1280), Some(720), 4));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert_eq!(entry.keyframe.as_ref().unwrap().seq, 4);
+
+        // Encoder pindah ke H.264; PPS baru saja juga berarti decoder di-reset
+        let _ = entry.publish(h264_idr(0x80));
+        assert!(matches!(events.try_recv().unwrap(), StreamEvent::CodecChanged { .. }));
+        let _ = entry.publish(Bytes::from_static(&[0, 0, 0, 1, 0x41, 0x9A]));
+        let _ = entry.publish(h264_idr(0x80));
+        assert!(events.try_recv().is_err());
+        let _ = entry.publish(h264_idr(0x40));
+        assert!(matches!(events.try_recv().unwrap(), StreamEvent::CodecChanged { seq: 8, .. }));
+    }
+}
@@ -0,0 +1,828 @@
+use std::{cell::RefCell, collections::HashMap, env, path::PathBuf, time::Duration};
+
+use crate::{
+    audio::{AudioStreams, StreamType}, auth::CredentialStore, bandwidth::BandwidthLimits,
+    breaker::BreakerConfig, clock::ClockSource, cluster::{self, ClusterConfig}, fairness::FairnessConfig,
+    federation::FederationPeers, ha::{HaConfig, HaRole}, leaks::LeakConfig, limits::RouteLimits, merge::MergeRules, mux::glob_match,
+    oidc::OidcConfig, outbound::{DialConfig, HttpTarget}, pipe::IngestPipe, playback::SessionPolicy, preempt::StreamPriorities, redact::MetadataRedaction, replay::ReplayConfig, rtt::LatencyBudgets, server::TcpTuning,
+    sniff::FormatPolicy, sources::{self, StaticStream}, storage::StorageConfig, timeline::TimelineConfig,
+    tls::ClientPermissions,
+};
+
+/// Konfigurasi server yang dibaca dari environment variables (dan `.env`)
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub bind_address: String,
+    pub port: u16,
+    /// Kapasitas broadcast channel per stream
+    pub channel_capacity: usize,
+    /// Jumlah frame terakhir yang disimpan per stream untuk resume/replay
+    pub dvr_frames: usize,
+    /// Native TLS (tanpa reverse proxy); `None` berarti HTTP biasa
+    pub tls: Option<TlsConfig>,
+    /// Token/Basic credentials untuk endpoint ingest (kosong = tanpa auth)
+    pub ingest_credentials: CredentialStore,
+    /// Ukuran maksimum satu pesan WebSocket yang diterima (bytes)
+    pub ws_max_message_size: usize,
+    /// Ukuran maksimum satu frame WebSocket yang diterima (bytes)
+    pub ws_max_frame_size: usize,
+    /// Frame yang digabung dalam satu flush ke subscriber (1 = flush setiap frame)
+    pub ws_coalesce_max_frames: usize,
+    /// Batas request `/ingest/:stream_id` (`INGEST_MAX_BODY_BYTES`, `INGEST_MAX_HEADERS`, ...)
+    pub ingest_limits: RouteLimits,
+    /// Batas request semua route lain, termasuk API admin (`API_MAX_BODY_BYTES`, ...)
+    pub api_limits: RouteLimits,
+    /// TCP accept queue (listen backlog)
+    pub listen_backlog: u32,
+    /// Opsi socket TCP koneksi yang diterima (`TCP_*`)
+    pub tcp: TcpTuning,
+    /// Alamat sumber dan happy eyeballs untuk koneksi keluar (`OUTBOUND_*`)
+    pub outbound: DialConfig,
+    /// Waktu maksimum untuk TLS handshake dan header HTTP (termasuk upgrade WebSocket)
+    pub handshake_timeout_secs: u64,
+    /// Producer WebSocket harus mengirim frame pertama dalam waktu ini
+    pub first_frame_timeout_secs: u64,
+    /// Producer WebSocket yang diam selama ini setelah frame terakhirnya ditutup (0 = tanpa batas)
+    pub producer_idle_timeout_secs: u64,
+    /// Maksimum koneksi per IP yang belum menyelesaikan handshake (0 = tanpa batas)
+    pub max_half_open_per_ip: usize,
+    /// Aktifkan halaman diagnostik HTML di /debug
+    pub debug_page: bool,
+    /// Primary producer dianggap mati setelah diam selama ini; backup mengambil alih
+    pub failover_timeout_secs: u64,
+    /// Token peer broker dan namespace stream yang boleh mereka akses (/federation)
+    pub federation_exports: CredentialStore,
+    /// Broker lain yang stream-nya di-relay on demand (`prefix/stream`)
+    pub federation_peers: FederationPeers,
+    /// CA bundle untuk memverifikasi peer `wss://`
+    pub federation_ca_path: Option<String>,
+    /// CA bundle untuk memverifikasi destination uplink `wss://`
+    pub uplink_ca_path: Option<String>,
+    /// Direktori spool store-and-forward uplink
+    pub store_forward_dir: String,
+    /// Umur maksimum frame per stream sebelum dibuang dari antrian subscriber
+    pub max_frame_age: FrameAgeLimits,
+    /// Interval ping WebSocket untuk mengukur RTT setiap koneksi (0 = tidak ping)
+    pub ws_ping_interval_secs: u64,
+    /// Latency budget per stream untuk subscriber `?critical=true` (`LATENCY_BUDGET_MS`)
+    pub latency_budgets: LatencyBudgets,
+    /// Jam untuk timestamp broker: penanda `clock` dan waktu terima di `/bundle`
+    pub timestamp_source: ClockSource,
+    /// Selisih TAI-UTC yang dikurangkan dari PTP hardware clock
+    pub ptp_utc_offset_secs: u64,
+    /// Derived stream yang menggabungkan frame dari beberapa source stream
+    pub merge_streams: MergeRules,
+    /// Token subscriber untuk /ws (kosong = tanpa auth)
+    pub playback_credentials: CredentialStore,
+    /// Maksimum sesi subscriber bersamaan per playback token (0 = tanpa batas)
+    pub playback_max_sessions: usize,
+    /// Tolak sesi baru atau tutup sesi tertua saat kuota token penuh
+    pub playback_session_policy: SessionPolicy,
+    /// Field metadata data channel yang disembunyikan dari viewer tanpa scope
+    pub metadata_redaction: MetadataRedaction,
+    /// Validasi token producer/subscriber lewat identity provider (`OIDC_*`); `None` = hanya credential lokal
+    pub oidc: Option<OidcConfig>,
+    /// Producer yang terhubung tapi diam selama ini dilaporkan watchdog (0 = nonaktif)
+    pub producer_stall_secs: u64,
+    /// Batas bawah frame rate (persen dari `?fps=` producer) sebelum dilaporkan (0 = nonaktif)
+    pub producer_min_fps_percent: u32,
+    /// Endpoint `http://` yang menerima alert watchdog sebagai JSON
+    pub producer_watchdog_webhook: Option<String>,
+    /// Putus producer yang dilaporkan watchdog supaya backup/reconnect mengambil alih
+    pub producer_watchdog_disconnect: bool,
+    /// Stream dari `STATIC_STREAMS_FILE` yang dibuat (dan di-pull) saat boot
+    pub static_streams: Vec<StaticStream>,
+    /// Path `STATIC_STREAMS_FILE`, ditulis ulang oleh POST /api/restore
+    pub static_streams_file: Option<String>,
+    /// Subscriber hanya untuk stream yang sudah ada (statis atau sudah menerima frame)
+    pub strict_streams: bool,
+    /// Pipe lokal (FIFO, file, atau stdin) yang dibaca broker sendiri (`INGEST_PIPES`)
+    pub ingest_pipes: Vec<IngestPipe>,
+    /// Unix socket untuk producer ring shared-memory di host yang sama (Linux)
+    pub shm_ingest_socket: Option<String>,
+    /// Direktori segmen rekaman
+    pub recordings_dir: String,
+    /// Durasi satu segmen rekaman sebelum file baru dibuka
+    pub recording_segment_secs: u64,
+    /// Interval fsync data segmen dan journal rekaman
+    pub recording_sync_ms: u64,
+    /// Direktori file hasil export MP4/MKV
+    pub export_dir: String,
+    /// Binary ffmpeg yang dipakai untuk remux export
+    pub ffmpeg_path: String,
+    /// Direktori file klip dari POST /api/streams/:id/clip
+    pub clips_dir: String,
+    /// Batas byte spill DVR ke disk per stream (0 = nonaktif)
+    pub dvr_spill_max_bytes: u64,
+    /// Direktori file spill DVR
+    pub dvr_spill_dir: PathBuf,
+    /// Frame yang belum di-ack yang ditahan di memori per stream, di atas `dvr_frames`
+    pub ack_max_pending_frames: usize,
+    /// File JSON berisi offset durable subscriber (`None` = hanya di memori)
+    pub durable_offsets_file: Option<String>,
+    /// Stream audio-only (pattern glob ke `opus`/`pcm`)
+    pub audio_streams: AudioStreams,
+    /// Format payload yang diizinkan per pattern stream (`STREAM_FORMATS`)
+    pub stream_formats: FormatPolicy,
+    /// Stream yang hanya menerima ingest envelope dengan seq naik (`INGEST_REPLAY_*`)
+    pub replay: ReplayConfig,
+    /// Izinkan fault injection lewat `/api/streams/:id/chaos` (hanya untuk pengujian)
+    pub chaos_enabled: bool,
+    /// Kapasitas broadcast channel untuk stream audio (packet kecil, rate tinggi)
+    pub audio_channel_capacity: usize,
+    /// Jumlah frame DVR untuk stream audio (1500 x 20 ms = 30 detik)
+    pub audio_dvr_frames: usize,
+    /// Ukuran maksimum satu pesan teks di data channel (bytes)
+    pub data_max_message_bytes: usize,
+    /// Ambang circuit breaker fan-out per stream
+    pub breaker: BreakerConfig,
+    /// Penjadwal fan-out per stream (giliran kirim subscriber)
+    pub fairness: FairnessConfig,
+    /// Timeline event per stream (`/api/streams/:stream_id/events`)
+    pub timeline: TimelineConfig,
+    /// Ambang pemakaian disk rekaman/spill untuk GET /api/storage dan alert-nya
+    pub storage: StorageConfig,
+    /// Sampel subsystem untuk GET /api/debug/leaks (`LEAK_*`)
+    pub leaks: LeakConfig,
+    /// Batas egress per subscriber (default dan per playback credential)
+    pub subscriber_bandwidth: BandwidthLimits,
+    /// Prioritas stream untuk shedding (pattern glob ke angka, label `priority` menang)
+    pub stream_priorities: StreamPriorities,
+    /// Total subscriber di seluruh broker sebelum stream prioritas rendah di-shed (0 = tanpa batas)
+    pub max_subscribers: usize,
+    /// Total byte DVR di memori sebelum stream prioritas rendah di-shed (0 = tanpa batas)
+    pub max_buffer_bytes: usize,
+    /// Credential untuk endpoint admin (`/debug/pprof/*`); kosong = endpoint admin nonaktif
+    pub admin_credentials: CredentialStore,
+    /// Wajibkan credential admin (sesuai peran) juga untuk `/api/*` dan `/debug`
+    pub admin_api_auth: bool,
+    /// File user/password lokal (hash Argon2), dikelola lewat `/api/users`; `None` = nonaktif
+    pub users_file: Option<String>,
+    /// Pasangan active-passive (`HA_ROLE`); `None` = broker tunggal
+    pub ha: Option<HaConfig>,
+    /// Pool broker yang membagi stream dengan consistent hashing (`CLUSTER_NODE_ID` kosong = tanpa cluster)
+    pub cluster: ClusterConfig,
+}
+
+/// Native TLS settings, enabled by setting `TLS_CERT_PATH` and `TLS_KEY_PATH`
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+    /// CA bundle used to validate client certificates (enables mTLS for ingest)
+    pub client_ca_path: Option<String>,
+    /// Mapping dari nama sertifikat (CN/SAN) ke stream yang boleh di-ingest
+    pub client_permissions: ClientPermissions,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind_address: "127.0.0.1".to_string(),
+            port: 3091, // Default to 3091 (internal, behind Caddy)
+            channel_capacity: 128,
+            dvr_frames: 256,
+            tls: None,
+            ingest_credentials: CredentialStore::default(),
+            // Library defaults (tungstenite / axum / tokio)
+            ws_max_message_size: 64 << 20,
+            ws_max_frame_size: 16 << 20,
+            ws_coalesce_max_frames: 16,
+            ingest_limits: RouteLimits {
+                body_timeout: Some(Duration::from_secs(30)),
+                ..RouteLimits::default()
+            },
+            api_limits: RouteLimits {
+                max_headers: 64,
+                max_header_bytes: 16 << 10,
+                timeout: Some(Duration::from_secs(90)),
+                ..RouteLimits::default()
+            },
+            listen_backlog: 1024,
+            tcp: TcpTuning::default(),
+            outbound: DialConfig::default(),
+            handshake_timeout_secs: 10,
+            first_frame_timeout_secs: 10,
+            producer_idle_timeout_secs: 60,
+            max_half_open_per_ip: 16,
+            debug_page: true,
+            failover_timeout_secs: 5,
+            federation_exports: CredentialStore::default(),
+            federation_peers: FederationPeers::default(),
+            federation_ca_path: None,
+            uplink_ca_path: None,
+            store_forward_dir: "spool".to_string(),
+            max_frame_age: FrameAgeLimits::default(),
+            ws_ping_interval_secs: 5,
+            latency_budgets: LatencyBudgets::default(),
+            timestamp_source: ClockSource::default(),
+            ptp_utc_offset_secs: 37,
+            merge_streams: MergeRules::default(),
+            playback_credentials: CredentialStore::default(),
+            playback_max_sessions: 0,
+            playback_session_policy: SessionPolicy::default(),
+            metadata_redaction: MetadataRedaction::default(),
+            producer_stall_secs: 15,
+            producer_min_fps_percent: 50,
+            producer_watchdog_webhook: None,
+            producer_watchdog_disconnect: false,
+            static_streams: Vec::new(),
+            static_streams_file: None,
+            strict_streams: false,
+            ingest_pipes: Vec::new(),
+            shm_ingest_socket: None,
+            recordings_dir: "recordings".to_string(),
+            recording_segment_secs: 60,
+            recording_sync_ms: 1000,
+            export_dir: "exports".to_string(),
+            ffmpeg_path: "ffmpeg".to_string(),
+            clips_dir: "clips".to_string(),
+            dvr_spill_max_bytes: 0,
+            dvr_spill_dir: env::temp_dir().join("bsb-spill"),
+            ack_max_pending_frames: 10_000,
+            durable_offsets_file: None,
+            audio_streams: AudioStreams::default(),
+            stream_formats: FormatPolicy::default(),
+            replay: ReplayConfig::default(),
+            chaos_enabled: false,
+            audio_channel_capacity: 1024,
+            audio_dvr_frames: 1500,
+            data_max_message_bytes: 64 << 10,
+            breaker: BreakerConfig::default(),
+            fairness: FairnessConfig::default(),
+            timeline: TimelineConfig::default(),
+            storage: StorageConfig::default(),
+            leaks: LeakConfig::default(),
+            subscriber_bandwidth: BandwidthLimits::default(),
+            stream_priorities: StreamPriorities::default(),
+            max_subscribers: 0,
+            max_buffer_bytes: 0,
+            admin_credentials: CredentialStore::default(),
+            admin_api_auth: false,
+            users_file: None,
+            ha: None,
+            oidc: None,
+            cluster: ClusterConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Read configuration from environment variables, falling back to defaults
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        let replay = replay_from_env(&defaults.replay)?;
+        Ok(Self {
+            bind_address: var("BIND_ADDRESS").unwrap_or(defaults.bind_address),
+            port: parse_var("PORT", defaults.port)?,
+            channel_capacity: parse_capacity("CHANNEL_CAPACITY", defaults.channel_capacity)?,
+            dvr_frames: parse_var("DVR_BUFFER_FRAMES", defaults.dvr_frames)?,
+            tls: tls_from_env()?,
+            ingest_credentials: CredentialStore::parse(
+                &var("INGEST_CREDENTIALS").unwrap_or_default(),
+                parse_var("INGEST_BASIC_AUTH", false)?,
+            )?,
+            ws_max_message_size: parse_var("WS_MAX_MESSAGE_SIZE", defaults.ws_max_message_size)?,
+            ws_max_frame_size: parse_var("WS_MAX_FRAME_SIZE", defaults.ws_max_frame_size)?,
+            ws_coalesce_max_frames: parse_var("WS_COALESCE_MAX_FRAMES", defaults.ws_coalesce_max_frames)?
+                .max(1),
+            ingest_limits: limits_from_env("INGEST", defaults.ingest_limits)?,
+            api_limits: limits_from_env("API", defaults.api_limits)?,
+            listen_backlog: parse_var("LISTEN_BACKLOG", defaults.listen_backlog)?,
+            tcp: tcp_from_env()?,
+            outbound: DialConfig::parse(
+                &var("OUTBOUND_BIND_ADDRESSES").unwrap_or_default(),
+                parse_var("OUTBOUND_ATTEMPT_DELAY_MS", defaults.outbound.attempt_delay.as_millis() as u64)?,
+            )?,
+            handshake_timeout_secs: parse_var(
+                "HANDSHAKE_TIMEOUT_SECS",
+                defaults.handshake_timeout_secs,
+            )?,
+            first_frame_timeout_secs: parse_var(
+                "FIRST_FRAME_TIMEOUT_SECS",
+                defaults.first_frame_timeout_secs,
+            )?,
+            producer_idle_timeout_secs: parse_var(
+                "PRODUCER_IDLE_TIMEOUT_SECS",
+                defaults.producer_idle_timeout_secs,
+            )?,
+            max_half_open_per_ip: parse_var("MAX_HALF_OPEN_PER_IP", defaults.max_half_open_per_ip)?,
+            debug_page: parse_var("DEBUG_PAGE", defaults.debug_page)?,
+            failover_timeout_secs: parse_var("FAILOVER_TIMEOUT_SECS", defaults.failover_timeout_secs)?,
+            federation_exports: CredentialStore::parse(
+                &var("FEDERATION_EXPORTS").unwrap_or_default(),
+                false,
+            )?,
+            federation_peers: FederationPeers::parse(&var("FEDERATION_PEERS").unwrap_or_default())?,
+            federation_ca_path: var("FEDERATION_CA_PATH").ok(),
+            uplink_ca_path: var("UPLINK_CA_PATH").ok(),
+            store_forward_dir: var("STORE_FORWARD_DIR").unwrap_or(defaults.store_forward_dir),
+            max_frame_age: FrameAgeLimits::parse(&var("MAX_FRAME_AGE_MS").unwrap_or_default())?,
+            ws_ping_interval_secs: parse_var("WS_PING_INTERVAL_SECS", defaults.ws_ping_interval_secs)?,
+            latency_budgets: LatencyBudgets::parse(&var("LATENCY_BUDGET_MS").unwrap_or_default())?,
+            timestamp_source: ClockSource::parse(&var("TIMESTAMP_SOURCE").unwrap_or_default())?,
+            ptp_utc_offset_secs: parse_var("PTP_UTC_OFFSET_SECS", defaults.ptp_utc_offset_secs)?,
+            merge_streams: MergeRules::parse(&var("MERGE_STREAMS").unwrap_or_default())?,
+            playback_credentials: CredentialStore::parse(
+                &var("PLAYBACK_CREDENTIALS").unwrap_or_default(),
+                false,
+            )?,
+            playback_max_sessions: parse_var("PLAYBACK_MAX_SESSIONS", defaults.playback_max_sessions)?,
+            playback_session_policy: parse_var(
+                "PLAYBACK_SESSION_POLICY",
+                defaults.playback_session_policy,
+            )?,
+            metadata_redaction: MetadataRedaction::parse(&var("METADATA_REDACTION").unwrap_or_default())?,
+            producer_stall_secs: parse_var("PRODUCER_STALL_SECS", defaults.producer_stall_secs)?,
+            producer_min_fps_percent: parse_var(
+                "PRODUCER_MIN_FPS_PERCENT",
+                defaults.producer_min_fps_percent,
+            )?,
+            producer_watchdog_webhook: var("PRODUCER_WATCHDOG_WEBHOOK")
+                .ok()
+                .map(|url| {
+                    HttpTarget::parse(&url)
+                        .map(|_| url)
+                        .map_err(|e| format!("Invalid PRODUCER_WATCHDOG_WEBHOOK value: {}", e))
+                })
+                .transpose()?,
+            producer_watchdog_disconnect: parse_var(
+                "PRODUCER_WATCHDOG_DISCONNECT",
+                defaults.producer_watchdog_disconnect,
+            )?,
+            static_streams: match var("STATIC_STREAMS_FILE") {
+                Ok(path) => sources::load(&path)?,
+                Err(_) => defaults.static_streams,
+            },
+            static_streams_file: var("STATIC_STREAMS_FILE").ok(),
+            strict_streams: parse_var("STRICT_STREAMS", defaults.strict_streams)?,
+            ingest_pipes: ingest_pipes_from_env(&replay)?,
+            shm_ingest_socket: var("SHM_INGEST_SOCKET").ok().filter(|path| !path.is_empty()),
+            recordings_dir: var("RECORDINGS_DIR").unwrap_or(defaults.recordings_dir),
+            recording_segment_secs: parse_var(
+                "RECORDING_SEGMENT_SECS",
+                defaults.recording_segment_secs,
+            )?,
+            recording_sync_ms: parse_var("RECORDING_SYNC_MS", defaults.recording_sync_ms)?,
+            export_dir: var("EXPORT_DIR").unwrap_or(defaults.export_dir),
+            ffmpeg_path: var("FFMPEG_PATH").unwrap_or(defaults.ffmpeg_path),
+            clips_dir: var("CLIPS_DIR").unwrap_or(defaults.clips_dir),
+            dvr_spill_max_bytes: parse_var("DVR_SPILL_MAX_BYTES", defaults.dvr_spill_max_bytes)?,
+            dvr_spill_dir: var("DVR_SPILL_DIR").map_or(defaults.dvr_spill_dir, PathBuf::from),
+            ack_max_pending_frames: parse_var("ACK_MAX_PENDING_FRAMES", defaults.ack_max_pending_frames)?,
+            durable_offsets_file: var("DURABLE_OFFSETS_FILE").ok().filter(|path| !path.is_empty()),
+            audio_streams: AudioStreams::parse(&var("AUDIO_STREAMS").unwrap_or_default())
+                .map_err(|e| format!("Invalid AUDIO_STREAMS value: {}", e))?,
+            stream_formats: FormatPolicy::parse(&var("STREAM_FORMATS").unwrap_or_default())
+                .map_err(|e| format!("Invalid STREAM_FORMATS value: {}", e))?,
+            replay,
+            chaos_enabled: parse_var("CHAOS_ENABLED", defaults.chaos_enabled)?,
+            audio_channel_capacity: parse_capacity("AUDIO_CHANNEL_CAPACITY", defaults.audio_channel_capacity)?,
+            audio_dvr_frames: parse_var("AUDIO_DVR_FRAMES", defaults.audio_dvr_frames)?,
+            data_max_message_bytes: parse_var(
+                "DATA_MAX_MESSAGE_BYTES",
+                defaults.data_max_message_bytes,
+            )?,
+            breaker: BreakerConfig {
+                failure_percent: parse_var("BREAKER_FAILURE_PERCENT", defaults.breaker.failure_percent)?,
+                min_failures: parse_var("BREAKER_MIN_FAILURES", defaults.breaker.min_failures)?,
+                max_backoff: Duration::from_secs(parse_var(
+                    "BREAKER_MAX_BACKOFF_SECS",
+                    defaults.breaker.max_backoff.as_secs(),
+                )?),
+                ..defaults.breaker
+            },
+            fairness: FairnessConfig {
+                max_concurrent_sends: parse_var(
+                    "FANOUT_MAX_CONCURRENT_SENDS",
+                    defaults.fairness.max_concurrent_sends,
+                )?,
+                burst_frames: parse_var("FANOUT_BURST_FRAMES", defaults.fairness.burst_frames)?,
+            },
+            timeline: TimelineConfig {
+                capacity: parse_var("STREAM_TIMELINE_EVENTS", defaults.timeline.capacity)?,
+                gap: Duration::from_secs(parse_var("STREAM_TIMELINE_GAP_SECS", defaults.timeline.gap.as_secs())?),
+            },
+            storage: storage_from_env(&defaults.storage)?,
+            leaks: leaks_from_env(&defaults.leaks)?,
+            subscriber_bandwidth: BandwidthLimits::parse(
+                parse_var("SUBSCRIBER_MAX_KBPS", defaults.subscriber_bandwidth.default_kbps)?,
+                &var("PLAYBACK_BANDWIDTH_KBPS").unwrap_or_default(),
+            )?,
+            stream_priorities: StreamPriorities::parse(&var("STREAM_PRIORITIES").unwrap_or_default())?,
+            max_subscribers: parse_var("MAX_SUBSCRIBERS", defaults.max_subscribers)?,
+            max_buffer_bytes: parse_var("MAX_BUFFER_BYTES", defaults.max_buffer_bytes)?,
+            admin_credentials: CredentialStore::parse(&var("ADMIN_CREDENTIALS").unwrap_or_default(), true)?,
+            admin_api_auth: parse_var("ADMIN_API_AUTH", defaults.admin_api_auth)?,
+            users_file: var("USERS_FILE").ok().filter(|path| !path.is_empty()),
+            ha: ha_from_env()?,
+            oidc: oidc_from_env()?,
+            cluster: cluster_from_env()?,
+        })
+    }
+
+    /// Pengaturan untuk diagnosa (`/api/debug/state`); credential dan token tidak pernah ikut
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "bind_address": self.bind_address,
+            "port": self.port,
+            "tls": self.tls.is_some(),
+            "channel_capacity": self.channel_capacity,
+            "dvr_frames": self.dvr_frames,
+            "audio_channel_capacity": self.audio_channel_capacity,
+            "audio_dvr_frames": self.audio_dvr_frames,
+            "ws_max_message_size": self.ws_max_message_size,
+            "ws_coalesce_max_frames": self.ws_coalesce_max_frames,
+            "ingest_max_body_bytes": self.ingest_limits.max_body_bytes,
+            "api_max_body_bytes": self.api_limits.max_body_bytes,
+            "ingest_auth": !self.ingest_credentials.is_empty(),
+            "playback_auth": !self.playback_credentials.is_empty(),
+            "metadata_redaction": !self.metadata_redaction.is_empty(),
+            "oidc_issuer": self.oidc.as_ref().and_then(|oidc| oidc.issuer.as_deref()),
+            "first_frame_timeout_secs": self.first_frame_timeout_secs,
+            "producer_idle_timeout_secs": self.producer_idle_timeout_secs,
+            "ingest_body_timeout_secs": self.ingest_limits.body_timeout.map(|timeout| timeout.as_secs()),
+            "ws_ping_interval_secs": self.ws_ping_interval_secs,
+            "latency_budgets": !self.latency_budgets.is_empty(),
+            "timestamp_source": self.timestamp_source.name(),
+            "failover_timeout_secs": self.failover_timeout_secs,
+            "producer_stall_secs": self.producer_stall_secs,
+            "producer_min_fps_percent": self.producer_min_fps_percent,
+            "static_streams": self.static_streams.len(),
+            "strict_streams": self.strict_streams,
+            "ingest_pipes": self.ingest_pipes.len(),
+            "shm_ingest_socket": self.shm_ingest_socket,
+            "ingest_replay_streams": self.replay.streams,
+            "ingest_replay_window": self.replay.window,
+            "chaos_enabled": self.chaos_enabled,
+            "recordings_dir": self.recordings_dir,
+            "dvr_spill_max_bytes": self.dvr_spill_max_bytes,
+            "max_subscribers": self.max_subscribers,
+            "max_buffer_bytes": self.max_buffer_bytes,
+            "fanout_max_concurrent_sends": self.fairness.max_concurrent_sends,
+            "stream_timeline_events": self.timeline.capacity,
+            "storage_warn_percent": self.storage.warn_percent,
+            "storage_critical_percent": self.storage.critical_percent,
+            "storage_max_bytes": self.storage.max_bytes,
+            "storage_pause_recording": self.storage.pause_recording,
+            "leak_sample_secs": self.leaks.sample_interval.as_secs(),
+            "leak_window": self.leaks.window,
+            "ha_role": self.ha.as_ref().map(|ha| ha.role),
+            "cluster_node_id": self.cluster.node_id,
+        })
+    }
+
+    /// Jenis stream saat dibuat: `type` stream statis, lalu `AUDIO_STREAMS`
+    pub fn stream_type(&self, stream_id: &str) -> StreamType {
+        self.static_streams
+            .iter()
+            .find(|stream| stream.id == stream_id && stream.stream_type.is_audio())
+            .map(|stream| stream.stream_type)
+            .or_else(|| self.audio_streams.for_stream(stream_id))
+            .unwrap_or_default()
+    }
+
+    pub fn bind_addr(&self) -> String {
+        format!("{}:{}", self.bind_address, self.port)
+    }
+}
+
+/// Per-stream frame TTL from `MAX_FRAME_AGE_MS`
+///
+/// Format: `pattern=ms;pattern=ms`, e.g. `teleop/*=150;cam1=500`. The first
+/// matching rule wins; streams without a rule never drop stale frames.
+#[derive(Debug, Clone, Default)]
+pub struct FrameAgeLimits {
+    rules: Vec<(String, Duration)>,
+}
+
+impl FrameAgeLimits {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for rule in spec.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            let (pattern, ms) = rule
+                .rsplit_once('=')
+                .ok_or_else(|| format!("Invalid MAX_FRAME_AGE_MS rule: {}", rule))?;
+            let ms: u64 = ms
+                .trim()
+                .parse()
+                .map_err(|e| format!("Invalid MAX_FRAME_AGE_MS value in {}: {}", rule, e))?;
+            rules.push((pattern.trim().to_string(), Duration::from_millis(ms)));
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn for_stream(&self, stream_id: &str) -> Option<Duration> {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, stream_id))
+            .map(|(_, max_age)| *max_age)
+    }
+}
+
+fn tls_from_env() -> Result<Option<TlsConfig>, String> {
+    match (var("TLS_CERT_PATH").ok(), var("TLS_KEY_PATH").ok()) {
+        (Some(cert_path), Some(key_path)) => Ok(Some(TlsConfig {
+            cert_path,
+            key_path,
+            client_ca_path: var("TLS_CLIENT_CA_PATH").ok(),
+            client_permissions: ClientPermissions::parse(
+                &var("TLS_CLIENT_PERMISSIONS").unwrap_or_default(),
+            )?,
+        })),
+        (None, None) => Ok(None),
+        _ => Err("TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string()),
+    }
+}
+
+/// `<prefix>_MAX_BODY_BYTES`, `_MAX_HEADERS`, `_MAX_HEADER_BYTES`, `_TIMEOUT_SECS`; 0 = tanpa batas
+fn limits_from_env(prefix: &str, defaults: RouteLimits) -> Result<RouteLimits, String> {
+    let var = |name: &str| format!("{}_{}", prefix, name);
+    let timeout_secs = parse_var(&var("TIMEOUT_SECS"), defaults.timeout.map_or(0, |timeout| timeout.as_secs()))?;
+    let body_timeout_secs = parse_var(
+        &var("BODY_TIMEOUT_SECS"),
+        defaults.body_timeout.map_or(0, |timeout| timeout.as_secs()),
+    )?;
+    Ok(RouteLimits {
+        max_body_bytes: parse_var(&var("MAX_BODY_BYTES"), defaults.max_body_bytes)?,
+        max_headers: parse_var(&var("MAX_HEADERS"), defaults.max_headers)?,
+        max_header_bytes: parse_var(&var("MAX_HEADER_BYTES"), defaults.max_header_bytes)?,
+        timeout: (timeout_secs > 0).then(|| Duration::from_secs(timeout_secs)),
+        body_timeout: (body_timeout_secs > 0).then(|| Duration::from_secs(body_timeout_secs)),
+    })
+}
+
+fn tcp_from_env() -> Result<TcpTuning, String> {
+    // 0 = default OS
+    let nonzero = |name| -> Result<Option<u64>, String> { Ok(Some(parse_var(name, 0u64)?).filter(|v| *v > 0)) };
+    let keepalive = nonzero("TCP_KEEPALIVE_SECS")?.map(Duration::from_secs);
+    let keepalive_interval = nonzero("TCP_KEEPALIVE_INTERVAL_SECS")?.map(Duration::from_secs);
+    let keepalive_retries = nonzero("TCP_KEEPALIVE_RETRIES")?.map(|retries| retries as u32);
+    if keepalive.is_none() && (keepalive_interval.is_some() || keepalive_retries.is_some()) {
+        return Err("TCP_KEEPALIVE_INTERVAL_SECS and TCP_KEEPALIVE_RETRIES require TCP_KEEPALIVE_SECS".to_string());
+    }
+    Ok(TcpTuning {
+        nodelay: parse_var("TCP_NODELAY", false)?,
+        send_buffer: nonzero("TCP_SEND_BUFFER_BYTES")?.map(|bytes| bytes as usize),
+        recv_buffer: nonzero("TCP_RECV_BUFFER_BYTES")?.map(|bytes| bytes as usize),
+        keepalive,
+        keepalive_interval,
+        keepalive_retries,
+    })
+}
+
+fn cluster_from_env() -> Result<ClusterConfig, String> {
+    let optional = |name| var(name).ok().filter(|value: &String| !value.is_empty());
+    if !cfg!(feature = "cluster") && optional("CLUSTER_NODE_ID").is_some() {
+        return Err("CLUSTER_NODE_ID requires a build with the `cluster` feature".to_string());
+    }
+    let seeds = optional("CLUSTER_SEEDS")
+        .map(|seeds| seeds.split(',').map(|url| url.trim().to_string()).filter(|url| !url.is_empty()).collect())
+        .unwrap_or_default();
+    let mut config = ClusterConfig::new(
+        optional("CLUSTER_NODE_ID"),
+        cluster::parse_nodes(&var("CLUSTER_NODES").unwrap_or_default())?,
+        optional("CLUSTER_ADVERTISE_URL"),
+        seeds,
+        optional("CLUSTER_TOKEN"),
+    )?;
+    config.suspect_after = Duration::from_secs(parse_var("CLUSTER_SUSPECT_SECS", config.suspect_after.as_secs())?);
+    config.dead_after = Duration::from_secs(parse_var("CLUSTER_DEAD_SECS", config.dead_after.as_secs())?);
+    if config.dead_after <= config.suspect_after {
+        return Err("CLUSTER_DEAD_SECS must be greater than CLUSTER_SUSPECT_SECS".to_string());
+    }
+    Ok(config)
+}
+
+fn ha_from_env() -> Result<Option<HaConfig>, String> {
+    let Some(role) = var("HA_ROLE").ok().filter(|role| !role.is_empty()) else {
+        return Ok(None);
+    };
+    if !cfg!(feature = "cluster") {
+        return Err("HA_ROLE requires a build with the `cluster` feature".to_string());
+    }
+    let role: HaRole = role.parse().map_err(|e| format!("Invalid HA_ROLE value: {}", e))?;
+    HaConfig::new(
+        role,
+        var("HA_PEER_URL").ok(),
+        var("HA_TOKEN").unwrap_or_default(),
+        var("HA_CA_PATH").ok(),
+        parse_var("HA_MIRROR_DVR", false)?,
+        Duration::from_secs(parse_var("HA_TAKEOVER_SECS", 5)?),
+        var("HA_TAKEOVER_COMMAND").ok().filter(|command| !command.is_empty()),
+    )
+    .map(Some)
+}
+
+fn storage_from_env(defaults: &StorageConfig) -> Result<StorageConfig, String> {
+    let storage = StorageConfig {
+        warn_percent: parse_var("STORAGE_WARN_PERCENT", defaults.warn_percent)?,
+        critical_percent: parse_var("STORAGE_CRITICAL_PERCENT", defaults.critical_percent)?,
+        max_bytes: parse_var("STORAGE_MAX_BYTES", defaults.max_bytes)?,
+        check_interval: Duration::from_secs(parse_var("STORAGE_CHECK_SECS", defaults.check_interval.as_secs())?),
+        webhook: var("STORAGE_ALERT_WEBHOOK")
+            .ok()
+            .filter(|url| !url.is_empty())
+            .map(|url| {
+                HttpTarget::parse(&url)
+                    .map(|_| url)
+                    .map_err(|e| format!("Invalid STORAGE_ALERT_WEBHOOK value: {}", e))
+            })
+            .transpose()?,
+        pause_recording: parse_var("STORAGE_PAUSE_RECORDING", defaults.pause_recording)?,
+    };
+    storage.validate()?;
+    Ok(storage)
+}
+
+fn leaks_from_env(defaults: &LeakConfig) -> Result<LeakConfig, String> {
+    let leaks = LeakConfig {
+        sample_interval: Duration::from_secs(parse_var("LEAK_SAMPLE_SECS", defaults.sample_interval.as_secs())?),
+        window: parse_var("LEAK_WINDOW", defaults.window)?,
+    };
+    leaks.validate()?;
+    Ok(leaks)
+}
+
+fn replay_from_env(defaults: &ReplayConfig) -> Result<ReplayConfig, String> {
+    let replay = ReplayConfig {
+        streams: ReplayConfig::parse_streams(&var("INGEST_REPLAY_STREAMS").unwrap_or_default()),
+        window: parse_var("INGEST_REPLAY_WINDOW", defaults.window)?,
+        webhook: var("INGEST_REPLAY_WEBHOOK")
+            .ok()
+            .filter(|url| !url.is_empty())
+            .map(|url| {
+                HttpTarget::parse(&url)
+                    .map(|_| url)
+                    .map_err(|e| format!("Invalid INGEST_REPLAY_WEBHOOK value: {}", e))
+            })
+            .transpose()?,
+    };
+    replay.validate()?;
+    Ok(replay)
+}
+
+fn ingest_pipes_from_env(replay: &ReplayConfig) -> Result<Vec<IngestPipe>, String> {
+    let pipes = IngestPipe::parse_list(&var("INGEST_PIPES").unwrap_or_default())?;
+    // Pipe tidak membawa seq envelope, jadi stream yang dilindungi replay akan menolak semua frame-nya
+    if let Some(pipe) = pipes.iter().find(|pipe| replay.protects(&pipe.stream_id)) {
+        return Err(format!(
+            "INGEST_PIPES stream {} is in INGEST_REPLAY_STREAMS, which only accepts envelope frames",
+            pipe.stream_id
+        ));
+    }
+    Ok(pipes)
+}
+
+fn oidc_from_env() -> Result<Option<OidcConfig>, String> {
+    let url = |name: &str| var(name).ok().filter(|url| !url.is_empty());
+    let (introspection_url, jwks_url) = (url("OIDC_INTROSPECTION_URL"), url("OIDC_JWKS_URL"));
+    if introspection_url.is_none() && jwks_url.is_none() {
+        return Ok(None);
+    }
+    let mut oidc = OidcConfig::new(
+        introspection_url,
+        var("OIDC_CLIENT_ID").unwrap_or_default(),
+        var("OIDC_CLIENT_SECRET").unwrap_or_default(),
+        jwks_url,
+        url("OIDC_ISSUER"),
+        url("OIDC_AUDIENCE"),
+        var("OIDC_SCOPE_CLAIM").unwrap_or_else(|_| "scope".to_string()),
+        Duration::from_secs(parse_var("OIDC_CACHE_SECS", 60)?),
+        &var("OIDC_APPLY_TO").unwrap_or_else(|_| "ingest,playback".to_string()),
+        var("OIDC_CA_PATH").ok().as_deref(),
+    )?;
+    oidc.publish_prefix = var("OIDC_PUBLISH_SCOPE").unwrap_or(oidc.publish_prefix);
+    oidc.subscribe_prefix = var("OIDC_SUBSCRIBE_SCOPE").unwrap_or(oidc.subscribe_prefix);
+    Ok(Some(oidc))
+}
+
+thread_local! {
+    /// Variabel kandidat yang menimpa environment proses selama [`with_candidate`]
+    static CANDIDATE: RefCell<Option<HashMap<String, Option<String>>>> = const { RefCell::new(None) };
+}
+
+/// Baca variabel konfigurasi: variabel kandidat bila sedang divalidasi, lalu environment proses
+fn var(name: &str) -> Result<String, env::VarError> {
+    let candidate = CANDIDATE.with(|candidate| candidate.borrow().as_ref().and_then(|vars| vars.get(name).cloned()));
+    match candidate {
+        Some(Some(value)) => Ok(value),
+        Some(None) => Err(env::VarError::NotPresent),
+        None => env::var(name),
+    }
+}
+
+/// Jalankan `f` (mis. [`Config::from_env`]) dengan `overrides` di atas environment proses
+///
+/// Used to dry-run a candidate configuration without touching the process
+/// environment; `None` unsets a variable. The overrides only apply on the
+/// calling thread and are removed when `f` returns or panics.
+pub fn with_candidate<R>(overrides: HashMap<String, Option<String>>, f: impl FnOnce() -> R) -> R {
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            CANDIDATE.with(|candidate| *candidate.borrow_mut() = None);
+        }
+    }
+    CANDIDATE.with(|candidate| *candidate.borrow_mut() = Some(overrides));
+    let _reset = Reset;
+    f()
+}
+
+/// Pengaturan yang berbeda antara dua [`Config::summary`], urut nama
+pub fn summary_diff(running: &serde_json::Value, candidate: &serde_json::Value) -> Vec<serde_json::Value> {
+    let empty = serde_json::Map::new();
+    let (running, candidate) = (
+        running.as_object().unwrap_or(&empty),
+        candidate.as_object().unwrap_or(&empty),
+    );
+    let mut settings: Vec<&String> = running.keys().chain(candidate.keys()).collect();
+    settings.sort();
+    settings.dedup();
+    settings
+        .into_iter()
+        .filter(|setting| running.get(*setting) != candidate.get(*setting))
+        .map(|setting| {
+            serde_json::json!({
+                "setting": setting,
+                "running": running.get(setting),
+                "candidate": candidate.get(setting),
+            })
+        })
+        .collect()
+}
+
+/// Parse an optional environment variable, returning a readable error on bad values
+pub fn parse_var<T>(name: &str, default: T) -> Result<T, String>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    match var(name) {
+        Ok(value) => value
+            .parse::<T>()
+            .map_err(|e| format!("Invalid {} value: {}", name, e)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Kapasitas channel broadcast; 0 ditolak karena `broadcast::channel(0)` panic
+fn parse_capacity(name: &str, default: usize) -> Result<usize, String> {
+    match parse_var(name, default)? {
+        0 => Err(format!("{} must be at least 1", name)),
+        capacity => Ok(capacity),
+    }
+}
+
+/// Parse durasi seperti `500ms`, `30s`, `5m`, `1h`; angka tanpa satuan = detik
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration: {:?}", value))?;
+    let secs = match unit {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(format!("invalid duration unit in {:?} (use ms, s, m or h)", value)),
+    };
+    Duration::try_from_secs_f64(secs).map_err(|_| format!("invalid duration: {:?}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("15"), Ok(Duration::from_secs(15)));
+        assert!(parse_duration("10d").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+
+    #[test]
+    fn test_zero_channel_capacity_is_rejected() {
+        for name in ["CHANNEL_CAPACITY", "AUDIO_CHANNEL_CAPACITY"] {
+            let candidate = HashMap::from([(name.to_string(), Some("0".to_string()))]);
+            let error = with_candidate(candidate, Config::from_env).unwrap_err();
+            assert_eq!(error, format!("{} must be at least 1", name));
+        }
+    }
+
+    #[test]
+    fn test_frame_age_limits() {
+        let limits = FrameAgeLimits::parse("teleop/*=150; cam1=500").unwrap();
+        assert_eq!(limits.for_stream("teleop/arm"), Some(Duration::from_millis(150)));
+        assert_eq!(limits.for_stream("cam1"), Some(Duration::from_millis(500)));
+        assert_eq!(limits.for_stream("cam2"), None);
+        assert!(FrameAgeLimits::parse("cam1").is_err());
+    }
+}
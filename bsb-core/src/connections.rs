@@ -0,0 +1,196 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    response::{IntoResponse, Json, Response},
+};
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::{error::BrokerError, qoe::ViewerStats, stats::unix_now_ms, AppState};
+
+/// Arah koneksi WebSocket pada sebuah stream
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionKind {
+    Producer,
+    Subscriber,
+}
+
+/// Satu koneksi yang sedang terbuka; di-update task koneksinya tanpa lock registry
+#[derive(Debug)]
+pub struct Connection {
+    pub id: u64,
+    pub kind: ConnectionKind,
+    /// Negotiated subprotocol
+    pub protocol: &'static str,
+    /// Subscriber options that change delivery (`delay`, `ack`, `tap`, ...), empty for plain live viewers
+    pub modes: Vec<&'static str>,
+    pub connected_ms: u64,
+    /// Last frame the subscriber sent
+    pub last_seq: AtomicU64,
+    /// Frames waiting in the subscriber's broadcast queue
+    pub queued: AtomicUsize,
+    /// Frames the producer sent
+    pub frames: AtomicU64,
+    /// When the producer sent its last frame (unix ms, 0 = none yet)
+    pub last_frame_ms: AtomicU64,
+    /// Latest playback statistics the subscriber reported
+    pub qoe: Mutex<Option<ViewerStats>>,
+    /// Last and smoothed WebSocket ping round trip (microseconds, 0 = not measured yet)
+    pub rtt_us: AtomicU64,
+    pub srtt_us: AtomicU64,
+}
+
+impl Connection {
+    /// Catat posisi subscriber: frame terakhir terkirim dan antriannya
+    pub fn progress(&self, last_seq: u64, queued: usize) {
+        self.last_seq.store(last_seq, Ordering::Relaxed);
+        self.queued.store(queued, Ordering::Relaxed);
+    }
+
+    /// Catat satu frame dari producer
+    pub fn frame(&self) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        self.last_frame_ms.store(unix_now_ms(), Ordering::Relaxed);
+    }
+
+    /// Catat sampel RTT ping/pong dan nilai yang sudah di-smooth
+    pub fn record_rtt(&self, rtt: Duration, smoothed: Duration) {
+        self.rtt_us.store(rtt.as_micros() as u64, Ordering::Relaxed);
+        self.srtt_us.store(smoothed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn describe(&self) -> Value {
+        let millis = |us: &AtomicU64| match us.load(Ordering::Relaxed) {
+            0 => Value::Null,
+            us => json!(us as f64 / 1000.0),
+        };
+        let mut value = json!({
+            "id": self.id,
+            "protocol": self.protocol,
+            "connected_ms": self.connected_ms,
+            "rtt_ms": millis(&self.rtt_us),
+            "srtt_ms": millis(&self.srtt_us),
+        });
+        match self.kind {
+            ConnectionKind::Producer => {
+                value["frames"] = self.frames.load(Ordering::Relaxed).into();
+                value["last_frame_ms"] = self.last_frame_ms.load(Ordering::Relaxed).into();
+            }
+            ConnectionKind::Subscriber => {
+                value["modes"] = json!(self.modes);
+                value["last_seq"] = self.last_seq.load(Ordering::Relaxed).into();
+                value["queued"] = self.queued.load(Ordering::Relaxed).into();
+                if let Some(qoe) = *self.qoe.lock() {
+                    value["qoe"] = json!(qoe);
+                }
+            }
+        }
+        value
+    }
+}
+
+/// Producer dan subscriber WebSocket yang terhubung ke satu stream (`/api/debug/state`)
+#[derive(Debug, Default)]
+pub struct ConnectionTable {
+    next_id: AtomicU64,
+    open: Mutex<BTreeMap<u64, Arc<Connection>>>,
+}
+
+impl ConnectionTable {
+    /// Daftarkan koneksi; dihapus lagi saat guard di-drop
+    pub fn register(
+        self: &Arc<Self>,
+        kind: ConnectionKind,
+        protocol: &'static str,
+        modes: Vec<&'static str>,
+    ) -> ConnectionGuard {
+        let connection = Arc::new(Connection {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed) + 1,
+            kind,
+            protocol,
+            modes,
+            connected_ms: unix_now_ms(),
+            last_seq: AtomicU64::new(0),
+            queued: AtomicUsize::new(0),
+            frames: AtomicU64::new(0),
+            last_frame_ms: AtomicU64::new(0),
+            qoe: Mutex::new(None),
+            rtt_us: AtomicU64::new(0),
+            srtt_us: AtomicU64::new(0),
+        });
+        self.open.lock().insert(connection.id, connection.clone());
+        ConnectionGuard {
+            table: self.clone(),
+            connection,
+        }
+    }
+
+    /// Jumlah koneksi terdaftar, termasuk yang guard-nya belum di-drop
+    pub fn count(&self) -> usize {
+        self.open.lock().len()
+    }
+
+    /// Laporan statistik terakhir koneksi `kind` yang pernah melapor, per id koneksi
+    pub fn reports(&self, kind: ConnectionKind) -> Vec<(u64, ViewerStats)> {
+        self.open
+            .lock()
+            .values()
+            .filter(|connection| connection.kind == kind)
+            .filter_map(|connection| Some((connection.id, (*connection.qoe.lock())?)))
+            .collect()
+    }
+
+    /// Koneksi terbuka dengan arah `kind`, urut waktu connect
+    pub fn describe(&self, kind: ConnectionKind) -> Vec<Value> {
+        self.open
+            .lock()
+            .values()
+            .filter(|connection| connection.kind == kind)
+            .map(|connection| connection.describe())
+            .collect()
+    }
+}
+
+pub struct ConnectionGuard {
+    table: Arc<ConnectionTable>,
+    connection: Arc<Connection>,
+}
+
+impl std::ops::Deref for ConnectionGuard {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.table.open.lock().remove(&self.connection.id);
+    }
+}
+
+/// Handler untuk GET /api/streams/:id/connections
+/// Open producers and subscribers of a stream with their ping round trip
+pub async fn connections_handler(AxumPath(stream_id): AxumPath<String>, State(state): State<AppState>) -> Response {
+    let map = state.streams.lock();
+    let Some(entry) = map.get(&stream_id) else {
+        return BrokerError::StreamNotFound(stream_id).into_response();
+    };
+    Json(json!({
+        "stream": stream_id,
+        "latency_budget_ms": state.config.latency_budgets.for_stream(&stream_id).map(|budget| budget.as_millis() as u64),
+        "producers": entry.connections.describe(ConnectionKind::Producer),
+        "subscribers": entry.connections.describe(ConnectionKind::Subscriber),
+    }))
+    .into_response()
+}
@@ -0,0 +1,239 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Json, Response},
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tracing::info;
+
+use crate::{error::BrokerError, AppState};
+
+/// Jumlah assignment round-robin terakhir yang diingat per group
+///
+/// Every member sees every frame and asks who owns it; the first member to ask
+/// assigns the frame. Assignments must outlive the slowest member's queue, so
+/// this is well above any broadcast channel capacity.
+const ASSIGNMENT_WINDOW: usize = 8192;
+
+/// Cara membagi frame antar anggota consumer group (`?balance=`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Balance {
+    /// Frames go to members in turn, regardless of stream
+    #[default]
+    RoundRobin,
+    /// All frames of one stream go to the same member (rendezvous hashing on the
+    /// stream ID), so per-stream state such as a tracker stays on one worker
+    Sticky,
+}
+
+/// Key group: (scope subscription, nama group)
+///
+/// The scope is the stream ID for `/ws` or the mux source for `/mux`; members of
+/// one group must subscribe to the same thing, or frames assigned to a member
+/// that does not see them would be lost.
+type GroupKey = (String, String);
+
+/// Consumer group aktif, dibuat saat anggota pertama join dan dihapus saat anggota terakhir keluar
+#[derive(Debug, Default)]
+pub struct ConsumerGroups {
+    groups: Mutex<HashMap<GroupKey, Arc<ConsumerGroup>>>,
+    next_member: AtomicU64,
+}
+
+#[derive(Debug)]
+struct ConsumerGroup {
+    balance: Balance,
+    state: Mutex<GroupState>,
+}
+
+#[derive(Debug, Default)]
+struct GroupState {
+    /// Member IDs in join order, with frames delivered to each
+    members: Vec<(u64, u64)>,
+    /// Next round-robin position
+    next: usize,
+    /// Recent round-robin assignments: (stream hash, seq) -> member ID
+    assigned: HashMap<(u64, u64), u64>,
+    order: VecDeque<(u64, u64)>,
+}
+
+fn hash_of(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl ConsumerGroups {
+    /// Join group `name` dalam `scope`; balance harus sama dengan anggota yang sudah ada
+    pub fn join(self: &Arc<Self>, scope: &str, name: &str, balance: Balance) -> Result<GroupMember, BrokerError> {
+        let key = (scope.to_string(), name.to_string());
+        let id = self.next_member.fetch_add(1, Ordering::Relaxed);
+        let mut groups = self.groups.lock();
+        let group = groups
+            .entry(key.clone())
+            .or_insert_with(|| {
+                Arc::new(ConsumerGroup {
+                    balance,
+                    state: Mutex::new(GroupState::default()),
+                })
+            })
+            .clone();
+        if group.balance != balance {
+            return Err(BrokerError::conflict(format!(
+                "consumer group {} on {} uses balance {:?}",
+                name, scope, group.balance
+            )));
+        }
+        let mut state = group.state.lock();
+        state.members.push((id, 0));
+        info!("Consumer {} joined group {} on {} ({} members)", id, name, scope, state.members.len());
+        drop(state);
+        Ok(GroupMember {
+            groups: Arc::clone(self),
+            key,
+            group,
+            id,
+        })
+    }
+
+    /// Ringkasan semua group untuk GET /api/consumer-groups
+    pub fn list(&self) -> Vec<serde_json::Value> {
+        let groups = self.groups.lock();
+        let mut list: Vec<_> = groups
+            .iter()
+            .map(|((scope, name), group)| {
+                let state = group.state.lock();
+                let delivered: Vec<u64> = state.members.iter().map(|(_, delivered)| *delivered).collect();
+                json!({
+                    "name": name,
+                    "scope": scope,
+                    "balance": group.balance,
+                    "members": state.members.len(),
+                    "delivered": delivered,
+                })
+            })
+            .collect();
+        list.sort_by(|a, b| (a["scope"].as_str(), a["name"].as_str()).cmp(&(b["scope"].as_str(), b["name"].as_str())));
+        list
+    }
+}
+
+/// Keanggotaan satu subscriber; keluar dari group saat di-drop
+#[derive(Debug)]
+pub struct GroupMember {
+    groups: Arc<ConsumerGroups>,
+    key: GroupKey,
+    group: Arc<ConsumerGroup>,
+    id: u64,
+}
+
+impl GroupMember {
+    /// Apakah frame `seq` dari `stream_id` dikirim ke anggota ini
+    ///
+    /// Delivery is at most once: a frame assigned to a member that lags past
+    /// it or disconnects before sending it is not reassigned.
+    pub fn owns(&self, stream_id: &str, seq: u64) -> bool {
+        let stream = hash_of(stream_id);
+        let mut state = self.group.state.lock();
+        let owner = match self.group.balance {
+            Balance::Sticky => state
+                .members
+                .iter()
+                .max_by_key(|(member, _)| hash_of((stream, member)))
+                .map(|(member, _)| *member),
+            Balance::RoundRobin => match state.assigned.get(&(stream, seq)) {
+                Some(owner) => Some(*owner),
+                None => {
+                    let position = state.next % state.members.len().max(1);
+                    let owner = state.members.get(position).map(|(member, _)| *member);
+                    state.next = position + 1;
+                    if let Some(owner) = owner {
+                        if state.order.len() == ASSIGNMENT_WINDOW {
+                            if let Some(oldest) = state.order.pop_front() {
+                                state.assigned.remove(&oldest);
+                            }
+                        }
+                        state.assigned.insert((stream, seq), owner);
+                        state.order.push_back((stream, seq));
+                    }
+                    owner
+                }
+            },
+        };
+        let owned = owner == Some(self.id);
+        if owned {
+            if let Some(entry) = state.members.iter_mut().find(|(member, _)| *member == self.id) {
+                entry.1 += 1;
+            }
+        }
+        owned
+    }
+}
+
+impl Drop for GroupMember {
+    fn drop(&mut self) {
+        let mut groups = self.groups.groups.lock();
+        let mut state = self.group.state.lock();
+        state.members.retain(|(member, _)| *member != self.id);
+        let (scope, name) = &self.key;
+        info!("Consumer {} left group {} on {} ({} members)", self.id, name, scope, state.members.len());
+        if state.members.is_empty() {
+            drop(state);
+            groups.remove(&self.key);
+        }
+    }
+}
+
+/// Handler untuk GET /api/consumer-groups
+/// Consumer group aktif dengan jumlah anggota dan frame yang dikirim ke tiap anggota
+pub async fn list_consumer_groups_handler(State(state): State<AppState>) -> Response {
+    Json(json!({ "groups": state.consumers.list() })).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_members_receive_disjoint_frames() {
+        let groups = Arc::new(ConsumerGroups::default());
+        let a = groups.join("cam1", "detectors", Balance::RoundRobin).unwrap();
+        let b = groups.join("cam1", "detectors", Balance::RoundRobin).unwrap();
+        assert!(groups.join("cam1", "detectors", Balance::Sticky).is_err());
+
+        // Setiap anggota menanyakan setiap frame, dalam urutan apa pun: tepat satu pemilik
+        let owned: Vec<(bool, bool)> = (1..=6)
+            .map(|seq| {
+                let b_owns = b.owns("cam1", seq);
+                (a.owns("cam1", seq), b_owns)
+            })
+            .collect();
+        assert!(owned.iter().all(|(a, b)| a != b));
+        assert_eq!(owned.iter().filter(|(a, _)| *a).count(), 3);
+
+        // Sticky: satu stream selalu ke anggota yang sama
+        let x = groups.join("mux", "trackers", Balance::Sticky).unwrap();
+        let y = groups.join("mux", "trackers", Balance::Sticky).unwrap();
+        for stream in ["cam1", "cam2", "cam3", "cam4"] {
+            let x_owns = x.owns(stream, 1);
+            assert_ne!(x_owns, y.owns(stream, 1));
+            assert!((2..10).all(|seq| x.owns(stream, seq) == x_owns));
+        }
+
+        // Anggota terakhir keluar: group dihapus, anggota sisa menerima semua frame
+        drop(b);
+        assert!((7..10).all(|seq| a.owns("cam1", seq)));
+        drop(a);
+        assert_eq!(groups.list().len(), 1);
+    }
+}
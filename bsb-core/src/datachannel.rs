@@ -0,0 +1,238 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path as AxumPath, Query, State,
+    },
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::{
+    connections::ConnectionKind,
+    error::BrokerError,
+    lifetime, playback,
+    redact::Redactor,
+    registry::StreamEvent,
+    timeline::{self, TimelineEvent},
+    ws, AppState,
+};
+
+/// Kapasitas broadcast channel data per stream
+pub const DATA_CHANNEL_CAPACITY: usize = 64;
+
+/// ID koneksi data channel, supaya pengirim tidak menerima pesannya sendiri
+static NEXT_CLIENT: AtomicU64 = AtomicU64::new(1);
+
+/// Satu pesan teks di data channel stream (caption, chat, control)
+#[derive(Debug, Clone)]
+pub struct DataMessage {
+    /// Connection that sent the message
+    pub sender: u64,
+    pub text: Arc<str>,
+}
+
+/// Query parameter untuk GET /ws/:stream_id/data
+#[derive(Debug, Default, Deserialize)]
+pub struct DataParams {
+    /// Playback token for browsers that cannot set an `Authorization` header
+    token: Option<String>,
+    /// Also deliver this connection's own messages back to it
+    #[serde(default, deserialize_with = "crate::deserialize_flag")]
+    echo: bool,
+}
+
+/// Siarkan pesan ke semua client data channel stream; mengembalikan jumlah penerima
+pub fn publish(state: &AppState, stream_id: &str, message: DataMessage) -> Result<usize, BrokerError> {
+    let map = state.streams.lock();
+    let entry = map
+        .get(stream_id)
+        .ok_or_else(|| BrokerError::StreamNotFound(stream_id.to_string()))?;
+    if entry.lifetime.ended.is_some() {
+        return Err(BrokerError::StreamEnded(stream_id.to_string()));
+    }
+    entry.counters.record_data_message();
+    Ok(entry.data.send(message).unwrap_or(0))
+}
+
+/// Handler untuk GET /ws/:stream_id/data
+/// Text pub/sub companion of the binary stream, with the same playback auth
+pub async fn data_channel_handler(
+    ws: WebSocketUpgrade,
+    AxumPath(stream_id): AxumPath<String>,
+    Query(params): Query<DataParams>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    info!("Data channel connection request for stream: {}", stream_id);
+    let viewer = match playback::authorize(&state, &stream_id, &headers, params.token.as_deref()).await {
+        Ok(viewer) => viewer,
+        Err(status) => return status.into_response(),
+    };
+    let redactor = state.config.metadata_redaction.for_viewer(&stream_id, &viewer.scopes);
+    let max_message = state.config.data_max_message_bytes;
+    ws::apply_limits(ws, &state.config)
+        .max_message_size(max_message)
+        .max_frame_size(max_message)
+        .on_upgrade(move |socket| data_channel_connection(socket, stream_id, params, viewer.session, redactor, state))
+}
+
+/// Handle data channel WebSocket connection
+async fn data_channel_connection(
+    socket: WebSocket,
+    stream_id: String,
+    params: DataParams,
+    mut session: Option<playback::Session>,
+    redactor: Option<Redactor>,
+    state: AppState,
+) {
+    let client = NEXT_CLIENT.fetch_add(1, Ordering::Relaxed);
+    // Data channel ikut lifecycle stream: dibuat bersama stream dan ditutup saat stream dihentikan
+    let (mut rx, mut events, ended) = state.with_stream(&stream_id, |entry| {
+        (entry.data.subscribe(), entry.events.subscribe(), entry.lifetime.ended)
+    });
+    let (mut sender, mut receiver) = socket.split();
+
+    if let Some(reason) = ended {
+        info!("Stream {} has been stopped, closing data channel client", stream_id);
+        let _ = sender.send(Message::Close(Some(lifetime::close_frame(reason)))).await;
+        return;
+    }
+    info!("Data channel client {} connected for stream: {}", client, stream_id);
+
+    loop {
+        tokio::select! {
+            result = rx.recv() => {
+                match result {
+                    Ok(message) if message.sender == client && !params.echo => {}
+                    Ok(message) => {
+                        // Metadata yang tidak boleh dilihat viewer ini dihapus (METADATA_REDACTION)
+                        let text = match &redactor {
+                            Some(redactor) => match redactor.apply(&message.text) {
+                                Some(text) => text,
+                                None => continue,
+                            },
+                            None => message.text.to_string(),
+                        };
+                        if let Err(e) = sender.send(Message::Text(text)).await {
+                            error!("Failed to send data message to client: {}", e);
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Data channel client lagged, skipped {} messages for stream: {}", skipped, stream_id);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+            Ok(event) = events.recv() => {
+                if let StreamEvent::StreamEnded { reason, .. } = event {
+                    info!("Stream {} stopped ({:?}), closing data channel client", stream_id, reason);
+                    let _ = sender.send(Message::Close(Some(lifetime::close_frame(reason)))).await;
+                    break;
+                }
+            }
+            _ = async { session.as_mut().unwrap().kicked().await }, if session.is_some() => {
+                warn!("Closing data channel for stream {}: playback session limit exceeded", stream_id);
+                let close = BrokerError::SessionLimit("session limit exceeded".to_string()).close_frame();
+                let _ = sender.send(Message::Close(Some(close))).await;
+                let reason = "data channel session limit exceeded".to_string();
+                timeline::record(&state, &stream_id, TimelineEvent::Kicked { role: ConnectionKind::Subscriber, reason });
+                break;
+            }
+            msg = receiver.next() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        let message = DataMessage {
+                            sender: client,
+                            text: text.into(),
+                        };
+                        // Stream yang berhenti ditangani lewat event StreamEnded
+                        let _ = publish(&state, &stream_id, message);
+                    }
+                    Some(Ok(Message::Ping(data))) => {
+                        if let Err(e) = sender.send(Message::Pong(data)).await {
+                            error!("Failed to send pong: {}", e);
+                            break;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => {
+                        // Binary messages belong on the stream itself; ignore
+                    }
+                    Some(Err(e)) => {
+                        error!("Data channel error for stream {}: {}", stream_id, e);
+                        if let Some(close) = ws::close_frame_for_error(&e) {
+                            let _ = sender.send(Message::Close(Some(close))).await;
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    info!("Data channel client {} disconnected for stream: {}", client, stream_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, lifetime::StopReason};
+
+    #[tokio::test]
+    async fn test_publish_follows_stream_lifecycle() {
+        let state = AppState::new(Config::default());
+        let message = |text: &str| DataMessage {
+            sender: 1,
+            text: text.into(),
+        };
+        assert!(matches!(publish(&state, "cam1", message("lost")), Err(BrokerError::StreamNotFound(_))));
+
+        let mut rx = state.with_stream("cam1", |entry| entry.data.subscribe());
+        assert_eq!(publish(&state, "cam1", message("hello")).unwrap(), 1);
+        assert_eq!(&*rx.try_recv().unwrap().text, "hello");
+
+        state.with_stream("cam1", |entry| entry.lifetime.ended = Some(StopReason::Scheduled));
+        assert!(matches!(publish(&state, "cam1", message("late")), Err(BrokerError::StreamEnded(_))));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_metadata_redacted_for_viewers_without_scope() {
+        use crate::{auth::CredentialStore, redact::MetadataRedaction, testing::TestBroker};
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let broker = TestBroker::start(Config {
+            playback_credentials: CredentialStore::parse("guest=cam*;ops=cam*,+metadata:full", false).unwrap(),
+            metadata_redaction: MetadataRedaction::parse("cam*=gps,device.serial").unwrap(),
+            ..Config::default()
+        })
+        .await;
+        let (mut guest, _) = broker.connect_with_headers("/ws/cam1/data?token=guest", &[]).await.unwrap();
+        let (mut ops, _) = broker.connect_with_headers("/ws/cam1/data?token=ops", &[]).await.unwrap();
+        while broker.state.with_stream("cam1", |entry| entry.data.receiver_count()) < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let message = |text: &str| DataMessage {
+            sender: 0,
+            text: text.into(),
+        };
+        publish(&broker.state, "cam1", message("plain text")).unwrap();
+        publish(&broker.state, "cam1", message(r#"{"gps":[-6.2,106.8],"device":{"serial":"SN-1"},"label":"gate"}"#)).unwrap();
+
+        // Viewer dengan scope metadata:full menerima pesan apa adanya
+        assert_eq!(ops.next_within(std::time::Duration::from_secs(2)).await, Some(WsMessage::Text("plain text".into())));
+        assert_eq!(ops.expect_json().await["gps"][0], -6.2);
+        // Guest tidak menerima teks yang tidak bisa diperiksa, dan field sensitif dihapus
+        assert_eq!(guest.expect_json().await, serde_json::json!({ "device": {}, "label": "gate" }));
+    }
+}
@@ -0,0 +1,182 @@
+use axum::{
+    extract::State,
+    response::{Html, Json},
+};
+use serde_json::json;
+use std::sync::atomic::Ordering;
+
+use crate::{connections::ConnectionKind, registry::StreamEntry, stats::unix_now_ms, AppState};
+
+/// Halaman diagnostik statis; data diambil dari /debug/streams
+const DEBUG_PAGE: &str = include_str!("debug.html");
+
+/// Sampel statistik 1 s terakhir dan skor kesehatan stream; `null` tanpa fitur `metrics`
+#[cfg(feature = "metrics")]
+fn history_snapshot(entry: &StreamEntry) -> (serde_json::Value, serde_json::Value) {
+    (json!(entry.history.latest()), json!(entry.history.health()))
+}
+
+#[cfg(not(feature = "metrics"))]
+fn history_snapshot(_entry: &StreamEntry) -> (serde_json::Value, serde_json::Value) {
+    (serde_json::Value::Null, serde_json::Value::Null)
+}
+
+/// Handler untuk GET /debug
+/// Minimal HTML UI for field technicians: live streams, rolling stats and a test player
+pub async fn debug_page_handler() -> Html<&'static str> {
+    Html(DEBUG_PAGE)
+}
+
+/// Handler untuk GET /debug/streams
+/// Snapshot of every live stream with its latest 1 s statistics sample
+pub async fn debug_streams_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let map = state.streams.lock();
+    let mut streams: Vec<_> = map
+        .iter()
+        .map(|(stream_id, entry)| {
+            let (latest, health) = history_snapshot(entry);
+            json!({
+                "stream": stream_id,
+                "subscribers": entry.tx.receiver_count(),
+                "last_seq": entry.last_seq(),
+                "source": entry.failover.active(),
+                "checksum_failures": entry.counters.checksum_failures.load(Ordering::Relaxed),
+                "format_rejections": entry.counters.format_rejections.load(Ordering::Relaxed),
+                "producer_alerts": entry.counters.producer_alerts.load(Ordering::Relaxed),
+                "type": entry.stream_type,
+                "format": entry.format.format(),
+                "audio_ms": entry.counters.audio_us.load(Ordering::Relaxed) / 1000,
+                "data_clients": entry.data.receiver_count(),
+                "data_messages": entry.counters.data_messages.load(Ordering::Relaxed),
+                "send_errors": entry.counters.send_errors.load(Ordering::Relaxed),
+                "breaker_trips": entry.counters.breaker_trips.load(Ordering::Relaxed),
+                "breaker_open": entry.breaker.is_open(std::time::Instant::now()),
+                "throttled": entry.counters.throttled.load(Ordering::Relaxed),
+                "fanout_waits": entry.counters.fanout_waits.load(Ordering::Relaxed),
+                "fanout_yields": entry.counters.fanout_yields.load(Ordering::Relaxed),
+                "coalescing_factor": entry.counters.coalescing_factor(),
+                "latest": latest,
+                "health": health,
+                "spill_bytes": entry.dvr.spill_bytes(),
+            })
+        })
+        .collect();
+    streams.sort_by(|a, b| a["stream"].as_str().cmp(&b["stream"].as_str()));
+    let total_connections: usize = map.values().map(|entry| entry.tx.receiver_count()).sum();
+
+    Json(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "total_connections": total_connections,
+        "streams": streams,
+    }))
+}
+
+/// Handler untuk GET /api/debug/state (admin)
+///
+/// Dumps the whole registry under one acquisition of its lock, so every
+/// stream is captured at the same instant; ingest and new subscribers wait for
+/// the few milliseconds it takes. Subscriber positions and queue depths are
+/// published by the connection tasks and read without stopping them.
+pub async fn debug_state_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let map = state.streams.lock();
+    let taken_at_ms = unix_now_ms();
+    let mut streams: Vec<_> = map
+        .iter()
+        .map(|(stream_id, entry)| {
+            let events: Vec<_> = entry
+                .recent_events
+                .iter()
+                .map(|(at_ms, event)| json!({ "at_ms": at_ms, "event": event }))
+                .collect();
+            json!({
+                "stream": stream_id,
+                "type": entry.stream_type,
+                "format": entry.format.format(),
+                "last_seq": entry.last_seq(),
+                "source": entry.failover.active(),
+                "ended": entry.lifetime.ended,
+                "labels": entry.labels,
+                "channel": {
+                    "capacity": entry.capacity,
+                    "queued": entry.tx.len(),
+                    "receivers": entry.tx.receiver_count(),
+                },
+                "dvr": {
+                    "frames": entry.dvr.buffered(),
+                    "capacity": entry.dvr.capacity(),
+                    "first_seq": entry.dvr.first_seq(),
+                    "bytes": entry.dvr.memory_bytes(),
+                    "spill_bytes": entry.dvr.spill_bytes(),
+                },
+                "breaker_open": entry.breaker.is_open(std::time::Instant::now()),
+                "producers": entry.connections.describe(ConnectionKind::Producer),
+                "subscribers": entry.connections.describe(ConnectionKind::Subscriber),
+                "data_clients": entry.data.receiver_count(),
+                "latest": history_snapshot(entry).0,
+                "recent_events": events,
+            })
+        })
+        .collect();
+    drop(map);
+    streams.sort_by(|a, b| a["stream"].as_str().cmp(&b["stream"].as_str()));
+
+    Json(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "taken_at_ms": taken_at_ms,
+        "paused": state.paused.load(Ordering::Relaxed),
+        "ha_role": state.ha.role(),
+        "cluster_draining": state.cluster.is_draining(),
+        "config": state.config.summary(),
+        "streams": streams,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[tokio::test]
+    async fn test_debug_streams_lists_streams() {
+        let state = AppState::new(Config::default());
+        state.with_stream("cam2", |_| ());
+        let _rx = state.with_stream("cam1", |entry| entry.tx.subscribe());
+
+        let Json(body) = debug_streams_handler(State(state)).await;
+        let streams = body["streams"].as_array().unwrap();
+        assert_eq!(streams.len(), 2);
+        assert_eq!(streams[0]["stream"], "cam1");
+        assert_eq!(streams[0]["subscribers"], 1);
+        assert_eq!(body["total_connections"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_debug_state_shows_connections_and_events() {
+        use crate::{lifetime::StopReason, registry::StreamEvent, testing::TestBroker};
+        use std::time::Duration;
+
+        let broker = TestBroker::start(Config::default()).await;
+        let _live = broker.subscriber("cam1").await;
+        let _delayed = broker.subscriber("cam1?delay=5s&seq=true").await;
+        let mut producer = broker.producer("cam1").await;
+        producer.send_frame(b"frame-1").await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let ended = StreamEvent::StreamEnded {
+            reason: StopReason::Scheduled,
+            seq: 1,
+        };
+        broker.state.with_stream("cam1", |entry| entry.emit(ended));
+
+        let Json(body) = debug_state_handler(State(broker.state.clone())).await;
+        let stream = &body["streams"][0];
+        assert_eq!(stream["stream"], "cam1");
+        assert_eq!(stream["last_seq"], 1);
+        assert_eq!(stream["dvr"]["frames"], 1);
+        assert_eq!(stream["producers"][0]["frames"], 1);
+        let subscribers = stream["subscribers"].as_array().unwrap();
+        assert_eq!(subscribers.len(), 2);
+        assert_eq!(subscribers[1]["modes"], json!(["delay"]));
+        assert_eq!(stream["recent_events"][0]["event"]["type"], "stream_ended");
+        assert!(body["config"].get("ingest_auth").is_some());
+    }
+}
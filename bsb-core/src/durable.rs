@@ -0,0 +1,290 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::StatusCode,
+    response::Json,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tracing::{error, info};
+
+use crate::{error::BrokerError, runtime, stats::unix_now, AppState};
+
+/// Interval penulisan `DURABLE_OFFSETS_FILE`
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Posisi satu durable subscriber: seq terakhir yang dikirim kepadanya
+#[derive(Debug, Default)]
+pub struct DurableCursor {
+    seq: AtomicU64,
+    /// Unix seconds of the last connect or disconnect
+    seen_at: AtomicU64,
+    connected: AtomicBool,
+}
+
+/// Key durable subscription: (stream ID, nama)
+type DurableKey = (String, String);
+
+/// Durable subscription yang diketahui broker, dipulihkan dari `DURABLE_OFFSETS_FILE` saat start
+#[derive(Debug, Default)]
+pub struct DurableSubscriptions {
+    cursors: Mutex<BTreeMap<DurableKey, Arc<DurableCursor>>>,
+}
+
+/// Isi `DURABLE_OFFSETS_FILE`
+///
+/// The stream's own `last_seq` is saved next to its subscribers' offsets, so
+/// sequence numbers continue across a broker restart and a saved offset still
+/// points at the same frame.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+struct OffsetsFile {
+    streams: BTreeMap<String, StreamOffsets>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+struct StreamOffsets {
+    last_seq: u64,
+    subscribers: BTreeMap<String, u64>,
+}
+
+impl DurableSubscriptions {
+    /// Hubungkan subscriber `name`; dibuat pada koneksi pertama dengan posisi `current_seq`
+    ///
+    /// Returns the guard that records delivered frames and the seq to resume
+    /// after. Only one connection may use a name at a time.
+    pub fn attach(&self, stream_id: &str, name: &str, current_seq: u64) -> Result<(DurableGuard, u64), BrokerError> {
+        let cursor = self
+            .cursors
+            .lock()
+            .entry((stream_id.to_string(), name.to_string()))
+            .or_insert_with(|| {
+                info!("Created durable subscription {} on stream {}", name, stream_id);
+                Arc::new(DurableCursor {
+                    seq: AtomicU64::new(current_seq),
+                    ..DurableCursor::default()
+                })
+            })
+            .clone();
+        if cursor.connected.swap(true, Ordering::AcqRel) {
+            return Err(BrokerError::conflict(format!(
+                "durable subscription {} on stream {} is already connected",
+                name, stream_id
+            )));
+        }
+        cursor.seen_at.store(unix_now(), Ordering::Relaxed);
+        let resume_from = cursor.seq.load(Ordering::Relaxed);
+        Ok((DurableGuard { cursor }, resume_from))
+    }
+
+    fn snapshot(&self) -> Vec<(DurableKey, u64, bool, u64)> {
+        self.cursors
+            .lock()
+            .iter()
+            .map(|(key, cursor)| {
+                (
+                    key.clone(),
+                    cursor.seq.load(Ordering::Relaxed),
+                    cursor.connected.load(Ordering::Relaxed),
+                    cursor.seen_at.load(Ordering::Relaxed),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Koneksi aktif satu durable subscriber; melepas nama saat di-drop
+#[derive(Debug)]
+pub struct DurableGuard {
+    cursor: Arc<DurableCursor>,
+}
+
+impl DurableGuard {
+    /// Catat seq terakhir yang dikirim (atau sengaja dilewati) ke koneksi ini
+    pub fn record(&self, seq: u64) {
+        self.cursor.seq.fetch_max(seq, Ordering::Relaxed);
+    }
+}
+
+impl Drop for DurableGuard {
+    fn drop(&mut self) {
+        self.cursor.seen_at.store(unix_now(), Ordering::Relaxed);
+        self.cursor.connected.store(false, Ordering::Release);
+    }
+}
+
+/// Muat offset yang tersimpan dan lanjutkan nomor urut stream-nya
+pub fn restore(state: &AppState) -> Result<(), String> {
+    let Some(path) = state.config.durable_offsets_file.as_deref() else {
+        return Ok(());
+    };
+    let file: OffsetsFile = match state.hooks.store.load(path) {
+        Ok(Some(contents)) => serde_json::from_slice(&contents)
+            .map_err(|e| format!("Invalid DURABLE_OFFSETS_FILE {}: {}", path, e))?,
+        Ok(None) => return Ok(()),
+        Err(e) => return Err(format!("Cannot read DURABLE_OFFSETS_FILE {}: {}", path, e)),
+    };
+    let mut cursors = state.durable.cursors.lock();
+    for (stream_id, offsets) in file.streams {
+        state.with_stream(&stream_id, |entry| entry.continue_from(offsets.last_seq));
+        for (name, seq) in offsets.subscribers {
+            let cursor = DurableCursor {
+                seq: AtomicU64::new(seq),
+                ..DurableCursor::default()
+            };
+            cursors.insert((stream_id.clone(), name), Arc::new(cursor));
+        }
+    }
+    info!("Restored {} durable subscriptions from {}", cursors.len(), path);
+    Ok(())
+}
+
+fn offsets_file(state: &AppState) -> OffsetsFile {
+    let mut file = OffsetsFile::default();
+    let snapshot = state.durable.snapshot();
+    let streams = state.streams.lock();
+    for ((stream_id, name), seq, _, _) in snapshot {
+        let offsets = file.streams.entry(stream_id.clone()).or_default();
+        offsets.last_seq = streams.get(&stream_id).map_or(seq, |entry| entry.last_seq());
+        offsets.subscribers.insert(name, seq);
+    }
+    file
+}
+
+/// Tulis file lewat file sementara + rename, supaya crash tidak meninggalkan file setengah jadi
+pub fn write_atomic(path: &str, contents: &[u8]) -> io::Result<()> {
+    let tmp = format!("{}.tmp", path);
+    fs::write(&tmp, contents)?;
+    fs::File::open(&tmp)?.sync_all()?;
+    fs::rename(&tmp, Path::new(path))
+}
+
+/// Simpan offset ke `DURABLE_OFFSETS_FILE` setiap detik selama ada perubahan
+pub async fn run_flusher(state: AppState) {
+    let Some(path) = state.config.durable_offsets_file.clone() else {
+        return;
+    };
+    let mut saved = OffsetsFile::default();
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let current = offsets_file(&state);
+        if current == saved {
+            continue;
+        }
+        let contents = serde_json::to_vec_pretty(&current).unwrap_or_default();
+        let (target, store) = (path.clone(), state.hooks.store.clone());
+        match runtime::spawn_disk_blocking(move || store.save(&target, &contents)).await {
+            Ok(Ok(())) => saved = current,
+            Ok(Err(e)) => error!("Cannot write DURABLE_OFFSETS_FILE {}: {}", path, e),
+            Err(e) => error!("DURABLE_OFFSETS_FILE writer failed: {}", e),
+        }
+    }
+}
+
+/// Tulis offset terakhir saat broker berhenti
+pub fn flush(state: &AppState) {
+    if let Some(path) = state.config.durable_offsets_file.as_deref() {
+        let contents = serde_json::to_vec_pretty(&offsets_file(state)).unwrap_or_default();
+        if let Err(e) = state.hooks.store.save(path, &contents) {
+            error!("Cannot write DURABLE_OFFSETS_FILE {}: {}", path, e);
+        }
+    }
+}
+
+/// Handler untuk GET /api/streams/:stream_id/durable
+pub async fn list_durable_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Json<serde_json::Value> {
+    let last_seq = state.streams.lock().get(&stream_id).map(|entry| entry.last_seq());
+    let subscribers: Vec<_> = state
+        .durable
+        .snapshot()
+        .into_iter()
+        .filter(|((stream, _), ..)| *stream == stream_id)
+        .map(|((_, name), seq, connected, seen_at)| {
+            json!({
+                "name": name,
+                "seq": seq,
+                "behind": last_seq.map(|last| last.saturating_sub(seq)),
+                "connected": connected,
+                "seen_at": seen_at,
+            })
+        })
+        .collect();
+    Json(json!({ "stream": stream_id, "last_seq": last_seq, "subscribers": subscribers }))
+}
+
+/// Handler untuk DELETE /api/streams/:stream_id/durable/:name
+/// Lupakan posisi subscriber; koneksi berikutnya dengan nama ini mulai dari live
+pub async fn delete_durable_handler(
+    AxumPath((stream_id, name)): AxumPath<(String, String)>,
+    State(state): State<AppState>,
+) -> Result<StatusCode, BrokerError> {
+    let mut cursors = state.durable.cursors.lock();
+    let key = (stream_id, name);
+    match cursors.get(&key) {
+        None => Err(BrokerError::not_found("durable subscription", &key.1)),
+        Some(cursor) if cursor.connected.load(Ordering::Acquire) => Err(BrokerError::conflict(format!(
+            "durable subscription {} is connected",
+            key.1
+        ))),
+        Some(_) => {
+            cursors.remove(&key);
+            info!("Removed durable subscription {} on stream {}", key.1, key.0);
+            Ok(StatusCode::NO_CONTENT)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use bytes::Bytes;
+
+    #[test]
+    fn test_offsets_survive_restart() {
+        let path = std::env::temp_dir().join(format!("bsb-durable-{}.json", std::process::id()));
+        let config = Config {
+            durable_offsets_file: Some(path.to_string_lossy().into_owned()),
+            ..Config::default()
+        };
+        let state = AppState::new(config.clone());
+        state.with_stream("cam1", |entry| {
+            for _ in 0..5 {
+                let _ = entry.publish(Bytes::from_static(b"frame"));
+            }
+        });
+        // Subscription baru mulai dari posisi saat itu; satu koneksi per nama
+        let (guard, resume_from) = state.durable.attach("cam1", "recorder", 2).unwrap();
+        assert_eq!(resume_from, 2);
+        assert!(state.durable.attach("cam1", "recorder", 5).is_err());
+        guard.record(4);
+        guard.record(3);
+        drop(guard);
+        flush(&state);
+
+        // Broker baru: offset dan nomor urut stream dilanjutkan dari file
+        let restarted = AppState::new(config);
+        restore(&restarted).unwrap();
+        let next_seq = restarted.with_stream("cam1", |entry| {
+            let _ = entry.publish(Bytes::from_static(b"frame"));
+            entry.last_seq()
+        });
+        assert_eq!(next_seq, 6);
+        let (_guard, resume_from) = restarted.durable.attach("cam1", "recorder", 0).unwrap();
+        assert_eq!(resume_from, 4);
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,206 @@
+use std::{collections::VecDeque, time::Instant};
+use tracing::warn;
+
+use crate::{
+    registry::Frame,
+    spill::{SpillBuffer, SpillRef},
+};
+
+/// Ring buffer berisi frame terakhir dari sebuah stream (DVR buffer)
+///
+/// Dipakai untuk replay ke subscriber yang reconnect dengan `resume_from`.
+#[derive(Debug)]
+pub struct DvrBuffer {
+    frames: VecDeque<Frame>,
+    capacity: usize,
+    /// Frame yang dibuang dari buffer ditulis ke disk (`DVR_SPILL_MAX_BYTES`)
+    spill: Option<SpillBuffer>,
+    /// Frames after this seq are not acked by every ack subscriber yet and are kept
+    /// beyond `capacity`, up to `max_retained` frames
+    retain_after: Option<u64>,
+    max_retained: usize,
+}
+
+impl DvrBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+            spill: None,
+            retain_after: None,
+            max_retained: 0,
+        }
+    }
+
+    pub fn set_spill(&mut self, spill: SpillBuffer) {
+        self.spill = Some(spill);
+    }
+
+    /// Simpan frame baru, buang frame tertua jika buffer penuh
+    pub fn push(&mut self, frame: Frame) {
+        if self.capacity == 0 && self.retain_after.is_none() {
+            return;
+        }
+        self.frames.push_back(frame);
+        self.evict();
+    }
+
+    /// Tahan frame setelah `acked_seq` (ack subscriber), paling banyak `max_frames`; `None` melepas
+    pub fn set_retention(&mut self, acked_seq: Option<u64>, max_frames: usize) {
+        self.retain_after = acked_seq;
+        self.max_retained = max_frames;
+        self.evict();
+    }
+
+    /// Buang frame tertua di atas kapasitas, kecuali yang belum di-ack (sampai `max_retained`)
+    ///
+    /// Frames evicted past `max_retained` are lost to ack subscribers unless the
+    /// spill catches them.
+    fn evict(&mut self) {
+        while self.frames.len() > self.capacity {
+            let unacked = self
+                .retain_after
+                .is_some_and(|acked| self.frames.front().is_some_and(|f| f.seq > acked));
+            if unacked && self.frames.len() <= self.max_retained.max(self.capacity) {
+                break;
+            }
+            let evicted = self.frames.pop_front().expect("buffer is not empty");
+            if let Some(spill) = self.spill.as_mut() {
+                if let Err(e) = spill.append(&evicted) {
+                    // Disk penuh/tidak bisa ditulis: berhenti spill, DVR tetap jalan
+                    warn!("Disabling DVR spill after write error: {}", e);
+                    self.spill = None;
+                }
+            }
+        }
+    }
+
+    /// Retained frames with a sequence number greater than `after_seq`
+    pub fn since(&self, after_seq: u64) -> Vec<Frame> {
+        // Frames are stored in ascending seq order, so we can skip from the front
+        let start = self.frames.partition_point(|f| f.seq <= after_seq);
+        self.frames.range(start..).cloned().collect()
+    }
+
+    /// Spilled frames with a sequence number greater than `after_seq`, older
+    /// than everything still in memory; read them with [`crate::spill::read`]
+    pub fn spilled_since(&mut self, after_seq: u64) -> Vec<SpillRef> {
+        let Some(spill) = self.spill.as_mut() else {
+            return Vec::new();
+        };
+        spill.since(after_seq).unwrap_or_else(|e| {
+            warn!("Cannot read DVR spill: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// Byte payload frame yang masih ada di memori (`MAX_BUFFER_BYTES`)
+    pub fn memory_bytes(&self) -> usize {
+        self.frames.iter().map(|frame| frame.data.len()).sum()
+    }
+
+    /// Lepaskan semua frame di memori (stream di-shed); spill di disk tetap ada
+    pub fn clear(&mut self) {
+        self.frames = VecDeque::new();
+    }
+
+    /// Frame yang ada di memori (bisa melebihi `capacity` demi ack subscriber)
+    pub fn buffered(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Seq frame tertua di memori
+    pub fn first_seq(&self) -> Option<u64> {
+        self.frames.front().map(|frame| frame.seq)
+    }
+
+    pub fn spill_bytes(&self) -> Option<u64> {
+        self.spill.as_ref().map(SpillBuffer::bytes)
+    }
+
+    /// Starting point for a timeshifted subscriber: the newest frame received at
+    /// or before `cutoff`, or just before the oldest retained frame if all are newer
+    pub fn seq_at(&self, cutoff: Option<Instant>) -> Option<u64> {
+        let oldest = self.frames.front()?;
+        let Some(cutoff) = cutoff else {
+            return Some(oldest.seq - 1);
+        };
+        let idx = self.frames.partition_point(|f| f.received_at <= cutoff);
+        Some(match idx {
+            0 => oldest.seq - 1,
+            _ => self.frames[idx - 1].seq,
+        })
+    }
+
+    /// Frames after `after_seq` received at or before `cutoff`, plus the receive
+    /// time of the first frame that is not due yet
+    pub fn due(&self, after_seq: u64, cutoff: Option<Instant>) -> (Vec<Frame>, Option<Instant>) {
+        let start = self.frames.partition_point(|f| f.seq <= after_seq);
+        let mut due = Vec::new();
+        for frame in self.frames.range(start..) {
+            match cutoff {
+                Some(cutoff) if frame.received_at <= cutoff => due.push(frame.clone()),
+                _ => return (due, Some(frame.received_at)),
+            }
+        }
+        (due, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    fn frame(seq: u64) -> Frame {
+        Frame {
+            seq,
+            data: Bytes::from(seq.to_string()),
+            received_at: Instant::now(),
+            producer_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_dvr_evicts_oldest() {
+        let mut dvr = DvrBuffer::new(3);
+        for seq in 1..=5 {
+            dvr.push(frame(seq));
+        }
+        assert_eq!(dvr.since(0).len(), 3);
+        let seqs: Vec<u64> = dvr.since(3).iter().map(|f| f.seq).collect();
+        assert_eq!(seqs, vec![4, 5]);
+
+        // Frame yang belum di-ack ditahan melebihi kapasitas, sampai batas max_retained
+        dvr.set_retention(Some(5), 5);
+        for seq in 6..=11 {
+            dvr.push(frame(seq));
+        }
+        assert_eq!(dvr.since(0).first().map(|f| f.seq), Some(7));
+        dvr.set_retention(Some(9), 5);
+        assert_eq!(dvr.since(0).first().map(|f| f.seq), Some(9));
+    }
+
+    #[test]
+    fn test_dvr_due_respects_cutoff() {
+        let mut dvr = DvrBuffer::new(8);
+        let base = Instant::now();
+        for seq in 1..=4 {
+            dvr.push(Frame {
+                received_at: base + std::time::Duration::from_secs(seq),
+                ..frame(seq)
+            });
+        }
+        let cutoff = Some(base + std::time::Duration::from_secs(2));
+        assert_eq!(dvr.seq_at(cutoff), Some(2));
+        assert_eq!(dvr.seq_at(None), Some(0));
+
+        let (due, next) = dvr.due(0, cutoff);
+        assert_eq!(due.iter().map(|f| f.seq).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(next, Some(base + std::time::Duration::from_secs(3)));
+    }
+}
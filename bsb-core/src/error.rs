@@ -0,0 +1,275 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ws::close_code, ws::CloseFrame, Request},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::{json, Value};
+use std::{any::Any, fmt, io};
+use tracing::error;
+
+use crate::supervisor;
+
+/// Body error yang lebih besar dari ini tidak disalin ke `message`
+const MAX_PLAIN_ERROR_BYTES: usize = 4096;
+
+/// Error broker yang bisa dikembalikan ke client
+///
+/// REST endpoints answer with `{"code", "message", "details"}`; WebSocket
+/// connections that are already upgraded get the matching close frame.
+#[derive(Debug)]
+pub enum BrokerError {
+    /// Query, path or body is malformed or out of range
+    InvalidRequest(String),
+    /// Missing or unknown credential
+    Unauthorized(String),
+    /// The credential is valid but not for this stream
+    Forbidden(String),
+    StreamNotFound(String),
+    /// Anything else addressed by name or id (group, job, tap, ...)
+    NotFound { resource: &'static str, id: String },
+    /// The resource is in a state that does not allow the operation
+    Conflict { message: String, details: Option<Value> },
+    /// The stream was stopped (`max_duration_secs` / `stop_at`)
+    StreamEnded(String),
+    /// A result that existed is no longer available (e.g. a deleted file)
+    Gone(String),
+    /// A frame was rejected (checksum mismatch, malformed Opus packet, undecodable JPEG)
+    InvalidFrame(String),
+    /// A frame's payload format is not allowed on its stream (`STREAM_FORMATS`)
+    UnsupportedFormat(String),
+    /// A playback token is over its concurrent session limit
+    SessionLimit(String),
+    /// Ingest is paused (maintenance / drain)
+    Unavailable(String),
+    Io(io::Error),
+    Internal(String),
+}
+
+impl BrokerError {
+    pub fn not_found(resource: &'static str, id: impl fmt::Display) -> Self {
+        BrokerError::NotFound {
+            resource,
+            id: id.to_string(),
+        }
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        BrokerError::Conflict {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            BrokerError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            BrokerError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            BrokerError::Forbidden(_) => StatusCode::FORBIDDEN,
+            BrokerError::StreamNotFound(_) | BrokerError::NotFound { .. } => StatusCode::NOT_FOUND,
+            BrokerError::Conflict { .. } => StatusCode::CONFLICT,
+            BrokerError::StreamEnded(_) | BrokerError::Gone(_) => StatusCode::GONE,
+            BrokerError::InvalidFrame(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            BrokerError::UnsupportedFormat(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            BrokerError::SessionLimit(_) => StatusCode::TOO_MANY_REQUESTS,
+            BrokerError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            BrokerError::Io(e) if e.kind() == io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            BrokerError::Io(_) | BrokerError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Kode stabil untuk client (`snake_case`)
+    pub fn code(&self) -> &'static str {
+        match self {
+            BrokerError::InvalidRequest(_) => "invalid_request",
+            BrokerError::Unauthorized(_) => "unauthorized",
+            BrokerError::Forbidden(_) => "forbidden",
+            BrokerError::StreamNotFound(_) => "stream_not_found",
+            BrokerError::NotFound { .. } => "not_found",
+            BrokerError::Conflict { .. } => "conflict",
+            BrokerError::StreamEnded(_) => "stream_ended",
+            BrokerError::Gone(_) => "gone",
+            BrokerError::InvalidFrame(_) => "invalid_frame",
+            BrokerError::UnsupportedFormat(_) => "unsupported_format",
+            BrokerError::SessionLimit(_) => "session_limit",
+            BrokerError::Unavailable(_) => "unavailable",
+            BrokerError::Io(_) => "io_error",
+            BrokerError::Internal(_) => "internal",
+        }
+    }
+
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            BrokerError::StreamNotFound(stream) | BrokerError::StreamEnded(stream) => {
+                Some(json!({ "stream": stream }))
+            }
+            BrokerError::NotFound { resource, id } => Some(json!({ "resource": resource, "id": id })),
+            BrokerError::Conflict { details, .. } => details.clone(),
+            _ => None,
+        }
+    }
+
+    /// Close frame untuk koneksi WebSocket yang sudah di-upgrade
+    pub fn close_frame(&self) -> CloseFrame<'static> {
+        let code = match self {
+            BrokerError::Unauthorized(_) | BrokerError::Forbidden(_) | BrokerError::SessionLimit(_) => {
+                close_code::POLICY
+            }
+            BrokerError::InvalidRequest(_) | BrokerError::InvalidFrame(_) => close_code::INVALID,
+            BrokerError::UnsupportedFormat(_) => close_code::UNSUPPORTED,
+            BrokerError::StreamEnded(_) => close_code::NORMAL,
+            BrokerError::Unavailable(_) => close_code::AGAIN,
+            _ => close_code::ERROR,
+        };
+        // Reason close frame dibatasi 123 byte
+        let mut reason = self.to_string();
+        while reason.len() > 123 {
+            reason.pop();
+        }
+        CloseFrame {
+            code,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for BrokerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BrokerError::StreamNotFound(stream) => write!(f, "stream not found: {}", stream),
+            BrokerError::NotFound { resource, id } => write!(f, "{} not found: {}", resource, id),
+            BrokerError::Conflict { message, .. } => f.write_str(message),
+            BrokerError::StreamEnded(stream) => write!(f, "stream has been stopped: {}", stream),
+            BrokerError::Io(e) => write!(f, "{}", e),
+            BrokerError::InvalidRequest(message)
+            | BrokerError::Unauthorized(message)
+            | BrokerError::Forbidden(message)
+            | BrokerError::Gone(message)
+            | BrokerError::InvalidFrame(message)
+            | BrokerError::UnsupportedFormat(message)
+            | BrokerError::SessionLimit(message)
+            | BrokerError::Unavailable(message)
+            | BrokerError::Internal(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for BrokerError {}
+
+impl From<io::Error> for BrokerError {
+    fn from(e: io::Error) -> Self {
+        BrokerError::Io(e)
+    }
+}
+
+impl From<tokio::task::JoinError> for BrokerError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        BrokerError::Internal(e.to_string())
+    }
+}
+
+impl IntoResponse for BrokerError {
+    fn into_response(self) -> Response {
+        let body = json!({
+            "code": self.code(),
+            "message": self.to_string(),
+            "details": self.details(),
+        });
+        (self.status(), Json(body)).into_response()
+    }
+}
+
+/// Response untuk handler yang panic; koneksi lain dan state broker tidak terpengaruh
+pub fn handler_panicked(panic: Box<dyn Any + Send + 'static>) -> Response {
+    let message = supervisor::panic_message(panic.as_ref());
+    error!("Request handler panicked: {}", message);
+    BrokerError::Internal("request handler panicked".to_string()).into_response()
+}
+
+/// Kode error untuk status yang dibuat di luar handler (extractor, body limit, routing)
+fn code_for_status(status: StatusCode) -> String {
+    match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNSUPPORTED_MEDIA_TYPE => "invalid_request".to_string(),
+        StatusCode::UNPROCESSABLE_ENTITY => "invalid_request".to_string(),
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large".to_string(),
+        StatusCode::INTERNAL_SERVER_ERROR => "internal".to_string(),
+        status => status
+            .canonical_reason()
+            .unwrap_or("error")
+            .to_ascii_lowercase()
+            .replace([' ', '-'], "_"),
+    }
+}
+
+/// Middleware: response error yang bukan JSON (rejection extractor, body limit,
+/// 404/405 routing, upgrade yang ditolak) diubah ke body error standar
+pub async fn json_errors(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !(status.is_client_error() || status.is_server_error()) || is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let text = to_bytes(body, MAX_PLAIN_ERROR_BYTES)
+        .await
+        .map(|bytes| String::from_utf8_lossy(&bytes).trim().to_string())
+        .unwrap_or_default();
+    let message = if text.is_empty() {
+        status.canonical_reason().unwrap_or("error").to_string()
+    } else {
+        text
+    };
+    let body = json!({
+        "code": code_for_status(status),
+        "message": message,
+        "details": null,
+    });
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    Response::from_parts(parts, Body::from(body.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn body_json(response: Response) -> Value {
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_error_bodies_are_consistent() {
+        let response = BrokerError::not_found("group", "site-A").into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = body_json(response).await;
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["message"], "group not found: site-A");
+        assert_eq!(body["details"]["id"], "site-A");
+
+        let io = BrokerError::from(io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert_eq!(io.status(), StatusCode::NOT_FOUND);
+        assert_eq!(BrokerError::SessionLimit("full".into()).close_frame().code, close_code::POLICY);
+
+        // Response plain-text dari luar handler ikut dibungkus
+        let app = Router::new()
+            .route("/plain", get(|| async { (StatusCode::BAD_REQUEST, "bad query") }))
+            .layer(middleware::from_fn(json_errors));
+        let request = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let body = body_json(app.clone().oneshot(request("/plain")).await.unwrap()).await;
+        assert_eq!(body, json!({ "code": "invalid_request", "message": "bad query", "details": null }));
+        let response = app.oneshot(request("/missing")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body_json(response).await["code"], "not_found");
+    }
+}
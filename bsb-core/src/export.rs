@@ -0,0 +1,416 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncWriteExt, BufReader},
+    process::{Child, ChildStdin, Command},
+};
+use tracing::{info, warn};
+
+use crate::{
+    byterange,
+    error::BrokerError,
+    recordings::SearchClock,
+    segment::{self, IndexedSegment, RECORD_HEADER_LEN},
+    runtime, AppState,
+};
+
+/// Container hasil export
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Mp4,
+    Mkv,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Mp4 => "mp4",
+            ExportFormat::Mkv => "mkv",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Mp4 => "video/mp4",
+            ExportFormat::Mkv => "video/x-matroska",
+        }
+    }
+}
+
+/// Body untuk POST /api/recordings/:stream_id/export
+#[derive(Debug, Deserialize)]
+pub struct ExportRequest {
+    /// Unix ms, inklusif
+    pub from_ms: u64,
+    /// Unix ms, inklusif
+    pub to_ms: u64,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase", tag = "state")]
+pub enum JobState {
+    Running,
+    Done,
+    Failed { error: String },
+}
+
+/// Satu job export di background
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportJob {
+    pub id: u64,
+    pub stream: String,
+    pub from_ms: u64,
+    pub to_ms: u64,
+    pub format: ExportFormat,
+    #[serde(flatten)]
+    pub state: JobState,
+    /// Byte segmen yang sudah dibaca / total byte segmen dalam rentang
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub frames: u64,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl ExportJob {
+    fn to_json(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).expect("job serializes");
+        let progress = match self.state {
+            JobState::Done => 1.0,
+            _ if self.bytes_total == 0 => 0.0,
+            _ => self.bytes_done as f64 / self.bytes_total as f64,
+        };
+        value["progress"] = progress.into();
+        value
+    }
+}
+
+/// Job export yang diketahui sejak broker start (hasilnya tetap di `EXPORT_DIR` sampai dihapus)
+#[derive(Debug, Default)]
+pub struct ExportJobs {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<u64, ExportJob>>,
+}
+
+impl ExportJobs {
+    fn update(&self, id: u64, f: impl FnOnce(&mut ExportJob)) {
+        if let Some(job) = self.jobs.lock().get_mut(&id) {
+            f(job);
+        }
+    }
+
+    fn get(&self, stream_id: &str, id: u64) -> Option<ExportJob> {
+        self.jobs
+            .lock()
+            .get(&id)
+            .filter(|job| job.stream == stream_id)
+            .cloned()
+    }
+}
+
+/// Frame rate rata-rata segmen yang sudah ditutup (untuk input H.264 mentah tanpa timestamp)
+fn average_fps(segments: &[IndexedSegment]) -> f64 {
+    let closed = segments
+        .iter()
+        .map(|segment| &segment.meta)
+        .filter(|meta| meta.frames > 1 && meta.end_ms > meta.start_ms);
+    let (frames, duration_ms) = closed.fold((0, 0), |(frames, duration_ms), meta| {
+        (frames + meta.frames - 1, duration_ms + meta.end_ms - meta.start_ms)
+    });
+    if frames == 0 {
+        return 25.0;
+    }
+    (frames as f64 * 1000.0 / duration_ms as f64).clamp(1.0, 240.0)
+}
+
+/// Argumen ffmpeg untuk remux tanpa re-encode: MPEG-TS (sync byte 0x47) atau H.264 Annex B
+fn ffmpeg_args(first_payload: &[u8], fps: f64, format: ExportFormat, output: &Path) -> Vec<String> {
+    let mut args: Vec<String> = ["-hide_banner", "-loglevel", "error", "-y"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    if first_payload.first() == Some(&0x47) {
+        args.extend(["-f".into(), "mpegts".into()]);
+    } else {
+        args.extend(["-f".into(), "h264".into(), "-framerate".into(), format!("{:.3}", fps)]);
+    }
+    args.extend(["-i".into(), "pipe:0".into(), "-c".into(), "copy".into()]);
+    match format {
+        ExportFormat::Mp4 => args.extend(["-f".into(), "mp4".into(), "-movflags".into(), "+faststart".into()]),
+        ExportFormat::Mkv => args.extend(["-f".into(), "matroska".into()]),
+    }
+    args.push(output.display().to_string());
+    args
+}
+
+/// Baca record dalam rentang dari semua segmen dan pipe payload-nya ke ffmpeg
+///
+/// ffmpeg is started on the first matching record, since its input format is
+/// sniffed from that payload.
+async fn run_export(state: AppState, job: ExportJob, segments: Vec<IndexedSegment>) -> Result<u64, String> {
+    let dir = segment::stream_dir(&state.config.recordings_dir, &job.stream);
+    let fps = average_fps(&segments);
+    let mut child: Option<(Child, ChildStdin)> = None;
+    let (mut frames, mut records, mut bytes_done) = (0u64, 0u64, 0u64);
+
+    'segments: for segment in &segments {
+        let file = File::open(dir.join(&segment.meta.segment)).await.map_err(|e| e.to_string())?;
+        let mut reader = BufReader::new(file);
+        let mut header = [0u8; RECORD_HEADER_LEN];
+        // Record terakhir segmen yang masih ditulis bisa belum lengkap
+        while reader.read_exact(&mut header).await.is_ok() {
+            let (_, unix_ms, len) = segment::decode_record_header(&header);
+            let mut payload = vec![0; len];
+            if reader.read_exact(&mut payload).await.is_err() {
+                break;
+            }
+            records += 1;
+            bytes_done += (RECORD_HEADER_LEN + len) as u64;
+            if records % 100 == 0 {
+                state.exports.update(job.id, |job| {
+                    job.bytes_done = bytes_done;
+                    job.frames = frames;
+                });
+            }
+            if !(job.from_ms..=job.to_ms).contains(&unix_ms) {
+                continue;
+            }
+            if child.is_none() {
+                child = Some(spawn_ffmpeg(&state, &job, &payload, fps).await?);
+            }
+            let (_, stdin) = child.as_mut().expect("spawned above");
+            // ffmpeg yang gagal menutup stdin lebih awal; error-nya dilaporkan dari stderr
+            if stdin.write_all(&payload).await.is_err() {
+                break 'segments;
+            }
+            frames += 1;
+        }
+    }
+
+    let Some((process, stdin)) = child else {
+        return Err("no recorded frames in the requested range".to_string());
+    };
+    state.exports.update(job.id, |job| job.frames = frames);
+    drop(stdin);
+    let output = process.wait_with_output().await.map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let _ = tokio::fs::remove_file(&job.path).await;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffmpeg failed ({}): {}", output.status, stderr.trim()));
+    }
+    Ok(frames)
+}
+
+async fn spawn_ffmpeg(
+    state: &AppState,
+    job: &ExportJob,
+    first_payload: &[u8],
+    fps: f64,
+) -> Result<(Child, ChildStdin), String> {
+    tokio::fs::create_dir_all(&state.config.export_dir)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut child = Command::new(&state.config.ffmpeg_path)
+        .args(ffmpeg_args(first_payload, fps, job.format, &job.path))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("cannot run {}: {}", state.config.ffmpeg_path, e))?;
+    let stdin = child.stdin.take().expect("stdin is piped");
+    Ok((child, stdin))
+}
+
+/// Handler untuk POST /api/recordings/:stream_id/export
+pub async fn start_export_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+    Json(request): Json<ExportRequest>,
+) -> Response {
+    if request.from_ms > request.to_ms {
+        return BrokerError::InvalidRequest("from_ms must not be after to_ms".into()).into_response();
+    }
+    let search_state = state.clone();
+    let search_stream = stream_id.clone();
+    let search = runtime::spawn_disk_blocking(move || {
+        search_state.recordings.search(
+            &search_state.config.recordings_dir,
+            &search_stream,
+            request.from_ms,
+            request.to_ms,
+            SearchClock::Wall,
+        )
+    });
+    let segments = match search.await {
+        Ok(Ok(segments)) => segments,
+        // Stream yang belum pernah direkam: sama dengan rentang kosong
+        Ok(Err(e)) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Ok(Err(e)) => return BrokerError::from(e).into_response(),
+        Err(e) => return BrokerError::from(e).into_response(),
+    };
+    if segments.is_empty() {
+        let error = BrokerError::not_found("recordings", format!("{} in the requested range", stream_id));
+        return error.into_response();
+    }
+
+    let id = state.exports.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+    let path = Path::new(&state.config.export_dir).join(format!(
+        "{}-{}.{}",
+        crate::federation::encode_path_segment(&stream_id),
+        id,
+        request.format.extension()
+    ));
+    let job = ExportJob {
+        id,
+        stream: stream_id.clone(),
+        from_ms: request.from_ms,
+        to_ms: request.to_ms,
+        format: request.format,
+        state: JobState::Running,
+        bytes_done: 0,
+        bytes_total: segments.iter().map(|segment| segment.meta.bytes).sum(),
+        frames: 0,
+        path,
+    };
+    state.exports.jobs.lock().insert(id, job.clone());
+    info!("Export job {} started for {} ({} segments)", id, stream_id, segments.len());
+
+    let response = job.to_json();
+    let task_state = state.clone();
+    runtime::spawn_disk(async move {
+        let result = run_export(task_state.clone(), job, segments).await;
+        task_state.exports.update(id, |job| match result {
+            Ok(frames) => {
+                info!("Export job {} finished: {} frames", id, frames);
+                job.bytes_done = job.bytes_total;
+                job.state = JobState::Done;
+            }
+            Err(error) => {
+                warn!("Export job {} failed: {}", id, error);
+                job.state = JobState::Failed { error };
+            }
+        });
+    });
+    (
+        StatusCode::ACCEPTED,
+        [(
+            header::LOCATION,
+            format!("/api/recordings/{}/exports/{}", crate::federation::encode_path_segment(&stream_id), id),
+        )],
+        Json(response),
+    )
+        .into_response()
+}
+
+fn job_not_found(id: u64) -> Response {
+    BrokerError::not_found("export job", id).into_response()
+}
+
+/// Handler untuk GET /api/recordings/:stream_id/exports/:id
+pub async fn get_export_handler(
+    AxumPath((stream_id, id)): AxumPath<(String, u64)>,
+    State(state): State<AppState>,
+) -> Response {
+    match state.exports.get(&stream_id, id) {
+        Some(job) => Json(job.to_json()).into_response(),
+        None => job_not_found(id),
+    }
+}
+
+/// Handler untuk GET /api/recordings/:stream_id/exports/:id/download
+pub async fn download_export_handler(
+    AxumPath((stream_id, id)): AxumPath<(String, u64)>,
+    State(state): State<AppState>,
+    request_headers: HeaderMap,
+) -> Response {
+    let Some(job) = state.exports.get(&stream_id, id) else {
+        return job_not_found(id);
+    };
+    if job.state != JobState::Done {
+        let error = BrokerError::Conflict {
+            message: "export is not finished".to_string(),
+            details: Some(json!({ "job": job.to_json() })),
+        };
+        return error.into_response();
+    }
+    let file = match File::open(&job.path).await {
+        Ok(file) => file,
+        Err(e) => {
+            return BrokerError::Gone(format!("export file is gone: {}", e)).into_response()
+        }
+    };
+    let filename = job.path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    byterange::serve_file(file, job.format.content_type(), &filename, &request_headers)
+        .await
+        .unwrap_or_else(IntoResponse::into_response)
+}
+
+/// Handler untuk DELETE /api/recordings/:stream_id/exports/:id (hapus job dan file hasilnya)
+pub async fn delete_export_handler(
+    AxumPath((stream_id, id)): AxumPath<(String, u64)>,
+    State(state): State<AppState>,
+) -> Response {
+    let removed = {
+        let mut jobs = state.exports.jobs.lock();
+        match jobs.get(&id) {
+            Some(job) if job.stream == stream_id && job.state != JobState::Running => jobs.remove(&id),
+            Some(job) if job.stream == stream_id => {
+                return BrokerError::conflict("export is still running").into_response()
+            }
+            _ => None,
+        }
+    };
+    let Some(job) = removed else {
+        return job_not_found(id);
+    };
+    let _ = tokio::fs::remove_file(&job.path).await;
+    StatusCode::NO_CONTENT.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment::SegmentMeta;
+
+    #[test]
+    fn test_average_fps_and_ffmpeg_args() {
+        let segment = |frames, end_ms, in_progress| IndexedSegment {
+            meta: SegmentMeta {
+                start_ms: 1000,
+                end_ms,
+                frames,
+                ..SegmentMeta::default()
+            },
+            in_progress,
+        };
+        // 31 frame dalam 1 detik -> 30 fps; segmen tanpa cukup frame diabaikan
+        assert_eq!(average_fps(&[segment(31, 2000, false), segment(0, 1000, true)]), 30.0);
+        assert_eq!(average_fps(&[segment(0, 1000, true)]), 25.0);
+
+        let ts = ffmpeg_args(&[0x47, 0x40], 30.0, ExportFormat::Mkv, Path::new("out.mkv"));
+        assert_eq!(&ts[4..6], ["-f", "mpegts"]);
+        assert_eq!(ts.last().map(String::as_str), Some("out.mkv"));
+        let h264 = ffmpeg_args(&[0, 0, 0, 1, 0x67], 30.0, ExportFormat::Mp4, Path::new("out.mp4"));
+        assert_eq!(&h264[4..8], ["-f", "h264", "-framerate", "30.000"]);
+        assert!(h264.contains(&"+faststart".to_string()));
+    }
+}
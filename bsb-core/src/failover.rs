@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Peran producer untuk sebuah stream (`POST /ingest/:id?source=backup`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceRole {
+    Primary,
+    Backup,
+}
+
+/// Keputusan untuk satu frame dari producer berlabel
+#[derive(Debug, PartialEq, Eq)]
+pub enum Admission {
+    /// Frame comes from the active source
+    Forward,
+    /// The active source changed to this role; forward the frame and notify subscribers
+    Switched(SourceRole),
+    /// Frame comes from the standby source and is discarded
+    Standby,
+}
+
+/// Primary/backup selection for one stream
+///
+/// The primary is active by default. Once it has been silent for the failover
+/// timeout, the next backup frame takes over; the next primary frame switches back.
+#[derive(Debug)]
+pub struct FailoverState {
+    active: SourceRole,
+    last_primary: Instant,
+}
+
+impl Default for FailoverState {
+    fn default() -> Self {
+        Self {
+            active: SourceRole::Primary,
+            last_primary: Instant::now(),
+        }
+    }
+}
+
+impl FailoverState {
+    pub fn admit(&mut self, role: SourceRole, now: Instant, timeout: Duration) -> Admission {
+        match (role, self.active) {
+            (SourceRole::Primary, SourceRole::Primary) => {
+                self.last_primary = now;
+                Admission::Forward
+            }
+            (SourceRole::Primary, SourceRole::Backup) => {
+                self.last_primary = now;
+                self.active = SourceRole::Primary;
+                Admission::Switched(SourceRole::Primary)
+            }
+            (SourceRole::Backup, SourceRole::Backup) => Admission::Forward,
+            (SourceRole::Backup, SourceRole::Primary) => {
+                if now.duration_since(self.last_primary) >= timeout {
+                    self.active = SourceRole::Backup;
+                    Admission::Switched(SourceRole::Backup)
+                } else {
+                    Admission::Standby
+                }
+            }
+        }
+    }
+
+    pub fn active(&self) -> SourceRole {
+        self.active
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failover_and_recovery() {
+        let timeout = Duration::from_secs(5);
+        let start = Instant::now();
+        let mut state = FailoverState::default();
+
+        assert_eq!(state.admit(SourceRole::Primary, start, timeout), Admission::Forward);
+        let t = start + Duration::from_secs(2);
+        assert_eq!(state.admit(SourceRole::Backup, t, timeout), Admission::Standby);
+
+        let t = start + Duration::from_secs(6);
+        assert_eq!(
+            state.admit(SourceRole::Backup, t, timeout),
+            Admission::Switched(SourceRole::Backup)
+        );
+        assert_eq!(state.admit(SourceRole::Backup, t, timeout), Admission::Forward);
+
+        assert_eq!(
+            state.admit(SourceRole::Primary, t, timeout),
+            Admission::Switched(SourceRole::Primary)
+        );
+        assert_eq!(state.active(), SourceRole::Primary);
+    }
+}
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::stats::StreamCounters;
+
+/// Pengaturan penjadwal fan-out dari `FANOUT_*`
+#[derive(Debug, Clone, Copy)]
+pub struct FairnessConfig {
+    /// Subscribers of one stream that may be writing a frame at the same time (0 = unlimited)
+    pub max_concurrent_sends: usize,
+    /// Frames a subscriber sends back-to-back from its queue before yielding the worker
+    pub burst_frames: u32,
+}
+
+impl Default for FairnessConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_sends: 0,
+            burst_frames: 8,
+        }
+    }
+}
+
+/// Penjadwal fan-out per stream: subscriber bergiliran menulis frame
+///
+/// Every subscriber task takes a turn before each write. Turns are handed out
+/// in FIFO order, so a subscriber that just wrote queues behind the ones
+/// already waiting (round-robin), and at most `max_concurrent_sends` writes of
+/// one stream run at once. A subscriber on a fast link that drains a backlog
+/// also yields its worker thread every `burst_frames` frames, bounding the work
+/// it does per scheduler tick.
+#[derive(Debug)]
+pub struct FairScheduler {
+    turns: Option<Arc<Semaphore>>,
+    burst_frames: u32,
+}
+
+impl FairScheduler {
+    pub fn new(config: FairnessConfig) -> Self {
+        Self {
+            turns: (config.max_concurrent_sends > 0).then(|| Arc::new(Semaphore::new(config.max_concurrent_sends))),
+            burst_frames: config.burst_frames.max(1),
+        }
+    }
+}
+
+impl Default for FairScheduler {
+    fn default() -> Self {
+        Self::new(FairnessConfig::default())
+    }
+}
+
+/// Giliran satu subscriber di [`FairScheduler`] stream-nya
+pub struct SubscriberTurns {
+    scheduler: Arc<FairScheduler>,
+    counters: Arc<StreamCounters>,
+    /// Frames written without the queue running empty
+    streak: u32,
+}
+
+impl SubscriberTurns {
+    pub fn new(scheduler: Arc<FairScheduler>, counters: Arc<StreamCounters>) -> Self {
+        Self {
+            scheduler,
+            counters,
+            streak: 0,
+        }
+    }
+
+    /// Tunggu giliran menulis satu frame; giliran dilepas saat permit di-drop
+    pub async fn turn(&self) -> Option<OwnedSemaphorePermit> {
+        let turns = self.scheduler.turns.clone()?;
+        match turns.clone().try_acquire_owned() {
+            Ok(permit) => Some(permit),
+            Err(_) => {
+                self.counters.record_fanout_wait();
+                turns.acquire_owned().await.ok()
+            }
+        }
+    }
+
+    /// Catat satu frame terkirim; `backlog` = masih ada frame mengantri untuk subscriber ini
+    pub async fn sent(&mut self, backlog: bool) {
+        if !backlog {
+            self.streak = 0;
+            return;
+        }
+        self.streak += 1;
+        if self.streak >= self.scheduler.burst_frames {
+            self.streak = 0;
+            self.counters.record_fanout_yield();
+            tokio::task::yield_now().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::Ordering;
+
+    #[tokio::test]
+    async fn test_turns_are_bounded_and_bursts_yield() {
+        let scheduler = Arc::new(FairScheduler::new(FairnessConfig {
+            max_concurrent_sends: 1,
+            burst_frames: 3,
+        }));
+        let counters = Arc::new(StreamCounters::default());
+        let mut fast = SubscriberTurns::new(scheduler.clone(), counters.clone());
+        let slow = SubscriberTurns::new(scheduler.clone(), counters.clone());
+
+        // Satu penulis per stream: yang kedua menunggu sampai giliran pertama dilepas
+        let turn = fast.turn().await;
+        assert!(turn.is_some());
+        let waiting = tokio::spawn(async move { slow.turn().await.is_some() });
+        tokio::task::yield_now().await;
+        assert_eq!(counters.fanout_waits.load(Ordering::Relaxed), 1);
+        drop(turn);
+        assert!(waiting.await.unwrap());
+
+        // Backlog dikuras: yield setiap 3 frame, streak direset saat antrian kosong
+        for _ in 0..7 {
+            fast.sent(true).await;
+        }
+        assert_eq!(counters.fanout_yields.load(Ordering::Relaxed), 2);
+        fast.sent(false).await;
+        fast.sent(true).await;
+        fast.sent(true).await;
+        assert_eq!(counters.fanout_yields.load(Ordering::Relaxed), 2);
+
+        // Tanpa batas: giliran langsung diberikan
+        let unlimited = SubscriberTurns::new(Arc::new(FairScheduler::default()), counters);
+        assert!(unlimited.turn().await.is_none());
+    }
+}
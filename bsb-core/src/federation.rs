@@ -0,0 +1,252 @@
+use axum::{
+    extract::{ws::WebSocketUpgrade, Path as AxumPath, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use futures_util::StreamExt;
+use parking_lot::Mutex;
+use std::{collections::HashSet, time::Duration};
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, Message as WsMessage},
+    Connector,
+};
+use tracing::{info, warn};
+
+use crate::{error::BrokerError, outbound, supervisor, tls, ws, AppState, SubscribeParams};
+
+/// Broker lain yang stream-nya bisa diakses lewat prefix namespace lokal
+#[derive(Clone)]
+pub struct FederationPeer {
+    /// Local namespace, e.g. `remote-a` for streams addressed as `remote-a/cam1`
+    pub prefix: String,
+    /// Base URL of the peer, e.g. `wss://broker-a.example:3090`
+    pub url: String,
+    token: String,
+}
+
+impl std::fmt::Debug for FederationPeer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FederationPeer")
+            .field("prefix", &self.prefix)
+            .field("url", &self.url)
+            .finish()
+    }
+}
+
+/// Peers parsed from `FEDERATION_PEERS`
+///
+/// Format: `prefix=url|token;prefix=url|token`, e.g.
+/// `remote-a=wss://broker-a.example:3090|s3cret`.
+#[derive(Debug, Clone, Default)]
+pub struct FederationPeers {
+    peers: Vec<FederationPeer>,
+}
+
+impl FederationPeers {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut peers = Vec::new();
+        for rule in spec.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            let invalid = || format!("Invalid FEDERATION_PEERS entry: {}", rule);
+            let (prefix, target) = rule.split_once('=').ok_or_else(invalid)?;
+            let (url, token) = target.rsplit_once('|').ok_or_else(invalid)?;
+            let (prefix, url) = (prefix.trim().trim_end_matches('/'), url.trim());
+            if prefix.is_empty() || !(url.starts_with("ws://") || url.starts_with("wss://")) {
+                return Err(invalid());
+            }
+            peers.push(FederationPeer {
+                prefix: prefix.to_string(),
+                url: url.trim_end_matches('/').to_string(),
+                token: token.trim().to_string(),
+            });
+        }
+        Ok(Self { peers })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    /// Peer dan stream ID remote untuk stream lokal `prefix/remote-id`
+    pub fn route<'a>(&self, stream_id: &'a str) -> Option<(&FederationPeer, &'a str)> {
+        self.peers.iter().find_map(|peer| {
+            let remote_id = stream_id.strip_prefix(peer.prefix.as_str())?.strip_prefix('/')?;
+            (!remote_id.is_empty()).then_some((peer, remote_id))
+        })
+    }
+}
+
+/// Stream lokal yang sedang di-relay dari peer
+#[derive(Debug, Default)]
+pub struct RelayRegistry {
+    active: Mutex<HashSet<String>>,
+}
+
+/// Start relaying `stream_id` from its peer if it is in a federated namespace
+///
+/// Called after a subscriber has subscribed; the relay runs while the local
+/// stream has subscribers.
+pub fn ensure_relay(state: &AppState, stream_id: &str) {
+    let Some((peer, remote_id)) = state.config.federation_peers.route(stream_id) else {
+        return;
+    };
+    if !state.relays.active.lock().insert(stream_id.to_string()) {
+        return;
+    }
+    info!("Starting federation relay {} <- {} ({})", stream_id, remote_id, peer.url);
+    let (state, stream_id, peer, remote_id) = (
+        state.clone(),
+        stream_id.to_string(),
+        peer.clone(),
+        remote_id.to_string(),
+    );
+    supervisor::supervise(format!("federation relay {}", stream_id), move || {
+        run_relay(state.clone(), stream_id.clone(), peer.clone(), remote_id.clone())
+    });
+}
+
+fn has_subscribers(state: &AppState, stream_id: &str) -> bool {
+    state
+        .streams
+        .lock()
+        .get(stream_id)
+        .is_some_and(|entry| entry.tx.receiver_count() > 0)
+}
+
+async fn run_relay(state: AppState, stream_id: String, peer: FederationPeer, remote_id: String) {
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        match relay_once(&state, &stream_id, &peer, &remote_id).await {
+            Ok(()) => backoff = Duration::from_secs(1),
+            Err(e) => warn!("Federation relay for {} failed: {}", stream_id, e),
+        }
+
+        // Cek dan hapus di bawah lock yang sama dengan ensure_relay, supaya subscriber
+        // yang baru datang tidak tertinggal tanpa relay
+        {
+            let mut active = state.relays.active.lock();
+            if !has_subscribers(&state, &stream_id) {
+                active.remove(&stream_id);
+                break;
+            }
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(Duration::from_secs(30));
+    }
+    info!("Federation relay for {} stopped (no subscribers)", stream_id);
+}
+
+/// Satu koneksi ke peer; selesai saat peer menutup koneksi atau subscriber lokal habis
+async fn relay_once(
+    state: &AppState,
+    stream_id: &str,
+    peer: &FederationPeer,
+    remote_id: &str,
+) -> Result<(), String> {
+    let url = format!("{}/federation/{}", peer.url, encode_path_segment(remote_id));
+    let mut request = url.as_str().into_client_request().map_err(|e| e.to_string())?;
+    request.headers_mut().insert(
+        header::AUTHORIZATION,
+        format!("Bearer {}", peer.token)
+            .parse()
+            .map_err(|_| "token is not a valid header value".to_string())?,
+    );
+    let connector = if url.starts_with("wss://") {
+        let ca_path = state
+            .config
+            .federation_ca_path
+            .as_deref()
+            .ok_or("wss:// peers require FEDERATION_CA_PATH")?;
+        Some(Connector::Rustls(tls::client_config(ca_path)?))
+    } else {
+        None
+    };
+
+    let (mut socket, _) = outbound::connect_ws(request, connector).await?;
+    info!("Federation relay connected: {} <- {}", stream_id, url);
+
+    let mut idle_check = tokio::time::interval(Duration::from_secs(5));
+    loop {
+        tokio::select! {
+            msg = socket.next() => match msg {
+                Some(Ok(WsMessage::Binary(data))) => {
+                    let _ = state.with_stream(stream_id, |entry| entry.publish(Bytes::from(data)));
+                }
+                Some(Ok(WsMessage::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.to_string()),
+            },
+            _ = idle_check.tick() => {
+                if !has_subscribers(state, stream_id) {
+                    let _ = socket.close(None).await;
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Percent-encode satu segmen path (stream ID boleh mengandung `/`)
+pub fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Handler untuk GET /federation/:stream_id
+/// WebSocket feed for a peer broker, authenticated with a `FEDERATION_EXPORTS` token
+pub async fn federation_handler(
+    ws: WebSocketUpgrade,
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    let exports = &state.config.federation_exports;
+    if exports.is_empty() {
+        return BrokerError::StreamNotFound(stream_id).into_response();
+    }
+    let credential = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|authorization| exports.authenticate(authorization));
+    match credential {
+        None => {
+            warn!("Rejected federation link for stream {}: invalid token", stream_id);
+            return BrokerError::Unauthorized("invalid federation token".to_string()).into_response();
+        }
+        Some(credential) if !credential.allows(&stream_id) => {
+            warn!("Rejected federation link for stream {}: not exported", stream_id);
+            return BrokerError::Forbidden(format!("stream is not exported: {}", stream_id)).into_response();
+        }
+        Some(_) => {}
+    }
+
+    info!("Federation link opened for stream: {}", stream_id);
+    ws::apply_limits(ws, &state.config).on_upgrade(move |socket| {
+        crate::websocket_connection(socket, stream_id, SubscribeParams::default(), None, None, None, None, None, None, state)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peer_routing() {
+        let peers = FederationPeers::parse("remote-a=wss://a.example:3090/|tok;b=ws://b:3091|t2").unwrap();
+        let (peer, remote_id) = peers.route("remote-a/cam/1").unwrap();
+        assert_eq!(peer.url, "wss://a.example:3090");
+        assert_eq!(remote_id, "cam/1");
+        assert!(peers.route("remote-ab/cam1").is_none());
+        assert!(peers.route("cam1").is_none());
+        assert!(FederationPeers::parse("x=http://a|t").is_err());
+
+        assert_eq!(encode_path_segment("cam/1 a"), "cam%2F1%20a");
+    }
+}
@@ -0,0 +1,229 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use tokio::sync::watch;
+use tracing::info;
+
+use crate::{error::BrokerError, mux::check_taggable, AppState};
+
+/// Registry of named stream groups (`site-A = [cam1, cam2, cam3]`)
+///
+/// Every membership change bumps a generation counter on a `watch` channel,
+/// so multiplexed subscribers can reconcile their subscriptions without polling.
+#[derive(Debug)]
+pub struct GroupRegistry {
+    groups: Mutex<HashMap<String, Vec<String>>>,
+    generation: watch::Sender<u64>,
+}
+
+impl Default for GroupRegistry {
+    fn default() -> Self {
+        Self {
+            groups: Mutex::new(HashMap::new()),
+            generation: watch::channel(0).0,
+        }
+    }
+}
+
+impl GroupRegistry {
+    /// Receiver that wakes up whenever any group changes
+    pub fn watch(&self) -> watch::Receiver<u64> {
+        self.generation.subscribe()
+    }
+
+    pub fn members(&self, name: &str) -> Option<Vec<String>> {
+        self.groups.lock().get(name).cloned()
+    }
+
+    pub fn list(&self) -> HashMap<String, Vec<String>> {
+        self.groups.lock().clone()
+    }
+
+    /// Replace (or create) a group with the given members
+    pub fn set(&self, name: &str, mut streams: Vec<String>) {
+        streams.sort();
+        streams.dedup();
+        self.groups.lock().insert(name.to_string(), streams);
+        self.bump();
+    }
+
+    /// Add a stream to a group, creating the group if needed
+    pub fn add_stream(&self, name: &str, stream_id: &str) {
+        {
+            let mut groups = self.groups.lock();
+            let members = groups.entry(name.to_string()).or_default();
+            if members.iter().any(|s| s == stream_id) {
+                return;
+            }
+            members.push(stream_id.to_string());
+            members.sort();
+        }
+        self.bump();
+    }
+
+    /// Remove a stream from a group; returns false if it was not a member
+    pub fn remove_stream(&self, name: &str, stream_id: &str) -> bool {
+        let removed = {
+            let mut groups = self.groups.lock();
+            match groups.get_mut(name) {
+                Some(members) => {
+                    let before = members.len();
+                    members.retain(|s| s != stream_id);
+                    members.len() != before
+                }
+                None => false,
+            }
+        };
+        if removed {
+            self.bump();
+        }
+        removed
+    }
+
+    /// Ganti semua group sekaligus (restore dari backup)
+    pub fn replace_all(&self, groups: impl IntoIterator<Item = (String, Vec<String>)>) {
+        let groups = groups
+            .into_iter()
+            .map(|(name, mut streams)| {
+                streams.sort();
+                streams.dedup();
+                (name, streams)
+            })
+            .collect();
+        *self.groups.lock() = groups;
+        self.bump();
+    }
+
+    pub fn delete(&self, name: &str) -> bool {
+        let removed = self.groups.lock().remove(name).is_some();
+        if removed {
+            self.bump();
+        }
+        removed
+    }
+
+    fn bump(&self) {
+        self.generation.send_modify(|generation| *generation += 1);
+    }
+}
+
+/// Body untuk PUT /api/groups/:name
+#[derive(Debug, Deserialize)]
+pub struct GroupBody {
+    pub streams: Vec<String>,
+}
+
+fn not_found(name: &str) -> Response {
+    BrokerError::not_found("group", name).into_response()
+}
+
+/// Handler untuk GET /api/groups
+pub async fn list_groups_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(json!({ "groups": state.groups.list() }))
+}
+
+/// Handler untuk GET /api/groups/:name
+pub async fn get_group_handler(
+    AxumPath(name): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Response {
+    match state.groups.members(&name) {
+        Some(streams) => Json(json!({ "name": name, "streams": streams })).into_response(),
+        None => not_found(&name),
+    }
+}
+
+/// Handler untuk PUT /api/groups/:name
+/// Membuat atau mengganti seluruh anggota group
+pub async fn put_group_handler(
+    AxumPath(name): AxumPath<String>,
+    State(state): State<AppState>,
+    Json(body): Json<GroupBody>,
+) -> Response {
+    // Anggota group dikirim lewat /mux dengan prefix ID satu byte
+    if let Err(e) = body.streams.iter().try_for_each(|stream_id| check_taggable(stream_id)) {
+        return e.into_response();
+    }
+    info!("Setting group {} to {} streams", name, body.streams.len());
+    state.groups.set(&name, body.streams);
+    Json(json!({ "name": name, "streams": state.groups.members(&name) })).into_response()
+}
+
+/// Handler untuk DELETE /api/groups/:name
+pub async fn delete_group_handler(
+    AxumPath(name): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Response {
+    if state.groups.delete(&name) {
+        info!("Deleted group {}", name);
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        not_found(&name)
+    }
+}
+
+/// Handler untuk PUT /api/groups/:name/streams/:stream_id
+pub async fn add_group_stream_handler(
+    AxumPath((name, stream_id)): AxumPath<(String, String)>,
+    State(state): State<AppState>,
+) -> Response {
+    if let Err(e) = check_taggable(&stream_id) {
+        return e.into_response();
+    }
+    info!("Adding stream {} to group {}", stream_id, name);
+    state.groups.add_stream(&name, &stream_id);
+    Json(json!({ "name": name, "streams": state.groups.members(&name) })).into_response()
+}
+
+/// Handler untuk DELETE /api/groups/:name/streams/:stream_id
+pub async fn remove_group_stream_handler(
+    AxumPath((name, stream_id)): AxumPath<(String, String)>,
+    State(state): State<AppState>,
+) -> Response {
+    if state.groups.remove_stream(&name, &stream_id) {
+        info!("Removed stream {} from group {}", stream_id, name);
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        not_found(&name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_changes_bump_generation() {
+        let groups = GroupRegistry::default();
+        let rx = groups.watch();
+
+        groups.set("site-A", vec!["cam2".into(), "cam1".into(), "cam1".into()]);
+        groups.add_stream("site-A", "cam3");
+        assert_eq!(
+            groups.members("site-A").unwrap(),
+            vec!["cam1", "cam2", "cam3"]
+        );
+        assert!(groups.remove_stream("site-A", "cam2"));
+        assert!(!groups.remove_stream("site-A", "cam9"));
+        assert_eq!(*rx.borrow(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_untaggable_stream_ids_are_rejected() {
+        let state = AppState::new(crate::config::Config::default());
+        let too_long = "c".repeat(crate::mux::MAX_TAGGED_ID_LEN + 1);
+        let path = AxumPath(("site-A".to_string(), too_long.clone()));
+        let response = add_group_stream_handler(path, State(state.clone())).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = GroupBody { streams: vec!["cam1".to_string(), too_long] };
+        let response = put_group_handler(AxumPath("site-A".to_string()), State(state.clone()), Json(body)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(state.groups.members("site-A"), None);
+    }
+}
@@ -0,0 +1,127 @@
+// Tanpa fitur `cluster` tidak ada link replikasi; peran HA selalu kosong
+#![cfg_attr(not(feature = "cluster"), allow(dead_code))]
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64},
+    time::Duration,
+};
+
+// Link replikasi active -> passive dan takeover; tanpa fitur `cluster` broker selalu tunggal
+#[cfg(feature = "cluster")]
+pub mod replication;
+
+/// Peran broker dalam pasangan active-passive
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HaRole {
+    /// Serves producers and feeds the replication link
+    Active,
+    /// Mirrors the active broker and rejects ingest until it takes over
+    Passive,
+}
+
+impl std::str::FromStr for HaRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "active" => Ok(HaRole::Active),
+            "passive" => Ok(HaRole::Passive),
+            other => Err(format!("unknown HA role {:?} (use active or passive)", other)),
+        }
+    }
+}
+
+/// Pengaturan HA dari `HA_ROLE`, `HA_PEER_URL`, `HA_TOKEN`, ...
+#[derive(Clone)]
+pub struct HaConfig {
+    pub role: HaRole,
+    /// Replication link of the passive broker, e.g. `ws://broker-a:3091`
+    pub peer_url: Option<String>,
+    token: String,
+    /// CA bundle untuk memverifikasi active `wss://`
+    pub ca_path: Option<String>,
+    /// Also mirror DVR frames, so resuming subscribers find them after a takeover
+    pub mirror_dvr: bool,
+    /// The passive takes over after the link has been silent this long
+    pub takeover_after: Duration,
+    /// Shell command run on takeover (claim a virtual IP, notify a load balancer)
+    pub takeover_command: Option<String>,
+}
+
+impl std::fmt::Debug for HaConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HaConfig")
+            .field("role", &self.role)
+            .field("peer_url", &self.peer_url)
+            .field("mirror_dvr", &self.mirror_dvr)
+            .field("takeover_after", &self.takeover_after)
+            .finish()
+    }
+}
+
+impl HaConfig {
+    pub fn new(
+        role: HaRole,
+        peer_url: Option<String>,
+        token: String,
+        ca_path: Option<String>,
+        mirror_dvr: bool,
+        takeover_after: Duration,
+        takeover_command: Option<String>,
+    ) -> Result<Self, String> {
+        if token.is_empty() {
+            return Err("HA_TOKEN is required when HA_ROLE is set".to_string());
+        }
+        let peer_url = peer_url.map(|url| url.trim_end_matches('/').to_string());
+        match (role, peer_url.as_deref()) {
+            (HaRole::Passive, None) => return Err("HA_ROLE=passive requires HA_PEER_URL".to_string()),
+            (HaRole::Passive, Some(url)) if !(url.starts_with("ws://") || url.starts_with("wss://")) => {
+                return Err(format!("Invalid HA_PEER_URL value: {} (use ws:// or wss://)", url))
+            }
+            _ => {}
+        }
+        Ok(Self {
+            role,
+            peer_url,
+            token,
+            ca_path,
+            mirror_dvr,
+            takeover_after,
+            takeover_command,
+        })
+    }
+}
+
+/// Status HA runtime; peran passive berubah menjadi active saat takeover
+#[derive(Debug)]
+pub struct HaState {
+    role: Mutex<Option<HaRole>>,
+    link_up: AtomicBool,
+    /// Unix ms of the last message from the active broker
+    last_sync_ms: AtomicU64,
+    /// Unix ms of the takeover, 0 if it never happened
+    promoted_ms: AtomicU64,
+}
+
+impl HaState {
+    pub fn new(role: Option<HaRole>) -> Self {
+        Self {
+            role: Mutex::new(role),
+            link_up: AtomicBool::new(false),
+            last_sync_ms: AtomicU64::new(0),
+            promoted_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// `None` tanpa HA (broker tunggal)
+    pub fn role(&self) -> Option<HaRole> {
+        *self.role.lock()
+    }
+
+    pub fn is_passive(&self) -> bool {
+        self.role() == Some(HaRole::Passive)
+    }
+}
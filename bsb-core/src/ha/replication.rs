@@ -0,0 +1,404 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use bytes::Bytes;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::atomic::Ordering,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, Message as WsMessage},
+    Connector,
+};
+use tracing::{error, info, warn};
+
+use super::{HaConfig, HaRole};
+use crate::{
+    audio::StreamType, auth::constant_time_eq, error::BrokerError, labels::Labels, outbound, sources, tls,
+    ws, AppState,
+};
+
+/// Interval snapshot registry dari active ke passive (juga heartbeat link)
+const REGISTRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Interval pengiriman frame DVR baru ke passive (`HA_MIRROR_DVR`)
+const FRAME_INTERVAL: Duration = Duration::from_millis(200);
+
+fn unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Snapshot registry yang dikirim sebagai pesan teks JSON
+#[derive(Debug, Serialize, Deserialize)]
+struct RegistrySync {
+    streams: Vec<StreamSync>,
+    groups: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StreamSync {
+    id: String,
+    seq: u64,
+    labels: Labels,
+    stream_type: StreamType,
+}
+
+/// Frame mirror sebagai pesan biner: `[u8 id_len][stream id][u64 seq][payload]`
+fn encode_frame(stream_id: &str, seq: u64, payload: &[u8]) -> Vec<u8> {
+    let id = &stream_id.as_bytes()[..stream_id.len().min(255)];
+    let mut message = Vec::with_capacity(1 + id.len() + 8 + payload.len());
+    message.push(id.len() as u8);
+    message.extend_from_slice(id);
+    message.extend_from_slice(&seq.to_be_bytes());
+    message.extend_from_slice(payload);
+    message
+}
+
+fn decode_frame(message: &[u8]) -> Option<(&str, u64, &[u8])> {
+    let (&id_len, rest) = message.split_first()?;
+    let id = std::str::from_utf8(rest.get(..id_len as usize)?).ok()?;
+    let rest = &rest[id_len as usize..];
+    let seq = u64::from_be_bytes(rest.get(..8)?.try_into().ok()?);
+    Some((id, seq, &rest[8..]))
+}
+
+fn registry_snapshot(state: &AppState) -> RegistrySync {
+    let streams = state
+        .streams
+        .lock()
+        .iter()
+        .map(|(id, entry)| StreamSync {
+            id: id.clone(),
+            seq: entry.last_seq(),
+            labels: entry.labels.clone(),
+            stream_type: entry.stream_type,
+        })
+        .collect();
+    RegistrySync {
+        streams,
+        groups: state.groups.list(),
+    }
+}
+
+/// Terapkan snapshot dari active; tanpa mirror DVR, nomor urut dilanjutkan dari snapshot
+fn apply_registry(state: &AppState, sync: RegistrySync, mirror_dvr: bool) {
+    for stream in sync.streams {
+        state.with_stream(&stream.id, |entry| {
+            entry.labels = stream.labels;
+            entry.stream_type = stream.stream_type;
+            if !mirror_dvr {
+                entry.continue_from(stream.seq);
+            }
+        });
+    }
+    if sync.groups != state.groups.list() {
+        for name in state.groups.list().keys() {
+            if !sync.groups.contains_key(name) {
+                state.groups.delete(name);
+            }
+        }
+        for (name, streams) in sync.groups {
+            state.groups.set(&name, streams);
+        }
+    }
+}
+
+/// Jalankan sisi passive: mirror dari active, ambil alih saat link diam `takeover_after`
+///
+/// Static pull sources are only started once this broker is active, so the
+/// pair never pulls the same cameras twice.
+pub fn spawn(state: &AppState) {
+    let Some(config) = state.config.ha.clone() else {
+        sources::spawn_all(state);
+        return;
+    };
+    if config.role == HaRole::Active {
+        info!("HA active: serving the replication link at /ha/replicate");
+        sources::spawn_all(state);
+        return;
+    }
+    let state = state.clone();
+    tokio::spawn(async move {
+        let mut last_seen = Instant::now();
+        loop {
+            match link_once(&state, &config, &mut last_seen).await {
+                Ok(()) => info!("HA replication link closed by active broker"),
+                Err(e) => warn!("HA replication link failed: {}", e),
+            }
+            state.ha.link_up.store(false, Ordering::Relaxed);
+            if last_seen.elapsed() >= config.takeover_after {
+                promote(&state, &config).await;
+                return;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    });
+}
+
+/// Satu koneksi ke active; selesai saat link putus atau diam lebih dari `takeover_after`
+async fn link_once(state: &AppState, config: &HaConfig, last_seen: &mut Instant) -> Result<(), String> {
+    let base = config.peer_url.as_deref().unwrap_or_default();
+    let url = format!("{}/ha/replicate", base);
+    let mut request = url.as_str().into_client_request().map_err(|e| e.to_string())?;
+    request.headers_mut().insert(
+        header::AUTHORIZATION,
+        format!("Bearer {}", config.token)
+            .parse()
+            .map_err(|_| "HA_TOKEN is not a valid header value".to_string())?,
+    );
+    let connector = if url.starts_with("wss://") {
+        let ca_path = config.ca_path.as_deref().ok_or("wss:// peers require HA_CA_PATH")?;
+        Some(Connector::Rustls(tls::client_config(ca_path)?))
+    } else {
+        None
+    };
+    let connect = outbound::connect_ws(request, connector);
+    let (mut socket, _) = tokio::time::timeout(config.takeover_after, connect)
+        .await
+        .map_err(|_| "connect timed out".to_string())??;
+    info!("HA replication link connected to {}", base);
+    state.ha.link_up.store(true, Ordering::Relaxed);
+
+    loop {
+        let deadline = *last_seen + config.takeover_after;
+        let msg = tokio::select! {
+            msg = socket.next() => msg,
+            _ = tokio::time::sleep_until(deadline.into()) => {
+                return Err(format!("no message from active broker for {:?}", config.takeover_after));
+            }
+        };
+        let message = match msg {
+            Some(Ok(message)) => message,
+            Some(Err(e)) => return Err(e.to_string()),
+            None => return Ok(()),
+        };
+        *last_seen = Instant::now();
+        state.ha.last_sync_ms.store(unix_ms(), Ordering::Relaxed);
+        match message {
+            WsMessage::Text(text) => match serde_json::from_str(&text) {
+                Ok(sync) => apply_registry(state, sync, config.mirror_dvr),
+                Err(e) => warn!("Ignoring HA registry message: {}", e),
+            },
+            WsMessage::Binary(data) => {
+                let Some((stream_id, seq, payload)) = decode_frame(&data) else {
+                    warn!("Ignoring malformed HA frame message");
+                    continue;
+                };
+                let payload = Bytes::copy_from_slice(payload);
+                state.with_stream(stream_id, |entry| {
+                    // Frame yang sudah ada (reconnect mengirim ulang DVR) dilewati
+                    if seq > entry.last_seq() {
+                        entry.continue_from(seq - 1);
+                        let _ = entry.publish(payload);
+                    }
+                });
+            }
+            WsMessage::Close(_) => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+/// Passive menjadi active: terima ingest, jalankan pull source dan `HA_TAKEOVER_COMMAND`
+async fn promote(state: &AppState, config: &HaConfig) {
+    *state.ha.role.lock() = Some(HaRole::Active);
+    state.ha.promoted_ms.store(unix_ms(), Ordering::Relaxed);
+    warn!(
+        "HA takeover: no contact with active broker for {:?}, this broker is now active",
+        config.takeover_after
+    );
+    sources::spawn_all(state);
+    if let Some(command) = config.takeover_command.as_deref() {
+        match tokio::process::Command::new("sh").arg("-c").arg(command).status().await {
+            Ok(status) if status.success() => info!("HA_TAKEOVER_COMMAND succeeded"),
+            Ok(status) => error!("HA_TAKEOVER_COMMAND exited with {}", status),
+            Err(e) => error!("Cannot run HA_TAKEOVER_COMMAND: {}", e),
+        }
+    }
+}
+
+/// Handler untuk GET /ha/replicate
+/// Link replikasi untuk broker passive, diautentikasi dengan `HA_TOKEN`
+pub async fn replicate_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(config) = state.config.ha.clone() else {
+        return BrokerError::not_found("replication link", "ha").into_response();
+    };
+    let authorized = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(&config.token, token.trim()));
+    if !authorized {
+        warn!("Rejected HA replication link: invalid token");
+        return BrokerError::Unauthorized("invalid HA token".to_string()).into_response();
+    }
+    if state.ha.role() != Some(HaRole::Active) {
+        return BrokerError::Unavailable("broker is passive".to_string()).into_response();
+    }
+    info!("HA replication link opened");
+    ws::apply_limits(ws, &state.config).on_upgrade(move |socket| replicate(socket, state, config.mirror_dvr))
+}
+
+/// Kirim snapshot registry tiap detik dan (opsional) frame DVR baru ke passive
+async fn replicate(mut socket: WebSocket, state: AppState, mirror_dvr: bool) {
+    let mut registry_tick = tokio::time::interval(REGISTRY_INTERVAL);
+    let mut frame_tick = tokio::time::interval(FRAME_INTERVAL);
+    // Frame terakhir yang sudah dikirim per stream; koneksi baru mulai dari seluruh DVR
+    let mut mirrored: HashMap<String, u64> = HashMap::new();
+    loop {
+        tokio::select! {
+            _ = registry_tick.tick() => {
+                let sync = serde_json::to_string(&registry_snapshot(&state)).unwrap_or_default();
+                if let Err(e) = socket.send(Message::Text(sync)).await {
+                    warn!("HA replication link closed: {}", e);
+                    break;
+                }
+            }
+            _ = frame_tick.tick(), if mirror_dvr => {
+                let pending: Vec<_> = state
+                    .streams
+                    .lock()
+                    .iter()
+                    .map(|(id, entry)| (id.clone(), entry.dvr.since(mirrored.get(id).copied().unwrap_or(0))))
+                    .collect();
+                let mut failed = false;
+                for (stream_id, frames) in pending {
+                    for frame in frames {
+                        let message = encode_frame(&stream_id, frame.seq, &frame.data);
+                        if let Err(e) = socket.send(Message::Binary(message)).await {
+                            warn!("HA replication link closed: {}", e);
+                            failed = true;
+                            break;
+                        }
+                        mirrored.insert(stream_id.clone(), frame.seq);
+                    }
+                    if failed {
+                        break;
+                    }
+                }
+                if failed {
+                    break;
+                }
+            }
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    info!("HA replication link closed");
+}
+
+/// Handler untuk GET /ha/status
+/// `200` selama broker ini active (atau tanpa HA), `503` selama passive, untuk health check load balancer
+pub async fn ha_status_handler(State(state): State<AppState>) -> Response {
+    let role = state.ha.role();
+    let promoted_ms = state.ha.promoted_ms.load(Ordering::Relaxed);
+    let last_sync_ms = state.ha.last_sync_ms.load(Ordering::Relaxed);
+    let body = Json(json!({
+        "role": role,
+        "link_up": state.ha.link_up.load(Ordering::Relaxed),
+        "last_sync_ms": (last_sync_ms > 0).then_some(last_sync_ms),
+        "promoted_ms": (promoted_ms > 0).then_some(promoted_ms),
+    }));
+    let status = match role {
+        Some(HaRole::Passive) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::OK,
+    };
+    (status, body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, testing::TestBroker};
+
+    #[test]
+    fn test_frame_encoding() {
+        let message = encode_frame("cam/1", 42, b"jpeg");
+        assert_eq!(decode_frame(&message), Some(("cam/1", 42, &b"jpeg"[..])));
+        assert_eq!(decode_frame(&message[..8]), None);
+    }
+
+    fn ha(role: HaRole, peer_url: Option<String>) -> HaConfig {
+        HaConfig::new(role, peer_url, "s3cret".to_string(), None, true, Duration::from_secs(1), None).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_passive_mirrors_registry_and_dvr() {
+        let active = TestBroker::start(Config {
+            ha: Some(ha(HaRole::Active, None)),
+            ..Config::default()
+        })
+        .await;
+        let passive = TestBroker::start(Config {
+            ha: Some(ha(HaRole::Passive, Some(format!("ws://{}", active.addr)))),
+            ..Config::default()
+        })
+        .await;
+        spawn(&passive.state);
+
+        active.state.with_stream("cam1", |entry| {
+            entry.labels.insert("site".to_string(), "a".to_string());
+            for i in 1..=3 {
+                let _ = entry.publish(Bytes::from(format!("frame-{}", i)));
+            }
+        });
+        assert_eq!(passive.post_frame("cam1", "frame").await, StatusCode::SERVICE_UNAVAILABLE);
+
+        let mirrored = || {
+            passive.state.streams.lock().get("cam1").map(|entry| {
+                let labels = entry.labels.get("site").cloned();
+                (entry.last_seq(), entry.dvr.since(0).len(), labels)
+            })
+        };
+        let expected = Some((3, 3, Some("a".to_string())));
+        for _ in 0..50 {
+            if mirrored() == expected {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(mirrored(), expected);
+        assert!(passive.state.ha.is_passive());
+    }
+
+    #[tokio::test]
+    async fn test_passive_takes_over_when_active_is_unreachable() {
+        // Port yang sudah ditutup: active dianggap mati sejak awal
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let peer_url = format!("ws://{}", closed.local_addr().unwrap());
+        drop(closed);
+        let passive = TestBroker::start(Config {
+            ha: Some(ha(HaRole::Passive, Some(peer_url))),
+            ..Config::default()
+        })
+        .await;
+        spawn(&passive.state);
+
+        for _ in 0..50 {
+            if !passive.state.ha.is_passive() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(passive.state.ha.role(), Some(HaRole::Active));
+        let _rx = passive.state.with_stream("cam1", |entry| entry.tx.subscribe());
+        assert_eq!(passive.post_frame("cam1", "frame").await, StatusCode::OK);
+    }
+}
@@ -0,0 +1,181 @@
+use axum::{
+    extract::{Path as AxumPath, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+use tracing::info;
+
+use crate::{error::BrokerError, AppState};
+
+/// Header tambahan terbanyak per stream
+pub const MAX_HEADERS: usize = 32;
+
+/// Header yang menentukan framing atau koneksi; broker yang mengaturnya
+const RESERVED: [HeaderName; 7] = [
+    header::CONNECTION,
+    header::CONTENT_LENGTH,
+    header::CONTENT_TYPE,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// Body untuk PUT /api/streams/:id/headers (juga `response_headers` di `STATIC_STREAMS_FILE`)
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct HeadersBody {
+    /// Added to (or replacing) the headers of successful HTTP playback responses
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Periksa dan ubah header konfigurasi menjadi `HeaderMap`
+pub fn compile(headers: &BTreeMap<String, String>) -> Result<HeaderMap, String> {
+    if headers.len() > MAX_HEADERS {
+        return Err(format!("at most {} headers per stream", MAX_HEADERS));
+    }
+    let mut compiled = HeaderMap::new();
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| format!("invalid header name {:?}", name))?;
+        if RESERVED.contains(&name) || name.as_str() == "keep-alive" {
+            return Err(format!("header {} is set by the broker", name));
+        }
+        let value = HeaderValue::from_str(value).map_err(|_| format!("invalid value for header {}", name))?;
+        compiled.insert(name, value);
+    }
+    Ok(compiled)
+}
+
+/// Header respons tambahan per stream
+#[derive(Debug, Default)]
+pub struct StreamHeaders {
+    active: Mutex<HashMap<String, Arc<HeaderMap>>>,
+}
+
+impl StreamHeaders {
+    pub fn set(&self, stream_id: &str, headers: HeaderMap) {
+        self.active.lock().insert(stream_id.to_string(), Arc::new(headers));
+    }
+
+    fn get(&self, stream_id: &str) -> Option<Arc<HeaderMap>> {
+        self.active.lock().get(stream_id).cloned()
+    }
+}
+
+/// Tambahkan header stream ke respons playback HTTP yang berhasil
+///
+/// Errors keep the broker's own headers, so a CDN told to cache frames for a
+/// while does not also cache a `404` from before the stream existed.
+pub async fn stream_headers_middleware(
+    State(state): State<AppState>,
+    AxumPath(params): AxumPath<HashMap<String, String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    if !response.status().is_success() {
+        return response;
+    }
+    if let Some(stream_id) = params.get("stream_id") {
+        apply(&state, stream_id, &mut response);
+    }
+    response
+}
+
+/// Tambahkan header stream ke satu respons (handler yang path-nya bukan `:stream_id`)
+pub fn apply(state: &AppState, stream_id: &str, response: &mut Response) {
+    if let Some(extra) = state.stream_headers.get(stream_id) {
+        for (name, value) in extra.iter() {
+            response.headers_mut().insert(name.clone(), value.clone());
+        }
+    }
+}
+
+fn to_body(headers: &HeaderMap) -> HeadersBody {
+    let headers = headers
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_str().unwrap_or_default().to_string()))
+        .collect();
+    HeadersBody { headers }
+}
+
+/// Handler untuk GET /api/streams/:id/headers
+pub async fn get_headers_handler(AxumPath(stream_id): AxumPath<String>, State(state): State<AppState>) -> Response {
+    match state.stream_headers.get(&stream_id) {
+        Some(headers) => Json(json!({ "stream": stream_id, "headers": to_body(&headers).headers })).into_response(),
+        None => BrokerError::not_found("headers", stream_id).into_response(),
+    }
+}
+
+/// Handler untuk PUT /api/streams/:id/headers
+/// Replace the extra response headers of the stream
+pub async fn put_headers_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+    Json(body): Json<HeadersBody>,
+) -> Response {
+    let headers = match compile(&body.headers) {
+        Ok(headers) => headers,
+        Err(message) => return BrokerError::InvalidRequest(message).into_response(),
+    };
+    info!("Response headers set for stream {}: {:?}", stream_id, body.headers.keys());
+    let response = json!({ "stream": stream_id, "headers": to_body(&headers).headers });
+    state.stream_headers.set(&stream_id, headers);
+    Json(response).into_response()
+}
+
+/// Handler untuk DELETE /api/streams/:id/headers
+pub async fn delete_headers_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Result<StatusCode, BrokerError> {
+    if state.stream_headers.active.lock().remove(&stream_id).is_some() {
+        info!("Response headers removed from stream {}", stream_id);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(BrokerError::not_found("headers", stream_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, testing::TestBroker};
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn test_stream_headers_on_successful_playback_responses() {
+        let broker = TestBroker::start(Config::default()).await;
+        let headers = BTreeMap::from([
+            ("Cache-Control".to_string(), "public, max-age=1".to_string()),
+            ("X-CDN-Tag".to_string(), "lobby".to_string()),
+        ]);
+        broker.state.stream_headers.set("lobby", compile(&headers).unwrap());
+        broker.state.with_stream("lobby", |_| ());
+        broker.post_frame("lobby", "frame-1").await;
+
+        let get = |uri: &str| {
+            let request = Request::builder().uri(uri).body(axum::body::Body::empty()).unwrap();
+            crate::build_router(broker.state.clone()).oneshot(request)
+        };
+        let response = get("/api/streams/lobby/frames?from_seq=0").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "public, max-age=1");
+        assert_eq!(response.headers()["x-cdn-tag"], "lobby");
+        // Stream lain dan respons error tidak mendapat header-nya
+        let response = get("/api/streams/other/bootstrap").await.unwrap();
+        assert!(response.headers().get("x-cdn-tag").is_none());
+
+        let reserved = BTreeMap::from([("Content-Length".to_string(), "0".to_string())]);
+        assert!(compile(&reserved).is_err());
+        let invalid = BTreeMap::from([("X-Tag".to_string(), "line\nbreak".to_string())]);
+        assert!(compile(&invalid).is_err());
+    }
+}
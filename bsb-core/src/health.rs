@@ -0,0 +1,183 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{error::BrokerError, history::StatsSample, AppState};
+
+/// Skor dihitung dari sampel 1 detik terakhir sebanyak ini
+pub const HEALTH_WINDOW_SECS: usize = 30;
+
+/// Komponen skor, masing-masing 0.0 (buruk) sampai 1.0 (sempurna)
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub struct HealthComponents {
+    /// 1 - coefficient of variation of the per-second bitrate
+    pub bitrate_stability: f64,
+    /// Share of seconds that carried frames (only judged for streams at 1 fps or more)
+    pub frame_continuity: f64,
+    /// 1 - share of subscriber deliveries dropped for lag or age
+    pub delivery: f64,
+    /// 1 - average queue fill of the slowest subscriber
+    pub subscriber_lag: f64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,
+    Unhealthy,
+    /// No frames at all within the window
+    Down,
+}
+
+/// Skor kesehatan stream 0-100
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub struct HealthScore {
+    pub score: f64,
+    pub status: HealthStatus,
+    pub components: HealthComponents,
+}
+
+fn ratio(part: f64, whole: f64) -> f64 {
+    if whole > 0.0 {
+        (part / whole).min(1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Hitung skor dari sampel 1 detik (terlama lebih dulu); `None` tanpa sampel
+pub fn score(samples: &[StatsSample]) -> Option<HealthScore> {
+    if samples.is_empty() {
+        return None;
+    }
+    let n = samples.len() as f64;
+    let frames: f64 = samples.iter().map(|s| s.fps).sum();
+    if frames == 0.0 {
+        let components = HealthComponents {
+            bitrate_stability: 0.0,
+            frame_continuity: 0.0,
+            delivery: 1.0,
+            subscriber_lag: 1.0,
+        };
+        return Some(HealthScore {
+            score: 0.0,
+            status: HealthStatus::Down,
+            components,
+        });
+    }
+
+    // Bitrate dihitung hanya dari detik yang membawa frame; detik kosong sudah masuk continuity
+    let active: Vec<f64> = samples
+        .iter()
+        .filter(|s| s.fps > 0.0)
+        .map(|s| s.bitrate_bps)
+        .collect();
+    let mean = active.iter().sum::<f64>() / active.len() as f64;
+    let variance = active.iter().map(|b| (b - mean).powi(2)).sum::<f64>() / active.len() as f64;
+    let bitrate_stability = 1.0 - ratio(variance.sqrt(), mean);
+
+    // Stream di bawah 1 fps wajar punya detik tanpa frame
+    let frame_continuity = if frames / n >= 1.0 {
+        active.len() as f64 / n
+    } else {
+        1.0
+    };
+
+    let deliveries: f64 = samples.iter().map(|s| s.fps * s.subscribers as f64).sum();
+    let drops: f64 = samples.iter().map(|s| s.drops as f64).sum();
+    let delivery = if deliveries > 0.0 {
+        1.0 - ratio(drops, deliveries)
+    } else {
+        1.0
+    };
+
+    let subscriber_lag = 1.0 - samples.iter().map(|s| s.lag).sum::<f64>() / n;
+
+    let components = HealthComponents {
+        bitrate_stability,
+        frame_continuity,
+        delivery,
+        subscriber_lag,
+    };
+    let score = (bitrate_stability + frame_continuity + delivery + subscriber_lag) * 25.0;
+    let score = (score * 10.0).round() / 10.0;
+    let status = match score {
+        s if s >= 90.0 => HealthStatus::Healthy,
+        s if s >= 60.0 => HealthStatus::Degraded,
+        _ => HealthStatus::Unhealthy,
+    };
+    Some(HealthScore {
+        score,
+        status,
+        components,
+    })
+}
+
+/// Handler untuk GET /api/streams/:id/health
+pub async fn stream_health_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Response {
+    let map = state.streams.lock();
+    let Some(entry) = map.get(&stream_id) else {
+        return BrokerError::StreamNotFound(stream_id).into_response();
+    };
+    Json(json!({
+        "stream": stream_id,
+        "window_secs": HEALTH_WINDOW_SECS,
+        "health": entry.history.health(),
+    }))
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(fps: f64, bitrate_bps: f64, subscribers: usize, drops: u64, lag: f64) -> StatsSample {
+        StatsSample {
+            timestamp: 0,
+            bitrate_bps,
+            fps,
+            subscribers,
+            drops,
+            checksum_failures: 0,
+            lag,
+            health: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_health_score() {
+        assert_eq!(score(&[]), None);
+
+        let steady = vec![sample(10.0, 80_000.0, 2, 0, 0.0); 30];
+        let health = score(&steady).unwrap();
+        assert_eq!(health.score, 100.0);
+        assert_eq!(health.status, HealthStatus::Healthy);
+
+        // Setiap detik ketiga kosong, 10% delivery hilang, subscriber setengah tertinggal
+        let degraded: Vec<_> = (0..30)
+            .map(|i| match i % 3 {
+                0 => sample(0.0, 0.0, 2, 0, 0.5),
+                _ => sample(10.0, 80_000.0, 2, 2, 0.5),
+            })
+            .collect();
+        let health = score(&degraded).unwrap();
+        assert!((health.components.frame_continuity - 2.0 / 3.0).abs() < 1e-9);
+        assert!((health.components.delivery - 0.9).abs() < 1e-9);
+        assert_eq!(health.components.subscriber_lag, 0.5);
+        assert_eq!(health.status, HealthStatus::Degraded);
+
+        let silent = vec![sample(0.0, 0.0, 1, 0, 0.0); 30];
+        assert_eq!(score(&silent).unwrap().status, HealthStatus::Down);
+
+        // 0.5 fps: detik kosong bukan gap
+        let slow: Vec<_> = (0..30).map(|i| sample((i % 2) as f64, 8000.0 * (i % 2) as f64, 0, 0, 0.0)).collect();
+        assert_eq!(score(&slow).unwrap().components.frame_continuity, 1.0);
+    }
+}
@@ -0,0 +1,215 @@
+use axum::{
+    extract::{Path as AxumPath, Query, State},
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::VecDeque;
+
+use crate::{
+    error::BrokerError,
+    health::{self, HealthScore, HEALTH_WINDOW_SECS},
+    stats::StreamCounters,
+    AppState,
+};
+
+/// Jumlah sampel yang disimpan per resolusi: 5 menit @1s, 1 jam @1m, 24 jam @5m
+const SECOND_SAMPLES: usize = 300;
+const MINUTE_SAMPLES: usize = 60;
+const FIVE_MINUTE_SAMPLES: usize = 288;
+
+/// Satu titik time series
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub struct StatsSample {
+    /// Unix timestamp (seconds) at the start of the sample window
+    pub timestamp: u64,
+    pub bitrate_bps: f64,
+    pub fps: f64,
+    /// Peak subscriber count within the window
+    pub subscribers: usize,
+    /// Frames dropped for lagging subscribers within the window
+    pub drops: u64,
+    /// Frames rejected for a bad checksum within the window
+    pub checksum_failures: u64,
+    /// Queue fill of the slowest subscriber (0.0-1.0 of the stream's channel capacity)
+    pub lag: f64,
+    /// Health score 0-100 over the preceding `HEALTH_WINDOW_SECS` seconds
+    pub health: f64,
+}
+
+impl StatsSample {
+    /// Roll a window of samples up into one: averages for rates, peak
+    /// subscribers and summed drops
+    fn rollup(samples: &[StatsSample]) -> Self {
+        let n = samples.len().max(1) as f64;
+        Self {
+            timestamp: samples.first().map_or(0, |s| s.timestamp),
+            bitrate_bps: samples.iter().map(|s| s.bitrate_bps).sum::<f64>() / n,
+            fps: samples.iter().map(|s| s.fps).sum::<f64>() / n,
+            subscribers: samples.iter().map(|s| s.subscribers).max().unwrap_or(0),
+            drops: samples.iter().map(|s| s.drops).sum(),
+            checksum_failures: samples.iter().map(|s| s.checksum_failures).sum(),
+            lag: samples.iter().map(|s| s.lag).sum::<f64>() / n,
+            health: samples.iter().map(|s| s.health).sum::<f64>() / n,
+        }
+    }
+}
+
+/// Riwayat statistik per stream dalam tiga resolusi
+#[derive(Debug, Default)]
+pub struct StatsHistory {
+    last: (u64, u64, u64, u64),
+    second: VecDeque<StatsSample>,
+    minute: VecDeque<StatsSample>,
+    five_minute: VecDeque<StatsSample>,
+    /// Samples not yet rolled up into the next resolution
+    pending_minute: Vec<StatsSample>,
+    pending_five_minute: Vec<StatsSample>,
+}
+
+impl StatsHistory {
+    /// Take a 1 s sample from the cumulative counters and roll up as needed
+    pub fn record(
+        &mut self,
+        timestamp: u64,
+        counters: &StreamCounters,
+        subscribers: usize,
+        lag: f64,
+    ) {
+        let (frames, bytes, drops, checksum_failures) = counters.snapshot();
+        let (last_frames, last_bytes, last_drops, last_checksum_failures) = self.last;
+        self.last = (frames, bytes, drops, checksum_failures);
+
+        let sample = StatsSample {
+            timestamp,
+            bitrate_bps: (bytes - last_bytes) as f64 * 8.0,
+            fps: (frames - last_frames) as f64,
+            subscribers,
+            drops: drops - last_drops,
+            checksum_failures: checksum_failures - last_checksum_failures,
+            lag,
+            health: 0.0,
+        };
+        push_bounded(&mut self.second, sample, SECOND_SAMPLES);
+        let health = self.health().map_or(0.0, |health| health.score);
+        let sample = StatsSample { health, ..sample };
+        if let Some(latest) = self.second.back_mut() {
+            latest.health = health;
+        }
+
+        self.pending_minute.push(sample);
+        if self.pending_minute.len() == 60 {
+            let minute = StatsSample::rollup(&self.pending_minute);
+            self.pending_minute.clear();
+            push_bounded(&mut self.minute, minute, MINUTE_SAMPLES);
+
+            self.pending_five_minute.push(minute);
+            if self.pending_five_minute.len() == 5 {
+                let five = StatsSample::rollup(&self.pending_five_minute);
+                self.pending_five_minute.clear();
+                push_bounded(&mut self.five_minute, five, FIVE_MINUTE_SAMPLES);
+            }
+        }
+    }
+
+    /// Most recent 1 s sample
+    pub fn latest(&self) -> Option<StatsSample> {
+        self.second.back().copied()
+    }
+
+    /// Skor kesehatan dari `HEALTH_WINDOW_SECS` sampel terakhir
+    pub fn health(&self) -> Option<HealthScore> {
+        let start = self.second.len().saturating_sub(HEALTH_WINDOW_SECS);
+        let window: Vec<_> = self.second.range(start..).copied().collect();
+        health::score(&window)
+    }
+
+    pub fn series(&self, resolution: &str) -> Option<Vec<StatsSample>> {
+        let series = match resolution {
+            "1s" => &self.second,
+            "1m" => &self.minute,
+            "5m" => &self.five_minute,
+            _ => return None,
+        };
+        Some(series.iter().copied().collect())
+    }
+}
+
+fn push_bounded(series: &mut VecDeque<StatsSample>, sample: StatsSample, max: usize) {
+    if series.len() == max {
+        series.pop_front();
+    }
+    series.push_back(sample);
+}
+
+/// Query parameter untuk GET /api/streams/:id/stats/history
+#[derive(Debug, Deserialize)]
+pub struct HistoryParams {
+    /// `1s`, `1m` or `5m`; all resolutions when omitted
+    pub resolution: Option<String>,
+}
+
+/// Handler untuk GET /api/streams/:id/stats/history
+pub async fn stats_history_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    Query(params): Query<HistoryParams>,
+    State(state): State<AppState>,
+) -> Response {
+    let map = state.streams.lock();
+    let Some(entry) = map.get(&stream_id) else {
+        return BrokerError::StreamNotFound(stream_id).into_response();
+    };
+
+    match params.resolution {
+        Some(resolution) => match entry.history.series(&resolution) {
+            Some(series) => Json(json!({
+                "stream": stream_id,
+                "resolution": resolution,
+                "samples": series,
+            }))
+            .into_response(),
+            None => BrokerError::InvalidRequest("resolution must be one of 1s, 1m, 5m".into())
+                .into_response(),
+        },
+        None => Json(json!({
+            "stream": stream_id,
+            "resolutions": {
+                "1s": entry.history.series("1s"),
+                "1m": entry.history.series("1m"),
+                "5m": entry.history.series("5m"),
+            },
+        }))
+        .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_rolls_up_minutes() {
+        let counters = StreamCounters::default();
+        let mut history = StatsHistory::default();
+        for t in 0..120 {
+            // 10 frames of 100 bytes per second, 1 drop every second
+            for _ in 0..10 {
+                counters.record_frame(100);
+            }
+            counters.record_drops(1);
+            history.record(t, &counters, (t % 3) as usize, 0.0);
+        }
+
+        let seconds = history.series("1s").unwrap();
+        assert_eq!(seconds.len(), 120);
+        assert_eq!(seconds[5].fps, 10.0);
+        assert_eq!(seconds[5].bitrate_bps, 8000.0);
+
+        let minutes = history.series("1m").unwrap();
+        assert_eq!(minutes.len(), 2);
+        assert_eq!(minutes[1].timestamp, 60);
+        assert_eq!(minutes[1].drops, 60);
+        assert_eq!(minutes[1].subscribers, 2);
+        assert!(history.series("5m").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,336 @@
+//! Extension point untuk aplikasi yang meng-embed broker
+//!
+//! Implement these traits and register them on [`crate::BrokerBuilder`] to
+//! change behaviour without patching the crate: who may publish or watch a
+//! stream ([`Authenticator`]), what happens to every ingested frame
+//! ([`FrameInterceptor`]) and where broker state is persisted ([`StateStore`]).
+//!
+//! Name resolution ([`Resolver`]) and TLS crypto ([`TlsBackend`]) are
+//! process-wide instead: they are installed with [`set_resolver`] and
+//! [`set_tls_backend`] before the config is parsed.
+
+use axum::http::HeaderMap;
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use std::{
+    fs, io,
+    net::SocketAddr,
+    sync::{Arc, OnceLock},
+};
+use tokio_rustls::rustls::crypto::{ring, CryptoProvider};
+
+use crate::{durable::write_atomic, error::BrokerError};
+
+/// Apa yang diminta klien pada sebuah stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// `POST`/WebSocket `/ingest/:stream_id`
+    Publish,
+    /// `/ws/:stream_id` and its data channel
+    Subscribe,
+}
+
+/// Request yang perlu diautentikasi
+#[derive(Debug, Clone, Copy)]
+pub struct AuthRequest<'a> {
+    pub access: Access,
+    pub stream_id: &'a str,
+    pub headers: &'a HeaderMap,
+    /// `?token=` of subscribers that cannot set headers
+    pub token: Option<&'a str>,
+}
+
+/// Identitas yang diterima sebuah [`Authenticator`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Identity {
+    /// Key of the subscriber's `PLAYBACK_MAX_SESSIONS` quota
+    pub subject: String,
+    /// Scopes for checks such as `METADATA_REDACTION`
+    pub scopes: Vec<String>,
+}
+
+/// Keputusan sebuah [`Authenticator`]
+#[derive(Debug)]
+pub enum AuthDecision {
+    /// Accept without the built-in checks
+    Allow(Identity),
+    /// Reject with this error (`401`, `403`, `503`, ...)
+    Deny(BrokerError),
+    /// No opinion: the next authenticator, then the built-in credentials, decide
+    Pass,
+}
+
+/// Autentikasi tambahan, dijalankan sebelum credential bawaan
+///
+/// Authenticators run in registration order; the first one that does not
+/// return [`AuthDecision::Pass`] decides. They also run when no built-in
+/// auth is configured, so an embedder can lock down an otherwise open broker.
+pub trait Authenticator: Send + Sync {
+    fn authenticate<'a>(&'a self, request: AuthRequest<'a>) -> BoxFuture<'a, AuthDecision>;
+}
+
+/// Dipanggil untuk setiap frame sebelum masuk DVR, rekaman dan subscriber
+///
+/// Runs on the ingest path, so it should be quick. It can rewrite the frame
+/// or reject it; the error goes back to the producer like any ingest error.
+pub trait FrameInterceptor: Send + Sync {
+    fn on_frame(&self, stream_id: &str, data: Bytes) -> Result<Bytes, BrokerError>;
+}
+
+/// Penyimpanan dokumen state broker: offset durable dan user lokal
+///
+/// Each document is named by its configured file (`DURABLE_OFFSETS_FILE`,
+/// `USERS_FILE`) and always written whole. Methods are called at startup,
+/// at shutdown and on the blocking pool, never on a runtime worker.
+pub trait StateStore: Send + Sync {
+    /// Isi dokumen `name`, `None` bila belum pernah disimpan
+    fn load(&self, name: &str) -> io::Result<Option<Vec<u8>>>;
+    fn save(&self, name: &str, contents: &[u8]) -> io::Result<()>;
+}
+
+/// Store bawaan: satu file per dokumen, ditulis lewat file sementara + rename
+#[derive(Debug, Default)]
+pub struct FileStore;
+
+impl StateStore for FileStore {
+    fn load(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(name) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn save(&self, name: &str, contents: &[u8]) -> io::Result<()> {
+        write_atomic(name, contents)
+    }
+}
+
+/// Resolusi nama host untuk semua koneksi keluar
+///
+/// The shared dialer, `outbound::connect`, asks it for the addresses it races.
+/// Devices without a usable system resolver (no `/etc/resolv.conf` in a
+/// minimal image, static host tables on a camera) can plug in their own.
+pub trait Resolver: Send + Sync {
+    /// Alamat untuk `host:port`, urutan preferensi resolver dipertahankan
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> BoxFuture<'a, io::Result<Vec<SocketAddr>>>;
+}
+
+/// Resolver bawaan: resolver sistem lewat `tokio::net::lookup_host`
+#[derive(Debug, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn resolve<'a>(&'a self, host: &'a str, port: u16) -> BoxFuture<'a, io::Result<Vec<SocketAddr>>> {
+        Box::pin(async move { Ok(tokio::net::lookup_host((host, port)).await?.collect()) })
+    }
+}
+
+/// Kriptografi untuk semua konfigurasi TLS broker
+///
+/// Used for the listener (`TLS_CERT_PATH`) and every outbound client config
+/// (`*_CA_PATH`). The default, [`RingBackend`], is pure rustls on *ring*: it
+/// needs no OpenSSL and cross-compiles to musl and ARM with just a C
+/// compiler. Hardware with a crypto engine or a FIPS module can supply its
+/// own rustls provider instead.
+pub trait TlsBackend: Send + Sync {
+    /// Dipakai di log startup
+    fn name(&self) -> &str;
+    fn provider(&self) -> Arc<CryptoProvider>;
+}
+
+/// Backend bawaan: rustls dengan *ring*
+#[derive(Debug, Default)]
+pub struct RingBackend;
+
+impl TlsBackend for RingBackend {
+    fn name(&self) -> &str {
+        "rustls/ring"
+    }
+
+    fn provider(&self) -> Arc<CryptoProvider> {
+        Arc::new(ring::default_provider())
+    }
+}
+
+static RESOLVER: OnceLock<Arc<dyn Resolver>> = OnceLock::new();
+static TLS_BACKEND: OnceLock<Arc<dyn TlsBackend>> = OnceLock::new();
+
+/// Pasang resolver untuk seluruh proses; gagal bila sudah ada yang dipakai
+///
+/// Call it before the first outbound connection, i.e. before
+/// [`crate::BrokerBuilder::build`].
+pub fn set_resolver(resolver: impl Resolver + 'static) -> Result<(), String> {
+    RESOLVER
+        .set(Arc::new(resolver))
+        .map_err(|_| "a resolver is already installed".to_string())
+}
+
+/// Pasang backend TLS untuk seluruh proses; gagal bila sudah ada yang dipakai
+///
+/// Client configs for `*_CA_PATH` are built while the config is parsed, so
+/// call it before [`crate::Config::from_env`].
+pub fn set_tls_backend(backend: impl TlsBackend + 'static) -> Result<(), String> {
+    TLS_BACKEND
+        .set(Arc::new(backend))
+        .map_err(|_| "a TLS backend is already installed".to_string())
+}
+
+pub(crate) fn resolver() -> &'static dyn Resolver {
+    RESOLVER.get_or_init(|| Arc::new(SystemResolver)).as_ref()
+}
+
+pub(crate) fn tls_backend() -> &'static dyn TlsBackend {
+    TLS_BACKEND.get_or_init(|| Arc::new(RingBackend)).as_ref()
+}
+
+/// Semua hook yang terdaftar pada satu broker
+#[derive(Clone)]
+pub struct Hooks {
+    pub(crate) authenticators: Vec<Arc<dyn Authenticator>>,
+    pub(crate) interceptors: Vec<Arc<dyn FrameInterceptor>>,
+    pub(crate) store: Arc<dyn StateStore>,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            authenticators: Vec::new(),
+            interceptors: Vec::new(),
+            store: Arc::new(FileStore),
+        }
+    }
+}
+
+impl std::fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hooks")
+            .field("authenticators", &self.authenticators.len())
+            .field("interceptors", &self.interceptors.len())
+            .finish()
+    }
+}
+
+impl Hooks {
+    /// Keputusan authenticator pertama yang tidak `Pass`
+    pub(crate) async fn authenticate(&self, request: AuthRequest<'_>) -> AuthDecision {
+        for authenticator in &self.authenticators {
+            match authenticator.authenticate(request).await {
+                AuthDecision::Pass => continue,
+                decision => return decision,
+            }
+        }
+        AuthDecision::Pass
+    }
+
+    /// Jalankan semua interceptor berurutan
+    pub(crate) fn intercept(&self, stream_id: &str, data: Bytes) -> Result<Bytes, BrokerError> {
+        self.interceptors
+            .iter()
+            .try_fold(data, |data, interceptor| interceptor.on_frame(stream_id, data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, playback, Broker};
+    use axum::{body::Body, extract::Request, http::StatusCode};
+    use parking_lot::Mutex;
+    use std::collections::HashMap;
+    use tower::util::ServiceExt;
+
+    /// Robot dengan header `x-robot-key` yang benar boleh publish dan menonton
+    struct RobotKey;
+
+    impl Authenticator for RobotKey {
+        fn authenticate<'a>(&'a self, request: AuthRequest<'a>) -> BoxFuture<'a, AuthDecision> {
+            Box::pin(async move {
+                match request.headers.get("x-robot-key").map(|key| key == "k") {
+                    Some(true) => AuthDecision::Allow(Identity {
+                        subject: "robot".to_string(),
+                        scopes: vec!["metadata:full".to_string()],
+                    }),
+                    Some(false) => AuthDecision::Deny(BrokerError::Unauthorized("bad robot key".to_string())),
+                    None if request.access == Access::Publish => {
+                        AuthDecision::Deny(BrokerError::Unauthorized("robot key required".to_string()))
+                    }
+                    None => AuthDecision::Pass,
+                }
+            })
+        }
+    }
+
+    /// Tolak frame bertanda `bad`, beri prefix `cam1:` pada yang lain
+    struct Tagger;
+
+    impl FrameInterceptor for Tagger {
+        fn on_frame(&self, stream_id: &str, data: Bytes) -> Result<Bytes, BrokerError> {
+            if data.starts_with(b"bad") {
+                return Err(BrokerError::InvalidFrame("marked bad".to_string()));
+            }
+            Ok([stream_id.as_bytes(), b":", &data].concat().into())
+        }
+    }
+
+    #[derive(Default, Clone)]
+    struct MemoryStore(Arc<Mutex<HashMap<String, Vec<u8>>>>);
+
+    impl StateStore for MemoryStore {
+        fn load(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+            Ok(self.0.lock().get(name).cloned())
+        }
+
+        fn save(&self, name: &str, contents: &[u8]) -> io::Result<()> {
+            self.0.lock().insert(name.to_string(), contents.to_vec());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embedder_hooks_extend_auth_ingest_and_storage() {
+        let store = MemoryStore::default();
+        let offsets = br#"{"streams":{"cam1":{"last_seq":41,"subscribers":{"recorder":40}}}}"#;
+        store.save("offsets", offsets).unwrap();
+        let broker = Broker::builder()
+            .config(Config {
+                durable_offsets_file: Some("offsets".to_string()),
+                ..Config::default()
+            })
+            .authenticator(RobotKey)
+            .frame_interceptor(Tagger)
+            .state_store(store.clone())
+            .build()
+            .await
+            .unwrap();
+        let app = broker.router();
+        let ingest = |key: Option<&str>, body: &'static str| {
+            let mut request = Request::post("/ingest/cam1");
+            if let Some(key) = key {
+                request = request.header("x-robot-key", key);
+            }
+            app.clone().oneshot(request.body(Body::from(body)).unwrap())
+        };
+
+        // Authenticator memutuskan lebih dulu, bahkan tanpa credential bawaan
+        assert_eq!(ingest(None, "frame").await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(ingest(Some("wrong"), "frame").await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let mut frames = broker.subscribe("cam1");
+        assert!(ingest(Some("k"), "frame").await.unwrap().status().is_success());
+        // Offset dari state store: seq melanjutkan dari 41
+        let frame = frames.recv().await.unwrap();
+        assert_eq!((frame.seq, &frame.data[..]), (42, &b"cam1:frame"[..]));
+        assert_eq!(ingest(Some("k"), "bad frame").await.unwrap().status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let mut headers = axum::http::HeaderMap::new();
+        let open = playback::authorize(&broker.state, "cam1", &headers, None).await.unwrap();
+        assert!(open.scopes.is_empty());
+        headers.insert("x-robot-key", "k".parse().unwrap());
+        let robot = playback::authorize(&broker.state, "cam1", &headers, None).await.unwrap();
+        assert_eq!(robot.scopes, vec!["metadata:full".to_string()]);
+
+        broker.shutdown();
+        let saved = String::from_utf8(store.load("offsets").unwrap().unwrap()).unwrap();
+        assert!(saved.contains("recorder"));
+    }
+}
@@ -0,0 +1,583 @@
+//! Decoder JPEG baseline minimal untuk transform MJPEG (watermark)
+//!
+//! Supports baseline and extended sequential Huffman JPEG with 8-bit samples,
+//! grayscale or YCbCr, any chroma subsampling, restart intervals and
+//! non-interleaved scans. Progressive, arithmetic-coded, lossless, 12-bit and
+//! CMYK images are rejected with an error.
+
+use std::f32::consts::PI;
+
+/// Gambar RGB hasil decode, 3 byte per piksel, baris demi baris
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbImage {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 3],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> [u8; 3] {
+        let i = (y * self.width + x) * 3;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2]]
+    }
+
+    /// Campur warna ke piksel; `alpha` 0 (tidak berubah) sampai 255 (ditimpa)
+    pub fn blend(&mut self, x: usize, y: usize, color: [u8; 3], alpha: u8) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let i = (y * self.width + x) * 3;
+        for (channel, value) in color.iter().enumerate() {
+            let old = self.pixels[i + channel] as u32;
+            let new = *value as u32;
+            let a = alpha as u32;
+            self.pixels[i + channel] = ((new * a + old * (255 - a)) / 255) as u8;
+        }
+    }
+}
+
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27,
+    20, 13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58,
+    59, 52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+/// Tabel Huffman dalam bentuk canonical (maxcode/valptr per panjang kode)
+#[derive(Debug, Clone, Default)]
+struct Huffman {
+    /// Kode terbesar per panjang 1..=16 (-1 jika tidak ada)
+    max_code: [i32; 17],
+    /// Index simbol pertama per panjang dikurangi kode pertama
+    offset: [i32; 17],
+    values: Vec<u8>,
+}
+
+impl Huffman {
+    fn new(counts: &[u8; 16], values: Vec<u8>) -> Result<Self, String> {
+        if counts.iter().map(|&c| c as usize).sum::<usize>() != values.len() {
+            return Err("Huffman table length mismatch".to_string());
+        }
+        let mut table = Huffman {
+            max_code: [-1; 17],
+            offset: [0; 17],
+            values,
+        };
+        let (mut code, mut index) = (0i32, 0i32);
+        for len in 1..=16 {
+            let count = counts[len - 1] as i32;
+            if count > 0 {
+                table.offset[len] = index - code;
+                code += count;
+                index += count;
+                table.max_code[len] = code - 1;
+            }
+            code <<= 1;
+        }
+        Ok(table)
+    }
+}
+
+/// Pembaca bit entropy-coded data: byte stuffing 0xFF00 dan marker
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bits: u32,
+    count: u32,
+    /// Marker ditemukan; setelahnya bit diisi nol
+    marker: Option<u8>,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self {
+            data,
+            pos,
+            bits: 0,
+            count: 0,
+            marker: None,
+        }
+    }
+
+    fn fill(&mut self) {
+        while self.count <= 24 {
+            let mut byte = 0;
+            if self.marker.is_none() && self.pos < self.data.len() {
+                byte = self.data[self.pos];
+                if byte == 0xFF {
+                    let next = self.data.get(self.pos + 1).copied().unwrap_or(0xD9);
+                    if next == 0x00 {
+                        self.pos += 2;
+                    } else {
+                        self.marker = Some(next);
+                        byte = 0;
+                    }
+                } else {
+                    self.pos += 1;
+                }
+            }
+            self.bits |= (byte as u32) << (24 - self.count);
+            self.count += 8;
+        }
+    }
+
+    fn bit(&mut self) -> u32 {
+        self.fill();
+        let bit = self.bits >> 31;
+        self.bits <<= 1;
+        self.count -= 1;
+        bit
+    }
+
+    fn receive(&mut self, len: u8) -> u32 {
+        if len == 0 {
+            return 0;
+        }
+        self.fill();
+        let value = self.bits >> (32 - len as u32);
+        self.bits <<= len;
+        self.count -= len as u32;
+        value
+    }
+
+    /// Nilai `len` bit sebagai koefisien bertanda (EXTEND dari spesifikasi)
+    fn receive_extend(&mut self, len: u8) -> i32 {
+        if len == 0 {
+            return 0;
+        }
+        let value = self.receive(len) as i32;
+        if value < 1 << (len - 1) {
+            value - (1 << len) + 1
+        } else {
+            value
+        }
+    }
+
+    fn decode(&mut self, table: &Huffman) -> Result<u8, String> {
+        let mut code = 0i32;
+        for len in 1..=16 {
+            code = (code << 1) | self.bit() as i32;
+            if code <= table.max_code[len] {
+                let index = (table.offset[len] + code) as usize;
+                return table.values.get(index).copied().ok_or_else(|| "bad Huffman code".to_string());
+            }
+        }
+        Err("bad Huffman code".to_string())
+    }
+
+    /// Lewati marker RSTn setelah interval restart
+    fn restart(&mut self) -> Result<(), String> {
+        self.bits = 0;
+        self.count = 0;
+        match self.marker.take() {
+            Some(0xD0..=0xD7) => {
+                self.pos += 2;
+                Ok(())
+            }
+            Some(other) => Err(format!("expected RST marker, found FF{:02X}", other)),
+            None => {
+                // Encoder mungkin menambahkan padding sebelum marker
+                while self.pos + 1 < self.data.len() {
+                    if self.data[self.pos] == 0xFF && (0xD0..=0xD7).contains(&self.data[self.pos + 1]) {
+                        self.pos += 2;
+                        return Ok(());
+                    }
+                    self.pos += 1;
+                }
+                Err("missing RST marker".to_string())
+            }
+        }
+    }
+
+    /// Posisi marker berikutnya setelah scan selesai
+    fn end(&self) -> usize {
+        let mut pos = self.pos;
+        while pos + 1 < self.data.len() {
+            if self.data[pos] == 0xFF && !matches!(self.data[pos + 1], 0x00 | 0xD0..=0xD7 | 0xFF) {
+                return pos;
+            }
+            pos += 1;
+        }
+        self.data.len()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+    quant: usize,
+    /// Lebar/tinggi dalam blok 8x8 (dibulatkan ke MCU)
+    blocks_w: usize,
+    blocks_h: usize,
+    /// Sampel hasil IDCT, `blocks_w * 8` per baris
+    samples: Vec<u8>,
+    dc_table: usize,
+    ac_table: usize,
+    dc_pred: i32,
+}
+
+/// Tabel cos untuk IDCT: `cos((2x + 1) u pi / 16) * C(u)`
+fn idct_table() -> [[f32; 8]; 8] {
+    let mut table = [[0.0; 8]; 8];
+    for (x, row) in table.iter_mut().enumerate() {
+        for (u, value) in row.iter_mut().enumerate() {
+            let c = if u == 0 { 1.0 / 2f32.sqrt() } else { 1.0 };
+            *value = c * ((2 * x + 1) as f32 * u as f32 * PI / 16.0).cos();
+        }
+    }
+    table
+}
+
+fn idct_block(coefficients: &[i32; 64], table: &[[f32; 8]; 8], out: &mut [u8], stride: usize) {
+    let mut temp = [0f32; 64];
+    // Baris: transform per u menjadi x
+    for v in 0..8 {
+        for x in 0..8 {
+            let mut sum = 0.0;
+            for u in 0..8 {
+                sum += table[x][u] * coefficients[v * 8 + u] as f32;
+            }
+            temp[v * 8 + x] = sum / 2.0;
+        }
+    }
+    for x in 0..8 {
+        for y in 0..8 {
+            let mut sum = 0.0;
+            for v in 0..8 {
+                sum += table[y][v] * temp[v * 8 + x];
+            }
+            let value = (sum / 2.0 + 128.0).round().clamp(0.0, 255.0);
+            out[y * stride + x] = value as u8;
+        }
+    }
+}
+
+fn be16(data: &[u8], pos: usize) -> Result<usize, String> {
+    match data.get(pos..pos + 2) {
+        Some(bytes) => Ok(u16::from_be_bytes([bytes[0], bytes[1]]) as usize),
+        None => Err("truncated JPEG".to_string()),
+    }
+}
+
+/// Decode JPEG baseline menjadi RGB
+pub fn decode(data: &[u8]) -> Result<RgbImage, String> {
+    if data.get(0..2) != Some(&[0xFF, 0xD8]) {
+        return Err("not a JPEG (missing SOI)".to_string());
+    }
+    let mut quant = [[0u16; 64]; 4];
+    let mut dc_tables: [Option<Huffman>; 4] = Default::default();
+    let mut ac_tables: [Option<Huffman>; 4] = Default::default();
+    let mut components: Vec<Component> = Vec::new();
+    let (mut width, mut height) = (0usize, 0usize);
+    let (mut h_max, mut v_max) = (1usize, 1usize);
+    let mut restart_interval = 0usize;
+    let table = idct_table();
+    let mut pos = 2;
+
+    loop {
+        // Lewati fill byte 0xFF sebelum marker
+        while data.get(pos) == Some(&0xFF) && data.get(pos + 1) == Some(&0xFF) {
+            pos += 1;
+        }
+        if data.get(pos) != Some(&0xFF) {
+            return Err("expected JPEG marker".to_string());
+        }
+        let marker = *data.get(pos + 1).ok_or("truncated JPEG")?;
+        pos += 2;
+        match marker {
+            0xD9 => break,
+            0xD0..=0xD7 | 0x01 => continue,
+            _ => {}
+        }
+        let len = be16(data, pos)?;
+        let segment = data.get(pos + 2..pos + len).ok_or("truncated JPEG segment")?;
+        match marker {
+            0xDB => {
+                let mut i = 0;
+                while i < segment.len() {
+                    let (precision, id) = (segment[i] >> 4, (segment[i] & 15) as usize);
+                    let table = quant.get_mut(id).ok_or("bad quantization table id")?;
+                    i += 1;
+                    for &zz in ZIGZAG.iter() {
+                        let value = if precision == 0 {
+                            *segment.get(i).ok_or("truncated DQT")? as u16
+                        } else {
+                            be16(segment, i)? as u16
+                        };
+                        table[zz] = value;
+                        i += if precision == 0 { 1 } else { 2 };
+                    }
+                }
+            }
+            0xC4 => {
+                let mut i = 0;
+                while i < segment.len() {
+                    let (class, id) = (segment[i] >> 4, (segment[i] & 15) as usize);
+                    let counts: [u8; 16] = segment
+                        .get(i + 1..i + 17)
+                        .ok_or("truncated DHT")?
+                        .try_into()
+                        .expect("16 bytes");
+                    let total: usize = counts.iter().map(|&c| c as usize).sum();
+                    let values = segment.get(i + 17..i + 17 + total).ok_or("truncated DHT")?.to_vec();
+                    let huffman = Some(Huffman::new(&counts, values)?);
+                    match (class, id) {
+                        (0, 0..=3) => dc_tables[id] = huffman,
+                        (1, 0..=3) => ac_tables[id] = huffman,
+                        _ => return Err("bad Huffman table id".to_string()),
+                    }
+                    i += 17 + total;
+                }
+            }
+            0xC0 | 0xC1 => {
+                if segment.first() != Some(&8) {
+                    return Err("only 8-bit JPEG is supported".to_string());
+                }
+                height = be16(segment, 1)?;
+                width = be16(segment, 3)?;
+                let count = *segment.get(5).ok_or("truncated SOF")? as usize;
+                if width == 0 || height == 0 || !(count == 1 || count == 3) {
+                    return Err("unsupported JPEG dimensions or components".to_string());
+                }
+                for c in 0..count {
+                    let spec = segment.get(6 + c * 3..9 + c * 3).ok_or("truncated SOF")?;
+                    let (h, v) = ((spec[1] >> 4) as usize, (spec[1] & 15) as usize);
+                    if !(1..=4).contains(&h) || !(1..=4).contains(&v) || spec[2] > 3 {
+                        return Err("bad component sampling".to_string());
+                    }
+                    components.push(Component {
+                        id: spec[0],
+                        h,
+                        v,
+                        quant: spec[2] as usize,
+                        blocks_w: 0,
+                        blocks_h: 0,
+                        samples: Vec::new(),
+                        dc_table: 0,
+                        ac_table: 0,
+                        dc_pred: 0,
+                    });
+                }
+                h_max = components.iter().map(|c| c.h).max().unwrap_or(1);
+                v_max = components.iter().map(|c| c.v).max().unwrap_or(1);
+                let mcus_x = width.div_ceil(8 * h_max);
+                let mcus_y = height.div_ceil(8 * v_max);
+                for component in &mut components {
+                    component.blocks_w = mcus_x * component.h;
+                    component.blocks_h = mcus_y * component.v;
+                    component.samples = vec![0; component.blocks_w * 8 * component.blocks_h * 8];
+                }
+            }
+            0xC2 | 0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => {
+                return Err("progressive, lossless and arithmetic JPEG are not supported".to_string());
+            }
+            0xDD => restart_interval = be16(segment, 0)?,
+            0xDA => {
+                if components.is_empty() {
+                    return Err("scan before frame header".to_string());
+                }
+                let count = *segment.first().ok_or("truncated SOS")? as usize;
+                let mut scan = Vec::with_capacity(count);
+                for c in 0..count {
+                    let spec = segment.get(1 + c * 2..3 + c * 2).ok_or("truncated SOS")?;
+                    let index = components
+                        .iter()
+                        .position(|component| component.id == spec[0])
+                        .ok_or("scan references unknown component")?;
+                    components[index].dc_table = (spec[1] >> 4) as usize & 3;
+                    components[index].ac_table = (spec[1] & 15) as usize & 3;
+                    components[index].dc_pred = 0;
+                    scan.push(index);
+                }
+                pos = decode_scan(
+                    data,
+                    pos + len,
+                    &mut components,
+                    &scan,
+                    (&dc_tables, &ac_tables, &quant),
+                    restart_interval,
+                    (width, height, h_max, v_max),
+                    &table,
+                )?;
+                continue;
+            }
+            _ => {}
+        }
+        pos += len;
+    }
+
+    if components.is_empty() {
+        return Err("JPEG has no frame header".to_string());
+    }
+    Ok(to_rgb(&components, width, height, h_max, v_max))
+}
+
+type Tables<'a> = (&'a [Option<Huffman>; 4], &'a [Option<Huffman>; 4], &'a [[u16; 64]; 4]);
+
+#[allow(clippy::too_many_arguments)]
+fn decode_scan(
+    data: &[u8],
+    start: usize,
+    components: &mut [Component],
+    scan: &[usize],
+    (dc_tables, ac_tables, quant): Tables<'_>,
+    restart_interval: usize,
+    (width, height, h_max, v_max): (usize, usize, usize, usize),
+    table: &[[f32; 8]; 8],
+) -> Result<usize, String> {
+    let mut reader = BitReader::new(data, start);
+    let decode_block = |reader: &mut BitReader<'_>, component: &mut Component, bx: usize, by: usize| {
+        let dc = dc_tables[component.dc_table].as_ref().ok_or("missing DC table")?;
+        let ac = ac_tables[component.ac_table].as_ref().ok_or("missing AC table")?;
+        let q = &quant[component.quant];
+        let mut coefficients = [0i32; 64];
+        let size = reader.decode(dc)?;
+        component.dc_pred += reader.receive_extend(size);
+        coefficients[0] = component.dc_pred * q[0] as i32;
+        let mut k = 1;
+        while k < 64 {
+            let symbol = reader.decode(ac)?;
+            let (run, size) = ((symbol >> 4) as usize, symbol & 15);
+            if size == 0 {
+                if run != 15 {
+                    break;
+                }
+                k += 16;
+                continue;
+            }
+            k += run;
+            if k > 63 {
+                return Err("AC coefficient out of range".to_string());
+            }
+            coefficients[ZIGZAG[k]] = reader.receive_extend(size) * q[ZIGZAG[k]] as i32;
+            k += 1;
+        }
+        let stride = component.blocks_w * 8;
+        let offset = by * 8 * stride + bx * 8;
+        idct_block(&coefficients, table, &mut component.samples[offset..], stride);
+        Ok::<(), String>(())
+    };
+
+    if scan.len() == 1 {
+        // Non-interleaved: blok komponen berurutan, hanya yang menutupi gambar
+        let component = &mut components[scan[0]];
+        let blocks_w = (width * component.h).div_ceil(h_max).div_ceil(8);
+        let blocks_h = (height * component.v).div_ceil(v_max).div_ceil(8);
+        for n in 0..blocks_w * blocks_h {
+            if restart_interval > 0 && n > 0 && n % restart_interval == 0 {
+                reader.restart()?;
+                component.dc_pred = 0;
+            }
+            decode_block(&mut reader, component, n % blocks_w, n / blocks_w)?;
+        }
+    } else {
+        let mcus_x = width.div_ceil(8 * h_max);
+        let mcus_y = height.div_ceil(8 * v_max);
+        for n in 0..mcus_x * mcus_y {
+            if restart_interval > 0 && n > 0 && n % restart_interval == 0 {
+                reader.restart()?;
+                for &index in scan {
+                    components[index].dc_pred = 0;
+                }
+            }
+            let (mx, my) = (n % mcus_x, n / mcus_x);
+            for &index in scan {
+                let component = &mut components[index];
+                for v in 0..component.v {
+                    for h in 0..component.h {
+                        let (bx, by) = (mx * component.h + h, my * component.v + v);
+                        decode_block(&mut reader, component, bx, by)?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(reader.end())
+}
+
+fn to_rgb(components: &[Component], width: usize, height: usize, h_max: usize, v_max: usize) -> RgbImage {
+    let mut image = RgbImage::new(width, height);
+    let sample = |component: &Component, x: usize, y: usize| -> f32 {
+        let (sx, sy) = (x * component.h / h_max, y * component.v / v_max);
+        component.samples[sy * component.blocks_w * 8 + sx] as f32
+    };
+    for y in 0..height {
+        for x in 0..width {
+            let i = (y * width + x) * 3;
+            let luma = sample(&components[0], x, y);
+            let rgb = if components.len() == 3 {
+                let cb = sample(&components[1], x, y) - 128.0;
+                let cr = sample(&components[2], x, y) - 128.0;
+                [
+                    luma + 1.402 * cr,
+                    luma - 0.344_136 * cb - 0.714_136 * cr,
+                    luma + 1.772 * cb,
+                ]
+            } else {
+                [luma; 3]
+            };
+            for (channel, value) in rgb.iter().enumerate() {
+                image.pixels[i + channel] = value.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jpeg_encoder::{ColorType, Encoder, SamplingFactor};
+
+    #[test]
+    fn test_decode_roundtrip() {
+        let (width, height) = (37u16, 21u16);
+        let mut rgb = Vec::new();
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let pixel = if x < 18 { [200, 40, 40] } else { [30, 60, 220] };
+                rgb.extend_from_slice(&if y > 15 { [250, 250, 250] } else { pixel });
+            }
+        }
+        for sampling in [SamplingFactor::R_4_4_4, SamplingFactor::R_4_2_0] {
+            for restart in [None, Some(2)] {
+                let mut jpeg = Vec::new();
+                let mut encoder = Encoder::new(&mut jpeg, 95);
+                encoder.set_sampling_factor(sampling);
+                encoder.set_restart_interval(restart.unwrap_or(0));
+                encoder.encode(&rgb, width, height, ColorType::Rgb).unwrap();
+                let image = decode(&jpeg).unwrap();
+                assert_eq!((image.width, image.height), (37, 21));
+                for (x, y, expected) in [(4, 4, [200, 40, 40]), (30, 6, [30, 60, 220]), (20, 19, [250, 250, 250])] {
+                    let actual = image.get(x, y);
+                    for channel in 0..3 {
+                        let diff = (actual[channel] as i32 - expected[channel]).abs();
+                        assert!(diff < 16, "{:?} at ({}, {}) vs {:?}", actual, x, y, expected);
+                    }
+                }
+            }
+        }
+
+        let mut gray = Vec::new();
+        Encoder::new(&mut gray, 90)
+            .encode(&[128u8; 64], 8, 8, ColorType::Luma)
+            .unwrap();
+        assert!(decode(&gray).unwrap().get(3, 3)[0].abs_diff(128) < 4);
+        assert!(decode(b"not a jpeg").is_err());
+
+        let mut progressive = Vec::new();
+        let mut encoder = Encoder::new(&mut progressive, 90);
+        encoder.set_progressive(true);
+        encoder.encode(&rgb, width, height, ColorType::Rgb).unwrap();
+        assert!(decode(&progressive).is_err());
+    }
+}
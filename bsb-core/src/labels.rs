@@ -0,0 +1,179 @@
+use axum::{
+    extract::{Path as AxumPath, Query, State},
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Deserializer};
+use serde_json::json;
+use std::collections::BTreeMap;
+use tracing::info;
+
+use crate::{error::BrokerError, AppState};
+
+/// Label key/value per stream (lokasi, model device, tenant, ...)
+pub type Labels = BTreeMap<String, String>;
+
+/// Parse `key=value,key=value` (producer `?labels=` and admin API)
+pub fn parse_labels(spec: &str) -> Result<Labels, String> {
+    let mut labels = Labels::new();
+    for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("invalid label {:?} (expected key=value)", pair))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(format!("invalid label {:?} (empty key)", pair));
+        }
+        labels.insert(key.to_string(), value.trim().to_string());
+    }
+    Ok(labels)
+}
+
+/// Serde helper untuk query parameter `?labels=key=value,...`
+pub fn deserialize_labels<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Labels, D::Error> {
+    let spec = String::deserialize(deserializer)?;
+    parse_labels(&spec).map_err(serde::de::Error::custom)
+}
+
+/// Satu syarat dalam label selector
+#[derive(Debug, Clone, PartialEq)]
+enum Requirement {
+    Equals(String, String),
+    NotEquals(String, String),
+    Exists(String),
+    NotExists(String),
+}
+
+/// Label selector: `site=a,tier!=test,gpu,!deprecated`
+///
+/// All requirements must hold. `key!=value` also matches streams without
+/// the label, like Kubernetes selectors.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LabelSelector {
+    requirements: Vec<Requirement>,
+}
+
+impl LabelSelector {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut requirements = Vec::new();
+        for term in spec.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            let requirement = if let Some((key, value)) = term.split_once("!=") {
+                Requirement::NotEquals(key.trim().to_string(), value.trim().to_string())
+            } else if let Some((key, value)) = term.split_once('=') {
+                Requirement::Equals(key.trim().to_string(), value.trim().to_string())
+            } else if let Some(key) = term.strip_prefix('!') {
+                Requirement::NotExists(key.trim().to_string())
+            } else {
+                Requirement::Exists(term.to_string())
+            };
+            let key = match &requirement {
+                Requirement::Equals(key, _)
+                | Requirement::NotEquals(key, _)
+                | Requirement::Exists(key)
+                | Requirement::NotExists(key) => key,
+            };
+            if key.is_empty() {
+                return Err(format!("invalid selector term {:?} (empty key)", term));
+            }
+            requirements.push(requirement);
+        }
+        Ok(Self { requirements })
+    }
+
+    pub fn matches(&self, labels: &Labels) -> bool {
+        self.requirements.iter().all(|requirement| match requirement {
+            Requirement::Equals(key, value) => labels.get(key) == Some(value),
+            Requirement::NotEquals(key, value) => labels.get(key) != Some(value),
+            Requirement::Exists(key) => labels.contains_key(key),
+            Requirement::NotExists(key) => !labels.contains_key(key),
+        })
+    }
+}
+
+/// Query parameter untuk GET /api/streams
+#[derive(Debug, Deserialize)]
+pub struct ListParams {
+    selector: Option<String>,
+}
+
+/// Body untuk PUT /api/streams/:id/labels
+#[derive(Debug, Deserialize)]
+pub struct LabelsBody {
+    labels: Labels,
+}
+
+/// Handler untuk GET /api/streams?selector=
+/// List streams with their labels, optionally filtered by a label selector
+pub async fn list_streams_handler(
+    Query(params): Query<ListParams>,
+    State(state): State<AppState>,
+) -> Response {
+    let selector = match params.selector.as_deref().map(LabelSelector::parse).transpose() {
+        Ok(selector) => selector.unwrap_or_default(),
+        Err(e) => return BrokerError::InvalidRequest(e).into_response(),
+    };
+    let streams = state.streams.lock();
+    let mut listed: Vec<_> = streams
+        .iter()
+        .filter(|(_, entry)| selector.matches(&entry.labels))
+        .map(|(id, entry)| {
+            json!({
+                "stream": id,
+                "labels": entry.labels,
+                "subscribers": entry.tx.receiver_count(),
+                "last_seq": entry.last_seq(),
+                "format": entry.format.format(),
+            })
+        })
+        .collect();
+    listed.sort_by(|a, b| a["stream"].as_str().cmp(&b["stream"].as_str()));
+    Json(json!({ "streams": listed })).into_response()
+}
+
+/// Handler untuk GET /api/streams/:id/labels
+pub async fn get_labels_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Response {
+    match state.streams.lock().get(&stream_id) {
+        Some(entry) => Json(json!({ "stream": stream_id, "labels": entry.labels })).into_response(),
+        None => BrokerError::StreamNotFound(stream_id).into_response(),
+    }
+}
+
+/// Handler untuk PUT /api/streams/:id/labels
+/// Replace all labels; creates the stream so it can be labelled before the producer starts
+pub async fn put_labels_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+    Json(body): Json<LabelsBody>,
+) -> Json<serde_json::Value> {
+    info!("Setting {} labels on stream {}", body.labels.len(), stream_id);
+    let labels = state.with_stream(&stream_id, |entry| {
+        entry.labels = body.labels;
+        entry.labels.clone()
+    });
+    // Subscriber `/mux?selector=` mengevaluasi ulang membership
+    state.stream_changes.send_modify(|generation| *generation += 1);
+    Json(json!({ "stream": stream_id, "labels": labels }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_selector() {
+        let labels = parse_labels("site=a, model=x100,gpu=").unwrap();
+        assert_eq!(labels["model"], "x100");
+
+        let selector = LabelSelector::parse("site=a,model!=x200,gpu,!deprecated").unwrap();
+        assert!(selector.matches(&labels));
+        assert!(!LabelSelector::parse("site=b").unwrap().matches(&labels));
+        assert!(!LabelSelector::parse("!gpu").unwrap().matches(&labels));
+        assert!(LabelSelector::parse("tenant!=acme").unwrap().matches(&labels));
+        assert!(LabelSelector::default().matches(&Labels::new()));
+
+        assert!(parse_labels("site").is_err());
+        assert!(LabelSelector::parse("=a").is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use axum::{extract::State, response::Json};
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{collections::VecDeque, time::Duration};
+use tracing::{info, warn};
+
+use crate::{stats::unix_now_ms, AppState};
+
+/// Subsystem yang dihitung di setiap sampel, urutan sama dengan `LeakSample::counts`
+pub const SUBSYSTEMS: [&str; 8] = [
+    "streams",
+    "dvr_frames",
+    "dvr_bytes",
+    "channel_queued",
+    "connections",
+    "playback_sessions",
+    "replay_guards",
+    "rss_bytes",
+];
+
+/// Detektor kebocoran dari `LEAK_*`
+#[derive(Debug, Clone)]
+pub struct LeakConfig {
+    /// How often the subsystems are counted
+    pub sample_interval: Duration,
+    /// Samples a subsystem must keep growing over before it is flagged
+    pub window: usize,
+}
+
+impl Default for LeakConfig {
+    fn default() -> Self {
+        Self {
+            sample_interval: Duration::from_secs(300),
+            window: 36,
+        }
+    }
+}
+
+impl LeakConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.sample_interval.is_zero() {
+            return Err("LEAK_SAMPLE_SECS must be greater than 0".to_string());
+        }
+        if self.window < 3 {
+            return Err("LEAK_WINDOW must be at least 3".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Hitungan semua subsystem pada satu waktu
+#[derive(Debug, Clone, Serialize)]
+pub struct LeakSample {
+    pub at_ms: u64,
+    /// Live subscribers and data channel clients: what the counts are expected to follow
+    pub load: u64,
+    pub counts: [u64; SUBSYSTEMS.len()],
+}
+
+/// Hasil analisis satu subsystem di jendela sampel
+#[derive(Debug, Clone, Serialize)]
+pub struct SubsystemTrend {
+    pub name: &'static str,
+    pub first: u64,
+    pub current: u64,
+    pub growth_per_hour: f64,
+    /// Samples that were higher than the one before
+    pub rising_samples: usize,
+    /// Grew at every step of a full window, faster than the load
+    pub suspected: bool,
+}
+
+/// Sampel terakhir dan subsystem yang sedang dicurigai bocor
+#[derive(Debug, Default)]
+pub struct LeakDetector {
+    samples: Mutex<VecDeque<LeakSample>>,
+    suspected: Mutex<Vec<&'static str>>,
+}
+
+impl LeakDetector {
+    /// Simpan sampel; subsystem yang baru dicurigai atau pulih dilaporkan ke log
+    pub fn record(&self, sample: LeakSample, window: usize) {
+        let trends = {
+            let mut samples = self.samples.lock();
+            if samples.len() == window {
+                samples.pop_front();
+            }
+            samples.push_back(sample);
+            analyze(samples.make_contiguous(), window)
+        };
+        let now: Vec<_> = trends.iter().filter(|trend| trend.suspected).map(|trend| trend.name).collect();
+        let previous = std::mem::replace(&mut *self.suspected.lock(), now.clone());
+        for trend in trends.iter().filter(|trend| trend.suspected && !previous.contains(&trend.name)) {
+            warn!(
+                "Possible leak in {}: {} -> {} over {} samples without matching load ({:.0}/hour)",
+                trend.name, trend.first, trend.current, window, trend.growth_per_hour
+            );
+        }
+        for name in previous.iter().filter(|name| !now.contains(name)) {
+            info!("{} stopped growing, no longer a leak suspect", name);
+        }
+    }
+
+    pub fn suspected(&self) -> Vec<&'static str> {
+        self.suspected.lock().clone()
+    }
+}
+
+/// Tren per subsystem; hanya jendela penuh yang bisa menandai kebocoran
+///
+/// A subsystem is suspected when it never shrank across the window, grew in
+/// at least half of its steps (a single new stream is not a leak) and grew
+/// relatively more than the load did, so counts that simply follow more
+/// viewers are not flagged.
+pub fn analyze(samples: &[LeakSample], window: usize) -> Vec<SubsystemTrend> {
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        return Vec::new();
+    };
+    let hours = last.at_ms.saturating_sub(first.at_ms) as f64 / 3_600_000.0;
+    let relative = |from: u64, to: u64| (to as f64 - from as f64) / from.max(1) as f64;
+    let load_growth = relative(first.load, last.load).max(0.0);
+    SUBSYSTEMS
+        .iter()
+        .enumerate()
+        .map(|(i, &name)| {
+            let (from, to) = (first.counts[i], last.counts[i]);
+            let steps = samples.windows(2).map(|pair| pair[1].counts[i].cmp(&pair[0].counts[i]));
+            let never_shrank = steps.clone().all(|step| step.is_ge());
+            let rising_samples = steps.filter(|step| step.is_gt()).count();
+            SubsystemTrend {
+                name,
+                first: from,
+                current: to,
+                growth_per_hour: if hours > 0.0 { (to as f64 - from as f64) / hours } else { 0.0 },
+                rising_samples,
+                suspected: samples.len() >= window
+                    && never_shrank
+                    && rising_samples * 2 >= samples.len() - 1
+                    && relative(from, to) > load_growth,
+            }
+        })
+        .collect()
+}
+
+/// Resident memory proses dari `/proc/self/statm`
+#[cfg(target_os = "linux")]
+fn rss_bytes() -> u64 {
+    std::fs::read_to_string("/proc/self/statm")
+        .ok()
+        .and_then(|statm| statm.split_whitespace().nth(1)?.parse::<u64>().ok())
+        .map_or(0, |pages| pages * 4096)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn rss_bytes() -> u64 {
+    0
+}
+
+/// Hitung semua subsystem sekarang
+fn collect(state: &AppState) -> LeakSample {
+    let mut counts = [0u64; SUBSYSTEMS.len()];
+    let mut load = 0;
+    {
+        let map = state.streams.lock();
+        counts[0] = map.len() as u64;
+        for entry in map.values() {
+            counts[1] += entry.dvr.buffered() as u64;
+            counts[2] += entry.dvr.memory_bytes() as u64;
+            counts[3] += entry.tx.len() as u64;
+            counts[4] += entry.connections.count() as u64;
+            load += (entry.tx.receiver_count() + entry.data.receiver_count()) as u64;
+        }
+    }
+    counts[5] = state.sessions.tracked() as u64;
+    counts[6] = state.replay.tracked() as u64;
+    counts[7] = rss_bytes();
+    LeakSample {
+        at_ms: unix_now_ms(),
+        load,
+        counts,
+    }
+}
+
+/// Background task: sampel subsystem setiap `LEAK_SAMPLE_SECS`
+pub async fn run_sampler(state: AppState) {
+    let mut ticker = tokio::time::interval(state.config.leaks.sample_interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        ticker.tick().await;
+        state.leaks.record(collect(&state), state.config.leaks.window);
+    }
+}
+
+/// Handler untuk GET /api/debug/leaks (admin)
+/// Per-subsystem growth over the sample window, flagging counts that rise without load
+pub async fn leaks_handler(State(state): State<AppState>) -> Json<Value> {
+    let config = &state.config.leaks;
+    let samples: Vec<_> = state.leaks.samples.lock().iter().cloned().collect();
+    Json(json!({
+        "sample_secs": config.sample_interval.as_secs(),
+        "window": config.window,
+        "samples": samples.len(),
+        "load": {
+            "first": samples.first().map(|sample| sample.load),
+            "current": samples.last().map(|sample| sample.load),
+        },
+        "subsystems": analyze(&samples, config.window),
+        "suspected": state.leaks.suspected(),
+        "current": collect(&state),
+        "history": samples,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(minute: u64, load: u64, connections: u64, streams: u64) -> LeakSample {
+        let mut counts = [0; SUBSYSTEMS.len()];
+        counts[0] = streams;
+        counts[4] = connections;
+        LeakSample {
+            at_ms: minute * 60_000,
+            load,
+            counts,
+        }
+    }
+
+    #[test]
+    fn test_growth_without_load_is_flagged() {
+        let trend = |samples: &[LeakSample], name: &str| {
+            analyze(samples, 4).into_iter().find(|trend| trend.name == name).unwrap()
+        };
+        // Koneksi naik terus dengan jumlah subscriber yang sama: bocor
+        let leaking: Vec<_> = (0..4).map(|i| sample(i * 20, 10, 10 + i * 5, 3)).collect();
+        let connections = trend(&leaking, "connections");
+        assert!(connections.suspected);
+        assert_eq!((connections.first, connections.current, connections.rising_samples), (10, 25, 3));
+        assert_eq!(connections.growth_per_hour, 15.0);
+        assert!(!trend(&leaking, "streams").suspected);
+        // Jendela belum penuh
+        assert!(!trend(&leaking[..3], "connections").suspected);
+
+        // Naik seiring load bukan kebocoran, begitu juga satu lonjakan lalu datar
+        let following: Vec<_> = (0..4).map(|i| sample(i, 10 + i * 10, 10 + i * 10, 3 + (i / 3))).collect();
+        assert!(!trend(&following, "connections").suspected);
+        assert!(!trend(&following, "streams").suspected);
+
+        let detector = LeakDetector::default();
+        for sample in leaking {
+            detector.record(sample, 4);
+        }
+        assert_eq!(detector.suspected(), vec!["connections"]);
+        detector.record(sample(80, 10, 20, 3), 4);
+        assert!(detector.suspected().is_empty());
+    }
+}
@@ -0,0 +1,229 @@
+use axum::{
+    extract::{ws::{close_code, CloseFrame}, Path as AxumPath, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::task::AbortHandle;
+use tracing::info;
+
+use crate::{error::BrokerError, registry::StreamEvent, AppState};
+
+/// Alasan broker menghentikan stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    /// `max_duration_secs` since the stream was created has elapsed
+    MaxDuration,
+    /// The `stop_at` time was reached
+    Scheduled,
+    /// Closed to free resources for higher-priority streams (`MAX_SUBSCRIBERS`, `MAX_BUFFER_BYTES`)
+    Shed,
+}
+
+/// Body untuk PUT /api/streams/:id/lifetime
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LifetimeLimits {
+    /// Stop the stream this long after it was created
+    pub max_duration_secs: Option<u64>,
+    /// Stop the stream at this wall-clock time (Unix seconds)
+    pub stop_at: Option<u64>,
+}
+
+impl LifetimeLimits {
+    /// Deadline paling awal dari kedua batas beserta alasannya
+    fn deadline(
+        &self,
+        created_at: Instant,
+        now: Instant,
+        wall_now: SystemTime,
+    ) -> Option<(Instant, StopReason)> {
+        let by_duration = self
+            .max_duration_secs
+            .map(|secs| (created_at + Duration::from_secs(secs), StopReason::MaxDuration));
+        let by_schedule = self.stop_at.map(|stop_at| {
+            let stop_at = UNIX_EPOCH + Duration::from_secs(stop_at);
+            let remaining = stop_at.duration_since(wall_now).unwrap_or_default();
+            (now + remaining, StopReason::Scheduled)
+        });
+        by_duration.into_iter().chain(by_schedule).min_by_key(|(deadline, _)| *deadline)
+    }
+}
+
+/// Batas umur satu stream dan status berhentinya
+#[derive(Debug)]
+pub struct StreamLifetime {
+    created_at: Instant,
+    limits: Option<LifetimeLimits>,
+    deadline: Option<Instant>,
+    /// Set once the broker stopped the stream; producers are rejected until the limits are cleared
+    pub ended: Option<StopReason>,
+    timer: Option<AbortHandle>,
+}
+
+impl Default for StreamLifetime {
+    fn default() -> Self {
+        Self {
+            created_at: Instant::now(),
+            limits: None,
+            deadline: None,
+            ended: None,
+            timer: None,
+        }
+    }
+}
+
+impl StreamLifetime {
+    fn clear(&mut self) {
+        if let Some(timer) = self.timer.take() {
+            timer.abort();
+        }
+        self.limits = None;
+        self.deadline = None;
+        self.ended = None;
+    }
+
+    fn status(&self, stream_id: &str) -> serde_json::Value {
+        let limits = self.limits.clone().unwrap_or_default();
+        json!({
+            "stream": stream_id,
+            "max_duration_secs": limits.max_duration_secs,
+            "stop_at": limits.stop_at,
+            "remaining_secs": self
+                .deadline
+                .filter(|_| self.ended.is_none())
+                .map(|deadline| deadline.saturating_duration_since(Instant::now()).as_secs()),
+            "ended": self.ended,
+        })
+    }
+}
+
+/// Close frame untuk producer/subscriber stream yang sudah dihentikan
+pub fn close_frame(reason: StopReason) -> CloseFrame<'static> {
+    let code = match reason {
+        // Klien boleh mencoba lagi nanti, seperti 503
+        StopReason::Shed => close_code::AGAIN,
+        StopReason::MaxDuration | StopReason::Scheduled => close_code::NORMAL,
+    };
+    let reason = match reason {
+        StopReason::MaxDuration => "stream ended: max duration reached",
+        StopReason::Scheduled => "stream ended: scheduled stop",
+        StopReason::Shed => "stream shed: broker under resource pressure",
+    };
+    CloseFrame {
+        code,
+        reason: reason.into(),
+    }
+}
+
+/// Tunggu sampai deadline lalu hentikan stream dan beri tahu subscriber
+async fn expire(state: AppState, stream_id: String, deadline: Instant, reason: StopReason) {
+    tokio::time::sleep_until(deadline.into()).await;
+    if let Some(entry) = state.streams.lock().get_mut(&stream_id) {
+        info!("Stopping stream {} ({:?})", stream_id, reason);
+        entry.lifetime.ended = Some(reason);
+        entry.lifetime.timer = None;
+        let seq = entry.last_seq();
+        entry.emit(StreamEvent::StreamEnded { reason, seq });
+    }
+}
+
+/// Handler untuk PUT /api/streams/:id/lifetime
+/// Set (or replace) the maximum duration and/or scheduled stop time of a stream
+pub async fn put_lifetime_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+    Json(limits): Json<LifetimeLimits>,
+) -> Response {
+    if limits.max_duration_secs.is_none() && limits.stop_at.is_none() {
+        let error = "max_duration_secs or stop_at is required";
+        return BrokerError::InvalidRequest(error.into()).into_response();
+    }
+
+    // Stream boleh dijadwalkan sebelum producer mulai mengirim
+    let status = state.with_stream(&stream_id, |entry| {
+        let lifetime = &mut entry.lifetime;
+        lifetime.clear();
+        let (deadline, reason) = limits
+            .deadline(lifetime.created_at, Instant::now(), SystemTime::now())
+            .expect("at least one limit is set");
+        info!(
+            "Stream {} will stop in {}s ({:?})",
+            stream_id,
+            deadline.saturating_duration_since(Instant::now()).as_secs(),
+            reason
+        );
+        let timer = tokio::spawn(expire(state.clone(), stream_id.clone(), deadline, reason));
+        lifetime.timer = Some(timer.abort_handle());
+        lifetime.deadline = Some(deadline);
+        lifetime.limits = Some(limits);
+        lifetime.status(&stream_id)
+    });
+    Json(status).into_response()
+}
+
+/// Handler untuk GET /api/streams/:id/lifetime
+pub async fn get_lifetime_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Response {
+    match state.streams.lock().get(&stream_id) {
+        Some(entry) => Json(entry.lifetime.status(&stream_id)).into_response(),
+        None => BrokerError::StreamNotFound(stream_id).into_response(),
+    }
+}
+
+/// Handler untuk DELETE /api/streams/:id/lifetime
+/// Remove the limits; a stopped (or shed) stream accepts producers again
+pub async fn delete_lifetime_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Result<StatusCode, BrokerError> {
+    match state.streams.lock().get_mut(&stream_id) {
+        Some(entry) if entry.lifetime.limits.is_some() || entry.lifetime.ended.is_some() => {
+            info!("Cleared lifetime limits for stream: {}", stream_id);
+            entry.lifetime.clear();
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Some(_) => Err(BrokerError::not_found("lifetime limits", stream_id)),
+        None => Err(BrokerError::StreamNotFound(stream_id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_earliest_deadline_wins() {
+        let created_at = Instant::now();
+        let now = created_at + Duration::from_secs(10);
+        let wall_now = UNIX_EPOCH + Duration::from_secs(1_000);
+
+        let limits = LifetimeLimits {
+            max_duration_secs: Some(60),
+            stop_at: Some(1_030),
+        };
+        let (deadline, reason) = limits.deadline(created_at, now, wall_now).unwrap();
+        assert_eq!(reason, StopReason::Scheduled);
+        assert_eq!(deadline, now + Duration::from_secs(30));
+
+        let limits = LifetimeLimits {
+            max_duration_secs: Some(20),
+            stop_at: Some(1_030),
+        };
+        let (deadline, reason) = limits.deadline(created_at, now, wall_now).unwrap();
+        assert_eq!(reason, StopReason::MaxDuration);
+        assert_eq!(deadline, created_at + Duration::from_secs(20));
+
+        // Waktu stop yang sudah lewat berlaku segera
+        let limits = LifetimeLimits {
+            max_duration_secs: None,
+            stop_at: Some(900),
+        };
+        assert_eq!(limits.deadline(created_at, now, wall_now).unwrap().0, now);
+        assert!(LifetimeLimits::default().deadline(created_at, now, wall_now).is_none());
+    }
+}
@@ -0,0 +1,246 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http_body_util::Limited;
+use hyper::body::{Frame, SizeHint};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::Sleep;
+use tracing::warn;
+
+use crate::AppState;
+
+/// Batas request satu grup route: `INGEST_*` untuk `/ingest/:stream_id`, `API_*` untuk route lain
+///
+/// Each limit is `0`/`None` for unlimited, so an ingest endpoint that takes
+/// long streamed bodies can drop its body limit and timeout while the admin
+/// API keeps tight ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RouteLimits {
+    /// Largest request body in bytes
+    pub max_body_bytes: usize,
+    /// Most request headers
+    pub max_headers: usize,
+    /// Total bytes of header names and values
+    pub max_header_bytes: usize,
+    /// Time to read the request and produce the response headers
+    pub timeout: Option<Duration>,
+    /// Time to receive the whole body, so a body trickling in cannot hold the connection
+    pub body_timeout: Option<Duration>,
+}
+
+impl Default for RouteLimits {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 2 << 20,
+            max_headers: 100,
+            max_header_bytes: 64 << 10,
+            timeout: None,
+            body_timeout: None,
+        }
+    }
+}
+
+impl RouteLimits {
+    fn check_headers(&self, headers: &HeaderMap) -> Result<(), String> {
+        if self.max_headers > 0 && headers.len() > self.max_headers {
+            return Err(format!("{} headers, at most {} allowed", headers.len(), self.max_headers));
+        }
+        let bytes: usize = headers.iter().map(|(name, value)| name.as_str().len() + value.len()).sum();
+        if self.max_header_bytes > 0 && bytes > self.max_header_bytes {
+            return Err(format!("{} header bytes, at most {} allowed", bytes, self.max_header_bytes));
+        }
+        Ok(())
+    }
+}
+
+/// Body request yang gagal bila belum selesai diterima sebelum tenggat
+struct DeadlineBody {
+    inner: Body,
+    deadline: Pin<Box<Sleep>>,
+    expired: Arc<AtomicBool>,
+}
+
+impl hyper::body::Body for DeadlineBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, axum::Error>>> {
+        let this = &mut *self;
+        if let Poll::Ready(frame) = Pin::new(&mut this.inner).poll_frame(cx) {
+            return Poll::Ready(frame);
+        }
+        if this.deadline.as_mut().poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        this.expired.store(true, Ordering::Relaxed);
+        Poll::Ready(Some(Err(axum::Error::new("request body not received in time"))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Middleware: batas header, body dan waktu per grup route
+///
+/// Rejections are plain statuses (`431`, `413`, `408`) that `json_errors`
+/// turns into the standard error body.
+pub async fn route_limits_middleware(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    let (group, limits) = if path.starts_with("/ingest/") {
+        ("ingest", state.config.ingest_limits)
+    } else {
+        ("api", state.config.api_limits)
+    };
+    if let Err(e) = limits.check_headers(request.headers()) {
+        warn!("Rejected {} request {}: {}", group, path, e);
+        return StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE.into_response();
+    }
+    let request = if limits.max_body_bytes > 0 {
+        let declared = request
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+        // Content-Length di atas batas ditolak sebelum body dibaca
+        if declared.is_some_and(|declared| declared > limits.max_body_bytes) {
+            return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+        }
+        request.map(|body| Body::new(Limited::new(body, limits.max_body_bytes)))
+    } else {
+        request
+    };
+    let body_expired = Arc::new(AtomicBool::new(false));
+    let request = match limits.body_timeout {
+        Some(body_timeout) => request.map(|body| {
+            Body::new(DeadlineBody {
+                inner: body,
+                deadline: Box::pin(tokio::time::sleep(body_timeout)),
+                expired: body_expired.clone(),
+            })
+        }),
+        None => request,
+    };
+    let response = match limits.timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, next.run(request)).await {
+            Ok(response) => response,
+            Err(_) => {
+                warn!("{} request timed out after {:?}", group, timeout);
+                return StatusCode::REQUEST_TIMEOUT.into_response();
+            }
+        },
+        None => next.run(request).await,
+    };
+    // Extractor body menjawab 400 untuk error body apa pun; tenggat body menjadi 408
+    if body_expired.load(Ordering::Relaxed) {
+        warn!("{} request body not received within {:?}", group, limits.body_timeout.unwrap_or_default());
+        return StatusCode::REQUEST_TIMEOUT.into_response();
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use axum::{body::Bytes, extract::DefaultBodyLimit, middleware, routing::post, Router};
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn test_limits_apply_per_route_group() {
+        let state = AppState::new(Config {
+            ingest_limits: RouteLimits {
+                max_body_bytes: 0,
+                ..RouteLimits::default()
+            },
+            api_limits: RouteLimits {
+                max_body_bytes: 8,
+                max_headers: 2,
+                max_header_bytes: 0,
+                timeout: Some(Duration::from_millis(50)),
+                body_timeout: None,
+            },
+            ..Config::default()
+        });
+        let echo = |body: Bytes| async move { body.len().to_string() };
+        let slow = || async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            "late"
+        };
+        let app = Router::new()
+            .route("/ingest/cam1", post(echo))
+            .route("/api/echo", post(echo))
+            .route("/api/slow", post(slow))
+            .layer(middleware::from_fn_with_state(state.clone(), route_limits_middleware))
+            .layer(DefaultBodyLimit::disable())
+            .with_state(state);
+        let send = |path: &str, body: Vec<u8>, headers: usize| {
+            let mut request = Request::post(path);
+            for i in 0..headers {
+                request = request.header(format!("x-extra-{}", i), "1");
+            }
+            app.clone().oneshot(request.body(Body::from(body)).unwrap())
+        };
+
+        // Ingest tanpa batas body; API dibatasi 8 byte (juga tanpa Content-Length)
+        assert_eq!(send("/ingest/cam1", vec![0; 3 << 20], 0).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(send("/api/echo", vec![0; 8], 0).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(send("/api/echo", vec![0; 9], 0).await.unwrap().status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let stream = Body::from_stream(futures_util::stream::iter([Ok::<_, std::io::Error>(vec![0u8; 16])]));
+        let streamed = app.clone().oneshot(Request::post("/api/echo").body(stream).unwrap()).await.unwrap();
+        assert_eq!(streamed.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        assert_eq!(
+            send("/api/echo", Vec::new(), 3).await.unwrap().status(),
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+        );
+        assert_eq!(send("/ingest/cam1", Vec::new(), 3).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(send("/api/slow", Vec::new(), 0).await.unwrap().status(), StatusCode::REQUEST_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn test_trickling_body_times_out() {
+        tokio::time::pause();
+        let state = AppState::new(Config {
+            ingest_limits: RouteLimits {
+                body_timeout: Some(Duration::from_secs(30)),
+                ..RouteLimits::default()
+            },
+            ..Config::default()
+        });
+        let app = Router::new()
+            .route("/ingest/cam1", post(|body: Bytes| async move { body.len().to_string() }))
+            .layer(middleware::from_fn_with_state(state.clone(), route_limits_middleware))
+            .with_state(state);
+        // Satu chunk tiap 10 detik: tiap jeda masih wajar, tetapi body tidak pernah selesai tepat waktu
+        let chunks = futures_util::stream::unfold(0, |sent| async move {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Some((Ok::<_, std::io::Error>(vec![0u8; 1]), sent + 1))
+        });
+        let request = Request::post("/ingest/cam1").body(Body::from_stream(chunks)).unwrap();
+        let start = tokio::time::Instant::now();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+        assert!(start.elapsed() >= Duration::from_secs(30));
+
+        let request = Request::post("/ingest/cam1").body(Body::from(vec![0u8; 3])).unwrap();
+        assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::OK);
+    }
+}
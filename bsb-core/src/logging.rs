@@ -0,0 +1,524 @@
+//! Output log: console (pretty/JSON), file yang dirotasi, syslog dan journald
+//!
+//! Configured from `LOG_*` before `Config::from_env`, so every sink sees the
+//! startup messages as well.
+
+use parking_lot::Mutex;
+use serde_json::{Map, Value};
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    net::UdpSocket,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{
+    filter::EnvFilter,
+    fmt::{format::Writer, FmtContext, FormatEvent, FormatFields, MakeWriter},
+    layer::{Context, Layered, SubscriberExt},
+    registry::{LookupSpan, Registry},
+    util::SubscriberInitExt,
+    Layer,
+};
+
+use crate::{config, stats::unix_now_ms};
+
+/// Filter log default (semua sink)
+pub const LOG_FILTER: &str = "ingest_server=info,bsb_core=info,tower_http=debug";
+
+/// Nama proses di syslog / journald
+const IDENTIFIER: &str = "ingest-server";
+
+/// Layer untuk subscriber yang sudah difilter `LOG_FILTER`
+pub type BoxedLayer = Box<dyn Layer<Layered<EnvFilter, Registry>> + Send + Sync>;
+
+/// Format baris log
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LogFormat {
+    /// Human-readable lines (the `tracing` default)
+    #[default]
+    Pretty,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "pretty" | "text" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("unknown log format {:?} (use pretty or json)", other)),
+        }
+    }
+}
+
+/// Tujuan `LOG_SYSLOG`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyslogTarget {
+    /// systemd-journald native protocol, with level and target as journal fields
+    Journald,
+    /// Local syslog daemon socket, e.g. `unix:/dev/log`
+    Unix(PathBuf),
+    /// Remote syslog over UDP, e.g. `udp:logs.example.com:514`
+    Udp(String),
+}
+
+impl FromStr for SyslogTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "journald" {
+            return Ok(SyslogTarget::Journald);
+        }
+        match s.split_once(':') {
+            Some(("unix", path)) if !path.is_empty() => Ok(SyslogTarget::Unix(PathBuf::from(path))),
+            Some(("udp", addr)) if addr.contains(':') => Ok(SyslogTarget::Udp(addr.to_string())),
+            _ => Err(format!(
+                "invalid LOG_SYSLOG {:?} (use journald, unix:/dev/log or udp:host:port)",
+                s
+            )),
+        }
+    }
+}
+
+/// File log yang dirotasi (`LOG_FILE`)
+#[derive(Debug, Clone)]
+pub struct FileSink {
+    pub path: PathBuf,
+    pub format: LogFormat,
+    /// Rotate once the file reaches this size (0 = no size limit)
+    pub max_bytes: u64,
+    /// Rotate files older than this
+    pub max_age: Option<Duration>,
+    /// Rotated files kept next to the active one (`broker.log.1` is the newest)
+    pub keep: usize,
+}
+
+/// Sink log dari environment
+#[derive(Debug, Clone, Default)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// Also log to stdout (`LOG_CONSOLE`)
+    pub console: bool,
+    pub file: Option<FileSink>,
+    pub syslog: Option<SyslogTarget>,
+}
+
+impl LogConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let format: LogFormat = config::parse_var("LOG_FORMAT", LogFormat::Pretty)?;
+        let file = match std::env::var("LOG_FILE") {
+            Ok(path) if !path.trim().is_empty() => {
+                let max_age = match std::env::var("LOG_FILE_MAX_AGE") {
+                    Ok(value) if !value.trim().is_empty() => config::parse_duration(&value)
+                        .map_err(|e| format!("Invalid LOG_FILE_MAX_AGE value: {}", e))?,
+                    _ => Duration::ZERO,
+                };
+                Some(FileSink {
+                    path: PathBuf::from(path.trim()),
+                    format: config::parse_var("LOG_FILE_FORMAT", format)?,
+                    max_bytes: config::parse_var("LOG_FILE_MAX_BYTES", 10u64 << 20)?,
+                    max_age: Some(max_age).filter(|age| !age.is_zero()),
+                    keep: config::parse_var("LOG_FILE_KEEP", 5usize)?,
+                })
+            }
+            _ => None,
+        };
+        let syslog = match std::env::var("LOG_SYSLOG") {
+            Ok(value) if !value.trim().is_empty() => Some(value.parse()?),
+            _ => None,
+        };
+        Ok(Self {
+            format,
+            console: config::parse_var("LOG_CONSOLE", true)?,
+            file,
+            syslog,
+        })
+    }
+
+    /// Layer untuk semua sink yang dikonfigurasi
+    pub fn layers(&self) -> io::Result<Vec<BoxedLayer>> {
+        let mut layers = Vec::new();
+        if self.console {
+            layers.push(fmt_layer(self.format, io::stdout, true));
+        }
+        if let Some(sink) = &self.file {
+            let file = RotatingFile::open(sink.clone())
+                .map_err(|e| io::Error::new(e.kind(), format!("LOG_FILE {}: {}", sink.path.display(), e)))?;
+            layers.push(fmt_layer(sink.format, file, false));
+        }
+        if let Some(target) = &self.syslog {
+            let layer = SyslogLayer::connect(target)
+                .map_err(|e| io::Error::new(e.kind(), format!("LOG_SYSLOG {:?}: {}", target, e)))?;
+            layers.push(Box::new(layer));
+        }
+        Ok(layers)
+    }
+
+    /// Pasang subscriber global
+    pub fn init(&self) -> io::Result<()> {
+        tracing_subscriber::registry()
+            .with(EnvFilter::new(LOG_FILTER))
+            .with(self.layers()?)
+            .init();
+        Ok(())
+    }
+}
+
+fn fmt_layer<W>(format: LogFormat, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer).with_ansi(ansi);
+    match format {
+        LogFormat::Pretty => layer.boxed(),
+        LogFormat::Json => layer.event_format(JsonFormat).boxed(),
+    }
+}
+
+/// Field event sebagai JSON (`message` ikut sebagai field biasa)
+struct JsonVisitor(Map<String, Value>);
+
+impl Visit for JsonVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+}
+
+/// `{"timestamp":"2024-05-01T12:00:00.123Z","level":"INFO","target":"...","message":"...",...}`
+struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, _ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        writeln!(writer, "{}", json_line(event, unix_now_ms()))
+    }
+}
+
+fn json_line(event: &Event<'_>, now_ms: u64) -> Value {
+    let metadata = event.metadata();
+    let mut object = Map::new();
+    object.insert("timestamp".into(), rfc3339(now_ms).into());
+    object.insert("level".into(), metadata.level().as_str().into());
+    object.insert("target".into(), metadata.target().into());
+    let mut fields = JsonVisitor(Map::new());
+    event.record(&mut fields);
+    object.extend(fields.0);
+    Value::Object(object)
+}
+
+/// Unix ms ke RFC 3339 UTC dengan milidetik
+fn rfc3339(unix_ms: u64) -> String {
+    let secs = unix_ms / 1000;
+    let (days, time) = (secs / 86_400, secs % 86_400);
+    // Civil-from-days (Howard Hinnant), epoch 0000-03-01
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        unix_ms % 1000
+    )
+}
+
+#[derive(Debug)]
+struct ActiveFile {
+    file: File,
+    written: u64,
+    opened: Instant,
+}
+
+/// File log dengan rotasi berdasarkan ukuran dan umur
+///
+/// Every log line is written with a single `write` call by the fmt layer, so
+/// rotation only ever happens between lines.
+#[derive(Debug)]
+pub struct RotatingFile {
+    sink: FileSink,
+    active: Mutex<ActiveFile>,
+}
+
+impl RotatingFile {
+    pub fn open(sink: FileSink) -> io::Result<Self> {
+        if let Some(dir) = sink.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let active = Mutex::new(open_append(&sink.path)?);
+        Ok(Self { sink, active })
+    }
+
+    fn write_line(&self, buf: &[u8]) -> io::Result<usize> {
+        let mut active = self.active.lock();
+        let full = self.sink.max_bytes > 0 && active.written + buf.len() as u64 > self.sink.max_bytes;
+        let expired = self.sink.max_age.is_some_and(|age| active.opened.elapsed() >= age);
+        if active.written > 0 && (full || expired) {
+            active.file.flush()?;
+            rotate(&self.sink.path, self.sink.keep)?;
+            *active = open_append(&self.sink.path)?;
+        }
+        let written = active.file.write(buf)?;
+        active.written += written as u64;
+        Ok(written)
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<ActiveFile> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(ActiveFile {
+        written: file.metadata()?.len(),
+        file,
+        opened: Instant::now(),
+    })
+}
+
+/// `log` -> `log.1` -> ... -> `log.<keep>`; file tertua dihapus
+fn rotate(path: &Path, keep: usize) -> io::Result<()> {
+    let numbered = |n: usize| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    };
+    if keep == 0 {
+        return fs::remove_file(path);
+    }
+    match fs::remove_file(numbered(keep)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    for n in (1..keep).rev() {
+        match fs::rename(numbered(n), numbered(n + 1)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    fs::rename(path, numbered(1))
+}
+
+/// Writer per event untuk `RotatingFile`
+pub struct RotatingWriter<'a>(&'a RotatingFile);
+
+impl Write for RotatingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write_line(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.active.lock().file.flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for RotatingFile {
+    type Writer = RotatingWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RotatingWriter(self)
+    }
+}
+
+/// Socket tujuan `SyslogLayer`
+enum SyslogSocket {
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixDatagram),
+    Udp(UdpSocket),
+}
+
+/// Layer yang mengirim event ke syslog (RFC 3164, facility daemon) atau journald
+pub struct SyslogLayer {
+    socket: SyslogSocket,
+    journald: bool,
+    pid: u32,
+}
+
+impl SyslogLayer {
+    pub fn connect(target: &SyslogTarget) -> io::Result<Self> {
+        let socket = match target {
+            #[cfg(unix)]
+            SyslogTarget::Journald => unix_socket(Path::new("/run/systemd/journal/socket"))?,
+            #[cfg(unix)]
+            SyslogTarget::Unix(path) => unix_socket(path)?,
+            #[cfg(not(unix))]
+            SyslogTarget::Journald | SyslogTarget::Unix(_) => {
+                return Err(io::Error::new(io::ErrorKind::Unsupported, "unix syslog sockets"))
+            }
+            SyslogTarget::Udp(addr) => {
+                let socket = UdpSocket::bind(if addr.starts_with('[') { "[::]:0" } else { "0.0.0.0:0" })?;
+                socket.connect(addr)?;
+                SyslogSocket::Udp(socket)
+            }
+        };
+        Ok(Self {
+            socket,
+            journald: *target == SyslogTarget::Journald,
+            pid: std::process::id(),
+        })
+    }
+
+    fn send(&self, datagram: &[u8]) {
+        // Error kirim tidak bisa di-log tanpa rekursi ke layer ini
+        let _ = match &self.socket {
+            #[cfg(unix)]
+            SyslogSocket::Unix(socket) => socket.send(datagram),
+            SyslogSocket::Udp(socket) => socket.send(datagram),
+        };
+    }
+}
+
+#[cfg(unix)]
+fn unix_socket(path: &Path) -> io::Result<SyslogSocket> {
+    let socket = std::os::unix::net::UnixDatagram::unbound()?;
+    socket.connect(path)?;
+    Ok(SyslogSocket::Unix(socket))
+}
+
+/// Severity syslog (RFC 5424) untuk level tracing
+fn severity(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        _ => 7,
+    }
+}
+
+/// `message` diikuti field lain sebagai `key=value`
+struct LineVisitor(String);
+
+impl Visit for LineVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        if field.name() == "message" {
+            self.0.push_str(&format!("{:?}", value));
+        } else {
+            self.0.push_str(&format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+/// Datagram journald native protocol; nilai multi-baris memakai encoding panjang biner
+fn journald_datagram(fields: &[(&str, &str)]) -> Vec<u8> {
+    let mut datagram = Vec::new();
+    for (name, value) in fields {
+        datagram.extend_from_slice(name.as_bytes());
+        if value.contains('\n') {
+            datagram.push(b'\n');
+            datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            datagram.push(b'=');
+        }
+        datagram.extend_from_slice(value.as_bytes());
+        datagram.push(b'\n');
+    }
+    datagram
+}
+
+impl<S: Subscriber> Layer<S> for SyslogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut line = LineVisitor(String::new());
+        event.record(&mut line);
+        let severity = severity(metadata.level());
+        if self.journald {
+            let priority = severity.to_string();
+            let pid = self.pid.to_string();
+            self.send(&journald_datagram(&[
+                ("MESSAGE", &line.0),
+                ("PRIORITY", &priority),
+                ("SYSLOG_IDENTIFIER", IDENTIFIER),
+                ("SYSLOG_PID", &pid),
+                ("TARGET", metadata.target()),
+            ]));
+        } else {
+            // Facility daemon (3); timestamp dan hostname diisi oleh penerima
+            let message = format!("<{}>{}[{}]: {}", 3 * 8 + severity, IDENTIFIER, self.pid, line.0);
+            self.send(message.as_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_json_and_journald_encoding() {
+        let dir = std::env::temp_dir().join(format!("bsb-log-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("broker.log");
+        let file = RotatingFile::open(FileSink {
+            path: path.clone(),
+            format: LogFormat::Json,
+            max_bytes: 10,
+            max_age: None,
+            keep: 2,
+        })
+        .unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.make_writer().write_all(line.as_bytes()).unwrap();
+        }
+        // Tiap baris melebihi 10 byte bersama baris sebelumnya: satu baris per file, 2 rotasi disimpan
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(fs::read_to_string(dir.join("broker.log.1")).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(dir.join("broker.log.2")).unwrap(), "second\n");
+        assert!(!dir.join("broker.log.3").exists());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(rfc3339(1_709_251_199_999), "2024-02-29T23:59:59.999Z");
+
+        let datagram = journald_datagram(&[("MESSAGE", "a\nb"), ("PRIORITY", "6")]);
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&3u64.to_le_bytes());
+        expected.extend_from_slice(b"a\nb\nPRIORITY=6\n");
+        assert_eq!(datagram, expected);
+
+        assert_eq!("udp:logs:514".parse(), Ok(SyslogTarget::Udp("logs:514".to_string())));
+        assert!("udp:logs".parse::<SyslogTarget>().is_err());
+        assert_eq!("json".parse(), Ok(LogFormat::Json));
+    }
+}
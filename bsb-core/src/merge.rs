@@ -0,0 +1,169 @@
+use std::collections::{HashMap, HashSet};
+use tokio::{sync::broadcast, task::JoinHandle};
+use tracing::{info, warn};
+
+use crate::{
+    mux::{check_taggable, encode_tagged, glob_match},
+    registry::Frame,
+    supervisor,
+    AppState,
+};
+
+/// Derived streams from `MERGE_STREAMS`
+///
+/// Format: `derived=pattern,pattern;derived=pattern`, e.g.
+/// `site-a/all=site-a/*;lobby=cam1,cam2`. Every frame of a matching source
+/// stream is republished on the derived stream, tagged with its origin.
+#[derive(Debug, Clone, Default)]
+pub struct MergeRules {
+    rules: Vec<(String, Vec<String>)>,
+}
+
+impl MergeRules {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for rule in spec.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            let (derived, patterns) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid MERGE_STREAMS rule: {}", rule))?;
+            let patterns: Vec<String> = patterns
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(str::to_string)
+                .collect();
+            if derived.trim().is_empty() || patterns.is_empty() {
+                return Err(format!("Invalid MERGE_STREAMS rule: {}", rule));
+            }
+            rules.push((derived.trim().to_string(), patterns));
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn is_derived(&self, stream_id: &str) -> bool {
+        self.rules.iter().any(|(derived, _)| derived == stream_id)
+    }
+
+    /// Source streams of `patterns` among `stream_ids`; derived streams are
+    /// never sources, so merges cannot feed each other in a loop
+    fn sources<'a>(&self, patterns: &[String], stream_ids: &'a [String]) -> Vec<&'a String> {
+        stream_ids
+            .iter()
+            .filter(|id| !self.is_derived(id))
+            .filter(|id| patterns.iter().any(|p| glob_match(p, id)))
+            .collect()
+    }
+}
+
+/// Jalankan satu task per derived stream yang dikonfigurasi
+pub fn spawn_all(state: &AppState) {
+    for (derived, patterns) in &state.config.merge_streams.rules {
+        info!("Merging {:?} into stream {}", patterns, derived);
+        let (state, derived, patterns) = (state.clone(), derived.clone(), patterns.clone());
+        supervisor::supervise(format!("merge {}", derived), move || {
+            run_merge(state.clone(), derived.clone(), patterns.clone())
+        });
+    }
+}
+
+async fn run_merge(state: AppState, derived: String, patterns: Vec<String>) {
+    // Source yang cocok dengan pattern dibuat saat frame pertamanya tiba
+    let _guards: Vec<_> = patterns.iter().map(|p| state.patterns.register(p)).collect();
+    // Derived stream selalu ada supaya bisa di-subscribe sebelum source pertama muncul
+    state.with_stream(&derived, |_| ());
+
+    let mut forwarders: HashMap<String, JoinHandle<()>> = HashMap::new();
+    let mut untaggable = HashSet::new();
+    let mut stream_changes = state.stream_changes.subscribe();
+    let mut startup = true;
+    loop {
+        let stream_ids = state.stream_ids();
+        let sources = state.config.merge_streams.sources(&patterns, &stream_ids);
+        forwarders.retain(|id, task| {
+            let keep = sources.contains(&id) && !task.is_finished();
+            if !keep {
+                task.abort();
+            }
+            keep
+        });
+        for source in sources {
+            if forwarders.contains_key(source) {
+                continue;
+            }
+            if let Err(e) = check_taggable(source) {
+                if untaggable.insert(source.clone()) {
+                    warn!("Stream {} not merged into {}: {}", source, derived, e);
+                }
+                continue;
+            }
+            info!("Stream {} joined merged stream {}", source, derived);
+            // Source baru dibuat oleh frame pertamanya sebelum kita subscribe;
+            // frame yang sudah ada di DVR ikut diteruskan supaya tidak hilang
+            let (frames, backlog) = state.with_stream(source, |entry| {
+                let backlog = if startup { Vec::new() } else { entry.dvr.since(0) };
+                (entry.tx.subscribe(), backlog)
+            });
+            let task = tokio::spawn(forward_source(
+                state.clone(),
+                source.clone(),
+                derived.clone(),
+                backlog,
+                frames,
+            ));
+            forwarders.insert(source.clone(), task);
+        }
+        startup = false;
+
+        if stream_changes.changed().await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Teruskan frame dari satu source ke derived stream, di-tag dengan ID asal
+async fn forward_source(
+    state: AppState,
+    source: String,
+    derived: String,
+    backlog: Vec<Frame>,
+    mut frames: broadcast::Receiver<Frame>,
+) {
+    // ID source sudah diperiksa dengan `check_taggable` sebelum task ini dibuat
+    for frame in backlog {
+        if let Some(tagged) = encode_tagged(&source, &frame.data) {
+            let _ = state.with_stream(&derived, |entry| entry.publish(tagged.into()));
+        }
+    }
+    loop {
+        match frames.recv().await {
+            Ok(frame) => {
+                if let Some(tagged) = encode_tagged(&source, &frame.data) {
+                    let _ = state.with_stream(&derived, |entry| entry.publish(tagged.into()));
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Merged stream {} lagged, skipped {} frames from {}", derived, skipped, source);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_sources_exclude_derived() {
+        let rules = MergeRules::parse("site-a/all=site-a/*; lobby=cam1,cam2").unwrap();
+        let ids: Vec<String> = ["site-a/cam1", "site-a/all", "cam1", "cam3"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let site = rules.sources(&rules.rules[0].1, &ids);
+        assert_eq!(site, vec!["site-a/cam1"]);
+        let lobby = rules.sources(&rules.rules[1].1, &ids);
+        assert_eq!(lobby, vec!["cam1"]);
+        assert!(MergeRules::parse("lobby=").is_err());
+    }
+}
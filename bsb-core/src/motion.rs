@@ -0,0 +1,477 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{sync::broadcast, task::JoinHandle};
+use tracing::{error, info, warn};
+
+use crate::{
+    error::BrokerError,
+    jpeg::{self, RgbImage},
+    registry::StreamEvent,
+    sources, supervisor, AppState,
+};
+
+/// Gambar diperkecil ke grid sel luma ini sebelum dibandingkan, berapa pun resolusinya
+const GRID_WIDTH: usize = 32;
+const GRID_HEIGHT: usize = 24;
+
+/// Body untuk PUT /api/streams/:id/motion (juga `motion` di `STATIC_STREAMS_FILE`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MotionConfig {
+    /// Share of the picture (percent of grid cells) that must change to count as motion
+    #[serde(default = "default_threshold_percent")]
+    pub threshold_percent: f64,
+    /// Average luma change (0-255) for a grid cell to count as changed
+    #[serde(default = "default_cell_delta")]
+    pub cell_delta: u8,
+    /// Analyse at most one frame per this many ms
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+    /// Motion ends once no analysed frame was above the threshold for this long
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+    /// Record the stream from motion start until motion ends
+    #[serde(default)]
+    pub record: bool,
+}
+
+fn default_threshold_percent() -> f64 {
+    2.0
+}
+
+fn default_cell_delta() -> u8 {
+    20
+}
+
+fn default_interval_ms() -> u64 {
+    200
+}
+
+fn default_cooldown_secs() -> u64 {
+    10
+}
+
+impl Default for MotionConfig {
+    fn default() -> Self {
+        Self {
+            threshold_percent: default_threshold_percent(),
+            cell_delta: default_cell_delta(),
+            interval_ms: default_interval_ms(),
+            cooldown_secs: default_cooldown_secs(),
+            record: false,
+        }
+    }
+}
+
+impl MotionConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.threshold_percent > 0.0 && self.threshold_percent <= 100.0) {
+            return Err("threshold_percent must be above 0 and at most 100".to_string());
+        }
+        if self.cell_delta == 0 {
+            return Err("cell_delta must be at least 1".to_string());
+        }
+        if !(10..=60_000).contains(&self.interval_ms) {
+            return Err("interval_ms must be between 10 and 60000".to_string());
+        }
+        if !(1..=3600).contains(&self.cooldown_secs) {
+            return Err("cooldown_secs must be between 1 and 3600".to_string());
+        }
+        if self.record && cfg!(not(feature = "recording")) {
+            return Err("record needs a broker built with the `recording` feature".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Rata-rata luma per sel grid
+pub fn luma_grid(image: &RgbImage) -> Vec<u8> {
+    let mut sums = vec![0u32; GRID_WIDTH * GRID_HEIGHT];
+    let mut counts = vec![0u32; GRID_WIDTH * GRID_HEIGHT];
+    for y in 0..image.height {
+        let row = y * GRID_HEIGHT / image.height * GRID_WIDTH;
+        for x in 0..image.width {
+            let [r, g, b] = image.get(x, y);
+            let cell = row + x * GRID_WIDTH / image.width;
+            sums[cell] += (77 * r as u32 + 150 * g as u32 + 29 * b as u32) >> 8;
+            counts[cell] += 1;
+        }
+    }
+    sums.iter()
+        .zip(&counts)
+        .map(|(sum, count)| sum.checked_div(*count).unwrap_or(0) as u8)
+        .collect()
+}
+
+/// Awal atau akhir gerakan
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MotionChange {
+    Started { score: f64 },
+    Ended { peak_score: f64 },
+}
+
+/// Deteksi gerakan dari selisih antar frame yang dianalisis
+///
+/// The score of a frame is the percentage of grid cells whose average luma
+/// moved by more than `cell_delta` since the previous analysed frame. Motion
+/// starts with the first frame at or above `threshold_percent` and ends
+/// after `cooldown_secs` without one, so a person pausing mid-scene does not
+/// split one event into many.
+#[derive(Debug)]
+pub struct MotionDetector {
+    config: MotionConfig,
+    previous: Option<Vec<u8>>,
+    last_score: f64,
+    /// Highest score of the ongoing motion
+    peak: Option<f64>,
+    last_motion: Option<Instant>,
+}
+
+impl MotionDetector {
+    pub fn new(config: MotionConfig) -> Self {
+        Self {
+            config,
+            previous: None,
+            last_score: 0.0,
+            peak: None,
+            last_motion: None,
+        }
+    }
+
+    pub fn observe(&mut self, grid: Vec<u8>, now: Instant) -> Option<MotionChange> {
+        let previous = self.previous.replace(grid)?;
+        let current = self.previous.as_ref().expect("just stored");
+        let changed = previous
+            .iter()
+            .zip(current)
+            .filter(|(before, after)| before.abs_diff(**after) > self.config.cell_delta)
+            .count();
+        let score = (changed as f64 * 1000.0 / current.len() as f64).round() / 10.0;
+        self.last_score = score;
+        if score < self.config.threshold_percent {
+            return self.expire(now);
+        }
+        self.last_motion = Some(now);
+        match self.peak.as_mut() {
+            Some(peak) => {
+                *peak = peak.max(score);
+                None
+            }
+            None => {
+                self.peak = Some(score);
+                Some(MotionChange::Started { score })
+            }
+        }
+    }
+
+    /// Akhiri gerakan bila tidak ada frame di atas threshold selama cooldown
+    pub fn expire(&mut self, now: Instant) -> Option<MotionChange> {
+        let cooldown = Duration::from_secs(self.config.cooldown_secs);
+        let quiet = self
+            .last_motion
+            .is_some_and(|last| now.saturating_duration_since(last) >= cooldown);
+        match self.peak {
+            Some(peak_score) if quiet => {
+                self.peak = None;
+                Some(MotionChange::Ended { peak_score })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Keadaan detektor untuk GET /api/streams/:id/motion
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MotionStatus {
+    pub active: bool,
+    /// Score of the last analysed frame (percent of cells changed)
+    pub score: f64,
+    pub frames_analyzed: u64,
+    /// A motion-triggered recording is running
+    pub recording: bool,
+    /// Why the last frame could not be analysed (e.g. not a baseline JPEG)
+    pub last_error: Option<String>,
+}
+
+/// Detektor yang berjalan, per stream
+struct RunningDetector {
+    config: MotionConfig,
+    status: Arc<Mutex<MotionStatus>>,
+    task: JoinHandle<()>,
+}
+
+/// Detektor gerakan yang dikonfigurasi lewat admin API atau `STATIC_STREAMS_FILE`
+#[derive(Default)]
+pub struct MotionDetectors {
+    running: Mutex<HashMap<String, RunningDetector>>,
+}
+
+impl MotionDetectors {
+    /// Mulai (atau ganti) detektor untuk `stream_id`
+    pub fn start(&self, state: &AppState, stream_id: &str, config: MotionConfig) {
+        let status = Arc::new(Mutex::new(MotionStatus::default()));
+        let task = {
+            let (state, stream_id, config, status) =
+                (state.clone(), stream_id.to_string(), config.clone(), status.clone());
+            supervisor::supervise(format!("motion {}", stream_id), move || {
+                run_detector(state.clone(), stream_id.clone(), config.clone(), status.clone())
+            })
+        };
+        let detector = RunningDetector { config, status, task };
+        if let Some(old) = self.running.lock().insert(stream_id.to_string(), detector) {
+            old.task.abort();
+        }
+    }
+
+    fn stop(&self, stream_id: &str) -> bool {
+        match self.running.lock().remove(stream_id) {
+            Some(detector) => {
+                detector.task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn describe(&self, stream_id: &str) -> Option<serde_json::Value> {
+        let running = self.running.lock();
+        let detector = running.get(stream_id)?;
+        let status = detector.status.lock().clone();
+        Some(json!({ "stream": stream_id, "motion": detector.config, "status": status }))
+    }
+}
+
+/// Analisis frame MJPEG stream dan kirim event saat gerakan mulai dan berakhir
+///
+/// Runs beside the ingest path on its own broadcast receiver: decoding never
+/// delays publishing, and a detector that falls behind just analyses fewer
+/// frames. A motion-triggered recorder is stopped when motion ends, or when
+/// the detector is removed or replaced.
+async fn run_detector(state: AppState, stream_id: String, config: MotionConfig, status: Arc<Mutex<MotionStatus>>) {
+    let mut frames = state.with_stream(&stream_id, |entry| entry.tx.subscribe());
+    let record = config.record && !sources::recorded_always(&state, &stream_id);
+    let interval = Duration::from_millis(config.interval_ms);
+    let mut expiry = tokio::time::interval(Duration::from_secs(1));
+    let mut detector = MotionDetector::new(config);
+    let mut last_analyzed: Option<Instant> = None;
+    // Sender di-drop saat task dihentikan, yang juga menghentikan recorder
+    let mut recording: Option<tokio::sync::oneshot::Sender<()>> = None;
+
+    loop {
+        let change = tokio::select! {
+            result = frames.recv() => match result {
+                Ok(frame) => {
+                    if last_analyzed.is_some_and(|last| frame.received_at.saturating_duration_since(last) < interval) {
+                        continue;
+                    }
+                    last_analyzed = Some(frame.received_at);
+                    let data = frame.data.clone();
+                    let grid = match tokio::task::spawn_blocking(move || jpeg::decode(&data).map(|image| luma_grid(&image))).await {
+                        Ok(Ok(grid)) => grid,
+                        Ok(Err(e)) => {
+                            let mut status = status.lock();
+                            if status.last_error.is_none() {
+                                warn!("Motion detection skips frames of {}: {}", stream_id, e);
+                            }
+                            status.last_error = Some(e);
+                            continue;
+                        }
+                        Err(e) => {
+                            error!("Motion analysis of {} failed: {}", stream_id, e);
+                            continue;
+                        }
+                    };
+                    let change = detector.observe(grid, Instant::now());
+                    let mut status = status.lock();
+                    status.frames_analyzed += 1;
+                    status.score = detector.last_score;
+                    status.last_error = None;
+                    change
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = expiry.tick() => detector.expire(Instant::now()),
+        };
+
+        match change {
+            Some(MotionChange::Started { score }) => {
+                info!("Motion started on stream {} ({}% changed)", stream_id, score);
+                state.with_stream(&stream_id, |entry| {
+                    let seq = entry.last_seq();
+                    entry.emit(StreamEvent::MotionStarted { score, seq });
+                });
+                if record && recording.is_none() {
+                    recording = Some(start_recording(&state, &stream_id));
+                }
+            }
+            Some(MotionChange::Ended { peak_score }) => {
+                info!("Motion ended on stream {} (peak {}% changed)", stream_id, peak_score);
+                state.with_stream(&stream_id, |entry| {
+                    let seq = entry.last_seq();
+                    entry.emit(StreamEvent::MotionEnded { peak_score, seq });
+                });
+                if let Some(stop) = recording.take() {
+                    let _ = stop.send(());
+                }
+            }
+            None => continue,
+        }
+        let mut status = status.lock();
+        status.active = detector.peak.is_some();
+        status.recording = recording.is_some();
+    }
+}
+
+/// Jalankan recorder sampai sender yang dikembalikan dipakai atau di-drop
+#[cfg(feature = "recording")]
+fn start_recording(state: &AppState, stream_id: &str) -> tokio::sync::oneshot::Sender<()> {
+    let (stop, stopped) = tokio::sync::oneshot::channel();
+    let (state, stream_id) = (state.clone(), stream_id.to_string());
+    crate::runtime::disk().spawn(async move {
+        crate::recorder::record_until(state, stream_id, Duration::ZERO, async move {
+            let _ = stopped.await;
+        })
+        .await
+    });
+    stop
+}
+
+/// Tidak pernah dipanggil: `record` ditolak oleh [`MotionConfig::validate`] tanpa fitur `recording`
+#[cfg(not(feature = "recording"))]
+fn start_recording(_state: &AppState, _stream_id: &str) -> tokio::sync::oneshot::Sender<()> {
+    tokio::sync::oneshot::channel().0
+}
+
+/// Handler untuk GET /api/streams/:id/motion
+pub async fn get_motion_handler(AxumPath(stream_id): AxumPath<String>, State(state): State<AppState>) -> Response {
+    match state.motion.describe(&stream_id) {
+        Some(body) => Json(body).into_response(),
+        None => BrokerError::not_found("motion detector", stream_id).into_response(),
+    }
+}
+
+/// Handler untuk PUT /api/streams/:id/motion
+/// Detect motion on an MJPEG stream (replaces a running detector)
+pub async fn put_motion_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+    Json(config): Json<MotionConfig>,
+) -> Response {
+    if let Err(message) = config.validate() {
+        return BrokerError::InvalidRequest(message).into_response();
+    }
+    info!("Starting motion detection for stream {}", stream_id);
+    state.motion.start(&state, &stream_id, config);
+    match state.motion.describe(&stream_id) {
+        Some(body) => Json(body).into_response(),
+        None => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// Handler untuk DELETE /api/streams/:id/motion
+pub async fn delete_motion_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Result<StatusCode, BrokerError> {
+    if state.motion.stop(&stream_id) {
+        info!("Stopped motion detection for stream {}", stream_id);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(BrokerError::not_found("motion detector", stream_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: usize, height: usize, bright: impl Fn(usize, usize) -> bool) -> RgbImage {
+        let mut image = RgbImage::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let value = if bright(x, y) { 220 } else { 30 };
+                image.blend(x, y, [value; 3], 255);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn test_detector_reports_motion_start_and_end() {
+        let mut detector = MotionDetector::new(MotionConfig {
+            threshold_percent: 5.0,
+            cooldown_secs: 2,
+            ..MotionConfig::default()
+        });
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let still = luma_grid(&image(64, 48, |_, _| false));
+        // Kotak terang 16x12 piksel = 4x3 sel dari 32x24 (1.6%), lalu 32x24 piksel (25%)
+        let small = luma_grid(&image(64, 48, |x, y| x < 8 && y < 6));
+        let person = luma_grid(&image(64, 48, |x, y| x < 32 && y < 24));
+
+        assert_eq!(detector.observe(still.clone(), at(0)), None);
+        assert_eq!(detector.observe(small, at(1)), None);
+        assert_eq!(detector.observe(still.clone(), at(1)), None);
+        assert_eq!(detector.observe(person.clone(), at(2)), Some(MotionChange::Started { score: 25.0 }));
+        // Orang pergi: masih gerakan, cooldown dihitung dari gerakan terakhir
+        assert_eq!(detector.observe(still.clone(), at(3)), None);
+        assert_eq!(detector.expire(at(4)), None);
+        assert_eq!(detector.observe(still, at(5)), Some(MotionChange::Ended { peak_score: 25.0 }));
+        assert_eq!(detector.expire(at(10)), None);
+
+        assert!(MotionConfig { threshold_percent: 0.0, ..MotionConfig::default() }.validate().is_err());
+        assert!(MotionConfig::default().validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_motion_events_reach_subscribers() {
+        use crate::{config::Config, testing::TestBroker};
+        use jpeg_encoder::{ColorType, Encoder};
+
+        let broker = TestBroker::start(Config::default()).await;
+        let config = MotionConfig {
+            interval_ms: 10,
+            cooldown_secs: 1,
+            ..MotionConfig::default()
+        };
+        broker.state.motion.start(&broker.state, "door", config);
+        let mut viewer = broker.subscriber("door?seq=true").await;
+        assert_eq!(viewer.expect_json().await["type"], "sync");
+        let jpeg = |image: RgbImage| {
+            let mut out = Vec::new();
+            Encoder::new(&mut out, 90)
+                .encode(&image.pixels, image.width as u16, image.height as u16, ColorType::Rgb)
+                .unwrap();
+            out
+        };
+        let still = jpeg(image(64, 48, |_, _| false));
+        let person = jpeg(image(64, 48, |x, _| x < 32));
+
+        let mut producer = broker.producer("door").await;
+        producer.send_frame(&still).await;
+        viewer.expect_binary().await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        producer.send_frame(&person).await;
+        viewer.expect_binary().await;
+        let started = viewer.expect_json().await;
+        assert_eq!((started["type"].as_str(), started["seq"].as_u64()), (Some("motion_started"), Some(2)));
+        assert!(started["score"].as_f64().unwrap() >= 40.0);
+
+        let ended = viewer.expect_json().await;
+        assert_eq!(ended["type"], "motion_ended");
+        let body = broker.state.motion.describe("door").unwrap();
+        assert_eq!((body["status"]["active"].as_bool(), body["status"]["frames_analyzed"].as_u64()), (Some(false), Some(2)));
+    }
+}
@@ -0,0 +1,522 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+};
+use futures_util::{future::select_all, SinkExt, StreamExt};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::Arc,
+};
+use tokio::{
+    sync::{broadcast, mpsc},
+    task::JoinHandle,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    consumers::{Balance, GroupMember},
+    error::BrokerError,
+    labels::{LabelSelector, Labels},
+    playback::{self, Session},
+    stats::StreamCounters,
+    ws::{self, Subprotocol},
+    AppState,
+};
+
+/// Query parameter untuk GET /mux
+#[derive(Debug, Deserialize)]
+pub struct MuxParams {
+    /// Named stream group to follow (see `/api/groups`)
+    pub group: Option<String>,
+    /// Glob pattern matched against stream IDs, e.g. `cam/*`
+    pub pattern: Option<String>,
+    /// Only streams whose labels match, e.g. `site=a,model!=x200`
+    pub selector: Option<String>,
+    /// Share the frames with other connections of this consumer group (same `group`/`pattern`)
+    pub consumer_group: Option<String>,
+    #[serde(default)]
+    pub balance: Balance,
+    /// Playback token for browsers that cannot set an `Authorization` header
+    pub token: Option<String>,
+}
+
+/// Sumber daftar stream untuk satu koneksi multiplexed
+#[derive(Debug, Clone)]
+enum MuxSource {
+    Group(String),
+    Pattern(String),
+}
+
+impl MuxSource {
+    /// Stream IDs of the group or pattern, before the label selector
+    fn members(&self, state: &AppState) -> BTreeSet<String> {
+        match self {
+            MuxSource::Group(name) => state
+                .groups
+                .members(name)
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+            MuxSource::Pattern(pattern) => state
+                .stream_ids()
+                .into_iter()
+                .filter(|id| glob_match(pattern, id))
+                .collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for MuxSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MuxSource::Group(name) => write!(f, "group {}", name),
+            MuxSource::Pattern(pattern) => write!(f, "pattern {}", pattern),
+        }
+    }
+}
+
+/// Stream yang cocok dengan sumber dan selector label koneksi saat ini
+fn matching_streams(source: &MuxSource, selector: Option<&LabelSelector>, state: &AppState) -> BTreeSet<String> {
+    let mut wanted = source.members(state);
+    if let Some(selector) = selector {
+        // Stream yang belum ada dianggap tanpa label
+        let streams = state.streams.lock();
+        let unlabelled = Labels::new();
+        wanted.retain(|id| selector.matches(streams.get(id).map_or(&unlabelled, |entry| &entry.labels)));
+    }
+    wanted
+}
+
+/// Playback credential sebuah koneksi multiplexed, diperiksa per stream yang diikuti
+struct MuxViewer {
+    headers: HeaderMap,
+    token: Option<String>,
+    /// Streams already authorized for this connection
+    allowed: HashSet<String>,
+    /// Sessions held per subscribed stream, counted against `PLAYBACK_MAX_SESSIONS`
+    sessions: HashMap<String, Session>,
+    /// Streams the credential may not watch, skipped for the rest of the connection
+    denied: HashSet<String>,
+}
+
+impl MuxViewer {
+    /// Izinkan `stream_id` untuk koneksi ini, menyimpan sesinya
+    ///
+    /// `Forbidden` only skips the stream: a pattern can match streams the token
+    /// has no rule for. Any other error is returned.
+    async fn authorize(&mut self, state: &AppState, stream_id: &str) -> Result<bool, BrokerError> {
+        if self.allowed.contains(stream_id) {
+            return Ok(true);
+        }
+        if self.denied.contains(stream_id) {
+            return Ok(false);
+        }
+        match playback::authorize(state, stream_id, &self.headers, self.token.as_deref()).await {
+            Ok(viewer) => {
+                self.allowed.insert(stream_id.to_string());
+                self.sessions.extend(viewer.session.map(|session| (stream_id.to_string(), session)));
+                Ok(true)
+            }
+            Err(BrokerError::Forbidden(_)) => {
+                self.denied.insert(stream_id.to_string());
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Match `text` against a glob where `*` matches any run of characters
+/// (including `/`) and `?` matches exactly one character
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    // Posisi `*` terakhir dan posisi text saat itu, untuk backtracking
+    let mut star: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((star_pi, star_ti)) = star {
+            pi = star_pi + 1;
+            ti = star_ti + 1;
+            star = Some((star_pi, star_ti + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Reference-counted set of glob patterns with at least one live subscriber
+///
+/// Ingest consults this to create streams that a wildcard viewer is waiting for.
+#[derive(Debug, Default)]
+pub struct PatternRegistry {
+    active: Mutex<HashMap<String, usize>>,
+}
+
+impl PatternRegistry {
+    pub fn register(self: &Arc<Self>, pattern: &str) -> PatternGuard {
+        *self
+            .active
+            .lock()
+            .entry(pattern.to_string())
+            .or_insert(0) += 1;
+        PatternGuard {
+            registry: Arc::clone(self),
+            pattern: pattern.to_string(),
+        }
+    }
+
+    pub fn matches(&self, stream_id: &str) -> bool {
+        self.active
+            .lock()
+            .keys()
+            .any(|pattern| glob_match(pattern, stream_id))
+    }
+}
+
+/// Unregisters its pattern when the owning connection ends
+pub struct PatternGuard {
+    registry: Arc<PatternRegistry>,
+    pattern: String,
+}
+
+impl Drop for PatternGuard {
+    fn drop(&mut self) {
+        let mut active = self.registry.active.lock();
+        if let Some(count) = active.get_mut(&self.pattern) {
+            *count -= 1;
+            if *count == 0 {
+                active.remove(&self.pattern);
+            }
+        }
+    }
+}
+
+/// Panjang stream ID terpanjang yang muat di prefix `[u8 id_len]`
+pub const MAX_TAGGED_ID_LEN: usize = u8::MAX as usize;
+
+/// Stream ID yang bisa di-tag; ID lebih panjang tidak bisa lewat mux atau merge
+pub fn check_taggable(stream_id: &str) -> Result<(), BrokerError> {
+    if stream_id.len() > MAX_TAGGED_ID_LEN {
+        return Err(BrokerError::InvalidRequest(format!(
+            "stream ID {:?} is longer than {} bytes and cannot be multiplexed",
+            stream_id, MAX_TAGGED_ID_LEN
+        )));
+    }
+    Ok(())
+}
+
+/// Prefix a payload with its origin stream so one socket can carry many streams
+///
+/// Layout: `[u8 id_len][id bytes][payload]`. Returns `None` for an ID longer
+/// than [`MAX_TAGGED_ID_LEN`], which the layout cannot carry.
+pub fn encode_tagged(stream_id: &str, payload: &[u8]) -> Option<Vec<u8>> {
+    let id = stream_id.as_bytes();
+    let id_len = u8::try_from(id.len()).ok()?;
+    let mut buf = Vec::with_capacity(1 + id.len() + payload.len());
+    buf.push(id_len);
+    buf.extend_from_slice(id);
+    buf.extend_from_slice(payload);
+    Some(buf)
+}
+
+/// Handler untuk GET /mux?group=:name atau /mux?pattern=:glob
+/// Multiplexed WebSocket: frames dari banyak stream di satu koneksi
+///
+/// Every stream the connection follows is checked like `/ws/:stream_id` and
+/// holds its own playback session; streams the token may not watch are skipped.
+pub async fn mux_handler(
+    ws: WebSocketUpgrade,
+    Query(params): Query<MuxParams>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    let protocol = match Subprotocol::negotiate(&headers, &[Subprotocol::Multiplex], Subprotocol::Multiplex) {
+        Ok(protocol) => protocol,
+        Err(e) => return e.into_response(),
+    };
+    let selector = match params.selector.as_deref().map(LabelSelector::parse).transpose() {
+        Ok(selector) => selector,
+        Err(e) => return BrokerError::InvalidRequest(e.to_string()).into_response(),
+    };
+    let source = match (params.group, params.pattern) {
+        (Some(group), None) => MuxSource::Group(group),
+        (None, Some(pattern)) => MuxSource::Pattern(pattern),
+        _ => {
+            let error = "exactly one of ?group= or ?pattern= is required";
+            return BrokerError::InvalidRequest(error.to_string()).into_response();
+        }
+    };
+    // Stream yang sudah cocok diperiksa sebelum upgrade: token salah dapat 401, kuota penuh 429
+    let mut viewer = MuxViewer {
+        headers: headers.clone(),
+        token: params.token,
+        allowed: HashSet::new(),
+        sessions: HashMap::new(),
+        denied: HashSet::new(),
+    };
+    for stream_id in matching_streams(&source, selector.as_ref(), &state) {
+        if let Err(e) = viewer.authorize(&state, &stream_id).await {
+            return e.into_response();
+        }
+    }
+    info!("Multiplexed connection request for {}", source);
+    let member = match params.consumer_group.as_deref() {
+        Some(group) => match state.consumers.join(&source.to_string(), group, params.balance) {
+            Ok(member) => Some(Arc::new(member)),
+            Err(e) => return e.into_response(),
+        },
+        None => None,
+    };
+    ws::accept_protocol(ws, protocol)
+        .on_upgrade(move |socket| mux_connection(socket, source, selector, member, viewer, state))
+}
+
+/// Handle multiplexed WebSocket connection
+async fn mux_connection(
+    socket: WebSocket,
+    source: MuxSource,
+    selector: Option<LabelSelector>,
+    member: Option<Arc<GroupMember>>,
+    mut viewer: MuxViewer,
+    state: AppState,
+) {
+    let (mut sender, mut receiver) = socket.split();
+
+    let _pattern_guard = match &source {
+        MuxSource::Pattern(pattern) => Some(state.patterns.register(pattern)),
+        MuxSource::Group(_) => None,
+    };
+
+    // Semua forwarder per stream menulis ke satu antrian, hanya task ini yang menulis ke socket
+    let (tx, mut rx) = mpsc::channel::<Vec<u8>>(state.config.channel_capacity);
+    let mut forwarders: HashMap<String, JoinHandle<()>> = HashMap::new();
+    let mut untaggable = HashSet::new();
+    let mut group_changes = state.groups.watch();
+    let mut stream_changes = state.stream_changes.subscribe();
+
+    loop {
+        // Sesuaikan subscription dengan anggota group / stream yang cocok saat ini
+        let mut wanted = matching_streams(&source, selector.as_ref(), &state);
+        // ID yang tidak muat di prefix tag dilewati, diperingatkan sekali saja
+        wanted.retain(|id| match check_taggable(id) {
+            Ok(()) => true,
+            Err(e) => {
+                if untaggable.insert(id.clone()) {
+                    warn!("Skipping stream for multiplexed client of {}: {}", source, e);
+                }
+                false
+            }
+        });
+        let mut notices = Vec::new();
+        forwarders.retain(|stream_id, task| {
+            let keep = wanted.contains(stream_id);
+            if !keep {
+                task.abort();
+                notices.push(json!({ "type": "stream_removed", "stream": stream_id }));
+            }
+            keep
+        });
+        // Sesi stream yang tidak diikuti lagi dilepas; stream yang kembali diperiksa ulang
+        viewer.allowed.retain(|stream_id| wanted.contains(stream_id));
+        viewer.sessions.retain(|stream_id, _| wanted.contains(stream_id));
+        let mut rejected = None;
+        for stream_id in wanted {
+            if forwarders.contains_key(&stream_id) {
+                continue;
+            }
+            match viewer.authorize(&state, &stream_id).await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    rejected = Some(e);
+                    break;
+                }
+            }
+            let (frames, counters) =
+                state.with_stream(&stream_id, |entry| (entry.tx.subscribe(), entry.counters.clone()));
+            notices.push(json!({ "type": "stream_added", "stream": stream_id }));
+            let task = tokio::spawn(forward_stream(stream_id.clone(), frames, counters, member.clone(), tx.clone()));
+            forwarders.insert(stream_id, task);
+        }
+        let mut send_failed = false;
+        if let Some(e) = rejected {
+            warn!("Closing multiplexed client of {}: {}", source, e);
+            let _ = sender.send(Message::Close(Some(e.close_frame()))).await;
+            break;
+        }
+        for notice in notices {
+            if let Err(e) = sender.send(Message::Text(notice.to_string())).await {
+                error!("Failed to send membership notice: {}", e);
+                send_failed = true;
+                break;
+            }
+        }
+        if send_failed {
+            break;
+        }
+
+        // Tunggu frame, perubahan membership, atau pesan dari klien
+        let closed = loop {
+            tokio::select! {
+                Some(tagged) = rx.recv() => {
+                    if let Err(e) = sender.send(Message::Binary(tagged)).await {
+                        error!("Failed to send frame to multiplexed client: {}", e);
+                        break true;
+                    }
+                }
+                changed = group_changes.changed() => break changed.is_err(),
+                changed = stream_changes.changed() => break changed.is_err(),
+                _ = async { select_all(viewer.sessions.values_mut().map(|session| Box::pin(session.kicked()))).await },
+                    if !viewer.sessions.is_empty() => {
+                    warn!("Closing multiplexed client of {}: playback session limit exceeded", source);
+                    let close = BrokerError::SessionLimit("session limit exceeded".to_string()).close_frame();
+                    let _ = sender.send(Message::Close(Some(close))).await;
+                    break true;
+                }
+                msg = receiver.next() => {
+                    match msg {
+                        Some(Ok(Message::Ping(data))) => {
+                            if let Err(e) = sender.send(Message::Pong(data)).await {
+                                error!("Failed to send pong: {}", e);
+                                break true;
+                            }
+                        }
+                        Some(Ok(Message::Close(_))) | None => break true,
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => {
+                            error!("WebSocket error: {}", e);
+                            break true;
+                        }
+                    }
+                }
+            }
+        };
+        if closed {
+            break;
+        }
+    }
+
+    for task in forwarders.values() {
+        task.abort();
+    }
+    info!("Multiplexed client disconnected for {}", source);
+}
+
+/// Teruskan frame dari satu stream ke antrian koneksi multiplexed
+async fn forward_stream(
+    stream_id: String,
+    mut frames: broadcast::Receiver<crate::registry::Frame>,
+    counters: Arc<StreamCounters>,
+    member: Option<Arc<GroupMember>>,
+    tx: mpsc::Sender<Vec<u8>>,
+) {
+    loop {
+        match frames.recv().await {
+            Ok(frame) => {
+                if member.as_ref().is_some_and(|member| !member.owns(&stream_id, frame.seq)) {
+                    continue;
+                }
+                // ID sudah diperiksa saat stream ditambahkan ke koneksi
+                let Some(tagged) = encode_tagged(&stream_id, &frame.data) else {
+                    break;
+                };
+                if tx.send(tagged).await.is_err() {
+                    break;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Multiplexed client lagged, skipped {} frames for stream: {}", skipped, stream_id);
+                counters.record_drops(skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_tagged_layout() {
+        let buf = encode_tagged("cam1", b"xyz").unwrap();
+        assert_eq!(buf[0], 4);
+        assert_eq!(&buf[1..5], b"cam1");
+        assert_eq!(&buf[5..], b"xyz");
+
+        // ID terpanjang masih utuh, satu byte lagi ditolak, tidak dipotong
+        let longest = "é".repeat(MAX_TAGGED_ID_LEN / 2) + "x";
+        let buf = encode_tagged(&longest, b"xyz").unwrap();
+        assert_eq!((buf[0] as usize, &buf[1..256]), (MAX_TAGGED_ID_LEN, longest.as_bytes()));
+        let too_long = "é".repeat(MAX_TAGGED_ID_LEN / 2 + 1);
+        assert_eq!(encode_tagged(&too_long, b"xyz"), None);
+        assert!(check_taggable(&longest).is_ok());
+        assert!(check_taggable(&too_long).is_err());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("cam/*", "cam/1"));
+        assert!(glob_match("cam/*", "cam/front/left"));
+        assert!(glob_match("cam?", "cam7"));
+        assert!(glob_match("*-hd", "lobby-hd"));
+        assert!(!glob_match("cam/*", "camera1"));
+        assert!(!glob_match("cam?", "cam12"));
+    }
+
+    #[test]
+    fn test_pattern_guard_unregisters() {
+        let registry = Arc::new(PatternRegistry::default());
+        let guard = registry.register("cam/*");
+        assert!(registry.matches("cam/1"));
+        drop(guard);
+        assert!(!registry.matches("cam/1"));
+    }
+
+    #[tokio::test]
+    async fn test_mux_checks_playback_token_per_stream() {
+        use crate::{auth::CredentialStore, config::Config, testing::TestBroker};
+        use axum::http::StatusCode;
+
+        let broker = TestBroker::start(Config {
+            playback_credentials: CredentialStore::parse("guest=cam*", false).unwrap(),
+            playback_max_sessions: 2,
+            ..Config::default()
+        })
+        .await;
+        for id in ["cam1", "cam2", "lobby"] {
+            broker.state.with_stream(id, |_| ());
+        }
+        let protocol = [("sec-websocket-protocol", "bsb.multiplex.v1")];
+        let rejected = broker.connect_with_headers("/mux?pattern=*", &protocol).await;
+        assert_eq!(rejected.err(), Some(StatusCode::UNAUTHORIZED));
+
+        // Stream tanpa aturan untuk token dilewati, bukan dikirim
+        let (mut mux, _) = broker.connect_with_headers("/mux?pattern=*&token=guest", &protocol).await.unwrap();
+        assert_eq!(mux.expect_json().await["stream"], "cam1");
+        assert_eq!(mux.expect_json().await["stream"], "cam2");
+        broker.post_frame("lobby", "secret").await;
+        broker.post_frame("cam2", "frame").await;
+        assert_eq!(mux.expect_binary().await, encode_tagged("cam2", b"frame").unwrap());
+
+        // Dua stream memakai kedua sesi token
+        let full = broker.connect_with_headers("/mux?pattern=cam*&token=guest", &protocol).await;
+        assert_eq!(full.err(), Some(StatusCode::TOO_MANY_REQUESTS));
+    }
+}
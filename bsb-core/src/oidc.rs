@@ -0,0 +1,592 @@
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use bytes::Bytes;
+use http_body_util::BodyExt;
+use parking_lot::Mutex;
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio_rustls::rustls::ClientConfig;
+use tracing::{info, warn};
+
+use crate::{error::BrokerError, mux::glob_match, outbound::HttpTarget, stats::unix_now_ms, tls};
+
+/// Batas waktu satu request ke identity provider
+const PROVIDER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// JWKS diambil ulang setelah sekian lama, atau saat `kid` tidak dikenal (paling sering sekali per menit)
+const JWKS_MAX_AGE: Duration = Duration::from_secs(3600);
+const JWKS_MIN_REFETCH: Duration = Duration::from_secs(60);
+
+/// Hasil introspection yang di-cache, dibersihkan saat melebihi jumlah ini
+const MAX_CACHED_TOKENS: usize = 10_000;
+
+/// Pengaturan validasi token lewat identity provider (`OIDC_*`)
+///
+/// Tokens are checked locally against the provider's JWKS when `jwks_url` is
+/// set and the token is a JWT, otherwise sent to the RFC 7662 introspection
+/// endpoint. Stream permissions come from scopes: `publish:site-a/*` lets the
+/// token publish to `site-a/*`, `subscribe:cam1` lets it watch `cam1`.
+#[derive(Clone)]
+pub struct OidcConfig {
+    /// RFC 7662 introspection endpoint
+    pub introspection_url: Option<String>,
+    /// Client credentials sent to the introspection endpoint with HTTP Basic
+    client_id: String,
+    client_secret: String,
+    /// JWKS of the provider, for verifying JWT access tokens without a round trip
+    pub jwks_url: Option<String>,
+    /// Required `iss` claim
+    pub issuer: Option<String>,
+    /// Required entry of the `aud` claim
+    pub audience: Option<String>,
+    /// Claim holding the scopes, a space-separated string or an array
+    pub scope_claim: String,
+    /// Scope prefix granting publish on the stream glob that follows
+    pub publish_prefix: String,
+    /// Scope prefix granting subscribe on the stream glob that follows
+    pub subscribe_prefix: String,
+    /// How long an introspection result is reused
+    pub cache_ttl: Duration,
+    /// Check producer tokens on `/ingest/:stream_id`
+    pub ingest: bool,
+    /// Check subscriber tokens on `/ws/:stream_id` and `/ws/:stream_id/data`
+    pub playback: bool,
+    /// Trust anchors for `https://` provider URLs (`OIDC_CA_PATH`)
+    tls: Option<Arc<ClientConfig>>,
+}
+
+impl std::fmt::Debug for OidcConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OidcConfig")
+            .field("introspection_url", &self.introspection_url)
+            .field("jwks_url", &self.jwks_url)
+            .field("issuer", &self.issuer)
+            .field("audience", &self.audience)
+            .field("ingest", &self.ingest)
+            .field("playback", &self.playback)
+            .finish()
+    }
+}
+
+impl OidcConfig {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        introspection_url: Option<String>,
+        client_id: String,
+        client_secret: String,
+        jwks_url: Option<String>,
+        issuer: Option<String>,
+        audience: Option<String>,
+        scope_claim: String,
+        cache_ttl: Duration,
+        apply_to: &str,
+        ca_path: Option<&str>,
+    ) -> Result<Self, String> {
+        if introspection_url.is_none() && jwks_url.is_none() {
+            return Err("OIDC needs OIDC_INTROSPECTION_URL or OIDC_JWKS_URL".to_string());
+        }
+        let tls = ca_path.map(tls::client_config).transpose()?;
+        // URL divalidasi sekarang supaya salah konfigurasi terlihat saat startup
+        for url in introspection_url.iter().chain(&jwks_url) {
+            HttpTarget::parse_with_tls(url, tls.clone()).map_err(|e| format!("Invalid OIDC URL: {}", e))?;
+        }
+        let (mut ingest, mut playback) = (false, false);
+        for target in apply_to.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            match target {
+                "ingest" => ingest = true,
+                "playback" => playback = true,
+                other => return Err(format!("Invalid OIDC_APPLY_TO entry {:?} (expected ingest or playback)", other)),
+            }
+        }
+        Ok(Self {
+            introspection_url,
+            client_id,
+            client_secret,
+            jwks_url,
+            issuer,
+            audience,
+            scope_claim,
+            publish_prefix: "publish:".to_string(),
+            subscribe_prefix: "subscribe:".to_string(),
+            cache_ttl,
+            ingest,
+            playback,
+            tls,
+        })
+    }
+}
+
+/// Izin stream dari sebuah token yang valid
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grants {
+    /// `sub` claim, used in logs and as the session quota key
+    pub subject: String,
+    publish: Vec<String>,
+    subscribe: Vec<String>,
+    /// Every scope of the token, for checks such as `METADATA_REDACTION`
+    pub scopes: Vec<String>,
+    /// Token expiry (unix ms)
+    expires_ms: Option<u64>,
+}
+
+impl Grants {
+    pub fn can_publish(&self, stream_id: &str) -> bool {
+        self.publish.iter().any(|p| glob_match(p, stream_id))
+    }
+
+    pub fn can_subscribe(&self, stream_id: &str) -> bool {
+        self.subscribe.iter().any(|p| glob_match(p, stream_id))
+    }
+}
+
+/// Kunci publik dari JWKS
+#[derive(Debug, Clone)]
+enum PublicKey {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    /// Uncompressed P-256 point (`0x04 || x || y`)
+    P256(Vec<u8>),
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    #[serde(default)]
+    kid: Option<String>,
+    #[serde(default)]
+    n: Option<String>,
+    #[serde(default)]
+    e: Option<String>,
+    #[serde(default)]
+    crv: Option<String>,
+    #[serde(default)]
+    x: Option<String>,
+    #[serde(default)]
+    y: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+/// Parse dokumen JWKS; kunci dengan tipe yang tidak didukung dilewati
+fn parse_jwks(body: &[u8]) -> Result<Vec<(Option<String>, PublicKey)>, String> {
+    let set: JwkSet = serde_json::from_slice(body).map_err(|e| format!("invalid JWKS: {}", e))?;
+    let decode = |value: &Option<String>| value.as_deref().and_then(|v| URL_SAFE_NO_PAD.decode(v).ok());
+    Ok(set
+        .keys
+        .into_iter()
+        .filter_map(|jwk| {
+            let key = match (jwk.kty.as_str(), jwk.crv.as_deref()) {
+                ("RSA", _) => PublicKey::Rsa {
+                    n: decode(&jwk.n)?,
+                    e: decode(&jwk.e)?,
+                },
+                ("EC", Some("P-256")) => {
+                    let mut point = vec![0x04];
+                    point.extend(decode(&jwk.x)?);
+                    point.extend(decode(&jwk.y)?);
+                    PublicKey::P256(point)
+                }
+                _ => return None,
+            };
+            Some((jwk.kid, key))
+        })
+        .collect())
+}
+
+#[derive(Default)]
+struct KeySet {
+    keys: Vec<(Option<String>, PublicKey)>,
+    fetched: Option<Instant>,
+}
+
+impl KeySet {
+    fn find(&self, kid: Option<&str>) -> Option<PublicKey> {
+        match kid {
+            Some(kid) => self.keys.iter().find(|(id, _)| id.as_deref() == Some(kid)),
+            // Tanpa `kid` hanya boleh bila provider punya satu kunci
+            None if self.keys.len() == 1 => self.keys.first(),
+            None => None,
+        }
+        .map(|(_, key)| key.clone())
+    }
+
+    fn stale(&self, now: Instant, max_age: Duration) -> bool {
+        self.fetched.is_none_or(|fetched| now.duration_since(fetched) >= max_age)
+    }
+}
+
+/// Validator token OIDC: cache JWKS dan hasil introspection
+pub struct Oidc {
+    config: OidcConfig,
+    keys: Mutex<KeySet>,
+    /// Introspected tokens (in memory only, never logged) and when to forget them
+    cache: Mutex<HashMap<String, (Instant, Grants)>>,
+    /// One JWKS fetch at a time
+    fetching: tokio::sync::Mutex<()>,
+}
+
+impl Oidc {
+    pub fn new(config: OidcConfig) -> Self {
+        Self {
+            config,
+            keys: Mutex::new(KeySet::default()),
+            cache: Mutex::new(HashMap::new()),
+            fetching: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    /// Validasi token dan kembalikan izin streamnya
+    ///
+    /// An invalid, expired or inactive token is `Unauthorized`; an unreachable
+    /// provider is `Unavailable`, so clients retry instead of giving up.
+    pub async fn validate(&self, token: &str) -> Result<Grants, BrokerError> {
+        if self.config.jwks_url.is_some() && token.split('.').count() == 3 {
+            return self.verify_jwt(token).await;
+        }
+        if self.config.introspection_url.is_some() {
+            return self.introspect(token).await;
+        }
+        Err(BrokerError::Unauthorized("token is not a JWT".to_string()))
+    }
+
+    async fn verify_jwt(&self, token: &str) -> Result<Grants, BrokerError> {
+        let invalid = |reason: &str| BrokerError::Unauthorized(format!("invalid token: {}", reason));
+        let mut parts = token.split('.');
+        let (Some(header), Some(payload), Some(signature)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(invalid("malformed JWT"));
+        };
+        let decode_json = |part: &str| -> Option<Value> {
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(part).ok()?).ok()
+        };
+        let header = decode_json(header).ok_or_else(|| invalid("malformed header"))?;
+        let claims = decode_json(payload).ok_or_else(|| invalid("malformed claims"))?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid("malformed signature"))?;
+        let kid = header["kid"].as_str();
+
+        let (cached, stale) = {
+            let keys = self.keys.lock();
+            (keys.find(kid), keys.stale(Instant::now(), JWKS_MAX_AGE))
+        };
+        let key = match cached {
+            Some(key) if !stale => key,
+            // JWKS lama tetap dipakai selama provider tidak terjangkau
+            Some(key) => {
+                let _ = self.refresh_keys().await;
+                self.keys.lock().find(kid).unwrap_or(key)
+            }
+            None => {
+                self.refresh_keys().await?;
+                self.keys.lock().find(kid).ok_or_else(|| invalid("unknown signing key"))?
+            }
+        };
+
+        let signed = &token[..token.rfind('.').expect("three parts")];
+        let verified = match (header["alg"].as_str(), &key) {
+            (Some("RS256"), PublicKey::Rsa { n, e }) => RsaPublicKeyComponents { n, e }
+                .verify(&signature::RSA_PKCS1_2048_8192_SHA256, signed.as_bytes(), &signature)
+                .is_ok(),
+            (Some("ES256"), PublicKey::P256(point)) => UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                .verify(signed.as_bytes(), &signature)
+                .is_ok(),
+            // `none`, HMAC dan algoritma lain tidak pernah diterima
+            _ => return Err(invalid("unsupported algorithm")),
+        };
+        if !verified {
+            return Err(invalid("bad signature"));
+        }
+        if claims["exp"].as_u64().is_none() {
+            return Err(invalid("missing exp"));
+        }
+        self.grants(&claims)
+    }
+
+    /// Ambil JWKS dari provider; dilewati bila baru saja diambil
+    async fn refresh_keys(&self) -> Result<(), BrokerError> {
+        let _fetching = self.fetching.lock().await;
+        if !self.keys.lock().stale(Instant::now(), JWKS_MIN_REFETCH) {
+            return Ok(());
+        }
+        let url = self.config.jwks_url.as_deref().expect("checked in validate");
+        let unavailable = |e: String| {
+            warn!("Cannot fetch OIDC JWKS from {}: {}", url, e);
+            BrokerError::Unavailable("identity provider is unreachable".to_string())
+        };
+        let fetch = async {
+            let mut target = HttpTarget::parse_with_tls(url, self.config.tls.clone())?;
+            let response = target.get(&[("accept", "application/json".to_string())]).await?;
+            if !response.status().is_success() {
+                return Err(format!("status {}", response.status()));
+            }
+            let body = response.into_body().collect().await.map_err(|e| e.to_string())?;
+            parse_jwks(&body.to_bytes())
+        };
+        let keys = match tokio::time::timeout(PROVIDER_TIMEOUT, fetch).await {
+            Ok(Ok(keys)) => keys,
+            Ok(Err(e)) => return Err(unavailable(e)),
+            Err(_) => return Err(unavailable("timed out".to_string())),
+        };
+        info!("Loaded {} OIDC signing keys from {}", keys.len(), url);
+        *self.keys.lock() = KeySet {
+            keys,
+            fetched: Some(Instant::now()),
+        };
+        Ok(())
+    }
+
+    async fn introspect(&self, token: &str) -> Result<Grants, BrokerError> {
+        let now = Instant::now();
+        if let Some((until, grants)) = self.cache.lock().get(token) {
+            if *until > now {
+                return Ok(grants.clone());
+            }
+        }
+        let url = self.config.introspection_url.as_deref().expect("checked in validate");
+        let unavailable = |e: String| {
+            warn!("OIDC introspection at {} failed: {}", url, e);
+            BrokerError::Unavailable("identity provider is unreachable".to_string())
+        };
+        let body = format!("token={}&token_type_hint=access_token", form_encode(token));
+        let mut headers = Vec::new();
+        if !self.config.client_id.is_empty() {
+            let basic = STANDARD.encode(format!(
+                "{}:{}",
+                form_encode(&self.config.client_id),
+                form_encode(&self.config.client_secret)
+            ));
+            headers.push(("authorization", format!("Basic {}", basic)));
+        }
+        let request = async {
+            let mut target = HttpTarget::parse_with_tls(url, self.config.tls.clone())?;
+            target
+                .post_read("application/x-www-form-urlencoded", &headers, Bytes::from(body))
+                .await
+        };
+        let (status, response) = match tokio::time::timeout(PROVIDER_TIMEOUT, request).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => return Err(unavailable(e)),
+            Err(_) => return Err(unavailable("timed out".to_string())),
+        };
+        if !status.is_success() {
+            return Err(unavailable(format!("status {}", status)));
+        }
+        let claims: Value = serde_json::from_slice(&response).map_err(|e| unavailable(e.to_string()))?;
+        if claims["active"] != Value::Bool(true) {
+            return Err(BrokerError::Unauthorized("token is not active".to_string()));
+        }
+        let grants = self.grants(&claims)?;
+
+        // Cache tidak melewati masa berlaku token
+        let mut ttl = self.config.cache_ttl;
+        if let Some(expires_ms) = grants.expires_ms {
+            ttl = ttl.min(Duration::from_millis(expires_ms.saturating_sub(unix_now_ms())));
+        }
+        let mut cache = self.cache.lock();
+        if cache.len() >= MAX_CACHED_TOKENS {
+            cache.retain(|_, (until, _)| *until > now);
+        }
+        if cache.len() < MAX_CACHED_TOKENS {
+            cache.insert(token.to_string(), (now + ttl, grants.clone()));
+        }
+        Ok(grants)
+    }
+
+    /// Periksa `exp`/`nbf`/`iss`/`aud` lalu petakan scope ke izin stream
+    fn grants(&self, claims: &Value) -> Result<Grants, BrokerError> {
+        let invalid = |reason: String| BrokerError::Unauthorized(format!("invalid token: {}", reason));
+        let now_secs = unix_now_ms() / 1000;
+        let expires = claims["exp"].as_u64();
+        if expires.is_some_and(|exp| exp <= now_secs) {
+            return Err(invalid("expired".to_string()));
+        }
+        if claims["nbf"].as_u64().is_some_and(|nbf| nbf > now_secs) {
+            return Err(invalid("not yet valid".to_string()));
+        }
+        if let Some(issuer) = &self.config.issuer {
+            if claims["iss"].as_str() != Some(issuer) {
+                return Err(invalid(format!("issuer is not {}", issuer)));
+            }
+        }
+        if let Some(audience) = &self.config.audience {
+            let matches = match &claims["aud"] {
+                Value::String(aud) => aud == audience,
+                Value::Array(auds) => auds.iter().any(|aud| aud.as_str() == Some(audience)),
+                _ => false,
+            };
+            if !matches {
+                return Err(invalid(format!("audience is not {}", audience)));
+            }
+        }
+
+        let scopes: Vec<&str> = match &claims[self.config.scope_claim.as_str()] {
+            Value::String(scopes) => scopes.split_whitespace().collect(),
+            Value::Array(scopes) => scopes.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let with_prefix = |prefix: &str| -> Vec<String> {
+            scopes
+                .iter()
+                .filter_map(|scope| scope.strip_prefix(prefix))
+                .filter(|glob| !glob.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        Ok(Grants {
+            subject: claims["sub"].as_str().unwrap_or("<unknown>").to_string(),
+            publish: with_prefix(&self.config.publish_prefix),
+            subscribe: with_prefix(&self.config.subscribe_prefix),
+            scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+            expires_ms: expires.map(|exp| exp * 1000),
+        })
+    }
+}
+
+/// Percent-encode untuk `application/x-www-form-urlencoded`
+fn form_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, testing::TestBroker};
+    use axum::{http::StatusCode, routing::get, routing::post, Form, Json, Router};
+    use ring::{
+        rand::SystemRandom,
+        signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+    };
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_oidc_jwt_and_introspection_map_scopes_to_streams() {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+        let point = key.public_key().as_ref().to_vec();
+        let jwks = json!({ "keys": [{
+            "kty": "EC", "crv": "P-256", "kid": "k1",
+            "x": URL_SAFE_NO_PAD.encode(&point[1..33]), "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+        }]});
+        let introspections = Arc::new(AtomicUsize::new(0));
+        let counter = introspections.clone();
+        let provider = Router::new()
+            .route("/jwks", get(move || async move { Json(jwks) }))
+            .route(
+                "/introspect",
+                post(move |Form(form): Form<HashMap<String, String>>| async move {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    Json(match form["token"].as_str() {
+                        "opaque-viewer" => json!({ "active": true, "sub": "alice", "iss": "https://idp", "scope": "subscribe:site-a-*" }),
+                        _ => json!({ "active": false }),
+                    })
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let idp = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, provider).await });
+
+        let config = OidcConfig::new(
+            Some(format!("{}/introspect", idp)),
+            "broker".to_string(),
+            "secret".to_string(),
+            Some(format!("{}/jwks", idp)),
+            Some("https://idp".to_string()),
+            None,
+            "scope".to_string(),
+            Duration::from_secs(60),
+            "ingest,playback",
+            None,
+        )
+        .unwrap();
+        let jwt = |header: Value, claims: Value| {
+            let signed = format!(
+                "{}.{}",
+                URL_SAFE_NO_PAD.encode(header.to_string()),
+                URL_SAFE_NO_PAD.encode(claims.to_string())
+            );
+            let signature = key.sign(&rng, signed.as_bytes()).unwrap();
+            format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature.as_ref()))
+        };
+        let es256 = json!({ "alg": "ES256", "kid": "k1" });
+        let exp = unix_now_ms() / 1000 + 600;
+        let camera = jwt(es256.clone(), json!({ "sub": "cam-7", "iss": "https://idp", "exp": exp, "scope": "publish:site-a-*" }));
+
+        // JWT diverifikasi lokal dengan JWKS; token buruk ditolak
+        let oidc = Oidc::new(config.clone());
+        let grants = oidc.validate(&camera).await.unwrap();
+        assert!(grants.can_publish("site-a-cam1") && !grants.can_publish("site-b-cam1"));
+        assert!(!grants.can_subscribe("site-a-cam1"));
+        let expired = jwt(es256.clone(), json!({ "iss": "https://idp", "exp": 1, "scope": "publish:*" }));
+        let foreign = jwt(es256.clone(), json!({ "iss": "https://evil", "exp": exp, "scope": "publish:*" }));
+        let unsigned = format!("{}.e30.", URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#));
+        let tampered = format!("{}x", &camera[..camera.len() - 1]);
+        for token in [expired, foreign, unsigned, tampered] {
+            assert!(matches!(oidc.validate(&token).await, Err(BrokerError::Unauthorized(_))), "{}", token);
+        }
+
+        // Token opaque lewat introspection, hasilnya di-cache
+        let viewer = oidc.validate("opaque-viewer").await.unwrap();
+        assert_eq!(viewer.subject, "alice");
+        assert!(viewer.can_subscribe("site-a-cam1"));
+        oidc.validate("opaque-viewer").await.unwrap();
+        assert_eq!(introspections.load(Ordering::Relaxed), 1);
+        assert!(matches!(oidc.validate("revoked").await, Err(BrokerError::Unauthorized(_))));
+
+        // Lewat broker: scope menentukan stream yang boleh di-publish dan ditonton
+        let broker = TestBroker::start(Config {
+            oidc: Some(config),
+            ..Config::default()
+        })
+        .await;
+        let mut viewer = broker.subscriber("site-a-cam1?token=opaque-viewer").await;
+        let publish = |stream: &str, token: &str| {
+            let target = HttpTarget::parse(&broker.url(&format!("/ingest/{}", stream)));
+            let headers = [("authorization", format!("Bearer {}", token))];
+            async move {
+                let body = Bytes::from_static(b"frame");
+                target.unwrap().post("application/octet-stream", &headers, body).await.unwrap()
+            }
+        };
+        assert_eq!(publish("site-b-cam1", &camera).await, StatusCode::FORBIDDEN);
+        assert_eq!(publish("site-a-cam1", "revoked").await, StatusCode::UNAUTHORIZED);
+        assert_eq!(publish("site-a-cam1", &camera).await, StatusCode::OK);
+        assert_eq!(viewer.expect_binary().await, b"frame");
+
+        // Provider mati: 503, bukan 401
+        let down = OidcConfig::new(
+            Some("http://127.0.0.1:1/introspect".to_string()),
+            String::new(),
+            String::new(),
+            None,
+            None,
+            None,
+            "scope".to_string(),
+            Duration::from_secs(60),
+            "ingest",
+            None,
+        )
+        .unwrap();
+        assert!(matches!(Oidc::new(down).validate("anything").await, Err(BrokerError::Unavailable(_))));
+    }
+}
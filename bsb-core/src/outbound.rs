@@ -0,0 +1,424 @@
+use axum::http::{header, Request, Response, StatusCode, Uri};
+use bytes::Bytes;
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use http_body_util::{BodyExt, Full};
+use hyper::{body::Incoming, client::conn::http1::SendRequest};
+use hyper_util::rt::TokioIo;
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use tokio::net::{TcpSocket, TcpStream};
+use tokio_rustls::{
+    rustls::{pki_types::ServerName, ClientConfig},
+    TlsConnector,
+};
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, handshake::client::Response as WsResponse},
+    Connector, MaybeTlsStream, WebSocketStream,
+};
+use tracing::{debug, warn};
+
+use crate::hooks::{self, Resolver};
+
+/// Pengaturan koneksi keluar, diset sekali saat startup
+static DIAL: OnceLock<DialConfig> = OnceLock::new();
+
+/// Cara membuka koneksi TCP keluar (`OUTBOUND_*`)
+///
+/// Every outbound connection (pull sources, taps, webhooks, federation, HA and
+/// cluster links) goes through [`connect`]. On multi-homed edge gateways the
+/// source addresses pick the uplink (e.g. wired vs LTE) by binding to an
+/// address of that interface.
+#[derive(Debug, Clone)]
+pub struct DialConfig {
+    /// Local addresses to connect from, at most one per IP family. With only one
+    /// family listed, destinations of the other family are not tried
+    pub bind: Vec<IpAddr>,
+    /// Happy eyeballs: wait this long for an attempt before racing the next address
+    pub attempt_delay: Duration,
+}
+
+impl Default for DialConfig {
+    fn default() -> Self {
+        Self {
+            bind: Vec::new(),
+            attempt_delay: Duration::from_millis(250),
+        }
+    }
+}
+
+impl DialConfig {
+    pub fn parse(bind: &str, attempt_delay_ms: u64) -> Result<Self, String> {
+        let mut addrs: Vec<IpAddr> = Vec::new();
+        for item in bind.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let addr: IpAddr = item
+                .parse()
+                .map_err(|_| format!("Invalid OUTBOUND_BIND_ADDRESSES entry {:?}", item))?;
+            if addrs.iter().any(|other| other.is_ipv4() == addr.is_ipv4()) {
+                return Err("OUTBOUND_BIND_ADDRESSES takes at most one address per IP family".to_string());
+            }
+            addrs.push(addr);
+        }
+        Ok(Self {
+            bind: addrs,
+            attempt_delay: Duration::from_millis(attempt_delay_ms),
+        })
+    }
+
+    /// Alamat lokal untuk tujuan `addr`: `Some(None)` = default OS, `None` = family tidak dipakai
+    fn source_for(&self, addr: &SocketAddr) -> Option<Option<IpAddr>> {
+        if self.bind.is_empty() {
+            return Some(None);
+        }
+        self.bind
+            .iter()
+            .find(|source| source.is_ipv4() == addr.is_ipv4())
+            .map(|source| Some(*source))
+    }
+}
+
+/// Pasang pengaturan koneksi keluar; dipanggil sekali sebelum koneksi pertama
+pub fn configure(config: DialConfig) {
+    let _ = DIAL.set(config);
+}
+
+fn dial_config() -> &'static DialConfig {
+    DIAL.get_or_init(DialConfig::default)
+}
+
+/// Buka koneksi TCP ke `host:port` dengan happy eyeballs (RFC 8305)
+///
+/// The resolved addresses are interleaved by family, starting with the family
+/// the resolver returned first. Attempts start `attempt_delay` apart (or as
+/// soon as the previous one fails) and race; the first to connect wins, so a
+/// broken IPv6 path costs at most one delay instead of a full TCP timeout.
+pub async fn connect(host: &str, port: u16) -> io::Result<TcpStream> {
+    connect_with(host, port, dial_config(), hooks::resolver()).await
+}
+
+async fn connect_with(host: &str, port: u16, config: &DialConfig, resolver: &dyn Resolver) -> io::Result<TcpStream> {
+    // Literal IPv6 di URI memakai kurung siku (`[::1]`)
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let resolved = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => resolver.resolve(host, port).await?,
+    };
+    let candidates: Vec<(SocketAddr, Option<IpAddr>)> = interleave(resolved)
+        .into_iter()
+        .filter_map(|addr| config.source_for(&addr).map(|source| (addr, source)))
+        .collect();
+    let mut pending = candidates.into_iter();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_error = None;
+    loop {
+        if attempts.is_empty() {
+            match pending.next() {
+                Some((addr, source)) => attempts.push(connect_from(addr, source)),
+                None => {
+                    return Err(last_error.unwrap_or_else(|| {
+                        io::Error::new(io::ErrorKind::NotFound, format!("{} has no usable address", host))
+                    }))
+                }
+            }
+        }
+        tokio::select! {
+            Some(result) = attempts.next() => match result {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            },
+            _ = tokio::time::sleep(config.attempt_delay), if pending.len() > 0 => {
+                if let Some((addr, source)) = pending.next() {
+                    attempts.push(connect_from(addr, source));
+                }
+            }
+        }
+    }
+}
+
+async fn connect_from(addr: SocketAddr, source: Option<IpAddr>) -> io::Result<TcpStream> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    if let Some(source) = source {
+        socket.bind(SocketAddr::new(source, 0))?;
+    }
+    let result = socket.connect(addr).await;
+    if let Err(e) = &result {
+        debug!("Connect to {} failed: {}", addr, e);
+    }
+    result
+}
+
+/// Urutkan alamat bergantian per family, dimulai dari family alamat pertama
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_v6 = addrs.first().is_some_and(SocketAddr::is_ipv6);
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) = addrs.into_iter().partition(|addr| addr.is_ipv6() == first_v6);
+    let mut ordered = Vec::with_capacity(preferred.len() + other.len());
+    preferred.reverse();
+    other.reverse();
+    while let Some(addr) = preferred.pop() {
+        ordered.push(addr);
+        if let Some(addr) = other.pop() {
+            ordered.push(addr);
+        }
+    }
+    ordered.extend(other.into_iter().rev());
+    ordered
+}
+
+/// Pisahkan `host:port` (juga `[v6]:port`) dari URL `tcp://`
+pub fn split_host_port(addr: &str) -> Result<(&str, u16), String> {
+    let (host, port) = addr
+        .rsplit_once(':')
+        .ok_or_else(|| format!("address {:?} has no port", addr))?;
+    let port = port.parse().map_err(|_| format!("invalid port in {:?}", addr))?;
+    Ok((host.trim_start_matches('[').trim_end_matches(']'), port))
+}
+
+/// Handshake WebSocket (`ws://`/`wss://`) di atas [`connect`]
+pub async fn connect_ws(
+    request: impl IntoClientRequest,
+    connector: Option<Connector>,
+) -> Result<(WebSocketStream<MaybeTlsStream<TcpStream>>, WsResponse), String> {
+    let request = request.into_client_request().map_err(|e| e.to_string())?;
+    let uri = request.uri();
+    let host = uri.host().ok_or("URL has no host")?.to_string();
+    let port = match (uri.port_u16(), uri.scheme_str()) {
+        (Some(port), _) => port,
+        (None, Some("wss")) => 443,
+        (None, Some("ws")) => 80,
+        _ => return Err(format!("unsupported WebSocket URL {}", uri)),
+    };
+    let stream = connect(&host, port)
+        .await
+        .map_err(|e| format!("connect to {}:{} failed: {}", host, port, e))?;
+    // Frame kecil dikirim segera
+    stream.set_nodelay(true).map_err(|e| e.to_string())?;
+    tokio_tungstenite::client_async_tls_with_config(request, stream, None, connector)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Koneksi HTTP/1.1 keluar ke `http://host[:port]/path` (tap output, webhook, pull source)
+///
+/// The connection is kept alive between requests and re-established after
+/// any error. Plain HTTP is the norm, since these targets are sidecars and
+/// internal services on the same network; `https://` needs a client TLS config
+/// ([`HttpTarget::parse_with_tls`]).
+pub struct HttpTarget {
+    uri: Uri,
+    authority: String,
+    tls: Option<Arc<ClientConfig>>,
+    sender: Option<SendRequest<Full<Bytes>>>,
+}
+
+impl HttpTarget {
+    pub fn parse(url: &str) -> Result<Self, String> {
+        Self::parse_with_tls(url, None)
+    }
+
+    /// Seperti [`HttpTarget::parse`], tapi `https://` diizinkan bila `tls` diberikan
+    pub fn parse_with_tls(url: &str, tls: Option<Arc<ClientConfig>>) -> Result<Self, String> {
+        let uri: Uri = url.parse().map_err(|e| format!("invalid URL {:?}: {}", url, e))?;
+        match (uri.scheme_str(), &tls) {
+            (Some("http"), _) | (Some("https"), Some(_)) => {}
+            (Some("https"), None) => return Err(format!("https:// URLs need a CA bundle, got {:?}", url)),
+            _ => return Err(format!("only http:// URLs are supported, got {:?}", url)),
+        }
+        let authority = uri
+            .authority()
+            .ok_or_else(|| format!("URL {:?} has no host", url))?
+            .to_string();
+        Ok(Self {
+            tls: tls.filter(|_| uri.scheme_str() == Some("https")),
+            uri,
+            authority,
+            sender: None,
+        })
+    }
+
+    async fn connect(&mut self) -> Result<&mut SendRequest<Full<Bytes>>, String> {
+        if self.sender.as_ref().is_some_and(|sender| !sender.is_closed()) {
+            return Ok(self.sender.as_mut().expect("checked above"));
+        }
+        let host = self.uri.host().unwrap_or_default();
+        let port = self.uri.port_u16().unwrap_or(if self.tls.is_some() { 443 } else { 80 });
+        let stream = connect(host, port)
+            .await
+            .map_err(|e| format!("connect to {} failed: {}", self.authority, e))?;
+        let sender = match &self.tls {
+            Some(tls) => {
+                let name = ServerName::try_from(host.to_string()).map_err(|e| e.to_string())?;
+                let stream = TlsConnector::from(tls.clone())
+                    .connect(name, stream)
+                    .await
+                    .map_err(|e| format!("TLS handshake with {} failed: {}", self.authority, e))?;
+                self.handshake(stream).await?
+            }
+            None => self.handshake(stream).await?,
+        };
+        Ok(self.sender.insert(sender))
+    }
+
+    async fn handshake<S>(&self, stream: S) -> Result<SendRequest<Full<Bytes>>, String>
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        let (sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+            .await
+            .map_err(|e| e.to_string())?;
+        let authority = self.authority.clone();
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                warn!("HTTP connection to {} failed: {}", authority, e);
+            }
+        });
+        Ok(sender)
+    }
+
+    fn path(&self) -> String {
+        self.uri
+            .path_and_query()
+            .map_or("/", |path| path.as_str())
+            .to_string()
+    }
+
+    /// GET dengan body response yang di-stream (misalnya MJPEG `multipart/x-mixed-replace`)
+    ///
+    /// The connection is busy until the returned body is dropped.
+    pub async fn get(&mut self, headers: &[(&str, String)]) -> Result<Response<Incoming>, String> {
+        let mut request = Request::get(self.path()).header(header::HOST, &self.authority);
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+        let request = request.body(Full::new(Bytes::new())).map_err(|e| e.to_string())?;
+
+        let sender = self.connect().await?;
+        sender.send_request(request).await.map_err(|e| {
+            self.sender = None;
+            e.to_string()
+        })
+    }
+
+    /// POST `body` dan kembalikan status response
+    pub async fn post(
+        &mut self,
+        content_type: &str,
+        headers: &[(&str, String)],
+        body: Bytes,
+    ) -> Result<StatusCode, String> {
+        self.post_read(content_type, headers, body).await.map(|(status, _)| status)
+    }
+
+    /// POST `body` dan kembalikan status beserta body response
+    pub async fn post_read(
+        &mut self,
+        content_type: &str,
+        headers: &[(&str, String)],
+        body: Bytes,
+    ) -> Result<(StatusCode, Bytes), String> {
+        let mut request = Request::post(self.path())
+            .header(header::HOST, &self.authority)
+            .header(header::CONTENT_TYPE, content_type);
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+        let request = request.body(Full::new(body)).map_err(|e| e.to_string())?;
+
+        let sender = self.connect().await?;
+        match sender.send_request(request).await {
+            Ok(response) => {
+                let status = response.status();
+                // Body harus dibaca habis supaya koneksi bisa dipakai ulang
+                match response.into_body().collect().await {
+                    Ok(body) => Ok((status, body.to_bytes())),
+                    Err(e) => {
+                        self.sender = None;
+                        Err(e.to_string())
+                    }
+                }
+            }
+            Err(e) => {
+                self.sender = None;
+                Err(e.to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hooks::SystemResolver;
+    use futures_util::future::BoxFuture;
+
+    #[test]
+    fn test_http_target_parse() {
+        let target = HttpTarget::parse("http://inference:8000/frames?model=yolo").unwrap();
+        assert_eq!(target.authority, "inference:8000");
+        assert_eq!(target.uri.path_and_query().unwrap().as_str(), "/frames?model=yolo");
+        assert!(HttpTarget::parse("https://inference/frames").is_err());
+        assert!(HttpTarget::parse("not a url").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_happy_eyeballs_falls_back_and_binds_source() {
+        let v4 = |port| SocketAddr::from(([127, 0, 0, 1], port));
+        let v6 = |port| SocketAddr::from((std::net::Ipv6Addr::LOCALHOST, port));
+        assert_eq!(interleave(vec![v6(1), v6(2), v4(3), v4(4), v4(5)]), vec![v6(1), v4(3), v6(2), v4(4), v4(5)]);
+        assert_eq!(interleave(vec![v4(1), v6(2)]), vec![v4(1), v6(2)]);
+
+        let listener = tokio::net::TcpListener::bind(v4(0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // localhost juga me-resolve ke ::1 yang tidak mendengarkan: IPv4 tetap tersambung
+        let config = DialConfig::parse("127.0.0.1", 50).unwrap();
+        let stream = connect_with("localhost", port, &config, &SystemResolver).await.unwrap();
+        assert_eq!(stream.local_addr().unwrap().ip(), IpAddr::from([127, 0, 0, 1]));
+        let stream = connect_with("127.0.0.1", port, &DialConfig::default(), &SystemResolver).await.unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), v4(port));
+
+        // Hanya IPv6 yang di-bind: tujuan IPv4 tidak dicoba
+        let config = DialConfig::parse("::1", 50).unwrap();
+        assert!(connect_with("127.0.0.1", port, &config, &SystemResolver).await.is_err());
+        assert!(DialConfig::parse("10.0.0.1,10.0.0.2", 250).is_err());
+    }
+
+    /// Tabel host statis, seperti di kamera tanpa DNS
+    struct StaticHosts(Vec<(&'static str, SocketAddr)>);
+
+    impl Resolver for StaticHosts {
+        fn resolve<'a>(&'a self, host: &'a str, port: u16) -> BoxFuture<'a, io::Result<Vec<SocketAddr>>> {
+            let found: Vec<SocketAddr> = self
+                .0
+                .iter()
+                .filter(|(name, _)| *name == host)
+                .map(|(_, addr)| SocketAddr::new(addr.ip(), port))
+                .collect();
+            Box::pin(async move {
+                if found.is_empty() {
+                    return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} not in host table", host)));
+                }
+                Ok(found)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect_resolves_through_custom_resolver() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let hosts = StaticHosts(vec![("gateway.local", SocketAddr::from(([127, 0, 0, 1], 0)))]);
+
+        let stream = connect_with("gateway.local", port, &DialConfig::default(), &hosts).await.unwrap();
+        assert_eq!(stream.peer_addr().unwrap().port(), port);
+        let missing = connect_with("nvr.local", port, &DialConfig::default(), &hosts).await.unwrap_err();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+        // Literal IP tidak ditanyakan ke resolver
+        assert!(connect_with("127.0.0.1", port, &DialConfig::default(), &hosts).await.is_ok());
+    }
+}
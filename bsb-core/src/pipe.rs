@@ -0,0 +1,173 @@
+use bytes::Bytes;
+use std::{
+    fs::File,
+    io::{BufReader, ErrorKind, Read},
+    time::Duration,
+};
+use tracing::{debug, info, warn};
+
+use crate::{supervisor, AppState, IngestParams};
+
+/// Path pipe yang berarti stdin broker
+pub const STDIN: &str = "-";
+
+/// Pipe lokal yang dibaca broker sendiri (`--ingest-pipe path=stream`, `INGEST_PIPES`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct IngestPipe {
+    /// Named pipe (FIFO), a regular file, or `-` for stdin
+    pub path: String,
+    pub stream_id: String,
+}
+
+impl IngestPipe {
+    /// Parse `path=stream,path=stream`; path boleh mengandung `=`, stream tidak
+    pub fn parse_list(spec: &str) -> Result<Vec<IngestPipe>, String> {
+        let mut pipes = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (path, stream_id) = entry
+                .rsplit_once('=')
+                .filter(|(path, stream_id)| !path.trim().is_empty() && !stream_id.trim().is_empty())
+                .ok_or_else(|| format!("Invalid INGEST_PIPES entry {:?}, expected <path>=<stream_id>", entry))?;
+            pipes.push(IngestPipe {
+                path: path.trim().to_string(),
+                stream_id: stream_id.trim().to_string(),
+            });
+        }
+        if pipes.iter().filter(|pipe| pipe.is_stdin()).count() > 1 {
+            return Err("INGEST_PIPES may read stdin (-) for one stream only".to_string());
+        }
+        Ok(pipes)
+    }
+
+    pub fn is_stdin(&self) -> bool {
+        self.path == STDIN
+    }
+}
+
+/// Mulai pembaca untuk setiap pipe di `INGEST_PIPES`
+pub fn spawn_all(state: &AppState) {
+    for pipe in &state.config.ingest_pipes {
+        info!("Ingesting stream {} from pipe {}", pipe.stream_id, pipe.path);
+        let (state, pipe) = (state.clone(), pipe.clone());
+        supervisor::supervise(format!("pipe {}", pipe.stream_id), move || run_pipe(state.clone(), pipe.clone()));
+    }
+}
+
+/// Baca pipe selamanya: FIFO dibuka ulang untuk writer berikutnya, stdin dan file biasa sekali saja
+async fn run_pipe(state: AppState, pipe: IngestPipe) {
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        let (reader_state, reader_pipe) = (state.clone(), pipe.clone());
+        let result = match tokio::task::spawn_blocking(move || read_pipe(&reader_state, &reader_pipe)).await {
+            Ok(result) => result,
+            // Panic di thread pembaca diteruskan ke supervisor
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        };
+        match result {
+            Ok((frames, reopen)) => {
+                info!("Pipe {} for stream {} closed after {} frames", pipe.path, pipe.stream_id, frames);
+                if !reopen {
+                    return;
+                }
+                if frames > 0 {
+                    backoff = Duration::from_secs(1);
+                }
+            }
+            Err(e) => warn!("Pipe {} for stream {} failed: {}", pipe.path, pipe.stream_id, e),
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(Duration::from_secs(30));
+    }
+}
+
+/// Baca satu sesi writer di thread blocking; `(frames, buka ulang?)`
+///
+/// Opening a FIFO blocks until an encoder opens it for writing, and reading
+/// ends when that encoder closes it, so a restarted encoder is picked up by
+/// the next open.
+fn read_pipe(state: &AppState, pipe: &IngestPipe) -> Result<(u64, bool), String> {
+    let max_len = state.config.ws_max_message_size;
+    let publish = |data: Bytes| {
+        if let Err(e) = crate::ingest_frame(state, &pipe.stream_id, &IngestParams::default(), data, None) {
+            debug!("Frame from pipe {} for stream {} rejected: {}", pipe.path, pipe.stream_id, e);
+        }
+    };
+    if pipe.is_stdin() {
+        return read_frames(std::io::stdin().lock(), max_len, publish).map(|frames| (frames, false));
+    }
+    let file = File::open(&pipe.path).map_err(|e| e.to_string())?;
+    let regular = file.metadata().is_ok_and(|metadata| metadata.is_file());
+    read_frames(file, max_len, publish).map(|frames| (frames, !regular))
+}
+
+/// Frame `[u32 len][payload]` (big-endian) sampai EOF, seperti source `tcp://`
+fn read_frames(reader: impl Read, max_len: usize, mut publish: impl FnMut(Bytes)) -> Result<u64, String> {
+    let mut reader = BufReader::with_capacity(64 << 10, reader);
+    let mut frames = 0;
+    loop {
+        let mut len = [0; 4];
+        match reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(frames),
+            Err(e) => return Err(e.to_string()),
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        // Panjang yang tidak masuk akal berarti writer tidak sinkron; baca ulang dari awal sesi berikutnya
+        if len > max_len {
+            return Err(format!("frame of {} bytes is above WS_MAX_MESSAGE_SIZE ({})", len, max_len));
+        }
+        let mut payload = vec![0; len];
+        reader
+            .read_exact(&mut payload)
+            .map_err(|e| format!("truncated frame after {} frames: {}", frames, e))?;
+        frames += 1;
+        publish(Bytes::from(payload));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[tokio::test]
+    async fn test_pipe_frames_are_published() {
+        assert_eq!(
+            IngestPipe::parse_list("/tmp/a=b.fifo=cam1, -=cam2").unwrap(),
+            vec![
+                IngestPipe { path: "/tmp/a=b.fifo".to_string(), stream_id: "cam1".to_string() },
+                IngestPipe { path: "-".to_string(), stream_id: "cam2".to_string() },
+            ]
+        );
+        assert!(IngestPipe::parse_list("/tmp/cam1.fifo").is_err());
+        assert!(IngestPipe::parse_list("-=cam1,-=cam2").is_err());
+
+        let mut data = Vec::new();
+        for payload in [&b"frame-1"[..], b"frame-2"] {
+            data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+            data.extend_from_slice(payload);
+        }
+        let mut frames = Vec::new();
+        assert_eq!(read_frames(&data[..], 16, |frame| frames.push(frame)), Ok(2));
+        assert_eq!(frames, [&b"frame-1"[..], b"frame-2"]);
+        assert!(read_frames(&data[..data.len() - 1], 16, |_| ()).is_err());
+        assert!(read_frames(&data[..], 4, |_| ()).is_err());
+
+        // File biasa dibaca sekali lalu pembacanya berhenti
+        let path = std::env::temp_dir().join(format!("bsb-pipe-test-{}", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let state = AppState::new(Config {
+            ingest_pipes: vec![IngestPipe {
+                path: path.to_str().unwrap().to_string(),
+                stream_id: "cam1".to_string(),
+            }],
+            ..Config::default()
+        });
+        let mut rx = state.with_stream("cam1", |entry| entry.tx.subscribe());
+        spawn_all(&state);
+        assert_eq!(&rx.recv().await.unwrap().data[..], b"frame-1");
+        let frame = rx.recv().await.unwrap();
+        assert_eq!((frame.seq, &frame.data[..]), (2, &b"frame-2"[..]));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,254 @@
+use bytes::Bytes;
+use std::collections::BTreeMap;
+
+use crate::{registry::Frame, ws::Subprotocol};
+
+/// Stage terbanyak dalam satu `?pipeline=`
+pub const MAX_STAGES: usize = 8;
+
+/// Satu langkah transformasi frame untuk satu subscriber
+///
+/// A transform sees the subscriber's frames in order and returns the frame to
+/// send, possibly with new data, or `None` to skip it. Skipped frames are not
+/// reported as a `gap`, like frames dropped by `max_fps`.
+pub trait Transform: Send {
+    fn apply(&mut self, frame: Frame) -> Option<Frame>;
+}
+
+/// Membuat transform dari argumen di antara kurung (`None` tanpa kurung)
+pub type TransformFactory = fn(Option<&str>) -> Result<Box<dyn Transform>, String>;
+
+/// Transform plugin yang bisa dipakai subscriber di `?pipeline=`
+///
+/// Plugins are registered by name when the broker starts; the built-in ones
+/// are `decimate(n)` and `rewrap(format)`.
+pub struct TransformRegistry {
+    plugins: BTreeMap<&'static str, TransformFactory>,
+}
+
+impl std::fmt::Debug for TransformRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransformRegistry")
+            .field("plugins", &self.plugins.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Default for TransformRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            plugins: BTreeMap::new(),
+        };
+        registry.register("decimate", Decimate::create);
+        registry.register("rewrap", Rewrap::create);
+        registry
+    }
+}
+
+impl TransformRegistry {
+    /// Daftarkan plugin; nama yang sama menggantikan plugin sebelumnya
+    pub fn register(&mut self, name: &'static str, factory: TransformFactory) {
+        self.plugins.insert(name, factory);
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.plugins.keys().copied().collect()
+    }
+
+    /// Parse `name(arg),name,...` menjadi pipeline untuk satu koneksi
+    pub fn build(&self, spec: &str) -> Result<Pipeline, String> {
+        let stages = split_stages(spec)?;
+        if stages.is_empty() {
+            return Err("pipeline is empty".to_string());
+        }
+        if stages.len() > MAX_STAGES {
+            return Err(format!("pipeline has {} stages, at most {} allowed", stages.len(), MAX_STAGES));
+        }
+        let mut transforms = Vec::with_capacity(stages.len());
+        for (name, arg) in stages {
+            let factory = self.plugins.get(name).ok_or_else(|| {
+                format!("unknown transform {:?} (available: {})", name, self.names().join(", "))
+            })?;
+            transforms.push(factory(arg).map_err(|e| format!("{}: {}", name, e))?);
+        }
+        Ok(Pipeline { transforms })
+    }
+}
+
+/// Pisahkan `decimate(5),rewrap(envelope)` di koma yang tidak berada di dalam kurung
+fn split_stages(spec: &str) -> Result<Vec<(&str, Option<&str>)>, String> {
+    let mut stages = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in spec.char_indices().chain(std::iter::once((spec.len(), ','))) {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1).ok_or_else(|| format!("unbalanced ')' in {:?}", spec))?,
+            ',' if depth == 0 => {
+                let stage = spec[start..i].trim();
+                start = i + 1;
+                if stage.is_empty() {
+                    continue;
+                }
+                let stage = match stage.split_once('(') {
+                    Some((name, arg)) => {
+                        let arg = arg
+                            .strip_suffix(')')
+                            .ok_or_else(|| format!("invalid transform {:?}", stage))?;
+                        (name.trim(), Some(arg.trim()))
+                    }
+                    None => (stage, None),
+                };
+                stages.push(stage);
+            }
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return Err(format!("unbalanced '(' in {:?}", spec));
+    }
+    Ok(stages)
+}
+
+/// Rangkaian transform milik satu subscriber, dijalankan berurutan
+pub struct Pipeline {
+    transforms: Vec<Box<dyn Transform>>,
+}
+
+impl std::fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pipeline").field("stages", &self.transforms.len()).finish()
+    }
+}
+
+impl Pipeline {
+    /// Frame setelah semua stage, atau `None` bila salah satu stage melewatinya
+    pub fn run(&mut self, frame: Frame) -> Option<Frame> {
+        self.transforms
+            .iter_mut()
+            .try_fold(frame, |frame, transform| transform.apply(frame))
+    }
+}
+
+/// `decimate(n)`: hanya setiap frame ke-n yang diteruskan
+struct Decimate {
+    every: u64,
+    seen: u64,
+}
+
+impl Decimate {
+    fn create(arg: Option<&str>) -> Result<Box<dyn Transform>, String> {
+        let every = arg
+            .ok_or("expected decimate(n)")?
+            .parse::<u64>()
+            .ok()
+            .filter(|every| (1..=1000).contains(every))
+            .ok_or("n must be between 1 and 1000")?;
+        Ok(Box::new(Self { every, seen: 0 }))
+    }
+}
+
+impl Transform for Decimate {
+    fn apply(&mut self, frame: Frame) -> Option<Frame> {
+        let keep = self.seen.is_multiple_of(self.every);
+        self.seen += 1;
+        keep.then_some(frame)
+    }
+}
+
+/// `rewrap(format)`: bungkus ulang payload untuk klien yang tidak bisa memilih subprotocol
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rewrap {
+    /// `bsb.envelope.v1` header: u64 seq, u64 producer ms
+    Envelope,
+    /// u32 big-endian payload length
+    Length,
+}
+
+impl Rewrap {
+    fn create(arg: Option<&str>) -> Result<Box<dyn Transform>, String> {
+        match arg {
+            Some("envelope") => Ok(Box::new(Rewrap::Envelope)),
+            Some("length") => Ok(Box::new(Rewrap::Length)),
+            _ => Err("expected rewrap(envelope) or rewrap(length)".to_string()),
+        }
+    }
+}
+
+impl Transform for Rewrap {
+    fn apply(&mut self, mut frame: Frame) -> Option<Frame> {
+        let data = match self {
+            Rewrap::Envelope => Subprotocol::envelope(frame.seq, frame.producer_ms.unwrap_or(0), &frame.data),
+            Rewrap::Length => {
+                let mut buf = Vec::with_capacity(4 + frame.data.len());
+                buf.extend_from_slice(&(frame.data.len() as u32).to_be_bytes());
+                buf.extend_from_slice(&frame.data);
+                buf
+            }
+        };
+        frame.data = Bytes::from(data);
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, testing::TestBroker};
+    use axum::http::StatusCode;
+    use std::time::Instant;
+
+    /// Plugin test: membalik urutan byte payload
+    struct Reverse;
+
+    impl Transform for Reverse {
+        fn apply(&mut self, mut frame: Frame) -> Option<Frame> {
+            frame.data = frame.data.iter().rev().copied().collect::<Vec<u8>>().into();
+            Some(frame)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_resolves_registered_transforms() {
+        let mut registry = TransformRegistry::default();
+        registry.register("reverse", |_| Ok(Box::new(Reverse)));
+        let frame = |seq: u64| Frame {
+            seq,
+            data: Bytes::from_static(b"abc"),
+            received_at: Instant::now(),
+            producer_ms: Some(7),
+        };
+
+        let mut pipeline = registry.build("decimate(2), reverse, rewrap(length)").unwrap();
+        let out: Vec<_> = (1..=4).filter_map(|seq| pipeline.run(frame(seq))).collect();
+        assert_eq!(out.iter().map(|f| f.seq).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(&out[0].data[..], b"\0\0\0\x03cba");
+
+        let mut envelope = registry.build("rewrap(envelope)").unwrap();
+        assert_eq!(envelope.run(frame(9)).unwrap().data.len(), 16 + 3);
+
+        assert!(registry.build("sharpen(3)").unwrap_err().contains("available: decimate, reverse, rewrap"));
+        assert!(registry.build("decimate(0)").is_err());
+        assert!(registry.build("decimate(5").is_err());
+        assert!(registry.build("rewrap(xml)").is_err());
+        assert!(registry.build(&["decimate(1)"; MAX_STAGES + 1].join(",")).is_err());
+
+        // Lewat broker: subscriber dengan pipeline menerima setiap frame kedua dalam envelope
+        let broker = TestBroker::start(Config::default()).await;
+        let mut plain = broker.subscriber("cam1").await;
+        let mut piped = broker.subscriber("cam1?pipeline=decimate(2),rewrap(envelope)").await;
+        for i in 1..=3 {
+            assert_eq!(broker.post_frame("cam1", format!("frame-{}", i)).await, StatusCode::OK);
+        }
+        for i in 1..=3 {
+            assert_eq!(plain.expect_binary().await, format!("frame-{}", i).into_bytes());
+        }
+        for seq in [1u64, 3] {
+            let data = piped.expect_binary().await;
+            assert_eq!(u64::from_be_bytes(data[..8].try_into().unwrap()), seq);
+            assert_eq!(&data[16..], format!("frame-{}", seq).as_bytes());
+        }
+        let rejected = broker.connect_with_headers("/ws/cam1?pipeline=sharpen(3)", &[]).await;
+        assert_eq!(rejected.err(), Some(StatusCode::BAD_REQUEST));
+    }
+}
@@ -0,0 +1,277 @@
+use axum::http::{header, HeaderMap};
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tokio::sync::oneshot;
+use tracing::warn;
+
+use crate::{
+    auth::Credential,
+    error::BrokerError,
+    hooks::{Access, AuthDecision, AuthRequest},
+    AppState,
+};
+
+/// Apa yang terjadi saat token melebihi `PLAYBACK_MAX_SESSIONS`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SessionPolicy {
+    /// Refuse the new upgrade with `429 Too Many Requests`
+    #[default]
+    Reject,
+    /// Accept the new session and close the token's oldest one
+    KickOldest,
+}
+
+impl FromStr for SessionPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "reject" => Ok(SessionPolicy::Reject),
+            "kick_oldest" => Ok(SessionPolicy::KickOldest),
+            other => Err(format!("expected reject or kick_oldest, got {:?}", other)),
+        }
+    }
+}
+
+/// Sesi aktif satu token, terlama di depan, beserta sinyal untuk menutupnya
+type ActiveSessions = VecDeque<(u64, oneshot::Sender<()>)>;
+
+/// Sesi subscriber yang aktif per playback token
+#[derive(Debug, Default)]
+pub struct SessionRegistry {
+    next_id: AtomicU64,
+    sessions: Mutex<HashMap<String, ActiveSessions>>,
+}
+
+impl SessionRegistry {
+    /// Register a session for `key`, applying the limit and policy
+    ///
+    /// Returns `None` when the session is rejected.
+    fn acquire(
+        self: &Arc<Self>,
+        key: &str,
+        max_sessions: usize,
+        policy: SessionPolicy,
+    ) -> Option<Session> {
+        let mut sessions = self.sessions.lock();
+        let active = sessions.entry(key.to_string()).or_default();
+        while active.len() >= max_sessions {
+            if policy == SessionPolicy::Reject {
+                return None;
+            }
+            let Some((_, kick)) = active.pop_front() else {
+                break;
+            };
+            let _ = kick.send(());
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (kick, kicked) = oneshot::channel();
+        active.push_back((id, kick));
+        Some(Session {
+            registry: self.clone(),
+            key: key.to_string(),
+            id,
+            kicked,
+        })
+    }
+
+    /// Token yang punya sesi aktif
+    pub fn tracked(&self) -> usize {
+        self.sessions.lock().len()
+    }
+
+    fn active(&self, key: &str) -> usize {
+        self.sessions.lock().get(key).map_or(0, VecDeque::len)
+    }
+}
+
+/// Satu sesi yang dihitung terhadap kuota token; dilepas saat di-drop
+#[derive(Debug)]
+pub struct Session {
+    registry: Arc<SessionRegistry>,
+    key: String,
+    id: u64,
+    kicked: oneshot::Receiver<()>,
+}
+
+impl Session {
+    /// Resolves when a newer session of the same token displaced this one
+    pub async fn kicked(&mut self) {
+        if (&mut self.kicked).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let mut sessions = self.registry.sessions.lock();
+        if let Some(active) = sessions.get_mut(&self.key) {
+            active.retain(|(id, _)| *id != self.id);
+            if active.is_empty() {
+                sessions.remove(&self.key);
+            }
+        }
+    }
+}
+
+/// Nilai `Authorization` dari header, atau `Bearer <token>` dari `?token=`
+fn authorization(headers: &HeaderMap, token: Option<&str>) -> Option<String> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| token.map(|token| format!("Bearer {}", token)))
+}
+
+/// Playback credential dari `Authorization: Bearer` atau `?token=`
+pub fn credential<'a>(state: &'a AppState, headers: &HeaderMap, token: Option<&str>) -> Option<&'a Credential> {
+    authorization(headers, token).and_then(|a| state.config.playback_credentials.authenticate(&a))
+}
+
+/// Subscriber yang lolos autentikasi
+#[derive(Debug, Default)]
+pub struct Viewer {
+    /// Session to hold for the lifetime of the connection when a limit applies
+    pub session: Option<Session>,
+    /// Scopes of the viewer's token or user, e.g. `metadata:full`
+    pub scopes: Vec<String>,
+}
+
+/// Autentikasi subscriber `/ws/:stream_id` dengan playback token
+///
+/// Without `PLAYBACK_CREDENTIALS`, OIDC or users with streams every
+/// subscriber is accepted. The token is read from `Authorization: Bearer` or,
+/// for browsers, `?token=`; tokens unknown to `PLAYBACK_CREDENTIALS` go to the
+/// identity provider. `Authorization: Basic` logs in a `USERS_FILE` user.
+/// Embedder [`Authenticator`](crate::hooks::Authenticator)s are asked first.
+pub async fn authorize(
+    state: &AppState,
+    stream_id: &str,
+    headers: &HeaderMap,
+    token: Option<&str>,
+) -> Result<Viewer, BrokerError> {
+    let hook = AuthRequest {
+        access: Access::Subscribe,
+        stream_id,
+        headers,
+        token,
+    };
+    match state.hooks.authenticate(hook).await {
+        AuthDecision::Allow(identity) => {
+            return Ok(Viewer {
+                session: acquire_session(state, stream_id, &format!("hook:{}", identity.subject))?,
+                scopes: identity.scopes,
+            })
+        }
+        AuthDecision::Deny(e) => {
+            warn!("Rejected subscriber for stream {}: {}", stream_id, e);
+            return Err(e);
+        }
+        AuthDecision::Pass => {}
+    }
+
+    let oidc = state.oidc.as_ref().filter(|oidc| oidc.config().playback);
+    if state.config.playback_credentials.is_empty() && oidc.is_none() && !state.users.has_viewers() {
+        return Ok(Viewer::default());
+    }
+
+    let (session_key, scopes) = match credential(state, headers, token) {
+        Some(credential) => {
+            if !credential.allows(stream_id) {
+                warn!("Rejected subscriber for stream {}: token not permitted", stream_id);
+                return Err(BrokerError::Forbidden(format!("token is not permitted for stream {}", stream_id)));
+            }
+            (credential.session_key().to_string(), credential.scopes.clone())
+        }
+        None => {
+            let authorization = authorization(headers, token);
+            // User lokal (USERS_FILE) login dengan Basic auth
+            if let Some(basic) = authorization.as_deref().filter(|a| a.starts_with("Basic ")) {
+                let Some((name, user)) = state.users.authenticate(basic).await else {
+                    warn!("Rejected subscriber for stream {}: invalid user credentials", stream_id);
+                    return Err(BrokerError::Unauthorized("invalid user credentials".to_string()));
+                };
+                if !user.can_subscribe(stream_id) {
+                    warn!("Rejected subscriber for stream {}: user {} not permitted", stream_id, name);
+                    return Err(BrokerError::Forbidden(format!("user is not permitted for stream {}", stream_id)));
+                }
+                (format!("user:{}", name), user.scopes)
+            } else {
+                let bearer = authorization.as_deref().and_then(|a| a.strip_prefix("Bearer "));
+                let (Some(oidc), Some(bearer)) = (oidc, bearer) else {
+                    warn!("Rejected subscriber for stream {}: invalid playback token", stream_id);
+                    return Err(BrokerError::Unauthorized("invalid playback token".to_string()));
+                };
+                let grants = oidc.validate(bearer.trim()).await.inspect_err(|e| {
+                    warn!("Rejected subscriber for stream {}: {}", stream_id, e);
+                })?;
+                if !grants.can_subscribe(stream_id) {
+                    warn!("Rejected subscriber for stream {}: {} has no subscribe scope", stream_id, grants.subject);
+                    return Err(BrokerError::Forbidden(format!("token is not permitted for stream {}", stream_id)));
+                }
+                // Kuota sesi per identitas, bukan per token: token baru dari refresh tetap dihitung
+                (format!("oidc:{}", grants.subject), grants.scopes)
+            }
+        }
+    };
+
+    Ok(Viewer {
+        session: acquire_session(state, stream_id, &session_key)?,
+        scopes,
+    })
+}
+
+/// Hitung sesi terhadap kuota `PLAYBACK_MAX_SESSIONS` milik `session_key`
+fn acquire_session(state: &AppState, stream_id: &str, session_key: &str) -> Result<Option<Session>, BrokerError> {
+    let max_sessions = state.config.playback_max_sessions;
+    if max_sessions == 0 {
+        return Ok(None);
+    }
+    let policy = state.config.playback_session_policy;
+    match state.sessions.acquire(session_key, max_sessions, policy) {
+        Some(session) => Ok(Some(session)),
+        None => {
+            warn!(
+                "Rejected subscriber for stream {}: token already has {} sessions",
+                stream_id,
+                state.sessions.active(session_key)
+            );
+            Err(BrokerError::SessionLimit("playback session limit exceeded".to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_session_limit_policies() {
+        let registry = Arc::new(SessionRegistry::default());
+        let first = registry.acquire("tok", 2, SessionPolicy::Reject).unwrap();
+        let mut second = registry.acquire("tok", 2, SessionPolicy::Reject).unwrap();
+        assert!(registry.acquire("tok", 2, SessionPolicy::Reject).is_none());
+        assert!(registry.acquire("other", 2, SessionPolicy::Reject).is_some());
+
+        // Sesi yang ditutup membebaskan kuota
+        drop(first);
+        let mut third = registry.acquire("tok", 2, SessionPolicy::Reject).unwrap();
+
+        // kick_oldest menutup sesi tertua (second), bukan yang baru
+        let _fourth = registry.acquire("tok", 2, SessionPolicy::KickOldest).unwrap();
+        assert_eq!(registry.active("tok"), 2);
+        let wait = std::time::Duration::from_millis(50);
+        assert!(tokio::time::timeout(wait, second.kicked()).await.is_ok());
+        assert!(tokio::time::timeout(wait, third.kicked()).await.is_err());
+        assert_eq!("kick_oldest".parse(), Ok(SessionPolicy::KickOldest));
+    }
+}
@@ -0,0 +1,279 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Json, Response},
+};
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::json;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tracing::warn;
+
+use crate::{
+    config::Config,
+    error::BrokerError,
+    labels::Labels,
+    lifetime::StopReason,
+    mux::glob_match,
+    registry::{StreamEntry, StreamEvent},
+    stats::unix_now,
+    AppState,
+};
+
+/// Label yang menimpa prioritas dari `STREAM_PRIORITIES`
+pub const PRIORITY_LABEL: &str = "priority";
+
+/// Jumlah shed event terakhir yang disimpan untuk GET /api/shed
+const RECENT_EVENTS: usize = 100;
+
+/// Per-stream priorities from `STREAM_PRIORITIES`
+///
+/// Format: `pattern=N,pattern=N`, e.g. `teleop/*=100,cam*=10,test/*=-10`. The
+/// first matching rule wins; streams without a rule have priority 0. Higher
+/// numbers are more important and are shed last.
+#[derive(Debug, Clone, Default)]
+pub struct StreamPriorities {
+    rules: Vec<(String, i32)>,
+}
+
+impl StreamPriorities {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for rule in spec.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            let (pattern, priority) = rule
+                .rsplit_once('=')
+                .ok_or_else(|| format!("Invalid STREAM_PRIORITIES rule: {}", rule))?;
+            let priority = priority
+                .trim()
+                .parse()
+                .map_err(|e| format!("Invalid STREAM_PRIORITIES value in {}: {}", rule, e))?;
+            rules.push((pattern.trim().to_string(), priority));
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn for_stream(&self, stream_id: &str) -> i32 {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, stream_id))
+            .map_or(0, |(_, priority)| *priority)
+    }
+}
+
+/// Prioritas stream: label `priority` (termasuk dari `STATIC_STREAMS_FILE`), lalu `STREAM_PRIORITIES`
+pub fn priority(config: &Config, stream_id: &str, labels: &Labels) -> i32 {
+    labels
+        .get(PRIORITY_LABEL)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| config.stream_priorities.for_stream(stream_id))
+}
+
+/// Batas resource broker yang memicu shedding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShedCause {
+    /// `MAX_SUBSCRIBERS` was reached when a higher-priority subscriber connected
+    Subscribers,
+    /// The DVR buffers of all streams exceeded `MAX_BUFFER_BYTES`
+    Memory,
+}
+
+/// Satu stream yang ditutup karena resource pressure
+#[derive(Debug, Clone, Serialize)]
+pub struct ShedEvent {
+    pub stream: String,
+    pub priority: i32,
+    pub cause: ShedCause,
+    /// Unix seconds
+    pub at: u64,
+    /// Subscribers closed with the stream
+    pub subscribers: usize,
+    /// DVR memory released
+    pub buffer_bytes: usize,
+}
+
+/// Riwayat shedding sejak broker start
+#[derive(Debug, Default)]
+pub struct ShedLog {
+    total: AtomicU64,
+    recent: Mutex<VecDeque<ShedEvent>>,
+}
+
+impl ShedLog {
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, event: ShedEvent) {
+        self.total.fetch_add(1, Ordering::Relaxed);
+        let mut recent = self.recent.lock();
+        if recent.len() == RECENT_EVENTS {
+            recent.pop_front();
+        }
+        recent.push_back(event);
+    }
+}
+
+/// Stream yang boleh di-shed: belum dihentikan, urut dari korban pertama
+///
+/// Lowest priority goes first; among equal priorities the stream using the
+/// most of the exhausted resource is closed, so fewer streams are shed.
+fn victims<'a>(
+    config: &Config,
+    map: &'a HashMap<String, StreamEntry>,
+    usage: impl Fn(&StreamEntry) -> usize,
+) -> Vec<(&'a String, i32, usize)> {
+    let mut victims: Vec<_> = map
+        .iter()
+        .filter(|(_, entry)| entry.lifetime.ended.is_none())
+        .map(|(stream_id, entry)| (stream_id, priority(config, stream_id, &entry.labels), usage(entry)))
+        .filter(|(_, _, usage)| *usage > 0)
+        .collect();
+    victims.sort_by(|a, b| a.1.cmp(&b.1).then(b.2.cmp(&a.2)));
+    victims
+}
+
+/// Tutup stream: producer ditolak dan subscriber diputus sampai lifetime-nya di-clear
+fn shed(state: &AppState, map: &mut HashMap<String, StreamEntry>, stream_id: &str, cause: ShedCause) {
+    let Some(entry) = map.get_mut(stream_id) else {
+        return;
+    };
+    let event = ShedEvent {
+        stream: stream_id.to_string(),
+        priority: priority(&state.config, stream_id, &entry.labels),
+        cause,
+        at: unix_now(),
+        subscribers: entry.tx.receiver_count(),
+        buffer_bytes: entry.dvr.memory_bytes(),
+    };
+    warn!(
+        "Shedding stream {} (priority {}, {:?}): closing {} subscribers, releasing {} buffered bytes",
+        stream_id, event.priority, cause, event.subscribers, event.buffer_bytes
+    );
+    entry.lifetime.ended = Some(StopReason::Shed);
+    entry.dvr.clear();
+    entry.keyframe = None;
+    entry.init_segment = None;
+    let seq = entry.last_seq();
+    entry.emit(StreamEvent::StreamEnded {
+        reason: StopReason::Shed,
+        seq,
+    });
+    state.shed.record(event);
+}
+
+/// Shed stream prioritas terendah sampai total DVR memori di bawah `MAX_BUFFER_BYTES`
+///
+/// Called by the stats sampler every second with the registry lock held.
+pub fn enforce_memory(state: &AppState, map: &mut HashMap<String, StreamEntry>) {
+    let limit = state.config.max_buffer_bytes;
+    if limit == 0 {
+        return;
+    }
+    let mut total: usize = map
+        .values()
+        .filter(|entry| entry.lifetime.ended.is_none())
+        .map(|entry| entry.dvr.memory_bytes())
+        .sum();
+    if total <= limit {
+        return;
+    }
+    let victims: Vec<_> = victims(&state.config, map, |entry| entry.dvr.memory_bytes())
+        .into_iter()
+        .map(|(stream_id, _, bytes)| (stream_id.clone(), bytes))
+        .collect();
+    for (stream_id, bytes) in victims {
+        if total <= limit {
+            break;
+        }
+        shed(state, map, &stream_id, ShedCause::Memory);
+        total -= bytes;
+    }
+}
+
+/// Admission subscriber baru di bawah `MAX_SUBSCRIBERS`
+///
+/// When the broker is full, the lowest-priority stream below `stream_id`'s
+/// priority is shed to make room; without one the subscriber is refused.
+pub fn admit_subscriber(state: &AppState, stream_id: &str) -> Result<(), BrokerError> {
+    let limit = state.config.max_subscribers;
+    if limit == 0 {
+        return Ok(());
+    }
+    let mut map = state.streams.lock();
+    let total: usize = map.values().map(|entry| entry.tx.receiver_count()).sum();
+    if total < limit {
+        return Ok(());
+    }
+    let own = match map.get(stream_id) {
+        Some(entry) => priority(&state.config, stream_id, &entry.labels),
+        None => priority(&state.config, stream_id, &Labels::new()),
+    };
+    let victim = victims(&state.config, &map, |entry| entry.tx.receiver_count())
+        .into_iter()
+        .find(|(victim, priority, _)| *priority < own && victim.as_str() != stream_id)
+        .map(|(victim, _, _)| victim.clone());
+    match victim {
+        Some(victim) => {
+            shed(state, &mut map, &victim, ShedCause::Subscribers);
+            Ok(())
+        }
+        None => Err(BrokerError::Unavailable(format!(
+            "subscriber limit reached ({}) and no lower-priority stream to shed",
+            limit
+        ))),
+    }
+}
+
+/// Handler untuk GET /api/shed
+/// Streams closed under resource pressure, newest last
+pub async fn list_shed_handler(State(state): State<AppState>) -> Response {
+    let events: Vec<ShedEvent> = state.shed.recent.lock().iter().cloned().collect();
+    Json(json!({
+        "total": state.shed.total(),
+        "max_subscribers": state.config.max_subscribers,
+        "max_buffer_bytes": state.config.max_buffer_bytes,
+        "events": events,
+    }))
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lowest_priority_stream_is_shed_first() {
+        let priorities = StreamPriorities::parse("teleop/*=100, test/*=-10").unwrap();
+        assert_eq!(priorities.for_stream("teleop/arm"), 100);
+        assert_eq!(priorities.for_stream("cam1"), 0);
+        assert!(StreamPriorities::parse("cam1").is_err());
+
+        let config = Config {
+            stream_priorities: priorities,
+            max_buffer_bytes: 250,
+            ..Config::default()
+        };
+        let state = AppState::new(config);
+        for (stream_id, frames) in [("teleop/arm", 1), ("cam1", 1), ("test/a", 1), ("test/b", 2)] {
+            state.with_stream(stream_id, |entry| {
+                for _ in 0..frames {
+                    let _ = entry.publish(vec![0; 100].into());
+                }
+            });
+        }
+        // Label `priority` menimpa STREAM_PRIORITIES
+        state.with_stream("cam1", |entry| entry.labels.insert(PRIORITY_LABEL.into(), "200".into()));
+
+        // 500 byte > 250: test/b (prioritas -10, paling besar) lalu test/a di-shed
+        enforce_memory(&state, &mut state.streams.lock());
+        let shed: Vec<_> = state.shed.recent.lock().iter().map(|e| e.stream.clone()).collect();
+        assert_eq!(shed, ["test/b", "test/a"]);
+        let map = state.streams.lock();
+        assert_eq!(map["test/b"].lifetime.ended, Some(StopReason::Shed));
+        assert_eq!(map["test/b"].dvr.memory_bytes(), 0);
+        assert_eq!(map["cam1"].lifetime.ended, None);
+    }
+}
@@ -0,0 +1,240 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Path as AxumPath, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use futures_util::{stream::SplitSink, SinkExt};
+use http_body_util::BodyExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tracing::{debug, info, warn};
+
+use crate::{error::BrokerError, outbound::HttpTarget, ws::Subprotocol, AppState};
+
+/// Body untuk PUT /api/streams/:id/preroll (juga `preroll` di `STATIC_STREAMS_FILE`)
+///
+/// Exactly one of `data` and `hook_url` is set.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PrerollConfig {
+    /// Payload sent to every new subscriber, base64
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    /// `http://` endpoint asked for the payload on every new subscriber
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hook_url: Option<String>,
+    /// Subscribers wait at most this long for the hook before going live without pre-roll
+    #[serde(default = "default_hook_timeout_ms")]
+    pub hook_timeout_ms: u64,
+}
+
+fn default_hook_timeout_ms() -> u64 {
+    1000
+}
+
+impl PrerollConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.data, &self.hook_url) {
+            (Some(data), None) => {
+                let payload = STANDARD.decode(data).map_err(|e| format!("invalid base64 data: {}", e))?;
+                if payload.is_empty() {
+                    return Err("pre-roll data is empty".to_string());
+                }
+            }
+            (None, Some(url)) => {
+                HttpTarget::parse(url)?;
+            }
+            _ => return Err("exactly one of data or hook_url is required".to_string()),
+        }
+        if !(1..=10_000).contains(&self.hook_timeout_ms) {
+            return Err("hook_timeout_ms must be between 1 and 10000".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Pre-roll siap pakai: payload yang sudah di-decode, atau hook
+#[derive(Debug)]
+pub struct Preroll {
+    config: PrerollConfig,
+    payload: Option<Bytes>,
+}
+
+impl Preroll {
+    pub fn compile(config: PrerollConfig) -> Result<Self, String> {
+        config.validate()?;
+        let payload = match &config.data {
+            Some(data) => Some(Bytes::from(STANDARD.decode(data).map_err(|e| e.to_string())?)),
+            None => None,
+        };
+        Ok(Self { config, payload })
+    }
+}
+
+/// Pre-roll aktif per stream
+#[derive(Debug, Default)]
+pub struct Prerolls {
+    active: Mutex<HashMap<String, Arc<Preroll>>>,
+}
+
+impl Prerolls {
+    pub fn set(&self, stream_id: &str, preroll: Preroll) {
+        self.active
+            .lock()
+            .insert(stream_id.to_string(), Arc::new(preroll));
+    }
+
+    fn get(&self, stream_id: &str) -> Option<Arc<Preroll>> {
+        self.active.lock().get(stream_id).cloned()
+    }
+}
+
+/// Payload pre-roll untuk satu subscriber baru, `None` bila tidak ada (atau hook gagal)
+///
+/// A failing or slow hook never keeps a viewer from the live stream: the
+/// subscriber simply starts without pre-roll.
+pub async fn fetch(state: &AppState, stream_id: &str) -> Option<Bytes> {
+    let preroll = state.prerolls.get(stream_id)?;
+    if let Some(payload) = &preroll.payload {
+        return Some(payload.clone());
+    }
+    let url = preroll.config.hook_url.as_deref()?;
+    let timeout = Duration::from_millis(preroll.config.hook_timeout_ms);
+    match tokio::time::timeout(timeout, call_hook(url, stream_id)).await {
+        Ok(Ok(payload)) => payload,
+        Ok(Err(e)) => {
+            warn!("Pre-roll hook for stream {} failed: {}", stream_id, e);
+            None
+        }
+        Err(_) => {
+            warn!("Pre-roll hook for stream {} timed out after {:?}", stream_id, timeout);
+            None
+        }
+    }
+}
+
+/// GET hook dengan header `X-Stream-Id`; body `200` adalah payload, `204` berarti tanpa pre-roll
+async fn call_hook(url: &str, stream_id: &str) -> Result<Option<Bytes>, String> {
+    let mut target = HttpTarget::parse(url)?;
+    let response = target.get(&[("x-stream-id", stream_id.to_string())]).await?;
+    let status = response.status();
+    if status == StatusCode::NO_CONTENT {
+        debug!("Pre-roll hook skipped stream {}", stream_id);
+        return Ok(None);
+    }
+    if !status.is_success() {
+        return Err(format!("hook answered {}", status));
+    }
+    let body = response.into_body().collect().await.map_err(|e| e.to_string())?.to_bytes();
+    Ok((!body.is_empty()).then_some(body))
+}
+
+/// Kirim pre-roll sebelum frame live
+///
+/// The payload has no sequence number: sequence-mode clients get a
+/// `{"type":"preroll"}` notice first, envelope clients see it as seq 0.
+pub async fn send(
+    sender: &mut SplitSink<WebSocket, Message>,
+    payload: Bytes,
+    protocol: Subprotocol,
+    seq_mode: bool,
+) -> Result<(), axum::Error> {
+    if protocol == Subprotocol::Envelope {
+        return sender.send(Message::Binary(Subprotocol::envelope(0, 0, &payload))).await;
+    }
+    if seq_mode {
+        let notice = json!({ "type": "preroll", "bytes": payload.len() });
+        sender.send(Message::Text(notice.to_string())).await?;
+    }
+    sender.send(Message::Binary(payload.to_vec())).await
+}
+
+/// Handler untuk GET /api/streams/:id/preroll
+pub async fn get_preroll_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Response {
+    match state.prerolls.get(&stream_id) {
+        Some(preroll) => Json(json!({ "stream": stream_id, "preroll": preroll.config })).into_response(),
+        None => BrokerError::not_found("preroll", stream_id).into_response(),
+    }
+}
+
+/// Handler untuk PUT /api/streams/:id/preroll
+pub async fn put_preroll_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+    Json(config): Json<PrerollConfig>,
+) -> Response {
+    let preroll = match Preroll::compile(config) {
+        Ok(preroll) => preroll,
+        Err(message) => return BrokerError::InvalidRequest(message).into_response(),
+    };
+    info!("Pre-roll set for stream {}", stream_id);
+    let body = json!({ "stream": stream_id, "preroll": preroll.config });
+    state.prerolls.set(&stream_id, preroll);
+    Json(body).into_response()
+}
+
+/// Handler untuk DELETE /api/streams/:id/preroll
+pub async fn delete_preroll_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Result<StatusCode, BrokerError> {
+    if state.prerolls.active.lock().remove(&stream_id).is_some() {
+        info!("Pre-roll removed from stream {}", stream_id);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(BrokerError::not_found("preroll", stream_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, testing::TestBroker};
+    use axum::{routing::get, Router};
+
+    #[tokio::test]
+    async fn test_preroll_before_live_frames() {
+        let broker = TestBroker::start(Config::default()).await;
+        let config: PrerollConfig = serde_json::from_value(json!({ "data": STANDARD.encode("slide") })).unwrap();
+        broker.state.prerolls.set("cam1", Preroll::compile(config).unwrap());
+
+        let mut viewer = broker.subscriber("cam1?seq=true").await;
+        assert_eq!(viewer.expect_json().await["type"], "preroll");
+        assert_eq!(viewer.expect_binary().await, b"slide");
+        assert_eq!(viewer.expect_json().await["type"], "sync");
+        // Reconnect tidak melihat pre-roll lagi
+        let mut resumed = broker.subscriber("cam1?resume_from=0").await;
+        assert_eq!(resumed.expect_json().await["type"], "sync");
+        assert_eq!(broker.post_frame("cam1", "frame-1").await, StatusCode::OK);
+        assert_eq!(viewer.expect_binary().await, b"frame-1");
+        assert_eq!(resumed.expect_binary().await, b"frame-1");
+
+        // Hook: payload per subscriber, dengan ID stream di header
+        let hook = Router::new().route(
+            "/preroll",
+            get(|headers: axum::http::HeaderMap| async move {
+                let stream = headers["x-stream-id"].to_str().unwrap().to_string();
+                format!("card for {}", stream)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let hook_url = format!("http://{}/preroll", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, hook).await });
+        let config: PrerollConfig = serde_json::from_value(json!({ "hook_url": hook_url })).unwrap();
+        broker.state.prerolls.set("cam2", Preroll::compile(config).unwrap());
+        let mut viewer = broker.subscriber("cam2").await;
+        assert_eq!(viewer.expect_binary().await, b"card for cam2");
+
+        let invalid: PrerollConfig = serde_json::from_value(json!({ "hook_url": "https://x/" })).unwrap();
+        assert!(invalid.validate().is_err());
+    }
+}
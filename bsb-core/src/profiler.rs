@@ -0,0 +1,757 @@
+use axum::{
+    extract::Query,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    time::Duration,
+};
+use tracing::info;
+
+use crate::error::BrokerError;
+
+/// Frekuensi sampling (bukan 100 supaya tidak sefase dengan timer lain)
+const SAMPLE_HZ: u64 = 99;
+
+/// Profil terpanjang yang boleh diminta
+const MAX_SECONDS: u64 = 60;
+
+/// Frame terdalam yang dicatat per sample
+const MAX_DEPTH: usize = 128;
+
+/// Batas sample per profil (~33 MB buffer); kelebihannya dihitung sebagai `dropped`
+const MAX_SAMPLES: usize = 32_000;
+
+/// Query parameter untuk GET /debug/pprof/profile
+#[derive(Debug, Deserialize)]
+pub struct ProfileParams {
+    /// Sampling duration (default 10, max 60)
+    pub seconds: Option<u64>,
+    /// `pprof` (protobuf, default) or `folded` (flamegraph.pl / inferno input)
+    pub format: Option<String>,
+}
+
+/// Hasil sampling: stack (leaf dulu) dan jumlah kemunculannya
+#[derive(Debug, Default)]
+pub struct Profile {
+    pub stacks: HashMap<Vec<usize>, u64>,
+    pub dropped: u64,
+    pub duration: Duration,
+    /// Unix nanoseconds when sampling started (pprof `time_nanos`)
+    pub time_nanos: u64,
+}
+
+/// Handler untuk GET /debug/pprof/profile
+/// Sample the broker's CPU usage for `seconds` and return the profile
+pub async fn profile_handler(Query(params): Query<ProfileParams>) -> Response {
+    let seconds = params.seconds.unwrap_or(10);
+    if !(1..=MAX_SECONDS).contains(&seconds) {
+        let error = format!("seconds must be between 1 and {}", MAX_SECONDS);
+        return BrokerError::InvalidRequest(error).into_response();
+    }
+    let folded = match params.format.as_deref() {
+        None | Some("pprof") => false,
+        Some("folded") => true,
+        Some(other) => {
+            let error = format!("unknown format {:?} (use pprof or folded)", other);
+            return BrokerError::InvalidRequest(error).into_response();
+        }
+    };
+
+    let duration = Duration::from_secs(seconds);
+    let session = match sampler::start(duration) {
+        Ok(session) => session,
+        Err(e) => return e.into_response(),
+    };
+    info!("CPU profiling for {}s", seconds);
+    tokio::time::sleep(duration).await;
+    let profile = session.finish();
+    info!(
+        "CPU profile finished: {} samples, {} dropped",
+        profile.stacks.values().sum::<u64>(),
+        profile.dropped
+    );
+
+    // Membaca symbol table dari binary bisa butuh puluhan MB I/O
+    let body = tokio::task::spawn_blocking(move || {
+        let symbols = symbols::Symbolizer::load();
+        if folded {
+            render_folded(&profile, &symbols).into_bytes()
+        } else {
+            pprof::encode(&profile, &symbols)
+        }
+    })
+    .await;
+    match body {
+        Ok(body) if folded => ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], body).into_response(),
+        Ok(body) => (
+            [
+                (header::CONTENT_TYPE, "application/octet-stream"),
+                (header::CONTENT_DISPOSITION, "attachment; filename=\"profile.pb\""),
+            ],
+            body,
+        )
+            .into_response(),
+        Err(e) => BrokerError::Internal(format!("cannot render profile: {}", e)).into_response(),
+    }
+}
+
+/// Satu baris per stack unik: `root;...;leaf count`, terbanyak dulu
+fn render_folded(profile: &Profile, symbols: &symbols::Symbolizer) -> String {
+    // Alamat berbeda di fungsi yang sama digabung menjadi satu baris
+    let mut folded: HashMap<String, u64> = HashMap::new();
+    for (stack, count) in &profile.stacks {
+        let frames: Vec<String> = stack.iter().rev().map(|&addr| symbols.name(addr)).collect();
+        *folded.entry(frames.join(";")).or_default() += count;
+    }
+    let mut stacks: Vec<_> = folded.into_iter().collect();
+    stacks.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    stacks
+        .into_iter()
+        .map(|(stack, count)| format!("{} {}\n", stack, count))
+        .collect()
+}
+
+/// SIGPROF sampler: `ITIMER_PROF` membangunkan thread yang sedang memakai CPU
+///
+/// The signal handler only copies the interrupted PC and the frame-pointer
+/// chain into a preallocated buffer; memory is read with `process_vm_readv`
+/// so a stale or missing frame pointer ends the walk instead of faulting.
+/// Frames compiled without frame pointers (parts of std) truncate the stack.
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod sampler {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering},
+            Once,
+        },
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    };
+
+    use super::{Profile, MAX_DEPTH, MAX_SAMPLES, SAMPLE_HZ};
+    use crate::error::BrokerError;
+
+    #[repr(C)]
+    struct Slot {
+        depth: usize,
+        frames: [usize; MAX_DEPTH],
+    }
+
+    /// Hanya satu profil pada satu waktu (timer bersifat per proses)
+    static RUNNING: AtomicBool = AtomicBool::new(false);
+    static SLOTS: AtomicPtr<Slot> = AtomicPtr::new(std::ptr::null_mut());
+    static CAPACITY: AtomicUsize = AtomicUsize::new(0);
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    /// Signal handler yang sedang menulis ke buffer
+    static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+    static INSTALL: Once = Once::new();
+
+    /// Profil yang sedang berjalan; timer dimatikan saat di-drop (klien putus di tengah jalan)
+    pub struct Session {
+        slots: Option<Vec<Slot>>,
+        started: Instant,
+        started_at: SystemTime,
+    }
+
+    pub fn start(duration: Duration) -> Result<Session, BrokerError> {
+        if RUNNING.swap(true, Ordering::AcqRel) {
+            return Err(BrokerError::Conflict {
+                message: "another CPU profile is already running".to_string(),
+                details: None,
+            });
+        }
+        INSTALL.call_once(install_handler);
+
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let capacity = (duration.as_secs() as usize * SAMPLE_HZ as usize * threads).min(MAX_SAMPLES);
+        let mut slots: Vec<Slot> = (0..capacity)
+            .map(|_| Slot {
+                depth: 0,
+                frames: [0; MAX_DEPTH],
+            })
+            .collect();
+        NEXT.store(0, Ordering::Relaxed);
+        CAPACITY.store(capacity, Ordering::Relaxed);
+        SLOTS.store(slots.as_mut_ptr(), Ordering::Release);
+        set_timer(Duration::from_micros(1_000_000 / SAMPLE_HZ));
+        Ok(Session {
+            slots: Some(slots),
+            started: Instant::now(),
+            started_at: SystemTime::now(),
+        })
+    }
+
+    impl Session {
+        pub fn finish(mut self) -> Profile {
+            let slots = self.stop();
+            let taken = NEXT.load(Ordering::Acquire);
+            let mut profile = Profile {
+                dropped: taken.saturating_sub(slots.len()) as u64,
+                duration: self.started.elapsed(),
+                time_nanos: self.started_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64,
+                ..Profile::default()
+            };
+            for slot in slots.iter().take(taken) {
+                let stack = slot.frames[..slot.depth.min(MAX_DEPTH)].to_vec();
+                if !stack.is_empty() {
+                    *profile.stacks.entry(stack).or_default() += 1;
+                }
+            }
+            RUNNING.store(false, Ordering::Release);
+            profile
+        }
+
+        fn stop(&mut self) -> Vec<Slot> {
+            set_timer(Duration::ZERO);
+            SLOTS.store(std::ptr::null_mut(), Ordering::Release);
+            // Tunggu handler yang masih menulis ke buffer lama
+            while ACTIVE.load(Ordering::Acquire) > 0 {
+                std::hint::spin_loop();
+            }
+            self.slots.take().unwrap_or_default()
+        }
+    }
+
+    impl Drop for Session {
+        fn drop(&mut self) {
+            if self.slots.is_some() {
+                self.stop();
+                RUNNING.store(false, Ordering::Release);
+            }
+        }
+    }
+
+    fn set_timer(interval: Duration) {
+        let tv = libc::timeval {
+            tv_sec: interval.as_secs() as libc::time_t,
+            tv_usec: interval.subsec_micros() as libc::suseconds_t,
+        };
+        let timer = libc::itimerval {
+            it_interval: tv,
+            it_value: tv,
+        };
+        // SAFETY: `timer` is a valid itimerval; the old value is not requested
+        unsafe { libc::setitimer(libc::ITIMER_PROF, &timer, std::ptr::null_mut()) };
+    }
+
+    /// Handler dipasang sekali dan tidak pernah dilepas: SIGPROF yang telat tidak boleh
+    /// memakai aksi default (terminate)
+    fn install_handler() {
+        // SAFETY: sigaction is initialised field by field before use
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_sigprof as *const () as usize;
+            action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(libc::SIGPROF, &action, std::ptr::null_mut());
+        }
+    }
+
+    extern "C" fn on_sigprof(_signal: libc::c_int, _info: *mut libc::siginfo_t, context: *mut libc::c_void) {
+        ACTIVE.fetch_add(1, Ordering::AcqRel);
+        let slots = SLOTS.load(Ordering::Acquire);
+        if !slots.is_null() && !context.is_null() {
+            let index = NEXT.fetch_add(1, Ordering::AcqRel);
+            if index < CAPACITY.load(Ordering::Relaxed) {
+                // SAFETY: index < capacity of the live buffer, and the buffer is only
+                // freed after ACTIVE drops to zero
+                let slot = unsafe { &mut *slots.add(index) };
+                // SAFETY: the kernel passes a ucontext_t as the third argument with SA_SIGINFO
+                let (pc, fp) = unsafe { registers(&*(context as *const libc::ucontext_t)) };
+                slot.depth = walk(pc, fp, &mut slot.frames);
+            }
+        }
+        ACTIVE.fetch_sub(1, Ordering::AcqRel);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn registers(context: &libc::ucontext_t) -> (usize, usize) {
+        let gregs = &context.uc_mcontext.gregs;
+        (gregs[libc::REG_RIP as usize] as usize, gregs[libc::REG_RBP as usize] as usize)
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    fn registers(context: &libc::ucontext_t) -> (usize, usize) {
+        let mcontext = &context.uc_mcontext;
+        (mcontext.pc as usize, mcontext.regs[29] as usize)
+    }
+
+    /// Ikuti rantai frame pointer: `[fp]` = fp pemanggil, `[fp + 8]` = return address
+    fn walk(pc: usize, mut fp: usize, frames: &mut [usize; MAX_DEPTH]) -> usize {
+        frames[0] = pc;
+        let mut depth = 1;
+        while depth < MAX_DEPTH && fp != 0 && fp.is_multiple_of(std::mem::size_of::<usize>()) {
+            let mut record = [0usize; 2];
+            if !read_memory(fp, &mut record) {
+                break;
+            }
+            let [next_fp, return_address] = record;
+            if return_address == 0 {
+                break;
+            }
+            // Alamat return menunjuk ke instruksi setelah call; -1 supaya masuk fungsi pemanggil
+            frames[depth] = return_address - 1;
+            depth += 1;
+            // Stack tumbuh ke bawah: frame pemanggil selalu di alamat lebih tinggi
+            if next_fp <= fp || next_fp - fp > 8 << 20 {
+                break;
+            }
+            fp = next_fp;
+        }
+        depth
+    }
+
+    /// Baca memori sendiri tanpa risiko SIGSEGV (async-signal-safe)
+    fn read_memory(address: usize, out: &mut [usize; 2]) -> bool {
+        let size = std::mem::size_of_val(out);
+        let local = libc::iovec {
+            iov_base: out.as_mut_ptr() as *mut libc::c_void,
+            iov_len: size,
+        };
+        let remote = libc::iovec {
+            iov_base: address as *mut libc::c_void,
+            iov_len: size,
+        };
+        // SAFETY: both iovecs describe `size` bytes; the kernel validates the remote range
+        let read = unsafe { libc::process_vm_readv(libc::getpid(), &local, 1, &remote, 1, 0) };
+        read == size as isize
+    }
+}
+
+#[cfg(not(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+mod sampler {
+    use std::time::Duration;
+
+    use super::Profile;
+    use crate::error::BrokerError;
+
+    pub struct Session;
+
+    pub fn start(_duration: Duration) -> Result<Session, BrokerError> {
+        Err(BrokerError::Unavailable(
+            "CPU profiling is only supported on Linux x86_64/aarch64".to_string(),
+        ))
+    }
+
+    impl Session {
+        pub fn finish(self) -> Profile {
+            Profile::default()
+        }
+    }
+}
+
+/// Alamat ke nama fungsi dari `.symtab` binary sendiri (tanpa debuginfo)
+mod symbols {
+    use std::{fs, path::PathBuf};
+
+    /// Satu region executable dari `/proc/self/maps`
+    #[derive(Debug, Clone)]
+    pub struct Mapping {
+        pub start: usize,
+        pub end: usize,
+        pub offset: u64,
+        pub path: String,
+    }
+
+    #[derive(Debug, Default)]
+    pub struct Symbolizer {
+        pub mappings: Vec<Mapping>,
+        /// (runtime address, size, demangled name), sorted by address
+        functions: Vec<(usize, usize, String)>,
+    }
+
+    impl Symbolizer {
+        pub fn load() -> Self {
+            let mappings = fs::read_to_string("/proc/self/maps")
+                .map(|maps| parse_maps(&maps))
+                .unwrap_or_default();
+            let exe = fs::read_link("/proc/self/exe").unwrap_or_else(|_| PathBuf::from("/proc/self/exe"));
+            let exe_name = exe.to_string_lossy().into_owned();
+            let text = mappings.iter().find(|mapping| mapping.path == exe_name);
+            let mut functions = match (fs::read(&exe), text) {
+                (Ok(elf), Some(text)) => elf_functions(&elf, text).unwrap_or_default(),
+                _ => Vec::new(),
+            };
+            functions.sort_by_key(|(address, _, _)| *address);
+            Self { mappings, functions }
+        }
+
+        pub fn function(&self, address: usize) -> Option<&str> {
+            let index = self.functions.partition_point(|(start, _, _)| *start <= address);
+            let (start, size, name) = self.functions.get(index.checked_sub(1)?)?;
+            (address < start + size).then_some(name.as_str())
+        }
+
+        pub fn mapping(&self, address: usize) -> Option<(usize, &Mapping)> {
+            self.mappings
+                .iter()
+                .enumerate()
+                .find(|(_, mapping)| (mapping.start..mapping.end).contains(&address))
+        }
+
+        /// Nama untuk output folded: fungsi, `[libc.so.6]` atau alamat mentah
+        pub fn name(&self, address: usize) -> String {
+            if let Some(name) = self.function(address) {
+                return name.to_string();
+            }
+            match self.mapping(address) {
+                Some((_, mapping)) => {
+                    let file = mapping.path.rsplit('/').next().unwrap_or(&mapping.path);
+                    format!("[{}]", file)
+                }
+                None => format!("{:#x}", address),
+            }
+        }
+    }
+
+    fn parse_maps(maps: &str) -> Vec<Mapping> {
+        maps.lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let (start, end) = fields.next()?.split_once('-')?;
+                let perms = fields.next()?;
+                let offset = fields.next()?;
+                let path = fields.nth(2)?;
+                if !perms.contains('x') || !path.starts_with('/') {
+                    return None;
+                }
+                Some(Mapping {
+                    start: usize::from_str_radix(start, 16).ok()?,
+                    end: usize::from_str_radix(end, 16).ok()?,
+                    offset: u64::from_str_radix(offset, 16).ok()?,
+                    path: path.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    fn u16_at(data: &[u8], at: usize) -> Option<u16> {
+        Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+    }
+
+    fn u32_at(data: &[u8], at: usize) -> Option<u32> {
+        Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+    }
+
+    fn u64_at(data: &[u8], at: usize) -> Option<u64> {
+        Some(u64::from_le_bytes(data.get(at..at + 8)?.try_into().ok()?))
+    }
+
+    /// Fungsi (`STT_FUNC`) dari `.symtab` ELF64 little-endian, digeser ke alamat runtime
+    fn elf_functions(elf: &[u8], text: &Mapping) -> Option<Vec<(usize, usize, String)>> {
+        const ET_DYN: u16 = 3;
+        const PT_LOAD: u32 = 1;
+        const SHT_SYMTAB: u32 = 2;
+        const STT_FUNC: u8 = 2;
+        const PAGE: u64 = 4096;
+        if elf.get(..4)? != b"\x7fELF" || elf[4] != 2 || elf[5] != 1 {
+            return None;
+        }
+        // PIE: geser symbol sebesar selisih alamat load segmen executable dan p_vaddr-nya;
+        // executable non-PIE sudah memakai alamat absolut
+        let bias = if u16_at(elf, 16)? == ET_DYN {
+            let header_offset = u64_at(elf, 0x20)? as usize;
+            let header_size = u16_at(elf, 0x36)? as usize;
+            let header_count = u16_at(elf, 0x38)? as usize;
+            let segment = (0..header_count).map(|i| header_offset + i * header_size).find(|&at| {
+                u32_at(elf, at) == Some(PT_LOAD)
+                    && u64_at(elf, at + 8).map(|offset| offset & !(PAGE - 1)) == Some(text.offset)
+            })?;
+            let vaddr = u64_at(elf, segment + 0x10)? & !(PAGE - 1);
+            text.start.checked_sub(vaddr as usize)?
+        } else {
+            0
+        };
+        let section_offset = u64_at(elf, 0x28)? as usize;
+        let section_size = u16_at(elf, 0x3a)? as usize;
+        let section_count = u16_at(elf, 0x3c)? as usize;
+        let section = |index: usize| section_offset + index * section_size;
+
+        let symtab = (0..section_count).find(|&i| u32_at(elf, section(i) + 4) == Some(SHT_SYMTAB))?;
+        let offset = u64_at(elf, section(symtab) + 0x18)? as usize;
+        let size = u64_at(elf, section(symtab) + 0x20)? as usize;
+        let strtab = u32_at(elf, section(symtab) + 0x28)? as usize;
+        let entry_size = (u64_at(elf, section(symtab) + 0x38)? as usize).max(24);
+        let strings_offset = u64_at(elf, section(strtab) + 0x18)? as usize;
+
+        let mut functions = Vec::new();
+        for at in (offset..offset + size).step_by(entry_size) {
+            let info = *elf.get(at + 4)?;
+            let value = u64_at(elf, at + 8)? as usize;
+            let size = u64_at(elf, at + 16)? as usize;
+            // Symbol tanpa ukuran (`_init`, `_fini`) tidak bisa dipakai untuk lookup rentang
+            if info & 0xf != STT_FUNC || value == 0 || size == 0 {
+                continue;
+            }
+            let name_at = strings_offset + u32_at(elf, at)? as usize;
+            let name_len = elf.get(name_at..)?.iter().position(|&b| b == 0)?;
+            let name = String::from_utf8_lossy(&elf[name_at..name_at + name_len]);
+            functions.push((value + bias, size, demangle(&name)));
+        }
+        Some(functions)
+    }
+
+    /// Demangle nama Rust skema legacy (`_ZN...17h<hash>E`); nama lain apa adanya
+    pub fn demangle(symbol: &str) -> String {
+        let Some(mut rest) = symbol.strip_prefix("_ZN") else {
+            return symbol.to_string();
+        };
+        let mut path = Vec::new();
+        while !rest.starts_with('E') {
+            let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+            let Ok(len) = rest[..digits].parse::<usize>() else {
+                return symbol.to_string();
+            };
+            let Some(ident) = rest.get(digits..digits + len) else {
+                return symbol.to_string();
+            };
+            path.push(ident);
+            rest = &rest[digits + len..];
+        }
+        // Segmen terakhir `h` + 16 hex adalah hash, bukan bagian nama
+        if path.last().is_some_and(|last| {
+            last.len() == 17 && last.starts_with('h') && last[1..].bytes().all(|b| b.is_ascii_hexdigit())
+        }) {
+            path.pop();
+        }
+        path.iter().map(|ident| unescape(ident)).collect::<Vec<_>>().join("::")
+    }
+
+    fn unescape(ident: &str) -> String {
+        // `_` ditambahkan compiler di depan identifier yang diawali escape `$`
+        let ident = if ident.starts_with("_$") { &ident[1..] } else { ident };
+        let mut out = String::new();
+        let mut rest = ident;
+        while !rest.is_empty() {
+            if let Some(tail) = rest.strip_prefix("..") {
+                out.push_str("::");
+                rest = tail;
+            } else if rest.starts_with('$') {
+                let Some(end) = rest[1..].find('$') else {
+                    out.push_str(rest);
+                    break;
+                };
+                let code = &rest[1..end + 1];
+                let decoded = match code {
+                    "SP" => Some('@'),
+                    "BP" => Some('*'),
+                    "RF" => Some('&'),
+                    "LT" => Some('<'),
+                    "GT" => Some('>'),
+                    "LP" => Some('('),
+                    "RP" => Some(')'),
+                    "C" => Some(','),
+                    _ => code
+                        .strip_prefix('u')
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                        .and_then(char::from_u32),
+                };
+                match decoded {
+                    Some(c) => out.push(c),
+                    None => out.push_str(&rest[..end + 2]),
+                }
+                rest = &rest[end + 2..];
+            } else {
+                let c = rest.chars().next().unwrap_or_default();
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        out
+    }
+}
+
+/// Encoder protobuf `perftools.profiles.Profile` (dibaca `go tool pprof`, tanpa gzip)
+mod pprof {
+    use std::collections::HashMap;
+
+    use super::{symbols::Symbolizer, Profile, SAMPLE_HZ};
+
+    #[derive(Default)]
+    struct Message(Vec<u8>);
+
+    impl Message {
+        fn varint(&mut self, mut value: u64) {
+            while value >= 0x80 {
+                self.0.push(value as u8 | 0x80);
+                value >>= 7;
+            }
+            self.0.push(value as u8);
+        }
+
+        fn uint(&mut self, field: u32, value: u64) {
+            if value != 0 {
+                self.varint(u64::from(field) << 3);
+                self.varint(value);
+            }
+        }
+
+        fn bytes(&mut self, field: u32, value: &[u8]) {
+            self.varint(u64::from(field) << 3 | 2);
+            self.varint(value.len() as u64);
+            self.0.extend_from_slice(value);
+        }
+
+        fn message(&mut self, field: u32, message: Message) {
+            self.bytes(field, &message.0);
+        }
+
+        fn packed(&mut self, field: u32, values: impl IntoIterator<Item = u64>) {
+            let mut packed = Message::default();
+            for value in values {
+                packed.varint(value);
+            }
+            self.bytes(field, &packed.0);
+        }
+    }
+
+    /// String table pprof: indeks 0 selalu string kosong
+    #[derive(Default)]
+    struct Strings {
+        table: Vec<String>,
+        index: HashMap<String, u64>,
+    }
+
+    impl Strings {
+        fn id(&mut self, value: &str) -> u64 {
+            if self.table.is_empty() {
+                self.table.push(String::new());
+                self.index.insert(String::new(), 0);
+            }
+            if let Some(&id) = self.index.get(value) {
+                return id;
+            }
+            let id = self.table.len() as u64;
+            self.table.push(value.to_string());
+            self.index.insert(value.to_string(), id);
+            id
+        }
+    }
+
+    fn value_type(strings: &mut Strings, kind: &str, unit: &str) -> Message {
+        let mut message = Message::default();
+        message.uint(1, strings.id(kind));
+        message.uint(2, strings.id(unit));
+        message
+    }
+
+    pub fn encode(profile: &Profile, symbols: &Symbolizer) -> Vec<u8> {
+        let period = 1_000_000_000 / SAMPLE_HZ;
+        let mut strings = Strings::default();
+        let mut out = Message::default();
+        let mut locations: HashMap<usize, u64> = HashMap::new();
+        let mut functions: HashMap<String, u64> = HashMap::new();
+        let mut location_messages = Vec::new();
+        let mut function_messages = Vec::new();
+
+        out.message(1, value_type(&mut strings, "samples", "count"));
+        out.message(1, value_type(&mut strings, "cpu", "nanoseconds"));
+        for (stack, &count) in &profile.stacks {
+            let mut ids = Vec::with_capacity(stack.len());
+            for &address in stack {
+                let next_id = locations.len() as u64 + 1;
+                let id = *locations.entry(address).or_insert_with(|| {
+                    let mut location = Message::default();
+                    location.uint(1, next_id);
+                    if let Some((index, _)) = symbols.mapping(address) {
+                        location.uint(2, index as u64 + 1);
+                    }
+                    location.uint(3, address as u64);
+                    if let Some(name) = symbols.function(address) {
+                        let next_function = functions.len() as u64 + 1;
+                        let function_id = *functions.entry(name.to_string()).or_insert_with(|| {
+                            let mut function = Message::default();
+                            function.uint(1, next_function);
+                            function.uint(2, strings.id(name));
+                            function.uint(3, strings.id(name));
+                            function_messages.push(function);
+                            next_function
+                        });
+                        let mut line = Message::default();
+                        line.uint(1, function_id);
+                        location.message(4, line);
+                    }
+                    location_messages.push(location);
+                    next_id
+                });
+                ids.push(id);
+            }
+            let mut sample = Message::default();
+            sample.packed(1, ids);
+            sample.packed(2, [count, count * period]);
+            out.message(2, sample);
+        }
+        for (index, mapping) in symbols.mappings.iter().enumerate() {
+            let mut message = Message::default();
+            message.uint(1, index as u64 + 1);
+            message.uint(2, mapping.start as u64);
+            message.uint(3, mapping.end as u64);
+            message.uint(4, mapping.offset);
+            message.uint(5, strings.id(&mapping.path));
+            message.uint(7, 1);
+            out.message(3, message);
+        }
+        for location in location_messages {
+            out.message(4, location);
+        }
+        for function in function_messages {
+            out.message(5, function);
+        }
+        let period_type = value_type(&mut strings, "cpu", "nanoseconds");
+        for value in std::mem::take(&mut strings.table) {
+            out.bytes(6, value.as_bytes());
+        }
+        out.uint(9, profile.time_nanos);
+        out.uint(10, profile.duration.as_nanos() as u64);
+        out.message(11, period_type);
+        out.uint(12, period);
+        out.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_demangle_and_protobuf_encoding() {
+        assert_eq!(
+            symbols::demangle("_ZN13ingest_server8registry11StreamEntry7publish17h0123456789abcdefE"),
+            "ingest_server::registry::StreamEntry::publish"
+        );
+        assert_eq!(
+            symbols::demangle("_ZN4core3ptr46drop_in_place$LT$alloc..vec..Vec$LT$u8$GT$$GT$17h0123456789abcdefE"),
+            "core::ptr::drop_in_place<alloc::vec::Vec<u8>>"
+        );
+        assert_eq!(symbols::demangle("memcpy"), "memcpy");
+
+        let profile = Profile {
+            stacks: HashMap::from([(vec![0x10, 0x20], 3)]),
+            duration: Duration::from_secs(1),
+            ..Profile::default()
+        };
+        let encoded = pprof::encode(&profile, &symbols::Symbolizer::default());
+        // sample_type pertama: field 1, panjang 4, type=1 ("samples"), unit=2 ("count")
+        assert_eq!(&encoded[..6], &[0x0a, 0x04, 0x08, 0x01, 0x10, 0x02]);
+        assert_eq!(render_folded(&profile, &symbols::Symbolizer::default()), "0x20;0x10 3\n");
+    }
+
+    #[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+    #[test]
+    fn test_sampler_captures_busy_thread() {
+        let session = sampler::start(Duration::from_secs(1)).unwrap();
+        assert!(sampler::start(Duration::from_secs(1)).is_err());
+        let start = std::time::Instant::now();
+        let mut x = 0u64;
+        while start.elapsed() < Duration::from_millis(300) {
+            x = std::hint::black_box(x.wrapping_mul(31).wrapping_add(7));
+        }
+        let profile = session.finish();
+        assert!(profile.stacks.values().sum::<u64>() > 0);
+        let symbols = symbols::Symbolizer::load();
+        let leaves = profile.stacks.keys().filter(|stack| symbols.function(stack[0]).is_some()).count();
+        assert!(leaves > 0);
+    }
+}
@@ -0,0 +1,210 @@
+use axum::{
+    extract::{Path as AxumPath, Query, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::{config, error::BrokerError, playback, registry::Frame, AppState};
+
+/// Batas atas `?max=` dan `?wait=` supaya satu request tidak menahan resource terlalu lama
+const MAX_BATCH: usize = 1000;
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+const MULTIPART_BOUNDARY: &str = "bsb-frame-3f9c1e7a";
+
+/// Format body untuk batch frame
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchFormat {
+    /// `[u64 seq][u32 len][payload]` per frame, big-endian
+    #[default]
+    LengthPrefixed,
+    /// `multipart/mixed`, one part per frame with an `X-Seq` header
+    Multipart,
+}
+
+/// Query parameter untuk GET /api/streams/:id/frames
+#[derive(Debug, Deserialize)]
+pub struct PullParams {
+    /// First sequence number wanted; defaults to the next live frame
+    from_seq: Option<u64>,
+    #[serde(default = "default_max")]
+    max: usize,
+    /// How long to wait for a frame when none is available yet (`5s`, `500ms`)
+    wait: Option<String>,
+    #[serde(default)]
+    format: BatchFormat,
+    /// Playback token for clients that cannot set `Authorization`
+    token: Option<String>,
+}
+
+fn default_max() -> usize {
+    100
+}
+
+impl BatchFormat {
+    pub fn encode(self, frames: &[Frame]) -> (String, Vec<u8>) {
+        let mut body = Vec::new();
+        match self {
+            BatchFormat::LengthPrefixed => {
+                for frame in frames {
+                    body.extend_from_slice(&frame.seq.to_be_bytes());
+                    body.extend_from_slice(&(frame.data.len() as u32).to_be_bytes());
+                    body.extend_from_slice(&frame.data);
+                }
+                ("application/octet-stream".to_string(), body)
+            }
+            BatchFormat::Multipart => {
+                for frame in frames {
+                    let part_header = format!(
+                        "--{}\r\nContent-Type: application/octet-stream\r\nX-Seq: {}\r\nContent-Length: {}\r\n\r\n",
+                        MULTIPART_BOUNDARY,
+                        frame.seq,
+                        frame.data.len()
+                    );
+                    body.extend_from_slice(part_header.as_bytes());
+                    body.extend_from_slice(&frame.data);
+                    body.extend_from_slice(b"\r\n");
+                }
+                body.extend_from_slice(format!("--{}--\r\n", MULTIPART_BOUNDARY).as_bytes());
+                (format!("multipart/mixed; boundary={}", MULTIPART_BOUNDARY), body)
+            }
+        }
+    }
+}
+
+/// Handler untuk GET /api/streams/:id/frames?from_seq=&max=&wait=
+/// Pull a batch of frames from the DVR buffer, long-polling up to `wait` when none is available
+///
+/// `X-Next-Seq` is the `from_seq` for the next request. When older frames were
+/// already evicted, the batch starts later than requested (see `X-First-Seq`).
+/// Access is checked like `/ws/:stream_id`; the session counts against
+/// `PLAYBACK_MAX_SESSIONS` for as long as the request long-polls.
+pub async fn pull_frames_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    Query(params): Query<PullParams>,
+    State(state): State<AppState>,
+    request_headers: HeaderMap,
+) -> Response {
+    // Viewer menahan sesi playback sampai response selesai dibangun
+    let _viewer = match playback::authorize(&state, &stream_id, &request_headers, params.token.as_deref()).await {
+        Ok(viewer) => viewer,
+        Err(e) => return e.into_response(),
+    };
+    let wait = match params.wait.as_deref().map(config::parse_duration).transpose() {
+        Ok(wait) => wait.unwrap_or_default().min(MAX_WAIT),
+        Err(e) => return BrokerError::InvalidRequest(e.to_string()).into_response(),
+    };
+    let max = params.max.clamp(1, MAX_BATCH);
+
+    // Seperti subscriber WebSocket, consumer membuat stream supaya producer mulai diterima.
+    // Subscribe dan baca DVR di bawah lock yang sama supaya frame baru tidak terlewat
+    let (mut rx, after, mut frames) = state.with_stream(&stream_id, |entry| {
+        let after = params.from_seq.map_or(entry.last_seq(), |seq| seq.saturating_sub(1));
+        (entry.tx.subscribe(), after, entry.dvr.since(after))
+    });
+
+    if frames.is_empty() && !wait.is_zero() {
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Ok(frame)) if frame.seq > after => {
+                    // Ambil dari DVR supaya frame yang tiba bersamaan ikut dalam batch
+                    frames = state
+                        .streams
+                        .lock()
+                        .get(&stream_id)
+                        .map(|entry| entry.dvr.since(after))
+                        .unwrap_or_default();
+                    if frames.is_empty() {
+                        // DVR dimatikan (DVR_BUFFER_FRAMES=0)
+                        frames.push(frame);
+                    }
+                    break;
+                }
+                Ok(Ok(_)) | Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+                Ok(Err(broadcast::error::RecvError::Closed)) | Err(_) => break,
+            }
+        }
+    }
+
+    frames.truncate(max);
+    let next_seq = frames.last().map_or(after + 1, |frame| frame.seq + 1);
+    if frames.is_empty() {
+        return (
+            StatusCode::NO_CONTENT,
+            [(HeaderName::from_static("x-next-seq"), next_seq.to_string())],
+        )
+            .into_response();
+    }
+    let first_seq = frames[0].seq;
+    let (content_type, body) = params.format.encode(&frames);
+    (
+        [
+            (header::CONTENT_TYPE, content_type),
+            (HeaderName::from_static("x-first-seq"), first_seq.to_string()),
+            (HeaderName::from_static("x-next-seq"), next_seq.to_string()),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use std::time::Instant;
+
+    #[test]
+    fn test_batch_encoding() {
+        let frames: Vec<Frame> = [(7u64, "ab"), (8, "xyz")]
+            .iter()
+            .map(|(seq, data)| Frame {
+                seq: *seq,
+                data: Bytes::from(*data),
+                received_at: Instant::now(),
+                producer_ms: None,
+            })
+            .collect();
+
+        let (content_type, body) = BatchFormat::LengthPrefixed.encode(&frames);
+        assert_eq!(content_type, "application/octet-stream");
+        assert_eq!(&body[..8], &7u64.to_be_bytes());
+        assert_eq!(&body[8..12], &2u32.to_be_bytes());
+        assert_eq!(&body[12..14], b"ab");
+        assert_eq!(body.len(), 2 * 12 + 5);
+
+        let (content_type, body) = BatchFormat::Multipart.encode(&frames);
+        assert!(content_type.starts_with("multipart/mixed; boundary="));
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.contains("X-Seq: 8\r\nContent-Length: 3\r\n\r\nxyz\r\n"));
+        assert!(body.ends_with("--\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_pull_requires_playback_token() {
+        use crate::{auth::CredentialStore, config::Config};
+        use axum::{body::Body, http::Request};
+        use tower::util::ServiceExt;
+
+        let state = AppState::new(Config {
+            playback_credentials: CredentialStore::parse("guest=cam*", false).unwrap(),
+            ..Config::default()
+        });
+        let get = |uri: &str| {
+            let request = Request::get(uri).body(Body::empty()).unwrap();
+            crate::build_router(state.clone()).oneshot(request)
+        };
+        assert_eq!(get("/api/streams/cam1/frames").await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let other = get("/api/streams/lobby/frames?token=guest").await.unwrap();
+        assert_eq!(other.status(), StatusCode::FORBIDDEN);
+        let allowed = get("/api/streams/cam1/frames?token=guest").await.unwrap();
+        assert_eq!(allowed.status(), StatusCode::NO_CONTENT);
+        // Request yang ditolak tidak membuat stream
+        assert!(!state.streams.lock().contains_key("lobby"));
+    }
+}
@@ -0,0 +1,204 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::atomic::Ordering;
+
+use crate::{
+    connections::{Connection, ConnectionKind},
+    error::BrokerError,
+    stats::{unix_now_ms, StreamCounters},
+    AppState,
+};
+
+/// Laporan lebih rapat dari ini per subscriber diabaikan
+const MIN_REPORT_INTERVAL_MS: u64 = 1000;
+/// Viewer yang tidak melapor selama ini tidak ikut dirata-rata
+pub const STALE_AFTER_MS: u64 = 30_000;
+
+/// Pesan statistik dari subscriber: `{"type":"stats","rtt_ms":42,"decoded_fps":24,"dropped":3}`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StatsMessage {
+    /// Optional; `"stats"` when present
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    rtt_ms: Option<f64>,
+    decoded_fps: Option<f64>,
+    dropped: Option<u64>,
+}
+
+/// Statistik yang dilaporkan player, field yang tidak dikirim tetap `None`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClientStats {
+    pub rtt_ms: Option<f64>,
+    pub decoded_fps: Option<f64>,
+    /// Frames the player dropped since it started (cumulative)
+    pub dropped: Option<u64>,
+}
+
+/// Parse pesan statistik; `None` untuk pesan lain (mis. ack) atau nilai di luar batas
+pub fn parse(text: &str) -> Option<ClientStats> {
+    let message: StatsMessage = serde_json::from_str(text).ok()?;
+    if message.kind.as_deref().is_some_and(|kind| kind != "stats") {
+        return None;
+    }
+    let in_range = |value: Option<f64>, max: f64| value.is_none_or(|value| (0.0..=max).contains(&value));
+    if !in_range(message.rtt_ms, 600_000.0) || !in_range(message.decoded_fps, 1000.0) {
+        return None;
+    }
+    let stats = ClientStats {
+        rtt_ms: message.rtt_ms,
+        decoded_fps: message.decoded_fps,
+        dropped: message.dropped,
+    };
+    (stats != ClientStats::default()).then_some(stats)
+}
+
+/// Laporan terakhir satu subscriber
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ViewerStats {
+    pub rtt_ms: Option<f64>,
+    pub decoded_fps: Option<f64>,
+    pub dropped: u64,
+    pub reported_ms: u64,
+}
+
+/// Simpan laporan subscriber; `false` bila datang terlalu cepat setelah laporan sebelumnya
+///
+/// `dropped` is cumulative on the player, so only its increase is added to the
+/// stream's total; a smaller value means the player restarted its count.
+pub fn record(connection: &Connection, counters: &StreamCounters, stats: ClientStats) -> bool {
+    let now = unix_now_ms();
+    let mut latest = connection.qoe.lock();
+    if latest.is_some_and(|previous| now < previous.reported_ms + MIN_REPORT_INTERVAL_MS) {
+        return false;
+    }
+    let previous = latest.unwrap_or(ViewerStats {
+        rtt_ms: None,
+        decoded_fps: None,
+        dropped: 0,
+        reported_ms: 0,
+    });
+    if let Some(dropped) = stats.dropped {
+        let added = dropped.checked_sub(previous.dropped).unwrap_or(dropped);
+        counters.viewer_dropped.fetch_add(added, Ordering::Relaxed);
+    }
+    *latest = Some(ViewerStats {
+        rtt_ms: stats.rtt_ms.or(previous.rtt_ms),
+        decoded_fps: stats.decoded_fps.or(previous.decoded_fps),
+        dropped: stats.dropped.unwrap_or(previous.dropped),
+        reported_ms: now,
+    });
+    true
+}
+
+/// Ringkasan quality-of-experience dari laporan viewer yang masih baru
+pub fn summarize(reports: &[ViewerStats], now_ms: u64) -> serde_json::Value {
+    let fresh: Vec<_> = reports
+        .iter()
+        .filter(|report| report.reported_ms + STALE_AFTER_MS >= now_ms)
+        .collect();
+    let mut rtts: Vec<f64> = fresh.iter().filter_map(|report| report.rtt_ms).collect();
+    rtts.sort_by(f64::total_cmp);
+    let fps: Vec<f64> = fresh.iter().filter_map(|report| report.decoded_fps).collect();
+    let average = |values: &[f64]| (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64);
+    // p95 dengan nearest-rank
+    let p95 = (!rtts.is_empty()).then(|| rtts[((rtts.len() as f64 * 0.95).ceil() as usize).max(1) - 1]);
+    json!({
+        "viewers_reporting": fresh.len(),
+        "rtt_ms": {
+            "avg": average(&rtts),
+            "p95": p95,
+            "max": rtts.last(),
+        },
+        "decoded_fps": {
+            "avg": average(&fps),
+            "min": fps.iter().copied().reduce(f64::min),
+        },
+    })
+}
+
+/// Handler untuk GET /api/streams/:id/qoe
+/// What viewers report about their playback: round trip, decoded frame rate and drops
+pub async fn qoe_handler(AxumPath(stream_id): AxumPath<String>, State(state): State<AppState>) -> Response {
+    let map = state.streams.lock();
+    let Some(entry) = map.get(&stream_id) else {
+        return BrokerError::StreamNotFound(stream_id).into_response();
+    };
+    let viewers = entry.connections.reports(ConnectionKind::Subscriber);
+    let reports: Vec<_> = viewers.iter().map(|(_, report)| *report).collect();
+    let mut body = summarize(&reports, unix_now_ms());
+    body["stream"] = json!(stream_id);
+    body["subscribers"] = json!(entry.tx.receiver_count());
+    body["dropped_frames"] = json!(entry.counters.viewer_dropped.load(Ordering::Relaxed));
+    body["viewers"] = viewers
+        .iter()
+        .map(|(id, report)| {
+            let mut viewer = json!(report);
+            viewer["id"] = json!(id);
+            viewer
+        })
+        .collect();
+    Json(body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, testing::TestBroker};
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_stats_messages() {
+        let stats = parse(r#"{"rtt_ms":42,"decoded_fps":24,"dropped":3}"#).unwrap();
+        assert_eq!((stats.rtt_ms, stats.decoded_fps, stats.dropped), (Some(42.0), Some(24.0), Some(3)));
+        assert_eq!(parse(r#"{"type":"stats","rtt_ms":10}"#).unwrap().rtt_ms, Some(10.0));
+        assert_eq!(parse(r#"{"type":"ack","seq":5}"#), None);
+        assert_eq!(parse(r#"{"rtt_ms":-1}"#), None);
+        assert_eq!(parse("{}"), None);
+    }
+
+    #[tokio::test]
+    async fn test_viewer_reports_aggregate_per_stream() {
+        let broker = TestBroker::start(Config::default()).await;
+        let mut viewers = Vec::new();
+        for (rtt, fps, dropped) in [(20, 30, 2), (100, 12, 5)] {
+            let mut viewer = broker.subscriber("cam1").await;
+            viewer
+                .send_text(&format!(r#"{{"rtt_ms":{},"decoded_fps":{},"dropped":{}}}"#, rtt, fps, dropped))
+                .await;
+            viewers.push(viewer);
+        }
+        let qoe = || async {
+            let response = qoe_handler(AxumPath("cam1".to_string()), State(broker.state.clone())).await;
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+        let mut body = qoe().await;
+        for _ in 0..100 {
+            if body["viewers_reporting"] == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            body = qoe().await;
+        }
+        assert_eq!(body["viewers_reporting"], 2);
+        assert_eq!(body["rtt_ms"], json!({ "avg": 60.0, "p95": 100.0, "max": 100.0 }));
+        assert_eq!(body["decoded_fps"], json!({ "avg": 21.0, "min": 12.0 }));
+        assert_eq!(body["dropped_frames"], 7);
+
+        // Laporan berikutnya menambah selisih `dropped`, bukan nilai kumulatifnya
+        let state = broker.state.clone();
+        let (connection, counters) = state.with_stream("cam1", |entry| {
+            (entry.connections.clone().register(ConnectionKind::Subscriber, "raw", Vec::new()), entry.counters.clone())
+        });
+        assert!(record(&connection, &counters, ClientStats { dropped: Some(4), ..ClientStats::default() }));
+        assert!(!record(&connection, &counters, ClientStats { dropped: Some(9), ..ClientStats::default() }));
+        connection.qoe.lock().as_mut().unwrap().reported_ms -= MIN_REPORT_INTERVAL_MS;
+        assert!(record(&connection, &counters, ClientStats { dropped: Some(9), ..ClientStats::default() }));
+        assert_eq!(counters.viewer_dropped.load(Ordering::Relaxed), 7 + 9);
+    }
+}
@@ -0,0 +1,181 @@
+use std::{collections::VecDeque, fs, future::Future, time::{Duration, Instant}};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::{
+    annotations::{self, Annotation, AnnotationWriter},
+    registry::Frame,
+    segment::{recover_dir, stream_dir, Segment},
+    spill,
+    stats::unix_now_ms,
+    timeline::{self, TimelineEvent},
+    AppState,
+};
+
+/// Cari journal yang tertinggal di semua direktori stream dan pulihkan segmennya
+pub fn recover_all(root: &str) {
+    let Ok(streams) = fs::read_dir(root) else {
+        return;
+    };
+    for stream_dir in streams.flatten().map(|entry| entry.path()) {
+        recover_dir(&stream_dir);
+    }
+}
+
+/// Rekam semua frame stream ke segmen di disk, diputar setiap `RECORDING_SEGMENT_SECS`
+///
+/// JSON objects sent on the stream's data channel are archived next to the
+/// segment being written, stamped with the newest recorded frame.
+pub async fn run_recorder(state: AppState, stream_id: String) {
+    record_until(state, stream_id, Duration::ZERO, std::future::pending()).await
+}
+
+/// Seperti [`run_recorder`], tapi berhenti saat `stop` selesai (rekaman dipicu gerakan/trigger)
+///
+/// Recording starts with the frames of the last `pre_roll` still in the DVR
+/// buffer (memory and spill), taken under the same lock as the live
+/// subscription so none is missed or written twice. The open segment is
+/// closed normally, so a stopped recording leaves no journal behind.
+pub async fn record_until(state: AppState, stream_id: String, pre_roll: Duration, stop: impl Future<Output = ()>) {
+    tokio::pin!(stop);
+    let dir = stream_dir(&state.config.recordings_dir, &stream_id);
+    let segment_duration = Duration::from_secs(state.config.recording_segment_secs);
+    let mut sync_interval =
+        tokio::time::interval(Duration::from_millis(state.config.recording_sync_ms.max(1)));
+    let cutoff = Instant::now().checked_sub(pre_roll);
+    let (mut frames, mut messages, mut last_seq, spilled, buffered) = state.with_stream(&stream_id, |entry| {
+        entry.timeline.record(entry.last_seq(), TimelineEvent::RecordingStarted);
+        let (spilled, buffered) = match cutoff.filter(|_| !pre_roll.is_zero()) {
+            Some(cutoff) => {
+                let spilled = entry.dvr.spilled_since(0).into_iter().filter(|frame| frame.received_at() >= cutoff);
+                let buffered = entry.dvr.since(0).into_iter().filter(|frame| frame.received_at >= cutoff);
+                (spilled.collect(), buffered.collect())
+            }
+            None => (Vec::new(), Vec::new()),
+        };
+        (entry.tx.subscribe(), entry.data.subscribe(), entry.last_seq(), spilled, buffered)
+    });
+    let mut backlog: VecDeque<Frame> = match crate::runtime::spawn_disk_blocking(move || spill::read(&spilled)).await {
+        Ok(spilled) => spilled.into_iter().chain(buffered).collect(),
+        Err(e) => {
+            error!("Cannot read DVR spill of {} for pre-roll: {}", stream_id, e);
+            buffered.into()
+        }
+    };
+    let mut segment: Option<Segment> = None;
+    let mut annotations = AnnotationWriter::new(&dir);
+    info!("Recording stream {} to {} ({} pre-roll frames)", stream_id, dir.display(), backlog.len());
+    let mut paused = false;
+
+    loop {
+        let frame: Frame = if let Some(frame) = backlog.pop_front() {
+            frame
+        } else {
+            tokio::select! {
+                result = frames.recv() => match result {
+                    Ok(frame) => frame,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Recorder for {} fell behind, {} frames not recorded", stream_id, skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                result = messages.recv() => {
+                    match result {
+                        Ok(message) => {
+                            if let Some(data) = annotations::parse(&message.text) {
+                                let annotation = Annotation { at_ms: unix_now_ms(), seq: last_seq, data };
+                                let segment_ms = segment.as_ref().map(|current| current.meta.start_ms);
+                                if let Err(e) = annotations.append(segment_ms, &annotation).await {
+                                    error!("Failed to write annotation for {}: {}", stream_id, e);
+                                }
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("Recorder for {} fell behind, {} annotations not recorded", stream_id, skipped);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                    continue;
+                }
+                _ = &mut stop => break,
+                _ = sync_interval.tick() => {
+                    if let Err(e) = annotations.sync().await {
+                        error!("Failed to sync annotations for {}: {}", stream_id, e);
+                    }
+                    if let Some(current) = segment.as_mut() {
+                        if let Err(e) = current.sync().await {
+                            error!("Failed to sync recording for {}: {}", stream_id, e);
+                        }
+                        state.recordings.upsert(&stream_id, &current.meta, true);
+                    }
+                    continue;
+                }
+            }
+        };
+        // Frame pre-roll ditulis dengan waktu terimanya, bukan waktu penulisan
+        let frame_ms = unix_now_ms().saturating_sub(frame.received_at.elapsed().as_millis() as u64);
+        last_seq = frame.seq;
+        // Disk hampir penuh (`STORAGE_PAUSE_RECORDING`): segmen ditutup, frame dilewati sampai pulih
+        if state.storage.recording_paused() {
+            if !paused {
+                paused = true;
+                warn!("Recording of {} paused, disk usage is critical", stream_id);
+                if let Some(current) = segment.take() {
+                    match current.close().await {
+                        Ok(meta) => state.recordings.upsert(&stream_id, &meta, false),
+                        Err(e) => error!("Failed to close recording segment for {}: {}", stream_id, e),
+                    }
+                }
+                timeline::record(&state, &stream_id, TimelineEvent::RecordingPaused);
+            }
+            continue;
+        }
+        if std::mem::take(&mut paused) {
+            info!("Recording of {} resumed", stream_id);
+            timeline::record(&state, &stream_id, TimelineEvent::RecordingResumed);
+        }
+
+        if segment
+            .as_ref()
+            .is_some_and(|current| current.opened_at.elapsed() >= segment_duration)
+        {
+            match segment.take().expect("checked above").close().await {
+                Ok(meta) => state.recordings.upsert(&stream_id, &meta, false),
+                Err(e) => error!("Failed to close recording segment for {}: {}", stream_id, e),
+            }
+        }
+        if segment.is_none() {
+            match Segment::open(&dir, frame_ms).await {
+                Ok(opened) => {
+                    state.recordings.upsert(&stream_id, &opened.meta, true);
+                    segment = Some(opened);
+                }
+                Err(e) => {
+                    error!("Cannot open recording segment for {}: {}", stream_id, e);
+                    timeline::record(&state, &stream_id, TimelineEvent::RecordingFailed { error: e.to_string() });
+                    continue;
+                }
+            }
+        }
+
+        if let Some(current) = segment.as_mut() {
+            if let Err(e) = current.append(&frame, frame_ms).await {
+                // Journal tetap di disk; segmen dipulihkan saat startup berikutnya
+                error!("Failed to write recording for {}: {}", stream_id, e);
+                timeline::record(&state, &stream_id, TimelineEvent::RecordingFailed { error: e.to_string() });
+                segment = None;
+            }
+        }
+    }
+
+    if let Some(current) = segment {
+        if let Ok(meta) = current.close().await {
+            state.recordings.upsert(&stream_id, &meta, false);
+        }
+    }
+    if let Err(e) = annotations.sync().await {
+        error!("Failed to sync annotations for {}: {}", stream_id, e);
+    }
+    timeline::record(&state, &stream_id, TimelineEvent::RecordingStopped);
+}
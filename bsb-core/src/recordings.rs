@@ -0,0 +1,239 @@
+use axum::{
+    extract::{Path as AxumPath, Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Json, Response},
+};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+};
+
+use crate::{
+    byterange,
+    error::BrokerError,
+    segment::{self, load_segments, IndexedSegment, SegmentMeta, SEGMENT_EXTENSION},
+    runtime,
+    stats::unix_now_ms,
+    AppState,
+};
+
+/// Jam yang dipakai untuk mencari segmen
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchClock {
+    /// Waktu frame diterima broker
+    #[default]
+    Wall,
+    /// Timestamp capture dari producer (`X-Producer-Timestamp`); segmen tanpa timestamp dilewati
+    Producer,
+}
+
+impl IndexedSegment {
+    /// Rentang waktu segmen `[start, end]` menurut `clock`
+    fn span(&self, clock: SearchClock, now_ms: u64) -> Option<(u64, u64)> {
+        match clock {
+            SearchClock::Wall if self.in_progress => Some((self.meta.start_ms, now_ms)),
+            SearchClock::Wall => Some((self.meta.start_ms, self.meta.end_ms)),
+            SearchClock::Producer => Some((self.meta.producer_start_ms?, self.meta.producer_end_ms?)),
+        }
+    }
+}
+
+/// Index segmen per stream, urut waktu mulai
+///
+/// A stream's segments are read from disk on its first search; afterwards
+/// the recorder keeps the entry current (open, every sync tick, close).
+#[derive(Debug, Default)]
+pub struct RecordingIndex {
+    streams: Mutex<HashMap<String, BTreeMap<u64, IndexedSegment>>>,
+}
+
+impl RecordingIndex {
+    /// Catat segmen dari recorder; diabaikan jika stream belum pernah dimuat
+    pub fn upsert(&self, stream_id: &str, meta: &SegmentMeta, in_progress: bool) {
+        if let Some(segments) = self.streams.lock().get_mut(stream_id) {
+            let segment = IndexedSegment {
+                meta: meta.clone(),
+                in_progress,
+            };
+            segments.insert(meta.start_ms, segment);
+        }
+    }
+
+    /// Segmen yang beririsan dengan `[from_ms, to_ms]` (inklusif), urut waktu
+    ///
+    /// Blocks on disk I/O the first time a stream is searched.
+    pub fn search(
+        &self,
+        root: &str,
+        stream_id: &str,
+        from_ms: u64,
+        to_ms: u64,
+        clock: SearchClock,
+    ) -> io::Result<Vec<IndexedSegment>> {
+        if !self.streams.lock().contains_key(stream_id) {
+            let loaded = load_segments(&segment::stream_dir(root, stream_id))?;
+            let loaded = loaded.into_iter().map(|segment| (segment.meta.start_ms, segment)).collect();
+            // Update dari recorder selama pemuatan lebih baru daripada isi disk
+            self.streams
+                .lock()
+                .entry(stream_id.to_string())
+                .or_insert(loaded);
+        }
+
+        let now_ms = unix_now_ms();
+        let streams = self.streams.lock();
+        let segments = streams.get(stream_id).map(|segments| {
+            let candidates = match clock {
+                // Segmen yang mulai setelah `to_ms` tidak mungkin beririsan
+                SearchClock::Wall => segments.range(..=to_ms),
+                SearchClock::Producer => segments.range(..),
+            };
+            candidates
+                .map(|(_, segment)| segment)
+                .filter(|segment| {
+                    segment
+                        .span(clock, now_ms)
+                        .is_some_and(|(start, end)| start <= to_ms && end >= from_ms)
+                })
+                .cloned()
+                .collect()
+        });
+        Ok(segments.unwrap_or_default())
+    }
+}
+
+/// Query untuk GET /api/recordings/:stream_id
+#[derive(Debug, Deserialize)]
+pub struct SearchParams {
+    /// Unix ms, inklusif
+    from: Option<u64>,
+    /// Unix ms, inklusif
+    to: Option<u64>,
+    #[serde(default)]
+    clock: SearchClock,
+}
+
+/// Handler untuk GET /api/recordings/:stream_id?from=&to=&clock=wall|producer
+pub async fn list_recordings_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    Query(params): Query<SearchParams>,
+    State(state): State<AppState>,
+) -> Response {
+    let from_ms = params.from.unwrap_or(0);
+    let to_ms = params.to.unwrap_or(u64::MAX);
+    if from_ms > to_ms {
+        return BrokerError::InvalidRequest("from must not be after to".into()).into_response();
+    }
+
+    let search_state = state.clone();
+    let search_stream = stream_id.clone();
+    let result = runtime::spawn_disk_blocking(move || {
+        search_state.recordings.search(
+            &search_state.config.recordings_dir,
+            &search_stream,
+            from_ms,
+            to_ms,
+            params.clock,
+        )
+    })
+    .await;
+    match result {
+        Ok(Ok(segments)) => Json(json!({
+            "stream": stream_id,
+            "segments": segments,
+        }))
+        .into_response(),
+        Ok(Err(e)) if e.kind() == io::ErrorKind::NotFound => {
+            BrokerError::not_found("recordings", stream_id).into_response()
+        }
+        Ok(Err(e)) => BrokerError::from(e).into_response(),
+        Err(e) => BrokerError::from(e).into_response(),
+    }
+}
+
+/// Handler untuk GET /api/recordings/:stream_id/segments/:segment
+/// Download one raw `.bsbrec` segment as listed by the search
+///
+/// Supports `Range` so players can seek and interrupted downloads resume.
+/// A segment still being written is served up to its current length.
+pub async fn download_segment_handler(
+    AxumPath((stream_id, name)): AxumPath<(String, String)>,
+    State(state): State<AppState>,
+    request_headers: HeaderMap,
+) -> Response {
+    // Hanya nama `<start_ms>.bsbrec`, supaya path tidak bisa keluar dari direktori stream
+    let valid = name
+        .strip_suffix(&format!(".{}", SEGMENT_EXTENSION))
+        .is_some_and(|start| !start.is_empty() && start.bytes().all(|b| b.is_ascii_digit()));
+    if !valid {
+        return BrokerError::not_found("segment", name).into_response();
+    }
+    let path = segment::stream_dir(&state.config.recordings_dir, &stream_id).join(&name);
+    match tokio::fs::File::open(&path).await {
+        Ok(file) => byterange::serve_file(file, "application/octet-stream", &name, &request_headers)
+            .await
+            .unwrap_or_else(IntoResponse::into_response),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => BrokerError::not_found("segment", name).into_response(),
+        Err(e) => BrokerError::from(e).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segment::SEGMENT_EXTENSION;
+    use std::fs;
+
+    fn meta(start_ms: u64, end_ms: u64, producer: Option<(u64, u64)>) -> SegmentMeta {
+        SegmentMeta {
+            segment: format!("{}.{}", start_ms, SEGMENT_EXTENSION),
+            start_ms,
+            end_ms,
+            producer_start_ms: producer.map(|(start, _)| start),
+            producer_end_ms: producer.map(|(_, end)| end),
+            ..SegmentMeta::default()
+        }
+    }
+
+    #[test]
+    fn test_search_returns_covering_segments() {
+        let root = std::env::temp_dir().join(format!("bsb-index-test-{}", std::process::id()));
+        let dir = segment::stream_dir(root.to_str().unwrap(), "cam1");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("1000.json"),
+            serde_json::to_vec(&meta(1000, 1999, Some((500, 1499)))).unwrap(),
+        )
+        .unwrap();
+        fs::write(dir.join("3000.wal"), b"").unwrap();
+
+        let index = RecordingIndex::default();
+        let root = root.to_str().unwrap();
+        let search = |from, to, clock| -> Vec<u64> {
+            index
+                .search(root, "cam1", from, to, clock)
+                .unwrap()
+                .iter()
+                .map(|segment| segment.meta.start_ms)
+                .collect()
+        };
+        assert_eq!(search(1500, 1600, SearchClock::Wall), [1000]);
+        // Segmen yang sedang ditulis berakhir "sekarang"
+        assert_eq!(search(1500, u64::MAX, SearchClock::Wall), [1000, 3000]);
+        assert!(search(2000, 2999, SearchClock::Wall).is_empty());
+        assert_eq!(search(0, 600, SearchClock::Producer), [1000]);
+        assert!(search(1500, 1600, SearchClock::Producer).is_empty());
+
+        // Update recorder menggantikan isi disk
+        index.upsert("cam1", &meta(3000, 3999, None), false);
+        index.upsert("cam1", &meta(2000, 2500, None), false);
+        assert_eq!(search(2400, 3100, SearchClock::Wall), [2000, 3000]);
+        assert!(search(4000, 5000, SearchClock::Wall).is_empty());
+        assert!(index.search(root, "missing", 0, 1, SearchClock::Wall).is_err());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
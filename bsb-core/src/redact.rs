@@ -0,0 +1,150 @@
+use serde_json::Value;
+
+use crate::mux::glob_match;
+
+/// Scope yang dipakai rule tanpa `@scope`
+pub const DEFAULT_SCOPE: &str = "metadata:full";
+
+/// Satu rule `METADATA_REDACTION`
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    pattern: String,
+    /// Dotted JSON paths to strip; `*` drops the whole message
+    fields: Vec<String>,
+    /// Scope that exempts a viewer from this rule
+    scope: String,
+}
+
+/// Redaksi metadata data channel untuk viewer tanpa scope tertentu
+///
+/// Format of `METADATA_REDACTION`: `pattern=field,field[@scope];...`, e.g.
+/// `site-a/*=gps,device.serial@metadata:precise;*=*@operator`. The first
+/// rule matching the stream applies. Viewers holding the rule's scope (default
+/// `metadata:full`) receive messages as sent; everyone else gets JSON messages
+/// with the listed fields removed, or nothing at all for `*`. Only data
+/// channel messages are touched; video frames are delivered unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataRedaction {
+    rules: Vec<Rule>,
+}
+
+impl MetadataRedaction {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for rule in spec.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            let (pattern, fields) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid METADATA_REDACTION rule: {}", rule))?;
+            let (fields, scope) = match fields.rsplit_once('@') {
+                Some((fields, scope)) if !scope.trim().is_empty() => (fields, scope.trim()),
+                Some(_) => return Err(format!("Invalid METADATA_REDACTION rule (empty scope): {}", rule)),
+                None => (fields, DEFAULT_SCOPE),
+            };
+            let fields: Vec<String> = fields
+                .split(',')
+                .map(str::trim)
+                .filter(|f| !f.is_empty())
+                .map(str::to_string)
+                .collect();
+            if fields.is_empty() {
+                return Err(format!("Invalid METADATA_REDACTION rule (no fields): {}", rule));
+            }
+            rules.push(Rule {
+                pattern: pattern.trim().to_string(),
+                fields,
+                scope: scope.to_string(),
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Filter yang berlaku untuk viewer `stream_id` dengan `scopes`; `None` = pesan utuh
+    pub fn for_viewer(&self, stream_id: &str, scopes: &[String]) -> Option<Redactor> {
+        let rule = self.rules.iter().find(|rule| glob_match(&rule.pattern, stream_id))?;
+        if scopes.contains(&rule.scope) {
+            return None;
+        }
+        Some(Redactor {
+            fields: rule.fields.clone(),
+        })
+    }
+}
+
+/// Filter metadata satu koneksi data channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redactor {
+    fields: Vec<String>,
+}
+
+impl Redactor {
+    /// Pesan yang boleh diterima viewer, atau `None` bila harus dibuang
+    ///
+    /// Text that is not a JSON object cannot be inspected for the listed
+    /// fields, so it is dropped rather than leaked.
+    pub fn apply(&self, text: &str) -> Option<String> {
+        if self.fields.iter().any(|field| field == "*") {
+            return None;
+        }
+        let mut value: Value = serde_json::from_str(text).ok()?;
+        if !value.is_object() {
+            return None;
+        }
+        let mut changed = false;
+        for field in &self.fields {
+            let path: Vec<&str> = field.split('.').collect();
+            changed |= remove_path(&mut value, &path);
+        }
+        if changed {
+            Some(value.to_string())
+        } else {
+            Some(text.to_string())
+        }
+    }
+}
+
+/// Hapus `path` dari object; array di tengah jalan diterapkan ke tiap elemen
+fn remove_path(value: &mut Value, path: &[&str]) -> bool {
+    match value {
+        Value::Array(items) => items.iter_mut().fold(false, |changed, item| remove_path(item, path) | changed),
+        Value::Object(map) => match path {
+            [] => false,
+            [last] => map.remove(*last).is_some(),
+            [head, rest @ ..] => map.get_mut(*head).is_some_and(|child| remove_path(child, rest)),
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redaction_strips_fields_for_viewers_without_scope() {
+        let policy = MetadataRedaction::parse("site-a-*=gps,device.serial,tracks.plate;lobby=*@operator").unwrap();
+        let scopes = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert!(policy.for_viewer("site-b-cam1", &[]).is_none());
+        assert!(policy.for_viewer("site-a-cam1", &scopes(&["metadata:full"])).is_none());
+        assert!(policy.for_viewer("lobby", &scopes(&["operator"])).is_none());
+
+        let redactor = policy.for_viewer("site-a-cam1", &scopes(&["operator"])).unwrap();
+        let message = r#"{"gps":[-6.2,106.8],"device":{"serial":"SN-1","model":"X"},"tracks":[{"id":1,"plate":"B 1"}],"caption":"hi"}"#;
+        let redacted: Value = serde_json::from_str(&redactor.apply(message).unwrap()).unwrap();
+        assert_eq!(
+            redacted,
+            serde_json::json!({ "device": { "model": "X" }, "tracks": [{ "id": 1 }], "caption": "hi" })
+        );
+        assert_eq!(redactor.apply(r#"{"caption":"hi"}"#).unwrap(), r#"{"caption":"hi"}"#);
+        // Teks non-JSON tidak bisa diperiksa, jadi dibuang
+        assert!(redactor.apply("gps -6.2 106.8").is_none());
+
+        assert!(policy.for_viewer("lobby", &[]).unwrap().apply(r#"{"caption":"hi"}"#).is_none());
+        assert!(MetadataRedaction::parse("cam1=gps@").is_err());
+        assert!(MetadataRedaction::parse("cam1=").is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use bytes::Bytes;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::Instant,
+};
+use tokio::sync::broadcast;
+
+use crate::{
+    acks::AckSubscribers,
+    audio::StreamType,
+    bootstrap,
+    breaker::{BreakerConfig, CircuitBreaker},
+    codec::CodecParams,
+    connections::ConnectionTable,
+    datachannel::{DataMessage, DATA_CHANNEL_CAPACITY},
+    dvr::DvrBuffer,
+    failover::{FailoverState, SourceRole},
+    fairness::FairScheduler,
+    labels::Labels,
+    lifetime::{StopReason, StreamLifetime},
+    sniff::{FormatDetector, PayloadFormat},
+    stats::{unix_now_ms, StreamCounters},
+    timeline::{Timeline, TimelineEvent},
+};
+#[cfg(feature = "metrics")]
+use crate::history::StatsHistory;
+
+/// Control event terakhir yang disimpan per stream untuk `/api/debug/state`
+const RECENT_EVENTS: usize = 32;
+
+/// Satu frame biner beserta nomor urutnya (sequence number) di dalam stream
+///
+/// `data` adalah `Bytes` (smart pointer, copy-on-write), jadi clone tetap murah.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub seq: u64,
+    pub data: Bytes,
+    /// Waktu frame diterima broker (untuk timeshift `?delay=`)
+    pub received_at: Instant,
+    /// Capture time reported by the producer (unix ms), if it sent one
+    pub producer_ms: Option<u64>,
+}
+
+/// Control event untuk subscriber (dikirim sebagai pesan teks JSON di mode seq)
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// Frames after `seq` come from `source`
+    SourceChanged { source: SourceRole, seq: u64 },
+    /// The broker stopped the stream after frame `seq`; the connection is closed next
+    StreamEnded { reason: StopReason, seq: u64 },
+    /// The producer is still connected but sent no frame for `silent_secs`
+    ProducerStalled { silent_secs: u64, seq: u64 },
+    /// The producer sends well below the frame rate it declared
+    ProducerSlow { fps: f64, declared_fps: f64, seq: u64 },
+    /// Too many subscriber sends failed; live frames are held back for `retry_secs`
+    BreakerOpened { failures: u64, subscribers: u64, retry_secs: f64, seq: u64 },
+    /// Live fan-out resumed; `suppressed_errors` sends failed while the breaker was open
+    BreakerClosed { suppressed_errors: u64, seq: u64 },
+    /// `score` percent of the picture changed (`/api/streams/:id/motion`)
+    MotionStarted { score: f64, seq: u64 },
+    /// No motion for the detector's cooldown; `peak_score` was the largest change
+    MotionEnded { peak_score: f64, seq: u64 },
+    /// Frame `seq` carries new codec parameters (SPS/PPS, resolution, init segment);
+    /// decoders have to be reinitialized from it
+    CodecChanged { codec: CodecParams, seq: u64 },
+}
+
+/// State per stream: channel siaran + DVR buffer untuk replay + statistik
+#[derive(Debug)]
+pub struct StreamEntry {
+    pub tx: broadcast::Sender<Frame>,
+    pub events: broadcast::Sender<StreamEvent>,
+    /// Text companion channel (`/ws/:stream_id/data`)
+    pub data: broadcast::Sender<DataMessage>,
+    pub dvr: DvrBuffer,
+    /// Shared with subscriber tasks so they can count drops without the registry lock
+    pub counters: Arc<StreamCounters>,
+    /// Shared with subscriber tasks like `counters`; see [`CircuitBreaker`]
+    pub breaker: Arc<CircuitBreaker>,
+    /// Shared with subscriber tasks like `counters`; see [`FairScheduler`]
+    pub fairness: Arc<FairScheduler>,
+    #[cfg(feature = "metrics")]
+    pub history: StatsHistory,
+    pub failover: FailoverState,
+    pub lifetime: StreamLifetime,
+    pub labels: Labels,
+    /// At-least-once subscribers; their unacked frames are retained in the DVR
+    pub acks: AckSubscribers,
+    /// Video (default) or audio; set at creation and by the producer's `?type=` hint
+    pub stream_type: StreamType,
+    /// Kapasitas broadcast channel (berbeda untuk stream audio)
+    pub capacity: usize,
+    /// Latest fragmented MP4 init segment, for `/api/streams/:stream_id/bootstrap`
+    pub init_segment: Option<Frame>,
+    /// Latest frame that decodes on its own: every MJPEG/WebP/Opus frame, H.264 IDRs,
+    /// MPEG-TS random access points
+    pub keyframe: Option<Frame>,
+    /// Payload format sniffed from the first frames
+    pub format: FormatDetector,
+    /// Codec parameters of the latest frame that carried them
+    pub codec: Option<CodecParams>,
+    /// Open WebSocket producers and subscribers; shared with their tasks like `counters`
+    pub connections: Arc<ConnectionTable>,
+    /// Latest control events as `(unix ms, event)`, oldest first
+    pub recent_events: VecDeque<(u64, StreamEvent)>,
+    /// Notable events for `/api/streams/:stream_id/events`
+    pub timeline: Timeline,
+    last_seq: u64,
+    /// `received_at` and `producer_ms` of frame `last_seq`
+    last_timing: Option<(Instant, Option<u64>)>,
+}
+
+impl StreamEntry {
+    pub fn new(channel_capacity: usize, dvr_frames: usize) -> Self {
+        Self {
+            tx: broadcast::channel(channel_capacity).0,
+            events: broadcast::channel(16).0,
+            data: broadcast::channel(DATA_CHANNEL_CAPACITY).0,
+            dvr: DvrBuffer::new(dvr_frames),
+            counters: Arc::new(StreamCounters::default()),
+            breaker: Arc::new(CircuitBreaker::new(BreakerConfig::default())),
+            fairness: Arc::new(FairScheduler::default()),
+            #[cfg(feature = "metrics")]
+            history: StatsHistory::default(),
+            failover: FailoverState::default(),
+            lifetime: StreamLifetime::default(),
+            labels: Labels::new(),
+            acks: AckSubscribers::default(),
+            stream_type: StreamType::Video,
+            capacity: channel_capacity,
+            init_segment: None,
+            keyframe: None,
+            format: FormatDetector::default(),
+            codec: None,
+            connections: Arc::new(ConnectionTable::default()),
+            recent_events: VecDeque::new(),
+            timeline: Timeline::default(),
+            last_seq: 0,
+            last_timing: None,
+        }
+    }
+
+    /// Beri nomor urut ke frame, simpan di DVR buffer, lalu siarkan ke subscriber
+    pub fn publish(&mut self, data: Bytes) -> Result<usize, broadcast::error::SendError<Frame>> {
+        self.publish_at(data, None)
+    }
+
+    /// Sama seperti `publish`, dengan timestamp capture dari producer
+    pub fn publish_at(
+        &mut self,
+        data: Bytes,
+        producer_ms: Option<u64>,
+    ) -> Result<usize, broadcast::error::SendError<Frame>> {
+        let ended = self.lifetime.ended.is_some();
+        if !ended {
+            self.last_seq += 1;
+            self.counters.record_frame(data.len());
+        }
+        let frame = Frame {
+            seq: self.last_seq,
+            data,
+            received_at: Instant::now(),
+            producer_ms,
+        };
+        // Stream yang dihentikan/di-shed tidak menerima frame dari source internal (pull, test, merge, relay)
+        if ended {
+            return Err(broadcast::error::SendError(frame));
+        }
+        self.last_timing = Some((frame.received_at, frame.producer_ms));
+        let resniff = self.timeline.on_frame(frame.seq, frame.received_at);
+        if self.format.observe(&frame.data) {
+            self.timeline.on_format(frame.seq, self.format.format());
+        } else if resniff && self.format.is_settled() {
+            // Encoder yang diganti biasanya datang setelah reconnect atau jeda
+            self.timeline.on_format(frame.seq, PayloadFormat::sniff(&frame.data));
+        }
+        if let Some(codec) = CodecParams::extract(&frame.data) {
+            if self.codec.as_ref().is_some_and(|previous| previous.changed(&codec)) {
+                // Cache bootstrap berisi parameter lama; diisi ulang dari frame ini
+                self.keyframe = None;
+                self.init_segment = None;
+                self.emit(StreamEvent::CodecChanged { codec: codec.clone(), seq: frame.seq });
+            }
+            self.codec = Some(codec);
+        }
+        // Disimpan terpisah dari DVR buffer, yang bisa berkapasitas 0
+        if bootstrap::is_init_segment(&frame.data) {
+            self.init_segment = Some(frame.clone());
+        } else if self.format.likely().is_keyframe(&frame.data) {
+            self.keyframe = Some(frame.clone());
+        }
+        self.dvr.push(frame.clone());
+        self.tx.send(frame)
+    }
+
+    /// Kirim control event ke subscriber dan simpan di daftar event terakhir dan timeline
+    pub fn emit(&mut self, event: StreamEvent) {
+        if self.recent_events.len() == RECENT_EVENTS {
+            self.recent_events.pop_front();
+        }
+        self.recent_events.push_back((unix_now_ms(), event.clone()));
+        self.timeline.record(self.last_seq, TimelineEvent::Control { event: event.clone() });
+        let _ = self.events.send(event);
+    }
+
+    /// Lanjutkan penomoran dari `seq` (offset durable setelah broker restart)
+    pub fn continue_from(&mut self, seq: u64) {
+        self.last_seq = self.last_seq.max(seq);
+    }
+
+    /// Sequence number of the most recently published frame (0 if none yet)
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    /// Kapan frame terakhir diterima dan timestamp capture-nya dari producer
+    pub fn last_timing(&self) -> Option<(Instant, Option<u64>)> {
+        self.last_timing
+    }
+}
+
+// Peta (map) dari Stream ID (String) ke state stream-nya
+pub type StreamMap = Arc<Mutex<HashMap<String, StreamEntry>>>;
@@ -0,0 +1,235 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use bytes::Bytes;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use crate::{error::BrokerError, mux::glob_match, outbound::HttpTarget, timeline, timeline::TimelineEvent, AppState};
+
+/// Jendela terbesar: satu bit per seq di belakang seq tertinggi
+pub const MAX_WINDOW: u64 = 63;
+
+/// Proteksi replay ingest dari `INGEST_REPLAY_*`
+#[derive(Debug, Clone, Default)]
+pub struct ReplayConfig {
+    /// Glob patterns of the protected streams; none = protection off
+    pub streams: Vec<String>,
+    /// How many sequence numbers behind the highest one may still arrive late (reordering)
+    pub window: u64,
+    /// `http://` endpoint that receives every burst of rejected frames as JSON
+    pub webhook: Option<String>,
+}
+
+impl ReplayConfig {
+    pub fn parse_streams(value: &str) -> Vec<String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window > MAX_WINDOW {
+            return Err(format!("INGEST_REPLAY_WINDOW must be at most {}", MAX_WINDOW));
+        }
+        Ok(())
+    }
+
+    /// Stream ini hanya menerima frame `bsb.envelope.v1` dengan seq yang diperiksa
+    pub fn protects(&self, stream_id: &str) -> bool {
+        self.streams.iter().any(|pattern| glob_match(pattern, stream_id))
+    }
+}
+
+/// Alasan frame ditolak
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayReason {
+    /// Sequence number 0 (the envelope carried none)
+    Missing,
+    /// This sequence number was already accepted
+    Repeated,
+    /// Further behind the highest sequence number than the window allows
+    TooOld,
+}
+
+/// Jendela anti-replay satu stream, bertahan melewati reconnect producer
+#[derive(Debug, Default)]
+struct ReplayGuard {
+    highest: u64,
+    /// Bit `n`: seq `highest - n` sudah diterima
+    seen: u64,
+    accepted: u64,
+    rejected: u64,
+    /// Sedang dalam rentetan penolakan; alert dikirim sekali per rentetan
+    alerting: bool,
+}
+
+impl ReplayGuard {
+    fn check(&mut self, seq: u64, window: u64) -> Result<(), ReplayReason> {
+        if seq == 0 {
+            return Err(ReplayReason::Missing);
+        }
+        if seq > self.highest {
+            let shift = seq - self.highest;
+            self.seen = if shift > MAX_WINDOW { 0 } else { self.seen << shift };
+            self.seen |= 1;
+            self.highest = seq;
+            return Ok(());
+        }
+        let behind = self.highest - seq;
+        if behind <= MAX_WINDOW && self.seen & (1 << behind) != 0 {
+            return Err(ReplayReason::Repeated);
+        }
+        if behind > window {
+            return Err(ReplayReason::TooOld);
+        }
+        self.seen |= 1 << behind;
+        Ok(())
+    }
+}
+
+/// Penolakan yang perlu di-alert (yang pertama dalam satu rentetan)
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayAlert {
+    pub producer_seq: u64,
+    pub highest: u64,
+    pub reason: ReplayReason,
+}
+
+/// Status jendela anti-replay per stream (ingest path, sebelum demux)
+#[derive(Debug, Default)]
+pub struct ReplayGuards {
+    streams: Mutex<HashMap<String, ReplayGuard>>,
+}
+
+impl ReplayGuards {
+    /// Periksa seq envelope; `Err(Some(alert))` untuk penolakan pertama dalam satu rentetan
+    pub fn check(&self, stream_id: &str, seq: u64, window: u64) -> Result<(), Option<ReplayAlert>> {
+        let mut streams = self.streams.lock();
+        let guard = streams.entry(stream_id.to_string()).or_default();
+        match guard.check(seq, window) {
+            Ok(()) => {
+                guard.accepted += 1;
+                guard.alerting = false;
+                Ok(())
+            }
+            Err(reason) => {
+                guard.rejected += 1;
+                if std::mem::replace(&mut guard.alerting, true) {
+                    return Err(None);
+                }
+                Err(Some(ReplayAlert {
+                    producer_seq: seq,
+                    highest: guard.highest,
+                    reason,
+                }))
+            }
+        }
+    }
+
+    /// Stream yang jendela anti-replay-nya disimpan
+    pub fn tracked(&self) -> usize {
+        self.streams.lock().len()
+    }
+}
+
+/// Log, catat di timeline dan kirim webhook untuk frame yang ditolak
+pub fn raise(state: &AppState, stream_id: &str, alert: ReplayAlert) {
+    warn!(
+        "Rejected replayed frame for stream {}: producer seq {} ({:?}, highest {})",
+        stream_id, alert.producer_seq, alert.reason, alert.highest
+    );
+    timeline::record(
+        state,
+        stream_id,
+        TimelineEvent::ReplayRejected {
+            producer_seq: alert.producer_seq,
+            highest: alert.highest,
+            reason: alert.reason,
+        },
+    );
+
+    let Some(url) = state.config.replay.webhook.clone() else {
+        return;
+    };
+    let mut body = serde_json::to_value(&alert).expect("alerts serialize");
+    body["type"] = "replay_rejected".into();
+    body["stream"] = stream_id.into();
+    tokio::spawn(async move {
+        let result = match HttpTarget::parse(&url) {
+            Ok(mut target) => {
+                target
+                    .post("application/json", &[], Bytes::from(body.to_string()))
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(status) if status.is_success() => {}
+            Ok(status) => warn!("Replay webhook {} answered {}", url, status),
+            Err(e) => warn!("Replay webhook {} failed: {}", url, e),
+        }
+    });
+}
+
+/// Handler untuk GET /api/streams/:id/replay
+pub async fn get_replay_handler(AxumPath(stream_id): AxumPath<String>, State(state): State<AppState>) -> Response {
+    if !state.config.replay.protects(&stream_id) {
+        return BrokerError::not_found("replay protection", stream_id).into_response();
+    }
+    let streams = state.replay.streams.lock();
+    let guard = streams.get(&stream_id);
+    Json(serde_json::json!({
+        "stream": stream_id,
+        "window": state.config.replay.window,
+        "highest_seq": guard.map_or(0, |guard| guard.highest),
+        "accepted": guard.map_or(0, |guard| guard.accepted),
+        "rejected": guard.map_or(0, |guard| guard.rejected),
+    }))
+    .into_response()
+}
+
+/// Handler untuk DELETE /api/streams/:id/replay
+/// Forget the highest sequence number, for a producer that was deliberately restarted from 1
+pub async fn delete_replay_handler(AxumPath(stream_id): AxumPath<String>, State(state): State<AppState>) -> Response {
+    if state.replay.streams.lock().remove(&stream_id).is_some() {
+        info!("Replay window of stream {} reset", stream_id);
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        BrokerError::not_found("replay protection", stream_id).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_window_rejects_repeats_and_old_frames() {
+        let guards = ReplayGuards::default();
+        let check = |seq| guards.check("vault", seq, 2).map_err(|alert| alert.map(|alert| alert.reason));
+        assert!(check(1).is_ok());
+        assert!(check(3).is_ok());
+        // Terlambat tapi masih di dalam jendela
+        assert!(check(2).is_ok());
+        assert_eq!(check(2), Err(Some(ReplayReason::Repeated)));
+        // Rentetan penolakan hanya di-alert sekali
+        assert_eq!(check(3), Err(None));
+        assert!(check(10).is_ok());
+        assert_eq!(check(7), Err(Some(ReplayReason::TooOld)));
+        assert_eq!(check(0), Err(None));
+        assert!(check(200).is_ok());
+        assert_eq!(check(10), Err(Some(ReplayReason::TooOld)));
+
+        let streams = guards.streams.lock();
+        assert_eq!((streams["vault"].accepted, streams["vault"].rejected), (5, 5));
+    }
+}
@@ -0,0 +1,197 @@
+use std::time::{Duration, Instant};
+
+use crate::mux::glob_match;
+
+/// Sampel RTT berturut-turut di atas budget sebelum subscriber kritis diputus
+pub const BUDGET_STRIKES: u32 = 3;
+
+/// Latency budget per stream dari `LATENCY_BUDGET_MS`
+///
+/// Format: `pattern=ms;pattern=ms`, e.g. `teleop/*=150`. The first matching
+/// rule wins; the budget only applies to subscribers that connect with
+/// `?critical=true`.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyBudgets {
+    rules: Vec<(String, Duration)>,
+}
+
+impl LatencyBudgets {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for rule in spec.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            let (pattern, ms) = rule
+                .rsplit_once('=')
+                .ok_or_else(|| format!("Invalid LATENCY_BUDGET_MS rule: {}", rule))?;
+            let ms: u64 = ms
+                .trim()
+                .parse()
+                .map_err(|e| format!("Invalid LATENCY_BUDGET_MS value in {}: {}", rule, e))?;
+            rules.push((pattern.trim().to_string(), Duration::from_millis(ms)));
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn for_stream(&self, stream_id: &str) -> Option<Duration> {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, stream_id))
+            .map(|(_, budget)| *budget)
+    }
+}
+
+/// Pengukuran RTT satu koneksi lewat ping/pong WebSocket
+///
+/// Every ping carries its own id, so a pong that arrives after the next ping
+/// was sent is ignored instead of producing a too-short sample. The smoothed
+/// RTT follows TCP's (7/8 old, 1/8 new).
+#[derive(Debug)]
+pub struct RttProbe {
+    next_id: u64,
+    outstanding: Option<(u64, Instant)>,
+    pub last: Option<Duration>,
+    pub smoothed: Option<Duration>,
+    budget: Option<Duration>,
+    strikes: u32,
+}
+
+impl RttProbe {
+    pub fn new(budget: Option<Duration>) -> Self {
+        Self {
+            next_id: 0,
+            outstanding: None,
+            last: None,
+            smoothed: None,
+            budget,
+            strikes: 0,
+        }
+    }
+
+    /// Payload ping berikutnya; ping sebelumnya yang belum dijawab melewati budget dihitung pelanggaran
+    pub fn ping(&mut self, now: Instant) -> Vec<u8> {
+        if let Some((_, sent)) = self.outstanding {
+            let waited = now.duration_since(sent);
+            if self.budget.is_some_and(|budget| waited > budget) {
+                self.last = Some(waited);
+                self.strikes += 1;
+            }
+        }
+        self.next_id += 1;
+        self.outstanding = Some((self.next_id, now));
+        self.next_id.to_be_bytes().to_vec()
+    }
+
+    /// Catat pong; `Some(rtt)` bila menjawab ping terakhir
+    pub fn on_pong(&mut self, payload: &[u8], now: Instant) -> Option<Duration> {
+        let (id, sent) = self.outstanding?;
+        if payload != id.to_be_bytes() {
+            return None;
+        }
+        self.outstanding = None;
+        let rtt = now.duration_since(sent);
+        self.last = Some(rtt);
+        self.smoothed = Some(match self.smoothed {
+            Some(smoothed) => (smoothed * 7 + rtt) / 8,
+            None => rtt,
+        });
+        match self.budget {
+            Some(budget) if rtt > budget => self.strikes += 1,
+            _ => self.strikes = 0,
+        }
+        Some(rtt)
+    }
+
+    /// `(rtt, budget)` setelah `BUDGET_STRIKES` sampel berturut-turut di atas budget
+    pub fn over_budget(&self) -> Option<(Duration, Duration)> {
+        let budget = self.budget?;
+        (self.strikes >= BUDGET_STRIKES).then(|| (self.last.unwrap_or_default(), budget))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtt_samples_and_budget_strikes() {
+        let budgets = LatencyBudgets::parse("teleop/*=100;*=1000").unwrap();
+        assert_eq!(budgets.for_stream("teleop/arm"), Some(Duration::from_millis(100)));
+        assert!(LatencyBudgets::parse("teleop/*").is_err());
+
+        let start = Instant::now();
+        let ms = |ms| start + Duration::from_millis(ms);
+        let mut probe = RttProbe::new(budgets.for_stream("teleop/arm"));
+        let first = probe.ping(start);
+        // Pong untuk ping lain diabaikan
+        assert_eq!(probe.on_pong(b"other", ms(10)), None);
+        assert_eq!(probe.on_pong(&first, ms(40)), Some(Duration::from_millis(40)));
+        let second = probe.ping(ms(1000));
+        assert_eq!(probe.on_pong(&second, ms(1120)), Some(Duration::from_millis(120)));
+        assert_eq!(probe.smoothed, Some(Duration::from_millis(50)));
+        assert_eq!(probe.over_budget(), None);
+
+        // Ping yang tidak dijawab lebih lama dari budget juga dihitung
+        probe.ping(ms(2000));
+        let fourth = probe.ping(ms(3000));
+        assert_eq!(probe.on_pong(&fourth, ms(3150)), Some(Duration::from_millis(150)));
+        assert_eq!(probe.over_budget(), Some((Duration::from_millis(150), Duration::from_millis(100))));
+
+        let mut unbudgeted = RttProbe::new(None);
+        for i in 0..5 {
+            unbudgeted.ping(ms(i * 1000));
+        }
+        assert_eq!(unbudgeted.over_budget(), None);
+    }
+
+    #[tokio::test]
+    async fn test_ping_rtt_reported_and_budget_enforced() {
+        use crate::{config::Config, connections::connections_handler, testing::TestBroker, AppState};
+        use axum::extract::{Path, State};
+
+        let broker = TestBroker::start(Config {
+            ws_ping_interval_secs: 1,
+            latency_budgets: LatencyBudgets::parse("teleop-*=50").unwrap(),
+            ..Config::default()
+        })
+        .await;
+        let connections = |state: AppState| async move {
+            let response = connections_handler(Path("teleop-arm".to_string()), State(state)).await;
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        // Klien yang membaca menjawab ping, RTT-nya terlihat di API koneksi
+        let mut viewer = broker.subscriber("teleop-arm?seq=true").await;
+        assert_eq!(viewer.expect_json().await["type"], "sync");
+        // Subscriber kritis yang tidak membaca tidak pernah menjawab ping
+        let _operator = broker.subscriber("teleop-arm?critical=true").await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        broker.post_frame("teleop-arm", "frame-1").await;
+        assert_eq!(viewer.expect_binary().await, b"frame-1");
+        let mut body = connections(broker.state.clone()).await;
+        for _ in 0..100 {
+            if !body["subscribers"][0]["rtt_ms"].is_null() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            body = connections(broker.state.clone()).await;
+        }
+        assert_eq!(body["latency_budget_ms"], 50);
+        assert!(body["subscribers"][0]["rtt_ms"].as_f64().unwrap() < 1000.0);
+
+        // Tiga ping tanpa jawaban di atas budget: hanya subscriber kritis yang diputus
+        let viewer_id = body["subscribers"][0]["id"].clone();
+        for _ in 0..60 {
+            body = connections(broker.state.clone()).await;
+            if body["subscribers"].as_array().unwrap().len() == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(body["subscribers"].as_array().unwrap().len(), 1);
+        assert_eq!(body["subscribers"][0]["id"], viewer_id);
+    }
+}
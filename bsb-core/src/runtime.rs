@@ -0,0 +1,153 @@
+use serde_json::json;
+use std::{future::Future, io, sync::OnceLock};
+use tokio::{
+    runtime::{Builder, Handle, Runtime},
+    task::JoinHandle,
+};
+
+use crate::config::parse_var;
+
+/// Runtime disk I/O terpisah (`RUNTIME_DISK_THREADS` > 0)
+static DISK: OnceLock<Handle> = OnceLock::new();
+
+/// Ukuran runtime Tokio dari `RUNTIME_*`, dibaca sebelum runtime dibuat
+///
+/// The main runtime serves HTTP and WebSocket fan-out. With
+/// `RUNTIME_DISK_THREADS` set, recorders, recovery, clip capture and export
+/// scans run on a second runtime with its own worker and blocking threads, so
+/// a slow `fsync` never occupies a thread that is delivering live frames.
+#[derive(Debug, Clone)]
+pub struct RuntimeConfig {
+    /// Worker threads of the main runtime (default: one per CPU core)
+    pub worker_threads: Option<usize>,
+    /// Upper bound of the main runtime's blocking pool
+    pub max_blocking_threads: usize,
+    /// Worker threads of the dedicated disk runtime (0 = disk I/O shares the main runtime)
+    pub disk_threads: usize,
+    /// Upper bound of the disk runtime's blocking pool (`tokio::fs`, fsync)
+    pub disk_blocking_threads: usize,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            worker_threads: None,
+            max_blocking_threads: 512,
+            disk_threads: 0,
+            disk_blocking_threads: 16,
+        }
+    }
+}
+
+impl RuntimeConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        let worker_threads = parse_var("RUNTIME_WORKER_THREADS", 0)?;
+        let config = Self {
+            worker_threads: (worker_threads > 0).then_some(worker_threads),
+            max_blocking_threads: parse_var("RUNTIME_MAX_BLOCKING_THREADS", defaults.max_blocking_threads)?,
+            disk_threads: parse_var("RUNTIME_DISK_THREADS", defaults.disk_threads)?,
+            disk_blocking_threads: parse_var("RUNTIME_DISK_BLOCKING_THREADS", defaults.disk_blocking_threads)?,
+        };
+        if config.max_blocking_threads == 0 || config.disk_blocking_threads == 0 {
+            return Err("RUNTIME_MAX_BLOCKING_THREADS and RUNTIME_DISK_BLOCKING_THREADS must be at least 1".to_string());
+        }
+        Ok(config)
+    }
+
+    /// Runtime utama (jaringan dan fan-out)
+    pub fn build(&self) -> io::Result<Runtime> {
+        let mut builder = Builder::new_multi_thread();
+        builder
+            .enable_all()
+            .thread_name("broker-worker")
+            .max_blocking_threads(self.max_blocking_threads);
+        if let Some(threads) = self.worker_threads {
+            builder.worker_threads(threads);
+        }
+        builder.build()
+    }
+
+    /// Buat runtime disk jika dikonfigurasi; harus tetap hidup selama runtime utama berjalan
+    pub fn start_disk(&self) -> io::Result<Option<Runtime>> {
+        let runtime = self.build_disk()?;
+        if let Some(runtime) = &runtime {
+            let _ = DISK.set(runtime.handle().clone());
+        }
+        Ok(runtime)
+    }
+
+    fn build_disk(&self) -> io::Result<Option<Runtime>> {
+        if self.disk_threads == 0 {
+            return Ok(None);
+        }
+        Builder::new_multi_thread()
+            .enable_all()
+            .thread_name("broker-disk")
+            .worker_threads(self.disk_threads)
+            .max_blocking_threads(self.disk_blocking_threads)
+            .build()
+            .map(Some)
+    }
+}
+
+/// Handle runtime untuk disk I/O: runtime disk bila ada, selain itu runtime saat ini
+pub fn disk() -> Handle {
+    DISK.get().cloned().unwrap_or_else(Handle::current)
+}
+
+/// `spawn_blocking` di runtime disk (scan segmen, recovery, tulis klip)
+pub fn spawn_disk_blocking<F, R>(f: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    disk().spawn_blocking(f)
+}
+
+/// Jalankan future di runtime disk
+pub fn spawn_disk<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    disk().spawn(future)
+}
+
+/// Ringkasan runtime untuk `/health`
+pub fn status() -> serde_json::Value {
+    let main = Handle::current().metrics();
+    json!({
+        "worker_threads": main.num_workers(),
+        "alive_tasks": main.num_alive_tasks(),
+        "disk_worker_threads": DISK.get().map(|disk| disk.metrics().num_workers()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_runtime_is_separate() {
+        let config = RuntimeConfig {
+            worker_threads: Some(2),
+            disk_threads: 1,
+            ..RuntimeConfig::default()
+        };
+        let main = config.build().unwrap();
+        let disk = config.build_disk().unwrap().expect("disk runtime");
+        let handle = disk.handle().clone();
+        // Task dari runtime utama yang dikirim ke runtime disk berjalan di thread disk
+        let name = main.block_on(async move {
+            handle
+                .spawn_blocking(|| std::thread::current().name().map(str::to_string))
+                .await
+                .unwrap()
+        });
+        assert_eq!(name.as_deref(), Some("broker-disk"));
+        assert_eq!(main.handle().metrics().num_workers(), 2);
+        assert_eq!(disk.handle().metrics().num_workers(), 1);
+        assert!(RuntimeConfig { disk_threads: 0, ..config }.build_disk().unwrap().is_none());
+    }
+}
@@ -0,0 +1,357 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+use tokio::{
+    fs::File,
+    io::{AsyncWriteExt, BufWriter},
+};
+use tracing::{error, info, warn};
+
+use crate::{checksum::crc32c, federation::encode_path_segment, registry::Frame};
+
+/// Ekstensi file segmen rekaman
+pub const SEGMENT_EXTENSION: &str = "bsbrec";
+/// Journal segmen yang masih ditulis; hanya ada selama segmen terbuka (atau setelah crash)
+pub const JOURNAL_EXTENSION: &str = "wal";
+/// Metadata segmen yang sudah ditutup
+pub const META_EXTENSION: &str = "json";
+
+pub use bsb_proto::record::{decode_record_header, encode_record, RECORD_HEADER_LEN};
+/// Entry journal: `[u64 seq][u64 offset][u32 len][u32 crc32c(payload)]`, big-endian
+const JOURNAL_ENTRY_LEN: usize = 24;
+
+/// Direktori rekaman satu stream: `RECORDINGS_DIR/<stream id, percent-encoded>`
+pub fn stream_dir(root: &str, stream_id: &str) -> PathBuf {
+    Path::new(root).join(encode_path_segment(stream_id))
+}
+
+fn encode_journal_entry(seq: u64, offset: u64, payload: &[u8]) -> [u8; JOURNAL_ENTRY_LEN] {
+    let mut entry = [0; JOURNAL_ENTRY_LEN];
+    entry[0..8].copy_from_slice(&seq.to_be_bytes());
+    entry[8..16].copy_from_slice(&offset.to_be_bytes());
+    entry[16..20].copy_from_slice(&(payload.len() as u32).to_be_bytes());
+    entry[20..24].copy_from_slice(&crc32c(payload).to_be_bytes());
+    entry
+}
+
+fn be_u64(bytes: &[u8]) -> u64 {
+    u64::from_be_bytes(bytes[..8].try_into().expect("8 bytes"))
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes[..4].try_into().expect("4 bytes"))
+}
+
+/// Metadata segmen (`<start_ms>.json` di samping `<start_ms>.bsbrec`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SegmentMeta {
+    pub segment: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub first_seq: u64,
+    pub last_seq: u64,
+    pub frames: u64,
+    pub bytes: u64,
+    /// Rebuilt from the journal after a crash; frames after the last intact one were dropped
+    pub recovered: bool,
+    /// Capture time range reported by producers (`X-Producer-Timestamp`); not kept by recovery
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub producer_start_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub producer_end_ms: Option<u64>,
+}
+
+impl SegmentMeta {
+    fn record(&mut self, seq: u64, unix_ms: u64, record_len: u64, producer_ms: Option<u64>) {
+        if self.frames == 0 {
+            self.first_seq = seq;
+        }
+        if let Some(producer_ms) = producer_ms {
+            self.producer_start_ms.get_or_insert(producer_ms);
+            self.producer_end_ms = Some(producer_ms);
+        }
+        self.last_seq = seq;
+        self.end_ms = unix_ms;
+        self.frames += 1;
+        self.bytes += record_len;
+    }
+
+    fn write(&self, dir: &Path) -> io::Result<()> {
+        let path = dir.join(format!("{}.{}", self.start_ms, META_EXTENSION));
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self).expect("meta serializes"))?;
+        // Rename atomik: metadata tidak pernah setengah tertulis
+        fs::rename(&tmp, &path)
+    }
+}
+
+/// Segmen yang sedang ditulis beserta journal-nya (juga dipakai spool store-and-forward)
+pub struct Segment {
+    dir: PathBuf,
+    data: BufWriter<File>,
+    journal: BufWriter<File>,
+    pub meta: SegmentMeta,
+    /// Age of the segment, for the recorder's `RECORDING_SEGMENT_SECS` rotation
+    #[cfg_attr(not(feature = "recording"), allow(dead_code))]
+    pub opened_at: Instant,
+}
+
+impl Segment {
+    pub async fn open(dir: &Path, unix_ms: u64) -> io::Result<Self> {
+        tokio::fs::create_dir_all(dir).await?;
+        let segment = format!("{}.{}", unix_ms, SEGMENT_EXTENSION);
+        let data = File::create(dir.join(&segment)).await?;
+        let journal = File::create(dir.join(format!("{}.{}", unix_ms, JOURNAL_EXTENSION))).await?;
+        info!("Recording segment {}", dir.join(&segment).display());
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            data: BufWriter::new(data),
+            journal: BufWriter::new(journal),
+            meta: SegmentMeta {
+                segment,
+                start_ms: unix_ms,
+                ..SegmentMeta::default()
+            },
+            opened_at: Instant::now(),
+        })
+    }
+
+    /// Tulis record ke segmen, lalu offset-nya ke journal (keduanya masih di buffer)
+    pub async fn append(&mut self, frame: &Frame, unix_ms: u64) -> io::Result<()> {
+        let record = encode_record(frame.seq, unix_ms, &frame.data);
+        let offset = self.meta.bytes;
+        self.data.write_all(&record).await?;
+        self.journal
+            .write_all(&encode_journal_entry(frame.seq, offset, &frame.data))
+            .await?;
+        self.meta.record(frame.seq, unix_ms, record.len() as u64, frame.producer_ms);
+        Ok(())
+    }
+
+    /// Flush journal dan data, lalu fsync data segmen sebelum journal
+    ///
+    /// A journal entry whose data did not reach the disk fails its CRC during
+    /// recovery, so a torn write only shortens the recovered segment.
+    pub async fn sync(&mut self) -> io::Result<()> {
+        self.journal.flush().await?;
+        self.data.flush().await?;
+        self.data.get_ref().sync_data().await?;
+        self.journal.get_ref().sync_data().await
+    }
+
+    /// Tutup segmen: fsync, tulis metadata, hapus journal
+    pub async fn close(mut self) -> io::Result<SegmentMeta> {
+        self.sync().await?;
+        let dir = self.dir.clone();
+        let meta = self.meta.clone();
+        tokio::task::spawn_blocking(move || {
+            meta.write(&dir)?;
+            fs::remove_file(dir.join(format!("{}.{}", meta.start_ms, JOURNAL_EXTENSION)))?;
+            Ok(meta)
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+}
+
+/// Pulihkan segmen dari journal-nya setelah crash/power loss
+///
+/// Journal entries are replayed in order and checked against the segment
+/// data (length and CRC); the segment is truncated after the last intact
+/// record, metadata is written with `recovered: true` and the journal removed.
+pub fn recover_segment(dir: &Path, start_ms: u64) -> io::Result<SegmentMeta> {
+    let segment = format!("{}.{}", start_ms, SEGMENT_EXTENSION);
+    let data_path = dir.join(&segment);
+    let journal_path = dir.join(format!("{}.{}", start_ms, JOURNAL_EXTENSION));
+    let mut data = Vec::new();
+    match fs::File::open(&data_path) {
+        Ok(mut file) => {
+            file.read_to_end(&mut data)?;
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let journal = fs::read(&journal_path)?;
+
+    let mut meta = SegmentMeta {
+        segment,
+        start_ms,
+        recovered: true,
+        ..SegmentMeta::default()
+    };
+    for entry in journal.chunks_exact(JOURNAL_ENTRY_LEN) {
+        let (seq, offset, len, crc) = (
+            be_u64(&entry[0..8]),
+            be_u64(&entry[8..16]),
+            be_u32(&entry[16..20]) as usize,
+            be_u32(&entry[20..24]),
+        );
+        // Record harus tepat menyambung record sebelumnya dan utuh
+        let start = offset as usize;
+        let end = start + RECORD_HEADER_LEN + len;
+        if offset != meta.bytes || end > data.len() {
+            break;
+        }
+        let header = &data[start..start + RECORD_HEADER_LEN];
+        let payload = &data[start + RECORD_HEADER_LEN..end];
+        if be_u64(&header[0..8]) != seq || be_u32(&header[16..20]) as usize != len || crc32c(payload) != crc {
+            break;
+        }
+        meta.record(seq, be_u64(&header[8..16]), (end - start) as u64, None);
+    }
+
+    let file = fs::OpenOptions::new().write(true).create(true).truncate(false).open(&data_path)?;
+    file.set_len(meta.bytes)?;
+    file.sync_all()?;
+    if meta.frames == 0 {
+        meta.end_ms = start_ms;
+    }
+    meta.write(dir)?;
+    fs::remove_file(&journal_path)?;
+    Ok(meta)
+}
+
+/// Pulihkan semua journal yang tertinggal di direktori satu stream
+pub fn recover_dir(stream_dir: &Path) {
+    let Ok(files) = fs::read_dir(stream_dir) else {
+        return;
+    };
+    for path in files.flatten().map(|entry| entry.path()) {
+        if path.extension().and_then(|ext| ext.to_str()) != Some(JOURNAL_EXTENSION) {
+            continue;
+        }
+        let Some(start_ms) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+        else {
+            continue;
+        };
+        match recover_segment(stream_dir, start_ms) {
+            Ok(meta) => warn!(
+                "Recovered recording segment {} with {} frames",
+                stream_dir.join(&meta.segment).display(),
+                meta.frames
+            ),
+            Err(e) => error!("Cannot recover recording journal {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Segmen rekaman di index
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct IndexedSegment {
+    #[serde(flatten)]
+    pub meta: SegmentMeta,
+    /// Still being written; its end is "now" for wall-clock searches
+    pub in_progress: bool,
+}
+
+/// Baca metadata semua segmen di direktori stream; journal tanpa metadata = segmen yang sedang ditulis
+pub fn load_segments(dir: &Path) -> io::Result<Vec<IndexedSegment>> {
+    let mut segments = Vec::new();
+    for path in fs::read_dir(dir)?.flatten().map(|entry| entry.path()) {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(META_EXTENSION) => {
+                let Ok(meta) = fs::read(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|bytes| serde_json::from_slice::<SegmentMeta>(&bytes).map_err(|e| e.to_string()))
+                else {
+                    warn!("Ignoring unreadable segment metadata {}", path.display());
+                    continue;
+                };
+                segments.push(IndexedSegment {
+                    meta,
+                    in_progress: false,
+                });
+            }
+            Some(JOURNAL_EXTENSION) => {
+                let Some(start_ms) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse::<u64>().ok())
+                else {
+                    continue;
+                };
+                let meta = SegmentMeta {
+                    segment: format!("{}.{}", start_ms, SEGMENT_EXTENSION),
+                    start_ms,
+                    end_ms: start_ms,
+                    ..SegmentMeta::default()
+                };
+                segments.push(IndexedSegment {
+                    meta,
+                    in_progress: true,
+                });
+            }
+            _ => {}
+        }
+    }
+    segments.sort_by_key(|segment| segment.meta.start_ms);
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_layout() {
+        let record = encode_record(7, 1_700_000_000_123, b"abc");
+        assert_eq!(record.len(), RECORD_HEADER_LEN + 3);
+        assert_eq!(&record[0..8], &7u64.to_be_bytes());
+        assert_eq!(&record[8..16], &1_700_000_000_123u64.to_be_bytes());
+        assert_eq!(&record[16..20], &3u32.to_be_bytes());
+        assert_eq!(&record[20..], b"abc");
+        let header: &[u8; RECORD_HEADER_LEN] = record[..RECORD_HEADER_LEN].try_into().unwrap();
+        assert_eq!(decode_record_header(header), (7, 1_700_000_000_123, 3));
+        assert_eq!(
+            stream_dir("recordings", "site-a/cam1"),
+            Path::new("recordings/site-a%2Fcam1")
+        );
+    }
+
+    #[test]
+    fn test_recover_truncates_after_last_intact_record() {
+        let root = std::env::temp_dir().join(format!("bsb-recorder-test-{}", std::process::id()));
+        let dir = root.join("cam1");
+        fs::create_dir_all(&dir).unwrap();
+
+        // Tiga frame ditulis; frame ketiga hanya setengah sampai ke disk
+        let mut data = Vec::new();
+        let mut journal = Vec::new();
+        for (seq, payload) in [(1u64, &b"first"[..]), (2, b"second"), (3, b"third")] {
+            journal.extend_from_slice(&encode_journal_entry(seq, data.len() as u64, payload));
+            data.extend(encode_record(seq, 1000 + seq, payload));
+        }
+        data.truncate(data.len() - 2);
+        fs::write(dir.join("1000.bsbrec"), &data).unwrap();
+        fs::write(dir.join("1000.wal"), &journal).unwrap();
+
+        recover_dir(&dir);
+        let meta: SegmentMeta =
+            serde_json::from_slice(&fs::read(dir.join("1000.json")).unwrap()).unwrap();
+        assert!(meta.recovered);
+        assert_eq!((meta.first_seq, meta.last_seq, meta.frames), (1, 2, 2));
+        assert_eq!(meta.end_ms, 1002);
+        assert_eq!(fs::metadata(dir.join("1000.bsbrec")).unwrap().len(), meta.bytes);
+        assert!(!dir.join("1000.wal").exists());
+
+        // Segmen hasil recovery tampil di pencarian rekaman dengan `recovered: true`
+        #[cfg(feature = "recording")]
+        {
+            let index = crate::recordings::RecordingIndex::default();
+            let listed = index
+                .search(root.to_str().unwrap(), "cam1", 0, u64::MAX, Default::default())
+                .unwrap();
+            assert_eq!(listed.len(), 1);
+            assert!(!listed[0].in_progress);
+            assert_eq!(serde_json::to_value(&listed[0]).unwrap()["recovered"], true);
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,344 @@
+use axum::{extract::Request, Router};
+use hyper::body::Incoming;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo, TokioTimer},
+    server::conn::auto,
+    service::TowerToHyperService,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use socket2::{SockRef, TcpKeepalive};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpSocket, TcpStream},
+};
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceBuilder;
+use tracing::{info, warn};
+
+use crate::{config::Config, tls};
+
+/// Bind the listener with an explicit accept queue size (`LISTEN_BACKLOG`)
+pub async fn bind(config: &Config) -> io::Result<TcpListener> {
+    let addr: SocketAddr = tokio::net::lookup_host(config.bind_addr())
+        .await?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "bind address did not resolve"))?;
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    socket.set_reuseaddr(true)?;
+    config.tcp.apply_listener(&socket)?;
+    socket.bind(addr)?;
+    socket.listen(config.listen_backlog)
+}
+
+/// Opsi socket TCP untuk koneksi yang diterima listener (`TCP_*`)
+///
+/// Low-latency streams (teleoperation) want `nodelay` and small buffers so a
+/// frame leaves at once and never queues behind older ones; bulk archive pulls
+/// want large buffers. Unset values keep the OS defaults.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TcpTuning {
+    /// Disable Nagle's algorithm (`TCP_NODELAY`)
+    pub nodelay: bool,
+    /// `SO_SNDBUF` in bytes
+    pub send_buffer: Option<usize>,
+    /// `SO_RCVBUF` in bytes
+    pub recv_buffer: Option<usize>,
+    /// Idle time before the first keepalive probe; `None` leaves keepalive off
+    pub keepalive: Option<Duration>,
+    pub keepalive_interval: Option<Duration>,
+    /// Unanswered probes before the connection is dropped (ignored on Windows)
+    pub keepalive_retries: Option<u32>,
+}
+
+impl TcpTuning {
+    /// Buffer diset sebelum `listen`, supaya window scaling koneksi yang diterima mengikutinya
+    fn apply_listener(&self, socket: &TcpSocket) -> io::Result<()> {
+        if let Some(size) = self.send_buffer {
+            socket.set_send_buffer_size(size as u32)?;
+        }
+        if let Some(size) = self.recv_buffer {
+            socket.set_recv_buffer_size(size as u32)?;
+        }
+        Ok(())
+    }
+
+    /// Terapkan ke satu koneksi yang baru diterima
+    pub fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        let socket = SockRef::from(stream);
+        if self.nodelay {
+            socket.set_tcp_nodelay(true)?;
+        }
+        // Listener dari systemd socket activation tidak lewat `bind`
+        if let Some(size) = self.send_buffer {
+            socket.set_send_buffer_size(size)?;
+        }
+        if let Some(size) = self.recv_buffer {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(idle) = self.keepalive {
+            let mut keepalive = TcpKeepalive::new().with_time(idle);
+            if let Some(interval) = self.keepalive_interval {
+                keepalive = keepalive.with_interval(interval);
+            }
+            #[cfg(unix)]
+            if let Some(retries) = self.keepalive_retries {
+                keepalive = keepalive.with_retries(retries);
+            }
+            socket.set_tcp_keepalive(&keepalive)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hitungan koneksi per IP yang belum mengirim request lengkap (slow-loris guard)
+#[derive(Debug, Default)]
+pub struct HalfOpenTracker {
+    counts: Mutex<HashMap<IpAddr, usize>>,
+}
+
+impl HalfOpenTracker {
+    /// Register a new half-open connection, or `None` if the IP is at its cap
+    pub fn acquire(self: &Arc<Self>, ip: IpAddr, max_per_ip: usize) -> Option<Arc<HalfOpen>> {
+        let mut counts = self.counts.lock();
+        let count = counts.entry(ip).or_insert(0);
+        if max_per_ip > 0 && *count >= max_per_ip {
+            return None;
+        }
+        *count += 1;
+        Some(Arc::new(HalfOpen {
+            tracker: Arc::clone(self),
+            ip,
+            released: AtomicBool::new(false),
+        }))
+    }
+
+    fn release(&self, ip: IpAddr) {
+        let mut counts = self.counts.lock();
+        if let Some(count) = counts.get_mut(&ip) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&ip);
+            }
+        }
+    }
+}
+
+/// One connection that has not yet delivered its first request headers
+#[derive(Debug)]
+pub struct HalfOpen {
+    tracker: Arc<HalfOpenTracker>,
+    ip: IpAddr,
+    released: AtomicBool,
+}
+
+impl HalfOpen {
+    pub fn is_released(&self) -> bool {
+        self.released.load(Ordering::Acquire)
+    }
+
+    /// Mark the connection as established; idempotent
+    pub fn release(&self) {
+        if !self.released.swap(true, Ordering::AcqRel) {
+            self.tracker.release(self.ip);
+        }
+    }
+}
+
+impl Drop for HalfOpen {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Accept loop untuk HTTP biasa maupun native TLS
+///
+/// Replaces `axum::serve` so connection-level limits apply: the TLS handshake
+/// and HTTP request headers (which includes the WebSocket upgrade request)
+/// must complete within `handshake_timeout_secs`, and each IP may only hold
+/// `max_half_open_per_ip` connections that have not got that far. With TLS,
+/// the client identity (if any) is attached to every request as a
+/// `ClientIdentity` extension.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    tls_acceptor: Option<TlsAcceptor>,
+    config: &Config,
+) -> io::Result<()> {
+    let handshake_timeout = Duration::from_secs(config.handshake_timeout_secs);
+    let half_open_tracker = Arc::new(HalfOpenTracker::default());
+
+    loop {
+        let (tcp, remote) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                accept_backoff(e).await;
+                continue;
+            }
+        };
+        if let Err(e) = config.tcp.apply(&tcp) {
+            warn!("Cannot apply TCP options to connection from {}: {}", remote, e);
+        }
+        let Some(half_open) = half_open_tracker.acquire(remote.ip(), config.max_half_open_per_ip)
+        else {
+            warn!("Too many half-open connections from {}, dropping", remote.ip());
+            continue;
+        };
+        let app = app.clone();
+        let tls_acceptor = tls_acceptor.clone();
+
+        tokio::spawn(async move {
+            let Some(acceptor) = tls_acceptor else {
+                serve_connection(tcp, app, remote, handshake_timeout, half_open).await;
+                return;
+            };
+
+            let stream = match tokio::time::timeout(handshake_timeout, acceptor.accept(tcp)).await {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(e)) => {
+                    warn!("TLS handshake with {} failed: {}", remote, e);
+                    return;
+                }
+                Err(_) => {
+                    warn!("TLS handshake with {} timed out", remote);
+                    return;
+                }
+            };
+            let identity = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(tls::identity_from_certs);
+            if let Some(identity) = &identity {
+                info!("TLS client {} authenticated as {:?}", remote, identity.names);
+            }
+
+            let app = app.layer(axum::middleware::map_request(move |mut req: Request| {
+                let identity = identity.clone();
+                async move {
+                    if let Some(identity) = identity {
+                        req.extensions_mut().insert(identity);
+                    }
+                    req
+                }
+            }));
+            serve_connection(stream, app, remote, handshake_timeout, half_open).await;
+        });
+    }
+}
+
+/// Error `accept` tidak pernah menghentikan server, seperti di `axum::serve`
+///
+/// A connection reset before it was accepted only concerns that client. Other
+/// errors (`EMFILE`/`ENFILE` when out of file descriptors) usually clear up
+/// once connections close, so the loop pauses instead of spinning on them.
+async fn accept_backoff(error: io::Error) {
+    if matches!(
+        error.kind(),
+        io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionAborted | io::ErrorKind::ConnectionReset
+    ) {
+        return;
+    }
+    warn!("Failed to accept connection: {}", error);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+}
+
+async fn serve_connection<S>(
+    stream: S,
+    app: Router,
+    remote: SocketAddr,
+    header_timeout: Duration,
+    half_open: Arc<HalfOpen>,
+) where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let request_half_open = Arc::clone(&half_open);
+    let service = ServiceBuilder::new()
+        .map_request(move |req: Request<Incoming>| {
+            // Header request pertama sudah lengkap: koneksi tidak lagi half-open
+            request_half_open.release();
+            req.map(axum::body::Body::new)
+        })
+        .service(app);
+    let mut builder = auto::Builder::new(TokioExecutor::new());
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .header_read_timeout(header_timeout);
+    let conn =
+        builder.serve_connection_with_upgrades(TokioIo::new(stream), TowerToHyperService::new(service));
+    tokio::pin!(conn);
+
+    // header_read_timeout hanya berlaku setelah protokol terdeteksi; client yang tidak
+    // mengirim apa pun sama sekali ditutup di sini
+    let result = tokio::select! {
+        result = conn.as_mut() => result,
+        _ = tokio::time::sleep(header_timeout) => {
+            if !half_open.is_released() {
+                warn!("No request from {} within {:?}, closing", remote, header_timeout);
+                return;
+            }
+            conn.await
+        }
+    };
+    if let Err(e) = result {
+        warn!("Error serving connection from {}: {}", remote, e);
+    }
+    half_open.release();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_half_open_cap_per_ip() {
+        let tracker = Arc::new(HalfOpenTracker::default());
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let first = tracker.acquire(ip, 2).unwrap();
+        let _second = tracker.acquire(ip, 2).unwrap();
+        assert!(tracker.acquire(ip, 2).is_none());
+        // Other IPs are unaffected
+        assert!(tracker.acquire("10.0.0.2".parse().unwrap(), 2).is_some());
+
+        first.release();
+        assert!(tracker.acquire(ip, 2).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_tuning_applies_to_accepted_connections() {
+        let tuning = TcpTuning {
+            nodelay: true,
+            recv_buffer: Some(32 * 1024),
+            keepalive: Some(Duration::from_secs(30)),
+            keepalive_interval: Some(Duration::from_secs(5)),
+            keepalive_retries: Some(3),
+            ..TcpTuning::default()
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (accepted, _) = listener.accept().await.unwrap();
+        tuning.apply(&accepted).unwrap();
+
+        let socket = SockRef::from(&accepted);
+        assert!(socket.tcp_nodelay().unwrap());
+        assert!(socket.keepalive().unwrap());
+        assert!(socket.recv_buffer_size().unwrap() >= 32 * 1024);
+        // Default: opsi OS tidak diubah
+        TcpTuning::default().apply(&client).unwrap();
+        assert!(!SockRef::from(&client).tcp_nodelay().unwrap());
+    }
+}
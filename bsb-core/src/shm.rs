@@ -0,0 +1,438 @@
+use crate::AppState;
+
+/// Mulai listener `SHM_INGEST_SOCKET` bila dikonfigurasi
+pub fn spawn(state: &AppState) {
+    let Some(path) = state.config.shm_ingest_socket.clone() else {
+        return;
+    };
+    #[cfg(target_os = "linux")]
+    {
+        let state = state.clone();
+        crate::supervisor::supervise("shm ingest".to_string(), move || ring::listen(state.clone(), path.clone()));
+    }
+    #[cfg(not(target_os = "linux"))]
+    tracing::warn!("SHM_INGEST_SOCKET {} ignored: shared-memory ingest needs Linux", path);
+}
+
+/// Ingest lewat ring shared-memory (memfd + eventfd) dari producer di host yang sama
+///
+/// A producer connects to the Unix socket and sends a hello naming the stream
+/// together with a sealed memfd holding the ring and an eventfd it signals
+/// after every record (`SCM_RIGHTS`). Each session runs on a blocking thread
+/// that drains the ring, copies every payload out once and publishes it like
+/// an HTTP ingest frame. The session ends when the producer closes the socket.
+#[cfg(target_os = "linux")]
+mod ring {
+    use bsb_proto::shm::{self as layout, ReadStep};
+    use bytes::Bytes;
+    use std::{
+        fs::File,
+        io::{self, Write},
+        os::{
+            fd::{AsRawFd, FromRawFd, OwnedFd},
+            unix::{fs::PermissionsExt, net::UnixStream},
+        },
+        sync::atomic::{AtomicU64, Ordering},
+        time::Duration,
+    };
+    use tracing::{debug, error, info, warn};
+
+    use super::*;
+    use crate::{connections::ConnectionKind, IngestParams};
+
+    /// Producer harus mengirim hello dalam waktu ini setelah connect
+    const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+    /// Ring tetap dikuras sesekali walau producer lupa memberi sinyal eventfd
+    const POLL_MS: i32 = 1000;
+
+    pub async fn listen(state: AppState, path: String) {
+        // Socket sisa proses sebelumnya akan membuat bind gagal
+        let _ = std::fs::remove_file(&path);
+        let listener = match tokio::net::UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to bind SHM_INGEST_SOCKET {}: {}", path, e);
+                return;
+            }
+        };
+        // Siapa pun yang bisa connect bisa publish ke stream mana pun, jadi hanya owner dan group
+        if let Err(e) = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o660)) {
+            warn!("Failed to restrict permissions of {}: {}", path, e);
+        }
+        info!("Shared-memory ingest listening on {}", path);
+        loop {
+            let socket = match listener.accept().await.and_then(|(socket, _)| socket.into_std()) {
+                Ok(socket) => socket,
+                Err(e) => {
+                    warn!("Failed to accept shared-memory producer: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            let state = state.clone();
+            tokio::task::spawn_blocking(move || session(&state, socket));
+        }
+    }
+
+    fn session(state: &AppState, socket: UnixStream) {
+        let opened = socket
+            .set_nonblocking(false)
+            .and_then(|_| socket.set_read_timeout(Some(HELLO_TIMEOUT)))
+            .map_err(|e| e.to_string())
+            .and_then(|_| receive_hello(&socket))
+            .and_then(|(hello, fds)| open(state, &hello, fds));
+        let (stream_id, ring, eventfd) = match opened {
+            Ok(opened) => opened,
+            Err(reason) => {
+                warn!("Rejected shared-memory producer: {}", reason);
+                let _ = (&socket).write_all(&layout::encode_reply(Err(&reason)));
+                return;
+            }
+        };
+        if let Err(e) = (&socket).write_all(&layout::encode_reply(Ok(()))) {
+            debug!("Shared-memory producer for stream {} left before the reply: {}", stream_id, e);
+            return;
+        }
+        info!("Shared-memory producer connected to stream {} ({} byte ring)", stream_id, ring.capacity);
+        match pump(state, &stream_id, &ring, &eventfd, &socket) {
+            Ok(frames) => info!("Shared-memory producer of stream {} closed after {} frames", stream_id, frames),
+            Err(e) => warn!("Shared-memory producer of stream {} dropped: {}", stream_id, e),
+        }
+    }
+
+    /// Terima hello beserta file descriptor yang dikirim bersamanya
+    fn receive_hello(socket: &UnixStream) -> Result<(Vec<u8>, Vec<OwnedFd>), String> {
+        let mut data = vec![0u8; 6 + u16::MAX as usize];
+        let mut control = [0u64; 8];
+        let mut iov = libc::iovec {
+            iov_base: data.as_mut_ptr().cast(),
+            iov_len: data.len(),
+        };
+        // SAFETY: msghdr is plain data; all-zero is a valid value
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = std::mem::size_of_val(&control) as _;
+        // SAFETY: `msg` points at `iov` and `control`, which outlive the call
+        let read = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC) };
+        if read < 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        let mut fds = Vec::new();
+        // SAFETY: the kernel filled `control` up to `msg_controllen`; CMSG_* stay inside it
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+            while !cmsg.is_null() {
+                if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                    let count = ((*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize) / size_of::<libc::c_int>();
+                    let first = libc::CMSG_DATA(cmsg) as *const libc::c_int;
+                    for i in 0..count {
+                        fds.push(OwnedFd::from_raw_fd(first.add(i).read_unaligned()));
+                    }
+                }
+                cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+            }
+        }
+        if msg.msg_flags & libc::MSG_CTRUNC != 0 {
+            return Err("too many file descriptors".to_string());
+        }
+        data.truncate(read as usize);
+        Ok((data, fds))
+    }
+
+    fn open(state: &AppState, hello: &[u8], fds: Vec<OwnedFd>) -> Result<(String, Ring, OwnedFd), String> {
+        let stream_id = layout::decode_hello(hello).map_err(|e| e.to_string())?.to_string();
+        // Ring tidak membawa seq envelope, sama seperti pipe lokal
+        if state.config.replay.protects(&stream_id) {
+            return Err(format!("stream {} only accepts envelope frames (INGEST_REPLAY_STREAMS)", stream_id));
+        }
+        let [memfd, eventfd]: [OwnedFd; 2] = fds
+            .try_into()
+            .map_err(|_| "expected a memfd and an eventfd with the hello".to_string())?;
+        Ok((stream_id, Ring::map(memfd)?, eventfd))
+    }
+
+    /// Ring yang di-mmap dari memfd producer
+    struct Ring {
+        base: *mut u8,
+        len: usize,
+        capacity: u64,
+    }
+
+    impl Ring {
+        fn map(memfd: OwnedFd) -> Result<Ring, String> {
+            // Tanpa seal, producer bisa mengecilkan memfd dan broker mati kena SIGBUS
+            // SAFETY: F_GET_SEALS only reads the seals of a descriptor we own
+            let seals = unsafe { libc::fcntl(memfd.as_raw_fd(), libc::F_GET_SEALS) };
+            if seals < 0 || seals & libc::F_SEAL_SHRINK == 0 {
+                return Err("ring memfd must be sealed with F_SEAL_SHRINK".to_string());
+            }
+            let file = File::from(memfd);
+            let len = file.metadata().map_err(|e| e.to_string())?.len();
+            if len < (layout::HEADER_LEN as u64 + layout::MIN_CAPACITY) || len > usize::MAX as u64 {
+                return Err(format!("ring memfd of {} bytes is too small or too large", len));
+            }
+            let len = len as usize;
+            // SAFETY: a fresh shared mapping of the whole memfd, checked for MAP_FAILED
+            let base = unsafe {
+                libc::mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            if base == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error().to_string());
+            }
+            let mut ring = Ring {
+                base: base.cast(),
+                len,
+                capacity: 0,
+            };
+            let mut header = [0u8; 16];
+            // SAFETY: the mapping is at least HEADER_LEN bytes long
+            unsafe { std::ptr::copy_nonoverlapping(ring.base, header.as_mut_ptr(), header.len()) };
+            ring.capacity = layout::decode_header(&header).map_err(|e| e.to_string())?;
+            if layout::HEADER_LEN as u64 + ring.capacity > len as u64 {
+                return Err(format!("ring capacity {} does not fit a {} byte memfd", ring.capacity, len));
+            }
+            Ok(ring)
+        }
+
+        fn position(&self, offset: usize) -> &AtomicU64 {
+            // SAFETY: offset is WRITE_POS_OFFSET or READ_POS_OFFSET, 8-byte aligned inside the mapping
+            unsafe { &*(self.base.add(offset) as *const AtomicU64) }
+        }
+
+        /// Panjang record di offset area data (ditulis producer, dibaca sekali)
+        fn len_at(&self, offset: usize) -> u32 {
+            // SAFETY: read_step only ever yields offsets below capacity, 8-byte aligned
+            unsafe { std::ptr::read_volatile(self.base.add(layout::HEADER_LEN + offset) as *const u32) }
+        }
+
+        fn copy(&self, payload: std::ops::Range<usize>) -> Bytes {
+            let mut data = vec![0u8; payload.len()];
+            // SAFETY: read_step checked that the range lies inside the data area
+            unsafe {
+                std::ptr::copy_nonoverlapping(self.base.add(layout::HEADER_LEN + payload.start), data.as_mut_ptr(), data.len())
+            };
+            Bytes::from(data)
+        }
+    }
+
+    impl Drop for Ring {
+        fn drop(&mut self) {
+            // SAFETY: `base`/`len` are the mapping created in `map`
+            unsafe { libc::munmap(self.base.cast(), self.len) };
+        }
+    }
+
+    /// Kuras ring setiap ada sinyal sampai producer menutup socket; jumlah frame
+    fn pump(state: &AppState, stream_id: &str, ring: &Ring, eventfd: &OwnedFd, socket: &UnixStream) -> Result<u64, String> {
+        let max_len = state.config.ws_max_message_size;
+        let params = IngestParams::default();
+        let mut read_pos = ring.position(layout::READ_POS_OFFSET).load(Ordering::Acquire);
+        let mut connection = None;
+        let mut frames = 0;
+        let mut closing = false;
+        loop {
+            let write_pos = ring.position(layout::WRITE_POS_OFFSET).load(Ordering::Acquire);
+            while read_pos < write_pos {
+                let len = ring.len_at((read_pos % ring.capacity) as usize);
+                match layout::read_step(read_pos, write_pos, ring.capacity, len).map_err(|e| e.to_string())? {
+                    ReadStep::Skip { next } => read_pos = next,
+                    ReadStep::Record { payload, next } => {
+                        if payload.len() > max_len {
+                            return Err(format!("frame of {} bytes is above WS_MAX_MESSAGE_SIZE", payload.len()));
+                        }
+                        let data = ring.copy(payload);
+                        read_pos = next;
+                        // Ruang dikembalikan ke producer sebelum frame di-fan-out
+                        ring.position(layout::READ_POS_OFFSET).store(read_pos, Ordering::Release);
+                        frames += 1;
+                        match crate::ingest_frame(state, stream_id, &params, data, None) {
+                            Ok(_) => {
+                                if connection.is_none() {
+                                    connection = state.streams.lock().get_mut(stream_id).map(|entry| {
+                                        let registered = entry.connections.register(ConnectionKind::Producer, "shm", Vec::new());
+                                        let seq = entry.last_seq();
+                                        entry.timeline.producer_connected(seq, registered.id, "shm");
+                                        registered
+                                    });
+                                }
+                                if let Some(connection) = &connection {
+                                    connection.frame();
+                                }
+                            }
+                            Err(e) => debug!("Shared-memory frame for stream {} rejected: {}", stream_id, e),
+                        }
+                    }
+                }
+            }
+            ring.position(layout::READ_POS_OFFSET).store(read_pos, Ordering::Release);
+            if closing {
+                return Ok(frames);
+            }
+            let mut fds = [
+                libc::pollfd {
+                    fd: eventfd.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+                libc::pollfd {
+                    fd: socket.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+            ];
+            // SAFETY: `fds` is a valid array of two pollfd
+            if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, POLL_MS) } < 0 {
+                let e = io::Error::last_os_error();
+                if e.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(e.to_string());
+            }
+            if fds[0].revents & libc::POLLIN != 0 {
+                let mut counter = [0u8; 8];
+                // SAFETY: reads at most 8 bytes into `counter`; POLLIN means it will not block
+                unsafe { libc::read(eventfd.as_raw_fd(), counter.as_mut_ptr().cast(), counter.len()) };
+            }
+            // Producer tidak mengirim apa pun setelah hello; socket terbaca berarti ditutup.
+            // Sisa record dikuras sekali lagi sebelum sesi selesai
+            closing = fds[1].revents != 0;
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::config::Config;
+
+        /// Producer minimal seperti `bsb.ShmPublisher`: memfd bersegel, eventfd, hello lewat SCM_RIGHTS
+        struct TestProducer {
+            socket: UnixStream,
+            ring: Ring,
+            eventfd: OwnedFd,
+        }
+
+        impl TestProducer {
+            fn connect(path: &str, stream_id: &str, capacity: u64) -> Result<TestProducer, String> {
+                // SAFETY: plain syscalls on descriptors created here; each result is checked
+                let (memfd, eventfd) = unsafe {
+                    let memfd = libc::memfd_create(c"bsb-ring".as_ptr(), libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING);
+                    assert!(memfd >= 0);
+                    let memfd = OwnedFd::from_raw_fd(memfd);
+                    assert_eq!(libc::ftruncate(memfd.as_raw_fd(), (layout::HEADER_LEN as u64 + capacity) as _), 0);
+                    let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_SEAL;
+                    assert_eq!(libc::fcntl(memfd.as_raw_fd(), libc::F_ADD_SEALS, seals), 0);
+                    let header = layout::encode_header(capacity);
+                    assert_eq!(libc::pwrite(memfd.as_raw_fd(), header.as_ptr().cast(), header.len(), 0), 16);
+                    let eventfd = libc::eventfd(0, libc::EFD_CLOEXEC);
+                    assert!(eventfd >= 0);
+                    (memfd, OwnedFd::from_raw_fd(eventfd))
+                };
+                let socket = UnixStream::connect(path).unwrap();
+                let mut hello = layout::encode_hello(stream_id).unwrap();
+                let mut iov = libc::iovec {
+                    iov_base: hello.as_mut_ptr().cast(),
+                    iov_len: hello.len(),
+                };
+                let mut control = [0u64; 8];
+                // SAFETY: one SCM_RIGHTS message with two fds fits in `control`
+                unsafe {
+                    let mut msg: libc::msghdr = std::mem::zeroed();
+                    msg.msg_iov = &mut iov;
+                    msg.msg_iovlen = 1;
+                    msg.msg_control = control.as_mut_ptr().cast();
+                    msg.msg_controllen = libc::CMSG_SPACE(2 * size_of::<libc::c_int>() as u32) as _;
+                    let cmsg = libc::CMSG_FIRSTHDR(&msg);
+                    (*cmsg).cmsg_level = libc::SOL_SOCKET;
+                    (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+                    (*cmsg).cmsg_len = libc::CMSG_LEN(2 * size_of::<libc::c_int>() as u32) as _;
+                    let fds = [memfd.as_raw_fd(), eventfd.as_raw_fd()];
+                    std::ptr::copy_nonoverlapping(fds.as_ptr(), libc::CMSG_DATA(cmsg) as *mut libc::c_int, 2);
+                    assert!(libc::sendmsg(socket.as_raw_fd(), &msg, 0) > 0);
+                }
+                let mut reply = [0u8; 256];
+                let read = io::Read::read(&mut &socket, &mut reply).unwrap();
+                layout::decode_reply(&reply[..read])?;
+                Ok(TestProducer {
+                    socket,
+                    ring: Ring::map(memfd)?,
+                    eventfd,
+                })
+            }
+
+            fn send(&self, payload: &[u8]) -> Result<(), layout::ShmError> {
+                let write_pos = self.ring.position(layout::WRITE_POS_OFFSET).load(Ordering::Relaxed);
+                let read_pos = self.ring.position(layout::READ_POS_OFFSET).load(Ordering::Acquire);
+                let plan = layout::plan_write(write_pos, read_pos, self.ring.capacity, payload.len())?;
+                // SAFETY: plan_write keeps padding and record inside the data area
+                unsafe {
+                    let data = self.ring.base.add(layout::HEADER_LEN);
+                    if let Some(offset) = plan.padding {
+                        (data.add(offset) as *mut u32).write_volatile(layout::PADDING);
+                    }
+                    (data.add(plan.offset) as *mut u32).write_volatile(payload.len() as u32);
+                    let start = data.add(plan.offset + layout::RECORD_HEADER_LEN);
+                    std::ptr::copy_nonoverlapping(payload.as_ptr(), start, payload.len());
+                    self.ring.position(layout::WRITE_POS_OFFSET).store(plan.next, Ordering::Release);
+                    libc::write(self.eventfd.as_raw_fd(), 1u64.to_ne_bytes().as_ptr().cast(), 8);
+                }
+                Ok(())
+            }
+        }
+
+        #[tokio::test]
+        async fn test_ring_frames_are_published_in_order() {
+            let path = std::env::temp_dir().join(format!("bsb-shm-test-{}.sock", std::process::id()));
+            let path = path.to_str().unwrap().to_string();
+            let state = AppState::new(Config {
+                shm_ingest_socket: Some(path.clone()),
+                replay: crate::replay::ReplayConfig {
+                    streams: vec!["signed-*".to_string()],
+                    ..Default::default()
+                },
+                ..Config::default()
+            });
+            let mut rx = state.with_stream("cam1", |entry| entry.tx.subscribe());
+            spawn(&state);
+            for _ in 0..100 {
+                if std::path::Path::new(&path).exists() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+
+            let producer = tokio::task::spawn_blocking({
+                let path = path.clone();
+                move || {
+                    let rejected = TestProducer::connect(&path, "signed-cam", layout::MIN_CAPACITY).err();
+                    assert!(rejected.unwrap().contains("envelope"));
+                    let producer = TestProducer::connect(&path, "cam1", layout::MIN_CAPACITY).unwrap();
+                    // Frame 40 KiB mengisi ring lebih dari sekali, jadi record ikut melompati ujung area data
+                    let mut sent = 0u8;
+                    while sent < 6 {
+                        match producer.send(&vec![sent; 40 << 10]) {
+                            Ok(()) => sent += 1,
+                            Err(layout::ShmError::Full) => std::thread::sleep(Duration::from_millis(5)),
+                            Err(e) => panic!("{}", e),
+                        }
+                    }
+                    drop(producer.socket);
+                }
+            });
+            for i in 0..6u8 {
+                let frame = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+                assert_eq!((frame.seq, frame.data.len(), frame.data[0]), (i as u64 + 1, 40 << 10, i));
+            }
+            producer.await.unwrap();
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use axum::{
+    extract::{Path as AxumPath, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::{checksum::crc32c, error::BrokerError, headers, playback, AppState};
+
+/// Query untuk GET /snapshot/:stream_id.jpg
+#[derive(Debug, Default, Deserialize)]
+pub struct SnapshotParams {
+    /// Playback token for browsers that cannot set `Authorization`
+    token: Option<String>,
+}
+
+/// ETag frame: sequence plus CRC32C payload, tetap benar saat stream dibuat ulang dari seq 1
+pub fn etag(seq: u64, data: &[u8]) -> String {
+    format!("\"{}-{:08x}\"", seq, crc32c(data))
+}
+
+/// `If-None-Match` cocok dengan `etag` (daftar dipisah koma, `*`, atau weak `W/"..."`)
+fn not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// Handler untuk GET /snapshot/:stream_id.jpg
+/// Newest JPEG frame of an MJPEG stream, for dashboards that poll instead of subscribing
+///
+/// The ETag changes with every new frame only, so a poller sending
+/// `If-None-Match` gets `304 Not Modified` without a body while the camera
+/// stalls. Access is checked like `/ws/:stream_id`.
+pub async fn snapshot_handler(
+    AxumPath(file): AxumPath<String>,
+    Query(params): Query<SnapshotParams>,
+    State(state): State<AppState>,
+    request_headers: HeaderMap,
+) -> Response {
+    let Some(stream_id) = file.strip_suffix(".jpg") else {
+        return BrokerError::not_found("snapshot", file).into_response();
+    };
+    if let Err(e) = playback::authorize(&state, stream_id, &request_headers, params.token.as_deref()).await {
+        return e.into_response();
+    }
+    let frame = match state.streams.lock().get(stream_id) {
+        Some(entry) => entry.keyframe.clone().filter(|frame| frame.data.starts_with(&[0xFF, 0xD8])),
+        None => return BrokerError::StreamNotFound(stream_id.to_string()).into_response(),
+    };
+    let Some(frame) = frame else {
+        return BrokerError::not_found("snapshot", stream_id).into_response();
+    };
+
+    let etag = etag(frame.seq, &frame.data);
+    let validators = [
+        (header::ETAG, etag.clone()),
+        (header::CACHE_CONTROL, "no-cache".to_string()),
+    ];
+    let mut response = if not_modified(&request_headers, &etag) {
+        (StatusCode::NOT_MODIFIED, validators).into_response()
+    } else {
+        let mut response = (validators, frame.data).into_response();
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, header::HeaderValue::from_static("image/jpeg"));
+        response
+    };
+    headers::apply(&state, stream_id, &mut response);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, testing::TestBroker};
+    use axum::{body::Body, http::Request};
+    use tower::util::ServiceExt;
+
+    #[tokio::test]
+    async fn test_snapshot_etag_answers_not_modified_until_next_frame() {
+        let broker = TestBroker::start(Config::default()).await;
+        broker.state.with_stream("lobby", |_| ());
+        broker.post_frame("lobby", "\u{7f}not a jpeg").await;
+        let get = |uri: &str, etag: Option<&str>| {
+            let mut request = Request::get(uri);
+            if let Some(etag) = etag {
+                request = request.header(header::IF_NONE_MATCH, etag);
+            }
+            crate::build_router(broker.state.clone()).oneshot(request.body(Body::empty()).unwrap())
+        };
+        // Stream tanpa frame JPEG belum punya snapshot
+        assert_eq!(get("/snapshot/lobby.jpg", None).await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        let jpeg = |marker: u8| bytes::Bytes::from(vec![0xFF, 0xD8, 0xFF, 0xE0, marker, 0xFF, 0xD9]);
+        broker.state.with_stream("lobby", |entry| {
+            let _ = entry.publish(jpeg(1));
+        });
+        let response = get("/snapshot/lobby.jpg", None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/jpeg");
+        let first = response.headers()[header::ETAG].to_str().unwrap().to_string();
+        assert_eq!(first, etag(2, &jpeg(1)));
+
+        // Kamera macet: polling berikutnya 304 tanpa body
+        let response = get("/snapshot/lobby.jpg", Some(&format!("W/{}, \"other\"", first))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap().is_empty());
+
+        broker.state.with_stream("lobby", |entry| {
+            let _ = entry.publish(jpeg(2));
+        });
+        let response = get("/snapshot/lobby.jpg", Some(&first)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[header::ETAG].to_str().unwrap(), first);
+        assert_eq!(get("/snapshot/lobby.png", None).await.unwrap().status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,297 @@
+use serde::Serialize;
+
+use crate::{audio::opus_packet_duration_us, mux::glob_match};
+
+/// Frame pertama yang diperiksa sebelum format stream ditetapkan
+pub const SNIFF_FRAMES: u32 = 4;
+
+/// Ukuran packet MPEG-TS
+const TS_PACKET_LEN: usize = 188;
+
+/// Format payload stream menurut isi frame-nya
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadFormat {
+    /// Not classified yet, or the first frames did not agree on one format
+    #[default]
+    Unknown,
+    /// One JPEG image per frame
+    Mjpeg,
+    /// One WebP image per frame
+    Webp,
+    /// H.264 Annex-B access units (start-code delimited NAL units)
+    H264,
+    /// 188-byte MPEG transport stream packets
+    Mpegts,
+    /// Fragmented MP4 (init segment, then `moof`/`mdat` fragments)
+    Fmp4,
+    /// One Opus packet per frame
+    Opus,
+}
+
+impl PayloadFormat {
+    /// Klasifikasi satu frame dari magic bytes-nya
+    pub fn sniff(data: &[u8]) -> Self {
+        if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            PayloadFormat::Mjpeg
+        } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+            PayloadFormat::Webp
+        } else if is_mpegts(data) {
+            PayloadFormat::Mpegts
+        } else if is_fmp4(data) {
+            PayloadFormat::Fmp4
+        } else if nal_units(data).next().is_some_and(|nal| nal & 0x80 == 0 && (1..=23).contains(&(nal & 0x1F))) {
+            PayloadFormat::H264
+        } else if data.len() <= 1275 && std::str::from_utf8(data).is_err() && opus_packet_duration_us(data).is_ok() {
+            // Payload teks (mis. JSON) tidak pernah dianggap Opus, walau TOC byte-nya lolos
+            PayloadFormat::Opus
+        } else {
+            PayloadFormat::Unknown
+        }
+    }
+
+    /// Frame bisa di-decode tanpa frame sebelumnya (untuk bootstrap subscriber terlambat)
+    ///
+    /// Only H.264 (an IDR NAL unit) and MPEG-TS (a packet with the random
+    /// access indicator) distinguish keyframes; every frame of the other
+    /// formats decodes on its own or, for fMP4, starts a fragment.
+    pub fn is_keyframe(self, data: &[u8]) -> bool {
+        match self {
+            PayloadFormat::H264 => nal_units(data).any(|nal| nal & 0x1F == 5),
+            PayloadFormat::Mpegts => data.chunks(TS_PACKET_LEN).any(|packet| {
+                // Adaptation field ada dan random_access_indicator diset
+                packet.len() > 5 && packet[3] & 0x20 != 0 && packet[4] > 0 && packet[5] & 0x40 != 0
+            }),
+            _ => true,
+        }
+    }
+}
+
+impl std::fmt::Display for PayloadFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PayloadFormat::Unknown => "unknown",
+            PayloadFormat::Mjpeg => "mjpeg",
+            PayloadFormat::Webp => "webp",
+            PayloadFormat::H264 => "h264",
+            PayloadFormat::Mpegts => "mpegts",
+            PayloadFormat::Fmp4 => "fmp4",
+            PayloadFormat::Opus => "opus",
+        };
+        f.write_str(name)
+    }
+}
+
+impl std::str::FromStr for PayloadFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "unknown" => Ok(PayloadFormat::Unknown),
+            "mjpeg" => Ok(PayloadFormat::Mjpeg),
+            "webp" => Ok(PayloadFormat::Webp),
+            "h264" => Ok(PayloadFormat::H264),
+            "mpegts" => Ok(PayloadFormat::Mpegts),
+            "fmp4" => Ok(PayloadFormat::Fmp4),
+            "opus" => Ok(PayloadFormat::Opus),
+            other => Err(format!(
+                "unknown payload format {:?} (use mjpeg, webp, h264, mpegts, fmp4, opus or unknown)",
+                other
+            )),
+        }
+    }
+}
+
+/// `STREAM_FORMATS`: pattern glob ke format payload yang diizinkan, mis. `archive/*=mpegts,cam-*=mjpeg|webp`
+///
+/// The first matching pattern decides; streams that match none accept any
+/// payload. Every frame is sniffed on its own, so a misconfigured encoder is
+/// rejected from its first frame rather than after detection settled.
+#[derive(Debug, Clone, Default)]
+pub struct FormatPolicy(Vec<(String, Vec<PayloadFormat>)>);
+
+impl FormatPolicy {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut patterns = Vec::new();
+        for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let Some((pattern, formats)) = entry.split_once('=') else {
+                return Err(format!("STREAM_FORMATS entry {:?} is not pattern=format", entry));
+            };
+            let formats = formats
+                .split('|')
+                .map(str::parse::<PayloadFormat>)
+                .collect::<Result<Vec<_>, _>>()?;
+            patterns.push((pattern.trim().to_string(), formats));
+        }
+        Ok(Self(patterns))
+    }
+
+    /// Format yang diizinkan untuk stream; `None` = tanpa batasan
+    pub fn for_stream(&self, stream_id: &str) -> Option<&[PayloadFormat]> {
+        self.0
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, stream_id))
+            .map(|(_, formats)| formats.as_slice())
+    }
+}
+
+fn is_mpegts(data: &[u8]) -> bool {
+    data.len() >= TS_PACKET_LEN
+        && data.len().is_multiple_of(TS_PACKET_LEN)
+        && data.chunks(TS_PACKET_LEN).all(|packet| packet[0] == 0x47)
+}
+
+fn is_fmp4(data: &[u8]) -> bool {
+    let Some(size) = data.get(0..4).map(|size| u32::from_be_bytes(size.try_into().expect("4 bytes"))) else {
+        return false;
+    };
+    let boxes: [&[u8]; 6] = [b"ftyp", b"styp", b"moof", b"sidx", b"emsg", b"prft"];
+    size >= 8 && data.get(4..8).is_some_and(|kind| boxes.contains(&kind))
+}
+
+/// Header byte setiap NAL unit Annex-B (setelah start code `00 00 01`)
+fn nal_units(data: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    let starts_with_code = data.starts_with(&[0, 0, 1]) || data.starts_with(&[0, 0, 0, 1]);
+    data.windows(4)
+        .enumerate()
+        .filter(move |(_, window)| starts_with_code && window[0..3] == [0, 0, 1])
+        .map(|(_, window)| window[3])
+}
+
+/// Deteksi format dari [`SNIFF_FRAMES`] frame pertama stream
+///
+/// The format is settled once that many frames were seen: the format they all
+/// agree on, or `unknown` if they disagree. It does not change afterwards, so
+/// features keyed on it do not flip mid-stream.
+#[derive(Debug, Default)]
+pub struct FormatDetector {
+    seen: u32,
+    /// Format of every frame so far, `None` once two frames disagreed
+    candidate: Option<PayloadFormat>,
+    format: PayloadFormat,
+}
+
+impl FormatDetector {
+    /// Periksa satu frame; `true` bila frame ini menetapkan format
+    pub fn observe(&mut self, data: &[u8]) -> bool {
+        if self.is_settled() {
+            return false;
+        }
+        let sniffed = PayloadFormat::sniff(data);
+        self.candidate = match (self.seen, self.candidate) {
+            (0, _) => Some(sniffed),
+            (_, Some(candidate)) if candidate == sniffed => Some(candidate),
+            _ => None,
+        };
+        self.seen += 1;
+        if !self.is_settled() {
+            return false;
+        }
+        self.format = self.candidate.unwrap_or_default();
+        true
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.seen >= SNIFF_FRAMES
+    }
+
+    /// Format yang sudah ditetapkan; `Unknown` selama deteksi berjalan
+    pub fn format(&self) -> PayloadFormat {
+        self.format
+    }
+
+    /// Format sementara selama deteksi, dipakai untuk keyframe sebelum format ditetapkan
+    pub fn likely(&self) -> PayloadFormat {
+        if self.is_settled() {
+            self.format
+        } else {
+            self.candidate.unwrap_or_default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_formats_and_keyframes() {
+        let idr = [0, 0, 0, 1, 0x67, 0x42, 0, 0, 1, 0x68, 0xCE, 0, 0, 1, 0x65, 0x88];
+        let p_frame = [0, 0, 0, 1, 0x41, 0x9A, 0x02];
+        assert_eq!(PayloadFormat::sniff(&idr), PayloadFormat::H264);
+        assert!(PayloadFormat::H264.is_keyframe(&idr));
+        assert!(!PayloadFormat::H264.is_keyframe(&p_frame));
+
+        let mut ts = vec![0u8; TS_PACKET_LEN * 2];
+        ts[0] = 0x47;
+        ts[TS_PACKET_LEN] = 0x47;
+        assert_eq!(PayloadFormat::sniff(&ts), PayloadFormat::Mpegts);
+        assert!(!PayloadFormat::Mpegts.is_keyframe(&ts));
+        ts[3] = 0x30;
+        ts[4] = 7;
+        ts[5] = 0x40;
+        assert!(PayloadFormat::Mpegts.is_keyframe(&ts));
+
+        assert_eq!(PayloadFormat::sniff(b"\xFF\xD8\xFF\xE0jfif"), PayloadFormat::Mjpeg);
+        assert_eq!(PayloadFormat::sniff(b"RIFF\x10\0\0\0WEBPVP8 "), PayloadFormat::Webp);
+        assert_eq!(PayloadFormat::sniff(b"\0\0\0\x18ftypiso5"), PayloadFormat::Fmp4);
+        assert_eq!(PayloadFormat::sniff(b"\0\0\0\x10moof\0\0\0\x08mfhd"), PayloadFormat::Fmp4);
+        assert_eq!(PayloadFormat::sniff(&[0xFC, 0xFF, 0xFE, 0x91]), PayloadFormat::Opus);
+        assert_eq!(PayloadFormat::sniff(b"frame-1"), PayloadFormat::Unknown);
+
+        // Init segment lalu fragmen: tetap fMP4
+        let mut detector = FormatDetector::default();
+        detector.observe(b"\0\0\0\x18ftypiso5");
+        assert_eq!(detector.likely(), PayloadFormat::Fmp4);
+        assert_eq!(detector.format(), PayloadFormat::Unknown);
+        for _ in 1..SNIFF_FRAMES {
+            detector.observe(b"\0\0\0\x10moof\0\0\0\x08mfhd");
+        }
+        assert_eq!(detector.format(), PayloadFormat::Fmp4);
+
+        // Frame yang tidak sepakat: unknown, dan tidak berubah lagi
+        let mut detector = FormatDetector::default();
+        detector.observe(b"\xFF\xD8\xFF\xE0");
+        detector.observe(&idr);
+        for _ in 2..SNIFF_FRAMES + 2 {
+            detector.observe(b"\xFF\xD8\xFF\xE0");
+        }
+        assert_eq!(detector.format(), PayloadFormat::Unknown);
+    }
+
+    #[tokio::test]
+    async fn test_format_policy_rejects_unexpected_payloads() {
+        use crate::{config::Config, testing::TestBroker};
+        use axum::http::StatusCode;
+        use std::{sync::atomic::Ordering, time::Duration};
+        use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+
+        assert!(FormatPolicy::parse("archive-*").is_err());
+        assert!(FormatPolicy::parse("archive-*=hevc").is_err());
+        let policy = FormatPolicy::parse("archive-*=mpegts, cam-*=mjpeg|webp").unwrap();
+        assert_eq!(policy.for_stream("cam-7"), Some(&[PayloadFormat::Mjpeg, PayloadFormat::Webp][..]));
+        assert_eq!(policy.for_stream("voice-1"), None);
+
+        let broker = TestBroker::start(Config {
+            stream_formats: policy,
+            ..Config::default()
+        })
+        .await;
+        let _viewer = broker.subscriber("archive-1").await;
+        let mut ts = vec![0u8; TS_PACKET_LEN];
+        ts[0] = 0x47;
+        assert_eq!(broker.post_frame("archive-1", ts).await, StatusCode::OK);
+        assert_eq!(
+            broker.post_frame("archive-1", &b"\xFF\xD8\xFF\xE0jfif"[..]).await,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        // Producer WebSocket ditutup dengan 1003 pada frame pertama yang salah format
+        let mut producer = broker.producer("archive-1").await;
+        producer.send_frame(b"frame-1").await;
+        let close = producer.expect_close(Duration::from_secs(2)).await.unwrap();
+        assert_eq!(close.code, CloseCode::Unsupported);
+        let counters = broker.state.with_stream("archive-1", |entry| entry.counters.clone());
+        assert_eq!(counters.format_rejections.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,402 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::{Buf, Bytes, BytesMut};
+use futures_util::StreamExt;
+use http_body_util::BodyExt;
+use serde::Deserialize;
+use std::{collections::BTreeMap, time::Duration};
+use tokio::io::AsyncReadExt;
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message as WsMessage};
+use tracing::{info, warn};
+
+use crate::{
+    audio::StreamType,
+    headers,
+    labels::Labels,
+    motion::MotionConfig,
+    outbound::{self, HttpTarget},
+    preroll::{Preroll, PrerollConfig},
+    supervisor,
+    uplink::UplinkConfig,
+    watermark::{self, Watermark, WatermarkConfig},
+    AppState,
+};
+
+/// Kredensial untuk source yang dilindungi
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SourceAuth {
+    Bearer { token: String },
+    Basic { username: String, password: String },
+}
+
+impl SourceAuth {
+    fn header_value(&self) -> String {
+        match self {
+            SourceAuth::Bearer { token } => format!("Bearer {}", token),
+            SourceAuth::Basic { username, password } => {
+                format!("Basic {}", STANDARD.encode(format!("{}:{}", username, password)))
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for SourceAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceAuth::Bearer { .. } => f.write_str("Bearer(..)"),
+            SourceAuth::Basic { username, .. } => write!(f, "Basic({}, ..)", username),
+        }
+    }
+}
+
+/// Stream yang dideklarasikan di `STATIC_STREAMS_FILE`, dibuat dan di-pull saat boot
+#[derive(Debug, Clone, Deserialize)]
+pub struct StaticStream {
+    pub id: String,
+    /// `ws://` (binary message per frame), `http://` (MJPEG `multipart/x-mixed-replace`)
+    /// or `tcp://host:port` (`[u32 len][payload]` per frame, big-endian);
+    /// without a source the stream is only created and waits for producers
+    pub source: Option<String>,
+    pub auth: Option<SourceAuth>,
+    /// Record every frame to `RECORDINGS_DIR`
+    #[serde(default)]
+    pub record: bool,
+    #[serde(default)]
+    pub labels: Labels,
+    /// Overlay stamped on every (MJPEG) frame before broadcast
+    pub watermark: Option<WatermarkConfig>,
+    /// Payload (or hook) sent to every new subscriber before live frames
+    pub preroll: Option<PrerollConfig>,
+    /// Extra headers on HTTP playback responses (pull, bootstrap, downloads), e.g. `Cache-Control`
+    #[serde(default)]
+    pub response_headers: BTreeMap<String, String>,
+    /// Upstream brokers the stream is replicated to, with failover
+    pub uplink: Option<UplinkConfig>,
+    /// Motion detection on the (MJPEG) frames, optionally recording while motion lasts
+    pub motion: Option<MotionConfig>,
+    /// `opus`/`pcm` membuat stream dengan ukuran buffer audio
+    #[serde(default, rename = "type")]
+    pub stream_type: StreamType,
+}
+
+/// Baca dan validasi file JSON berisi array stream statis
+pub fn load(path: &str) -> Result<Vec<StaticStream>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read STATIC_STREAMS_FILE {}: {}", path, e))?;
+    parse(&contents).map_err(|e| format!("Invalid STATIC_STREAMS_FILE {}: {}", path, e))
+}
+
+pub fn parse(contents: &str) -> Result<Vec<StaticStream>, String> {
+    let streams: Vec<StaticStream> = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+    for (i, stream) in streams.iter().enumerate() {
+        if stream.id.is_empty() {
+            return Err(format!("stream #{} has an empty id", i));
+        }
+        if streams[..i].iter().any(|other| other.id == stream.id) {
+            return Err(format!("stream {} is declared twice", stream.id));
+        }
+        if let Some(watermark) = &stream.watermark {
+            watermark
+                .validate()
+                .map_err(|e| format!("watermark of stream {}: {}", stream.id, e))?;
+        }
+        if let Some(preroll) = &stream.preroll {
+            preroll
+                .validate()
+                .map_err(|e| format!("preroll of stream {}: {}", stream.id, e))?;
+        }
+        headers::compile(&stream.response_headers)
+            .map_err(|e| format!("response_headers of stream {}: {}", stream.id, e))?;
+        if let Some(uplink) = &stream.uplink {
+            uplink
+                .validate()
+                .map_err(|e| format!("uplink of stream {}: {}", stream.id, e))?;
+        }
+        if let Some(motion) = &stream.motion {
+            motion
+                .validate()
+                .map_err(|e| format!("motion of stream {}: {}", stream.id, e))?;
+        }
+        if let Some(source) = &stream.source {
+            let supported = ["ws://", "http://", "tcp://"];
+            if !supported.iter().any(|scheme| source.starts_with(scheme)) {
+                return Err(format!(
+                    "unsupported source {:?} for stream {} (use ws://, http:// or tcp://)",
+                    source, stream.id
+                ));
+            }
+        }
+    }
+    Ok(streams)
+}
+
+/// Buat semua stream statis, mulai recorder dan pull source-nya
+pub fn spawn_all(state: &AppState) {
+    for stream in &state.config.static_streams {
+        let labels = stream.labels.clone();
+        state.with_stream(&stream.id, |entry| entry.labels.extend(labels));
+        if let Some(config) = &stream.watermark {
+            match Watermark::compile(config.clone()) {
+                Ok(compiled) => state.watermarks.set(&stream.id, compiled),
+                Err(e) => warn!("Watermark of static stream {} not applied: {}", stream.id, e),
+            }
+        }
+        if let Some(config) = &stream.preroll {
+            match Preroll::compile(config.clone()) {
+                Ok(compiled) => state.prerolls.set(&stream.id, compiled),
+                Err(e) => warn!("Pre-roll of static stream {} not applied: {}", stream.id, e),
+            }
+        }
+        if !stream.response_headers.is_empty() {
+            match headers::compile(&stream.response_headers) {
+                Ok(compiled) => state.stream_headers.set(&stream.id, compiled),
+                Err(e) => warn!("Response headers of static stream {} not applied: {}", stream.id, e),
+            }
+        }
+        if let Some(config) = &stream.uplink {
+            info!("Replicating static stream {} to {:?}", stream.id, config.destinations);
+            state.uplinks.start(state, &stream.id, config.clone());
+        }
+        if let Some(config) = &stream.motion {
+            info!("Detecting motion on static stream {}", stream.id);
+            state.motion.start(state, &stream.id, config.clone());
+        }
+        #[cfg(feature = "recording")]
+        if stream.record {
+            let (state, stream_id) = (state.clone(), stream.id.clone());
+            // fsync segmen berjalan di runtime disk bila `RUNTIME_DISK_THREADS` diset
+            supervisor::supervise_on(&crate::runtime::disk(), format!("recorder {}", stream_id), move || {
+                crate::recorder::run_recorder(state.clone(), stream_id.clone())
+            });
+        }
+        #[cfg(not(feature = "recording"))]
+        if stream.record {
+            warn!("Static stream {} is not recorded: built without the `recording` feature", stream.id);
+        }
+        if let Some(source) = &stream.source {
+            info!("Pulling static stream {} from {}", stream.id, source);
+            let (state, stream) = (state.clone(), stream.clone());
+            supervisor::supervise(format!("source {}", stream.id), move || {
+                run_source(state.clone(), stream.clone())
+            });
+        }
+    }
+}
+
+/// Stream yang sudah direkam terus (`record: true`); rekaman gerakan/trigger tidak menulisnya dua kali
+pub fn recorded_always(state: &AppState, stream_id: &str) -> bool {
+    cfg!(feature = "recording")
+        && state
+            .config
+            .static_streams
+            .iter()
+            .any(|stream| stream.id == stream_id && stream.record)
+}
+
+/// Pull source selamanya; reconnect dengan backoff saat putus
+async fn run_source(state: AppState, stream: StaticStream) {
+    let url = stream.source.clone().unwrap_or_default();
+    let auth = stream.auth.as_ref().map(SourceAuth::header_value);
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        let publish = |data: Bytes| {
+            if let Some(data) = watermark::apply(&state, &stream.id, data, None) {
+                let _ = state.with_stream(&stream.id, |entry| entry.publish(data));
+            }
+        };
+        let result = if url.starts_with("ws://") {
+            pull_ws(&url, auth.as_deref(), publish).await
+        } else if url.starts_with("http://") {
+            pull_mjpeg(&url, auth.as_deref(), publish).await
+        } else {
+            pull_tcp(&url, publish).await
+        };
+        match result {
+            Ok(frames) => {
+                info!("Source {} for stream {} ended after {} frames", url, stream.id, frames);
+                if frames > 0 {
+                    backoff = Duration::from_secs(1);
+                }
+            }
+            Err(e) => warn!("Source {} for stream {} failed: {}", url, stream.id, e),
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(Duration::from_secs(30));
+    }
+}
+
+async fn pull_ws(url: &str, auth: Option<&str>, publish: impl Fn(Bytes)) -> Result<u64, String> {
+    let mut request = url.into_client_request().map_err(|e| e.to_string())?;
+    if let Some(auth) = auth {
+        let value = auth
+            .parse()
+            .map_err(|_| "credentials are not a valid header value".to_string())?;
+        request.headers_mut().insert("authorization", value);
+    }
+    let (mut socket, _) = outbound::connect_ws(request, None).await?;
+    let mut frames = 0;
+    while let Some(msg) = socket.next().await {
+        match msg.map_err(|e| e.to_string())? {
+            WsMessage::Binary(data) => {
+                frames += 1;
+                publish(Bytes::from(data));
+            }
+            WsMessage::Close(_) => break,
+            _ => {}
+        }
+    }
+    Ok(frames)
+}
+
+async fn pull_tcp(url: &str, publish: impl Fn(Bytes)) -> Result<u64, String> {
+    let (host, port) = outbound::split_host_port(url.trim_start_matches("tcp://").trim_end_matches('/'))?;
+    let mut stream = outbound::connect(host, port).await.map_err(|e| e.to_string())?;
+    let mut frames = 0;
+    loop {
+        let len = match stream.read_u32().await {
+            Ok(len) => len as usize,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(frames),
+            Err(e) => return Err(e.to_string()),
+        };
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload).await.map_err(|e| e.to_string())?;
+        frames += 1;
+        publish(Bytes::from(payload));
+    }
+}
+
+async fn pull_mjpeg(url: &str, auth: Option<&str>, publish: impl Fn(Bytes)) -> Result<u64, String> {
+    let mut target = HttpTarget::parse(url)?;
+    let headers: Vec<_> = auth.map(|auth| ("authorization", auth.to_string())).into_iter().collect();
+    let response = target.get(&headers).await?;
+    if !response.status().is_success() {
+        return Err(format!("source answered {}", response.status()));
+    }
+    let boundary = response
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .and_then(multipart_boundary)
+        .ok_or("source is not multipart/x-mixed-replace")?;
+
+    let mut parser = MultipartParser::new(&boundary);
+    let mut body = response.into_body();
+    let mut frames = 0;
+    while let Some(chunk) = body.frame().await {
+        let chunk = chunk.map_err(|e| e.to_string())?;
+        let Ok(data) = chunk.into_data() else {
+            continue;
+        };
+        parser.push(&data);
+        while let Some(part) = parser.next_part() {
+            frames += 1;
+            publish(part);
+        }
+    }
+    Ok(frames)
+}
+
+/// Boundary dari `Content-Type: multipart/x-mixed-replace; boundary=...`
+fn multipart_boundary(content_type: &str) -> Option<String> {
+    let (mime, params) = content_type.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/x-mixed-replace") {
+        return None;
+    }
+    params.split(';').find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"').trim_start_matches("--").to_string())
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Pisahkan body `multipart/x-mixed-replace` menjadi part (satu JPEG per part)
+struct MultipartParser {
+    delimiter: Vec<u8>,
+    buf: BytesMut,
+}
+
+impl MultipartParser {
+    fn new(boundary: &str) -> Self {
+        Self {
+            delimiter: format!("--{}", boundary).into_bytes(),
+            buf: BytesMut::new(),
+        }
+    }
+
+    fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Part lengkap berikutnya; memakai `Content-Length` bila ada, selain itu boundary berikutnya
+    fn next_part(&mut self) -> Option<Bytes> {
+        let start = find(&self.buf, &self.delimiter)?;
+        let headers_start = start + self.delimiter.len();
+        let headers_len = find(&self.buf[headers_start..], b"\r\n\r\n")?;
+        let body_start = headers_start + headers_len + 4;
+        let headers = String::from_utf8_lossy(&self.buf[headers_start..body_start]).to_string();
+        let content_length = headers.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("content-length")
+                .then(|| value.trim().parse::<usize>().ok())?
+        });
+
+        let body_len = match content_length {
+            Some(len) if self.buf.len() >= body_start + len => len,
+            Some(_) => return None,
+            None => {
+                let next = find(&self.buf[body_start..], &self.delimiter)?;
+                // CRLF sebelum boundary berikutnya bukan bagian dari payload
+                let body = &self.buf[body_start..body_start + next];
+                next - if body.ends_with(b"\r\n") { 2 } else { 0 }
+            }
+        };
+        self.buf.advance(body_start);
+        Some(self.buf.split_to(body_len).freeze())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_static_streams_and_multipart() {
+        let streams = parse(
+            r#"[
+                {"id": "cam1", "source": "http://10.0.0.5/mjpg", "record": true,
+                 "auth": {"username": "admin", "password": "secret"}},
+                {"id": "site-a/cam2", "source": "tcp://10.0.0.6:9000", "labels": {"site": "a"}},
+                {"id": "lobby"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(streams.len(), 3);
+        assert!(streams[0].record && !streams[1].record);
+        assert_eq!(
+            streams[0].auth.as_ref().unwrap().header_value(),
+            "Basic YWRtaW46c2VjcmV0"
+        );
+        assert!(!format!("{:?}", streams[0]).contains("secret"));
+        assert!(parse(r#"[{"id": "a"}, {"id": "a"}]"#).is_err());
+        assert!(parse(r#"[{"id": "a", "source": "rtsp://cam/1"}]"#).is_err());
+
+        assert_eq!(
+            multipart_boundary("multipart/x-mixed-replace; boundary=--frame").as_deref(),
+            Some("frame")
+        );
+        let mut parser = MultipartParser::new("frame");
+        parser.push(b"--frame\r\nContent-Type: image/jpeg\r\nContent-Length: 3\r\n\r\nab");
+        assert_eq!(parser.next_part(), None);
+        parser.push(b"c\r\n--frame\r\nContent-Type: image/jpeg\r\n\r\nxyz\r\n--fr");
+        assert_eq!(parser.next_part().as_deref(), Some(&b"abc"[..]));
+        assert_eq!(parser.next_part(), None);
+        parser.push(b"ame\r\n");
+        assert_eq!(parser.next_part().as_deref(), Some(&b"xyz"[..]));
+    }
+}
@@ -0,0 +1,242 @@
+use bytes::Bytes;
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+use tracing::warn;
+
+use crate::{federation::encode_path_segment, registry::Frame};
+
+/// Lokasi satu frame yang sudah di-spill ke disk
+#[derive(Debug, Clone)]
+pub struct SpillRef {
+    pub seq: u64,
+    path: Arc<Path>,
+    offset: u64,
+    len: u32,
+    received_at: Instant,
+    producer_ms: Option<u64>,
+}
+
+impl SpillRef {
+    /// Kapan frame diterima broker (untuk memilih frame pre-roll sebelum dibaca)
+    #[cfg_attr(not(feature = "recording"), allow(dead_code))]
+    pub fn received_at(&self) -> Instant {
+        self.received_at
+    }
+}
+
+/// File segmen spill yang sedang/pernah ditulis
+#[derive(Debug)]
+struct SpillSegment {
+    path: Arc<Path>,
+    writer: BufWriter<File>,
+    len: u64,
+}
+
+/// Frame yang dibuang dari DVR buffer disimpan di file temp per stream
+///
+/// Two segment files of up to `max_bytes / 2` each are kept; when the newer
+/// one is full the older one is deleted, so disk usage stays bounded and the
+/// oldest frames are the first to go. Only payloads are written; the index
+/// (sequence, offset, length) stays in memory.
+#[derive(Debug)]
+pub struct SpillBuffer {
+    dir: PathBuf,
+    name: String,
+    max_bytes: u64,
+    next_segment: u64,
+    segments: VecDeque<SpillSegment>,
+    index: VecDeque<SpillRef>,
+}
+
+impl SpillBuffer {
+    /// Buat direktori dan segmen pertama; disk I/O, jadi jangan dipanggil di bawah lock registry
+    pub fn create(dir: &Path, stream_id: &str, max_bytes: u64) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let mut spill = Self {
+            dir: dir.to_path_buf(),
+            name: encode_path_segment(stream_id),
+            max_bytes,
+            next_segment: 0,
+            segments: VecDeque::new(),
+            index: VecDeque::new(),
+        };
+        spill.rotate()?;
+        Ok(spill)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let path: Arc<Path> = self
+            .dir
+            .join(format!("{}.{}.spill", self.name, self.next_segment))
+            .into();
+        self.next_segment += 1;
+        let file = File::create(&path)?;
+        self.segments.push_back(SpillSegment {
+            path,
+            writer: BufWriter::new(file),
+            len: 0,
+        });
+        if self.segments.len() > 2 {
+            let oldest = self.segments.pop_front().expect("more than two segments");
+            while self.index.front().is_some_and(|r| r.path == oldest.path) {
+                self.index.pop_front();
+            }
+            drop(oldest.writer);
+            fs::remove_file(&oldest.path)?;
+        }
+        Ok(())
+    }
+
+    /// Tulis frame yang baru dibuang dari DVR buffer
+    pub fn append(&mut self, frame: &Frame) -> io::Result<()> {
+        let segment_bytes = self.max_bytes / 2;
+        let full = self.segments.back().is_none_or(|segment| {
+            segment.len > 0 && segment.len + frame.data.len() as u64 > segment_bytes
+        });
+        if full {
+            self.rotate()?;
+        }
+        let segment = self.segments.back_mut().expect("rotated above");
+        segment.writer.write_all(&frame.data)?;
+        self.index.push_back(SpillRef {
+            seq: frame.seq,
+            path: segment.path.clone(),
+            offset: segment.len,
+            len: frame.data.len() as u32,
+            received_at: frame.received_at,
+            producer_ms: frame.producer_ms,
+        });
+        segment.len += frame.data.len() as u64;
+        Ok(())
+    }
+
+    /// Lokasi frame dengan seq > `after_seq`; data di buffer ditulis dulu ke file
+    pub fn since(&mut self, after_seq: u64) -> io::Result<Vec<SpillRef>> {
+        let start = self.index.partition_point(|r| r.seq <= after_seq);
+        if start == self.index.len() {
+            return Ok(Vec::new());
+        }
+        for segment in &mut self.segments {
+            segment.writer.flush()?;
+        }
+        Ok(self.index.range(start..).cloned().collect())
+    }
+
+    /// Total byte di disk (untuk diagnostik)
+    pub fn bytes(&self) -> u64 {
+        self.segments.iter().map(|segment| segment.len).sum()
+    }
+}
+
+impl Drop for SpillBuffer {
+    fn drop(&mut self) {
+        for segment in self.segments.drain(..) {
+            drop(segment.writer);
+            let _ = fs::remove_file(&segment.path);
+        }
+    }
+}
+
+/// Baca frame yang di-spill (dipanggil di luar lock registry)
+///
+/// Stops at the first frame that can no longer be read (its segment was
+/// rotated away meanwhile); the subscriber then sees a gap instead.
+pub fn read(refs: &[SpillRef]) -> Vec<Frame> {
+    let mut frames = Vec::with_capacity(refs.len());
+    let mut open: Option<(Arc<Path>, File)> = None;
+    for spill_ref in refs {
+        if open.as_ref().is_none_or(|(path, _)| *path != spill_ref.path) {
+            match File::open(&spill_ref.path) {
+                Ok(file) => open = Some((spill_ref.path.clone(), file)),
+                Err(e) => {
+                    warn!("Spilled frame {} is no longer available: {}", spill_ref.seq, e);
+                    break;
+                }
+            }
+        }
+        let (_, file) = open.as_mut().expect("opened above");
+        let mut data = vec![0; spill_ref.len as usize];
+        let result = file
+            .seek(SeekFrom::Start(spill_ref.offset))
+            .and_then(|_| file.read_exact(&mut data));
+        if let Err(e) = result {
+            warn!("Failed to read spilled frame {}: {}", spill_ref.seq, e);
+            break;
+        }
+        frames.push(Frame {
+            seq: spill_ref.seq,
+            data: Bytes::from(data),
+            received_at: spill_ref.received_at,
+            producer_ms: spill_ref.producer_ms,
+        });
+    }
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spill_is_bounded_and_readable() {
+        let dir = std::env::temp_dir().join(format!("bsb-spill-test-{}", std::process::id()));
+        let mut spill = SpillBuffer::create(&dir, "site-a/cam1", 40).unwrap();
+        let frame = |seq: u64| Frame {
+            seq,
+            data: Bytes::from(format!("frame-{:03}", seq)),
+            received_at: Instant::now(),
+            producer_ms: None,
+        };
+        // 10 byte per frame, segmen 20 byte -> maksimal 4 frame tersimpan
+        for seq in 1..=9 {
+            spill.append(&frame(seq)).unwrap();
+        }
+        assert!(spill.bytes() <= 40);
+
+        let refs = spill.since(0).unwrap();
+        let seqs: Vec<u64> = refs.iter().map(|r| r.seq).collect();
+        assert_eq!(seqs, vec![7, 8, 9]);
+        let frames = read(&refs[1..]);
+        assert_eq!(frames[0].data, Bytes::from("frame-008"));
+        assert_eq!(frames[1].data, Bytes::from("frame-009"));
+        assert!(spill.since(9).unwrap().is_empty());
+
+        drop(spill);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_spill_is_installed_after_stream_creation() {
+        use crate::{config::Config, AppState};
+
+        let dir = std::env::temp_dir().join(format!("bsb-spill-install-{}", std::process::id()));
+        let state = AppState::new(Config {
+            dvr_spill_max_bytes: 1 << 20,
+            dvr_spill_dir: dir.clone(),
+            ..Config::default()
+        });
+        // Stream langsung tersedia; file spill dibuat di runtime disk lalu dipasang
+        assert_eq!(state.with_stream("cam1", |entry| entry.dvr.spill_bytes()), None);
+        let mut installed = false;
+        for _ in 0..200 {
+            installed = state.streams.lock()["cam1"].dvr.spill_bytes().is_some();
+            if installed {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(installed);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        drop(state);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir(&dir).unwrap();
+    }
+}
@@ -0,0 +1,210 @@
+use bytes::Bytes;
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+use crate::{
+    segment::{self, Segment, SegmentMeta, META_EXTENSION, RECORD_HEADER_LEN},
+    registry::Frame,
+    stats::unix_now_ms,
+};
+
+/// Segmen spool diputar setelah sekian lama, supaya bisa dikirim dan dihapus bertahap
+const SEGMENT_MS: u64 = 10_000;
+
+/// Frame uplink yang ditahan di disk selama upstream tidak terjangkau (store-and-forward)
+///
+/// The spool is a directory of recorder segments under `STORE_FORWARD_DIR`,
+/// written with the same journal, so frames survive a crash or restart of the
+/// site broker. Each record carries the frame's original timestamp (producer
+/// time, or the time this broker received it) instead of the time it was spooled.
+pub struct Spool {
+    dir: PathBuf,
+    max_bytes: u64,
+    /// Closed segments, oldest first
+    closed: VecDeque<SegmentMeta>,
+    open: Option<Segment>,
+    /// Frames dropped because the spool outgrew `max_bytes`
+    pub dropped_frames: u64,
+}
+
+impl Spool {
+    /// Buka spool satu stream; segmen dari run sebelumnya (dan journal yang tertinggal) ikut dikirim
+    pub fn open(root: &str, stream_id: &str, max_bytes: u64) -> io::Result<Self> {
+        let dir = segment::stream_dir(root, stream_id);
+        fs::create_dir_all(&dir)?;
+        segment::recover_dir(&dir);
+        let closed: VecDeque<SegmentMeta> = segment::load_segments(&dir)?
+            .into_iter()
+            .map(|segment| segment.meta)
+            .filter(|meta| meta.frames > 0)
+            .collect();
+        if !closed.is_empty() {
+            let frames: u64 = closed.iter().map(|meta| meta.frames).sum();
+            info!("Spool {} holds {} frames from a previous run", dir.display(), frames);
+        }
+        Ok(Self {
+            dir,
+            max_bytes,
+            closed,
+            open: None,
+            dropped_frames: 0,
+        })
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.closed.iter().chain(self.open.as_ref().map(|open| &open.meta)).map(|meta| meta.frames).sum()
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.closed.iter().chain(self.open.as_ref().map(|open| &open.meta)).map(|meta| meta.bytes).sum()
+    }
+
+    /// Tulis frame dengan timestamp aslinya; segmen tertua dibuang bila melebihi `max_bytes`
+    pub async fn append(&mut self, frame: &Frame, original_ms: u64) -> io::Result<()> {
+        let now_ms = unix_now_ms();
+        if self
+            .open
+            .as_ref()
+            .is_some_and(|open| now_ms.saturating_sub(open.meta.start_ms) >= SEGMENT_MS)
+        {
+            self.rotate().await?;
+        }
+        if self.open.is_none() {
+            // Nama segmen = waktu buka; tidak boleh sama dengan segmen sebelumnya
+            let newest = self.closed.back().map_or(0, |meta| meta.start_ms + 1);
+            self.open = Some(Segment::open(&self.dir, now_ms.max(newest)).await?);
+        }
+        let open = self.open.as_mut().expect("opened above");
+        open.append(frame, original_ms).await?;
+        while self.bytes() > self.max_bytes && !self.closed.is_empty() {
+            let oldest = self.closed.pop_front().expect("checked above");
+            warn!(
+                "Spool {} is full, dropping {} frames of segment {}",
+                self.dir.display(),
+                oldest.frames,
+                oldest.segment
+            );
+            self.dropped_frames += oldest.frames;
+            remove_segment(&self.dir, &oldest);
+        }
+        Ok(())
+    }
+
+    /// Flush dan fsync segmen yang sedang ditulis
+    pub async fn sync(&mut self) -> io::Result<()> {
+        match self.open.as_mut() {
+            Some(open) => open.sync().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn rotate(&mut self) -> io::Result<()> {
+        if let Some(open) = self.open.take() {
+            let meta = open.close().await?;
+            if meta.frames > 0 {
+                self.closed.push_back(meta);
+            } else {
+                remove_segment(&self.dir, &meta);
+            }
+        }
+        Ok(())
+    }
+
+    /// Segmen tertua yang siap dikirim; segmen yang masih ditulis ditutup dulu
+    pub async fn next_segment(&mut self) -> io::Result<Option<SegmentMeta>> {
+        if self.closed.is_empty() {
+            self.rotate().await?;
+        }
+        Ok(self.closed.front().cloned())
+    }
+
+    /// Record segmen sebagai `(original_ms, payload)`, urut tulis
+    pub async fn read(&self, meta: &SegmentMeta) -> io::Result<Vec<(u64, Bytes)>> {
+        let data = Bytes::from(tokio::fs::read(self.dir.join(&meta.segment)).await?);
+        let mut records = Vec::with_capacity(meta.frames as usize);
+        let mut offset = 0;
+        while let Some(header) = data.get(offset..offset + RECORD_HEADER_LEN) {
+            let (_, original_ms, len) = segment::decode_record_header(header.try_into().expect("header length"));
+            let start = offset + RECORD_HEADER_LEN;
+            if start + len > data.len() {
+                break;
+            }
+            records.push((original_ms, data.slice(start..start + len)));
+            offset = start + len;
+        }
+        Ok(records)
+    }
+
+    /// Hapus segmen yang sudah dikonfirmasi upstream
+    pub fn remove(&mut self, meta: &SegmentMeta) {
+        if self.closed.front().is_some_and(|front| front.start_ms == meta.start_ms) {
+            self.closed.pop_front();
+            remove_segment(&self.dir, meta);
+        }
+    }
+}
+
+fn remove_segment(dir: &Path, meta: &SegmentMeta) {
+    let meta_path = dir.join(format!("{}.{}", meta.start_ms, META_EXTENSION));
+    for path in [dir.join(&meta.segment), meta_path] {
+        if let Err(e) = fs::remove_file(&path) {
+            warn!("Cannot remove spool file {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_spool_keeps_original_timestamps_and_survives_restart() {
+        let root = std::env::temp_dir().join(format!("bsb-spool-test-{}", std::process::id()));
+        let root = root.to_str().unwrap();
+        let frame = |seq: u64, data: &'static [u8]| Frame {
+            seq,
+            data: Bytes::from_static(data),
+            received_at: Instant::now(),
+            producer_ms: None,
+        };
+
+        let mut spool = Spool::open(root, "site/cam1", 1 << 20).unwrap();
+        spool.append(&frame(1, b"one"), 1_000).await.unwrap();
+        spool.append(&frame(2, b"two"), 1_040).await.unwrap();
+        assert_eq!(spool.frames(), 2);
+        // Segmen yang masih ditulis ditutup saat dibutuhkan
+        let segment = spool.next_segment().await.unwrap().unwrap();
+        assert_eq!(
+            spool.read(&segment).await.unwrap(),
+            vec![(1_000, Bytes::from_static(b"one")), (1_040, Bytes::from_static(b"two"))]
+        );
+        spool.append(&frame(3, b"three"), 1_080).await.unwrap();
+        spool.sync().await.unwrap();
+        drop(spool);
+
+        // Restart: segmen tertutup masih ada, journal segmen terbuka dipulihkan
+        let mut spool = Spool::open(root, "site/cam1", 1 << 20).unwrap();
+        assert_eq!(spool.frames(), 3);
+        let first = spool.next_segment().await.unwrap().unwrap();
+        spool.remove(&first);
+        let second = spool.next_segment().await.unwrap().unwrap();
+        assert_eq!(spool.read(&second).await.unwrap(), vec![(1_080, Bytes::from_static(b"three"))]);
+        spool.remove(&second);
+        assert!(spool.next_segment().await.unwrap().is_none());
+
+        // Melebihi kapasitas: segmen tertua dibuang
+        let mut spool = Spool::open(root, "cam2", 1).unwrap();
+        spool.append(&frame(1, b"old"), 1).await.unwrap();
+        spool.rotate().await.unwrap();
+        spool.append(&frame(2, b"new"), 2).await.unwrap();
+        assert_eq!(spool.dropped_frames, 1);
+        assert_eq!(spool.frames(), 1);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{breaker, preempt, AppState};
+
+/// Counter kumulatif per stream, di-update dari hot path tanpa lock
+#[derive(Debug, Default)]
+pub struct StreamCounters {
+    pub frames: AtomicU64,
+    pub bytes: AtomicU64,
+    /// Frames skipped by lagging subscribers (summed over subscribers)
+    pub drops: AtomicU64,
+    /// Ingested frames rejected because their checksum trailer did not match
+    pub checksum_failures: AtomicU64,
+    /// Ingested frames rejected because their format is not allowed on the stream
+    pub format_rejections: AtomicU64,
+    /// Stalled or slow producers reported by the watchdog
+    pub producer_alerts: AtomicU64,
+    /// Total playout duration of validated Opus packets (microseconds)
+    pub audio_us: AtomicU64,
+    /// Text messages published on the stream's data channel
+    pub data_messages: AtomicU64,
+    /// Failed sends to subscribers (each one ends that subscriber's connection)
+    pub send_errors: AtomicU64,
+    /// Times the stream's circuit breaker opened
+    pub breaker_trips: AtomicU64,
+    /// Live frames skipped because a subscriber was over its bandwidth cap
+    pub throttled: AtomicU64,
+    /// Frame writes that waited for their turn in the fan-out scheduler
+    pub fanout_waits: AtomicU64,
+    /// Times a subscriber draining a backlog yielded its worker to other subscribers
+    pub fanout_yields: AtomicU64,
+    /// Flushes of live frames to subscriber sockets; `frames_flushed / flushes` is the
+    /// coalescing factor (frames per write)
+    pub flushes: AtomicU64,
+    pub frames_flushed: AtomicU64,
+    /// Frames viewers reported dropping in their players (`{"dropped":N}` stats messages)
+    pub viewer_dropped: AtomicU64,
+}
+
+impl StreamCounters {
+    pub fn record_frame(&self, len: usize) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_drops(&self, skipped: u64) {
+        self.drops.fetch_add(skipped, Ordering::Relaxed);
+    }
+
+    pub fn record_checksum_failure(&self) {
+        self.checksum_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_format_rejection(&self) {
+        self.format_rejections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_producer_alert(&self) {
+        self.producer_alerts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_audio(&self, duration_us: u32) {
+        self.audio_us.fetch_add(duration_us as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_data_message(&self) {
+        self.data_messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_send_error(&self) {
+        self.send_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_throttled(&self) {
+        self.throttled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_fanout_wait(&self) {
+        self.fanout_waits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_fanout_yield(&self) {
+        self.fanout_yields.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_flush(&self, frames: usize) {
+        self.flushes.fetch_add(1, Ordering::Relaxed);
+        self.frames_flushed.fetch_add(frames as u64, Ordering::Relaxed);
+    }
+
+    /// Rata-rata frame per flush ke subscriber (0 sebelum flush pertama)
+    pub fn coalescing_factor(&self) -> f64 {
+        let flushes = self.flushes.load(Ordering::Relaxed);
+        if flushes == 0 {
+            return 0.0;
+        }
+        self.frames_flushed.load(Ordering::Relaxed) as f64 / flushes as f64
+    }
+
+    /// `(frames, bytes, drops, checksum_failures)` kumulatif, untuk riwayat statistik
+    #[cfg(feature = "metrics")]
+    pub fn snapshot(&self) -> (u64, u64, u64, u64) {
+        (
+            self.frames.load(Ordering::Relaxed),
+            self.bytes.load(Ordering::Relaxed),
+            self.drops.load(Ordering::Relaxed),
+            self.checksum_failures.load(Ordering::Relaxed),
+        )
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+pub fn unix_now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Background task: circuit breaker, batas memori dan sampel statistik setiap detik
+pub async fn run_sampler(state: AppState) {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        #[cfg(feature = "metrics")]
+        let now = unix_now();
+        let mut map = state.streams.lock();
+        for (stream_id, entry) in map.iter_mut() {
+            let subscribers = entry.tx.receiver_count();
+            breaker::report(stream_id, entry, subscribers);
+            #[cfg(feature = "metrics")]
+            {
+                // Sender::len = frame yang belum dibaca subscriber paling lambat
+                let lag = entry.tx.len() as f64 / entry.capacity as f64;
+                entry.history.record(now, &entry.counters, subscribers, lag.min(1.0));
+            }
+        }
+        preempt::enforce_memory(&state, &mut map);
+    }
+}
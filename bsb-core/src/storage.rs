@@ -0,0 +1,420 @@
+use axum::{extract::State, response::Json};
+use bytes::Bytes;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+use tracing::{info, warn};
+
+use crate::{outbound::HttpTarget, runtime, stats::unix_now_ms, AppState};
+
+/// Label stream yang mengelompokkan pemakaian disk per tenant di GET /api/storage
+pub const TENANT_LABEL: &str = "tenant";
+
+/// Ambang pemakaian disk dari `STORAGE_*`
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    /// Used percentage at which the `warning` alert is raised
+    pub warn_percent: f64,
+    /// Used percentage at which the `critical` alert is raised (and recording paused)
+    pub critical_percent: f64,
+    /// Budget for recordings plus DVR spill, on top of the filesystems' own size (0 = none)
+    pub max_bytes: u64,
+    /// How often usage is measured in the background
+    pub check_interval: Duration,
+    /// `http://` endpoint that receives every level change as JSON
+    pub webhook: Option<String>,
+    /// Stop writing recording segments while the level is `critical`
+    pub pause_recording: bool,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            warn_percent: 80.0,
+            critical_percent: 95.0,
+            max_bytes: 0,
+            check_interval: Duration::from_secs(30),
+            webhook: None,
+            pause_recording: false,
+        }
+    }
+}
+
+impl StorageConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=100.0).contains(&self.warn_percent) || !(0.0..=100.0).contains(&self.critical_percent) {
+            return Err("STORAGE_WARN_PERCENT and STORAGE_CRITICAL_PERCENT must be between 0 and 100".to_string());
+        }
+        if self.warn_percent > self.critical_percent {
+            return Err("STORAGE_WARN_PERCENT must not be above STORAGE_CRITICAL_PERCENT".to_string());
+        }
+        if self.check_interval.is_zero() {
+            return Err("STORAGE_CHECK_SECS must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn level(&self, used_percent: f64) -> StorageLevel {
+        if used_percent >= self.critical_percent {
+            StorageLevel::Critical
+        } else if used_percent >= self.warn_percent {
+            StorageLevel::Warning
+        } else {
+            StorageLevel::Ok
+        }
+    }
+}
+
+/// Tingkat alert pemakaian disk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageLevel {
+    #[default]
+    Ok,
+    Warning,
+    Critical,
+}
+
+/// Filesystem tempat broker menulis (rekaman, spill DVR)
+#[derive(Debug, Clone, Serialize)]
+pub struct Volume {
+    /// Directory the broker writes to, as configured
+    pub path: String,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+    pub used_percent: f64,
+}
+
+/// Byte di disk milik satu stream
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StreamUsage {
+    pub recording_bytes: u64,
+    pub spill_bytes: u64,
+    /// `tenant` label; unknown for recordings of streams that no longer exist
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+}
+
+/// Pemakaian per tenant (stream dengan label `tenant`)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TenantUsage {
+    pub recording_bytes: u64,
+    pub spill_bytes: u64,
+    pub streams: usize,
+}
+
+/// Hasil satu pengukuran pemakaian disk
+#[derive(Debug, Clone, Default)]
+pub struct Usage {
+    pub volumes: Vec<Volume>,
+    pub streams: BTreeMap<String, StreamUsage>,
+}
+
+impl Usage {
+    pub fn total_bytes(&self) -> u64 {
+        self.streams.values().map(|usage| usage.recording_bytes + usage.spill_bytes).sum()
+    }
+
+    /// Persentase terpakai yang terburuk: filesystem penuh atau budget `STORAGE_MAX_BYTES`
+    pub fn used_percent(&self, max_bytes: u64) -> Option<f64> {
+        let budget = (max_bytes > 0).then(|| self.total_bytes() as f64 * 100.0 / max_bytes as f64);
+        self.volumes.iter().map(|volume| volume.used_percent).chain(budget).reduce(f64::max)
+    }
+
+    pub fn tenants(&self) -> BTreeMap<String, TenantUsage> {
+        let mut tenants: BTreeMap<String, TenantUsage> = BTreeMap::new();
+        for usage in self.streams.values() {
+            if let Some(tenant) = &usage.tenant {
+                let total = tenants.entry(tenant.clone()).or_default();
+                total.recording_bytes += usage.recording_bytes;
+                total.spill_bytes += usage.spill_bytes;
+                total.streams += 1;
+            }
+        }
+        tenants
+    }
+}
+
+/// Ukur pemakaian disk: spill DVR tiap stream, direktori rekaman, dan filesystem-nya
+pub async fn measure(state: &AppState) -> Usage {
+    let mut streams: BTreeMap<String, StreamUsage> = state
+        .streams
+        .lock()
+        .iter()
+        .map(|(stream_id, entry)| {
+            let usage = StreamUsage {
+                recording_bytes: 0,
+                spill_bytes: entry.dvr.spill_bytes().unwrap_or(0),
+                tenant: entry.labels.get(TENANT_LABEL).cloned(),
+            };
+            (stream_id.clone(), usage)
+        })
+        .collect();
+
+    let config = state.config.clone();
+    let measured = runtime::spawn_disk_blocking(move || {
+        let mut paths: Vec<PathBuf> = Vec::new();
+        #[cfg(feature = "recording")]
+        paths.push(PathBuf::from(&config.recordings_dir));
+        if config.dvr_spill_max_bytes > 0 {
+            paths.push(config.dvr_spill_dir.clone());
+        }
+        let volumes: Vec<Volume> = paths.iter().filter_map(|path| volume(path)).collect();
+        (recording_bytes(&config.recordings_dir), volumes)
+    })
+    .await;
+    let (recorded, volumes) = measured.unwrap_or_else(|e| {
+        warn!("Storage measurement failed: {}", e);
+        Default::default()
+    });
+    for (stream_id, bytes) in recorded {
+        streams.entry(stream_id).or_default().recording_bytes = bytes;
+    }
+    Usage { volumes, streams }
+}
+
+/// Byte rekaman per stream di `RECORDINGS_DIR` (segmen, journal, sidecar anotasi)
+#[cfg(feature = "recording")]
+fn recording_bytes(root: &str) -> BTreeMap<String, u64> {
+    let Ok(dirs) = std::fs::read_dir(root) else {
+        return BTreeMap::new();
+    };
+    dirs.flatten()
+        .filter(|dir| dir.file_type().is_ok_and(|kind| kind.is_dir()))
+        .filter_map(|dir| {
+            let stream_id = decode_path_segment(dir.file_name().to_str()?)?;
+            let bytes = std::fs::read_dir(dir.path())
+                .ok()?
+                .flatten()
+                .filter_map(|file| file.metadata().ok())
+                .filter(|meta| meta.is_file())
+                .map(|meta| meta.len())
+                .sum();
+            Some((stream_id, bytes))
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "recording"))]
+fn recording_bytes(_root: &str) -> BTreeMap<String, u64> {
+    BTreeMap::new()
+}
+
+/// Kebalikan `federation::encode_path_segment` untuk nama direktori stream
+#[cfg(feature = "recording")]
+fn decode_path_segment(segment: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Ukuran filesystem tempat `path` berada; direktori yang belum dibuat diukur dari induknya
+fn volume(path: &Path) -> Option<Volume> {
+    let existing = path
+        .ancestors()
+        .map(|dir| if dir.as_os_str().is_empty() { Path::new(".") } else { dir })
+        .find(|dir| dir.exists())?;
+    let (total_bytes, available_bytes) = filesystem_space(existing)?;
+    let used = total_bytes.saturating_sub(available_bytes);
+    Some(Volume {
+        path: path.display().to_string(),
+        total_bytes,
+        available_bytes,
+        used_percent: if total_bytes == 0 { 0.0 } else { used as f64 * 100.0 / total_bytes as f64 },
+    })
+}
+
+/// Total dan sisa byte filesystem untuk user biasa (tanpa blok cadangan root)
+#[cfg(target_os = "linux")]
+fn filesystem_space(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs is plain data; all-zero is a valid value
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stat` is a valid statvfs to write into
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block = stat.f_frsize as u64;
+    Some((stat.f_blocks as u64 * block, stat.f_bavail as u64 * block))
+}
+
+/// Di luar Linux hanya budget `STORAGE_MAX_BYTES` yang diperiksa
+#[cfg(not(target_os = "linux"))]
+fn filesystem_space(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+/// Status alert pemakaian disk, diperbarui oleh pemeriksaan berkala dan GET /api/storage
+#[derive(Debug, Default)]
+pub struct StorageMonitor {
+    level: Mutex<StorageLevel>,
+    recording_paused: AtomicBool,
+    /// Times the level rose to `warning` or `critical`
+    alerts: AtomicU64,
+}
+
+impl StorageMonitor {
+    /// Recorder berhenti menulis segmen selama ini `true` (`STORAGE_PAUSE_RECORDING`)
+    #[cfg_attr(not(feature = "recording"), allow(dead_code))]
+    pub fn recording_paused(&self) -> bool {
+        self.recording_paused.load(Ordering::Relaxed)
+    }
+
+    pub fn level(&self) -> StorageLevel {
+        *self.level.lock()
+    }
+
+    /// Terapkan pengukuran baru; perubahan tingkat dilaporkan ke log dan webhook
+    pub fn update(&self, state: &AppState, used_percent: Option<f64>) -> StorageLevel {
+        let config = &state.config.storage;
+        let level = used_percent.map_or(StorageLevel::Ok, |percent| config.level(percent));
+        let previous = std::mem::replace(&mut *self.level.lock(), level);
+        let paused = config.pause_recording && level == StorageLevel::Critical;
+        self.recording_paused.store(paused, Ordering::Relaxed);
+        if level == previous {
+            return level;
+        }
+
+        let percent = used_percent.unwrap_or(0.0);
+        if level > previous {
+            self.alerts.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                "Storage {:?}: {:.1}% used{}",
+                level,
+                percent,
+                if paused { ", recording paused" } else { "" }
+            );
+        } else {
+            info!("Storage back to {:?}: {:.1}% used", level, percent);
+        }
+        if let Some(url) = config.webhook.clone() {
+            let body = json!({
+                "type": "storage_alert",
+                "level": level,
+                "previous": previous,
+                "used_percent": used_percent,
+                "recording_paused": paused,
+            });
+            tokio::spawn(async move {
+                let result = match HttpTarget::parse(&url) {
+                    Ok(mut target) => {
+                        target
+                            .post("application/json", &[], Bytes::from(body.to_string()))
+                            .await
+                    }
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(status) if status.is_success() => {}
+                    Ok(status) => warn!("Storage webhook {} answered {}", url, status),
+                    Err(e) => warn!("Storage webhook {} failed: {}", url, e),
+                }
+            });
+        }
+        level
+    }
+
+    pub fn alerts(&self) -> u64 {
+        self.alerts.load(Ordering::Relaxed)
+    }
+}
+
+/// Ukur pemakaian disk setiap `STORAGE_CHECK_SECS`
+pub async fn run_monitor(state: AppState) {
+    let mut ticker = tokio::time::interval(state.config.storage.check_interval);
+    loop {
+        ticker.tick().await;
+        let usage = measure(&state).await;
+        state.storage.update(&state, usage.used_percent(state.config.storage.max_bytes));
+    }
+}
+
+/// Handler untuk GET /api/storage
+/// Disk usage of recordings and DVR spill per stream and tenant, measured now
+pub async fn storage_handler(State(state): State<AppState>) -> Json<Value> {
+    let usage = measure(&state).await;
+    let config = &state.config.storage;
+    let used_percent = usage.used_percent(config.max_bytes);
+    let level = state.storage.update(&state, used_percent);
+    Json(json!({
+        "checked_ms": unix_now_ms(),
+        "level": level,
+        "used_percent": used_percent,
+        "warn_percent": config.warn_percent,
+        "critical_percent": config.critical_percent,
+        "max_bytes": (config.max_bytes > 0).then_some(config.max_bytes),
+        "recording_paused": state.storage.recording_paused(),
+        "alerts": state.storage.alerts(),
+        "recording_bytes": usage.streams.values().map(|usage| usage.recording_bytes).sum::<u64>(),
+        "spill_bytes": usage.streams.values().map(|usage| usage.spill_bytes).sum::<u64>(),
+        "volumes": usage.volumes,
+        "tenants": usage.tenants(),
+        "streams": usage.streams,
+    }))
+}
+
+#[cfg(all(test, feature = "recording"))]
+mod tests {
+    use super::*;
+    use crate::{config::Config, federation::encode_path_segment, testing::TestBroker};
+
+    #[tokio::test]
+    async fn test_storage_usage_per_tenant_pauses_recording_over_budget() {
+        let root = std::env::temp_dir().join(format!("bsb-storage-test-{}", std::process::id()));
+        let broker = TestBroker::start(Config {
+            recordings_dir: root.to_str().unwrap().to_string(),
+            storage: StorageConfig {
+                warn_percent: 50.0,
+                critical_percent: 90.0,
+                max_bytes: 2000,
+                pause_recording: true,
+                ..StorageConfig::default()
+            },
+            ..Config::default()
+        })
+        .await;
+        for (stream_id, bytes) in [("site-a/cam1", 1000), ("gone", 500)] {
+            let dir = root.join(encode_path_segment(stream_id));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("1000.seg"), vec![0; bytes]).unwrap();
+        }
+        broker.state.with_stream("site-a/cam1", |entry| {
+            entry.labels.insert(TENANT_LABEL.to_string(), "acme".to_string());
+        });
+
+        let Json(report) = storage_handler(State(broker.state.clone())).await;
+        assert_eq!(report["streams"]["site-a/cam1"]["tenant"], "acme");
+        assert_eq!(report["streams"]["gone"]["recording_bytes"], 500);
+        assert_eq!(report["tenants"]["acme"]["recording_bytes"], 1000);
+        assert_eq!(report["tenants"]["acme"]["streams"], 1);
+        assert_eq!(report["recording_bytes"], 1500);
+        // 1500 dari budget 2000 = 75%; filesystem yang lebih penuh bisa menaikkan level-nya
+        assert!(report["used_percent"].as_f64().unwrap() >= 75.0);
+        assert!(broker.state.storage.level() >= StorageLevel::Warning);
+        assert_eq!(report["alerts"], 1);
+
+        std::fs::write(root.join("gone").join("2000.seg"), vec![0; 500]).unwrap();
+        let Json(report) = storage_handler(State(broker.state.clone())).await;
+        assert_eq!((report["level"].as_str(), report["recording_paused"].as_bool()), (Some("critical"), Some(true)));
+        assert!(broker.state.storage.recording_paused());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
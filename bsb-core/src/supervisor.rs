@@ -0,0 +1,122 @@
+use std::{
+    any::Any,
+    future::Future,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+use tokio::{runtime::Handle, task::JoinHandle};
+use tracing::error;
+
+/// Jeda restart pertama; digandakan tiap panic beruntun sampai `MAX_BACKOFF`
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Task yang berjalan selama ini sebelum panic dianggap sehat lagi (backoff di-reset)
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+
+/// Jumlah task yang di-restart setelah panic sejak broker start (`/health`)
+pub static RESTARTS: AtomicU64 = AtomicU64::new(0);
+
+/// Abort task yang diawasi saat supervisor-nya di-abort (tap dihapus, relay dihentikan)
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Jalankan task per stream (recorder, pull source, merge, tap, relay) di bawah supervisor
+///
+/// A panic inside the task is logged and the task is started again from
+/// `make` after a backoff, instead of the stream silently losing its recorder
+/// or source until the broker restarts. A task that returns normally is not
+/// restarted. Aborting the returned handle also aborts the running task.
+pub fn supervise<F, Fut>(name: String, make: F) -> JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    supervise_on(&Handle::current(), name, make)
+}
+
+/// Seperti [`supervise`], tapi task berjalan di runtime `handle` (mis. runtime disk)
+pub fn supervise_on<F, Fut>(handle: &Handle, name: String, mut make: F) -> JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let runtime = handle.clone();
+    handle.spawn(async move {
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            let started = Instant::now();
+            let mut task = AbortOnDrop(runtime.spawn(make()));
+            let panic = match (&mut task.0).await {
+                Ok(()) => return,
+                Err(e) if e.is_cancelled() => return,
+                Err(e) => e.into_panic(),
+            };
+            if started.elapsed() >= STABLE_AFTER {
+                backoff = INITIAL_BACKOFF;
+            }
+            error!(
+                "Task {} panicked: {}; restarting in {:?}",
+                name,
+                panic_message(panic.as_ref()),
+                backoff
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+            RESTARTS.fetch_add(1, Ordering::Relaxed);
+        }
+    })
+}
+
+/// Pesan panic (`panic!("...")` menghasilkan `&str` atau `String`)
+pub fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_panicking_task_is_restarted() {
+        let runs = Arc::new(AtomicU64::new(0));
+        let counter = runs.clone();
+        let handle = supervise("test".to_string(), move || {
+            let run = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if run == 0 {
+                    panic!("run {} failed", run);
+                }
+            }
+        });
+        // Run pertama panic, setelah backoff 1 s run kedua selesai normal
+        tokio::time::timeout(Duration::from_secs(5), handle).await.unwrap().unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert!(RESTARTS.load(Ordering::Relaxed) >= 1);
+
+        // Abort supervisor ikut menghentikan task di dalamnya
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<()>(1);
+        let handle = supervise("idle".to_string(), move || {
+            let tx = tx.clone();
+            async move {
+                let _tx = tx;
+                std::future::pending::<()>().await
+            }
+        });
+        tokio::task::yield_now().await;
+        handle.abort();
+        assert_eq!(rx.recv().await, None);
+    }
+}
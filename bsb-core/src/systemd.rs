@@ -0,0 +1,144 @@
+//! systemd integration: socket activation (`LISTEN_FDS`) and `sd_notify`
+//!
+//! Both are no-ops when the broker is not started by systemd, and on
+//! non-Unix platforms.
+
+use std::{env, io, time::Duration};
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+use crate::AppState;
+
+/// File descriptor pertama yang diteruskan systemd (`SD_LISTEN_FDS_START`)
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// Jumlah socket yang diteruskan untuk proses ini (`LISTEN_PID` harus cocok)
+fn listen_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> usize {
+    let for_us = listen_pid
+        .and_then(|value| value.parse::<u32>().ok())
+        .is_some_and(|listen_pid| listen_pid == pid);
+    if !for_us {
+        return 0;
+    }
+    listen_fds.and_then(|value| value.parse().ok()).unwrap_or(0)
+}
+
+/// Listening socket dari systemd (`.socket` unit), atau `None` jika tidak di-activate
+///
+/// Only the first socket is used; the unit should declare a single `ListenStream=`.
+#[cfg(unix)]
+pub fn activated_listener() -> io::Result<Option<TcpListener>> {
+    use std::os::unix::io::FromRawFd;
+
+    let count = listen_fds(
+        env::var("LISTEN_PID").ok().as_deref(),
+        env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+    // Jangan wariskan ke child process
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+    if count == 0 {
+        return Ok(None);
+    }
+    if count > 1 {
+        warn!("systemd passed {} sockets, only the first one is used", count);
+    }
+    // SAFETY: systemd guarantees fds 3..3+LISTEN_FDS are open sockets owned by this process
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(LISTEN_FDS_START) };
+    listener.set_nonblocking(true)?;
+    TcpListener::from_std(listener).map(Some)
+}
+
+#[cfg(not(unix))]
+pub fn activated_listener() -> io::Result<Option<TcpListener>> {
+    Ok(None)
+}
+
+/// Kirim status ke `NOTIFY_SOCKET` (mis. `READY=1`, `WATCHDOG=1`, `STOPPING=1`)
+#[cfg(unix)]
+pub fn notify(state: &str) {
+    use std::os::unix::net::UnixDatagram;
+
+    let Ok(path) = env::var("NOTIFY_SOCKET") else {
+        return;
+    };
+    let result = UnixDatagram::unbound().and_then(|socket| {
+        if let Some(name) = path.strip_prefix('@') {
+            // Abstract namespace socket (Linux)
+            #[cfg(target_os = "linux")]
+            {
+                use std::os::linux::net::SocketAddrExt;
+                let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+                return socket.send_to_addr(state.as_bytes(), &addr).map(|_| ());
+            }
+            #[cfg(not(target_os = "linux"))]
+            {
+                let _ = name;
+                return Err(io::Error::new(io::ErrorKind::Unsupported, "abstract sockets"));
+            }
+        }
+        socket.send_to(state.as_bytes(), &path).map(|_| ())
+    });
+    if let Err(e) = result {
+        warn!("sd_notify {:?} to {} failed: {}", state, path, e);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn notify(_state: &str) {}
+
+/// Interval ping watchdog: setengah `WATCHDOG_USEC`, jika watchdog ditujukan untuk proses ini
+fn watchdog_interval(usec: Option<&str>, watchdog_pid: Option<&str>, pid: u32) -> Option<Duration> {
+    if watchdog_pid.is_some_and(|value| value.parse::<u32>().ok() != Some(pid)) {
+        return None;
+    }
+    let usec: u64 = usec?.parse().ok().filter(|&usec| usec > 0)?;
+    Some(Duration::from_micros(usec / 2))
+}
+
+/// Kirim `WATCHDOG=1` selama runtime dan registry stream masih responsif
+///
+/// The registry lock is taken on every ping, so a deadlocked broker stops
+/// pinging and systemd restarts it (`WatchdogSec=` in the service unit).
+pub fn spawn_watchdog(state: &AppState) {
+    let Some(interval) = watchdog_interval(
+        env::var("WATCHDOG_USEC").ok().as_deref(),
+        env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    ) else {
+        return;
+    };
+    info!("systemd watchdog enabled, pinging every {:?}", interval);
+    let state = state.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            drop(state.streams.lock());
+            notify("WATCHDOG=1");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activation_env() {
+        assert_eq!(listen_fds(Some("42"), Some("1"), 42), 1);
+        // Socket untuk proses lain (mis. parent) diabaikan
+        assert_eq!(listen_fds(Some("41"), Some("1"), 42), 0);
+        assert_eq!(listen_fds(None, Some("1"), 42), 0);
+
+        assert_eq!(
+            watchdog_interval(Some("30000000"), None, 42),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(watchdog_interval(Some("30000000"), Some("7"), 42), None);
+        assert_eq!(watchdog_interval(None, None, 42), None);
+    }
+}
@@ -0,0 +1,290 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use futures_util::SinkExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::AsyncWriteExt,
+    net::TcpStream,
+    sync::broadcast,
+    task::JoinHandle,
+};
+use tokio_tungstenite::{tungstenite::Message as WsMessage, MaybeTlsStream, WebSocketStream};
+use tracing::{info, warn};
+
+use crate::{
+    error::BrokerError,
+    outbound::{self, HttpTarget},
+    registry::Frame,
+    supervisor, AppState,
+};
+
+/// Batasi frame rate: frame hanya diteruskan jika sudah lewat `1 / max_fps` sejak frame terakhir
+#[derive(Debug)]
+pub struct FrameRateLimiter {
+    interval: Duration,
+    last_sent: Option<Instant>,
+}
+
+impl FrameRateLimiter {
+    pub fn new(max_fps: f64) -> Self {
+        Self {
+            interval: Duration::from_secs_f64(1.0 / max_fps),
+            last_sent: None,
+        }
+    }
+
+    pub fn admit(&mut self, now: Instant) -> bool {
+        if self.last_sent.is_some_and(|last| now.duration_since(last) < self.interval) {
+            return false;
+        }
+        self.last_sent = Some(now);
+        true
+    }
+}
+
+/// Validasi `max_fps` dari query/body
+pub fn validate_max_fps(max_fps: f64) -> Result<(), &'static str> {
+    if max_fps.is_finite() && max_fps > 0.0 && max_fps <= 1000.0 {
+        Ok(())
+    } else {
+        Err("max_fps must be greater than 0 and at most 1000")
+    }
+}
+
+/// Body untuk PUT /api/streams/:id/taps/:name
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TapConfig {
+    /// `http://` (POST per frame), `ws://` (binary message per frame) or
+    /// `tcp://host:port` (`[u32 len][payload]` per frame, big-endian)
+    pub url: String,
+    #[serde(default = "default_max_fps")]
+    pub max_fps: f64,
+}
+
+fn default_max_fps() -> f64 {
+    1.0
+}
+
+impl TapConfig {
+    fn validate(&self) -> Result<(), String> {
+        validate_max_fps(self.max_fps)?;
+        let supported = ["http://", "ws://", "tcp://"];
+        if !supported.iter().any(|scheme| self.url.starts_with(scheme)) {
+            return Err(format!("unsupported tap URL {:?} (use http://, ws:// or tcp://)", self.url));
+        }
+        Ok(())
+    }
+}
+
+/// Tujuan output tap yang sedang terhubung
+enum TapSink {
+    Http(HttpTarget),
+    Ws(Box<WebSocketStream<MaybeTlsStream<TcpStream>>>),
+    Tcp(TcpStream),
+}
+
+impl TapSink {
+    async fn connect(url: &str) -> Result<Self, String> {
+        if url.starts_with("http://") {
+            Ok(TapSink::Http(HttpTarget::parse(url)?))
+        } else if let Some(addr) = url.strip_prefix("tcp://") {
+            let (host, port) = outbound::split_host_port(addr.trim_end_matches('/'))?;
+            let stream = outbound::connect(host, port).await.map_err(|e| e.to_string())?;
+            stream.set_nodelay(true).map_err(|e| e.to_string())?;
+            Ok(TapSink::Tcp(stream))
+        } else {
+            let (socket, _) = outbound::connect_ws(url, None).await?;
+            Ok(TapSink::Ws(Box::new(socket)))
+        }
+    }
+
+    async fn send(&mut self, stream_id: &str, frame: &Frame) -> Result<(), String> {
+        match self {
+            TapSink::Http(target) => {
+                let headers = [("x-stream-id", stream_id.to_string()), ("x-seq", frame.seq.to_string())];
+                let status = target
+                    .post("application/octet-stream", &headers, frame.data.clone())
+                    .await?;
+                // Inference service yang menolak frame tidak memutus tap
+                if !status.is_success() {
+                    warn!("Tap target rejected frame {} of {}: {}", frame.seq, stream_id, status);
+                }
+                Ok(())
+            }
+            TapSink::Ws(socket) => socket
+                .send(WsMessage::Binary(frame.data.to_vec()))
+                .await
+                .map_err(|e| e.to_string()),
+            TapSink::Tcp(stream) => {
+                let mut buf = Vec::with_capacity(4 + frame.data.len());
+                buf.extend_from_slice(&(frame.data.len() as u32).to_be_bytes());
+                buf.extend_from_slice(&frame.data);
+                stream.write_all(&buf).await.map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+/// Key tap: (stream ID, nama tap)
+type TapKey = (String, String);
+
+/// Tap yang dikonfigurasi lewat admin API
+#[derive(Debug, Default)]
+pub struct TapRegistry {
+    running: Mutex<HashMap<TapKey, (TapConfig, JoinHandle<()>)>>,
+}
+
+impl TapRegistry {
+    fn start(&self, stream_id: &str, name: &str, config: TapConfig, task: JoinHandle<()>) {
+        let key = (stream_id.to_string(), name.to_string());
+        if let Some((_, old)) = self.running.lock().insert(key, (config, task)) {
+            old.abort();
+        }
+    }
+
+    fn stop(&self, stream_id: &str, name: &str) -> bool {
+        let key = (stream_id.to_string(), name.to_string());
+        match self.running.lock().remove(&key) {
+            Some((_, task)) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn list(&self, stream_id: &str) -> Vec<serde_json::Value> {
+        let running = self.running.lock();
+        let mut taps: Vec<_> = running
+            .iter()
+            .filter(|((stream, _), _)| stream == stream_id)
+            .map(|((_, name), (config, _))| {
+                json!({ "name": name, "url": config.url, "max_fps": config.max_fps })
+            })
+            .collect();
+        taps.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        taps
+    }
+}
+
+/// Teruskan salinan stream yang sudah di-decimate ke output tap, reconnect jika putus
+async fn run_tap(state: AppState, stream_id: String, config: TapConfig) {
+    let mut frames = state.with_stream(&stream_id, |entry| entry.tx.subscribe());
+    let mut limiter = FrameRateLimiter::new(config.max_fps);
+    let mut sink: Option<TapSink> = None;
+    let mut retry_at = Instant::now();
+
+    loop {
+        let frame = match frames.recv().await {
+            Ok(frame) => frame,
+            // Tap yang lambat hanya kehilangan frame miliknya sendiri
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if !limiter.admit(Instant::now()) {
+            continue;
+        }
+
+        if sink.is_none() {
+            if Instant::now() < retry_at {
+                continue;
+            }
+            match TapSink::connect(&config.url).await {
+                Ok(connected) => {
+                    info!("Tap for stream {} connected to {}", stream_id, config.url);
+                    sink = Some(connected);
+                }
+                Err(e) => {
+                    warn!("Tap for stream {} cannot reach {}: {}", stream_id, config.url, e);
+                    retry_at = Instant::now() + Duration::from_secs(5);
+                    continue;
+                }
+            }
+        }
+        if let Some(connected) = sink.as_mut() {
+            if let Err(e) = connected.send(&stream_id, &frame).await {
+                warn!("Tap for stream {} lost {}: {}", stream_id, config.url, e);
+                sink = None;
+                retry_at = Instant::now() + Duration::from_secs(1);
+            }
+        }
+    }
+}
+
+/// Handler untuk GET /api/streams/:id/taps
+pub async fn list_taps_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Json<serde_json::Value> {
+    Json(json!({ "stream": stream_id, "taps": state.taps.list(&stream_id) }))
+}
+
+/// Handler untuk PUT /api/streams/:id/taps/:name
+/// Forward a decimated copy of the stream to an inference sidecar (replaces a tap with the same name)
+pub async fn put_tap_handler(
+    AxumPath((stream_id, name)): AxumPath<(String, String)>,
+    State(state): State<AppState>,
+    Json(config): Json<TapConfig>,
+) -> Response {
+    if let Err(message) = config.validate() {
+        return BrokerError::InvalidRequest(message).into_response();
+    }
+    info!(
+        "Starting tap {} for stream {} -> {} at {} fps",
+        name, stream_id, config.url, config.max_fps
+    );
+    let task = {
+        let (state, stream_id, config) = (state.clone(), stream_id.clone(), config.clone());
+        supervisor::supervise(format!("tap {}/{}", stream_id, name), move || {
+            run_tap(state.clone(), stream_id.clone(), config.clone())
+        })
+    };
+    state.taps.start(&stream_id, &name, config.clone(), task);
+    Json(json!({ "stream": stream_id, "name": name, "url": config.url, "max_fps": config.max_fps }))
+        .into_response()
+}
+
+/// Handler untuk DELETE /api/streams/:id/taps/:name
+pub async fn delete_tap_handler(
+    AxumPath((stream_id, name)): AxumPath<(String, String)>,
+    State(state): State<AppState>,
+) -> Result<StatusCode, BrokerError> {
+    if state.taps.stop(&stream_id, &name) {
+        info!("Stopped tap {} for stream {}", name, stream_id);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(BrokerError::not_found("tap", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_rate_limiter() {
+        let mut limiter = FrameRateLimiter::new(2.0);
+        let start = Instant::now();
+        // 30 fps input selama 1 detik -> 2 frame
+        let sent = (0..30)
+            .filter(|i| limiter.admit(start + Duration::from_millis(i * 1000 / 30)))
+            .count();
+        assert_eq!(sent, 2);
+
+        let config: TapConfig = serde_json::from_str(r#"{"url":"tcp://127.0.0.1:9000"}"#).unwrap();
+        assert_eq!(config.max_fps, 1.0);
+        assert!(config.validate().is_ok());
+        let bad: TapConfig = serde_json::from_str(r#"{"url":"udp://x","max_fps":2}"#).unwrap();
+        assert!(bad.validate().is_err());
+        assert!(validate_max_fps(0.0).is_err());
+    }
+}
@@ -0,0 +1,298 @@
+//! Harness test end-to-end: broker sungguhan di port ephemeral plus klien producer/subscriber
+//!
+//! Tests talk to the broker over real sockets, through the same router,
+//! middleware and accept loop as production. Timeout policies run on Tokio's
+//! clock, so a test can call [`pause`] once its connections are up and the
+//! runtime jumps to the next deadline instead of sleeping. While paused, the
+//! clock also advances whenever every task waits on a socket, so assert that
+//! a policy fired *no earlier* than its deadline, not at the exact instant.
+
+use axum::http::StatusCode;
+use bytes::Bytes;
+use futures_util::{SinkExt, StreamExt};
+use std::{net::SocketAddr, time::Duration};
+use tokio::{net::TcpStream, task::JoinHandle};
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, protocol::CloseFrame, Error as WsError, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::{build_router, config::Config, outbound::HttpTarget, server, AppState};
+
+/// Batas tunggu default satu pesan; di clock yang di-pause ini juga waktu virtual
+pub const RECV_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub use tokio::time::pause;
+
+/// Broker yang berjalan selama test; server dihentikan saat di-drop
+pub struct TestBroker {
+    pub state: AppState,
+    pub addr: SocketAddr,
+    server: JoinHandle<()>,
+}
+
+impl TestBroker {
+    /// Start the broker on `127.0.0.1` with an OS-assigned port
+    pub async fn start(config: Config) -> Self {
+        let config = Config {
+            bind_address: "127.0.0.1".to_string(),
+            port: 0,
+            ..config
+        };
+        let state = AppState::new(config);
+        let listener = server::bind(&state.config).await.expect("bind test broker");
+        let addr = listener.local_addr().expect("test broker address");
+        let app = build_router(state.clone());
+        let config = state.config.clone();
+        let server = tokio::spawn(async move {
+            let _ = server::serve(listener, app, None, &config).await;
+        });
+        Self { state, addr, server }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// POST satu frame ke `/ingest/:stream_id`
+    pub async fn post_frame(&self, stream_id: &str, data: impl Into<Bytes>) -> StatusCode {
+        let mut target = HttpTarget::parse(&self.url(&format!("/ingest/{}", stream_id))).expect("ingest URL");
+        target
+            .post("application/octet-stream", &[], data.into())
+            .await
+            .expect("POST /ingest")
+    }
+
+    /// WebSocket producer di `/ingest/:stream_id?...`
+    pub async fn producer(&self, path: &str) -> TestClient {
+        self.connect(&format!("/ingest/{}", path)).await
+    }
+
+    /// WebSocket subscriber di `/ws/:stream_id?...`
+    pub async fn subscriber(&self, path: &str) -> TestClient {
+        self.connect(&format!("/ws/{}", path)).await
+    }
+
+    /// WebSocket di `path` yang menawarkan `protocols` (`Sec-WebSocket-Protocol`)
+    ///
+    /// Returns the subprotocol the broker picked, or the status it rejected the upgrade with.
+    pub async fn connect_with_protocols(
+        &self,
+        path: &str,
+        protocols: &str,
+    ) -> Result<(TestClient, Option<String>), StatusCode> {
+        self.connect_with_headers(path, &[("sec-websocket-protocol", protocols)]).await
+    }
+
+    /// WebSocket di `path` dengan header tambahan (misalnya `Authorization`)
+    ///
+    /// Returns the subprotocol the broker picked, or the status it rejected the upgrade with.
+    pub async fn connect_with_headers(
+        &self,
+        path: &str,
+        headers: &[(&'static str, &str)],
+    ) -> Result<(TestClient, Option<String>), StatusCode> {
+        let url = format!("ws://{}{}", self.addr, path);
+        let mut request = url.as_str().into_client_request().expect("WebSocket request");
+        for (name, value) in headers {
+            request.headers_mut().insert(*name, value.parse().expect("header value"));
+        }
+        match tokio_tungstenite::connect_async(request).await {
+            Ok((socket, response)) => {
+                let protocol = response
+                    .headers()
+                    .get("sec-websocket-protocol")
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string);
+                Ok((TestClient { socket }, protocol))
+            }
+            Err(WsError::Http(response)) => Err(StatusCode::from_u16(response.status().as_u16()).expect("status")),
+            Err(e) => panic!("WebSocket connect to {} failed: {}", url, e),
+        }
+    }
+
+    async fn connect(&self, path: &str) -> TestClient {
+        let url = format!("ws://{}{}", self.addr, path);
+        let (socket, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .unwrap_or_else(|e| panic!("WebSocket connect to {} failed: {}", url, e));
+        TestClient { socket }
+    }
+}
+
+impl Drop for TestBroker {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Klien WebSocket test; setiap `expect_*` gagal (panic) bila pesan lain atau timeout
+pub struct TestClient {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl TestClient {
+    pub async fn send_frame(&mut self, data: &[u8]) {
+        self.socket
+            .send(Message::Binary(data.to_vec()))
+            .await
+            .expect("send frame");
+    }
+
+    /// Pesan teks dari klien, mis. ack atau laporan statistik
+    pub async fn send_text(&mut self, text: &str) {
+        self.socket
+            .send(Message::Text(text.to_string()))
+            .await
+            .expect("send text");
+    }
+
+    /// Next data or close message within `timeout`; `None` once the socket is gone
+    pub async fn next_within(&mut self, timeout: Duration) -> Option<Message> {
+        loop {
+            let message = tokio::time::timeout(timeout, self.socket.next())
+                .await
+                .unwrap_or_else(|_| panic!("no message within {:?}", timeout));
+            match message {
+                Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+                Some(Ok(message)) => return Some(message),
+                Some(Err(_)) | None => return None,
+            }
+        }
+    }
+
+    pub async fn expect_binary(&mut self) -> Vec<u8> {
+        match self.next_within(RECV_TIMEOUT).await {
+            Some(Message::Binary(data)) => data,
+            other => panic!("expected a binary frame, got {:?}", other),
+        }
+    }
+
+    /// Pesan kontrol JSON (mode seq)
+    pub async fn expect_json(&mut self) -> serde_json::Value {
+        match self.next_within(RECV_TIMEOUT).await {
+            Some(Message::Text(text)) => serde_json::from_str(&text).expect("JSON control message"),
+            other => panic!("expected a text message, got {:?}", other),
+        }
+    }
+
+    /// Close frame dari broker, menunggu paling lama `timeout`
+    pub async fn expect_close(&mut self, timeout: Duration) -> Option<CloseFrame<'static>> {
+        match self.next_within(timeout).await {
+            Some(Message::Close(frame)) => frame,
+            other => panic!("expected close, got {:?}", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::Ordering;
+    use tokio::time::Instant;
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+
+    #[tokio::test]
+    async fn test_end_to_end_fan_out_and_drop_policy() {
+        let broker = TestBroker::start(Config {
+            channel_capacity: 4,
+            ..Config::default()
+        })
+        .await;
+
+        let mut subscriber = broker.subscriber("cam1?seq=true").await;
+        assert_eq!(subscriber.expect_json().await["type"], "sync");
+        assert_eq!(broker.post_frame("cam1", "frame-1").await, StatusCode::OK);
+        assert_eq!(subscriber.expect_binary().await, b"frame-1");
+        let mut producer = broker.producer("cam1").await;
+        producer.send_frame(b"frame-ws").await;
+        assert_eq!(subscriber.expect_binary().await, b"frame-ws");
+
+        // Producer lebih cepat dari subscriber: 10 frame tanpa yield, antrian hanya 4
+        broker.state.with_stream("cam1", |entry| {
+            for i in 3..=12 {
+                let _ = entry.publish(Bytes::from(format!("frame-{}", i)));
+            }
+        });
+        let gap = subscriber.expect_json().await;
+        assert_eq!((gap["type"].as_str(), gap["from"].as_u64()), (Some("gap"), Some(3)));
+        let mut last = String::new();
+        for _ in 0..4 {
+            last = String::from_utf8(subscriber.expect_binary().await).unwrap();
+        }
+        assert_eq!(last, "frame-12");
+        let drops = broker.state.streams.lock()["cam1"].counters.drops.load(Ordering::Relaxed);
+        assert_eq!(drops, 6);
+    }
+
+    #[tokio::test]
+    async fn test_producer_timeouts_on_paused_clock() {
+        let broker = TestBroker::start(Config::default()).await;
+        let mut producer = broker.producer("cam1").await;
+
+        // Clock virtual: deadline 10 s tercapai tanpa menunggu 10 s sungguhan
+        pause();
+        let start = Instant::now();
+        let close = producer.expect_close(Duration::from_secs(60)).await.expect("close frame");
+        assert_eq!(close.code, CloseCode::Policy);
+        assert!(start.elapsed() >= Duration::from_secs(broker.state.config.first_frame_timeout_secs));
+
+        // Producer yang berhenti setelah frame pertamanya juga ditutup, setelah batas idle
+        let mut producer = broker.producer("cam2").await;
+        producer.send_frame(b"frame-1").await;
+        let start = Instant::now();
+        let close = producer.expect_close(Duration::from_secs(600)).await.expect("close frame");
+        assert_eq!((close.code, close.reason.as_ref()), (CloseCode::Policy, "no frame received within 60s"));
+        assert!(start.elapsed() >= Duration::from_secs(broker.state.config.producer_idle_timeout_secs));
+    }
+
+    #[tokio::test]
+    async fn test_subprotocol_negotiation() {
+        let broker = TestBroker::start(Config::default()).await;
+
+        // Versi yang tidak dikenal ditolak, bukan diam-diam diberi format lama
+        let rejected = broker.connect_with_protocols("/ws/cam1", "bsb.envelope.v9").await;
+        assert_eq!(rejected.err(), Some(StatusCode::BAD_REQUEST));
+
+        let (mut envelope, protocol) = broker
+            .connect_with_protocols("/ws/cam1", "bsb.envelope.v9,bsb.envelope.v1,bsb.raw.v1")
+            .await
+            .unwrap();
+        assert_eq!(protocol.as_deref(), Some("bsb.envelope.v1"));
+        let (mut raw, protocol) = broker.connect_with_protocols("/ws/cam1", "bsb.raw.v1").await.unwrap();
+        assert_eq!(protocol.as_deref(), Some("bsb.raw.v1"));
+        let mut legacy = broker.subscriber("cam1").await;
+
+        assert_eq!(envelope.expect_json().await["type"], "sync");
+        assert_eq!(broker.post_frame("cam1", "frame-1").await, StatusCode::OK);
+        let data = envelope.expect_binary().await;
+        assert_eq!(u64::from_be_bytes(data[..8].try_into().unwrap()), 1);
+        assert_eq!(&data[16..], b"frame-1");
+        assert_eq!(raw.expect_binary().await, b"frame-1");
+        assert_eq!(legacy.expect_binary().await, b"frame-1");
+
+        let (_, protocol) = broker.connect_with_protocols("/mux?pattern=cam*", "bsb.multiplex.v1").await.unwrap();
+        assert_eq!(protocol.as_deref(), Some("bsb.multiplex.v1"));
+        let rejected = broker.connect_with_protocols("/mux?pattern=cam*", "bsb.raw.v1").await;
+        assert_eq!(rejected.err(), Some(StatusCode::BAD_REQUEST));
+    }
+
+    #[tokio::test]
+    async fn test_strict_streams_rejects_unknown_stream() {
+        let broker = TestBroker::start(Config {
+            strict_streams: true,
+            ..Config::default()
+        })
+        .await;
+
+        let rejected = broker.connect_with_protocols("/ws/cma1", "bsb.raw.v1").await;
+        assert_eq!(rejected.err(), Some(StatusCode::NOT_FOUND));
+        assert!(broker.state.streams.lock().is_empty());
+
+        // Frame pertama dari producer membuat stream
+        assert_eq!(broker.post_frame("cam1", "frame-1").await, StatusCode::ACCEPTED);
+        let mut subscriber = broker.subscriber("cam1").await;
+        assert_eq!(broker.post_frame("cam1", "frame-2").await, StatusCode::OK);
+        assert_eq!(subscriber.expect_binary().await, b"frame-2");
+    }
+}
@@ -0,0 +1,238 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use bytes::Bytes;
+use jpeg_encoder::{ColorType, Encoder};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{collections::HashMap, time::Duration};
+use tokio::task::JoinHandle;
+use tracing::info;
+
+use crate::{error::BrokerError, watermark, AppState};
+
+/// Jenis frame yang dihasilkan test source
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TestPattern {
+    /// 8-byte big-endian frame counter, zero-padded to `frame_bytes`
+    #[default]
+    Counter,
+    /// JPEG color bars with a moving block (one JPEG per frame, MJPEG style)
+    Mjpeg,
+}
+
+/// Body untuk POST /api/streams/:id/test-source
+#[derive(Debug, Clone, Deserialize)]
+pub struct TestSourceBody {
+    #[serde(default)]
+    pub pattern: TestPattern,
+    #[serde(default = "default_fps")]
+    pub fps: u32,
+    #[serde(default = "default_width")]
+    pub width: u16,
+    #[serde(default = "default_height")]
+    pub height: u16,
+    /// Payload size of counter frames (at least 8)
+    #[serde(default = "default_frame_bytes")]
+    pub frame_bytes: usize,
+    /// Stop automatically after this many seconds; runs until deleted when omitted
+    pub duration_secs: Option<u64>,
+}
+
+fn default_fps() -> u32 {
+    10
+}
+
+fn default_width() -> u16 {
+    320
+}
+
+fn default_height() -> u16 {
+    240
+}
+
+fn default_frame_bytes() -> usize {
+    8
+}
+
+impl TestSourceBody {
+    fn validate(&self) -> Result<(), &'static str> {
+        if !(1..=120).contains(&self.fps) {
+            return Err("fps must be between 1 and 120");
+        }
+        if !(16..=4096).contains(&self.width) || !(16..=4096).contains(&self.height) {
+            return Err("width and height must be between 16 and 4096");
+        }
+        if !(8..=16 << 20).contains(&self.frame_bytes) {
+            return Err("frame_bytes must be between 8 and 16777216");
+        }
+        Ok(())
+    }
+
+    /// Buat frame ke-`n` sesuai pattern
+    pub fn render(&self, n: u64) -> Bytes {
+        match self.pattern {
+            TestPattern::Counter => {
+                let mut buf = vec![0u8; self.frame_bytes];
+                buf[..8].copy_from_slice(&n.to_be_bytes());
+                Bytes::from(buf)
+            }
+            TestPattern::Mjpeg => render_jpeg(self.width, self.height, n),
+        }
+    }
+}
+
+/// Color bars (SMPTE-ish order) with a white block sweeping left to right
+fn render_jpeg(width: u16, height: u16, n: u64) -> Bytes {
+    const BARS: [[u8; 3]; 7] = [
+        [192, 192, 192],
+        [192, 192, 0],
+        [0, 192, 192],
+        [0, 192, 0],
+        [192, 0, 192],
+        [192, 0, 0],
+        [0, 0, 192],
+    ];
+    let (w, h) = (width as usize, height as usize);
+    let block = (h / 4).max(8);
+    let block_x = (n as usize * 4) % w.saturating_sub(block).max(1);
+    let block_y = (h - block) / 2;
+
+    let mut rgb = Vec::with_capacity(w * h * 3);
+    for y in 0..h {
+        for x in 0..w {
+            let in_block = (block_x..block_x + block).contains(&x) && (block_y..block_y + block).contains(&y);
+            let pixel = if in_block { [255, 255, 255] } else { BARS[x * BARS.len() / w] };
+            rgb.extend_from_slice(&pixel);
+        }
+    }
+
+    let mut jpeg = Vec::new();
+    // Encoding RGB yang sudah tervalidasi ukurannya tidak bisa gagal
+    Encoder::new(&mut jpeg, 75)
+        .encode(&rgb, width, height, ColorType::Rgb)
+        .expect("test pattern has the declared dimensions");
+    Bytes::from(jpeg)
+}
+
+/// Test source yang sedang berjalan, satu per stream
+#[derive(Debug, Default)]
+pub struct TestSources {
+    running: Mutex<HashMap<String, JoinHandle<()>>>,
+}
+
+impl TestSources {
+    /// Ganti test source untuk stream ini (yang lama dihentikan)
+    fn start(&self, stream_id: &str, task: JoinHandle<()>) {
+        let mut running = self.running.lock();
+        running.retain(|_, task| !task.is_finished());
+        if let Some(old) = running.insert(stream_id.to_string(), task) {
+            old.abort();
+        }
+    }
+
+    fn stop(&self, stream_id: &str) -> bool {
+        match self.running.lock().remove(stream_id) {
+            Some(task) => {
+                let was_running = !task.is_finished();
+                task.abort();
+                was_running
+            }
+            None => false,
+        }
+    }
+}
+
+async fn run_test_source(state: AppState, stream_id: String, body: TestSourceBody) {
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / body.fps as f64));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let deadline = body
+        .duration_secs
+        .map(|secs| tokio::time::Instant::now() + Duration::from_secs(secs));
+
+    for n in 1.. {
+        interval.tick().await;
+        if deadline.is_some_and(|deadline| tokio::time::Instant::now() >= deadline) {
+            break;
+        }
+        let Some(frame) = watermark::apply(&state, &stream_id, body.render(n), None) else {
+            continue;
+        };
+        // Stream selalu dibuat supaya test source terlihat di /debug sebelum ada subscriber
+        let _ = state.with_stream(&stream_id, |entry| entry.publish(frame));
+    }
+    info!("Test source finished for stream: {}", stream_id);
+}
+
+/// Handler untuk POST /api/streams/:id/test-source
+/// Start (or replace) a synthetic producer for load testing and client development
+pub async fn start_test_source_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+    body: Option<Json<TestSourceBody>>,
+) -> Response {
+    let body = match body {
+        Some(Json(body)) => body,
+        None => serde_json::from_value(json!({})).expect("all fields have defaults"),
+    };
+    if let Err(message) = body.validate() {
+        return BrokerError::InvalidRequest(message.into()).into_response();
+    }
+
+    info!(
+        "Starting {:?} test source for stream {} at {} fps",
+        body.pattern, stream_id, body.fps
+    );
+    let task = tokio::spawn(run_test_source(state.clone(), stream_id.clone(), body.clone()));
+    state.test_sources.start(&stream_id, task);
+
+    (
+        StatusCode::ACCEPTED,
+        Json(json!({
+            "stream": stream_id,
+            "pattern": body.pattern,
+            "fps": body.fps,
+            "width": body.width,
+            "height": body.height,
+            "duration_secs": body.duration_secs,
+        })),
+    )
+        .into_response()
+}
+
+/// Handler untuk DELETE /api/streams/:id/test-source
+pub async fn stop_test_source_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Result<StatusCode, BrokerError> {
+    if state.test_sources.stop(&stream_id) {
+        info!("Stopped test source for stream: {}", stream_id);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(BrokerError::not_found("test source", stream_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_patterns() {
+        let mut body: TestSourceBody = serde_json::from_str(r#"{"frame_bytes":16}"#).unwrap();
+        let frame = body.render(3);
+        assert_eq!(frame.len(), 16);
+        assert_eq!(&frame[..8], &3u64.to_be_bytes());
+
+        body.pattern = TestPattern::Mjpeg;
+        body.width = 64;
+        body.height = 48;
+        let jpeg = body.render(1);
+        assert_eq!(&jpeg[..2], &[0xFF, 0xD8]);
+        assert_eq!(&jpeg[jpeg.len() - 2..], &[0xFF, 0xD9]);
+    }
+}
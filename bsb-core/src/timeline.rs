@@ -0,0 +1,296 @@
+use axum::{
+    extract::{Path as AxumPath, Query, State},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    connections::ConnectionKind, error::BrokerError, registry::StreamEvent, replay::ReplayReason,
+    sniff::PayloadFormat, stats::unix_now_ms, AppState,
+};
+
+/// Pengaturan timeline event per stream dari `STREAM_TIMELINE_*`
+#[derive(Debug, Clone, Copy)]
+pub struct TimelineConfig {
+    /// Events kept per stream; the oldest are dropped first (0 = no timeline)
+    pub capacity: usize,
+    /// Silence between two frames that is recorded as a gap (0 = gaps not recorded)
+    pub gap: Duration,
+}
+
+impl Default for TimelineConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 256,
+            gap: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Kejadian penting pada sebuah stream
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TimelineEvent {
+    /// A WebSocket producer sent its first frame
+    ProducerConnected { connection: u64, protocol: &'static str },
+    /// A WebSocket producer went away after sending `frames`
+    ProducerDisconnected { connection: u64, frames: u64 },
+    /// The broker closed a producer or subscriber connection
+    Kicked { role: ConnectionKind, reason: String },
+    /// No frame arrived for `silent_ms` before frame `seq`
+    Gap { silent_ms: u64 },
+    /// The sniffed payload format settled or changed
+    FormatChanged { from: PayloadFormat, to: PayloadFormat },
+    #[cfg(feature = "recording")]
+    RecordingStarted,
+    #[cfg(feature = "recording")]
+    RecordingStopped,
+    /// `POST /api/streams/:id/record/trigger`; the recording starts `pre_secs` earlier
+    #[cfg(feature = "recording")]
+    RecordingTriggered { pre_secs: u64, reason: Option<String> },
+    /// The disk reached `STORAGE_CRITICAL_PERCENT` with `STORAGE_PAUSE_RECORDING` set
+    #[cfg(feature = "recording")]
+    RecordingPaused,
+    #[cfg(feature = "recording")]
+    RecordingResumed,
+    /// A segment could not be opened or written; frames are lost until the next one opens
+    #[cfg(feature = "recording")]
+    RecordingFailed { error: String },
+    /// An envelope ingest frame was dropped by replay protection; later rejections
+    /// are only counted until a frame is accepted again
+    ReplayRejected { producer_seq: u64, highest: u64, reason: ReplayReason },
+    /// A control event also sent to subscribers (`stream_ended`, `producer_stalled`, ...)
+    Control { event: StreamEvent },
+}
+
+/// Satu entri timeline: kapan, pada frame ke berapa, dan apa yang terjadi
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEntry {
+    pub at_ms: u64,
+    /// Last frame published when the event happened
+    pub seq: u64,
+    #[serde(flatten)]
+    pub event: TimelineEvent,
+}
+
+/// Riwayat kejadian per stream dengan kapasitas terbatas
+///
+/// Filled from the ingest path and the connection tasks under the registry
+/// lock, read by `GET /api/streams/:stream_id/events`. Gaps are measured
+/// between published frames, so they show up for HTTP and pulled producers
+/// too, not only for WebSocket ones with a watchdog.
+#[derive(Debug)]
+pub struct Timeline {
+    config: TimelineConfig,
+    entries: VecDeque<TimelineEntry>,
+    /// Entries dropped to stay within capacity
+    evicted: u64,
+    last_frame: Option<Instant>,
+    /// Last format reported, to tell a change from a repeat
+    format: PayloadFormat,
+    /// Sniff the next frame again: a producer (re)connected or came back from a gap
+    resniff: bool,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new(TimelineConfig::default())
+    }
+}
+
+impl Timeline {
+    pub fn new(config: TimelineConfig) -> Self {
+        Self {
+            config,
+            entries: VecDeque::new(),
+            evicted: 0,
+            last_frame: None,
+            format: PayloadFormat::Unknown,
+            resniff: false,
+        }
+    }
+
+    pub fn record(&mut self, seq: u64, event: TimelineEvent) {
+        if self.config.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.config.capacity {
+            self.entries.pop_front();
+            self.evicted += 1;
+        }
+        self.entries.push_back(TimelineEntry {
+            at_ms: unix_now_ms(),
+            seq,
+            event,
+        });
+    }
+
+    /// Catat gap sebelum frame `seq`; `true` bila format frame ini perlu diperiksa ulang
+    pub fn on_frame(&mut self, seq: u64, received_at: Instant) -> bool {
+        if let Some(last) = self.last_frame.replace(received_at) {
+            let silent = received_at.saturating_duration_since(last);
+            if !self.config.gap.is_zero() && silent >= self.config.gap {
+                self.record(seq, TimelineEvent::Gap {
+                    silent_ms: silent.as_millis() as u64,
+                });
+                self.resniff = true;
+            }
+        }
+        std::mem::take(&mut self.resniff)
+    }
+
+    /// Catat format frame bila berbeda dari yang terakhir dilaporkan
+    pub fn on_format(&mut self, seq: u64, format: PayloadFormat) {
+        if format != self.format {
+            let from = std::mem::replace(&mut self.format, format);
+            self.record(seq, TimelineEvent::FormatChanged { from, to: format });
+        }
+    }
+
+    pub fn producer_connected(&mut self, seq: u64, connection: u64, protocol: &'static str) {
+        self.record(seq, TimelineEvent::ProducerConnected { connection, protocol });
+        self.resniff = true;
+    }
+}
+
+/// Catat event pada stream bila stream-nya ada
+pub fn record(state: &AppState, stream_id: &str, event: TimelineEvent) {
+    if let Some(entry) = state.streams.lock().get_mut(stream_id) {
+        let seq = entry.last_seq();
+        entry.timeline.record(seq, event);
+    }
+}
+
+/// Query parameter untuk GET /api/streams/:stream_id/events
+#[derive(Debug, Default, Deserialize)]
+pub struct EventsQuery {
+    /// Only events at or after this unix time in ms
+    since_ms: Option<u64>,
+    /// Only events of this `type`
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    /// Newest N matching events (default: all kept)
+    limit: Option<usize>,
+}
+
+/// Handler untuk GET /api/streams/:stream_id/events
+/// Timeline kejadian stream, terlama lebih dulu
+pub async fn stream_events_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    Query(query): Query<EventsQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, BrokerError> {
+    let streams = state.streams.lock();
+    let entry = streams
+        .get(&stream_id)
+        .ok_or_else(|| BrokerError::StreamNotFound(stream_id.clone()))?;
+    let timeline = &entry.timeline;
+    let matching: Vec<&TimelineEntry> = timeline
+        .entries
+        .iter()
+        .filter(|item| query.since_ms.is_none_or(|since| item.at_ms >= since))
+        .filter(|item| {
+            query.kind.as_deref().is_none_or(|kind| {
+                serde_json::to_value(&item.event).is_ok_and(|value| value["type"] == kind)
+            })
+        })
+        .collect();
+    let skip = matching.len().saturating_sub(query.limit.unwrap_or(usize::MAX));
+    Ok(Json(json!({
+        "stream": stream_id,
+        "last_seq": entry.last_seq(),
+        "capacity": timeline.config.capacity,
+        "evicted": timeline.evicted,
+        "events": &matching[skip..],
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeline_bounds_and_detects_gaps() {
+        let mut timeline = Timeline::new(TimelineConfig {
+            capacity: 3,
+            gap: Duration::from_secs(2),
+        });
+        let start = Instant::now();
+        assert!(!timeline.on_frame(1, start));
+        assert!(!timeline.on_frame(2, start + Duration::from_secs(1)));
+        // Diam 3 detik: gap dicatat dan format frame berikutnya diperiksa ulang
+        assert!(timeline.on_frame(3, start + Duration::from_secs(4)));
+        assert!(matches!(timeline.entries[0].event, TimelineEvent::Gap { silent_ms: 3000 }));
+        assert_eq!(timeline.entries[0].seq, 3);
+
+        timeline.on_format(3, PayloadFormat::Mjpeg);
+        timeline.on_format(4, PayloadFormat::Mjpeg);
+        timeline.on_format(5, PayloadFormat::H264);
+        assert_eq!(timeline.entries.len(), 3);
+        // Kapasitas 3: gap terlama dibuang
+        timeline.producer_connected(5, 1, "raw");
+        assert_eq!(timeline.evicted, 1);
+        let kinds: Vec<_> = timeline
+            .entries
+            .iter()
+            .map(|item| serde_json::to_value(item).unwrap()["type"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(kinds, vec!["format_changed", "format_changed", "producer_connected"]);
+        let change = serde_json::to_value(&timeline.entries[1]).unwrap();
+        assert_eq!((change["from"].as_str(), change["to"].as_str()), (Some("mjpeg"), Some("h264")));
+
+        let mut off = Timeline::new(TimelineConfig {
+            capacity: 0,
+            gap: Duration::ZERO,
+        });
+        off.on_frame(1, start);
+        off.on_frame(2, start + Duration::from_secs(60));
+        off.record(2, TimelineEvent::Gap { silent_ms: 1 });
+        assert!(off.entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_events_endpoint_tells_what_happened_to_stream() {
+        use crate::{config::Config, testing::TestBroker};
+
+        let broker = TestBroker::start(Config::default()).await;
+        let _viewer = broker.subscriber("cam7").await;
+        let mut producer = broker.producer("cam7").await;
+        for _ in 0..4 {
+            producer.send_frame(&[0xFF, 0xD8, 0xFF, 0xE0, 0, 0]).await;
+        }
+        drop(producer);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let events = |query: &str| {
+            let uri: axum::http::Uri = format!("/?{}", query).parse().unwrap();
+            let query = Query::try_from_uri(&uri).unwrap();
+            stream_events_handler(AxumPath("cam7".to_string()), query, State(broker.state.clone()))
+        };
+        let Json(body) = events("").await.unwrap();
+        let kinds: Vec<_> = body["events"].as_array().unwrap().iter().map(|event| event["type"].clone()).collect();
+        assert_eq!(kinds, vec!["producer_connected", "format_changed", "producer_disconnected"]);
+        assert_eq!(body["events"][1]["to"], "mjpeg");
+        assert_eq!(body["events"][2]["frames"], 4);
+
+        let Json(body) = events("type=format_changed").await.unwrap();
+        assert_eq!(body["events"].as_array().unwrap().len(), 1);
+        let Json(body) = events("limit=1").await.unwrap();
+        assert_eq!(body["events"][0]["type"], "producer_disconnected");
+        let future_ms = unix_now_ms() + 60_000;
+        let Json(body) = events(&format!("since_ms={}", future_ms)).await.unwrap();
+        assert!(body["events"].as_array().unwrap().is_empty());
+
+        let missing = stream_events_handler(
+            AxumPath("cam8".to_string()),
+            Query(EventsQuery::default()),
+            State(broker.state.clone()),
+        );
+        assert!(matches!(missing.await, Err(BrokerError::StreamNotFound(_))));
+    }
+}
@@ -0,0 +1,164 @@
+use std::{fs::File, io::BufReader, sync::Arc};
+use tokio_rustls::{
+    rustls::{
+        pki_types::{CertificateDer, PrivateKeyDer},
+        server::WebPkiClientVerifier,
+        ClientConfig, RootCertStore, ServerConfig,
+    },
+    TlsAcceptor,
+};
+use x509_parser::{extensions::GeneralName, prelude::parse_x509_certificate};
+
+use crate::{config::TlsConfig, hooks, mux::glob_match};
+
+/// Identitas client yang diambil dari sertifikat TLS (CN dan SAN DNS/URI)
+///
+/// Inserted as a request extension on every request of an mTLS connection
+/// (see `server::serve`).
+#[derive(Debug, Clone, Default)]
+pub struct ClientIdentity {
+    pub names: Vec<String>,
+}
+
+/// Mapping from certificate names to the streams they may ingest into
+///
+/// Format: `name=pattern,pattern;name=pattern`, e.g.
+/// `camera-01=cam1,cam2;gateway.site-a=site-a/*`. Both sides accept globs.
+#[derive(Debug, Clone, Default)]
+pub struct ClientPermissions {
+    rules: Vec<(String, Vec<String>)>,
+}
+
+impl ClientPermissions {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for rule in spec.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            let (name, patterns) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid TLS_CLIENT_PERMISSIONS rule: {}", rule))?;
+            let patterns = patterns
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(str::to_string)
+                .collect();
+            rules.push((name.trim().to_string(), patterns));
+        }
+        Ok(Self { rules })
+    }
+
+    /// Apakah identitas ini boleh mengirim frame ke `stream_id`
+    pub fn allows(&self, identity: &ClientIdentity, stream_id: &str) -> bool {
+        self.rules.iter().any(|(name, patterns)| {
+            identity.names.iter().any(|n| glob_match(name, n))
+                && patterns.iter().any(|p| glob_match(p, stream_id))
+        })
+    }
+}
+
+/// Extract CN and SAN names from the leaf certificate
+pub fn identity_from_certs(certs: &[CertificateDer<'_>]) -> Option<ClientIdentity> {
+    let (_, cert) = parse_x509_certificate(certs.first()?.as_ref()).ok()?;
+    let mut names: Vec<String> = cert
+        .subject()
+        .iter_common_name()
+        .filter_map(|cn| cn.as_str().ok().map(str::to_string))
+        .collect();
+    if let Ok(Some(san)) = cert.subject_alternative_name() {
+        for name in &san.value.general_names {
+            match name {
+                GeneralName::DNSName(dns) => names.push(dns.to_string()),
+                GeneralName::URI(uri) => names.push(uri.to_string()),
+                _ => {}
+            }
+        }
+    }
+    Some(ClientIdentity { names })
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read certificates from {}: {}", path, e))
+}
+
+fn load_key(path: &str) -> Result<PrivateKeyDer<'static>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .map_err(|e| format!("Failed to read private key from {}: {}", path, e))?
+        .ok_or_else(|| format!("No private key found in {}", path))
+}
+
+/// Buat TLS acceptor dari konfigurasi
+///
+/// Client certificates are requested and validated against the CA when
+/// `client_ca_path` is set, but not required at the handshake: browsers on
+/// the WebSocket endpoint have none. Ingest handlers enforce presence instead.
+pub fn build_acceptor(tls: &TlsConfig) -> Result<TlsAcceptor, String> {
+    let provider = hooks::tls_backend().provider();
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("Invalid TLS protocol configuration: {}", e))?;
+
+    let builder = match &tls.client_ca_path {
+        Some(ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca_path)? {
+                roots
+                    .add(cert)
+                    .map_err(|e| format!("Invalid client CA certificate: {}", e))?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .allow_unauthenticated()
+                .build()
+                .map_err(|e| format!("Invalid client CA configuration: {}", e))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut config = builder
+        .with_single_cert(load_certs(&tls.cert_path)?, load_key(&tls.key_path)?)
+        .map_err(|e| format!("Invalid TLS certificate/key: {}", e))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Client TLS config untuk koneksi keluar (federation), dipercaya via CA bundle sendiri
+pub fn client_config(ca_path: &str) -> Result<Arc<ClientConfig>, String> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(ca_path)? {
+        roots
+            .add(cert)
+            .map_err(|e| format!("Invalid CA certificate in {}: {}", ca_path, e))?;
+    }
+    let config = ClientConfig::builder_with_provider(hooks::tls_backend().provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("Invalid TLS protocol configuration: {}", e))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_permissions() {
+        let permissions =
+            ClientPermissions::parse("camera-01=cam1,cam2; gateway.*=site-a/*").unwrap();
+        let camera = ClientIdentity {
+            names: vec!["camera-01".to_string()],
+        };
+        let gateway = ClientIdentity {
+            names: vec!["gateway.site-a.local".to_string()],
+        };
+        assert!(permissions.allows(&camera, "cam2"));
+        assert!(!permissions.allows(&camera, "cam3"));
+        assert!(permissions.allows(&gateway, "site-a/cam9"));
+        assert!(!permissions.allows(&gateway, "site-b/cam1"));
+        assert!(ClientPermissions::parse("no-equals-sign").is_err());
+    }
+}
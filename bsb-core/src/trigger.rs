@@ -0,0 +1,212 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    response::{IntoResponse, Json, Response},
+};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::json;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::time::Instant;
+use tracing::info;
+
+use crate::{
+    error::BrokerError,
+    recorder, runtime, sources,
+    stats::unix_now_ms,
+    timeline::{self, TimelineEvent},
+    AppState,
+};
+
+/// Pre-roll terpanjang; lebih jauh dari ini DVR buffer biasanya sudah tidak menyimpan frame-nya
+pub const MAX_PRE_SECS: u64 = 300;
+pub const MAX_POST_SECS: u64 = 3600;
+
+/// Body untuk POST /api/streams/:id/record/trigger
+#[derive(Debug, Deserialize)]
+pub struct TriggerRequest {
+    /// Seconds before the trigger taken from the DVR buffer
+    #[serde(default = "default_pre_secs")]
+    pub pre_secs: u64,
+    /// Keep recording this long after the trigger; a later trigger extends it
+    #[serde(default = "default_post_secs")]
+    pub post_secs: u64,
+    /// Stored in the stream timeline, e.g. the alarm zone
+    pub reason: Option<String>,
+}
+
+fn default_pre_secs() -> u64 {
+    10
+}
+
+fn default_post_secs() -> u64 {
+    30
+}
+
+impl TriggerRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.pre_secs > MAX_PRE_SECS {
+            return Err(format!("pre_secs must be at most {}", MAX_PRE_SECS));
+        }
+        if !(1..=MAX_POST_SECS).contains(&self.post_secs) {
+            return Err(format!("post_secs must be between 1 and {}", MAX_POST_SECS));
+        }
+        Ok(())
+    }
+}
+
+/// Rekaman yang dipicu dari luar, per stream, dengan batas waktu yang bisa diperpanjang
+#[derive(Debug, Default)]
+pub struct RecordingTriggers {
+    running: Mutex<HashMap<String, Arc<Mutex<Instant>>>>,
+}
+
+impl RecordingTriggers {
+    /// Mulai rekaman dengan pre-roll, atau perpanjang yang sedang berjalan; `true` bila baru dimulai
+    pub fn trigger(&self, state: &AppState, stream_id: &str, pre_roll: Duration, until: Instant) -> bool {
+        let mut running = self.running.lock();
+        if let Some(deadline) = running.get(stream_id) {
+            let mut deadline = deadline.lock();
+            *deadline = (*deadline).max(until);
+            return false;
+        }
+        let deadline = Arc::new(Mutex::new(until));
+        running.insert(stream_id.to_string(), deadline.clone());
+
+        let (state, stream_id) = (state.clone(), stream_id.to_string());
+        runtime::disk().spawn(async move {
+            let stop = wait_for_deadline(state.clone(), stream_id.clone(), deadline.clone());
+            recorder::record_until(state.clone(), stream_id.clone(), pre_roll, stop).await;
+            // Recorder berhenti sendiri (stream dihapus): trigger berikutnya memulai rekaman baru
+            let mut running = state.triggers.running.lock();
+            if running.get(&stream_id).is_some_and(|current| Arc::ptr_eq(current, &deadline)) {
+                running.remove(&stream_id);
+            }
+        });
+        true
+    }
+}
+
+/// Selesai saat batas waktu lewat tanpa diperpanjang
+///
+/// The entry is removed under the same lock `trigger` extends it with, so a
+/// trigger arriving as the recording stops starts a new one instead of
+/// extending a recording that is already closing.
+async fn wait_for_deadline(state: AppState, stream_id: String, deadline: Arc<Mutex<Instant>>) {
+    loop {
+        let until = *deadline.lock();
+        tokio::time::sleep_until(until).await;
+        let mut running = state.triggers.running.lock();
+        if *deadline.lock() <= Instant::now() {
+            running.remove(&stream_id);
+            return;
+        }
+    }
+}
+
+/// Handler untuk POST /api/streams/:id/record/trigger
+/// Record the stream from `pre_secs` before now until `post_secs` after the last trigger
+pub async fn trigger_recording_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+    Json(request): Json<TriggerRequest>,
+) -> Response {
+    if let Err(message) = request.validate() {
+        return BrokerError::InvalidRequest(message).into_response();
+    }
+    if !state.streams.lock().contains_key(&stream_id) {
+        return BrokerError::StreamNotFound(stream_id).into_response();
+    }
+    let TriggerRequest { pre_secs, post_secs, reason } = request;
+    info!("Recording of stream {} triggered ({:?})", stream_id, reason);
+    timeline::record(&state, &stream_id, TimelineEvent::RecordingTriggered { pre_secs, reason });
+
+    // Stream yang direkam terus sudah berisi pre-roll dan post-roll-nya
+    let continuous = sources::recorded_always(&state, &stream_id);
+    let started = !continuous
+        && state.triggers.trigger(
+            &state,
+            &stream_id,
+            Duration::from_secs(pre_secs),
+            Instant::now() + Duration::from_secs(post_secs),
+        );
+    let now_ms = unix_now_ms();
+    Json(json!({
+        "stream": stream_id,
+        "started": started,
+        "continuous": continuous,
+        "from_ms": now_ms.saturating_sub(pre_secs * 1000),
+        "until_ms": now_ms + post_secs * 1000,
+    }))
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, segment, testing::TestBroker};
+    use axum::body::to_bytes;
+
+    #[tokio::test]
+    async fn test_trigger_records_frames_from_before_the_alarm() {
+        let root = std::env::temp_dir().join(format!("bsb-trigger-test-{}", std::process::id()));
+        let broker = TestBroker::start(Config {
+            recordings_dir: root.to_str().unwrap().to_string(),
+            recording_sync_ms: 50,
+            ..Config::default()
+        })
+        .await;
+        // Stream statis: frame masuk DVR walau belum ada yang menonton
+        broker.state.with_stream("gate", |_| ());
+        for seq in 1..=3 {
+            assert!(broker.post_frame("gate", format!("before-{}", seq)).await.is_success());
+        }
+
+        let trigger = |post_secs: u64| {
+            let request = TriggerRequest {
+                pre_secs: 10,
+                post_secs,
+                reason: Some("zone 3".to_string()),
+            };
+            trigger_recording_handler(AxumPath("gate".to_string()), State(broker.state.clone()), Json(request))
+        };
+        let body = to_bytes(trigger(1).await.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!((body["started"].as_bool(), body["continuous"].as_bool()), (Some(true), Some(false)));
+        // Trigger kedua memperpanjang rekaman yang sama
+        let body = to_bytes(trigger(2).await.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&body).unwrap()["started"], false);
+        broker.post_frame("gate", "after").await;
+
+        // Berhenti 2 detik setelah trigger: segmen ditutup dengan metadata
+        let dir = segment::stream_dir(root.to_str().unwrap(), "gate");
+        let mut closed = Vec::new();
+        for _ in 0..60 {
+            closed = segment::load_segments(&dir).unwrap_or_default();
+            if closed.first().is_some_and(|segment| !segment.in_progress) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        let meta = &closed[0].meta;
+        assert_eq!((meta.first_seq, meta.last_seq, meta.frames), (1, 4, 4));
+        assert!(!broker.state.triggers.running.lock().contains_key("gate"));
+
+        let Json(events) = timeline::stream_events_handler(
+            AxumPath("gate".to_string()),
+            axum::extract::Query(Default::default()),
+            State(broker.state.clone()),
+        )
+        .await
+        .unwrap();
+        let triggered = events["events"].as_array().unwrap().iter().find(|event| event["type"] == "recording_triggered");
+        assert_eq!(triggered.unwrap()["reason"], "zone 3");
+
+        let missing = trigger_recording_handler(
+            AxumPath("nobody".to_string()),
+            State(broker.state.clone()),
+            Json(TriggerRequest { pre_secs: 5, post_secs: 5, reason: None }),
+        );
+        assert_eq!(missing.await.status(), axum::http::StatusCode::NOT_FOUND);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
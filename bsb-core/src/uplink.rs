@@ -0,0 +1,927 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use futures_util::{SinkExt, StreamExt};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpStream, sync::broadcast, task::JoinHandle};
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, Error as WsError, Message as WsMessage},
+    Connector, MaybeTlsStream, WebSocketStream,
+};
+use tracing::{info, warn};
+
+use crate::{
+    error::BrokerError,
+    federation::encode_path_segment,
+    outbound,
+    registry::Frame,
+    spill,
+    spool::Spool,
+    stats::unix_now_ms,
+    supervisor, tls,
+    ws::Subprotocol,
+    AppState,
+};
+
+/// Probe sukses berturut-turut sebelum kembali ke destination berprioritas lebih tinggi
+const FAILBACK_PROBES: u32 = 3;
+
+/// Link aktif dianggap mati setelah sekian interval tanpa pong
+const MISSED_PONGS: u32 = 3;
+
+/// Body untuk PUT /api/streams/:id/uplink (juga `uplink` di `STATIC_STREAMS_FILE`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UplinkConfig {
+    /// Upstream brokers (`ws://` or `wss://`, no path) in priority order
+    pub destinations: Vec<String>,
+    /// Stream ID on the upstream broker, defaults to the local ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_id: Option<String>,
+    /// Bearer token for the upstream `/ingest` endpoint
+    #[serde(default, skip_serializing)]
+    pub token: Option<String>,
+    /// Interval of destination probes and of delivery acknowledgements on the active link
+    #[serde(default = "default_health_interval_ms")]
+    pub health_interval_ms: u64,
+    /// Buffer frames on disk while no upstream is reachable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store_forward: Option<StoreForwardConfig>,
+}
+
+fn default_health_interval_ms() -> u64 {
+    2000
+}
+
+/// `store_forward` di [`UplinkConfig`]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StoreForwardConfig {
+    /// Disk budget of the spool; the oldest segments are dropped beyond it
+    #[serde(default = "default_spool_max_bytes")]
+    pub max_bytes: u64,
+    /// Replay pace as a multiple of the original frame timing (0 = as fast as the link takes)
+    #[serde(default = "default_replay_speed")]
+    pub replay_speed: f64,
+}
+
+fn default_spool_max_bytes() -> u64 {
+    1 << 30
+}
+
+fn default_replay_speed() -> f64 {
+    4.0
+}
+
+impl UplinkConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.destinations.is_empty() {
+            return Err("at least one destination is required".to_string());
+        }
+        for destination in &self.destinations {
+            let uri: axum::http::Uri = destination
+                .parse()
+                .map_err(|e| format!("invalid destination {:?}: {}", destination, e))?;
+            if !matches!(uri.scheme_str(), Some("ws" | "wss")) || uri.host().is_none() {
+                return Err(format!("unsupported destination {:?} (use ws:// or wss://)", destination));
+            }
+        }
+        if !(100..=60_000).contains(&self.health_interval_ms) {
+            return Err("health_interval_ms must be between 100 and 60000".to_string());
+        }
+        if let Some(store_forward) = &self.store_forward {
+            if store_forward.max_bytes == 0 {
+                return Err("store_forward.max_bytes must be greater than 0".to_string());
+            }
+            let speed = store_forward.replay_speed;
+            if !(speed == 0.0 || (1.0..=100.0).contains(&speed)) {
+                return Err("store_forward.replay_speed must be 0 or between 1 and 100".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Kondisi satu destination menurut probe terakhir
+#[derive(Debug, Clone, Serialize)]
+pub struct DestinationStatus {
+    pub url: String,
+    pub healthy: bool,
+    /// Successful probes in a row
+    pub successes: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Status uplink satu stream, dibaca GET /api/streams/:id/uplink
+#[derive(Debug, Clone, Serialize)]
+pub struct UplinkStatus {
+    pub destinations: Vec<DestinationStatus>,
+    /// Index into `destinations` of the connected upstream
+    pub active: Option<usize>,
+    /// Times the uplink moved to a different destination
+    pub switches: u64,
+    /// Last local seq written to the upstream
+    pub last_sent_seq: u64,
+    /// Last local seq the upstream is known to have received
+    pub confirmed_seq: u64,
+    pub frames_sent: u64,
+    /// Frames sent from the DVR to catch up after a reconnect, switch or lag
+    pub frames_caught_up: u64,
+    /// Frames that left the DVR before they could be caught up
+    pub frames_missed: u64,
+    /// Store-and-forward: frames are being written to the spool
+    pub spooling: bool,
+    pub spooled_frames: u64,
+    pub spooled_bytes: u64,
+    /// Spooled frames delivered upstream with their original timestamps
+    pub frames_forwarded: u64,
+    /// Frames dropped because the spool reached `max_bytes`
+    pub spool_dropped: u64,
+}
+
+impl UplinkStatus {
+    fn new(config: &UplinkConfig, start_seq: u64) -> Self {
+        Self {
+            destinations: config
+                .destinations
+                .iter()
+                .map(|url| DestinationStatus {
+                    url: url.clone(),
+                    // Belum diprobe: coba dulu, urut prioritas
+                    healthy: true,
+                    successes: 0,
+                    last_error: None,
+                })
+                .collect(),
+            active: None,
+            switches: 0,
+            last_sent_seq: start_seq,
+            confirmed_seq: start_seq,
+            frames_sent: 0,
+            frames_caught_up: 0,
+            frames_missed: 0,
+            spooling: false,
+            spooled_frames: 0,
+            spooled_bytes: 0,
+            frames_forwarded: 0,
+            spool_dropped: 0,
+        }
+    }
+
+    /// Destination sehat dengan prioritas tertinggi
+    fn preferred(&self) -> Option<usize> {
+        self.destinations.iter().position(|d| d.healthy)
+    }
+
+    /// Destination berprioritas lebih tinggi dari yang aktif sudah stabil sehat lagi
+    fn should_fail_back(&self) -> bool {
+        match (self.preferred(), self.active) {
+            (Some(preferred), Some(active)) => {
+                preferred < active && self.destinations[preferred].successes >= FAILBACK_PROBES
+            }
+            _ => false,
+        }
+    }
+
+    fn record_probe(&mut self, index: usize, result: Result<(), String>) {
+        let destination = &mut self.destinations[index];
+        match result {
+            Ok(()) => {
+                destination.healthy = true;
+                destination.successes = destination.successes.saturating_add(1);
+            }
+            Err(e) => {
+                destination.healthy = false;
+                destination.successes = 0;
+                destination.last_error = Some(e);
+            }
+        }
+    }
+}
+
+/// Uplink yang berjalan, per stream lokal
+struct RunningUplink {
+    config: UplinkConfig,
+    status: Arc<Mutex<UplinkStatus>>,
+    task: JoinHandle<()>,
+}
+
+/// Uplink yang dikonfigurasi lewat admin API atau `STATIC_STREAMS_FILE`
+#[derive(Default)]
+pub struct Uplinks {
+    running: Mutex<HashMap<String, RunningUplink>>,
+}
+
+impl Uplinks {
+    /// Mulai (atau ganti) uplink untuk `stream_id`
+    pub fn start(&self, state: &AppState, stream_id: &str, config: UplinkConfig) {
+        // Uplink baru mulai dari frame live; frame lama di DVR tidak dikirim ulang
+        let start_seq = state.with_stream(stream_id, |entry| entry.last_seq());
+        let status = Arc::new(Mutex::new(UplinkStatus::new(&config, start_seq)));
+        let task = {
+            let (state, stream_id, config, status) =
+                (state.clone(), stream_id.to_string(), config.clone(), status.clone());
+            supervisor::supervise(format!("uplink {}", stream_id), move || {
+                run_uplink(state.clone(), stream_id.clone(), config.clone(), status.clone())
+            })
+        };
+        let uplink = RunningUplink { config, status, task };
+        if let Some(old) = self.running.lock().insert(stream_id.to_string(), uplink) {
+            old.task.abort();
+        }
+    }
+
+    fn stop(&self, stream_id: &str) -> bool {
+        match self.running.lock().remove(stream_id) {
+            Some(uplink) => {
+                uplink.task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn describe(&self, stream_id: &str) -> Option<serde_json::Value> {
+        let running = self.running.lock();
+        let uplink = running.get(stream_id)?;
+        let status = uplink.status.lock().clone();
+        Some(json!({ "stream": stream_id, "uplink": uplink.config, "status": status }))
+    }
+}
+
+/// Probe destination dan kirim stream ke yang aktif, bersamaan dalam satu task
+async fn run_uplink(
+    state: AppState,
+    stream_id: String,
+    config: UplinkConfig,
+    status: Arc<Mutex<UplinkStatus>>,
+) {
+    tokio::select! {
+        _ = probe_destinations(&config, &status) => {}
+        _ = push(&state, &stream_id, &config, &status) => {}
+    }
+}
+
+/// Health check berkala: TCP connect ke setiap destination yang tidak sedang dipakai
+///
+/// The active destination is judged by its own link instead (pongs on the
+/// uplink socket), so a half-open connection is caught too.
+async fn probe_destinations(config: &UplinkConfig, status: &Mutex<UplinkStatus>) {
+    let interval = Duration::from_millis(config.health_interval_ms);
+    let mut tick = tokio::time::interval(interval);
+    loop {
+        tick.tick().await;
+        let active = status.lock().active;
+        let probes = config
+            .destinations
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != active)
+            .map(|(index, url)| async move { (index, probe(url, interval).await) });
+        for (index, result) in futures_util::future::join_all(probes).await {
+            status.lock().record_probe(index, result);
+        }
+    }
+}
+
+async fn probe(url: &str, timeout: Duration) -> Result<(), String> {
+    let request = url.into_client_request().map_err(|e| e.to_string())?;
+    let uri = request.uri();
+    let host = uri.host().ok_or("URL has no host")?.to_string();
+    let port = uri
+        .port_u16()
+        .unwrap_or(if uri.scheme_str() == Some("wss") { 443 } else { 80 });
+    match tokio::time::timeout(timeout, outbound::connect(&host, port)).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(format!("connect to {}:{} failed: {}", host, port, e)),
+        Err(_) => Err(format!("connect to {}:{} timed out", host, port)),
+    }
+}
+
+/// Sambung ke destination terbaik, kejar ketertinggalan (spool, lalu DVR), lalu kirim frame live
+async fn push(state: &AppState, stream_id: &str, config: &UplinkConfig, status: &Mutex<UplinkStatus>) {
+    let interval = Duration::from_millis(config.health_interval_ms);
+    let mut store = config.store_forward.as_ref().and_then(|store_forward| {
+        let root = &state.config.store_forward_dir;
+        match Spool::open(root, stream_id, store_forward.max_bytes) {
+            Ok(spool) => Some(StoreForward::new(spool, store_forward.replay_speed)),
+            Err(e) => {
+                warn!("Store-and-forward for stream {} disabled, cannot open spool in {}: {}", stream_id, root, e);
+                None
+            }
+        }
+    });
+    if let Some(store) = &store {
+        store.report(status);
+    }
+    let mut previous: Option<usize> = None;
+    loop {
+        let preferred = status.lock().preferred();
+        let attempt = async {
+            match preferred {
+                Some(index) => Some((index, connect(state, stream_id, config, &config.destinations[index]).await)),
+                None => {
+                    // Semua destination gagal probe; tunggu probe berikutnya
+                    tokio::time::sleep(interval).await;
+                    None
+                }
+            }
+        };
+        let attempt = match store.as_mut() {
+            Some(store) => store.while_offline(state, stream_id, status, attempt).await,
+            None => attempt.await,
+        };
+        let (index, mut link) = match attempt {
+            Some((index, Ok(link))) => (index, link),
+            Some((index, Err(e))) => {
+                warn!("Uplink for stream {} cannot reach {}: {}", stream_id, config.destinations[index], e);
+                status.lock().record_probe(index, Err(e));
+                go_offline(&mut store, state, stream_id, status).await;
+                continue;
+            }
+            None => {
+                go_offline(&mut store, state, stream_id, status).await;
+                continue;
+            }
+        };
+        let url = &config.destinations[index];
+        {
+            let mut status = status.lock();
+            status.active = Some(index);
+            if previous.is_some_and(|previous| previous != index) {
+                status.switches += 1;
+            }
+        }
+        if previous == Some(index) {
+            info!("Uplink for stream {} reconnected to {}", stream_id, url);
+        } else {
+            info!("Uplink for stream {} switched to {}", stream_id, url);
+        }
+        previous = Some(index);
+
+        let mut result = Ok(());
+        if let Some(store) = store.as_mut() {
+            result = store.drain(state, stream_id, &mut link, interval, status).await;
+        }
+        if result.is_ok() {
+            result = forward(state, stream_id, link, interval, status).await;
+        }
+        status.lock().active = None;
+        match result {
+            Ok(()) => info!("Uplink for stream {} leaving {} for a preferred destination", stream_id, url),
+            Err(e) => {
+                warn!("Uplink for stream {} lost {}: {}", stream_id, url, e);
+                status.lock().record_probe(index, Err(e));
+                go_offline(&mut store, state, stream_id, status).await;
+            }
+        }
+    }
+}
+
+async fn go_offline(
+    store: &mut Option<StoreForward>,
+    state: &AppState,
+    stream_id: &str,
+    status: &Mutex<UplinkStatus>,
+) {
+    if let Some(store) = store.as_mut() {
+        store.go_offline(state, stream_id, status).await;
+    }
+}
+
+/// Koneksi WebSocket producer ke broker upstream
+struct Link {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    /// Upstream accepted `bsb.envelope.v1`, so frames keep their original timestamp
+    envelope: bool,
+}
+
+impl Link {
+    async fn send(&mut self, original_ms: u64, payload: &[u8]) -> Result<(), String> {
+        let message = if self.envelope {
+            // Seq diberikan ulang oleh upstream
+            Subprotocol::envelope(0, original_ms, payload)
+        } else {
+            payload.to_vec()
+        };
+        self.socket.send(WsMessage::Binary(message)).await.map_err(|e| e.to_string())
+    }
+}
+
+/// Timestamp asli frame: waktu capture dari producer, atau waktu broker ini menerimanya
+fn original_ms(frame: &Frame) -> u64 {
+    frame
+        .producer_ms
+        .unwrap_or_else(|| unix_now_ms().saturating_sub(frame.received_at.elapsed().as_millis() as u64))
+}
+
+async fn connect(state: &AppState, stream_id: &str, config: &UplinkConfig, url: &str) -> Result<Link, String> {
+    let remote_id = config.remote_id.as_deref().unwrap_or(stream_id);
+    let url = format!("{}/ingest/{}", url.trim_end_matches('/'), encode_path_segment(remote_id));
+    let mut request = url.as_str().into_client_request().map_err(|e| e.to_string())?;
+    let headers = request.headers_mut();
+    if let Some(token) = &config.token {
+        headers.insert(
+            header::AUTHORIZATION,
+            format!("Bearer {}", token)
+                .parse()
+                .map_err(|_| "token is not a valid header value".to_string())?,
+        );
+    }
+    let offered = format!("{},{}", Subprotocol::Envelope.name(), Subprotocol::Raw.name());
+    headers.insert(header::SEC_WEBSOCKET_PROTOCOL, offered.parse().expect("valid header value"));
+    let connector = if url.starts_with("wss://") {
+        let ca_path = state
+            .config
+            .uplink_ca_path
+            .as_deref()
+            .ok_or("wss:// destinations require UPLINK_CA_PATH")?;
+        Some(Connector::Rustls(tls::client_config(ca_path)?))
+    } else {
+        None
+    };
+    let connect = outbound::connect_ws(request, connector);
+    let (socket, response) = tokio::time::timeout(Duration::from_secs(10), connect)
+        .await
+        .map_err(|_| "connect timed out".to_string())??;
+    let envelope = response
+        .headers()
+        .get(header::SEC_WEBSOCKET_PROTOCOL)
+        .is_some_and(|protocol| protocol.as_bytes() == Subprotocol::Envelope.name().as_bytes());
+    Ok(Link { socket, envelope })
+}
+
+/// Kirim frame setelah `confirmed_seq` ke satu koneksi upstream
+///
+/// Upstream ingest does not acknowledge frames, so every interval the uplink
+/// sends a ping carrying the last seq written; the pong proves the upstream
+/// read everything before it. After a reconnect or switch, frames after the
+/// last confirmed seq are replayed from the DVR (and its spill), so the
+/// upstream sees each frame at least once. Returns `Ok` when a higher-priority
+/// destination is healthy again and the uplink should fail back.
+async fn forward(
+    state: &AppState,
+    stream_id: &str,
+    mut link: Link,
+    interval: Duration,
+    status: &Mutex<UplinkStatus>,
+) -> Result<(), String> {
+    let confirmed = status.lock().confirmed_seq;
+    let (mut frames, backlog) = subscribe_after(state, stream_id, confirmed, status);
+    send_backlog(&mut link, backlog, status).await?;
+
+    let mut ack_tick = tokio::time::interval(interval);
+    let mut last_pong = Instant::now();
+    loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Ok(frame) => send(&mut link, &frame, status, false).await?,
+                // Uplink tertinggal dari channel: kejar dari DVR
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    let last_sent = status.lock().last_sent_seq;
+                    let (resubscribed, backlog) = subscribe_after(state, stream_id, last_sent, status);
+                    frames = resubscribed;
+                    send_backlog(&mut link, backlog, status).await?;
+                }
+                Err(broadcast::error::RecvError::Closed) => return Err("stream removed".to_string()),
+            },
+            msg = link.socket.next() => {
+                if let Some(seq) = pong(msg)? {
+                    last_pong = Instant::now();
+                    let mut status = status.lock();
+                    status.confirmed_seq = status.confirmed_seq.max(seq);
+                }
+            }
+            _ = ack_tick.tick() => {
+                if last_pong.elapsed() > interval * MISSED_PONGS {
+                    return Err(format!("no pong for {:?}", last_pong.elapsed()));
+                }
+                if status.lock().should_fail_back() {
+                    let _ = link.socket.close(None).await;
+                    return Ok(());
+                }
+                let last_sent = status.lock().last_sent_seq;
+                link.socket
+                    .send(WsMessage::Ping(last_sent.to_be_bytes().to_vec()))
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+}
+
+/// Pesan dari upstream: seq di pong, `None` untuk pesan lain, error bila koneksi selesai
+fn pong(msg: Option<Result<WsMessage, WsError>>) -> Result<Option<u64>, String> {
+    match msg {
+        Some(Ok(WsMessage::Pong(payload))) => {
+            Ok(Some(<[u8; 8]>::try_from(payload.as_slice()).map_or(0, u64::from_be_bytes)))
+        }
+        Some(Ok(WsMessage::Close(frame))) => {
+            Err(frame.map_or("closed by upstream".to_string(), |f| f.reason.to_string()))
+        }
+        Some(Ok(_)) => Ok(None),
+        Some(Err(e)) => Err(e.to_string()),
+        None => Err("closed by upstream".to_string()),
+    }
+}
+
+/// Subscribe ke stream plus frame tersimpan setelah `after_seq`, atomik terhadap publish
+///
+/// Frames after `after_seq` that already left the DVR are counted as missed.
+fn subscribe_after(
+    state: &AppState,
+    stream_id: &str,
+    after_seq: u64,
+    status: &Mutex<UplinkStatus>,
+) -> (broadcast::Receiver<Frame>, Vec<Frame>) {
+    let (frames, (spilled, retained), last_seq) = state.with_stream(stream_id, |entry| {
+        let stored = (entry.dvr.spilled_since(after_seq), entry.dvr.since(after_seq));
+        (entry.tx.subscribe(), stored, entry.last_seq())
+    });
+    let backlog: Vec<Frame> = spill::read(&spilled).into_iter().chain(retained).collect();
+    let first = backlog.first().map_or(last_seq + 1, |frame| frame.seq);
+    let missed = first.saturating_sub(after_seq + 1);
+    if missed > 0 {
+        warn!("Uplink for stream {} cannot catch up {} frames (no longer in the DVR)", stream_id, missed);
+        status.lock().frames_missed += missed;
+    }
+    (frames, backlog)
+}
+
+async fn send_backlog(link: &mut Link, backlog: Vec<Frame>, status: &Mutex<UplinkStatus>) -> Result<(), String> {
+    for frame in &backlog {
+        send(link, frame, status, true).await?;
+    }
+    Ok(())
+}
+
+async fn send(link: &mut Link, frame: &Frame, status: &Mutex<UplinkStatus>, catch_up: bool) -> Result<(), String> {
+    // Frame live yang sudah terkirim lewat backlog
+    if !catch_up && frame.seq <= status.lock().last_sent_seq {
+        return Ok(());
+    }
+    link.send(original_ms(frame), &frame.data).await?;
+    let mut status = status.lock();
+    status.last_sent_seq = status.last_sent_seq.max(frame.seq);
+    status.frames_sent += 1;
+    if catch_up {
+        status.frames_caught_up += 1;
+    }
+    Ok(())
+}
+
+/// Store-and-forward: tahan frame di disk selama offline, kirim ulang dengan pace terbatas
+///
+/// While no upstream is connected, every frame after the last confirmed one
+/// is appended to the [`Spool`]. Once a destination accepts the link, the
+/// spool is replayed oldest first at `replay_speed` times the original frame
+/// timing, while new frames keep going to the spool so their order is kept.
+/// A segment is deleted only after a pong confirms the upstream read all of
+/// it. When the spool runs empty the uplink continues live from the DVR.
+struct StoreForward {
+    spool: Spool,
+    replay_speed: f64,
+    /// Live frames being spooled; `None` while online
+    frames: Option<broadcast::Receiver<Frame>>,
+    /// Last local seq appended to the spool in this run
+    spooled_seq: u64,
+}
+
+impl StoreForward {
+    fn new(spool: Spool, replay_speed: f64) -> Self {
+        Self {
+            spool,
+            replay_speed,
+            frames: None,
+            spooled_seq: 0,
+        }
+    }
+
+    fn report(&self, status: &Mutex<UplinkStatus>) {
+        let mut status = status.lock();
+        status.spooling = self.frames.is_some();
+        status.spooled_frames = self.spool.frames();
+        status.spooled_bytes = self.spool.bytes();
+        status.spool_dropped = self.spool.dropped_frames;
+    }
+
+    /// Mulai menahan frame: yang belum dikonfirmasi upstream dulu, lalu frame live
+    async fn go_offline(&mut self, state: &AppState, stream_id: &str, status: &Mutex<UplinkStatus>) {
+        if self.frames.is_some() {
+            return;
+        }
+        let after_seq = status.lock().confirmed_seq.max(self.spooled_seq);
+        info!("Uplink for stream {} offline, spooling frames after seq {}", stream_id, after_seq);
+        let (frames, backlog) = subscribe_after(state, stream_id, after_seq, status);
+        self.frames = Some(frames);
+        for frame in &backlog {
+            self.append(stream_id, frame).await;
+        }
+        self.report(status);
+    }
+
+    async fn append(&mut self, stream_id: &str, frame: &Frame) {
+        if frame.seq <= self.spooled_seq {
+            return;
+        }
+        match self.spool.append(frame, original_ms(frame)).await {
+            Ok(()) => self.spooled_seq = frame.seq,
+            Err(e) => warn!("Cannot spool frame {} of stream {}: {}", frame.seq, stream_id, e),
+        }
+    }
+
+    /// Jalankan `task` sambil terus menulis frame live ke spool (bila sedang offline)
+    async fn while_offline<T>(
+        &mut self,
+        state: &AppState,
+        stream_id: &str,
+        status: &Mutex<UplinkStatus>,
+        task: impl Future<Output = T>,
+    ) -> T {
+        tokio::pin!(task);
+        let mut sync_tick = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            let Some(frames) = self.frames.as_mut() else {
+                return task.await;
+            };
+            tokio::select! {
+                output = &mut task => return output,
+                frame = frames.recv() => match frame {
+                    Ok(frame) => self.append(stream_id, &frame).await,
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        // Ambil frame yang terlewat dari DVR
+                        let (frames, backlog) = subscribe_after(state, stream_id, self.spooled_seq, status);
+                        self.frames = Some(frames);
+                        for frame in &backlog {
+                            self.append(stream_id, frame).await;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return task.await,
+                },
+                _ = sync_tick.tick() => {
+                    if let Err(e) = self.spool.sync().await {
+                        warn!("Cannot sync spool of stream {}: {}", stream_id, e);
+                    }
+                    self.report(status);
+                }
+            }
+        }
+    }
+
+    /// Kirim isi spool ke `link`, lalu kembali online
+    async fn drain(
+        &mut self,
+        state: &AppState,
+        stream_id: &str,
+        link: &mut Link,
+        interval: Duration,
+        status: &Mutex<UplinkStatus>,
+    ) -> Result<(), String> {
+        loop {
+            let next = match self.spool.next_segment().await {
+                Ok(next) => next,
+                Err(e) => return Err(format!("cannot read spool: {}", e)),
+            };
+            let Some(segment) = next else {
+                break;
+            };
+            let records = match self.spool.read(&segment).await {
+                Ok(records) => records,
+                Err(e) => {
+                    warn!("Skipping unreadable spool segment {} of stream {}: {}", segment.segment, stream_id, e);
+                    self.spool.remove(&segment);
+                    continue;
+                }
+            };
+            let started = tokio::time::Instant::now();
+            let first_ms = records.first().map_or(0, |(original_ms, _)| *original_ms);
+            for (original_ms, payload) in &records {
+                let due = match self.replay_speed {
+                    speed if speed > 0.0 => {
+                        let offset_ms = original_ms.saturating_sub(first_ms) as f64 / speed;
+                        started + Duration::from_secs_f64(offset_ms / 1000.0)
+                    }
+                    _ => started,
+                };
+                let sent = async {
+                    tokio::time::sleep_until(due).await;
+                    link.send(*original_ms, payload).await
+                };
+                self.while_offline(state, stream_id, status, sent).await?;
+                status.lock().frames_forwarded += 1;
+            }
+
+            // Segmen baru dihapus setelah pong membuktikan upstream sudah membaca semuanya
+            let acked = async {
+                let marker = status.lock().last_sent_seq;
+                link.socket
+                    .send(WsMessage::Ping(marker.to_be_bytes().to_vec()))
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let deadline = tokio::time::Instant::now() + interval * MISSED_PONGS;
+                loop {
+                    tokio::select! {
+                        msg = link.socket.next() => if pong(msg)?.is_some() {
+                            return Ok(());
+                        },
+                        _ = tokio::time::sleep_until(deadline) => return Err("no pong for spooled frames".to_string()),
+                    }
+                }
+            };
+            self.while_offline(state, stream_id, status, acked).await?;
+            self.spool.remove(&segment);
+            self.report(status);
+        }
+
+        if self.frames.take().is_some() {
+            // Semua frame sampai `spooled_seq` sudah dikonfirmasi; lanjut live dari DVR
+            let mut status = status.lock();
+            status.confirmed_seq = status.confirmed_seq.max(self.spooled_seq);
+            status.last_sent_seq = status.last_sent_seq.max(status.confirmed_seq);
+            info!("Uplink for stream {} forwarded its spool, going live", stream_id);
+        }
+        self.report(status);
+        Ok(())
+    }
+}
+
+/// Handler untuk GET /api/streams/:id/uplink
+pub async fn get_uplink_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Response {
+    match state.uplinks.describe(&stream_id) {
+        Some(body) => Json(body).into_response(),
+        None => BrokerError::not_found("uplink", stream_id).into_response(),
+    }
+}
+
+/// Handler untuk PUT /api/streams/:id/uplink
+/// Replicate the stream to upstream brokers with failover (replaces a running uplink)
+pub async fn put_uplink_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+    Json(config): Json<UplinkConfig>,
+) -> Response {
+    if let Err(message) = config.validate() {
+        return BrokerError::InvalidRequest(message).into_response();
+    }
+    info!("Starting uplink for stream {} -> {:?}", stream_id, config.destinations);
+    state.uplinks.start(&state, &stream_id, config);
+    match state.uplinks.describe(&stream_id) {
+        Some(body) => Json(body).into_response(),
+        None => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// Handler untuk DELETE /api/streams/:id/uplink
+pub async fn delete_uplink_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Result<StatusCode, BrokerError> {
+    if state.uplinks.stop(&stream_id) {
+        info!("Stopped uplink for stream {}", stream_id);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(BrokerError::not_found("uplink", stream_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, testing::TestBroker};
+
+    #[tokio::test]
+    async fn test_uplink_fails_over_to_next_destination() {
+        let cloud = TestBroker::start(Config::default()).await;
+        let mut viewer = cloud.subscriber("site-cam1").await;
+        // Destination pertama tidak bisa dihubungi
+        let unreachable = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead = format!("ws://{}", unreachable.local_addr().unwrap());
+        drop(unreachable);
+
+        let site = TestBroker::start(Config::default()).await;
+        let config: UplinkConfig = serde_json::from_value(json!({
+            "destinations": [dead, format!("ws://{}", cloud.addr)],
+            "remote_id": "site-cam1",
+            "health_interval_ms": 100,
+        }))
+        .unwrap();
+        assert!(config.validate().is_ok());
+        site.state.uplinks.start(&site.state, "cam1", config);
+
+        // Frame yang masuk sebelum uplink tersambung dikejar dari DVR
+        site.post_frame("cam1", "frame-1").await;
+        site.post_frame("cam1", "frame-2").await;
+        assert_eq!(viewer.expect_binary().await, b"frame-1");
+        assert_eq!(viewer.expect_binary().await, b"frame-2");
+        let status = site.state.uplinks.describe("cam1").unwrap()["status"].clone();
+        assert_eq!(status["active"], 1);
+        assert_eq!(status["destinations"][0]["healthy"], false);
+        assert_eq!(status["last_sent_seq"], 2);
+
+        // Kembali ke destination utama hanya setelah probe stabil
+        let mut status = UplinkStatus::new(&serde_json::from_value(json!({
+            "destinations": ["ws://a", "ws://b"],
+        })).unwrap(), 0);
+        status.record_probe(0, Err("refused".to_string()));
+        status.active = Some(1);
+        assert_eq!(status.preferred(), Some(1));
+        for _ in 0..FAILBACK_PROBES - 1 {
+            status.record_probe(0, Ok(()));
+        }
+        assert!(!status.should_fail_back());
+        status.record_probe(0, Ok(()));
+        assert!(status.should_fail_back());
+
+        let invalid: UplinkConfig = serde_json::from_value(json!({ "destinations": ["http://x"] })).unwrap();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_store_and_forward_replays_spool_with_original_timestamps() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let cloud = TestBroker::start(Config::default()).await;
+        let (mut viewer, _) = cloud.connect_with_protocols("/ws/cam1", "bsb.envelope.v1").await.unwrap();
+        assert_eq!(viewer.expect_json().await["type"], "sync");
+
+        // WAN link: koneksi diteruskan ke cloud hanya saat `online`
+        let online = Arc::new(AtomicBool::new(false));
+        let gate = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let gate_url = format!("ws://{}", gate.local_addr().unwrap());
+        let (cloud_addr, link_up) = (cloud.addr, online.clone());
+        tokio::spawn(async move {
+            while let Ok((mut inbound, _)) = gate.accept().await {
+                if !link_up.load(Ordering::SeqCst) {
+                    continue;
+                }
+                tokio::spawn(async move {
+                    let mut outbound = TcpStream::connect(cloud_addr).await.unwrap();
+                    let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                });
+            }
+        });
+
+        let spool_dir = std::env::temp_dir().join(format!("bsb-uplink-spool-{}", std::process::id()));
+        let site = TestBroker::start(Config {
+            store_forward_dir: spool_dir.to_str().unwrap().to_string(),
+            ..Config::default()
+        })
+        .await;
+        let config: UplinkConfig = serde_json::from_value(json!({
+            "destinations": [gate_url],
+            "health_interval_ms": 100,
+            "store_forward": { "replay_speed": 0 },
+        }))
+        .unwrap();
+        assert!(config.validate().is_ok());
+        site.state.uplinks.start(&site.state, "cam1", config);
+
+        let before_ms = unix_now_ms();
+        site.post_frame("cam1", "offline-1").await;
+        site.post_frame("cam1", "offline-2").await;
+        let status = || site.state.uplinks.describe("cam1").unwrap()["status"].clone();
+        for _ in 0..100 {
+            if status()["spooled_frames"] == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(status()["spooling"], true);
+        assert_eq!(status()["spooled_frames"], 2);
+
+        // Link kembali: spool dikirim dulu dengan timestamp asli, lalu live
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        online.store(true, Ordering::SeqCst);
+        for expected in [&b"offline-1"[..], b"offline-2"] {
+            let envelope = viewer.expect_binary().await;
+            let original_ms = u64::from_be_bytes(envelope[8..16].try_into().unwrap());
+            assert!(original_ms >= before_ms && original_ms + 200 <= unix_now_ms());
+            assert_eq!(&envelope[16..], expected);
+        }
+        for _ in 0..100 {
+            if status()["spooling"] == false {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        site.post_frame("cam1", "live").await;
+        assert_eq!(&viewer.expect_binary().await[16..], b"live");
+        let status = status();
+        assert_eq!(status["frames_forwarded"], 2);
+        assert_eq!(status["spooled_frames"], 0);
+
+        let _ = std::fs::remove_dir_all(spool_dir);
+    }
+}
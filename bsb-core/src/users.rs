@@ -0,0 +1,366 @@
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use parking_lot::Mutex;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{collections::BTreeMap, sync::Arc};
+use tracing::{error, info, warn};
+
+use crate::{auth::Role, error::BrokerError, hooks::StateStore, mux::glob_match, runtime, AppState};
+
+/// Password user lokal minimal sepanjang ini
+const MIN_PASSWORD_LEN: usize = 8;
+
+/// Satu user lokal di `USERS_FILE`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct User {
+    /// Argon2id PHC string; never returned by the API
+    password_hash: String,
+    /// Admin API role; `None` = no admin access
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<Role>,
+    /// Stream globs the user may watch on `/ws/:stream_id`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub streams: Vec<String>,
+    /// Extra playback scopes, e.g. `metadata:full` to see unredacted metadata
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+}
+
+impl User {
+    pub fn can_subscribe(&self, stream_id: &str) -> bool {
+        self.streams.iter().any(|p| glob_match(p, stream_id))
+    }
+}
+
+/// Isi `USERS_FILE`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UsersFile {
+    users: BTreeMap<String, User>,
+}
+
+/// User/password lokal untuk instalasi kecil tanpa identity provider
+///
+/// Users log in with HTTP Basic auth, against the admin API (with their
+/// role) and `/ws` (with their stream globs). Passwords are stored as
+/// Argon2id hashes in `USERS_FILE` and managed through `/api/users`; every
+/// change is written back to the file right away.
+pub struct UserStore {
+    path: Option<String>,
+    users: Mutex<BTreeMap<String, User>>,
+    /// Where the file is read from and written to (a file unless an embedder replaced it)
+    store: Arc<dyn StateStore>,
+    /// Writes of the file, one at a time and in order of the changes
+    saving: tokio::sync::Mutex<()>,
+}
+
+impl std::fmt::Debug for UserStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserStore")
+            .field("path", &self.path)
+            .field("users", &self.users.lock().len())
+            .finish()
+    }
+}
+
+impl UserStore {
+    pub fn new(path: Option<String>, store: Arc<dyn StateStore>) -> Self {
+        Self {
+            path,
+            users: Mutex::default(),
+            store,
+            saving: tokio::sync::Mutex::default(),
+        }
+    }
+
+    /// Muat `USERS_FILE`; file yang belum ada = store kosong
+    pub fn load(&self) -> Result<(), String> {
+        let Some(path) = self.path.as_deref() else {
+            return Ok(());
+        };
+        let file: UsersFile = match self.store.load(path) {
+            Ok(Some(contents)) => {
+                serde_json::from_slice(&contents).map_err(|e| format!("Invalid USERS_FILE {}: {}", path, e))?
+            }
+            Ok(None) => UsersFile::default(),
+            Err(e) => return Err(format!("Cannot read USERS_FILE {}: {}", path, e)),
+        };
+        for (name, user) in &file.users {
+            PasswordHash::new(&user.password_hash)
+                .map_err(|e| format!("Invalid password hash of user {} in {}: {}", name, path, e))?;
+        }
+        if !file.users.is_empty() {
+            info!("Loaded {} users from {}", file.users.len(), path);
+        }
+        *self.users.lock() = file.users;
+        Ok(())
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Ada user dengan peran admin API
+    pub fn has_admins(&self) -> bool {
+        self.users.lock().values().any(|user| user.role.is_some())
+    }
+
+    /// Ada user yang boleh menonton stream
+    pub fn has_viewers(&self) -> bool {
+        self.users.lock().values().any(|user| !user.streams.is_empty())
+    }
+
+    /// Resolve `Authorization: Basic` into the user it names
+    ///
+    /// The Argon2 check runs on the blocking pool; it is deliberately slow.
+    pub async fn authenticate(&self, authorization: &str) -> Option<(String, User)> {
+        let encoded = authorization.strip_prefix("Basic ")?;
+        let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+        let (name, password) = decoded.split_once(':')?;
+        let user = self.users.lock().get(name).cloned()?;
+        let hash = user.password_hash.clone();
+        let password = password.to_string();
+        let verified = tokio::task::spawn_blocking(move || verify_password(&hash, &password))
+            .await
+            .unwrap_or(false);
+        if !verified {
+            warn!("Invalid password for user {}", name);
+            return None;
+        }
+        Some((name.to_string(), user))
+    }
+
+    /// Isi `USERS_FILE` untuk backup: hash password, bukan password
+    pub fn export(&self) -> serde_json::Value {
+        let file = UsersFile {
+            users: self.users.lock().clone(),
+        };
+        serde_json::to_value(file).expect("users serialize")
+    }
+
+    /// Ganti semua user dengan isi backup dan tulis ke `USERS_FILE`
+    pub async fn replace(&self, users: BTreeMap<String, User>) -> Result<(), BrokerError> {
+        *self.users.lock() = users;
+        self.save().await
+    }
+
+    fn list(&self) -> serde_json::Value {
+        let users: Vec<_> = self
+            .users
+            .lock()
+            .iter()
+            .map(|(name, user)| json!({ "name": name, "role": user.role, "streams": user.streams, "scopes": user.scopes }))
+            .collect();
+        json!({ "users": users })
+    }
+
+    /// Tulis seluruh store ke `USERS_FILE`
+    async fn save(&self) -> Result<(), BrokerError> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        let _saving = self.saving.lock().await;
+        let file = UsersFile {
+            users: self.users.lock().clone(),
+        };
+        let contents = serde_json::to_vec_pretty(&file).unwrap_or_default();
+        let (target, store) = (path.clone(), self.store.clone());
+        match runtime::spawn_disk_blocking(move || store.save(&target, &contents)).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => {
+                error!("Cannot write USERS_FILE {}: {}", path, e);
+                Err(BrokerError::Io(e))
+            }
+            Err(e) => Err(BrokerError::Internal(format!("USERS_FILE writer failed: {}", e))),
+        }
+    }
+}
+
+/// Baca dan periksa isi `USERS_FILE` dari backup sebelum diterapkan
+pub fn parse_backup(value: serde_json::Value) -> Result<BTreeMap<String, User>, String> {
+    let file: UsersFile = serde_json::from_value(value).map_err(|e| format!("invalid users: {}", e))?;
+    for (name, user) in &file.users {
+        PasswordHash::new(&user.password_hash)
+            .map_err(|e| format!("invalid password hash of user {}: {}", name, e))?;
+    }
+    Ok(file.users)
+}
+
+fn verify_password(hash: &str, password: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+/// Hash Argon2id dengan salt acak, dalam format PHC
+fn hash_password(password: &str) -> Result<String, String> {
+    let mut salt = [0u8; 16];
+    SystemRandom::new().fill(&mut salt).map_err(|_| "no system randomness".to_string())?;
+    let salt = SaltString::encode_b64(&salt).map_err(|e| e.to_string())?;
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Body untuk PUT /api/users/:name
+#[derive(Debug, Deserialize)]
+pub struct UserBody {
+    /// Required when creating the user, optional when changing role or streams
+    pub password: Option<String>,
+    pub role: Option<Role>,
+    #[serde(default)]
+    pub streams: Vec<String>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+fn disabled() -> Response {
+    BrokerError::Unavailable("user store is disabled (USERS_FILE is not set)".to_string()).into_response()
+}
+
+/// Handler untuk GET /api/users
+pub async fn list_users_handler(State(state): State<AppState>) -> Response {
+    if !state.users.enabled() {
+        return disabled();
+    }
+    Json(state.users.list()).into_response()
+}
+
+/// Handler untuk PUT /api/users/:name
+/// Membuat user atau mengganti role, stream dan (opsional) password-nya
+pub async fn put_user_handler(
+    AxumPath(name): AxumPath<String>,
+    State(state): State<AppState>,
+    Json(body): Json<UserBody>,
+) -> Response {
+    if !state.users.enabled() {
+        return disabled();
+    }
+    if name.is_empty() || name.contains(':') {
+        return BrokerError::InvalidRequest("user name must be non-empty and without ':'".to_string()).into_response();
+    }
+    let password_hash = match body.password {
+        Some(password) if password.len() < MIN_PASSWORD_LEN => {
+            let error = format!("password must be at least {} characters", MIN_PASSWORD_LEN);
+            return BrokerError::InvalidRequest(error).into_response();
+        }
+        Some(password) => match tokio::task::spawn_blocking(move || hash_password(&password)).await {
+            Ok(Ok(hash)) => hash,
+            Ok(Err(e)) => return BrokerError::Internal(format!("cannot hash password: {}", e)).into_response(),
+            Err(e) => return BrokerError::Internal(format!("password hasher failed: {}", e)).into_response(),
+        },
+        None => match state.users.users.lock().get(&name) {
+            Some(user) => user.password_hash.clone(),
+            None => {
+                return BrokerError::InvalidRequest("password is required for a new user".to_string()).into_response()
+            }
+        },
+    };
+    let user = User {
+        password_hash,
+        role: body.role,
+        streams: body.streams,
+        scopes: body.scopes,
+    };
+    let created = state.users.users.lock().insert(name.clone(), user.clone()).is_none();
+    if let Err(e) = state.users.save().await {
+        return e.into_response();
+    }
+    info!("{} user {}", if created { "Created" } else { "Updated" }, name);
+    let status = if created { StatusCode::CREATED } else { StatusCode::OK };
+    (status, Json(json!({ "name": name, "role": user.role, "streams": user.streams, "scopes": user.scopes }))).into_response()
+}
+
+/// Handler untuk DELETE /api/users/:name
+pub async fn delete_user_handler(AxumPath(name): AxumPath<String>, State(state): State<AppState>) -> Response {
+    if !state.users.enabled() {
+        return disabled();
+    }
+    if state.users.users.lock().remove(&name).is_none() {
+        return BrokerError::not_found("user", &name).into_response();
+    }
+    if let Err(e) = state.users.save().await {
+        return e.into_response();
+    }
+    info!("Deleted user {}", name);
+    StatusCode::NO_CONTENT.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{auth::CredentialStore, config::Config, testing::TestBroker};
+    use axum::{body::Body, extract::Request, http::Method};
+    use std::fs;
+    use tower::util::ServiceExt;
+
+    fn basic(user: &str, password: &str) -> String {
+        format!("Basic {}", STANDARD.encode(format!("{}:{}", user, password)))
+    }
+
+    #[tokio::test]
+    async fn test_users_manage_admin_roles_and_playback() {
+        let path = std::env::temp_dir().join(format!("bsb-users-test-{}.json", std::process::id()));
+        let broker = TestBroker::start(Config {
+            admin_credentials: CredentialStore::parse("root:bootstrap=@admin", true).unwrap(),
+            users_file: Some(path.to_str().unwrap().to_string()),
+            ..Config::default()
+        })
+        .await;
+        let app = crate::build_router(broker.state.clone());
+        let call = |method: Method, uri: &str, authorization: String, body: serde_json::Value| {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", authorization)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let response = app.clone().oneshot(request);
+            async move { response.await.unwrap().status() }
+        };
+        let root = basic("root", "bootstrap");
+
+        // User dibuat lewat admin API; password tersimpan sebagai hash Argon2
+        let grafana = json!({ "password": "dashboards", "role": "viewer" });
+        assert_eq!(call(Method::PUT, "/api/users/grafana", root.clone(), grafana).await, StatusCode::CREATED);
+        let alice = json!({ "password": "watch-cams", "streams": ["cam*"] });
+        assert_eq!(call(Method::PUT, "/api/users/alice", root.clone(), alice).await, StatusCode::CREATED);
+        let short = json!({ "password": "short" });
+        assert_eq!(call(Method::PUT, "/api/users/bob", root.clone(), short).await, StatusCode::BAD_REQUEST);
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("$argon2id$") && !saved.contains("dashboards"));
+
+        // Peran user berlaku seperti credential admin
+        let viewer = basic("grafana", "dashboards");
+        assert_eq!(call(Method::GET, "/api/users", viewer.clone(), json!(null)).await, StatusCode::FORBIDDEN);
+        assert_eq!(call(Method::GET, "/api/debug/state", viewer.clone(), json!(null)).await, StatusCode::FORBIDDEN);
+        let wrong = basic("grafana", "guess");
+        assert_eq!(call(Method::GET, "/api/users", wrong, json!(null)).await, StatusCode::UNAUTHORIZED);
+
+        // Playback dengan Basic auth, dibatasi glob stream user
+        let login = basic("alice", "watch-cams");
+        let (mut viewer, _) = broker.connect_with_headers("/ws/cam1", &[("authorization", &login)]).await.unwrap();
+        let denied = broker.connect_with_headers("/ws/lobby", &[("authorization", &login)]).await;
+        assert_eq!(denied.err(), Some(StatusCode::FORBIDDEN));
+        assert_eq!(broker.connect_with_headers("/ws/cam1", &[]).await.err(), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(broker.post_frame("cam1", "frame").await, StatusCode::OK);
+        assert_eq!(viewer.expect_binary().await, b"frame");
+
+        // Restart: user dimuat ulang dari file; hapus user lewat API
+        let reloaded = UserStore::new(Some(path.to_str().unwrap().to_string()), Arc::new(crate::hooks::FileStore));
+        reloaded.load().unwrap();
+        assert!(reloaded.authenticate(&basic("alice", "watch-cams")).await.is_some());
+        assert_eq!(call(Method::DELETE, "/api/users/alice", root, json!(null)).await, StatusCode::NO_CONTENT);
+        assert!(!broker.state.users.has_viewers());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::json;
+use std::{collections::HashMap, env};
+
+use crate::{
+    config::{self, Config},
+    error::BrokerError,
+    runtime::{self, RuntimeConfig},
+    AppState,
+};
+
+/// Handler untuk POST /api/config/validate
+/// Dry-run a candidate configuration and diff it against the running one
+///
+/// The body maps environment variable names to their candidate values
+/// (`null` unsets one); variables not mentioned keep the broker's current
+/// environment. The candidate is parsed exactly like at startup, files it
+/// names (`STATIC_STREAMS_FILE`, TLS certificates) included, and nothing is
+/// applied: configuration only takes effect when the broker restarts.
+pub async fn validate_config_handler(
+    State(state): State<AppState>,
+    Json(candidate): Json<HashMap<String, Option<String>>>,
+) -> Response {
+    let mut variables: Vec<String> = candidate
+        .iter()
+        .filter(|(name, value)| env::var(name).ok() != **value)
+        .map(|(name, _)| name.clone())
+        .collect();
+    variables.sort();
+
+    let parsed = runtime::spawn_disk_blocking(move || {
+        config::with_candidate(candidate, || {
+            let config = Config::from_env()?;
+            RuntimeConfig::from_env()?;
+            Ok::<_, String>(config)
+        })
+    })
+    .await;
+    let config = match parsed {
+        Ok(Ok(config)) => config,
+        Ok(Err(error)) => {
+            return Json(json!({ "valid": false, "error": error, "variables": variables })).into_response()
+        }
+        Err(e) => return BrokerError::from(e).into_response(),
+    };
+
+    let changes = config::summary_diff(&state.config.summary(), &config.summary());
+    Json(json!({
+        "valid": true,
+        "variables": variables,
+        "changes": changes,
+        "restart_required": !variables.is_empty(),
+    }))
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestBroker;
+    use axum::body::to_bytes;
+
+    async fn validate(broker: &TestBroker, candidate: serde_json::Value) -> serde_json::Value {
+        let candidate = serde_json::from_value(candidate).unwrap();
+        let response = validate_config_handler(State(broker.state.clone()), Json(candidate)).await;
+        serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_candidate_config_is_diffed_without_being_applied() {
+        let broker = TestBroker::start(Config::default()).await;
+        let result = validate(&broker, json!({ "DVR_BUFFER_FRAMES": "600", "STORAGE_PAUSE_RECORDING": "true" })).await;
+        assert_eq!(result["valid"], true);
+        assert_eq!(result["variables"], json!(["DVR_BUFFER_FRAMES", "STORAGE_PAUSE_RECORDING"]));
+        let changes = result["changes"].as_array().unwrap();
+        let change = |setting: &str| changes.iter().find(|change| change["setting"] == setting).cloned();
+        assert_eq!(change("dvr_frames").unwrap()["candidate"], 600);
+        assert_eq!(change("storage_pause_recording").unwrap()["running"], false);
+        assert!(change("channel_capacity").is_none());
+        assert_eq!(broker.state.config.dvr_frames, Config::default().dvr_frames);
+        // Kandidat hanya berlaku di thread validasi
+        assert!(Config::from_env().unwrap().dvr_frames != 600);
+
+        let result = validate(&broker, json!({ "PORT": "not-a-port" })).await;
+        assert_eq!(result["valid"], false);
+        assert!(result["error"].as_str().unwrap().contains("PORT"));
+        let result = validate(&broker, json!({ "STORAGE_WARN_PERCENT": "99" })).await;
+        assert!(result["error"].as_str().unwrap().contains("STORAGE_WARN_PERCENT"));
+    }
+}
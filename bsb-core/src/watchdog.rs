@@ -0,0 +1,215 @@
+use bytes::Bytes;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::{config::Config, outbound::HttpTarget, registry::StreamEvent, AppState};
+
+/// Frame rate producer diukur per jendela waktu ini
+const FPS_WINDOW: Duration = Duration::from_secs(5);
+
+/// Masalah yang terdeteksi pada producer yang masih terhubung
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProducerAlert {
+    /// No frame for `silent_secs` seconds
+    Stalled { silent_secs: u64 },
+    /// Measured frame rate fell below `PRODUCER_MIN_FPS_PERCENT` of the declared `?fps=`
+    Slow { fps: f64, declared_fps: f64 },
+}
+
+impl ProducerAlert {
+    pub fn event(&self, seq: u64) -> StreamEvent {
+        match *self {
+            ProducerAlert::Stalled { silent_secs } => StreamEvent::ProducerStalled { silent_secs, seq },
+            ProducerAlert::Slow { fps, declared_fps } => StreamEvent::ProducerSlow {
+                fps,
+                declared_fps,
+                seq,
+            },
+        }
+    }
+
+    /// Alasan untuk close frame saat producer diputus
+    pub fn reason(&self) -> String {
+        match self {
+            ProducerAlert::Stalled { silent_secs } => format!("no frame for {}s", silent_secs),
+            ProducerAlert::Slow { fps, declared_fps } => {
+                format!("{} fps, declared {} fps", fps, declared_fps)
+            }
+        }
+    }
+}
+
+/// Pantau satu koneksi producer WebSocket: diam terlalu lama atau frame rate terlalu rendah
+///
+/// Each problem is reported once; the watchdog re-arms when the producer
+/// recovers (a new frame after a stall, a healthy window after a slow one).
+#[derive(Debug)]
+pub struct ProducerWatchdog {
+    stall_after: Option<Duration>,
+    declared_fps: Option<f64>,
+    min_fps: Option<f64>,
+    last_frame: Instant,
+    window_start: Instant,
+    window_frames: u32,
+    stalled: bool,
+    slow: bool,
+}
+
+impl ProducerWatchdog {
+    /// Dibuat saat frame pertama diterima (sebelumnya berlaku first-frame timeout)
+    pub fn new(config: &Config, declared_fps: Option<f64>, now: Instant) -> Self {
+        let min_fps = declared_fps
+            .filter(|_| config.producer_min_fps_percent > 0)
+            .map(|fps| fps * config.producer_min_fps_percent as f64 / 100.0);
+        Self {
+            stall_after: (config.producer_stall_secs > 0)
+                .then(|| Duration::from_secs(config.producer_stall_secs)),
+            declared_fps,
+            min_fps,
+            last_frame: now,
+            window_start: now,
+            window_frames: 1,
+            stalled: false,
+            slow: false,
+        }
+    }
+
+    pub fn on_frame(&mut self, now: Instant) {
+        self.last_frame = now;
+        self.window_frames += 1;
+        self.stalled = false;
+    }
+
+    /// Dipanggil secara periodik; `Some` saat masalah baru terdeteksi
+    pub fn check(&mut self, now: Instant) -> Option<ProducerAlert> {
+        if let Some(stall_after) = self.stall_after {
+            let silent = now.duration_since(self.last_frame);
+            if silent >= stall_after {
+                if self.stalled {
+                    return None;
+                }
+                self.stalled = true;
+                return Some(ProducerAlert::Stalled {
+                    silent_secs: silent.as_secs(),
+                });
+            }
+        }
+
+        let (Some(min_fps), Some(declared_fps)) = (self.min_fps, self.declared_fps) else {
+            return None;
+        };
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed < FPS_WINDOW {
+            return None;
+        }
+        let fps = self.window_frames as f64 / elapsed.as_secs_f64();
+        self.window_start = now;
+        self.window_frames = 0;
+        let was_slow = self.slow;
+        self.slow = fps < min_fps;
+        (self.slow && !was_slow).then(|| ProducerAlert::Slow {
+            fps: (fps * 100.0).round() / 100.0,
+            declared_fps,
+        })
+    }
+}
+
+/// Validasi `?fps=` yang dideklarasikan producer
+pub fn validate_declared_fps(fps: f64) -> Result<(), &'static str> {
+    if fps.is_finite() && fps > 0.0 && fps <= 1000.0 {
+        Ok(())
+    } else {
+        Err("fps must be greater than 0 and at most 1000")
+    }
+}
+
+/// Laporkan alert: log, metric, control event untuk subscriber, dan webhook (jika dikonfigurasi)
+pub fn raise(state: &AppState, stream_id: &str, alert: &ProducerAlert) {
+    let disconnect = state.config.producer_watchdog_disconnect;
+    warn!(
+        "Producer for stream {} looks stuck ({}){}",
+        stream_id,
+        alert.reason(),
+        if disconnect { ", disconnecting" } else { "" }
+    );
+
+    let seq = match state.streams.lock().get_mut(stream_id) {
+        Some(entry) => {
+            entry.counters.record_producer_alert();
+            let seq = entry.last_seq();
+            entry.emit(alert.event(seq));
+            seq
+        }
+        None => 0,
+    };
+
+    let Some(url) = state.config.producer_watchdog_webhook.clone() else {
+        return;
+    };
+    let mut body = serde_json::to_value(alert.event(seq)).expect("events serialize");
+    body["stream"] = stream_id.into();
+    body["disconnect"] = disconnect.into();
+    tokio::spawn(async move {
+        let result = match HttpTarget::parse(&url) {
+            Ok(mut target) => {
+                target
+                    .post("application/json", &[], Bytes::from(body.to_string()))
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(status) if status.is_success() => {}
+            Ok(status) => warn!("Watchdog webhook {} answered {}", url, status),
+            Err(e) => warn!("Watchdog webhook {} failed: {}", url, e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stall_and_slow_detection() {
+        let config = Config {
+            producer_stall_secs: 3,
+            producer_min_fps_percent: 50,
+            ..Config::default()
+        };
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        // 10 fps dideklarasikan, producer hanya mengirim 2 fps
+        let mut watchdog = ProducerWatchdog::new(&config, Some(10.0), start);
+        for ms in (500..5000).step_by(500) {
+            watchdog.on_frame(at(ms));
+            assert_eq!(watchdog.check(at(ms)), None);
+        }
+        assert_eq!(
+            watchdog.check(at(5000)),
+            Some(ProducerAlert::Slow {
+                fps: 2.0,
+                declared_fps: 10.0
+            })
+        );
+
+        // Diam 3 detik -> satu alert stall, lalu re-arm setelah frame baru
+        assert_eq!(
+            watchdog.check(at(7500)),
+            Some(ProducerAlert::Stalled { silent_secs: 3 })
+        );
+        assert_eq!(watchdog.check(at(8500)), None);
+        watchdog.on_frame(at(9000));
+        assert_eq!(watchdog.check(at(9000)), None);
+        assert_eq!(
+            watchdog.check(at(12000)),
+            Some(ProducerAlert::Stalled { silent_secs: 3 })
+        );
+
+        // Tanpa `?fps=` hanya stall yang dipantau
+        let mut watchdog = ProducerWatchdog::new(&config, None, start);
+        assert_eq!(watchdog.check(at(6000)).map(|a| a.reason()), Some("no frame for 6s".into()));
+        assert!(validate_declared_fps(0.0).is_err());
+    }
+}
@@ -0,0 +1,413 @@
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use bytes::Bytes;
+use jpeg_encoder::{ColorType, Encoder};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+};
+use tracing::{info, warn};
+
+use crate::{
+    error::BrokerError,
+    jpeg::{self, RgbImage},
+    stats::unix_now_ms,
+    AppState,
+};
+
+/// Sudut tempat teks overlay digambar; logo di sudut horizontal seberangnya
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverlayPosition {
+    TopLeft,
+    TopRight,
+    #[default]
+    BottomLeft,
+    BottomRight,
+}
+
+/// Body untuk PUT /api/streams/:id/watermark (juga `watermark` di `STATIC_STREAMS_FILE`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WatermarkConfig {
+    /// UTC time of the frame (producer capture time when sent, else receive time)
+    #[serde(default = "default_true")]
+    pub timestamp: bool,
+    #[serde(default = "default_true")]
+    pub stream_name: bool,
+    /// Free text line, e.g. a case number
+    pub text: Option<String>,
+    /// Path of a JPEG logo drawn in the opposite corner
+    pub logo: Option<String>,
+    #[serde(default)]
+    pub position: OverlayPosition,
+    /// Font scale, 1 = 5x7 pixel glyphs
+    #[serde(default = "default_scale")]
+    pub scale: u8,
+    /// JPEG quality of the re-encoded frame
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_scale() -> u8 {
+    2
+}
+
+fn default_quality() -> u8 {
+    85
+}
+
+impl WatermarkConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=8).contains(&self.scale) {
+            return Err("scale must be between 1 and 8".to_string());
+        }
+        if !(1..=100).contains(&self.quality) {
+            return Err("quality must be between 1 and 100".to_string());
+        }
+        if !self.timestamp && !self.stream_name && self.text.is_none() && self.logo.is_none() {
+            return Err("watermark has nothing to draw".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Watermark siap pakai: konfigurasi plus logo yang sudah di-decode
+#[derive(Debug)]
+pub struct Watermark {
+    config: WatermarkConfig,
+    logo: Option<RgbImage>,
+}
+
+impl Watermark {
+    /// Validasi konfigurasi dan baca logo dari disk
+    pub fn compile(config: WatermarkConfig) -> Result<Self, String> {
+        config.validate()?;
+        let logo = match &config.logo {
+            Some(path) => {
+                let data = std::fs::read(path).map_err(|e| format!("cannot read logo {}: {}", path, e))?;
+                Some(jpeg::decode(&data).map_err(|e| format!("cannot decode logo {}: {}", path, e))?)
+            }
+            None => None,
+        };
+        Ok(Self { config, logo })
+    }
+
+    /// Decode JPEG, gambar overlay, encode ulang
+    pub fn apply(&self, stream_id: &str, frame: &[u8], frame_ms: u64) -> Result<Bytes, String> {
+        let mut image = jpeg::decode(frame)?;
+        let mut lines = Vec::new();
+        if self.config.stream_name {
+            lines.push(stream_id.to_string());
+        }
+        if let Some(text) = &self.config.text {
+            lines.push(text.clone());
+        }
+        if self.config.timestamp {
+            lines.push(format_utc(frame_ms));
+        }
+        draw_text(&mut image, &lines, self.config.position, self.config.scale as usize);
+        if let Some(logo) = &self.logo {
+            draw_logo(&mut image, logo, self.config.position, self.config.scale as usize);
+        }
+
+        let (width, height) = (
+            u16::try_from(image.width).map_err(|_| "frame is too wide")?,
+            u16::try_from(image.height).map_err(|_| "frame is too tall")?,
+        );
+        let mut out = Vec::with_capacity(frame.len());
+        Encoder::new(&mut out, self.config.quality)
+            .encode(&image.pixels, width, height, ColorType::Rgb)
+            .map_err(|e| e.to_string())?;
+        Ok(Bytes::from(out))
+    }
+}
+
+/// `YYYY-MM-DD HH:MM:SS.mmm UTC`
+fn format_utc(unix_ms: u64) -> String {
+    let secs = unix_ms / 1000;
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+    // Civil-from-days (Howard Hinnant), proleptic Gregorian
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03} UTC",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60,
+        unix_ms % 1000
+    )
+}
+
+/// Glyph 5x7, satu byte per baris (bit 4 = kolom paling kiri)
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0; 7],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+/// Posisi kiri-atas kotak `width` x `height` di sudut `position`
+fn corner(image: &RgbImage, position: OverlayPosition, width: usize, height: usize, margin: usize) -> (usize, usize) {
+    let right = image.width.saturating_sub(width + margin);
+    let bottom = image.height.saturating_sub(height + margin);
+    match position {
+        OverlayPosition::TopLeft => (margin, margin),
+        OverlayPosition::TopRight => (right, margin),
+        OverlayPosition::BottomLeft => (margin, bottom),
+        OverlayPosition::BottomRight => (right, bottom),
+    }
+}
+
+/// Teks putih di atas kotak hitam semi-transparan
+fn draw_text(image: &mut RgbImage, lines: &[String], position: OverlayPosition, scale: usize) {
+    if lines.is_empty() {
+        return;
+    }
+    let (advance, line_height, pad) = (6 * scale, 9 * scale, 2 * scale);
+    let columns = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+    let box_w = columns * advance + 2 * pad;
+    let box_h = lines.len() * line_height + 2 * pad - 2 * scale;
+    let (x0, y0) = corner(image, position, box_w, box_h, 4 * scale);
+
+    for y in y0..y0 + box_h {
+        for x in x0..x0 + box_w {
+            image.blend(x, y, [0, 0, 0], 160);
+        }
+    }
+    for (row, line) in lines.iter().enumerate() {
+        for (column, c) in line.chars().enumerate() {
+            let (gx, gy) = (x0 + pad + column * advance, y0 + pad + row * line_height);
+            for (dy, bits) in glyph(c).iter().enumerate() {
+                for dx in 0..5 {
+                    if bits & (0x10 >> dx) == 0 {
+                        continue;
+                    }
+                    for sy in 0..scale {
+                        for sx in 0..scale {
+                            image.blend(gx + dx * scale + sx, gy + dy * scale + sy, [255, 255, 255], 255);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn draw_logo(image: &mut RgbImage, logo: &RgbImage, position: OverlayPosition, scale: usize) {
+    let mirrored = match position {
+        OverlayPosition::TopLeft => OverlayPosition::TopRight,
+        OverlayPosition::TopRight => OverlayPosition::TopLeft,
+        OverlayPosition::BottomLeft => OverlayPosition::BottomRight,
+        OverlayPosition::BottomRight => OverlayPosition::BottomLeft,
+    };
+    let (x0, y0) = corner(image, mirrored, logo.width, logo.height, 4 * scale);
+    for y in 0..logo.height {
+        for x in 0..logo.width {
+            image.blend(x0 + x, y0 + y, logo.get(x, y), 255);
+        }
+    }
+}
+
+/// Watermark aktif per stream
+#[derive(Debug, Default)]
+pub struct Watermarks {
+    active: Mutex<HashMap<String, Arc<Watermark>>>,
+}
+
+impl Watermarks {
+    pub fn set(&self, stream_id: &str, watermark: Watermark) {
+        self.active
+            .lock()
+            .insert(stream_id.to_string(), Arc::new(watermark));
+    }
+
+    fn get(&self, stream_id: &str) -> Option<Arc<Watermark>> {
+        self.active.lock().get(stream_id).cloned()
+    }
+
+    /// Ada watermark untuk frame yang masuk ke `stream_id`, termasuk channel demux `stream_id/chN`
+    pub fn covers(&self, stream_id: &str) -> bool {
+        self.active.lock().keys().any(|id| {
+            id.strip_prefix(stream_id)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with("/ch"))
+        })
+    }
+}
+
+/// Terapkan watermark stream (jika ada) pada frame sebelum disiarkan
+///
+/// Returns `None` when the frame cannot be watermarked (not a baseline JPEG);
+/// such frames are dropped rather than published without the overlay.
+pub fn apply(state: &AppState, stream_id: &str, frame: Bytes, producer_ms: Option<u64>) -> Option<Bytes> {
+    let Some(watermark) = state.watermarks.get(stream_id) else {
+        return Some(frame);
+    };
+    match watermark.apply(stream_id, &frame, producer_ms.unwrap_or_else(unix_now_ms)) {
+        Ok(stamped) => Some(stamped),
+        Err(e) => {
+            warn!("Dropping frame of {}: cannot apply watermark: {}", stream_id, e);
+            None
+        }
+    }
+}
+
+/// Handler untuk GET /api/streams/:id/watermark
+pub async fn get_watermark_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Response {
+    match state.watermarks.get(&stream_id) {
+        Some(watermark) => Json(json!({ "stream": stream_id, "watermark": watermark.config })).into_response(),
+        None => BrokerError::not_found("watermark", stream_id).into_response(),
+    }
+}
+
+/// Handler untuk PUT /api/streams/:id/watermark
+pub async fn put_watermark_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+    Json(config): Json<WatermarkConfig>,
+) -> Response {
+    let watermark = match tokio::task::spawn_blocking(move || Watermark::compile(config)).await {
+        Ok(Ok(watermark)) => watermark,
+        Ok(Err(message)) => return BrokerError::InvalidRequest(message).into_response(),
+        Err(e) => return BrokerError::from(e).into_response(),
+    };
+    info!("Watermark set for stream {}", stream_id);
+    let body = json!({ "stream": stream_id, "watermark": watermark.config });
+    state.watermarks.set(&stream_id, watermark);
+    Json(body).into_response()
+}
+
+/// Handler untuk DELETE /api/streams/:id/watermark
+pub async fn delete_watermark_handler(
+    AxumPath(stream_id): AxumPath<String>,
+    State(state): State<AppState>,
+) -> Result<StatusCode, BrokerError> {
+    if state.watermarks.active.lock().remove(&stream_id).is_some() {
+        info!("Watermark removed from stream {}", stream_id);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(BrokerError::not_found("watermark", stream_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watermark_stamps_overlay() {
+        assert_eq!(format_utc(0), "1970-01-01 00:00:00.000 UTC");
+        assert_eq!(format_utc(1_709_210_096_789), "2024-02-29 12:34:56.789 UTC");
+
+        let gray = vec![100u8; 64 * 48 * 3];
+        let mut frame = Vec::new();
+        Encoder::new(&mut frame, 90)
+            .encode(&gray, 64, 48, ColorType::Rgb)
+            .unwrap();
+        let config: WatermarkConfig =
+            serde_json::from_value(json!({ "position": "top-left", "scale": 1, "timestamp": false })).unwrap();
+        let watermark = Watermark::compile(config).unwrap();
+        let stamped = jpeg::decode(&watermark.apply("c1", &frame, 0).unwrap()).unwrap();
+        assert_eq!((stamped.width, stamped.height), (64, 48));
+        // Kotak teks gelap di kiri-atas, sudut kanan-bawah tidak berubah
+        assert!(stamped.get(5, 5)[0] < 60);
+        assert!(stamped.get(60, 44)[0].abs_diff(100) < 8);
+
+        assert!(watermark.apply("c1", b"not a jpeg", 0).is_err());
+        let nothing: WatermarkConfig =
+            serde_json::from_value(json!({ "timestamp": false, "stream_name": false })).unwrap();
+        assert!(nothing.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_watermarked_ingest_covers_demuxed_channels() {
+        use crate::{config::Config, testing::TestBroker};
+
+        let broker = TestBroker::start(Config::default()).await;
+        let config: WatermarkConfig =
+            serde_json::from_value(json!({ "position": "top-left", "scale": 1, "timestamp": false })).unwrap();
+        broker.state.watermarks.set("uplink/ch1", Watermark::compile(config).unwrap());
+        assert!(broker.state.watermarks.covers("uplink"));
+        assert!(broker.state.watermarks.covers("uplink/ch1"));
+        assert!(!broker.state.watermarks.covers("uplink2"));
+        assert!(!broker.state.watermarks.covers("cam1"));
+
+        let mut subscriber = broker.subscriber("uplink%2Fch1").await;
+        let mut producer = broker.producer("uplink?demux=true").await;
+        let mut frame = vec![1];
+        Encoder::new(&mut frame, 90)
+            .encode(&vec![100u8; 64 * 48 * 3], 64, 48, ColorType::Rgb)
+            .unwrap();
+        producer.send_frame(&frame).await;
+        let stamped = jpeg::decode(&subscriber.expect_binary().await).unwrap();
+        assert!(stamped.get(5, 5)[0] < 60);
+    }
+}
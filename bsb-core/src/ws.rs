@@ -0,0 +1,209 @@
+use axum::{
+    extract::ws::{close_code, CloseFrame, WebSocketUpgrade},
+    http::{header, HeaderMap},
+};
+use bytes::Bytes;
+use futures_util::{Sink, SinkExt};
+use std::{error::Error as _, sync::Arc};
+use tungstenite::error::{CapacityError, Error as WsError};
+
+use crate::{config::Config, error::BrokerError, stats::StreamCounters};
+
+/// Subprotocol WebSocket yang dinegosiasikan lewat `Sec-WebSocket-Protocol`
+///
+/// New wire formats get a new name (or version suffix) instead of changing
+/// an existing one, so clients that offer no subprotocol keep the legacy
+/// format of the endpoint they connect to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Subprotocol {
+    /// Payload frames as-is; JSON control messages only with `?seq=true`
+    #[default]
+    Raw,
+    /// Every binary frame is `[u64 seq][u64 producer_ms][payload]` (big-endian),
+    /// and JSON control messages (sync/gap/events) are always sent
+    Envelope,
+    /// Frames of many streams tagged `[u8 id_len][id][payload]` (`/mux`)
+    Multiplex,
+    /// Time-aligned frame groups of several streams (`/bundle`, see [`crate::bundle::Bundle::encode`])
+    Bundle,
+}
+
+impl Subprotocol {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Subprotocol::Raw => "bsb.raw.v1",
+            Subprotocol::Envelope => "bsb.envelope.v1",
+            Subprotocol::Multiplex => "bsb.multiplex.v1",
+            Subprotocol::Bundle => "bsb.bundle.v1",
+        }
+    }
+
+    /// Pilih subprotocol pertama yang ditawarkan klien dan didukung endpoint
+    ///
+    /// No header means a legacy client and gets `default`; offering only
+    /// unknown versions is rejected with 400 rather than silently falling back,
+    /// since such a client would misparse the legacy format.
+    pub fn negotiate(headers: &HeaderMap, supported: &[Subprotocol], default: Subprotocol) -> Result<Self, BrokerError> {
+        let offered: Vec<&str> = headers
+            .get_all(header::SEC_WEBSOCKET_PROTOCOL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect();
+        if offered.is_empty() {
+            return Ok(default);
+        }
+        offered
+            .iter()
+            .find_map(|name| supported.iter().copied().find(|protocol| protocol.name() == *name))
+            .ok_or_else(|| {
+                let supported: Vec<&str> = supported.iter().map(|protocol| protocol.name()).collect();
+                BrokerError::InvalidRequest(format!(
+                    "unsupported subprotocol {}, this endpoint speaks {}",
+                    offered.join(", "),
+                    supported.join(", ")
+                ))
+            })
+    }
+
+    /// Header pembuka frame `bsb.envelope.v1`
+    pub fn envelope(seq: u64, producer_ms: u64, payload: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(16 + payload.len());
+        buf.extend_from_slice(&seq.to_be_bytes());
+        buf.extend_from_slice(&producer_ms.to_be_bytes());
+        buf.extend_from_slice(payload);
+        buf
+    }
+
+    /// Buka frame `bsb.envelope.v1` dari producer: seq producer, payload dan `producer_ms` (`0` = tidak ada)
+    pub fn open_envelope(mut frame: Bytes) -> Option<(u64, Bytes, Option<u64>)> {
+        if frame.len() < 16 {
+            return None;
+        }
+        let header = frame.split_to(16);
+        let seq = u64::from_be_bytes(header[0..8].try_into().expect("8 bytes"));
+        let producer_ms = u64::from_be_bytes(header[8..16].try_into().expect("8 bytes"));
+        Some((seq, frame, (producer_ms > 0).then_some(producer_ms)))
+    }
+}
+
+/// Balas dengan subprotocol yang dipilih, bila klien menawarkan salah satu
+pub fn accept_protocol(ws: WebSocketUpgrade, protocol: Subprotocol) -> WebSocketUpgrade {
+    ws.protocols([protocol.name()])
+}
+
+/// Terapkan batas ukuran pesan/frame dari konfigurasi ke upgrade WebSocket
+pub fn apply_limits(ws: WebSocketUpgrade, config: &Config) -> WebSocketUpgrade {
+    ws.max_message_size(config.ws_max_message_size)
+        .max_frame_size(config.ws_max_frame_size)
+}
+
+/// Map a receive error to the close frame explaining it to the peer
+///
+/// Only limit violations get a close frame (1009 "message too big"); other
+/// errors mean the connection is already unusable.
+pub fn close_frame_for_error(error: &axum::Error) -> Option<CloseFrame<'static>> {
+    let ws_error = error.source()?.downcast_ref::<WsError>()?;
+    let reason = match ws_error {
+        WsError::Capacity(CapacityError::MessageTooLong { size, max_size }) => {
+            format!("message too big: {} bytes exceeds limit of {}", size, max_size)
+        }
+        WsError::Capacity(CapacityError::TooManyHeaders) => "too many headers".to_string(),
+        _ => return None,
+    };
+    Some(CloseFrame {
+        code: close_code::SIZE,
+        reason: reason.into(),
+    })
+}
+
+/// Gabungkan beberapa frame subscriber dalam satu flush (satu write ke socket)
+///
+/// Frames are fed to the sink, which only encodes them into its write buffer;
+/// the buffer goes out in one write once the subscriber's queue is empty or
+/// `max_frames` are pending. Every frame stays its own WebSocket message, so
+/// this works for every subprotocol.
+pub struct Coalescer {
+    max_frames: usize,
+    pending: usize,
+    counters: Arc<StreamCounters>,
+}
+
+impl Coalescer {
+    pub fn new(max_frames: usize, counters: Arc<StreamCounters>) -> Self {
+        Self {
+            max_frames: max_frames.max(1),
+            pending: 0,
+            counters,
+        }
+    }
+
+    /// Satu frame sudah di-feed; flush bila tidak ada lagi yang mengantri atau batas tercapai
+    pub async fn fed<S: Sink<M> + Unpin, M>(&mut self, sender: &mut S, more_queued: bool) -> Result<(), S::Error> {
+        self.pending += 1;
+        if !more_queued || self.pending >= self.max_frames {
+            self.flush(sender).await?;
+        }
+        Ok(())
+    }
+
+    /// Kirim frame yang masih di buffer
+    pub async fn flush<S: Sink<M> + Unpin, M>(&mut self, sender: &mut S) -> Result<(), S::Error> {
+        if self.pending == 0 {
+            return Ok(());
+        }
+        sender.flush().await?;
+        self.counters.record_flush(self.pending);
+        self.pending = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::sink;
+    use std::sync::atomic::Ordering;
+
+    #[tokio::test]
+    async fn test_coalescer_flushes_once_per_burst() {
+        let counters = Arc::new(StreamCounters::default());
+        let mut coalescer = Coalescer::new(4, counters.clone());
+        let mut sender = sink::drain::<u32>();
+
+        // 6 frame mengantri: flush di frame ke-4 (batas) dan saat antrian kosong
+        for i in 0..6 {
+            sender.feed(i).await.unwrap();
+            coalescer.fed(&mut sender, i < 5).await.unwrap();
+        }
+        assert_eq!(counters.flushes.load(Ordering::Relaxed), 2);
+        assert_eq!(counters.coalescing_factor(), 3.0);
+        // Tidak ada yang tertunda: flush tidak dihitung
+        coalescer.flush(&mut sender).await.unwrap();
+        assert_eq!(counters.flushes.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_message_closes_with_1009() {
+        use crate::testing::{TestBroker, RECV_TIMEOUT};
+        use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+
+        let broker = TestBroker::start(Config {
+            ws_max_message_size: 1024,
+            ws_max_frame_size: 1024,
+            ..Config::default()
+        })
+        .await;
+        let mut subscriber = broker.subscriber("cam1").await;
+        let mut producer = broker.producer("cam1").await;
+        // Frame yang masih dalam batas tetap dipublikasikan
+        producer.send_frame(&[7; 1024]).await;
+        assert_eq!(subscriber.expect_binary().await.len(), 1024);
+        producer.send_frame(&[7; 1025]).await;
+        let close = producer.expect_close(RECV_TIMEOUT).await.expect("close frame");
+        assert_eq!(close.code, CloseCode::Size);
+        assert!(close.reason.contains("1024"), "{}", close.reason);
+    }
+}
@@ -0,0 +1,19 @@
+//! Display an MJPEG stream: `cargo run --example play_mjpeg -- ws://127.0.0.1:3091 cam1`
+
+fn main() -> Result<(), bsb_gst::Error> {
+    tracing_subscriber::fmt::init();
+    let mut args = std::env::args().skip(1);
+    let url = args.next().unwrap_or_else(|| "ws://127.0.0.1:3091".to_string());
+    let stream_id = args.next().unwrap_or_else(|| "cam1".to_string());
+
+    let mut broker = bsb_gst::BrokerConfig::new(url);
+    if let Ok(token) = std::env::var("BSB_TOKEN") {
+        broker = broker.with_token(token);
+    }
+    let pipeline = bsb_gst::subscribe_pipeline(
+        "appsrc name=bsb caps=image/jpeg ! jpegdec ! videoconvert ! autovideosink",
+        &broker,
+        &stream_id,
+    )?;
+    bsb_gst::run(&pipeline)
+}
@@ -0,0 +1,20 @@
+//! Publish color bars as MJPEG: `cargo run --example publish_testsrc -- ws://127.0.0.1:3091 cam1`
+
+fn main() -> Result<(), bsb_gst::Error> {
+    tracing_subscriber::fmt::init();
+    let mut args = std::env::args().skip(1);
+    let url = args.next().unwrap_or_else(|| "ws://127.0.0.1:3091".to_string());
+    let stream_id = args.next().unwrap_or_else(|| "cam1".to_string());
+
+    let mut broker = bsb_gst::BrokerConfig::new(url);
+    if let Ok(token) = std::env::var("BSB_TOKEN") {
+        broker = broker.with_token(token);
+    }
+    let pipeline = bsb_gst::publish_pipeline(
+        "videotestsrc is-live=true ! video/x-raw,width=640,height=360,framerate=15/1 \
+         ! jpegenc ! appsink name=bsb sync=false",
+        &broker,
+        &stream_id,
+    )?;
+    bsb_gst::run(&pipeline)
+}
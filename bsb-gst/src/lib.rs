@@ -0,0 +1,322 @@
+//! GStreamer integration for the binary stream broker
+//!
+//! - [`Publisher`] sends every buffer of an `appsink` to the broker's WebSocket
+//!   ingest (`/ingest/:stream_id`), one buffer per frame
+//! - [`Subscriber`] pushes every frame of `/ws/:stream_id` into an `appsrc`
+//!
+//! [`publish_pipeline`] and [`subscribe_pipeline`] wire either one into a
+//! `gst-launch` style description whose appsink/appsrc is named `bsb`:
+//!
+//! ```no_run
+//! let broker = bsb_gst::BrokerConfig::new("ws://127.0.0.1:3091");
+//! let pipeline = bsb_gst::publish_pipeline(
+//!     "videotestsrc is-live=true ! jpegenc ! appsink name=bsb",
+//!     &broker,
+//!     "cam1",
+//! )?;
+//! bsb_gst::run(&pipeline)?;
+//! # Ok::<(), bsb_gst::Error>(())
+//! ```
+
+use gstreamer as gst;
+use gstreamer_app as gst_app;
+
+use gst::prelude::*;
+use std::{
+    fmt,
+    net::TcpStream,
+    sync::Mutex,
+    thread::{self, JoinHandle},
+};
+use tracing::{error, info};
+use tungstenite::{
+    client::IntoClientRequest,
+    http::{header, HeaderValue},
+    stream::MaybeTlsStream,
+    Message, WebSocket,
+};
+
+/// Nama appsink/appsrc yang dicari oleh [`publish_pipeline`] dan [`subscribe_pipeline`]
+pub const ELEMENT_NAME: &str = "bsb";
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug)]
+pub enum Error {
+    WebSocket(tungstenite::Error),
+    InvalidToken,
+    Gst(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::WebSocket(e) => write!(f, "broker connection failed: {}", e),
+            Error::InvalidToken => write!(f, "token is not a valid header value"),
+            Error::Gst(e) => write!(f, "gstreamer: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<tungstenite::Error> for Error {
+    fn from(e: tungstenite::Error) -> Self {
+        Error::WebSocket(e)
+    }
+}
+
+impl From<gst::glib::Error> for Error {
+    fn from(e: gst::glib::Error) -> Self {
+        Error::Gst(e.to_string())
+    }
+}
+
+impl From<gst::glib::BoolError> for Error {
+    fn from(e: gst::glib::BoolError) -> Self {
+        Error::Gst(e.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Alamat broker dan token opsional (ingest credential atau playback token)
+#[derive(Clone)]
+pub struct BrokerConfig {
+    /// Base URL, e.g. `ws://127.0.0.1:3091` or `wss://broker.example:3090`
+    pub url: String,
+    token: Option<String>,
+}
+
+impl fmt::Debug for BrokerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BrokerConfig").field("url", &self.url).finish()
+    }
+}
+
+impl BrokerConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            token: None,
+        }
+    }
+
+    /// Send `Authorization: Bearer <token>` on connect
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    fn endpoint_url(&self, path: &str, stream_id: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            self.url.trim_end_matches('/'),
+            path,
+            encode_path_segment(stream_id)
+        )
+    }
+
+    fn connect(&self, path: &str, stream_id: &str) -> Result<Socket> {
+        let url = self.endpoint_url(path, stream_id);
+        let mut request = url.as_str().into_client_request()?;
+        if let Some(token) = &self.token {
+            let value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|_| Error::InvalidToken)?;
+            request.headers_mut().insert(header::AUTHORIZATION, value);
+        }
+        let (socket, _) = tungstenite::connect(request)?;
+        info!("Connected to {}", url);
+        Ok(socket)
+    }
+}
+
+/// Percent-encode satu segmen path (stream ID boleh mengandung `/`)
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Producer WebSocket ke `/ingest/:stream_id`
+pub struct Publisher {
+    socket: Socket,
+}
+
+impl Publisher {
+    pub fn connect(broker: &BrokerConfig, stream_id: &str) -> Result<Self> {
+        Ok(Self {
+            socket: broker.connect("ingest", stream_id)?,
+        })
+    }
+
+    /// Kirim satu frame
+    pub fn send(&mut self, frame: &[u8]) -> Result<()> {
+        self.socket.send(Message::Binary(frame.to_vec()))?;
+        Ok(())
+    }
+
+    /// Publish every sample of `appsink` as one frame
+    ///
+    /// A failed send posts a flow error, so the pipeline stops with an error
+    /// message on its bus instead of silently dropping frames.
+    pub fn attach(self, appsink: &gst_app::AppSink) {
+        let publisher = Mutex::new(self);
+        appsink.set_callbacks(
+            gst_app::AppSinkCallbacks::builder()
+                .new_sample(move |sink| {
+                    let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                    let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+                    let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+                    publisher.lock().unwrap().send(&map).map_err(|e| {
+                        error!("Failed to publish frame: {}", e);
+                        gst::FlowError::Error
+                    })?;
+                    Ok(gst::FlowSuccess::Ok)
+                })
+                .build(),
+        );
+    }
+}
+
+/// Subscriber WebSocket ke `/ws/:stream_id`
+pub struct Subscriber {
+    socket: Socket,
+}
+
+impl Subscriber {
+    pub fn connect(broker: &BrokerConfig, stream_id: &str) -> Result<Self> {
+        Ok(Self {
+            socket: broker.connect("ws", stream_id)?,
+        })
+    }
+
+    /// Blocking read of the next binary frame; `None` once the broker closed the stream
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>> {
+        loop {
+            match self.socket.read() {
+                Ok(Message::Binary(data)) => return Ok(Some(data)),
+                Ok(Message::Close(_)) | Err(tungstenite::Error::ConnectionClosed) => return Ok(None),
+                // Ping dijawab otomatis oleh tungstenite; pesan teks adalah control message
+                Ok(_) => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Push every frame into `appsrc` from a background thread
+    ///
+    /// The appsrc is configured as a live, timestamping source; it receives
+    /// EOS when the broker closes the connection.
+    pub fn attach(mut self, appsrc: &gst_app::AppSrc) -> JoinHandle<()> {
+        appsrc.set_is_live(true);
+        appsrc.set_format(gst::Format::Time);
+        appsrc.set_do_timestamp(true);
+        let appsrc = appsrc.clone();
+        thread::spawn(move || {
+            loop {
+                match self.next_frame() {
+                    Ok(Some(frame)) => {
+                        if appsrc.push_buffer(gst::Buffer::from_mut_slice(frame)).is_err() {
+                            // Pipeline sudah berhenti (flushing/EOS)
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!("Subscription failed: {}", e);
+                        break;
+                    }
+                }
+            }
+            let _ = appsrc.end_of_stream();
+        })
+    }
+}
+
+fn launch(description: &str) -> Result<gst::Pipeline> {
+    gst::init()?;
+    gst::parse::launch(description)?
+        .dynamic_cast::<gst::Pipeline>()
+        .map_err(|_| Error::Gst("description is not a pipeline".to_string()))
+}
+
+fn named<T: IsA<gst::Element>>(pipeline: &gst::Pipeline) -> Result<T> {
+    pipeline
+        .by_name(ELEMENT_NAME)
+        .and_then(|element| element.dynamic_cast::<T>().ok())
+        .ok_or_else(|| {
+            Error::Gst(format!(
+                "pipeline has no {} named {}",
+                T::static_type().name(),
+                ELEMENT_NAME
+            ))
+        })
+}
+
+/// Build a pipeline ending in `appsink name=bsb` that publishes to `stream_id`
+pub fn publish_pipeline(
+    description: &str,
+    broker: &BrokerConfig,
+    stream_id: &str,
+) -> Result<gst::Pipeline> {
+    let pipeline = launch(description)?;
+    let appsink: gst_app::AppSink = named(&pipeline)?;
+    Publisher::connect(broker, stream_id)?.attach(&appsink);
+    Ok(pipeline)
+}
+
+/// Build a pipeline starting with `appsrc name=bsb` fed by `stream_id`
+pub fn subscribe_pipeline(
+    description: &str,
+    broker: &BrokerConfig,
+    stream_id: &str,
+) -> Result<gst::Pipeline> {
+    let pipeline = launch(description)?;
+    let appsrc: gst_app::AppSrc = named(&pipeline)?;
+    Subscriber::connect(broker, stream_id)?.attach(&appsrc);
+    Ok(pipeline)
+}
+
+/// Jalankan pipeline sampai EOS atau error
+pub fn run(pipeline: &gst::Pipeline) -> Result<()> {
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|e| Error::Gst(e.to_string()))?;
+    let bus = pipeline.bus().expect("pipelines always have a bus");
+    let mut result = Ok(());
+    for msg in bus.iter_timed(gst::ClockTime::NONE) {
+        match msg.view() {
+            gst::MessageView::Eos(..) => break,
+            gst::MessageView::Error(err) => {
+                result = Err(Error::Gst(err.error().to_string()));
+                break;
+            }
+            _ => {}
+        }
+    }
+    let _ = pipeline.set_state(gst::State::Null);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_url() {
+        let broker = BrokerConfig::new("wss://broker.example:3090/").with_token("tok");
+        assert_eq!(
+            broker.endpoint_url("ingest", "site-a/cam 1"),
+            "wss://broker.example:3090/ingest/site-a%2Fcam%201"
+        );
+        assert_eq!(broker.endpoint_url("ws", "cam1"), "wss://broker.example:3090/ws/cam1");
+        assert!(!format!("{:?}", broker).contains("tok"));
+    }
+}
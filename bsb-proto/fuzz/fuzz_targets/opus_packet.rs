@@ -0,0 +1,10 @@
+#![no_main]
+
+use bsb_proto::opus::{packet_duration_us, MAX_PACKET_DURATION_US};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|packet: &[u8]| {
+    if let Ok(duration) = packet_duration_us(packet) {
+        assert!(duration > 0 && duration <= MAX_PACKET_DURATION_US);
+    }
+});
@@ -0,0 +1,20 @@
+//! Frame ingest dari producer: byte pertama input memilih opsi, sisanya frame
+
+#![no_main]
+
+use bsb_proto::{parse_frame, FrameOptions};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&flags, frame)) = data.split_first() else {
+        return;
+    };
+    let options = FrameOptions {
+        crc32c: flags & 1 != 0,
+        demux: flags & 2 != 0,
+    };
+    if let Ok(parsed) = parse_frame(frame, options) {
+        assert!(parsed.payload.start <= parsed.payload.end && parsed.payload.end <= frame.len());
+        assert_eq!(parsed.channel.is_some(), options.demux);
+    }
+});
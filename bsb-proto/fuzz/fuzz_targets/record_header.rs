@@ -0,0 +1,16 @@
+#![no_main]
+
+use bsb_proto::record::{decode_record_header, encode_record, RECORD_HEADER_LEN};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some(header) = data.get(..RECORD_HEADER_LEN) else {
+        return;
+    };
+    let header: &[u8; RECORD_HEADER_LEN] = header.try_into().unwrap();
+    let (seq, unix_ms, len) = decode_record_header(header);
+    let payload = &data[RECORD_HEADER_LEN..];
+    if payload.len() == len {
+        assert_eq!(encode_record(seq, unix_ms, payload), data);
+    }
+});
@@ -0,0 +1,21 @@
+#![no_main]
+
+use bsb_proto::shm::{decode_hello, read_step, ReadStep};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_hello(data);
+    let Some(fields) = data.get(..28) else {
+        return;
+    };
+    let u64_at = |at: usize| u64::from_ne_bytes(fields[at..at + 8].try_into().unwrap());
+    let (read_pos, write_pos, capacity) = (u64_at(0) >> 2, u64_at(8) >> 2, u64_at(16) >> 32);
+    let len = u32::from_ne_bytes(fields[24..28].try_into().unwrap());
+    match read_step(read_pos, write_pos, capacity, len) {
+        Ok(ReadStep::Record { payload, next }) => {
+            assert!(payload.end as u64 <= capacity && next <= write_pos);
+        }
+        Ok(ReadStep::Skip { next }) => assert!(next > read_pos && next <= write_pos),
+        Err(_) => {}
+    }
+});
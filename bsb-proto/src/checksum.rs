@@ -0,0 +1,44 @@
+//! CRC-32C (Castagnoli) untuk trailer frame dan journal rekaman
+
+/// Panjang trailer checksum di akhir frame (big-endian)
+pub const CRC32C_TRAILER_LEN: usize = 4;
+
+const CRC32C_POLY: u32 = 0x82F6_3B78;
+
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ CRC32C_POLY } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+pub fn crc32c(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &b| {
+        CRC32C_TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Payload diikuti trailer CRC-32C
+pub fn append_crc32c(payload: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(payload.len() + CRC32C_TRAILER_LEN);
+    buf.extend_from_slice(payload);
+    buf.extend_from_slice(&crc32c(payload).to_be_bytes());
+    buf
+}
+
+/// Payload tanpa trailer; `None` bila trailer tidak ada atau tidak cocok
+pub fn strip_crc32c(frame: &[u8]) -> Option<&[u8]> {
+    let payload_len = frame.len().checked_sub(CRC32C_TRAILER_LEN)?;
+    let (payload, trailer) = frame.split_at(payload_len);
+    let expected = u32::from_be_bytes(trailer.try_into().ok()?);
+    (crc32c(payload) == expected).then_some(payload)
+}
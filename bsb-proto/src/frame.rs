@@ -0,0 +1,60 @@
+//! Frame ingest: trailer checksum lalu byte channel demux
+
+use std::{fmt, ops::Range};
+
+use crate::checksum::{strip_crc32c, CRC32C_TRAILER_LEN};
+
+/// Bagian frame yang dipakai producer, dari query parameter ingest
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameOptions {
+    /// The frame ends with a CRC-32C trailer (`?checksum=crc32c`)
+    pub crc32c: bool,
+    /// The first payload byte is a channel number (`?demux=true`)
+    pub demux: bool,
+}
+
+/// Frame yang valid: channel demux dan posisi payload di frame asli
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedFrame {
+    /// Channel byte when `demux` is set
+    pub channel: Option<u8>,
+    /// Payload range within the input, so callers can slice without copying
+    pub payload: Range<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    /// The CRC-32C trailer is missing or does not match the payload
+    ChecksumMismatch,
+    /// A demux frame without the channel byte
+    MissingChannel,
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::ChecksumMismatch => write!(f, "checksum mismatch"),
+            FrameError::MissingChannel => write!(f, "demux frame needs a channel byte"),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+/// Parse satu frame dari producer: verifikasi trailer, lalu pisahkan byte channel
+pub fn parse_frame(frame: &[u8], options: FrameOptions) -> Result<ParsedFrame, FrameError> {
+    let mut payload = 0..frame.len();
+    if options.crc32c {
+        strip_crc32c(frame).ok_or(FrameError::ChecksumMismatch)?;
+        payload.end -= CRC32C_TRAILER_LEN;
+    }
+    let mut channel = None;
+    if options.demux {
+        if payload.is_empty() {
+            return Err(FrameError::MissingChannel);
+        }
+        channel = Some(frame[payload.start]);
+        payload.start += 1;
+    }
+    Ok(ParsedFrame { channel, payload })
+}
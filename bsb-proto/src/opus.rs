@@ -0,0 +1,35 @@
+//! TOC byte packet Opus (RFC 6716 section 3.1)
+
+/// Batas durasi satu packet Opus (RFC 6716 section 3.2.5)
+pub const MAX_PACKET_DURATION_US: u32 = 120_000;
+
+/// Durasi satu packet Opus dalam mikrodetik, dari TOC byte
+pub fn packet_duration_us(packet: &[u8]) -> Result<u32, &'static str> {
+    let toc = *packet.first().ok_or("empty Opus packet")?;
+    let config = toc >> 3;
+    let frame_us = match config {
+        0..=11 => [10_000, 20_000, 40_000, 60_000][config as usize % 4],
+        12..=15 => [10_000, 20_000][config as usize % 2],
+        _ => [2_500, 5_000, 10_000, 20_000][config as usize % 4],
+    };
+    let frames = match toc & 3 {
+        0 => 1,
+        1 if !(packet.len() - 1).is_multiple_of(2) => return Err("Opus code 1 packet has odd payload length"),
+        1 | 2 => 2,
+        _ => {
+            let count = packet.get(1).ok_or("Opus code 3 packet without frame count")? & 0x3F;
+            if count == 0 {
+                return Err("Opus code 3 packet with zero frames");
+            }
+            count as u32
+        }
+    };
+    if toc & 3 == 2 && packet.len() < 2 {
+        return Err("Opus code 2 packet without frame length");
+    }
+    let duration = frame_us * frames;
+    if duration > MAX_PACKET_DURATION_US {
+        return Err("Opus packet longer than 120 ms");
+    }
+    Ok(duration)
+}
@@ -0,0 +1,25 @@
+//! Record segmen rekaman (`.bsbrec`)
+
+/// Header satu record di segmen: `[u64 seq][u64 unix_ms][u32 len]`, big-endian
+pub const RECORD_HEADER_LEN: usize = 20;
+
+/// Encode satu frame sebagai record segmen
+pub fn encode_record(seq: u64, unix_ms: u64, payload: &[u8]) -> Vec<u8> {
+    let mut record = Vec::with_capacity(RECORD_HEADER_LEN + payload.len());
+    record.extend_from_slice(&seq.to_be_bytes());
+    record.extend_from_slice(&unix_ms.to_be_bytes());
+    record.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    record.extend_from_slice(payload);
+    record
+}
+
+/// Decode header record: `(seq, unix_ms, payload len)`
+pub fn decode_record_header(header: &[u8; RECORD_HEADER_LEN]) -> (u64, u64, usize) {
+    let (seq, rest) = header.split_at(8);
+    let (unix_ms, len) = rest.split_at(8);
+    (
+        u64::from_be_bytes(seq.try_into().expect("8 bytes")),
+        u64::from_be_bytes(unix_ms.try_into().expect("8 bytes")),
+        u32::from_be_bytes(len.try_into().expect("4 bytes")) as usize,
+    )
+}
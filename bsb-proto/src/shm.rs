@@ -0,0 +1,213 @@
+//! Ring shared-memory untuk producer di host yang sama (`SHM_INGEST_SOCKET`)
+//!
+//! The producer creates a memfd of `HEADER_LEN + capacity` bytes plus an
+//! eventfd, and hands both to the broker over a Unix socket (`SCM_RIGHTS`)
+//! with a hello naming the stream. Integers inside the ring are in native
+//! byte order, since both sides run on the same host:
+//!
+//! ```text
+//! 0    [u32 MAGIC][u32 VERSION][u64 capacity]
+//! 64   [u64 write_pos]   written by the producer only
+//! 128  [u64 read_pos]    written by the broker only
+//! 192  data: records [u32 len][u32 0][payload], each padded to 8 bytes
+//! ```
+//!
+//! Positions count every byte ever written or read and are taken modulo the
+//! capacity. A record never wraps: when it does not fit before the end of the
+//! data area, the producer writes a `PADDING` length there and starts the
+//! record at offset 0.
+
+use std::{fmt, ops::Range};
+
+pub const MAGIC: u32 = u32::from_be_bytes(*b"BSBR");
+pub const VERSION: u32 = 1;
+/// Header ring sampai awal area data; posisi di cache line sendiri
+pub const HEADER_LEN: usize = 192;
+pub const WRITE_POS_OFFSET: usize = 64;
+pub const READ_POS_OFFSET: usize = 128;
+/// `[u32 len][u32 0]`, supaya payload selalu rata 8 byte
+pub const RECORD_HEADER_LEN: usize = 8;
+/// Panjang penanda sisa area data yang dilewati
+pub const PADDING: u32 = u32::MAX;
+pub const MIN_CAPACITY: u64 = 64 << 10;
+/// Batas atas yang masih bisa di-mmap di perangkat 32-bit
+pub const MAX_CAPACITY: u64 = 1 << 30;
+/// Status balasan broker untuk hello yang diterima
+pub const ACCEPTED: u8 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShmError {
+    BadMagic,
+    UnsupportedVersion(u32),
+    /// Outside `MIN_CAPACITY..=MAX_CAPACITY` or not a multiple of 8
+    BadCapacity(u64),
+    /// The broker has not read far enough yet to make room for the record
+    Full,
+    /// The record does not fit in the ring at all
+    TooLarge,
+    /// Positions or a length that no correct producer writes
+    Corrupt,
+}
+
+impl fmt::Display for ShmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShmError::BadMagic => write!(f, "not a broker shared-memory ring"),
+            ShmError::UnsupportedVersion(version) => write!(f, "unsupported ring version {}", version),
+            ShmError::BadCapacity(capacity) => write!(
+                f,
+                "ring capacity {} must be a multiple of 8 between {} and {}",
+                capacity, MIN_CAPACITY, MAX_CAPACITY
+            ),
+            ShmError::Full => write!(f, "ring is full"),
+            ShmError::TooLarge => write!(f, "frame is larger than the ring"),
+            ShmError::Corrupt => write!(f, "ring positions or record length are corrupt"),
+        }
+    }
+}
+
+impl std::error::Error for ShmError {}
+
+/// 16 byte pertama ring
+pub fn encode_header(capacity: u64) -> [u8; 16] {
+    let mut header = [0; 16];
+    header[..4].copy_from_slice(&MAGIC.to_ne_bytes());
+    header[4..8].copy_from_slice(&VERSION.to_ne_bytes());
+    header[8..].copy_from_slice(&capacity.to_ne_bytes());
+    header
+}
+
+/// Kapasitas area data dari header ring
+pub fn decode_header(header: &[u8; 16]) -> Result<u64, ShmError> {
+    let (magic, rest) = header.split_at(4);
+    let (version, capacity) = rest.split_at(4);
+    if u32::from_ne_bytes(magic.try_into().expect("4 bytes")) != MAGIC {
+        return Err(ShmError::BadMagic);
+    }
+    let version = u32::from_ne_bytes(version.try_into().expect("4 bytes"));
+    if version != VERSION {
+        return Err(ShmError::UnsupportedVersion(version));
+    }
+    let capacity = u64::from_ne_bytes(capacity.try_into().expect("8 bytes"));
+    if !(MIN_CAPACITY..=MAX_CAPACITY).contains(&capacity) || !capacity.is_multiple_of(8) {
+        return Err(ShmError::BadCapacity(capacity));
+    }
+    Ok(capacity)
+}
+
+/// Byte yang dipakai satu record di area data
+pub fn slot_len(payload_len: usize) -> u64 {
+    (RECORD_HEADER_LEN as u64 + payload_len as u64 + 7) & !7
+}
+
+/// Tempat record berikutnya ditulis producer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WritePlan {
+    /// Offset that gets a `PADDING` length because the record wraps to 0
+    pub padding: Option<usize>,
+    /// Offset of the record header in the data area
+    pub offset: usize,
+    /// `write_pos` once the record is written
+    pub next: u64,
+}
+
+/// Rencana tulis satu payload; `Full` bila broker belum membaca cukup jauh
+pub fn plan_write(write_pos: u64, read_pos: u64, capacity: u64, payload_len: usize) -> Result<WritePlan, ShmError> {
+    let slot = slot_len(payload_len);
+    if slot > capacity {
+        return Err(ShmError::TooLarge);
+    }
+    if read_pos > write_pos || write_pos - read_pos > capacity {
+        return Err(ShmError::Corrupt);
+    }
+    let tail = capacity - write_pos % capacity;
+    let (padding, start) = if slot > tail {
+        (Some((write_pos % capacity) as usize), write_pos + tail)
+    } else {
+        (None, write_pos)
+    };
+    let next = start + slot;
+    if next - read_pos > capacity {
+        return Err(ShmError::Full);
+    }
+    Ok(WritePlan {
+        padding,
+        offset: (start % capacity) as usize,
+        next,
+    })
+}
+
+/// Yang ditemukan broker di `read_pos`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadStep {
+    /// Payload range in the data area
+    Record { payload: Range<usize>, next: u64 },
+    /// Padding up to the end of the data area
+    Skip { next: u64 },
+}
+
+/// Baca satu langkah; `len` adalah u32 di offset `read_pos % capacity`
+///
+/// The producer controls every byte of the ring, so the result is checked to
+/// lie inside the data area and behind `write_pos`.
+pub fn read_step(read_pos: u64, write_pos: u64, capacity: u64, len: u32) -> Result<ReadStep, ShmError> {
+    if capacity == 0 || !capacity.is_multiple_of(8) || read_pos >= write_pos || write_pos - read_pos > capacity {
+        return Err(ShmError::Corrupt);
+    }
+    let offset = read_pos % capacity;
+    let tail = capacity - offset;
+    if len == PADDING {
+        let next = read_pos + tail;
+        return if next <= write_pos { Ok(ReadStep::Skip { next }) } else { Err(ShmError::Corrupt) };
+    }
+    let slot = slot_len(len as usize);
+    if slot > tail || read_pos + slot > write_pos {
+        return Err(ShmError::Corrupt);
+    }
+    let start = offset as usize + RECORD_HEADER_LEN;
+    Ok(ReadStep::Record {
+        payload: start..start + len as usize,
+        next: read_pos + slot,
+    })
+}
+
+/// Hello producer: `[u32 MAGIC][u16 len][stream id]`, big-endian seperti pesan wire lain
+pub fn encode_hello(stream_id: &str) -> Result<Vec<u8>, ShmError> {
+    let len = u16::try_from(stream_id.len()).map_err(|_| ShmError::TooLarge)?;
+    let mut hello = Vec::with_capacity(6 + stream_id.len());
+    hello.extend_from_slice(&MAGIC.to_be_bytes());
+    hello.extend_from_slice(&len.to_be_bytes());
+    hello.extend_from_slice(stream_id.as_bytes());
+    Ok(hello)
+}
+
+/// Stream ID dari hello producer
+pub fn decode_hello(hello: &[u8]) -> Result<&str, ShmError> {
+    let (Some(magic), Some(len)) = (hello.get(..4), hello.get(4..6)) else {
+        return Err(ShmError::Corrupt);
+    };
+    if magic != MAGIC.to_be_bytes() {
+        return Err(ShmError::BadMagic);
+    }
+    let len = u16::from_be_bytes([len[0], len[1]]) as usize;
+    match hello.get(6..) {
+        Some(id) if id.len() == len && !id.is_empty() => std::str::from_utf8(id).map_err(|_| ShmError::Corrupt),
+        _ => Err(ShmError::Corrupt),
+    }
+}
+
+/// Balasan broker: `[u8 status]`, diikuti alasan (UTF-8) bila ditolak
+pub fn encode_reply(result: Result<(), &str>) -> Vec<u8> {
+    match result {
+        Ok(()) => vec![ACCEPTED],
+        Err(reason) => [&[1][..], reason.as_bytes()].concat(),
+    }
+}
+
+pub fn decode_reply(reply: &[u8]) -> Result<(), String> {
+    match reply.split_first() {
+        Some((&ACCEPTED, _)) => Ok(()),
+        Some((_, reason)) => Err(String::from_utf8_lossy(reason).into_owned()),
+        None => Err("broker closed the connection".to_string()),
+    }
+}
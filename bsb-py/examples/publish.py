@@ -0,0 +1,17 @@
+"""Publish a counter frame every 100 ms: python examples/publish.py cam1"""
+import asyncio
+import sys
+
+import bsb
+
+
+async def main(stream_id: str) -> None:
+    pub = await bsb.Publisher.connect("ws://127.0.0.1:3091", stream_id)
+    for n in range(100):
+        await pub.send(n.to_bytes(8, "big"))
+        await asyncio.sleep(0.1)
+    await pub.close()
+
+
+if __name__ == "__main__":
+    asyncio.run(main(sys.argv[1] if len(sys.argv) > 1 else "cam1"))
@@ -0,0 +1,15 @@
+"""Print the size of every frame of a stream: python examples/subscribe.py cam1"""
+import asyncio
+import sys
+
+import bsb
+
+
+async def main(stream_id: str) -> None:
+    sub = await bsb.Subscriber.connect("ws://127.0.0.1:3091", stream_id)
+    async for frame in sub:
+        print(f"{stream_id}: {len(frame)} bytes")
+
+
+if __name__ == "__main__":
+    asyncio.run(main(sys.argv[1] if len(sys.argv) > 1 else "cam1"))
@@ -0,0 +1,230 @@
+//! Python bindings for the binary stream broker
+//!
+//! ```python
+//! import asyncio, bsb
+//!
+//! async def main():
+//!     pub = await bsb.Publisher.connect("ws://127.0.0.1:3091", "cam1", token="tok-123")
+//!     await pub.send(b"\xff\xd8...")
+//!
+//!     sub = await bsb.Subscriber.connect("ws://127.0.0.1:3091", "cam1")
+//!     async for frame in sub:
+//!         print(len(frame))
+//! ```
+//!
+//! Both classes also have blocking variants (`send_blocking`, `recv_blocking`)
+//! for scripts without an event loop; they release the GIL while waiting.
+//! On Linux, `ShmPublisher` publishes through the broker's shared-memory ring
+//! (`SHM_INGEST_SOCKET`) when the producer runs on the same host.
+
+#[cfg(target_os = "linux")]
+mod shm;
+
+use futures_util::{SinkExt, StreamExt};
+use pyo3::{
+    exceptions::{PyConnectionError, PyStopAsyncIteration, PyValueError},
+    prelude::*,
+    types::PyBytes,
+};
+use std::sync::Arc;
+use tokio::{net::TcpStream, sync::Mutex};
+use tokio_tungstenite::{
+    tungstenite::{
+        client::IntoClientRequest,
+        http::{header, HeaderValue},
+        Message,
+    },
+    MaybeTlsStream, WebSocketStream,
+};
+
+type Socket = Arc<Mutex<WebSocketStream<MaybeTlsStream<TcpStream>>>>;
+
+fn connection_error(e: impl std::fmt::Display) -> PyErr {
+    PyConnectionError::new_err(e.to_string())
+}
+
+/// URL endpoint broker, stream ID di-percent-encode (boleh mengandung `/`)
+fn endpoint_url(base: &str, path: &str, stream_id: &str) -> String {
+    let stream_id: String = stream_id
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!("{}/{}/{}", base.trim_end_matches('/'), path, stream_id)
+}
+
+async fn connect(url: String, token: Option<String>) -> PyResult<Socket> {
+    let mut request = url.as_str().into_client_request().map_err(connection_error)?;
+    if let Some(token) = token {
+        let value = HeaderValue::from_str(&format!("Bearer {}", token))
+            .map_err(|_| PyValueError::new_err("token is not a valid header value"))?;
+        request.headers_mut().insert(header::AUTHORIZATION, value);
+    }
+    let (socket, _) = tokio_tungstenite::connect_async(request)
+        .await
+        .map_err(connection_error)?;
+    Ok(Arc::new(Mutex::new(socket)))
+}
+
+async fn send(socket: Socket, frame: Vec<u8>) -> PyResult<()> {
+    socket
+        .lock()
+        .await
+        .send(Message::Binary(frame))
+        .await
+        .map_err(connection_error)
+}
+
+/// Frame biner berikutnya; `None` saat broker menutup koneksi
+async fn recv(socket: Socket) -> PyResult<Option<Vec<u8>>> {
+    let mut socket = socket.lock().await;
+    loop {
+        match socket.next().await {
+            Some(Ok(Message::Binary(data))) => return Ok(Some(data)),
+            Some(Ok(Message::Close(_))) | None => return Ok(None),
+            // Control message (JSON) dan ping/pong bukan frame
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(connection_error(e)),
+        }
+    }
+}
+
+fn to_bytes(frame: Vec<u8>) -> Py<PyBytes> {
+    Python::with_gil(|py| PyBytes::new_bound(py, &frame).unbind())
+}
+
+/// Producer: every `send()` publishes one frame to `/ingest/<stream_id>`
+#[pyclass]
+struct Publisher {
+    socket: Socket,
+}
+
+#[pymethods]
+impl Publisher {
+    /// `await Publisher.connect(url, stream_id, token=None)`
+    #[staticmethod]
+    #[pyo3(signature = (url, stream_id, token=None))]
+    fn connect(
+        py: Python<'_>,
+        url: String,
+        stream_id: String,
+        token: Option<String>,
+    ) -> PyResult<Bound<'_, PyAny>> {
+        let url = endpoint_url(&url, "ingest", &stream_id);
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            Ok(Publisher {
+                socket: connect(url, token).await?,
+            })
+        })
+    }
+
+    /// `await pub.send(frame)`
+    fn send<'py>(&self, py: Python<'py>, frame: Vec<u8>) -> PyResult<Bound<'py, PyAny>> {
+        pyo3_async_runtimes::tokio::future_into_py(py, send(self.socket.clone(), frame))
+    }
+
+    fn send_blocking(&self, py: Python<'_>, frame: Vec<u8>) -> PyResult<()> {
+        let socket = self.socket.clone();
+        py.allow_threads(|| pyo3_async_runtimes::tokio::get_runtime().block_on(send(socket, frame)))
+    }
+
+    /// `await pub.close()`
+    fn close<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let socket = self.socket.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            socket.lock().await.close(None).await.map_err(connection_error)
+        })
+    }
+}
+
+/// Consumer of `/ws/<stream_id>`; `async for frame in sub` yields `bytes`
+#[pyclass]
+struct Subscriber {
+    socket: Socket,
+}
+
+#[pymethods]
+impl Subscriber {
+    /// `await Subscriber.connect(url, stream_id, token=None)`
+    ///
+    /// The token is a playback token (`PLAYBACK_CREDENTIALS`) if the broker requires one.
+    #[staticmethod]
+    #[pyo3(signature = (url, stream_id, token=None))]
+    fn connect(
+        py: Python<'_>,
+        url: String,
+        stream_id: String,
+        token: Option<String>,
+    ) -> PyResult<Bound<'_, PyAny>> {
+        let url = endpoint_url(&url, "ws", &stream_id);
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            Ok(Subscriber {
+                socket: connect(url, token).await?,
+            })
+        })
+    }
+
+    /// `await sub.recv()` returns the next frame, or `None` once the stream closed
+    fn recv<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let socket = self.socket.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            Ok(recv(socket).await?.map(to_bytes))
+        })
+    }
+
+    fn recv_blocking(&self, py: Python<'_>) -> PyResult<Option<Py<PyBytes>>> {
+        let socket = self.socket.clone();
+        let frame =
+            py.allow_threads(|| pyo3_async_runtimes::tokio::get_runtime().block_on(recv(socket)))?;
+        Ok(frame.map(to_bytes))
+    }
+
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let socket = self.socket.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            match recv(socket).await? {
+                Some(frame) => Ok(to_bytes(frame)),
+                None => Err(PyStopAsyncIteration::new_err(())),
+            }
+        })
+    }
+
+    /// `await sub.close()`
+    fn close<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let socket = self.socket.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            socket.lock().await.close(None).await.map_err(connection_error)
+        })
+    }
+}
+
+#[pymodule]
+fn bsb(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Publisher>()?;
+    m.add_class::<Subscriber>()?;
+    #[cfg(target_os = "linux")]
+    m.add_class::<shm::ShmPublisher>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_url() {
+        assert_eq!(
+            endpoint_url("ws://127.0.0.1:3091/", "ingest", "site-a/cam1"),
+            "ws://127.0.0.1:3091/ingest/site-a%2Fcam1"
+        );
+        assert_eq!(endpoint_url("wss://b:3090", "ws", "cam1"), "wss://b:3090/ws/cam1");
+    }
+}
@@ -0,0 +1,244 @@
+//! `ShmPublisher`: producer lewat ring shared-memory broker di host yang sama (Linux)
+//!
+//! The ring is a sealed memfd shared with the broker plus an eventfd that
+//! wakes it; both are handed over once through `SHM_INGEST_SOCKET`. After
+//! that a frame is one `memcpy` into the ring and one eventfd write, with no
+//! socket, framing or serialization in between.
+
+use bsb_proto::shm::{self as layout, ShmError};
+use pyo3::{
+    buffer::PyBuffer,
+    exceptions::{PyConnectionError, PyValueError},
+    prelude::*,
+};
+use std::{
+    io::{self, Read},
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::net::UnixStream,
+    },
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// Cukup untuk beberapa frame 4K mentah (3840x2160 RGB = 24 MiB)
+const DEFAULT_CAPACITY: u64 = 128 << 20;
+
+fn os_error(what: &str) -> PyErr {
+    PyConnectionError::new_err(format!("{}: {}", what, io::Error::last_os_error()))
+}
+
+/// Mapping ring milik producer
+struct Mapping {
+    base: *mut u8,
+    len: usize,
+}
+
+// SAFETY: the mapping is only touched through `&mut ShmPublisher`
+unsafe impl Send for Mapping {}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // SAFETY: `base`/`len` are the mapping created in `create_ring`
+        unsafe { libc::munmap(self.base.cast(), self.len) };
+    }
+}
+
+/// memfd bersegel berisi header ring, dan mapping-nya
+fn create_ring(capacity: u64) -> PyResult<(OwnedFd, Mapping)> {
+    let len = layout::HEADER_LEN + capacity as usize;
+    // SAFETY: plain syscalls on a descriptor created here; every result is checked
+    unsafe {
+        let fd = libc::memfd_create(c"bsb-ring".as_ptr(), libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING);
+        if fd < 0 {
+            return Err(os_error("memfd_create"));
+        }
+        let memfd = OwnedFd::from_raw_fd(fd);
+        if libc::ftruncate(fd, len as libc::off_t) != 0 {
+            return Err(os_error("ftruncate"));
+        }
+        // Broker menolak ring yang masih bisa dikecilkan
+        let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_SEAL;
+        if libc::fcntl(fd, libc::F_ADD_SEALS, seals) != 0 {
+            return Err(os_error("F_ADD_SEALS"));
+        }
+        let base = libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd,
+            0,
+        );
+        if base == libc::MAP_FAILED {
+            return Err(os_error("mmap"));
+        }
+        let header = layout::encode_header(capacity);
+        std::ptr::copy_nonoverlapping(header.as_ptr(), base.cast::<u8>(), header.len());
+        Ok((memfd, Mapping { base: base.cast(), len }))
+    }
+}
+
+/// Kirim hello dengan memfd dan eventfd sebagai `SCM_RIGHTS`
+fn send_hello(socket: &UnixStream, hello: &[u8], fds: [libc::c_int; 2]) -> PyResult<()> {
+    let mut iov = libc::iovec {
+        iov_base: hello.as_ptr() as *mut libc::c_void,
+        iov_len: hello.len(),
+    };
+    let mut control = [0u64; 8];
+    // SAFETY: one SCM_RIGHTS message with two descriptors fits in `control`
+    let sent = unsafe {
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = libc::CMSG_SPACE(std::mem::size_of_val(&fds) as u32) as _;
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(std::mem::size_of_val(&fds) as u32) as _;
+        std::ptr::copy_nonoverlapping(fds.as_ptr(), libc::CMSG_DATA(cmsg).cast(), fds.len());
+        libc::sendmsg(socket.as_raw_fd(), &msg, libc::MSG_NOSIGNAL)
+    };
+    if sent < 0 {
+        return Err(os_error("sendmsg"));
+    }
+    Ok(())
+}
+
+/// Producer for a broker on the same host: `ShmPublisher(socket_path, stream_id)`
+///
+/// `send(frame)` copies the frame into the shared ring and returns `False`
+/// instead of blocking when the broker has fallen a full ring behind.
+#[pyclass]
+pub struct ShmPublisher {
+    socket: Option<UnixStream>,
+    mapping: Mapping,
+    eventfd: OwnedFd,
+    capacity: u64,
+    write_pos: u64,
+    dropped: u64,
+}
+
+impl ShmPublisher {
+    fn connect(socket_path: &str, stream_id: &str, capacity: u64) -> PyResult<Self> {
+        let hello = layout::encode_hello(stream_id).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        // Kapasitas dibulatkan ke kelipatan 8 sebelum divalidasi seperti di broker
+        let capacity = (capacity + 7) & !7;
+        layout::decode_header(&layout::encode_header(capacity)).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let (memfd, mapping) = create_ring(capacity)?;
+        // SAFETY: eventfd creates a new descriptor, checked below
+        let eventfd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC) };
+        if eventfd < 0 {
+            return Err(os_error("eventfd"));
+        }
+        // SAFETY: `eventfd` is a fresh descriptor owned by nobody else
+        let eventfd = unsafe { OwnedFd::from_raw_fd(eventfd) };
+
+        let mut socket = UnixStream::connect(socket_path).map_err(|e| PyConnectionError::new_err(e.to_string()))?;
+        send_hello(&socket, &hello, [memfd.as_raw_fd(), eventfd.as_raw_fd()])?;
+        // Broker sudah punya salinan memfd; milik kita cukup ditutup
+        drop(memfd);
+        let mut reply = [0u8; 512];
+        let read = socket
+            .read(&mut reply)
+            .map_err(|e| PyConnectionError::new_err(e.to_string()))?;
+        layout::decode_reply(&reply[..read]).map_err(PyConnectionError::new_err)?;
+        Ok(ShmPublisher {
+            socket: Some(socket),
+            mapping,
+            eventfd,
+            capacity,
+            write_pos: 0,
+            dropped: 0,
+        })
+    }
+
+    fn position(&self, offset: usize) -> &AtomicU64 {
+        // SAFETY: offset is WRITE_POS_OFFSET or READ_POS_OFFSET, 8-byte aligned inside the mapping
+        unsafe { &*(self.mapping.base.add(offset) as *const AtomicU64) }
+    }
+
+    /// Broker menutup socket (mis. ring ditolak sebagai corrupt)
+    fn broker_closed(&self) -> bool {
+        let Some(socket) = &self.socket else {
+            return true;
+        };
+        let mut fd = libc::pollfd {
+            fd: socket.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: one valid pollfd, zero timeout
+        unsafe { libc::poll(&mut fd, 1, 0) > 0 }
+    }
+
+    fn write(&mut self, payload: &[u8]) -> PyResult<bool> {
+        if self.socket.is_none() {
+            return Err(PyConnectionError::new_err("publisher is closed"));
+        }
+        let read_pos = self.position(layout::READ_POS_OFFSET).load(Ordering::Acquire);
+        let plan = match layout::plan_write(self.write_pos, read_pos, self.capacity, payload.len()) {
+            Ok(plan) => plan,
+            Err(ShmError::Full) if self.broker_closed() => {
+                self.socket = None;
+                return Err(PyConnectionError::new_err("broker closed the ring"));
+            }
+            Err(ShmError::Full) => {
+                self.dropped += 1;
+                return Ok(false);
+            }
+            Err(e) => return Err(PyValueError::new_err(e.to_string())),
+        };
+        // SAFETY: plan_write keeps the padding marker and the record inside the data area
+        unsafe {
+            let data = self.mapping.base.add(layout::HEADER_LEN);
+            if let Some(offset) = plan.padding {
+                (data.add(offset) as *mut u32).write_volatile(layout::PADDING);
+            }
+            (data.add(plan.offset) as *mut u32).write_volatile(payload.len() as u32);
+            let start = data.add(plan.offset + layout::RECORD_HEADER_LEN);
+            std::ptr::copy_nonoverlapping(payload.as_ptr(), start, payload.len());
+        }
+        self.write_pos = plan.next;
+        self.position(layout::WRITE_POS_OFFSET).store(plan.next, Ordering::Release);
+        // SAFETY: writes the 8-byte counter increment eventfd expects
+        unsafe { libc::write(self.eventfd.as_raw_fd(), 1u64.to_ne_bytes().as_ptr().cast(), 8) };
+        Ok(true)
+    }
+}
+
+#[pymethods]
+impl ShmPublisher {
+    /// `ShmPublisher(socket_path, stream_id, capacity=128 MiB)`
+    ///
+    /// `socket_path` is the broker's `SHM_INGEST_SOCKET`; `capacity` is the ring
+    /// size in bytes and bounds the largest frame.
+    #[new]
+    #[pyo3(signature = (socket_path, stream_id, capacity=DEFAULT_CAPACITY))]
+    fn new(py: Python<'_>, socket_path: String, stream_id: String, capacity: u64) -> PyResult<Self> {
+        py.allow_threads(|| ShmPublisher::connect(&socket_path, &stream_id, capacity))
+    }
+
+    /// Publish one frame (`bytes`, `bytearray`, a contiguous numpy `uint8` array...)
+    ///
+    /// Returns `False` when the ring is full and the frame was dropped.
+    fn send(&mut self, py: Python<'_>, frame: PyBuffer<u8>) -> PyResult<bool> {
+        if !frame.is_c_contiguous() {
+            return Err(PyValueError::new_err("frame must be a C-contiguous buffer"));
+        }
+        // SAFETY: the buffer stays exported, and its memory alive, while `frame` is held
+        let payload = unsafe { std::slice::from_raw_parts(frame.buf_ptr() as *const u8, frame.len_bytes()) };
+        py.allow_threads(|| self.write(payload))
+    }
+
+    /// Frames dropped so far because the ring was full
+    #[getter]
+    fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Disconnect; the broker publishes what is left in the ring and ends the session
+    fn close(&mut self) {
+        self.socket = None;
+    }
+}
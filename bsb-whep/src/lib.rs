@@ -0,0 +1,529 @@
+//! WHEP gateway for the binary stream broker's Opus audio streams
+//!
+//! `POST /whep/:stream_id` with an SDP offer subscribes to `/ws/:stream_id`
+//! on the broker and answers with one Opus audio track fed from it, one RTP
+//! packet per Opus packet. The viewer's `Authorization` header or `?token=`
+//! is passed on to that subscription, so the broker's playback tokens, users
+//! and session quota apply unchanged; the gateway holds no credentials.
+//!
+//! `DELETE` on the `Location` of the answer ends the session. Candidates are
+//! gathered before answering, so trickle ICE (`PATCH`) is not supported.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, post},
+    Router,
+};
+use bsb_proto::opus;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{net::TcpStream, task::JoinHandle};
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, http::HeaderValue, Error as WsError, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+use tower_http::cors::CorsLayer;
+use tracing::{info, warn};
+use webrtc::{
+    api::{
+        interceptor_registry::register_default_interceptors,
+        media_engine::{MediaEngine, MIME_TYPE_OPUS},
+        setting_engine::SettingEngine,
+        APIBuilder, API,
+    },
+    ice::udp_network::{EphemeralUDP, UDPNetwork},
+    ice_transport::{ice_candidate_type::RTCIceCandidateType, ice_server::RTCIceServer},
+    interceptor::registry::Registry,
+    media::Sample,
+    peer_connection::{
+        configuration::RTCConfiguration, peer_connection_state::RTCPeerConnectionState,
+        sdp::session_description::RTCSessionDescription, RTCPeerConnection,
+    },
+    rtp_transceiver::rtp_codec::RTCRtpCodecCapability,
+    track::track_local::{track_local_static_sample::TrackLocalStaticSample, TrackLocal},
+};
+
+/// Subprotocol broker yang mengirim payload frame apa adanya
+const RAW_SUBPROTOCOL: &str = "bsb.raw.v1";
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The broker refused the subscription (`401`, `403`, `404`, `429`, ...)
+    Broker(StatusCode),
+    /// The broker could not be reached
+    Connect(String),
+    /// The offer could not be negotiated
+    InvalidOffer(webrtc::Error),
+    WebRtc(webrtc::Error),
+    /// Invalid gateway settings (`WHEP_UDP_PORTS`, ...)
+    Config(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Broker(status) => write!(f, "broker refused the subscription: {}", status),
+            Error::Connect(e) => write!(f, "broker connection failed: {}", e),
+            Error::InvalidOffer(e) => write!(f, "invalid SDP offer: {}", e),
+            Error::WebRtc(e) => write!(f, "webrtc: {}", e),
+            Error::Config(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<webrtc::Error> for Error {
+    fn from(e: webrtc::Error) -> Self {
+        Error::WebRtc(e)
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            Error::Broker(status) => *status,
+            Error::Connect(_) => StatusCode::BAD_GATEWAY,
+            Error::InvalidOffer(_) => StatusCode::BAD_REQUEST,
+            Error::WebRtc(_) | Error::Config(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Pengaturan gateway
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Broker base URL, e.g. `ws://127.0.0.1:3091`; plain `ws://`, the gateway runs next to the broker
+    pub broker_url: String,
+    /// STUN/TURN URLs handed to the peer connection, e.g. `stun:stun.l.google.com:19302`
+    pub ice_servers: Vec<String>,
+    /// Addresses announced instead of the host's own (1:1 NAT, containers)
+    pub public_ips: Vec<String>,
+    /// UDP port range for ICE, `None` for any ephemeral port
+    pub udp_ports: Option<(u16, u16)>,
+}
+
+/// Satu viewer WHEP: peer connection dan task yang meneruskan frame dari broker
+struct Session {
+    stream_id: String,
+    peer: Arc<RTCPeerConnection>,
+    forward: JoinHandle<()>,
+}
+
+type Sessions = Arc<Mutex<HashMap<String, Session>>>;
+
+/// Gateway WHEP; clone murah, semua clone berbagi sesi yang sama
+#[derive(Clone)]
+pub struct Gateway {
+    config: Arc<Config>,
+    api: Arc<API>,
+    sessions: Sessions,
+}
+
+/// Query untuk POST /whep/:stream_id
+#[derive(Debug, Default, Deserialize)]
+pub struct OfferParams {
+    /// Playback token for players that cannot set `Authorization`
+    token: Option<String>,
+}
+
+impl Gateway {
+    pub fn new(config: Config) -> Result<Self> {
+        let mut media = MediaEngine::default();
+        media.register_default_codecs()?;
+        let interceptors = register_default_interceptors(Registry::new(), &mut media)?;
+        let mut settings = SettingEngine::default();
+        if !config.public_ips.is_empty() {
+            settings.set_nat_1to1_ips(config.public_ips.clone(), RTCIceCandidateType::Host);
+        }
+        if let Some((min, max)) = config.udp_ports {
+            let ports = EphemeralUDP::new(min, max).map_err(|e| Error::Config(format!("WHEP_UDP_PORTS: {}", e)))?;
+            settings.set_udp_network(UDPNetwork::Ephemeral(ports));
+        }
+        let api = APIBuilder::new()
+            .with_media_engine(media)
+            .with_interceptor_registry(interceptors)
+            .with_setting_engine(settings)
+            .build();
+        Ok(Self {
+            config: Arc::new(config),
+            api: Arc::new(api),
+            sessions: Arc::default(),
+        })
+    }
+
+    /// Router dengan endpoint WHEP, siap di-serve atau di-mount
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/whep/:stream_id", post(offer_handler))
+            .route("/whep/:stream_id/:session", delete(delete_handler))
+            .layer(CorsLayer::permissive())
+            .with_state(self)
+    }
+
+    /// Sesi yang sedang berjalan
+    pub fn sessions(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    /// Subscribe ke `/ws/:stream_id` di broker dengan credential viewer
+    async fn subscribe(&self, stream_id: &str, headers: &HeaderMap, token: Option<&str>) -> Result<Socket> {
+        let mut url = format!(
+            "{}/ws/{}",
+            self.config.broker_url.trim_end_matches('/'),
+            encode_path_segment(stream_id)
+        );
+        if let Some(token) = token {
+            url.push_str(&format!("?token={}", encode_path_segment(token)));
+        }
+        let mut request = url.into_client_request().map_err(|e| Error::Connect(e.to_string()))?;
+        request
+            .headers_mut()
+            .insert(header::SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static(RAW_SUBPROTOCOL));
+        if let Some(authorization) = headers.get(header::AUTHORIZATION) {
+            request.headers_mut().insert(header::AUTHORIZATION, authorization.clone());
+        }
+        match tokio_tungstenite::connect_async(request).await {
+            Ok((socket, _)) => Ok(socket),
+            Err(WsError::Http(response)) => Err(Error::Broker(response.status())),
+            Err(e) => Err(Error::Connect(e.to_string())),
+        }
+    }
+
+    /// Jawab offer dengan satu track Opus; kandidat ICE dikumpulkan dulu (tanpa trickle)
+    async fn negotiate(&self, offer: String) -> Result<(Arc<RTCPeerConnection>, Arc<TrackLocalStaticSample>, String)> {
+        let mut ice_servers = Vec::new();
+        if !self.config.ice_servers.is_empty() {
+            ice_servers.push(RTCIceServer {
+                urls: self.config.ice_servers.clone(),
+                ..Default::default()
+            });
+        }
+        let peer = Arc::new(
+            self.api
+                .new_peer_connection(RTCConfiguration {
+                    ice_servers,
+                    ..Default::default()
+                })
+                .await?,
+        );
+        let track = Arc::new(TrackLocalStaticSample::new(
+            RTCRtpCodecCapability {
+                mime_type: MIME_TYPE_OPUS.to_string(),
+                clock_rate: 48_000,
+                channels: 2,
+                ..Default::default()
+            },
+            "audio".to_string(),
+            "bsb".to_string(),
+        ));
+        let answer = async {
+            let sender = peer.add_track(track.clone() as Arc<dyn TrackLocal + Send + Sync>).await?;
+            // RTCP dari browser harus dibaca supaya interceptor (NACK, report) berjalan
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 1500];
+                while sender.read(&mut buf).await.is_ok() {}
+            });
+            let offer = RTCSessionDescription::offer(offer).map_err(Error::InvalidOffer)?;
+            peer.set_remote_description(offer).await.map_err(Error::InvalidOffer)?;
+            let answer = peer.create_answer(None).await.map_err(Error::InvalidOffer)?;
+            let mut gathered = peer.gathering_complete_promise().await;
+            peer.set_local_description(answer).await?;
+            let _ = gathered.recv().await;
+            let local = peer.local_description().await;
+            local.map(|description| description.sdp).ok_or_else(|| {
+                Error::WebRtc(webrtc::Error::new("no local description after negotiation".to_string()))
+            })
+        }
+        .await;
+        match answer {
+            Ok(answer) => Ok((peer, track, answer)),
+            Err(e) => {
+                let _ = peer.close().await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Ambil sesi dari daftar; pemanggil yang menutup peer connection-nya
+    fn take(&self, id: &str) -> Option<Session> {
+        self.sessions.lock().unwrap().remove(id)
+    }
+}
+
+/// Handler untuk POST /whep/:stream_id
+/// Offer SDP masuk, answer SDP keluar dengan `Location` sesi untuk DELETE
+async fn offer_handler(
+    State(gateway): State<Gateway>,
+    Path(stream_id): Path<String>,
+    Query(params): Query<OfferParams>,
+    headers: HeaderMap,
+    offer: String,
+) -> Response {
+    let content_type = headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok());
+    if !content_type.is_some_and(|v| v.starts_with("application/sdp")) {
+        return (StatusCode::UNSUPPORTED_MEDIA_TYPE, "expected Content-Type: application/sdp").into_response();
+    }
+    // Broker memeriksa akses dulu, sebelum ada peer connection yang perlu dibereskan
+    let socket = match gateway.subscribe(&stream_id, &headers, params.token.as_deref()).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("Rejected WHEP viewer for stream {}: {}", stream_id, e);
+            return e.into_response();
+        }
+    };
+    let (peer, track, answer) = match gateway.negotiate(offer).await {
+        Ok(negotiated) => negotiated,
+        Err(e) => {
+            warn!("WHEP negotiation failed for stream {}: {}", stream_id, e);
+            return e.into_response();
+        }
+    };
+
+    let id = format!("{:032x}", rand::random::<u128>());
+    let sessions = gateway.sessions.clone();
+    let closed_id = id.clone();
+    peer.on_peer_connection_state_change(Box::new(move |state| {
+        let sessions = sessions.clone();
+        let id = closed_id.clone();
+        Box::pin(async move {
+            if matches!(state, RTCPeerConnectionState::Failed | RTCPeerConnectionState::Closed) {
+                // Ditutup di task lain: close() dari dalam handler state menunggu handler ini
+                let session = sessions.lock().unwrap().remove(&id);
+                if let Some(session) = session {
+                    info!("WHEP viewer {} of stream {} is {}", id, session.stream_id, state);
+                    session.forward.abort();
+                    tokio::spawn(async move {
+                        let _ = session.peer.close().await;
+                    });
+                }
+            }
+        })
+    }));
+    let forward = {
+        let gateway = gateway.clone();
+        let (id, stream_id) = (id.clone(), stream_id.clone());
+        tokio::spawn(async move {
+            forward(socket, track, &stream_id).await;
+            if let Some(session) = gateway.take(&id) {
+                info!("Broker ended stream {} for WHEP viewer {}", stream_id, id);
+                let _ = session.peer.close().await;
+            }
+        })
+    };
+    gateway.sessions.lock().unwrap().insert(
+        id.clone(),
+        Session {
+            stream_id: stream_id.clone(),
+            peer,
+            forward,
+        },
+    );
+    info!("WHEP viewer {} playing stream {}", id, stream_id);
+    let location = format!("/whep/{}/{}", encode_path_segment(&stream_id), id);
+    (
+        StatusCode::CREATED,
+        [(header::CONTENT_TYPE, "application/sdp".to_string()), (header::LOCATION, location)],
+        answer,
+    )
+        .into_response()
+}
+
+/// Handler untuk DELETE /whep/:stream_id/:session
+async fn delete_handler(State(gateway): State<Gateway>, Path((stream_id, id)): Path<(String, String)>) -> Response {
+    let session = match gateway.take(&id) {
+        Some(session) if session.stream_id == stream_id => session,
+        Some(session) => {
+            // Sesi stream lain: kembalikan, jangan ditutup lewat URL yang salah
+            gateway.sessions.lock().unwrap().insert(id, session);
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    session.forward.abort();
+    let _ = session.peer.close().await;
+    info!("WHEP viewer {} of stream {} left", id, stream_id);
+    StatusCode::OK.into_response()
+}
+
+/// Teruskan paket Opus dari broker ke track sampai salah satu sisi selesai
+///
+/// Each frame must be one Opus packet; its TOC byte gives the sample duration.
+/// Anything else is dropped, with one warning per session.
+async fn forward(mut socket: Socket, track: Arc<TrackLocalStaticSample>, stream_id: &str) {
+    let mut warned = false;
+    while let Some(message) = socket.next().await {
+        let data = match message {
+            Ok(Message::Binary(data)) => data,
+            Ok(Message::Close(_)) | Err(_) => break,
+            Ok(_) => continue,
+        };
+        let duration_us = match opus::packet_duration_us(&data) {
+            Ok(duration_us) => duration_us,
+            Err(e) => {
+                if !warned {
+                    warn!("Dropping non-Opus frames of stream {} for WHEP: {}", stream_id, e);
+                    warned = true;
+                }
+                continue;
+            }
+        };
+        let sample = Sample {
+            data: Bytes::from(data),
+            duration: Duration::from_micros(duration_us.into()),
+            ..Default::default()
+        };
+        if let Err(e) = track.write_sample(&sample).await {
+            warn!("Failed to write Opus packet of stream {} to WHEP track: {}", stream_id, e);
+            break;
+        }
+    }
+}
+
+/// Percent-encode satu segmen path (`site-a/cam1` -> `site-a%2Fcam1`)
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use futures_util::SinkExt;
+    use tokio_tungstenite::tungstenite::handshake::server::{
+        ErrorResponse, Request as WsRequest, Response as WsResponse,
+    };
+    use tower::util::ServiceExt;
+    use webrtc::rtp_transceiver::{
+        rtp_codec::RTPCodecType, rtp_transceiver_direction::RTCRtpTransceiverDirection, RTCRtpTransceiverInit,
+    };
+
+    /// Broker tiruan: `/ws/:stream_id` hanya untuk playback token `guest`, lalu paket Opus 20 ms
+    // Callback handshake tungstenite menentukan tipe error-nya sendiri
+    #[allow(clippy::result_large_err)]
+    async fn fake_broker() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let check = |request: &WsRequest, mut response: WsResponse| {
+                        let authorized = request.uri().query() == Some("token=guest")
+                            || request.headers().get(header::AUTHORIZATION).is_some_and(|v| v == "Bearer guest");
+                        if !authorized {
+                            let mut rejected = ErrorResponse::new(None);
+                            *rejected.status_mut() = StatusCode::UNAUTHORIZED;
+                            return Err(rejected);
+                        }
+                        let protocol = HeaderValue::from_static(RAW_SUBPROTOCOL);
+                        response.headers_mut().insert(header::SEC_WEBSOCKET_PROTOCOL, protocol);
+                        Ok(response)
+                    };
+                    if let Ok(mut socket) = tokio_tungstenite::accept_hdr_async(stream, check).await {
+                        let mut ticks = tokio::time::interval(Duration::from_millis(20));
+                        loop {
+                            ticks.tick().await;
+                            if socket.send(Message::Binary(vec![1 << 3, 0xAA])).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        format!("ws://{}", addr)
+    }
+
+    async fn browser_offer() -> (RTCPeerConnection, String) {
+        let mut media = MediaEngine::default();
+        media.register_default_codecs().unwrap();
+        let api = APIBuilder::new().with_media_engine(media).build();
+        let peer = api.new_peer_connection(RTCConfiguration::default()).await.unwrap();
+        let recvonly = RTCRtpTransceiverInit {
+            direction: RTCRtpTransceiverDirection::Recvonly,
+            send_encodings: Vec::new(),
+        };
+        peer.add_transceiver_from_kind(RTPCodecType::Audio, &[recvonly]).await.unwrap();
+        let offer = peer.create_offer(None).await.unwrap();
+        let mut gathered = peer.gathering_complete_promise().await;
+        peer.set_local_description(offer).await.unwrap();
+        let _ = gathered.recv().await;
+        let sdp = peer.local_description().await.unwrap().sdp;
+        (peer, sdp)
+    }
+
+    #[tokio::test]
+    async fn test_whep_offer_plays_through_broker_auth() {
+        let gateway = Gateway::new(Config {
+            broker_url: fake_broker().await,
+            ..Config::default()
+        })
+        .unwrap();
+        let (browser, offer) = browser_offer().await;
+        let send = |method: &str, uri: &str, content_type: &str, body: String| {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, content_type)
+                .body(Body::from(body))
+                .unwrap();
+            gateway.clone().router().oneshot(request)
+        };
+
+        let rejected = send("POST", "/whep/voice-1", "application/sdp", offer.clone()).await.unwrap();
+        assert_eq!(rejected.status(), StatusCode::UNAUTHORIZED);
+        let wrong_type = send("POST", "/whep/voice-1?token=guest", "text/plain", offer.clone()).await.unwrap();
+        assert_eq!(wrong_type.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(gateway.sessions(), 0);
+
+        let response = send("POST", "/whep/voice-1?token=guest", "application/sdp", offer).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/sdp");
+        let location = response.headers()[header::LOCATION].to_str().unwrap().to_string();
+        assert!(location.starts_with("/whep/voice-1/"));
+        let answer = String::from_utf8(to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap();
+        assert!(answer.contains("opus/48000/2"));
+        // Browser menerima paket Opus dari broker lewat RTP
+        let (track_tx, mut tracks) = tokio::sync::mpsc::channel(1);
+        browser.on_track(Box::new(move |track, _| {
+            let track_tx = track_tx.clone();
+            Box::pin(async move {
+                let _ = track_tx.send(track).await;
+            })
+        }));
+        browser.set_remote_description(RTCSessionDescription::answer(answer).unwrap()).await.unwrap();
+        assert_eq!(gateway.sessions(), 1);
+        let track = tokio::time::timeout(Duration::from_secs(10), tracks.recv()).await.unwrap().flatten().unwrap();
+        assert_eq!(track.codec().await.capability.mime_type, MIME_TYPE_OPUS);
+        let (packet, _) = tokio::time::timeout(Duration::from_secs(5), track.read_rtp()).await.unwrap().unwrap();
+        assert_eq!(&packet.payload[..], &[1 << 3, 0xAA]);
+
+        let wrong_stream = location.replace("/voice-1/", "/voice-2/");
+        assert_eq!(send("DELETE", &wrong_stream, "", String::new()).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(send("DELETE", &location, "", String::new()).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(gateway.sessions(), 0);
+        assert_eq!(send("DELETE", &location, "", String::new()).await.unwrap().status(), StatusCode::NOT_FOUND);
+        browser.close().await.unwrap();
+    }
+}
@@ -0,0 +1,78 @@
+//! `bsb-whep`: WHEP gateway binary, configured from the environment
+//!
+//! - `BSB_URL`: broker base URL (default `ws://127.0.0.1:3091`)
+//! - `WHEP_LISTEN`: HTTP listen address (default `0.0.0.0:3093`)
+//! - `WHEP_ICE_SERVERS`: comma-separated STUN/TURN URLs (default: none, host candidates only)
+//! - `WHEP_PUBLIC_IPS`: comma-separated addresses announced instead of the host's own
+//! - `WHEP_UDP_PORTS`: UDP port range for ICE, e.g. `50000-50100` (default: any)
+
+use bsb_whep::{Config, Gateway};
+use std::env;
+use tracing::{error, info};
+
+fn list(name: &str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// `WHEP_UDP_PORTS=min-max`
+fn udp_ports() -> Result<Option<(u16, u16)>, String> {
+    let Ok(range) = env::var("WHEP_UDP_PORTS") else {
+        return Ok(None);
+    };
+    let invalid = || format!("Invalid WHEP_UDP_PORTS {:?} (expected min-max)", range);
+    let (min, max) = range.split_once('-').ok_or_else(invalid)?;
+    let min = min.trim().parse().map_err(|_| invalid())?;
+    let max = max.trim().parse().map_err(|_| invalid())?;
+    if min > max {
+        return Err(invalid());
+    }
+    Ok(Some((min, max)))
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
+        .init();
+
+    let udp_ports = match udp_ports() {
+        Ok(udp_ports) => udp_ports,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let config = Config {
+        broker_url: env::var("BSB_URL").unwrap_or_else(|_| "ws://127.0.0.1:3091".to_string()),
+        ice_servers: list("WHEP_ICE_SERVERS"),
+        public_ips: list("WHEP_PUBLIC_IPS"),
+        udp_ports,
+    };
+    let broker_url = config.broker_url.clone();
+    let gateway = match Gateway::new(config) {
+        Ok(gateway) => gateway,
+        Err(e) => {
+            error!("Failed to set up WebRTC: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let listen = env::var("WHEP_LISTEN").unwrap_or_else(|_| "0.0.0.0:3093".to_string());
+    let listener = match tokio::net::TcpListener::bind(&listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to bind {}: {}", listen, e);
+            std::process::exit(1);
+        }
+    };
+    info!("WHEP gateway on http://{} for broker {}", listen, broker_url);
+    if let Err(e) = axum::serve(listener, gateway.router()).await {
+        error!("WHEP gateway stopped: {}", e);
+    }
+}
@@ -0,0 +1,231 @@
+//! Windows service mode: `ingest-server.exe --service`, started by the service control manager
+//!
+//! - Start/Stop/Shutdown controls start and stop the broker
+//! - Pause/Continue temporarily reject ingest (`503`) while subscribers stay connected
+//! - Warnings and errors (plus service lifecycle messages) go to the Application event log
+//!   under the source `binary-stream-broker`
+
+use parking_lot::Mutex;
+use std::{
+    env,
+    ffi::OsString,
+    iter,
+    ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+    time::Duration,
+};
+use tokio::sync::oneshot;
+use tracing::{
+    field::{Field, Visit},
+    error, info, Event, Level, Subscriber,
+};
+use tracing_subscriber::{
+    filter::filter_fn,
+    layer::{Context, SubscriberExt},
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus,
+        ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle},
+    service_dispatcher,
+};
+use windows_sys::Win32::System::EventLog::{
+    RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE, EVENTLOG_INFORMATION_TYPE,
+    EVENTLOG_WARNING_TYPE,
+};
+
+/// Nama service di SCM dan sumber event log
+pub const SERVICE_NAME: &str = "binary-stream-broker";
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Serahkan thread ini ke SCM; kembali setelah service berhenti
+pub fn run_service() -> Result<(), Box<dyn std::error::Error>> {
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)?;
+    Ok(())
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_under_scm() {
+        error!("Service failed: {}", e);
+    }
+}
+
+fn wide(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(iter::once(0)).collect()
+}
+
+/// Tracing layer yang menulis ke Windows event log
+struct EventLogLayer {
+    handle: isize,
+}
+
+impl EventLogLayer {
+    fn register() -> Option<Self> {
+        let source = wide(SERVICE_NAME);
+        // SAFETY: `source` is a NUL-terminated UTF-16 string that outlives the call
+        let handle = unsafe { RegisterEventSourceW(ptr::null(), source.as_ptr()) };
+        (handle != 0).then_some(Self { handle })
+    }
+}
+
+/// Gabungkan `message` dan field lain menjadi satu baris
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        if field.name() == "message" {
+            self.0.push_str(&format!("{:?}", value));
+        } else {
+            self.0.push_str(&format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for EventLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let kind = match *event.metadata().level() {
+            Level::ERROR => EVENTLOG_ERROR_TYPE,
+            Level::WARN => EVENTLOG_WARNING_TYPE,
+            _ => EVENTLOG_INFORMATION_TYPE,
+        };
+        let mut visitor = MessageVisitor(String::new());
+        event.record(&mut visitor);
+        let message = wide(&visitor.0);
+        let strings = [message.as_ptr()];
+        // SAFETY: the handle comes from RegisterEventSourceW and `strings` holds one
+        // NUL-terminated UTF-16 string that outlives the call
+        unsafe {
+            ReportEventW(
+                self.handle,
+                kind,
+                0,
+                0,
+                ptr::null_mut(),
+                1,
+                0,
+                strings.as_ptr(),
+                ptr::null(),
+            );
+        }
+    }
+}
+
+fn set_state(handle: ServiceStatusHandle, state: ServiceState, exit_code: u32) {
+    let controls_accepted = match state {
+        ServiceState::StopPending | ServiceState::Stopped => ServiceControlAccept::empty(),
+        _ => {
+            ServiceControlAccept::STOP
+                | ServiceControlAccept::SHUTDOWN
+                | ServiceControlAccept::PAUSE_CONTINUE
+        }
+    };
+    let status = ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted,
+        exit_code: ServiceExitCode::Win32(exit_code),
+        checkpoint: 0,
+        wait_hint: Duration::from_secs(10),
+        process_id: None,
+    };
+    if let Err(e) = handle.set_service_status(status) {
+        error!("Failed to report service state {:?}: {}", state, e);
+    }
+}
+
+fn run_under_scm() -> Result<(), Box<dyn std::error::Error>> {
+    // SCM memulai service di System32; .env dan path relatif dibaca dari direktori exe
+    if let Some(dir) = env::current_exe()?.parent() {
+        env::set_current_dir(dir)?;
+    }
+    let env_loaded = crate::load_env();
+
+    // Event log hanya menerima warning/error dan pesan lifecycle dari modul ini
+    let event_log = EventLogLayer::register().map(|layer| {
+        layer.with_filter(filter_fn(|metadata| {
+            *metadata.level() <= Level::WARN || metadata.target() == module_path!()
+        }))
+    });
+    tracing_subscriber::registry()
+        .with(EnvFilter::new(bsb_core::logging::LOG_FILTER))
+        .with(bsb_core::logging::LogConfig::from_env()?.layers()?)
+        .with(event_log)
+        .init();
+    if env_loaded {
+        info!("Environment variables loaded from .env file");
+    }
+
+    let paused = Arc::new(AtomicBool::new(false));
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let shutdown_tx = Mutex::new(Some(shutdown_tx));
+    let status_handle: Arc<OnceLock<ServiceStatusHandle>> = Arc::new(OnceLock::new());
+
+    let handler_paused = paused.clone();
+    let handler_status = status_handle.clone();
+    let event_handler = move |control| {
+        let report = |state| {
+            if let Some(handle) = handler_status.get() {
+                set_state(*handle, state, 0);
+            }
+        };
+        match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                info!("Service stop requested");
+                report(ServiceState::StopPending);
+                if let Some(tx) = shutdown_tx.lock().take() {
+                    let _ = tx.send(());
+                }
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Pause => {
+                info!("Service paused: ingest is rejected until continued");
+                handler_paused.store(true, Ordering::Relaxed);
+                report(ServiceState::Paused);
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Continue => {
+                info!("Service continued");
+                handler_paused.store(false, Ordering::Relaxed);
+                report(ServiceState::Running);
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        }
+    };
+    let handle = service_control_handler::register(SERVICE_NAME, event_handler)?;
+    let _ = status_handle.set(handle);
+    set_state(handle, ServiceState::Running, 0);
+    info!("Service started");
+
+    let runtime_config = bsb_core::runtime::RuntimeConfig::from_env()?;
+    let runtime = runtime_config.build()?;
+    let disk = runtime_config.start_disk()?;
+    let result = runtime.block_on(bsb_core::run(paused, async {
+        let _ = shutdown_rx.await;
+    }));
+    // Koneksi yang masih terbuka diberi waktu singkat sebelum proses berhenti
+    runtime.shutdown_timeout(Duration::from_secs(5));
+    if let Some(disk) = disk {
+        disk.shutdown_timeout(Duration::from_secs(5));
+    }
+
+    match &result {
+        Ok(()) => info!("Service stopped"),
+        Err(e) => error!("Broker stopped with an error: {}", e),
+    }
+    set_state(handle, ServiceState::Stopped, u32::from(result.is_err()));
+    result
+}